@@ -0,0 +1,224 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//! A CUSE character device that loops writes back to readers.
+//!
+//! Data written to the device is buffered, and returned by subsequent reads
+//! in the order it was written, similar to a pipe or a tty in raw mode. The
+//! device supports blocking and non-blocking I/O, and `poll()`.
+//!
+//! ```sh
+//! sudo ./cuse_loopback loopback &
+//! echo hello | sudo tee /dev/loopback
+//! sudo head -c 6 /dev/loopback
+//! ```
+
+use std::cell::RefCell;
+use std::num::NonZeroU64;
+
+use fuse::cuse::{PollWaiters, RingBuffer};
+use fuse::os::linux::OsError;
+use fuse::server;
+use fuse::server::CuseRequest;
+
+const POLLIN: fuse::PollEvents = 0x1;
+const POLLOUT: fuse::PollEvents = 0x4;
+
+const BUFFER_SIZE: usize = 64 * 1024;
+const MAX_PENDING_READS: usize = 16;
+const MAX_POLLERS: usize = 16;
+
+// A blocking read that arrived while the buffer was empty. It will be
+// replied to when data is written, or when the reader is interrupted.
+#[derive(Clone, Copy)]
+struct PendingRead {
+	request_id: NonZeroU64,
+	size: u32,
+}
+
+struct LoopbackDevice<'a, S> {
+	conn: &'a server::CuseConnection<S>,
+	buffer: RefCell<RingBuffer<'a>>,
+	pending_reads: RefCell<[Option<PendingRead>; MAX_PENDING_READS]>,
+	pollers: RefCell<PollWaiters<MAX_POLLERS>>,
+}
+
+impl<'a, S: server::CuseSocket> LoopbackDevice<'a, S>
+where
+	S::Error: core::fmt::Debug,
+{
+	fn new(
+		conn: &'a server::CuseConnection<S>,
+		buffer: &'a mut [u8],
+	) -> LoopbackDevice<'a, S> {
+		Self {
+			conn,
+			buffer: RefCell::new(RingBuffer::new(buffer)),
+			pending_reads: RefCell::new([None; MAX_PENDING_READS]),
+			pollers: RefCell::new(PollWaiters::new()),
+		}
+	}
+
+	fn ready_events(&self) -> fuse::PollEvents {
+		let buffer = self.buffer.borrow();
+		let mut events = 0;
+		if !buffer.is_empty() {
+			events |= POLLIN;
+		}
+		if !buffer.is_full() {
+			events |= POLLOUT;
+		}
+		events
+	}
+
+	// Replies to pending reads, oldest first, until the buffer is empty.
+	fn complete_pending_reads(&self) {
+		let mut buffer = self.buffer.borrow_mut();
+		let mut pending_reads = self.pending_reads.borrow_mut();
+		for slot in pending_reads.iter_mut() {
+			if buffer.is_empty() {
+				break;
+			}
+			if let Some(read) = slot.take() {
+				let reply = self.conn.reply(read.request_id);
+				match buffer.send_read_reply(read.size, reply) {
+					Ok(_) | Err(server::SendError::NotFound(_)) => {},
+					Err(err) => panic!("{:?}", err),
+				}
+			}
+		}
+	}
+
+	fn defer_read(&self, read: PendingRead) -> bool {
+		let mut pending_reads = self.pending_reads.borrow_mut();
+		match pending_reads.iter_mut().find(|slot| slot.is_none()) {
+			Some(slot) => {
+				*slot = Some(read);
+				true
+			},
+			None => false,
+		}
+	}
+}
+
+impl<S: server::CuseSocket> server::CuseHandlers for LoopbackDevice<'_, S>
+where
+	S::Error: core::fmt::Debug,
+{
+	fn unimplemented(&self, request: CuseRequest<'_>) {
+		self.conn.reply(request.id()).err(OsError::UNIMPLEMENTED).unwrap();
+	}
+
+	fn open(&self, request: CuseRequest<'_>) {
+		let send_reply = self.conn.reply(request.id());
+		let mut reply = server::OpenResponse::new();
+		reply.set_nonseekable(true);
+		send_reply.ok(&reply).unwrap();
+	}
+
+	fn read(&self, request: CuseRequest<'_>) {
+		let request_id = request.id();
+		let send_reply = self.conn.reply(request_id);
+		let request = server::ReadRequest::try_from(request).unwrap();
+
+		let mut buffer = self.buffer.borrow_mut();
+		if buffer.is_empty() {
+			let read = PendingRead {
+				request_id,
+				size: request.size(),
+			};
+			if request.open_flags().non_blocking() || !self.defer_read(read) {
+				send_reply.err(OsError::UNAVAILABLE).unwrap();
+			}
+			return;
+		}
+
+		buffer.send_read_reply(request.size(), send_reply).unwrap();
+		drop(buffer);
+
+		// Reading made room in the buffer, so writers may proceed.
+		self.pollers.borrow_mut().notify(self.conn).unwrap();
+	}
+
+	fn write(&self, request: CuseRequest<'_>) {
+		let send_reply = self.conn.reply(request.id());
+		let request = server::WriteRequest::try_from(request).unwrap();
+
+		// Write requests can't be deferred without copying their data, so
+		// writes to a full buffer fail even if the writer would block.
+		let mut buffer = self.buffer.borrow_mut();
+		if buffer.is_full() {
+			send_reply.err(OsError::UNAVAILABLE).unwrap();
+			return;
+		}
+		let reply = buffer.push_write(&request);
+		drop(buffer);
+		send_reply.ok(&reply).unwrap();
+
+		self.complete_pending_reads();
+		self.pollers.borrow_mut().notify(self.conn).unwrap();
+	}
+
+	fn poll(&self, request: CuseRequest<'_>) {
+		let send_reply = self.conn.reply(request.id());
+		let request = server::PollRequest::try_from(request).unwrap();
+		let ready = self.ready_events();
+		let reply = self.pollers.borrow_mut().poll(&request, ready);
+		send_reply.ok(&reply).unwrap();
+	}
+
+	fn interrupt(&self, request: CuseRequest<'_>) {
+		let request = server::InterruptRequest::try_from(request).unwrap();
+		let mut pending_reads = self.pending_reads.borrow_mut();
+		for slot in pending_reads.iter_mut() {
+			let Some(read) = *slot else {
+				continue;
+			};
+			if read.request_id == request.request_id() {
+				*slot = None;
+				let send_reply = self.conn.reply(read.request_id);
+				send_reply.err(OsError::INTERRUPTED).unwrap();
+			}
+		}
+	}
+
+	fn flush(&self, request: CuseRequest<'_>) {
+		self.conn.reply(request.id()).ok_empty().unwrap();
+	}
+
+	fn release(&self, request: CuseRequest<'_>) {
+		self.conn.reply(request.id()).ok_empty().unwrap();
+	}
+}
+
+fn main() {
+	let device_name = std::env::args().nth(1);
+	let device_name = device_name.as_deref().unwrap_or("loopback");
+	let device_name = fuse::CuseDeviceName::new(device_name).unwrap();
+
+	// A major number of zero asks the kernel to allocate a device number.
+	let device_number = fuse::CuseDeviceNumber { major: 0, minor: 0 };
+
+	let mut srv = server::CuseServer::new(device_name, device_number);
+	srv.max_read(4096);
+	srv.max_write(4096);
+	let dev_cuse = fuse_libc::CuseServerSocket::new().unwrap();
+	let conn = srv.connect(dev_cuse).unwrap();
+
+	let mut buffer = vec![0u8; BUFFER_SIZE];
+	let handlers = LoopbackDevice::new(&conn, &mut buffer);
+	let mut buf = fuse::io::MinReadBuffer::new();
+	server::cuse_serve_local(&conn, &handlers, &mut buf).unwrap();
+}
@@ -0,0 +1,262 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! A FUSE server that runs without `std` or `alloc`.
+//!
+//! This example serves the same filesystem as `helloworld`, but over a
+//! user-provided [`Transport`] such as a virtio queue or a serial link. All
+//! buffers are either borrowed from the caller or sized at compile time.
+
+#![no_std]
+
+use core::num::{NonZeroI32, NonZeroU64};
+
+use fuse::server;
+use fuse::server::FuseRequest;
+
+const HELLO_WORLD: &[u8] = b"Hello, world!\n";
+
+// Without `std` there is no platform errno table, so errors are constructed
+// from their Linux values. Embedded FUSE servers are typically serving a
+// Linux guest (e.g. over virtio-fs).
+const ENOENT: fuse::Error = linux_error(2);
+const ENOSYS: fuse::Error = linux_error(38);
+const EINVAL: fuse::Error = linux_error(22);
+
+const fn linux_error(errno: i32) -> fuse::Error {
+	match NonZeroI32::new(-errno) {
+		Some(err) => fuse::Error(err),
+		None => panic!("errno must be non-zero"),
+	}
+}
+
+/// A minimal message-oriented transport.
+///
+/// Each call to `recv` must receive exactly one FUSE request, and each call
+/// to `send` must send exactly one reply.
+pub trait Transport {
+	/// Receives a single request into `buf`, returning its length.
+	///
+	/// Returns `None` if the client has disconnected.
+	fn recv(&self, buf: &mut [u8]) -> Option<usize>;
+
+	/// Sends a single reply, which is split across one or more chunks.
+	fn send(&self, chunks: &[&[u8]]);
+}
+
+/// Adapts a [`Transport`] to the [`server::Socket`] interface.
+pub struct TransportSocket<T> {
+	transport: T,
+}
+
+impl<T: Transport> TransportSocket<T> {
+	pub fn new(transport: T) -> TransportSocket<T> {
+		Self { transport }
+	}
+}
+
+impl<T: Transport> server::Socket for TransportSocket<T> {
+	type Error = ();
+
+	fn recv(&self, buf: &mut [u8]) -> Result<usize, server::RecvError<()>> {
+		match self.transport.recv(buf) {
+			Some(len) => Ok(len),
+			None => Err(server::RecvError::ConnectionClosed(())),
+		}
+	}
+
+	fn send(
+		&self,
+		buf: fuse::io::SendBuf,
+	) -> Result<(), server::SendError<()>> {
+		self.transport.send(buf.chunks());
+		Ok(())
+	}
+}
+
+impl<T: Transport> server::FuseSocket for TransportSocket<T> {}
+
+struct HelloTxt {}
+
+impl HelloTxt {
+	fn name(&self) -> &fuse::NodeName {
+		fuse::NodeName::new("hello.txt").unwrap()
+	}
+
+	fn node_id(&self) -> fuse::NodeId {
+		fuse::NodeId::new(100).unwrap()
+	}
+
+	fn set_attr(&self, attr: &mut fuse::NodeAttr) {
+		attr.set_mode(fuse::FileMode::S_IFREG | 0o644);
+		attr.set_size(HELLO_WORLD.len() as u64);
+		attr.set_link_count(1);
+	}
+}
+
+const HELLO_TXT: HelloTxt = HelloTxt {};
+
+struct HelloWorldFS<'a, S> {
+	conn: &'a server::FuseConnection<S>,
+}
+
+impl<S> server::FuseHandlers for HelloWorldFS<'_, S>
+where
+	S: server::FuseSocket,
+	S::Error: core::fmt::Debug,
+{
+	fn unimplemented(&self, request: FuseRequest<'_>) {
+		self.conn.reply(request.id()).err(ENOSYS).unwrap();
+	}
+
+	fn lookup(&self, request: FuseRequest<'_>) {
+		let send_reply = self.conn.reply(request.id());
+		let request = server::LookupRequest::try_from(request).unwrap();
+
+		if !request.parent_id().is_root() {
+			send_reply.err(ENOENT).unwrap();
+			return;
+		}
+		if request.name() != HELLO_TXT.name() {
+			send_reply.err(ENOENT).unwrap();
+			return;
+		}
+
+		let mut attr = fuse::NodeAttr::new(HELLO_TXT.node_id());
+		HELLO_TXT.set_attr(&mut attr);
+
+		send_reply.ok(&fuse::Entry::new(attr)).unwrap();
+	}
+
+	fn getattr(&self, request: FuseRequest<'_>) {
+		let send_reply = self.conn.reply(request.id());
+		let request = server::GetattrRequest::try_from(request).unwrap();
+
+		let mut attr = fuse::NodeAttr::new(request.node_id());
+		if request.node_id().is_root() {
+			attr.set_mode(fuse::FileMode::S_IFDIR | 0o755);
+			attr.set_link_count(2);
+
+			let reply = server::GetattrResponse::new(attr);
+			send_reply.ok(&reply).unwrap();
+			return;
+		}
+
+		if request.node_id() == HELLO_TXT.node_id() {
+			HELLO_TXT.set_attr(&mut attr);
+			let reply = server::GetattrResponse::new(attr);
+			send_reply.ok(&reply).unwrap();
+			return;
+		}
+
+		send_reply.err(ENOENT).unwrap();
+	}
+
+	fn open(&self, request: FuseRequest<'_>) {
+		let send_reply = self.conn.reply(request.id());
+		let request = server::OpenRequest::try_from(request).unwrap();
+		if request.node_id() != HELLO_TXT.node_id() {
+			send_reply.err(ENOENT).unwrap();
+			return;
+		}
+		let mut reply = server::OpenResponse::new();
+		reply.set_handle(1001);
+		send_reply.ok(&reply).unwrap();
+	}
+
+	fn read(&self, request: FuseRequest<'_>) {
+		let send_reply = self.conn.reply(request.id());
+		let request = server::ReadRequest::try_from(request).unwrap();
+		if request.handle() != 1001 {
+			send_reply.err(EINVAL).unwrap();
+			return;
+		}
+		send_reply.ok_buf(HELLO_WORLD).unwrap();
+	}
+
+	fn opendir(&self, request: FuseRequest<'_>) {
+		let send_reply = self.conn.reply(request.id());
+		let request = server::OpendirRequest::try_from(request).unwrap();
+		if !request.node_id().is_root() {
+			send_reply.err(ENOENT).unwrap();
+			return;
+		}
+		let mut reply = server::OpendirResponse::new();
+		reply.set_handle(1002);
+		send_reply.ok(&reply).unwrap();
+	}
+
+	fn readdir(&self, request: FuseRequest<'_>) {
+		let send_reply = self.conn.reply(request.id());
+		let request = server::ReaddirRequest::try_from(request).unwrap();
+
+		if request.handle() != 1002 {
+			send_reply.err(EINVAL).unwrap();
+			return;
+		}
+
+		if request.offset().is_some() {
+			send_reply.ok_empty().unwrap();
+			return;
+		}
+
+		// A single entry always fits in a small fixed-size buffer, so there
+		// is no need to allocate one of `request.size()` bytes.
+		let mut buf = [0u8; 128];
+		let buf_len = buf.len().min(request.size() as usize);
+		let buf = &mut buf[..buf_len];
+		let mut entries = server::ReaddirEntriesWriter::new(buf);
+
+		let node_offset = NonZeroU64::new(1).unwrap();
+		let mut entry = server::ReaddirEntry::new(
+			HELLO_TXT.node_id(),
+			HELLO_TXT.name(),
+			node_offset,
+		);
+		entry.set_file_type(fuse::FileType::Regular);
+		entries.try_push(&entry).unwrap();
+
+		send_reply.ok(&entries.into_entries()).unwrap();
+	}
+
+	fn releasedir(&self, request: FuseRequest<'_>) {
+		let send_reply = self.conn.reply(request.id());
+		let request = server::ReleasedirRequest::try_from(request).unwrap();
+		if request.handle() != 1002 {
+			send_reply.err(EINVAL).unwrap();
+			return;
+		}
+		send_reply.ok_empty().unwrap();
+	}
+}
+
+/// Serves the `helloworld` filesystem until the client disconnects.
+///
+/// The receive buffer is provided by the caller so that it can be placed in
+/// static memory rather than on the stack.
+pub fn serve<T: Transport>(
+	transport: T,
+	buf: &mut fuse::io::MinReadBuffer,
+) -> Result<(), server::ServerError<()>> {
+	let socket = TransportSocket::new(transport);
+
+	let mut srv = server::FuseServer::new();
+	srv.max_write(4096);
+	let conn = srv.connect_with_buf(socket, buf)?;
+
+	let handlers = HelloWorldFS { conn: &conn };
+	server::fuse_serve_local(&conn, &handlers, buf)
+}
@@ -94,16 +94,14 @@ where
 			attr.set_mode(fuse::FileMode::S_IFDIR | 0o755);
 			attr.set_link_count(2);
 
-			let mut reply = fuse::kernel::fuse_attr_out::new();
-			reply.attr = *attr.raw();
+			let reply = server::GetattrResponse::new(attr);
 			send_reply.ok(&reply).unwrap();
 			return;
 		}
 
 		if request.node_id() == HELLO_TXT.node_id() {
 			HELLO_TXT.set_attr(&mut attr);
-			let mut reply = fuse::kernel::fuse_attr_out::new();
-			reply.attr = *attr.raw();
+			let reply = server::GetattrResponse::new(attr);
 			send_reply.ok(&reply).unwrap();
 			return;
 		}
@@ -118,8 +116,8 @@ where
 			send_reply.err(OsError::NOT_FOUND).unwrap();
 			return;
 		}
-		let mut reply = fuse::kernel::fuse_open_out::new();
-		reply.fh = 1001;
+		let mut reply = server::OpenResponse::new();
+		reply.set_handle(1001);
 		send_reply.ok(&reply).unwrap();
 	}
 
@@ -140,8 +138,8 @@ where
 			send_reply.err(OsError::NOT_FOUND).unwrap();
 			return;
 		}
-		let mut reply = fuse::kernel::fuse_open_out::new();
-		reply.fh = 1002;
+		let mut reply = server::OpendirResponse::new();
+		reply.set_handle(1002);
 		send_reply.ok(&reply).unwrap();
 	}
 
@@ -0,0 +1,609 @@
+// Copyright 2021 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Kernel-backed integration tests for FUSE and CUSE servers.
+//!
+//! An interop test mounts a filesystem (or creates a character device) that
+//! is served by a handler in the test process, then runs a test function
+//! that performs ordinary system calls against it. The kernel translates the
+//! system calls into FUSE requests, so the test checks both the handler's
+//! behavior and the requests that the kernel sends for a given operation.
+//!
+//! ```no_run
+//! use fuse::server;
+//! use fuse_interop_test::{fuse_interop_test, DevFuse, TestFS};
+//!
+//! struct HelloFS;
+//!
+//! impl TestFS for HelloFS {
+//! 	fn dispatch_request(
+//! 		&self,
+//! 		conn: &server::FuseConnection<DevFuse>,
+//! 		request: server::FuseRequest<'_>,
+//! 	) {
+//! 		// Decode the request and send a reply.
+//! 		# let _ = (conn, request);
+//! 	}
+//! }
+//!
+//! fuse_interop_test(HelloFS, |root| {
+//! 	let path = root.join("hello.txt");
+//! 	assert_eq!(std::fs::read_to_string(path).unwrap(), "Hello, world!\n");
+//! });
+//! ```
+//!
+//! Tests must run as a user with permission to mount FUSE filesystems
+//! without `fusermount`, which usually means the root user in a container
+//! or virtual machine. CUSE tests additionally require `/dev/cuse`.
+
+#![allow(clippy::tabs_in_doc_comments)]
+
+#![warn(missing_docs)]
+
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::{env, ffi, fs, io, panic, path, thread};
+
+use fuse::{
+	CuseDeviceName,
+	CuseDeviceNumber,
+};
+use fuse::server;
+use fuse::server::{SendError, RecvError};
+
+/// The `errno` crate of the target OS.
+#[cfg(target_os = "linux")]
+pub use linux_errno as errno;
+
+/// The OS error type of the target OS.
+#[cfg(target_os = "linux")]
+pub use fuse::os::linux::OsError;
+
+/// The `errno` crate of the target OS.
+#[cfg(target_os = "freebsd")]
+pub use freebsd_errno as errno;
+
+/// The OS error type of the target OS.
+#[cfg(target_os = "freebsd")]
+pub use fuse::os::freebsd::OsError;
+
+/// The socket of a FUSE filesystem mounted by [`fuse_interop_test`].
+#[cfg(target_os = "linux")]
+pub type DevFuse = fuse_linux::FuseServerSocket;
+
+/// The socket of a FUSE filesystem mounted by [`fuse_interop_test`].
+#[cfg(target_os = "freebsd")]
+pub type DevFuse = fuse_libc::FuseServerSocket;
+
+// TestDev {{{
+
+/// A CUSE device under test.
+pub trait TestDev {
+	/// Handles a request received from the kernel.
+	///
+	/// This is called on the server thread, once per request.
+	fn dispatch_request(
+		&self,
+		conn: &server::CuseConnection<DevCuse>,
+		request: server::CuseRequest<'_>,
+	);
+
+	/// Adjusts the flags sent to the kernel in the `CUSE_INIT` reply.
+	#[allow(unused)]
+	fn cuse_init_flags(flags: &mut fuse::CuseInitFlags) {}
+}
+
+// }}}
+
+// TestFS {{{
+
+/// A FUSE filesystem under test.
+pub trait TestFS {
+	/// Handles a request received from the kernel.
+	///
+	/// This is called on the server thread, once per request.
+	fn dispatch_request(
+		&self,
+		conn: &server::FuseConnection<DevFuse>,
+		request: server::FuseRequest<'_>,
+	);
+
+	/// Adjusts the flags sent to the kernel in the `FUSE_INIT` reply.
+	#[allow(unused)]
+	fn fuse_init_flags(flags: &mut fuse::FuseInitFlags) {}
+
+	/// Returns the subtype of the mounted filesystem.
+	fn mount_subtype(&self) -> ffi::CString {
+		ffi::CString::new("rust_fuse_test").unwrap()
+	}
+
+	/// Returns the type of the mounted filesystem.
+	#[cfg(target_os = "linux")]
+	fn mount_type(&self) -> &'static fuse::os::linux::MountType {
+		fuse::os::linux::MountType::FUSE
+	}
+
+	/// Returns the source of the mounted filesystem.
+	#[cfg(target_os = "linux")]
+	fn mount_source(&self) -> ffi::CString {
+		ffi::CString::new("rust_fuse_test").unwrap()
+	}
+
+	/// Adjusts the options used to mount the filesystem.
+	#[cfg(target_os = "freebsd")]
+	#[allow(unused)]
+	fn freebsd_mount_options(
+		&self,
+		freebsd_options: &mut fuse::os::freebsd::MountOptions,
+	) {
+	}
+
+	/// Adjusts the options used to mount the filesystem.
+	#[cfg(target_os = "linux")]
+	#[allow(unused)]
+	fn linux_mount_options(
+		&self,
+		mount_options: &mut fuse::os::linux::MountOptions,
+	) {
+	}
+}
+
+// }}}
+
+// fuse_interop_test {{{
+
+/// Mounts `fs` in a temporary directory and runs `test_fn` against it.
+///
+/// Requests are handled on a separate thread until the filesystem is
+/// unmounted. The path passed to `test_fn` is the root of the mounted
+/// filesystem.
+///
+/// # Panics
+///
+/// Panics if the filesystem can't be mounted or unmounted, if the server
+/// thread panics, or if `test_fn` panics. The filesystem is unmounted before
+/// a panic from `test_fn` is resumed.
+pub fn fuse_interop_test<Fs: TestFS + Send + 'static>(
+	fs: Fs,
+	test_fn: impl FnOnce(&std::path::Path) + panic::UnwindSafe,
+) {
+	let mut mkdtemp_template = {
+		let mut tmp = env::temp_dir();
+		tmp.push("rust_fuse.XXXXXX\x00");
+		tmp.into_os_string().into_vec()
+	};
+
+	{
+		let template_ptr = mkdtemp_template.as_mut_ptr() as *mut libc::c_char;
+		let mkdtemp_ret = unsafe { libc::mkdtemp(template_ptr) };
+		assert!(!mkdtemp_ret.is_null());
+	}
+	mkdtemp_template.truncate(mkdtemp_template.len() - 1);
+	let mount_cstr = ffi::CString::new(mkdtemp_template.clone()).unwrap();
+	let mount_path = path::Path::new(ffi::OsStr::from_bytes(&mkdtemp_template))
+		.to_path_buf();
+
+	let dev_fuse;
+
+	#[cfg(target_os = "linux")]
+	{
+		let mut mount_options = fuse::os::linux::MountOptions::new();
+
+		let mount_source = fs.mount_source();
+		let mount_subtype = fs.mount_subtype();
+		mount_options.set_mount_source(
+			fuse::os::linux::MountSource::new(&mount_source).unwrap(),
+		);
+		mount_options.set_mount_type(fs.mount_type());
+		mount_options.set_subtype(Some(
+			fuse::os::linux::FuseSubtype::new(&mount_subtype).unwrap(),
+		));
+
+		fs.linux_mount_options(&mut mount_options);
+
+		dev_fuse = fuse_linux::mount(&mount_cstr, mount_options).unwrap();
+	}
+
+	#[cfg(target_os = "freebsd")]
+	{
+		let mut mount_options = fuse::os::freebsd::MountOptions::new();
+
+		let mount_subtype = fs.mount_subtype();
+		mount_options.set_subtype(Some(
+			fuse::os::freebsd::FuseSubtype::new(&mount_subtype).unwrap(),
+		));
+
+		fs.freebsd_mount_options(&mut mount_options);
+
+		dev_fuse = fuse_libc::os::freebsd::mount(&mount_cstr, mount_options)
+			.unwrap();
+	}
+
+	let conn = server::FuseServer::new()
+		.update_flags(Fs::fuse_init_flags)
+		.connect(dev_fuse)
+		.unwrap();
+
+	let server_thread = thread::spawn(move || {
+		let mut buf = fuse::io::MinReadBuffer::new();
+		while let Some(request) = conn.recv(buf.as_aligned_slice_mut()).unwrap() {
+			fs.dispatch_request(&conn, request);
+		}
+	});
+
+	let test_result = panic::catch_unwind(|| test_fn(&mount_path));
+
+	let unmount_rc = unsafe {
+		#[cfg(target_os = "linux")]
+		let unmount_rc = libc::umount(mount_cstr.as_ptr());
+
+		#[cfg(target_os = "freebsd")]
+		let unmount_rc = libc::unmount(mount_cstr.as_ptr(), 0);
+
+		if unmount_rc == -1 {
+			#[cfg(target_os = "linux")]
+			libc::umount2(mount_cstr.as_ptr(), libc::MNT_FORCE);
+
+			#[cfg(target_os = "freebsd")]
+			libc::unmount(mount_cstr.as_ptr(), libc::MNT_FORCE);
+		}
+		unmount_rc
+	};
+
+	let server_result = server_thread.join();
+
+	if let Err(err) = test_result {
+		panic::resume_unwind(err);
+	} else {
+		match server_result {
+			Err(err) => panic::resume_unwind(err),
+			Ok(_) => {
+				assert_eq!(unmount_rc, 0);
+			},
+		}
+	}
+}
+
+// }}}
+
+// DevCuse {{{
+
+/// The socket of a CUSE device created by [`cuse_interop_test`].
+///
+/// Unlike `/dev/fuse`, a `/dev/cuse` socket isn't closed when the device is
+/// deleted, so the socket also polls a pipe that is closed when the test
+/// function returns.
+pub struct DevCuse {
+	dev_cuse: fs::File,
+	pipe_r: fs::File,
+}
+
+impl DevCuse {
+	fn new() -> (Self, /* pipe_w */ fs::File) {
+		use std::os::unix::io::FromRawFd;
+
+		let mut pipe_fds = [(0 as libc::c_int); 2];
+		let pipe_rc = unsafe { libc::pipe(pipe_fds.as_mut_ptr()) };
+		assert_eq!(pipe_rc, 0);
+
+		let dev_cuse = fs::OpenOptions::new()
+			.read(true)
+			.write(true)
+			.open("/dev/cuse")
+			.unwrap();
+
+		let pipe_r = unsafe { fs::File::from_raw_fd(pipe_fds[0]) };
+		let pipe_w = unsafe { fs::File::from_raw_fd(pipe_fds[1]) };
+
+		(Self { dev_cuse, pipe_r }, pipe_w)
+	}
+}
+
+impl server::CuseSocket for DevCuse {}
+
+impl server::Socket for DevCuse {
+	type Error = io::Error;
+
+	fn send(
+		&self,
+		buf: fuse::io::SendBuf,
+	) -> Result<(), SendError<io::Error>> {
+		use std::io::Write;
+		let mut bytes = vec![0u8; buf.len()];
+		let len = buf.copy_to(&mut bytes);
+		bytes.truncate(len);
+		let write_size = match Write::write(&mut &self.dev_cuse, &bytes) {
+			Err(err) => return Err(SendError::Other(err)),
+			Ok(x) => x,
+		};
+		if write_size < len {
+			let err = io::Error::other("incomplete send");
+			return Err(SendError::Other(err));
+		}
+		Ok(())
+	}
+
+	fn recv(&self, buf: &mut [u8]) -> Result<usize, RecvError<io::Error>> {
+		use std::io::Read;
+		use std::os::unix::io::AsRawFd;
+
+		let mut poll_fds: [libc::pollfd; 2] = [
+			libc::pollfd {
+				fd: self.dev_cuse.as_raw_fd(),
+				events: libc::POLLIN,
+				revents: 0,
+			},
+			libc::pollfd {
+				fd: self.pipe_r.as_raw_fd(),
+				events: 0,
+				revents: 0,
+			},
+		];
+
+		loop {
+			let poll_rc = unsafe { libc::poll(
+				poll_fds.as_mut_ptr(),
+				poll_fds.len() as libc::nfds_t,
+				-1, // timeout
+			) };
+			if poll_rc == -1 && libc_errno() == libc::EINTR {
+				continue;
+			}
+			assert!(poll_rc > 0);
+
+			if (poll_fds[1].revents & libc::POLLERR) > 0 ||
+			   (poll_fds[1].revents & libc::POLLHUP) > 0 {
+				let err = io::ErrorKind::ConnectionReset;
+				return Err(RecvError::ConnectionClosed(err.into()));
+			}
+
+			if (poll_fds[0].revents & libc::POLLIN) == 0 {
+				continue;
+			}
+
+			match Read::read(&mut &self.dev_cuse, buf) {
+				Ok(size) => return Ok(size),
+				Err(err) => match err.raw_os_error() {
+					Some(libc::ENOENT) => {
+						// The next request in the kernel buffer was interrupted before
+						// it could be deleted. Try again.
+					},
+					Some(libc::EINTR) => {
+						// Interrupted by signal. Try again.
+					},
+					_ => return Err(RecvError::Other(err)),
+				},
+			}
+		}
+	}
+}
+
+// }}}
+
+// cuse_interop_test {{{
+
+extern "C" {
+	#[link_name = "mktemp"]
+	fn libc_mktemp(template: *mut libc::c_char) -> *mut libc::c_char;
+}
+
+const CUSE_DEV_MAJOR: libc::c_uint = 240; // "LOCAL/EXPERIMENTAL USE"
+const CUSE_DEV_MINOR: libc::c_uint = 1;
+
+/// Creates a CUSE device served by `dev` and runs `test_fn` against it.
+///
+/// The device is created under `/dev/subdir/` with a major number reserved
+/// for local use, and the path passed to `test_fn` is the path of its device
+/// node. Requests are handled on a separate thread until `test_fn` returns.
+///
+/// # Panics
+///
+/// Panics if the device can't be created, if the server thread panics, or
+/// if `test_fn` panics.
+pub fn cuse_interop_test<D: TestDev + Send + 'static>(
+	dev: D,
+	test_fn: impl FnOnce(&path::Path) + panic::UnwindSafe,
+) {
+	let mut mktemp_template = {
+		let mut tmp = path::PathBuf::from("/dev/subdir/");
+		tmp.push("rust-cuse.XXXXXX\x00");
+		tmp.into_os_string().into_vec()
+	};
+
+	unsafe { libc::mkdir(c"/dev/subdir".as_ptr(), 0o777) };
+	{
+		let template_ptr = mktemp_template.as_mut_ptr() as *mut libc::c_char;
+		let mktemp_ret = unsafe { libc_mktemp(template_ptr) };
+		assert!(!mktemp_ret.is_null());
+	}
+	mktemp_template.truncate(mktemp_template.len() - 1);
+	let device_path = path::Path::new(ffi::OsStr::from_bytes(&mktemp_template))
+		.to_path_buf();
+
+	#[cfg(target_os = "linux")]
+	{
+		let devpath_cstr = ffi::CString::new(mktemp_template.clone()).unwrap();
+		let mknod_rc = unsafe {
+			let dev_t = libc::makedev(CUSE_DEV_MAJOR, CUSE_DEV_MINOR);
+			libc::mknod(devpath_cstr.as_ptr(), libc::S_IFCHR | 0o777, dev_t)
+		};
+		assert_eq!(mknod_rc, 0);
+	}
+
+	mktemp_template = mktemp_template.split_off("/dev/".len());
+
+	let (dev_cuse, dev_cuse_closer) = DevCuse::new();
+
+	let dev_name = CuseDeviceName::from_bytes(&mktemp_template).unwrap();
+	let dev_number = CuseDeviceNumber {
+		major: CUSE_DEV_MAJOR,
+		minor: CUSE_DEV_MINOR,
+	};
+
+	let conn = server::CuseServer::new(dev_name, dev_number)
+		.update_flags(D::cuse_init_flags)
+		.connect(dev_cuse)
+		.unwrap();
+
+	let server_thread = thread::spawn(move || {
+		let serve = || -> Result<(), server::ServerError<std::io::Error>> {
+			let mut buf = fuse::io::MinReadBuffer::new();
+			loop {
+				let request = conn.recv(buf.as_aligned_slice_mut())?;
+				dev.dispatch_request(&conn, request);
+			}
+		};
+
+		fn is_conn_reset(err: &server::ServerError<io::Error>) -> bool {
+			matches!(
+				err,
+				server::ServerError::RecvError(
+					server::RecvError::ConnectionClosed(_),
+				),
+			)
+		}
+
+		match serve() {
+			Ok(_) => {},
+			Err(err) if is_conn_reset(&err) => {},
+			Err(err) => panic!("{:?}", err),
+		}
+	});
+
+	let test_result = panic::catch_unwind(|| test_fn(&device_path));
+
+	drop(dev_cuse_closer);
+	let server_result = server_thread.join();
+
+	if let Err(err) = test_result {
+		panic::resume_unwind(err);
+	} else if let Err(err) = server_result {
+		panic::resume_unwind(err);
+	}
+}
+
+// }}}
+
+// Helpers {{{
+
+/// Converts a path to a NUL-terminated C string.
+///
+/// # Panics
+///
+/// Panics if the path contains a NUL byte.
+#[must_use]
+pub fn path_cstr(path: std::path::PathBuf) -> ffi::CString {
+	ffi::CString::new(path.as_os_str().as_bytes()).unwrap()
+}
+
+/// Compares two strings line by line.
+///
+/// Returns `None` if the strings are equal, or a diff of their lines if
+/// they differ. Lines only in `want` are prefixed with `- `, and lines only
+/// in `got` are prefixed with `+ `.
+///
+/// ```
+/// use fuse_interop_test::diff_str;
+///
+/// assert_eq!(diff_str("a\nb", "a\nb"), None);
+/// assert_eq!(
+/// 	diff_str("a\nb", "a\nc").as_deref(),
+/// 	Some("  a\n- b\n+ c\n"),
+/// );
+/// ```
+#[must_use]
+pub fn diff_str(want: &str, got: &str) -> Option<String> {
+	let mut out = String::new();
+	let mut ok = true;
+	for result in diff::lines(want, got) {
+		match result {
+			diff::Result::Left(l) => {
+				ok = false;
+				out.push_str("- ");
+				out.push_str(l);
+				out.push('\n');
+			},
+			diff::Result::Both(l, _) => {
+				out.push_str("  ");
+				out.push_str(l);
+				out.push('\n');
+			},
+			diff::Result::Right(r) => {
+				ok = false;
+				out.push_str("+ ");
+				out.push_str(r);
+				out.push('\n');
+			},
+		}
+	}
+
+	if ok {
+		return None;
+	}
+	Some(out)
+}
+
+/// Returns the `errno` of the calling thread.
+#[must_use]
+pub fn libc_errno() -> libc::c_int {
+	unsafe {
+		#[cfg(target_os = "linux")]
+		return *libc::__errno_location();
+
+		#[cfg(target_os = "freebsd")]
+		return *libc::__error();
+	}
+}
+
+/// Runs `f` in a child process with the given user and group IDs.
+///
+/// Returns zero if `f` succeeded, or the child's `errno` if `f` returned -1.
+///
+/// The child process is forked from a multi-threaded parent, so `f` must
+/// only call async-signal-safe functions (e.g. syscall wrappers).
+///
+/// # Panics
+///
+/// Panics if the child process can't be forked or doesn't exit normally.
+pub fn fork_as_user(
+	uid: libc::uid_t,
+	gid: libc::gid_t,
+	f: impl FnOnce() -> libc::c_int,
+) -> libc::c_int {
+	let pid = unsafe { libc::fork() };
+	assert_ne!(pid, -1);
+	if pid == 0 {
+		let rc = unsafe {
+			if libc::setgroups(0, core::ptr::null()) == -1
+				|| libc::setgid(gid) == -1
+				|| libc::setuid(uid) == -1
+			{
+				-1
+			} else {
+				f()
+			}
+		};
+		let status = if rc == -1 { libc_errno() } else { 0 };
+		unsafe { libc::_exit(status) };
+	}
+
+	let mut wait_status: libc::c_int = 0;
+	let wait_rc = unsafe { libc::waitpid(pid, &mut wait_status, 0) };
+	assert_eq!(wait_rc, pid);
+	assert!(libc::WIFEXITED(wait_status));
+	libc::WEXITSTATUS(wait_status)
+}
+
+// }}}
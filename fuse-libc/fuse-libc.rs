@@ -48,21 +48,41 @@
 	clippy::print_stdout,
 )]
 
+#[cfg(feature = "std")]
+extern crate std;
+
 use core::ffi;
 
 mod io {
+	#[cfg(not(target_os = "freebsd"))]
+	pub(crate) mod cuse_devices;
 	pub(crate) mod iovec;
+	pub(crate) mod mmap_buffer;
+	pub(crate) mod nonblock;
 	pub(crate) mod socket;
+	pub(crate) mod unix_socket;
 }
 
 pub use crate::io::socket::{
+	FuseMount,
 	FuseServerSocket,
 	LibcError,
 };
 
+pub use crate::io::mmap_buffer::MmapBuffer;
+
+pub use crate::io::unix_socket::UnixSocket;
+
 #[cfg(any(doc, not(target_os = "freebsd")))]
 pub use crate::io::socket::CuseServerSocket;
 
+#[cfg(any(doc, not(target_os = "freebsd")))]
+pub use crate::io::cuse_devices::{
+	CuseDevice,
+	CuseDevices,
+	CuseDevicesCapacityError,
+};
+
 pub mod os {
 	#[cfg(any(doc, target_os = "freebsd"))]
 	pub mod freebsd;
@@ -71,6 +91,21 @@ pub mod os {
 	pub mod linux;
 }
 
+/// Returns the page size of the host.
+///
+/// The page size may be passed to [`FuseServer::page_size`] so that the
+/// connection's `max_pages` and receive buffer length are computed correctly
+/// on hosts with pages larger than 4 KiB.
+///
+/// [`FuseServer::page_size`]: fuse::server::FuseServer::page_size
+pub fn page_size() -> Result<usize, LibcError> {
+	let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+	if page_size <= 0 {
+		return Err(LibcError::last_os_error());
+	}
+	Ok(page_size as usize)
+}
+
 #[cfg(not(target_os = "freebsd"))]
 const DEV_CUSE: &ffi::CStr = c"/dev/cuse";
 
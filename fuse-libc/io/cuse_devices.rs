@@ -0,0 +1,182 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use fuse::io::AsAlignedSliceMut;
+use fuse::server;
+use fuse::server::{RecvError, ServerError};
+
+use crate::io::socket::{errno, CuseServerSocket, LibcError};
+
+// CuseDevice {{{
+
+/// A CUSE device registered with [`CuseDevices`].
+///
+/// Each device has its own connection, created by opening `/dev/cuse` and
+/// performing a separate handshake, and its own request handlers.
+#[derive(Clone, Copy)]
+pub struct CuseDevice<'a> {
+	conn: &'a server::CuseConnection<CuseServerSocket>,
+	handlers: &'a dyn server::CuseHandlers,
+}
+
+impl<'a> CuseDevice<'a> {
+	/// Creates a `CuseDevice` that dispatches requests received on `conn`
+	/// to `handlers`.
+	#[must_use]
+	pub fn new(
+		conn: &'a server::CuseConnection<CuseServerSocket>,
+		handlers: &'a dyn server::CuseHandlers,
+	) -> CuseDevice<'a> {
+		Self { conn, handlers }
+	}
+}
+
+impl core::fmt::Debug for CuseDevice<'_> {
+	fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+		fmt.debug_struct("CuseDevice")
+			.field("fd", &self.conn.socket().cuse_device_fd())
+			.finish_non_exhaustive()
+	}
+}
+
+// }}}
+
+// CuseDevices {{{
+
+/// Error returned by [`CuseDevices::register`] when all slots are in use.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct CuseDevicesCapacityError {}
+
+/// A set of up to `N` CUSE devices served from a single thread.
+///
+/// A process that exposes a family of devices (e.g. `/dev/foo0` through
+/// `/dev/fooN`) opens one [`CuseServerSocket`] per device, connects each of
+/// them with [`server::CuseServer`], and registers the resulting connections
+/// along with their handlers.
+pub struct CuseDevices<'a, const N: usize> {
+	devices: [Option<CuseDevice<'a>>; N],
+	len: usize,
+}
+
+impl<'a, const N: usize> CuseDevices<'a, N> {
+	/// Creates an empty `CuseDevices`.
+	#[must_use]
+	pub const fn new() -> CuseDevices<'a, N> {
+		Self {
+			devices: [None; N],
+			len: 0,
+		}
+	}
+
+	/// Returns the number of registered devices.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Returns `true` if no devices have been registered.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Registers a device, whose requests will be dispatched to `handlers`.
+	pub fn register(
+		&mut self,
+		conn: &'a server::CuseConnection<CuseServerSocket>,
+		handlers: &'a dyn server::CuseHandlers,
+	) -> Result<(), CuseDevicesCapacityError> {
+		let Some(slot) = self.devices.get_mut(self.len) else {
+			return Err(CuseDevicesCapacityError {});
+		};
+		*slot = Some(CuseDevice::new(conn, handlers));
+		self.len += 1;
+		Ok(())
+	}
+
+	/// Serve CUSE requests for the registered devices.
+	///
+	/// The devices' sockets are waited on together with `poll()`, and each
+	/// request is dispatched to the handlers of the device it was received
+	/// on. Requests are received into `buf`, which must be large enough for
+	/// any of the devices' connections.
+	///
+	/// Returns an error if any device's socket fails.
+	pub fn serve(
+		&self,
+		buf: &mut impl AsAlignedSliceMut,
+	) -> Result<(), ServerError<LibcError>> {
+		let devices = &self.devices[..self.len];
+		let mut poll_fds = [libc::pollfd {
+			fd: -1,
+			events: libc::POLLIN,
+			revents: 0,
+		}; N];
+		for (device, poll_fd) in devices.iter().zip(poll_fds.iter_mut()) {
+			if let Some(device) = device {
+				poll_fd.fd = device.conn.socket().cuse_device_fd() as i32;
+			}
+		}
+		let poll_fds = &mut poll_fds[..self.len];
+
+		loop {
+			let poll_rc = unsafe {
+				libc::poll(
+					poll_fds.as_mut_ptr(),
+					poll_fds.len() as libc::nfds_t,
+					-1,
+				)
+			};
+			if poll_rc == -1 {
+				let err = errno();
+				if err == libc::EINTR {
+					continue;
+				}
+				let err = LibcError::from_raw_os_error(err);
+				return Err(RecvError::Other(err).into());
+			}
+
+			for (device, poll_fd) in devices.iter().zip(poll_fds.iter_mut()) {
+				if poll_fd.revents == 0 {
+					continue;
+				}
+				poll_fd.revents = 0;
+				let Some(device) = device else {
+					continue;
+				};
+				let request = device.conn.recv(buf.as_aligned_slice_mut())?;
+				device.handlers.dispatch(request);
+			}
+		}
+	}
+}
+
+impl<const N: usize> Default for CuseDevices<'_, N> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<const N: usize> core::fmt::Debug for CuseDevices<'_, N> {
+	fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+		fmt.debug_list()
+			.entries(self.devices[..self.len].iter().flatten())
+			.finish()
+	}
+}
+
+// }}}
@@ -0,0 +1,175 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::fmt;
+use core::ptr::NonNull;
+
+use fuse::io::{
+	AlignedSlice,
+	AlignedSliceMut,
+	AsAlignedSlice,
+	AsAlignedSliceMut,
+};
+use fuse::kernel::FUSE_MIN_READ_BUFFER;
+
+use crate::io::socket::LibcError;
+
+/// A receive buffer allocated with `mmap(2)`.
+///
+/// The buffer is an anonymous private mapping, so it is page-aligned and
+/// its pages are only committed when first written. On Linux, the mapping
+/// may be backed by transparent huge pages, which reduces TLB pressure when
+/// a server with a large `max_write` receives many large `FUSE_WRITE`
+/// requests.
+///
+/// ```no_run
+/// # fn f() -> Result<(), fuse_libc::LibcError> {
+/// # let recv_buf_len = 0;
+/// let buf = fuse_libc::MmapBuffer::new(recv_buf_len)?;
+/// # #[cfg(target_os = "linux")]
+/// buf.advise_huge_pages()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct MmapBuffer {
+	ptr: NonNull<u8>,
+	len: usize,
+	map_len: usize,
+}
+
+unsafe impl Send for MmapBuffer {}
+
+unsafe impl Sync for MmapBuffer {}
+
+impl Drop for MmapBuffer {
+	fn drop(&mut self) {
+		unsafe {
+			let _ = libc::munmap(self.ptr.as_ptr().cast(), self.map_len);
+		}
+	}
+}
+
+impl MmapBuffer {
+	/// Maps a new `MmapBuffer` of at least `len` bytes, initialized with
+	/// zeros.
+	///
+	/// Buffers smaller than [`FUSE_MIN_READ_BUFFER`] are rounded up to that
+	/// length, because the client may reject reads into smaller buffers. The
+	/// mapping itself is rounded up to a multiple of the page size.
+	pub fn new(len: usize) -> Result<MmapBuffer, LibcError> {
+		let len = core::cmp::max(len, FUSE_MIN_READ_BUFFER);
+		let page_size = crate::page_size()?;
+		let Some(map_len) = len.checked_next_multiple_of(page_size) else {
+			return Err(LibcError::from_raw_os_error(libc::ENOMEM));
+		};
+		let ptr = unsafe {
+			libc::mmap(
+				core::ptr::null_mut(),
+				map_len,
+				libc::PROT_READ | libc::PROT_WRITE,
+				libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+				-1,
+				0,
+			)
+		};
+		if ptr == libc::MAP_FAILED {
+			return Err(LibcError::last_os_error());
+		}
+		match NonNull::new(ptr.cast::<u8>()) {
+			Some(ptr) => Ok(MmapBuffer { ptr, len, map_len }),
+			None => Err(LibcError::from_raw_os_error(libc::ENOMEM)),
+		}
+	}
+
+	/// Requests that the buffer be backed by transparent huge pages.
+	///
+	/// This is a hint, as by `madvise(MADV_HUGEPAGE)`. The kernel only uses
+	/// huge pages for aligned regions of the mapping that span a whole huge
+	/// page, so it has no effect on buffers smaller than the huge page size
+	/// (usually 2 MiB).
+	///
+	/// Returns an error if the kernel doesn't support transparent huge
+	/// pages.
+	#[cfg(target_os = "linux")]
+	pub fn advise_huge_pages(&self) -> Result<(), LibcError> {
+		let rc = unsafe {
+			libc::madvise(
+				self.ptr.as_ptr().cast(),
+				self.map_len,
+				libc::MADV_HUGEPAGE,
+			)
+		};
+		if rc == -1 {
+			return Err(LibcError::last_os_error());
+		}
+		Ok(())
+	}
+
+	/// Returns the length of the buffer, in bytes.
+	#[inline]
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Returns `true` if the buffer has a length of zero.
+	///
+	/// This is always `false`, because a `MmapBuffer` is never smaller than
+	/// [`FUSE_MIN_READ_BUFFER`].
+	#[inline]
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Borrows the buffer as a byte slice.
+	#[inline]
+	#[must_use]
+	pub fn as_slice(&self) -> &[u8] {
+		unsafe {
+			core::slice::from_raw_parts(self.ptr.as_ptr(), self.len)
+		}
+	}
+
+	/// Borrows the buffer as a mutable byte slice.
+	#[inline]
+	#[must_use]
+	pub fn as_slice_mut(&mut self) -> &mut [u8] {
+		unsafe {
+			core::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len)
+		}
+	}
+}
+
+impl fmt::Debug for MmapBuffer {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("MmapBuffer")
+			.field("len", &self.len)
+			.finish_non_exhaustive()
+	}
+}
+
+impl AsAlignedSlice for MmapBuffer {
+	fn as_aligned_slice(&self) -> AlignedSlice<'_> {
+		unsafe { AlignedSlice::new_unchecked(self.as_slice()) }
+	}
+}
+
+impl AsAlignedSliceMut for MmapBuffer {
+	fn as_aligned_slice_mut(&mut self) -> AlignedSliceMut<'_> {
+		unsafe { AlignedSliceMut::new_unchecked(self.as_slice_mut()) }
+	}
+}
@@ -0,0 +1,96 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use fuse::io::SendBuf;
+use fuse::server;
+use fuse::server::{RecvError, SendError};
+
+use crate::io::socket::{FuseServerSocket, LibcError};
+
+/// A `/dev/fuse` socket in non-blocking mode.
+///
+/// The socket's file descriptor may be registered with an event loop such
+/// as `epoll(7)` on Linux or `kqueue(2)` on FreeBSD, which will report it as
+/// readable when a request is available. Receiving from the socket when no
+/// request is available fails with [`RecvError::WouldBlock`] rather than
+/// blocking the calling thread.
+///
+/// Replies are written to `/dev/fuse` without blocking, so sending is
+/// unaffected by non-blocking mode.
+pub struct NonblockDevFuse {
+	socket: FuseServerSocket,
+}
+
+impl NonblockDevFuse {
+	/// Switches `socket` to non-blocking mode.
+	///
+	/// The connection handshake receives the client's `FUSE_INIT` request,
+	/// which the kernel sends while mounting the filesystem. The socket
+	/// should therefore be switched to non-blocking mode after it has been
+	/// mounted, so that [`FuseServer::connect`] doesn't fail with
+	/// [`RecvError::WouldBlock`].
+	///
+	/// [`FuseServer::connect`]: fuse::server::FuseServer::connect
+	pub fn new(socket: FuseServerSocket) -> Result<NonblockDevFuse, LibcError> {
+		let fd = socket.fuse_device_fd() as i32;
+		let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+		if flags == -1 {
+			return Err(LibcError::last_os_error());
+		}
+		let flags = flags | libc::O_NONBLOCK;
+		let rc = unsafe { libc::fcntl(fd, libc::F_SETFL, flags) };
+		if rc == -1 {
+			return Err(LibcError::last_os_error());
+		}
+		Ok(NonblockDevFuse { socket })
+	}
+
+	/// Returns the socket's file descriptor.
+	///
+	/// The file descriptor remains owned by the `NonblockDevFuse`, and must
+	/// be removed from any event loop before the socket is dropped.
+	#[must_use]
+	pub fn as_raw_fd(&self) -> i32 {
+		self.socket.fuse_device_fd() as i32
+	}
+
+	/// Returns a reference to the underlying `/dev/fuse` socket.
+	#[must_use]
+	pub fn get_ref(&self) -> &FuseServerSocket {
+		&self.socket
+	}
+}
+
+impl server::FuseSocket for NonblockDevFuse {}
+
+#[cfg(feature = "std")]
+impl std::os::fd::AsRawFd for NonblockDevFuse {
+	fn as_raw_fd(&self) -> std::os::fd::RawFd {
+		NonblockDevFuse::as_raw_fd(self)
+	}
+}
+
+impl server::Socket for NonblockDevFuse {
+	type Error = LibcError;
+
+	fn recv(&self, buf: &mut [u8]) -> Result<usize, RecvError<LibcError>> {
+		server::Socket::recv(&self.socket, buf)
+	}
+
+	fn send(&self, buf: SendBuf) -> Result<(), SendError<LibcError>> {
+		server::Socket::send(&self.socket, buf)
+	}
+}
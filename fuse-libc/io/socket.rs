@@ -78,6 +78,12 @@ impl Socket {
 			// Interrupted by signal. Try again.
 			libc::EINTR => Ok(()),
 
+			// The socket is non-blocking and no request is available.
+			libc::EAGAIN => {
+				let err = LibcError::from_raw_os_error(libc::EAGAIN);
+				Err(RecvError::WouldBlock(err))
+			},
+
 			// FUSE (but not CUSE) uses ENODEV to signal the clean shutdown of
 			// the connection by the client.
 			libc::ENODEV if self.enodev_is_eof => {
@@ -157,6 +163,11 @@ impl CuseServerSocket {
 		Ok(CuseServerSocket { socket })
 	}
 
+	#[must_use]
+	pub fn cuse_device_fd(&self) -> u32 {
+		self.socket.fd as u32
+	}
+
 	#[must_use]
 	pub unsafe fn from_raw_fd(fd: i32) -> CuseServerSocket {
 		let socket = Socket {
@@ -170,6 +181,13 @@ impl CuseServerSocket {
 #[cfg(any(doc, not(target_os = "freebsd")))]
 impl server::CuseSocket for CuseServerSocket {}
 
+#[cfg(all(feature = "std", not(target_os = "freebsd")))]
+impl std::os::fd::AsRawFd for CuseServerSocket {
+	fn as_raw_fd(&self) -> std::os::fd::RawFd {
+		self.socket.fd
+	}
+}
+
 #[cfg(any(doc, not(target_os = "freebsd")))]
 impl server::Socket for CuseServerSocket {
 	type Error = LibcError;
@@ -212,6 +230,123 @@ impl FuseServerSocket {
 		self.socket.fd as u32
 	}
 
+	/// Opens a new `/dev/fuse` handle attached to this socket's session.
+	///
+	/// Requests may be received from either handle, which lets a server
+	/// process requests in parallel with one handle per thread.
+	///
+	/// On Linux this uses the `FUSE_DEV_IOC_CLONE` ioctl. FreeBSD does not
+	/// support cloning `/dev/fuse` handles, so on FreeBSD this method always
+	/// fails with `EOPNOTSUPP`.
+	pub fn try_clone(&self) -> Result<FuseServerSocket, LibcError> {
+		self.try_clone_impl()
+	}
+
+	#[cfg(target_os = "linux")]
+	fn try_clone_impl(&self) -> Result<FuseServerSocket, LibcError> {
+		use fuse::os::linux::FUSE_DEV_IOC_CLONE;
+
+		let cloned = Self::new()?;
+		let session_fd = self.fuse_device_fd();
+		let ioctl_rc = unsafe {
+			libc::ioctl(
+				cloned.socket.fd,
+				FUSE_DEV_IOC_CLONE as _,
+				&session_fd as *const u32,
+			)
+		};
+		if ioctl_rc == -1 {
+			return Err(LibcError::last_os_error());
+		}
+		Ok(cloned)
+	}
+
+	#[cfg(not(target_os = "linux"))]
+	fn try_clone_impl(&self) -> Result<FuseServerSocket, LibcError> {
+		Err(LibcError::from_raw_os_error(libc::EOPNOTSUPP))
+	}
+
+	/// Registers a passthrough backing file with the client.
+	///
+	/// The returned backing file ID may be sent in replies to `FUSE_OPEN`
+	/// and `FUSE_CREATE` to enable passthrough I/O, and must be unregistered
+	/// with [`close_backing`](Self::close_backing) once no longer needed.
+	///
+	/// Passthrough requires the `PASSTHROUGH` init flag, and registering a
+	/// backing file requires the `CAP_SYS_ADMIN` capability.
+	///
+	/// Passthrough is only supported on Linux. On other platforms this method
+	/// always fails with `EOPNOTSUPP`.
+	pub fn open_backing(&self, fd: i32) -> Result<u32, LibcError> {
+		self.open_backing_impl(fd)
+	}
+
+	/// Unregisters a passthrough backing file.
+	///
+	/// Files already opened with the backing file ID are unaffected.
+	pub fn close_backing(&self, backing_id: u32) -> Result<(), LibcError> {
+		self.close_backing_impl(backing_id)
+	}
+
+	#[cfg(target_os = "linux")]
+	fn open_backing_impl(&self, fd: i32) -> Result<u32, LibcError> {
+		use fuse::os::linux::FUSE_DEV_IOC_BACKING_OPEN;
+
+		let mut map = fuse::kernel::fuse_backing_map::new();
+		map.fd = fd;
+		let ioctl_rc = unsafe {
+			libc::ioctl(
+				self.socket.fd,
+				FUSE_DEV_IOC_BACKING_OPEN as _,
+				&map as *const fuse::kernel::fuse_backing_map,
+			)
+		};
+		if ioctl_rc == -1 {
+			return Err(LibcError::last_os_error());
+		}
+		Ok(ioctl_rc as u32)
+	}
+
+	#[cfg(not(target_os = "linux"))]
+	fn open_backing_impl(&self, _fd: i32) -> Result<u32, LibcError> {
+		Err(LibcError::from_raw_os_error(libc::EOPNOTSUPP))
+	}
+
+	#[cfg(target_os = "linux")]
+	fn close_backing_impl(&self, backing_id: u32) -> Result<(), LibcError> {
+		use fuse::os::linux::FUSE_DEV_IOC_BACKING_CLOSE;
+
+		let ioctl_rc = unsafe {
+			libc::ioctl(
+				self.socket.fd,
+				FUSE_DEV_IOC_BACKING_CLOSE as _,
+				&backing_id as *const u32,
+			)
+		};
+		if ioctl_rc == -1 {
+			return Err(LibcError::last_os_error());
+		}
+		Ok(())
+	}
+
+	#[cfg(not(target_os = "linux"))]
+	fn close_backing_impl(&self, _backing_id: u32) -> Result<(), LibcError> {
+		Err(LibcError::from_raw_os_error(libc::EOPNOTSUPP))
+	}
+
+	/// Creates a `FuseServerSocket` from an open `/dev/fuse` file descriptor.
+	///
+	/// The file descriptor may have been received from a mount helper or a
+	/// service manager that mounted the filesystem. If the helper has also
+	/// performed the `FUSE_INIT` handshake, the connection should be created
+	/// with [`FuseConnection::from_init_response`].
+	///
+	/// # Safety
+	///
+	/// The file descriptor must be open, and ownership of it is transferred
+	/// to the returned socket, which closes it when dropped.
+	///
+	/// [`FuseConnection::from_init_response`]: fuse::server::FuseConnection::from_init_response
 	#[must_use]
 	pub unsafe fn from_raw_fd(fd: i32) -> FuseServerSocket {
 		let socket = Socket {
@@ -224,6 +359,20 @@ impl FuseServerSocket {
 
 impl server::FuseSocket for FuseServerSocket {}
 
+#[cfg(feature = "std")]
+impl std::os::fd::AsRawFd for FuseServerSocket {
+	fn as_raw_fd(&self) -> std::os::fd::RawFd {
+		self.socket.fd
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::os::fd::FromRawFd for FuseServerSocket {
+	unsafe fn from_raw_fd(fd: std::os::fd::RawFd) -> FuseServerSocket {
+		FuseServerSocket::from_raw_fd(fd)
+	}
+}
+
 impl server::Socket for FuseServerSocket {
 	type Error = LibcError;
 
@@ -236,12 +385,90 @@ impl server::Socket for FuseServerSocket {
 	}
 }
 
+/// A [`FuseServerSocket`] for a filesystem mounted at a known mountpoint.
+///
+/// `FuseMount` implements [`FuseUnmount`], so that a server can unmount its
+/// filesystem with [`FuseConnection::unmount`]:
+///
+/// ```no_run
+/// # use fuse_libc::{FuseServerSocket, LibcError};
+/// # fn f(socket: FuseServerSocket) -> Result<(), LibcError> {
+/// use fuse_libc::FuseMount;
+///
+/// let target = c"/mnt/example";
+/// let mount = FuseMount::new(socket, target);
+/// let conn = fuse::server::FuseServer::new().connect(mount).unwrap();
+/// // ...
+/// conn.unmount()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`FuseUnmount`]: fuse::server::FuseUnmount
+/// [`FuseConnection::unmount`]: fuse::server::FuseConnection::unmount
+pub struct FuseMount<'a> {
+	socket: FuseServerSocket,
+	target: &'a ffi::CStr,
+}
+
+impl<'a> FuseMount<'a> {
+	/// Creates a `FuseMount` for the filesystem mounted at `target`.
+	#[must_use]
+	pub fn new(socket: FuseServerSocket, target: &'a ffi::CStr) -> Self {
+		Self { socket, target }
+	}
+
+	/// Returns the socket of the mounted filesystem.
+	#[must_use]
+	pub fn socket(&self) -> &FuseServerSocket {
+		&self.socket
+	}
+
+	/// Returns the mountpoint of the filesystem.
+	#[must_use]
+	pub fn target(&self) -> &'a ffi::CStr {
+		self.target
+	}
+
+	/// Consumes the `FuseMount`, returning its socket.
+	#[must_use]
+	pub fn into_socket(self) -> FuseServerSocket {
+		self.socket
+	}
+}
+
+impl server::FuseSocket for FuseMount<'_> {}
+
+impl server::FuseUnmount for FuseMount<'_> {
+	#[cfg(target_os = "linux")]
+	fn unmount(&self) -> Result<(), LibcError> {
+		crate::os::linux::unmount(self.target, 0)
+	}
+
+	#[cfg(target_os = "freebsd")]
+	fn unmount(&self) -> Result<(), LibcError> {
+		crate::os::freebsd::unmount(self.target, 0)
+	}
+}
+
+impl server::Socket for FuseMount<'_> {
+	type Error = LibcError;
+
+	fn recv(&self, buf: &mut [u8]) -> Result<usize, RecvError<LibcError>> {
+		self.socket.recv(buf)
+	}
+
+	fn send(&self, buf: SendBuf) -> Result<(), SendError<LibcError>> {
+		self.socket.send(buf)
+	}
+}
+
 #[cfg(target_os = "linux")]
-fn errno() -> i32 {
+pub(crate) fn errno() -> i32 {
 	unsafe { *libc::__errno_location() }
 }
 
 #[cfg(target_os = "freebsd")]
-fn errno() -> i32 {
+pub(crate) fn errno() -> i32 {
 	unsafe { *libc::__error() }
 }
@@ -0,0 +1,454 @@
+// Copyright 2021 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+
+use core::mem;
+use core::ptr;
+
+use fuse::client;
+use fuse::io::SendBuf;
+use fuse::server;
+
+use crate::io::iovec::IoVec;
+use crate::io::socket::{errno, LibcError};
+
+// Large enough for a handful of file descriptors. Any further descriptors
+// attached to a single message are closed by the kernel.
+const CMSG_BUF_WORDS: usize = 8;
+
+/// A FUSE or CUSE transport over a Unix domain socket.
+///
+/// `UnixSocket` lets a session be served by a process other than the one
+/// that opened `/dev/fuse` or `/dev/cuse`. A privileged stub holds the
+/// device handle and relays messages (for example with a [`FuseProxy`]),
+/// and an unprivileged server receives them from its end of the socket.
+///
+/// Messages are sent over a `SOCK_STREAM` socket. Every FUSE request and
+/// reply starts with its total length, so that length is used as the frame
+/// length and no extra framing bytes are added.
+///
+/// A `UnixSocket` implements both the server and client socket traits. The
+/// server end should be used with [`server::FuseSocket`] or
+/// [`server::CuseSocket`], and the stub end with [`client::io::Socket`].
+///
+/// [`FuseProxy`]: fuse::proxy::FuseProxy
+pub struct UnixSocket {
+	fd: i32,
+}
+
+impl Drop for UnixSocket {
+	fn drop(&mut self) {
+		unsafe {
+			let _ = libc::close(self.fd);
+		};
+	}
+}
+
+impl UnixSocket {
+	/// Creates a connected pair of Unix sockets.
+	pub fn pair() -> Result<(UnixSocket, UnixSocket), LibcError> {
+		let mut fds = [-1i32; 2];
+		let rc = unsafe {
+			libc::socketpair(
+				libc::AF_UNIX,
+				libc::SOCK_STREAM | libc::SOCK_CLOEXEC,
+				0,
+				fds.as_mut_ptr(),
+			)
+		};
+		if rc == -1 {
+			return Err(LibcError::last_os_error());
+		}
+		Ok((UnixSocket { fd: fds[0] }, UnixSocket { fd: fds[1] }))
+	}
+
+	/// Takes ownership of a connected `SOCK_STREAM` Unix socket.
+	///
+	/// # Safety
+	///
+	/// The file descriptor must be open and not owned by any other value.
+	#[must_use]
+	pub unsafe fn from_raw_fd(fd: i32) -> UnixSocket {
+		UnixSocket { fd }
+	}
+
+	#[must_use]
+	pub fn socket_fd(&self) -> i32 {
+		self.fd
+	}
+
+	/// Sends a file descriptor to the peer.
+	///
+	/// This is intended for the session handshake, in which one process
+	/// opens `/dev/fuse` (or one end of a socket pair) and hands it to
+	/// another. It must not be called while messages are being relayed
+	/// over the same socket. The peer receives the file descriptor with
+	/// [`recv_fd`](Self::recv_fd).
+	pub fn send_fd(&self, fd: i32) -> Result<(), LibcError> {
+		let iovecs = [IoVec::global(b"\0")];
+		let sent = self.sendmsg(&iovecs, Some(fd))?;
+		if sent != 1 {
+			return Err(LibcError::from_raw_os_error(libc::EIO));
+		}
+		Ok(())
+	}
+
+	/// Receives a file descriptor sent by the peer with
+	/// [`send_fd`](Self::send_fd).
+	///
+	/// The returned file descriptor has `FD_CLOEXEC` set and is owned by
+	/// the caller.
+	pub fn recv_fd(&self) -> Result<i32, LibcError> {
+		let mut buf = [0u8; 1];
+		let (received, fd) = self.recvmsg(&mut buf)?;
+		match (received, fd) {
+			(1, Some(fd)) => Ok(fd),
+			(0, _) => Err(LibcError::from_raw_os_error(libc::ENOTCONN)),
+			(_, fd) => {
+				if let Some(fd) = fd {
+					unsafe { libc::close(fd) };
+				}
+				Err(LibcError::from_raw_os_error(libc::EPROTO))
+			},
+		}
+	}
+
+	/// Receives a single message, and the file descriptor attached to it
+	/// by the peer's [`send_with_fd`].
+	///
+	/// If a file descriptor is returned it has `FD_CLOEXEC` set and is owned
+	/// by the caller. Messages received by the `recv()` methods of the
+	/// socket traits have any attached file descriptors closed.
+	///
+	/// [`send_with_fd`]: server::Socket::send_with_fd
+	pub fn recv_with_fd(
+		&self,
+		buf: &mut [u8],
+	) -> Result<(usize, Option<i32>), LibcError> {
+		match self.recv_message(buf)? {
+			Some(received) => Ok(received),
+			None => Err(LibcError::from_raw_os_error(libc::ENOTCONN)),
+		}
+	}
+
+	// Returns `None` if the peer closed the socket between messages.
+	fn recv_message(
+		&self,
+		buf: &mut [u8],
+	) -> Result<Option<(usize, Option<i32>)>, LibcError> {
+		const LEN_SIZE: usize = mem::size_of::<u32>();
+		if buf.len() < LEN_SIZE {
+			return Err(LibcError::from_raw_os_error(libc::EINVAL));
+		}
+
+		// Any attached file descriptor arrives with the first byte of the
+		// message, so only the first read needs to use `recvmsg()`.
+		let (received, fd) = self.recvmsg(&mut buf[..LEN_SIZE])?;
+		if received == 0 {
+			return Ok(None);
+		}
+		match self.recv_message_rest(buf, received) {
+			Ok(msg_len) => Ok(Some((msg_len, fd))),
+			Err(err) => {
+				if let Some(fd) = fd {
+					unsafe { libc::close(fd) };
+				}
+				Err(err)
+			},
+		}
+	}
+
+	fn recv_message_rest(
+		&self,
+		buf: &mut [u8],
+		received: usize,
+	) -> Result<usize, LibcError> {
+		const LEN_SIZE: usize = mem::size_of::<u32>();
+		self.read_exact(&mut buf[received..LEN_SIZE])?;
+		let mut len_bytes = [0u8; LEN_SIZE];
+		len_bytes.copy_from_slice(&buf[..LEN_SIZE]);
+		let msg_len = u32::from_ne_bytes(len_bytes) as usize;
+		if msg_len < LEN_SIZE {
+			return Err(LibcError::from_raw_os_error(libc::EPROTO));
+		}
+		if msg_len > buf.len() {
+			// The rest of the message is discarded, so that the next
+			// message can still be received.
+			self.discard(buf, msg_len - LEN_SIZE)?;
+			return Err(LibcError::from_raw_os_error(libc::EMSGSIZE));
+		}
+		self.read_exact(&mut buf[LEN_SIZE..msg_len])?;
+		Ok(msg_len)
+	}
+
+	// Reads and discards `len` bytes, using `buf` as scratch space.
+	fn discard(&self, buf: &mut [u8], mut len: usize) -> Result<(), LibcError> {
+		while len > 0 {
+			let chunk_len = core::cmp::min(len, buf.len());
+			self.read_exact(&mut buf[..chunk_len])?;
+			len -= chunk_len;
+		}
+		Ok(())
+	}
+
+	fn read_exact(&self, mut buf: &mut [u8]) -> Result<(), LibcError> {
+		while !buf.is_empty() {
+			let rc = unsafe {
+				libc::read(self.fd, buf.as_mut_ptr().cast(), buf.len())
+			};
+			if rc == -1 {
+				if errno() == libc::EINTR {
+					continue;
+				}
+				return Err(LibcError::last_os_error());
+			}
+			if rc == 0 {
+				// The peer closed the socket partway through a message.
+				return Err(LibcError::from_raw_os_error(libc::ECONNRESET));
+			}
+			buf = &mut buf[rc as usize..];
+		}
+		Ok(())
+	}
+
+	fn recvmsg(
+		&self,
+		buf: &mut [u8],
+	) -> Result<(usize, Option<i32>), LibcError> {
+		let mut iov = libc::iovec {
+			iov_base: buf.as_mut_ptr().cast(),
+			iov_len: buf.len(),
+		};
+		let mut cmsg_buf = [0u64; CMSG_BUF_WORDS];
+		let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+		msg.msg_iov = &mut iov;
+		msg.msg_iovlen = 1;
+		msg.msg_control = cmsg_buf.as_mut_ptr().cast();
+		msg.msg_controllen = mem::size_of_val(&cmsg_buf) as _;
+
+		let received = loop {
+			let rc = unsafe {
+				libc::recvmsg(self.fd, &mut msg, libc::MSG_CMSG_CLOEXEC)
+			};
+			if rc >= 0 {
+				break rc as usize;
+			}
+			if errno() != libc::EINTR {
+				return Err(LibcError::last_os_error());
+			}
+		};
+
+		// Keep the first file descriptor and close any others.
+		let mut fd = None;
+		unsafe {
+			let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+			while !cmsg.is_null() {
+				let hdr = &*cmsg;
+				if hdr.cmsg_level == libc::SOL_SOCKET
+					&& hdr.cmsg_type == libc::SCM_RIGHTS
+				{
+					let data = libc::CMSG_DATA(cmsg).cast::<i32>();
+					let data_len = hdr.cmsg_len as usize
+						- libc::CMSG_LEN(0) as usize;
+					for ii in 0..data_len / mem::size_of::<i32>() {
+						let received_fd = ptr::read_unaligned(data.add(ii));
+						if fd.is_none() {
+							fd = Some(received_fd);
+						} else {
+							libc::close(received_fd);
+						}
+					}
+				}
+				cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+			}
+		}
+		Ok((received, fd))
+	}
+
+	fn send_message(
+		&self,
+		buf: SendBuf,
+		fd: Option<i32>,
+	) -> Result<(), LibcError> {
+		type UninitIoVec<'a> = mem::MaybeUninit<IoVec<'a>>;
+
+		let mut iovec_storage: [UninitIoVec; SendBuf::MAX_CHUNKS_LEN] = unsafe {
+			mem::MaybeUninit::uninit().assume_init()
+		};
+		let iovecs = buf.map_chunks_into_uninit(
+			&mut iovec_storage,
+			IoVec::borrow,
+		);
+		let mut sent = self.sendmsg(iovecs, fd)?;
+
+		// Stream sockets may accept only part of a message. The remainder
+		// is sent without the file descriptor, which is attached to the
+		// first byte.
+		for chunk in buf.chunks() {
+			if sent >= chunk.len() {
+				sent -= chunk.len();
+				continue;
+			}
+			self.send_all(&chunk[sent..])?;
+			sent = 0;
+		}
+		Ok(())
+	}
+
+	fn send_all(&self, mut buf: &[u8]) -> Result<(), LibcError> {
+		while !buf.is_empty() {
+			let rc = unsafe {
+				libc::send(
+					self.fd,
+					buf.as_ptr().cast(),
+					buf.len(),
+					libc::MSG_NOSIGNAL,
+				)
+			};
+			if rc == -1 {
+				if errno() == libc::EINTR {
+					continue;
+				}
+				return Err(LibcError::last_os_error());
+			}
+			buf = &buf[rc as usize..];
+		}
+		Ok(())
+	}
+
+	fn sendmsg(
+		&self,
+		iovecs: &[IoVec],
+		fd: Option<i32>,
+	) -> Result<usize, LibcError> {
+		let mut cmsg_buf = [0u64; CMSG_BUF_WORDS];
+		let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+		msg.msg_iov = iovecs.as_ptr().cast::<libc::iovec>().cast_mut();
+		msg.msg_iovlen = iovecs.len() as _;
+		if let Some(fd) = fd {
+			const FD_SIZE: u32 = mem::size_of::<i32>() as u32;
+			msg.msg_control = cmsg_buf.as_mut_ptr().cast();
+			msg.msg_controllen = unsafe { libc::CMSG_SPACE(FD_SIZE) } as _;
+			unsafe {
+				let cmsg = libc::CMSG_FIRSTHDR(&msg);
+				(*cmsg).cmsg_level = libc::SOL_SOCKET;
+				(*cmsg).cmsg_type = libc::SCM_RIGHTS;
+				(*cmsg).cmsg_len = libc::CMSG_LEN(FD_SIZE) as _;
+				let data = libc::CMSG_DATA(cmsg).cast::<i32>();
+				ptr::write_unaligned(data, fd);
+			}
+		}
+		loop {
+			let rc = unsafe {
+				libc::sendmsg(self.fd, &msg, libc::MSG_NOSIGNAL)
+			};
+			if rc >= 0 {
+				return Ok(rc as usize);
+			}
+			if errno() != libc::EINTR {
+				return Err(LibcError::last_os_error());
+			}
+		}
+	}
+}
+
+// Server {{{
+
+impl server::Socket for UnixSocket {
+	type Error = LibcError;
+
+	fn recv(
+		&self,
+		buf: &mut [u8],
+	) -> Result<usize, server::RecvError<LibcError>> {
+		match self.recv_message(buf) {
+			Ok(Some((msg_len, fd))) => {
+				if let Some(fd) = fd {
+					unsafe { libc::close(fd) };
+				}
+				Ok(msg_len)
+			},
+			Ok(None) => {
+				let err = LibcError::from_raw_os_error(libc::ENOTCONN);
+				Err(server::RecvError::ConnectionClosed(err))
+			},
+			Err(err) => Err(server::RecvError::Other(err)),
+		}
+	}
+
+	fn send(&self, buf: SendBuf) -> Result<(), server::SendError<LibcError>> {
+		self.send_message(buf, None).map_err(server::SendError::Other)
+	}
+
+	fn can_send_fd(&self) -> bool {
+		true
+	}
+
+	fn send_with_fd(
+		&self,
+		buf: SendBuf,
+		fd: i32,
+	) -> Result<(), server::SendError<LibcError>> {
+		self.send_message(buf, Some(fd)).map_err(server::SendError::Other)
+	}
+}
+
+impl server::CuseSocket for UnixSocket {}
+
+impl server::FuseSocket for UnixSocket {}
+
+// }}}
+
+// Client {{{
+
+impl client::io::Socket for UnixSocket {
+	type Error = LibcError;
+
+	fn send(
+		&self,
+		buf: SendBuf,
+	) -> Result<(), client::io::SendError<LibcError>> {
+		if buf.len() > u32::MAX as usize {
+			return Err(client::io::SendError::RequestTooBig(buf.len() as u64));
+		}
+		self.send_message(buf, None).map_err(client::io::SendError::Other)
+	}
+
+	fn recv(
+		&self,
+		buf: &mut [u8],
+	) -> Result<usize, client::io::RecvError<LibcError>> {
+		match self.recv_message(buf) {
+			Ok(Some((msg_len, fd))) => {
+				if let Some(fd) = fd {
+					unsafe { libc::close(fd) };
+				}
+				Ok(msg_len)
+			},
+			Ok(None) => {
+				let err = LibcError::from_raw_os_error(libc::ENOTCONN);
+				Err(client::io::RecvError::Other(err))
+			},
+			Err(err) => Err(client::io::RecvError::Other(err)),
+		}
+	}
+}
+
+impl client::io::CuseSocket for UnixSocket {}
+
+impl client::io::FuseSocket for UnixSocket {}
+
+// }}}
@@ -0,0 +1,145 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::thread;
+use std::time::Duration;
+
+use fuse::io::SendBuf;
+use fuse::server::{RecvError, Socket};
+
+use fuse_libc::UnixSocket;
+
+// Returns a message of `len` bytes, starting with its length.
+fn message(len: usize, fill: u8) -> Vec<u8> {
+	let mut msg = vec![fill; len];
+	msg[..4].copy_from_slice(&(len as u32).to_ne_bytes());
+	msg
+}
+
+fn write_raw(socket: &UnixSocket, buf: &[u8]) {
+	let rc = unsafe {
+		libc::write(socket.socket_fd(), buf.as_ptr().cast(), buf.len())
+	};
+	assert_eq!(rc, buf.len() as isize);
+}
+
+fn recv_errno(socket: &UnixSocket, buf: &mut [u8]) -> i32 {
+	match Socket::recv(socket, buf) {
+		Err(RecvError::Other(err)) => err.raw_os_error(),
+		result => panic!("expected error, got {:?}", result),
+	}
+}
+
+#[test]
+fn send_recv() {
+	let (server, client) = UnixSocket::pair().unwrap();
+	let msg = message(16, 0xAA);
+	Socket::send(&client, SendBuf::new(&msg)).unwrap();
+
+	let mut buf = [0u8; 64];
+	let len = Socket::recv(&server, &mut buf).unwrap();
+	assert_eq!(&buf[..len], msg.as_slice());
+}
+
+#[test]
+fn recv_partial_reads() {
+	let (server, client) = UnixSocket::pair().unwrap();
+	let msg = message(16, 0xAA);
+
+	let writer = thread::spawn(move || {
+		// The length prefix and the body arrive in separate reads.
+		for byte in msg.chunks(3) {
+			write_raw(&client, byte);
+			thread::sleep(Duration::from_millis(5));
+		}
+		client
+	});
+
+	let mut buf = [0u8; 64];
+	let len = Socket::recv(&server, &mut buf).unwrap();
+	assert_eq!(&buf[..len], message(16, 0xAA).as_slice());
+	drop(writer.join().unwrap());
+}
+
+#[test]
+fn send_partial_writes() {
+	// Larger than the socket buffer, so the message is only partially
+	// accepted by the first write.
+	const LEN: usize = 4 << 20;
+	let (server, client) = UnixSocket::pair().unwrap();
+
+	let reader = thread::spawn(move || {
+		let mut buf = vec![0u8; LEN];
+		let len = Socket::recv(&server, &mut buf).unwrap();
+		buf.truncate(len);
+		buf
+	});
+
+	let msg = message(LEN, 0xAA);
+	let (head, tail) = msg.split_at(100);
+	let send_buf = SendBuf::from_chunks([head, tail]).unwrap();
+	Socket::send(&client, send_buf).unwrap();
+
+	assert!(reader.join().unwrap() == msg);
+}
+
+#[test]
+fn recv_oversized_message() {
+	let (server, client) = UnixSocket::pair().unwrap();
+	Socket::send(&client, SendBuf::new(&message(100, 0xAA))).unwrap();
+	Socket::send(&client, SendBuf::new(&message(16, 0xBB))).unwrap();
+
+	// The oversized message is discarded, and the next message is received
+	// intact.
+	let mut buf = [0u8; 32];
+	assert_eq!(recv_errno(&server, &mut buf), libc::EMSGSIZE);
+	let len = Socket::recv(&server, &mut buf).unwrap();
+	assert_eq!(&buf[..len], message(16, 0xBB).as_slice());
+}
+
+#[test]
+fn recv_message_cut_off_by_eof() {
+	let (server, client) = UnixSocket::pair().unwrap();
+	write_raw(&client, &message(100, 0xAA)[..10]);
+	drop(client);
+
+	let mut buf = [0u8; 128];
+	assert_eq!(recv_errno(&server, &mut buf), libc::ECONNRESET);
+}
+
+#[test]
+fn recv_length_cut_off_by_eof() {
+	let (server, client) = UnixSocket::pair().unwrap();
+	write_raw(&client, &message(100, 0xAA)[..2]);
+	drop(client);
+
+	let mut buf = [0u8; 128];
+	assert_eq!(recv_errno(&server, &mut buf), libc::ECONNRESET);
+}
+
+#[test]
+fn recv_eof_between_messages() {
+	let (server, client) = UnixSocket::pair().unwrap();
+	Socket::send(&client, SendBuf::new(&message(16, 0xAA))).unwrap();
+	drop(client);
+
+	let mut buf = [0u8; 64];
+	assert_eq!(Socket::recv(&server, &mut buf), Ok(16));
+	assert!(matches!(
+		Socket::recv(&server, &mut buf),
+		Err(RecvError::ConnectionClosed(_)),
+	));
+}
@@ -22,12 +22,15 @@ use fuse::os::freebsd as fuse_os_freebsd;
 use crate::io::iovec::IoVec;
 use crate::io::socket::{FuseServerSocket, LibcError};
 
+pub use crate::io::nonblock::NonblockDevFuse;
+
 #[cfg(all(doc, not(target_os = "freebsd")))]
 mod fuse_os_freebsd {
 	#[derive(Copy, Clone)]
 	pub struct MountOptions<'a> { _p: &'a () }
 }
 
+const MNT_RDONLY: i32 = 0x01;
 const MNT_NOSUID: i32 = 0x08;
 
 const DEFAULT_FLAGS: i32 = MNT_NOSUID;
@@ -47,6 +50,21 @@ impl<'a> MountOptions<'a> {
 	pub fn set_flags(&mut self, flags: i32) {
 		self.flags = flags;
 	}
+
+	/// Returns whether the filesystem will be mounted read-only.
+	#[must_use]
+	pub fn read_only(&self) -> bool {
+		self.flags & MNT_RDONLY != 0
+	}
+
+	/// Sets whether the filesystem will be mounted read-only (`MNT_RDONLY`).
+	pub fn set_read_only(&mut self, read_only: bool) {
+		if read_only {
+			self.flags |= MNT_RDONLY;
+		} else {
+			self.flags &= !MNT_RDONLY;
+		}
+	}
 }
 
 impl<'a> From<fuse_os_freebsd::MountOptions<'a>> for MountOptions<'a> {
@@ -67,7 +85,9 @@ pub fn mount<'a>(
 	let socket = FuseServerSocket::new()?;
 
 	let mut fd_buf = [0u8; 32];
-	fmt_raw_fd(&mut fd_buf, socket.fuse_device_fd());
+	fmt_u32(&mut fd_buf, socket.fuse_device_fd());
+	let mut max_read_buf = [0u8; 32];
+	let mut timeout_buf = [0u8; 32];
 	let mut iovecs = [
 		// fstype
 		IoVec::global(b"fstype\0"),
@@ -85,23 +105,48 @@ pub fn mount<'a>(
 		IoVec::global(b"fd\0"),
 		IoVec::borrow(&fd_buf),
 
+		// placeholder: allow_other
+		IoVec::null(),
+		IoVec::null(),
+
 		// placeholder: default_permissions
 		IoVec::null(),
 		IoVec::null(),
 
+		// placeholder: max_read=
+		IoVec::null(),
+		IoVec::null(),
+
 		// placeholder: subtype=
 		IoVec::null(),
 		IoVec::null(),
+
+		// placeholder: timeout=
+		IoVec::null(),
+		IoVec::null(),
 	];
 
 	let mut iovecs_len: usize = 8;
 
+	if opts.allow_other() {
+		iovecs[iovecs_len] = IoVec::global(b"allow_other\0");
+		iovecs[iovecs_len + 1] = IoVec::global(b"\0");
+		iovecs_len += 2;
+	}
+
 	if opts.default_permissions() {
 		iovecs[iovecs_len] = IoVec::global(b"default_permissions\0");
 		iovecs[iovecs_len + 1] = IoVec::global(b"\0");
 		iovecs_len += 2;
 	}
 
+	if let Some(max_read) = opts.max_read() {
+		fmt_u32(&mut max_read_buf, max_read);
+		iovecs[iovecs_len] = IoVec::global(b"max_read=\0");
+		iovecs[iovecs_len + 1] = IoVec::borrow(&max_read_buf);
+		iovecs_len += 2;
+	}
+
 	if let Some(subtype) = opts.subtype() {
 		let subtype_bytes = subtype.as_cstr().to_bytes_with_nul();
 		iovecs[iovecs_len] = IoVec::global(b"subtype=\0");
@@ -109,6 +154,13 @@ pub fn mount<'a>(
 		iovecs_len += 2;
 	}
 
+	if let Some(daemon_timeout) = opts.daemon_timeout() {
+		fmt_u32(&mut timeout_buf, daemon_timeout);
+		iovecs[iovecs_len] = IoVec::global(b"timeout=\0");
+		iovecs[iovecs_len + 1] = IoVec::borrow(&timeout_buf);
+		iovecs_len += 2;
+	}
+
 	let nmount_rc = unsafe {
 		libc::nmount(
 			iovecs.as_mut_ptr().cast::<libc::iovec>(),
@@ -123,10 +175,22 @@ pub fn mount<'a>(
 	Ok(socket)
 }
 
-fn fmt_raw_fd(buf: &mut [u8; 32], fd: u32) {
+fn fmt_u32(buf: &mut [u8; 32], value: u32) {
 	let buf_ptr = buf.as_mut_ptr().cast::<libc::c_char>();
 	let format_ptr = b"%u\0".as_ptr().cast::<libc::c_char>();
 	unsafe {
-		libc::snprintf(buf_ptr, 32, format_ptr, fd);
+		libc::snprintf(buf_ptr, 32, format_ptr, value);
+	}
+}
+
+/// Unmount the filesystem mounted at `target`.
+///
+/// The `flags` are passed through to `unmount(2)`, and may be used to
+/// request a forced (`MNT_FORCE`) unmount.
+pub fn unmount(target: &ffi::CStr, flags: i32) -> Result<(), LibcError> {
+	let rc = unsafe { libc::unmount(target.as_ptr(), flags) };
+	if rc != 0 {
+		return Err(LibcError::last_os_error());
 	}
+	Ok(())
 }
@@ -21,12 +21,17 @@ use fuse::os::linux as fuse_os_linux;
 
 use crate::io::socket::{FuseServerSocket, LibcError};
 
+pub use crate::io::nonblock::NonblockDevFuse;
+
 #[cfg(all(doc, not(target_os = "linux")))]
 mod fuse_os_linux {
 	#[derive(Copy, Clone)]
 	pub struct MountOptions<'a> { _p: &'a () }
+	pub struct FuseConnectionId;
+	pub struct FusectlInfo;
 }
 
+const MS_RDONLY: u32 = 0x1;
 const MS_NOSUID: u32 = 0x2;
 const MS_NODEV: u32 = 0x4;
 
@@ -63,6 +68,21 @@ impl<'a> MountOptions<'a> {
 	pub fn set_flags(&mut self, flags: u32) {
 		self.flags = flags;
 	}
+
+	/// Returns whether the filesystem will be mounted read-only.
+	#[must_use]
+	pub fn read_only(&self) -> bool {
+		self.flags & MS_RDONLY != 0
+	}
+
+	/// Sets whether the filesystem will be mounted read-only (`MS_RDONLY`).
+	pub fn set_read_only(&mut self, read_only: bool) {
+		if read_only {
+			self.flags |= MS_RDONLY;
+		} else {
+			self.flags &= !MS_RDONLY;
+		}
+	}
 }
 
 impl<'a> From<fuse_os_linux::MountOptions<'a>> for MountOptions<'a> {
@@ -133,3 +153,288 @@ fn get_root_mode(target: &ffi::CStr) -> Result<fuse::FileMode, LibcError> {
 	}
 	Ok(fuse::FileMode::new(u32::from(statx_buf.stx_mode)))
 }
+
+/// Unmount the filesystem mounted at `target`.
+///
+/// The `flags` are passed through to `umount2(2)`, and may be used to
+/// request a lazy (`MNT_DETACH`) or forced (`MNT_FORCE`) unmount.
+pub fn unmount(target: &ffi::CStr, flags: u32) -> Result<(), LibcError> {
+	let rc = unsafe { libc::umount2(target.as_ptr(), flags as libc::c_int) };
+	if rc != 0 {
+		return Err(LibcError::last_os_error());
+	}
+	Ok(())
+}
+
+/// Returns the `fusectl` identifier of the FUSE filesystem mounted at
+/// `target`.
+pub fn connection_id(
+	target: &ffi::CStr,
+) -> Result<fuse_os_linux::FuseConnectionId, LibcError> {
+	let mut statx_buf: libc::statx = unsafe { core::mem::zeroed() };
+	let rc = unsafe {
+		libc::statx(
+			libc::AT_FDCWD,
+			target.as_ptr(),
+			0,
+			0,
+			&mut statx_buf as *mut libc::statx,
+		)
+	};
+	if rc != 0 {
+		return Err(LibcError::last_os_error());
+	}
+	Ok(fuse_os_linux::FuseConnectionId::new(
+		statx_buf.stx_dev_major,
+		statx_buf.stx_dev_minor,
+	))
+}
+
+/// Reads the state of the FUSE connection of the filesystem mounted at
+/// `target` from the `fusectl` filesystem.
+///
+/// The `fusectl` filesystem must be mounted at `/sys/fs/fuse/connections`.
+pub fn connection_info(
+	target: &ffi::CStr,
+) -> Result<fuse_os_linux::FusectlInfo, LibcError> {
+	FuseConnControl::new(connection_id(target)?).info()
+}
+
+/// Abort the FUSE connection of the filesystem mounted at `target`.
+///
+/// The connection is aborted by writing to its `abort` file in the
+/// `fusectl` filesystem, which must be mounted at `/sys/fs/fuse/connections`.
+/// All pending and future requests will fail with `ENOTCONN`, and the
+/// server's socket will be closed by the kernel.
+///
+/// The filesystem remains mounted after its connection is aborted, and must
+/// be separately unmounted with [`unmount`].
+pub fn abort(target: &ffi::CStr) -> Result<(), LibcError> {
+	FuseConnControl::new(connection_id(target)?).abort()
+}
+
+/// Runtime control of a FUSE connection through the `fusectl` filesystem.
+///
+/// The `fusectl` filesystem must be mounted at `/sys/fs/fuse/connections`.
+/// Changing a connection's limits requires the `CAP_SYS_ADMIN` capability,
+/// unless the new value is within the limits set by the `fuse` kernel
+/// module's `max_user_bgreq` and `max_user_congthresh` parameters.
+#[derive(Clone, Debug)]
+pub struct FuseConnControl {
+	connection_id: fuse_os_linux::FuseConnectionId,
+}
+
+impl FuseConnControl {
+	/// Creates a `FuseConnControl` for the connection with the given ID.
+	#[must_use]
+	pub fn new(connection_id: fuse_os_linux::FuseConnectionId) -> Self {
+		Self { connection_id }
+	}
+
+	/// Creates a `FuseConnControl` for the connection of the filesystem
+	/// mounted at `target`.
+	pub fn for_mountpoint(target: &ffi::CStr) -> Result<Self, LibcError> {
+		Ok(Self::new(connection_id(target)?))
+	}
+
+	/// Returns the connection's `fusectl` identifier.
+	#[must_use]
+	pub fn connection_id(&self) -> fuse_os_linux::FuseConnectionId {
+		self.connection_id
+	}
+
+	/// Reads the current state of the connection.
+	pub fn info(&self) -> Result<fuse_os_linux::FusectlInfo, LibcError> {
+		match fuse_os_linux::read_fusectl_info(self.connection_id, read_file)? {
+			Some(info) => Ok(info),
+			None => Err(LibcError::from_raw_os_error(libc::EINVAL)),
+		}
+	}
+
+	/// Aborts the connection.
+	///
+	/// See [`abort`] for details.
+	pub fn abort(&self) -> Result<(), LibcError> {
+		self.write(fuse_os_linux::FusectlFile::Abort, 1)
+	}
+
+	/// Sets the maximum number of background requests.
+	///
+	/// This overrides the value negotiated by `FUSE_INIT`. The kernel
+	/// rejects values greater than [`u16::MAX`] with `EINVAL`.
+	pub fn set_max_background(
+		&self,
+		max_background: u32,
+	) -> Result<(), LibcError> {
+		self.write(fuse_os_linux::FusectlFile::MaxBackground, max_background)
+	}
+
+	/// Sets the number of background requests that may be queued before
+	/// the kernel marks the filesystem as congested.
+	///
+	/// This overrides the value negotiated by `FUSE_INIT`. The kernel
+	/// rejects values greater than [`u16::MAX`] with `EINVAL`.
+	pub fn set_congestion_threshold(
+		&self,
+		congestion_threshold: u32,
+	) -> Result<(), LibcError> {
+		self.write(
+			fuse_os_linux::FusectlFile::CongestionThreshold,
+			congestion_threshold,
+		)
+	}
+
+	fn write(
+		&self,
+		file: fuse_os_linux::FusectlFile,
+		value: u32,
+	) -> Result<(), LibcError> {
+		fuse_os_linux::write_fusectl_file(
+			self.connection_id,
+			file,
+			value,
+			write_file,
+		)
+	}
+}
+
+fn read_file(path: &ffi::CStr, buf: &mut [u8]) -> Result<usize, LibcError> {
+	let fd = unsafe {
+		libc::open(path.as_ptr(), libc::O_RDONLY | libc::O_CLOEXEC)
+	};
+	if fd == -1 {
+		return Err(LibcError::last_os_error());
+	}
+	let read_rc = unsafe {
+		libc::read(fd, buf.as_mut_ptr().cast(), buf.len())
+	};
+	let read_err = LibcError::last_os_error();
+	unsafe { libc::close(fd) };
+	if read_rc == -1 {
+		return Err(read_err);
+	}
+	Ok(read_rc as usize)
+}
+
+fn write_file(path: &ffi::CStr, value: &[u8]) -> Result<(), LibcError> {
+	let fd = unsafe {
+		libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC)
+	};
+	if fd == -1 {
+		return Err(LibcError::last_os_error());
+	}
+	let write_rc = unsafe {
+		libc::write(fd, value.as_ptr().cast(), value.len())
+	};
+	let write_err = LibcError::last_os_error();
+	unsafe { libc::close(fd) };
+	if write_rc == -1 {
+		return Err(write_err);
+	}
+	Ok(())
+}
+
+/// Returns the client's limit on the `max_pages` of FUSE connections.
+///
+/// The limit is read from the `fs.fuse.max_pages_limit` sysctl. Kernels
+/// older than Linux 6.13 don't have this sysctl, and their fixed limit of
+/// [`DEFAULT_MAX_PAGES_LIMIT`] is returned instead.
+///
+/// A server can pass the limit to [`FuseServer::max_pages`] so that the
+/// connection's `max_write` matches the `max_pages` used by the client.
+///
+/// [`DEFAULT_MAX_PAGES_LIMIT`]: fuse_os_linux::DEFAULT_MAX_PAGES_LIMIT
+/// [`FuseServer::max_pages`]: fuse::server::FuseServer::max_pages
+pub fn max_pages_limit() -> Result<u16, LibcError> {
+	use fuse_os_linux::{
+		parse_max_pages_limit,
+		DEFAULT_MAX_PAGES_LIMIT,
+		FUSECTL_READ_MAX,
+		MAX_PAGES_LIMIT_PATH,
+	};
+
+	let mut buf = [0u8; FUSECTL_READ_MAX];
+	let read_len = match read_file(MAX_PAGES_LIMIT_PATH, &mut buf) {
+		Ok(read_len) => read_len,
+		Err(err) if err.raw_os_error() == libc::ENOENT => {
+			return Ok(DEFAULT_MAX_PAGES_LIMIT);
+		},
+		Err(err) => return Err(err),
+	};
+	match parse_max_pages_limit(&buf[..read_len]) {
+		Some(limit) => Ok(limit),
+		None => Err(LibcError::from_raw_os_error(libc::EINVAL)),
+	}
+}
+
+/// Reads the supplementary groups of a process from `/proc/<pid>/status`.
+///
+/// The group IDs are written into `storage`. Returns `EINVAL` if the status
+/// file couldn't be parsed, or if the process has more supplementary groups
+/// than fit in `storage`.
+///
+/// This can be used to complete the [`fuse::Credentials`] of a request, for
+/// filesystems that perform their own permission checks.
+pub fn supplementary_groups(
+	process_id: core::num::NonZeroU32,
+	storage: &mut [u32],
+) -> Result<&[u32], LibcError> {
+	let mut status_buf = [0u8; PAGE_SIZE];
+	let status = read_proc_status(process_id, &mut status_buf)?;
+	fuse_os_linux::parse_proc_status_groups(status, storage)
+		.ok_or(LibcError::from_raw_os_error(libc::EINVAL))
+}
+
+/// Reads the IDs of a process in each of its PID namespaces from
+/// `/proc/<pid>/status`.
+///
+/// The first process ID is `process_id`, and the last is the process's ID
+/// in its own PID namespace. See [`parse_proc_status_nspid`] for details.
+///
+/// The process IDs are written into `storage`. Returns `EINVAL` if the
+/// status file couldn't be parsed, or if the process is nested in more PID
+/// namespaces than fit in `storage`.
+///
+/// [`parse_proc_status_nspid`]: fuse_os_linux::parse_proc_status_nspid
+pub fn namespace_process_ids(
+	process_id: core::num::NonZeroU32,
+	storage: &mut [u32],
+) -> Result<&[u32], LibcError> {
+	let mut status_buf = [0u8; PAGE_SIZE];
+	let status = read_proc_status(process_id, &mut status_buf)?;
+	fuse_os_linux::parse_proc_status_nspid(status, storage)
+		.ok_or(LibcError::from_raw_os_error(libc::EINVAL))
+}
+
+fn read_proc_status(
+	process_id: core::num::NonZeroU32,
+	status_buf: &mut [u8; PAGE_SIZE],
+) -> Result<&[u8], LibcError> {
+	use fuse_os_linux::{proc_status_path, PROC_STATUS_PATH_MAX};
+
+	let mut path_buf = [0u8; PROC_STATUS_PATH_MAX];
+	let path = proc_status_path(process_id, &mut path_buf);
+
+	let fd = unsafe {
+		libc::open(path.as_ptr(), libc::O_RDONLY | libc::O_CLOEXEC)
+	};
+	if fd == -1 {
+		return Err(LibcError::last_os_error());
+	}
+	let mut status_len = 0;
+	let read_result = loop {
+		let buf = &mut status_buf[status_len..];
+		if buf.is_empty() {
+			break Ok(());
+		}
+		let rc = unsafe { libc::read(fd, buf.as_mut_ptr().cast(), buf.len()) };
+		match rc {
+			0 => break Ok(()),
+			-1 => break Err(LibcError::last_os_error()),
+			n => status_len += n as usize,
+		}
+	};
+	unsafe { libc::close(fd) };
+	read_result?;
+	Ok(&status_buf[..status_len])
+}
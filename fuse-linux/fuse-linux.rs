@@ -56,13 +56,16 @@ use fuse::os::linux as fuse_os_linux;
 #[cfg(all(doc, not(target_os = "linux")))]
 mod fuse_os_linux {
 	pub struct MountOptions<'a> { _p: &'a () }
+	pub struct FuseConnectionId;
+	pub struct FusectlInfo;
 }
 
 mod socket;
 mod sys;
 
-pub use socket::{CuseServerSocket, FuseServerSocket};
+pub use socket::{CuseServerSocket, FuseMount, FuseServerSocket};
 
+const MS_RDONLY: u32 = 1 << 0;
 const MS_NOSUID: u32 = 1 << 1;
 const MS_NODEV:  u32 = 1 << 2;
 
@@ -102,6 +105,21 @@ impl<'a> MountOptions<'a> {
 	pub fn set_flags(&mut self, flags: u32) {
 		self.flags = flags;
 	}
+
+	/// Returns whether the filesystem will be mounted read-only.
+	#[must_use]
+	pub fn read_only(&self) -> bool {
+		self.flags & MS_RDONLY != 0
+	}
+
+	/// Sets whether the filesystem will be mounted read-only (`MS_RDONLY`).
+	pub fn set_read_only(&mut self, read_only: bool) {
+		if read_only {
+			self.flags |= MS_RDONLY;
+		} else {
+			self.flags &= !MS_RDONLY;
+		}
+	}
 }
 
 impl<'a> From<fuse_os_linux::MountOptions<'a>> for MountOptions<'a> {
@@ -159,3 +177,266 @@ fn get_root_mode(target: &ffi::CStr) -> Result<fuse::FileMode, linux_errno::Erro
 	};
 	Ok(fuse::FileMode::new(u32::from(statx.stx_mode)))
 }
+
+/// Unmount the filesystem mounted at `target`.
+///
+/// The `flags` are passed through to `umount2(2)`, and may be used to
+/// request a lazy (`MNT_DETACH`) or forced (`MNT_FORCE`) unmount.
+pub fn unmount(target: &ffi::CStr, flags: u32) -> Result<(), linux_errno::Error> {
+	unsafe { sys::umount2(target, flags) }
+}
+
+/// Returns the `fusectl` identifier of the FUSE filesystem mounted at
+/// `target`.
+pub fn connection_id(
+	target: &ffi::CStr,
+) -> Result<fuse_os_linux::FuseConnectionId, linux_errno::Error> {
+	let statx = unsafe { sys::statx(sys::AT_FDCWD, target, 0, 0)? };
+	Ok(fuse_os_linux::FuseConnectionId::new(
+		statx.stx_dev_major,
+		statx.stx_dev_minor,
+	))
+}
+
+/// Reads the state of the FUSE connection of the filesystem mounted at
+/// `target` from the `fusectl` filesystem.
+///
+/// The `fusectl` filesystem must be mounted at `/sys/fs/fuse/connections`.
+pub fn connection_info(
+	target: &ffi::CStr,
+) -> Result<fuse_os_linux::FusectlInfo, linux_errno::Error> {
+	FuseConnControl::new(connection_id(target)?).info()
+}
+
+/// Abort the FUSE connection of the filesystem mounted at `target`.
+///
+/// The connection is aborted by writing to its `abort` file in the
+/// `fusectl` filesystem, which must be mounted at `/sys/fs/fuse/connections`.
+/// All pending and future requests will fail with `ENOTCONN`, and the
+/// server's socket will be closed by the kernel.
+///
+/// The filesystem remains mounted after its connection is aborted, and must
+/// be separately unmounted with [`unmount`].
+pub fn abort(target: &ffi::CStr) -> Result<(), linux_errno::Error> {
+	FuseConnControl::new(connection_id(target)?).abort()
+}
+
+/// Runtime control of a FUSE connection through the `fusectl` filesystem.
+///
+/// The `fusectl` filesystem must be mounted at `/sys/fs/fuse/connections`.
+/// Changing a connection's limits requires the `CAP_SYS_ADMIN` capability,
+/// unless the new value is within the limits set by the `fuse` kernel
+/// module's `max_user_bgreq` and `max_user_congthresh` parameters.
+#[derive(Clone, Debug)]
+pub struct FuseConnControl {
+	connection_id: fuse_os_linux::FuseConnectionId,
+}
+
+impl FuseConnControl {
+	/// Creates a `FuseConnControl` for the connection with the given ID.
+	#[must_use]
+	pub fn new(connection_id: fuse_os_linux::FuseConnectionId) -> Self {
+		Self { connection_id }
+	}
+
+	/// Creates a `FuseConnControl` for the connection of the filesystem
+	/// mounted at `target`.
+	pub fn for_mountpoint(
+		target: &ffi::CStr,
+	) -> Result<Self, linux_errno::Error> {
+		Ok(Self::new(connection_id(target)?))
+	}
+
+	/// Returns the connection's `fusectl` identifier.
+	#[must_use]
+	pub fn connection_id(&self) -> fuse_os_linux::FuseConnectionId {
+		self.connection_id
+	}
+
+	/// Reads the current state of the connection.
+	pub fn info(
+		&self,
+	) -> Result<fuse_os_linux::FusectlInfo, linux_errno::Error> {
+		fuse_os_linux::read_fusectl_info(self.connection_id, read_file)?
+			.ok_or(linux_errno::EINVAL)
+	}
+
+	/// Aborts the connection.
+	///
+	/// See [`abort`] for details.
+	pub fn abort(&self) -> Result<(), linux_errno::Error> {
+		self.write(fuse_os_linux::FusectlFile::Abort, 1)
+	}
+
+	/// Sets the maximum number of background requests.
+	///
+	/// This overrides the value negotiated by `FUSE_INIT`. The kernel
+	/// rejects values greater than [`u16::MAX`] with `EINVAL`.
+	pub fn set_max_background(
+		&self,
+		max_background: u32,
+	) -> Result<(), linux_errno::Error> {
+		self.write(fuse_os_linux::FusectlFile::MaxBackground, max_background)
+	}
+
+	/// Sets the number of background requests that may be queued before
+	/// the kernel marks the filesystem as congested.
+	///
+	/// This overrides the value negotiated by `FUSE_INIT`. The kernel
+	/// rejects values greater than [`u16::MAX`] with `EINVAL`.
+	pub fn set_congestion_threshold(
+		&self,
+		congestion_threshold: u32,
+	) -> Result<(), linux_errno::Error> {
+		self.write(
+			fuse_os_linux::FusectlFile::CongestionThreshold,
+			congestion_threshold,
+		)
+	}
+
+	fn write(
+		&self,
+		file: fuse_os_linux::FusectlFile,
+		value: u32,
+	) -> Result<(), linux_errno::Error> {
+		fuse_os_linux::write_fusectl_file(
+			self.connection_id,
+			file,
+			value,
+			write_file,
+		)
+	}
+}
+
+fn read_file(
+	path: &ffi::CStr,
+	buf: &mut [u8],
+) -> Result<usize, linux_errno::Error> {
+	let fd = unsafe {
+		sys::open(sys::AT_FDCWD, path, sys::O_RDONLY | sys::O_CLOEXEC, 0)?
+	};
+	let read_result = unsafe { sys::read(fd, buf) };
+	let close_result = unsafe { sys::close(fd) };
+	let read_len = read_result?;
+	close_result?;
+	Ok(read_len)
+}
+
+fn write_file(
+	path: &ffi::CStr,
+	value: &[u8],
+) -> Result<(), linux_errno::Error> {
+	let fd = unsafe {
+		sys::open(sys::AT_FDCWD, path, sys::O_WRONLY | sys::O_CLOEXEC, 0)?
+	};
+	let write_result = unsafe { sys::write(fd, value) };
+	let close_result = unsafe { sys::close(fd) };
+	write_result?;
+	close_result
+}
+
+/// Returns the page size of the host, as read from `/proc/self/auxv`.
+///
+/// The page size may be passed to [`FuseServer::page_size`] so that the
+/// connection's `max_pages` and receive buffer length are computed correctly
+/// on hosts with pages larger than 4 KiB.
+///
+/// [`FuseServer::page_size`]: fuse::server::FuseServer::page_size
+pub fn page_size() -> Result<usize, linux_errno::Error> {
+	let fd = unsafe {
+		sys::open(
+			sys::AT_FDCWD,
+			fuse_os_linux::PROC_SELF_AUXV_PATH,
+			sys::O_RDONLY | sys::O_CLOEXEC,
+			0,
+		)?
+	};
+	let mut auxv_buf = [0u8; PAGE_SIZE];
+	let mut auxv_len = 0;
+	let read_result = loop {
+		let buf = &mut auxv_buf[auxv_len..];
+		if buf.is_empty() {
+			break Ok(());
+		}
+		match unsafe { sys::read(fd, buf) } {
+			Ok(0) => break Ok(()),
+			Ok(n) => auxv_len += n,
+			Err(err) => break Err(err),
+		}
+	};
+	let close_result = unsafe { sys::close(fd) };
+	read_result?;
+	close_result?;
+
+	fuse_os_linux::parse_auxv_page_size(&auxv_buf[..auxv_len])
+		.ok_or(linux_errno::EINVAL)
+}
+
+/// Reads the supplementary groups of a process from `/proc/<pid>/status`.
+///
+/// The group IDs are written into `storage`. Returns `EINVAL` if the status
+/// file couldn't be parsed, or if the process has more supplementary groups
+/// than fit in `storage`.
+///
+/// This can be used to complete the [`fuse::Credentials`] of a request, for
+/// filesystems that perform their own permission checks.
+pub fn supplementary_groups(
+	process_id: core::num::NonZeroU32,
+	storage: &mut [u32],
+) -> Result<&[u32], linux_errno::Error> {
+	let mut status_buf = [0u8; PAGE_SIZE];
+	let status = read_proc_status(process_id, &mut status_buf)?;
+	fuse_os_linux::parse_proc_status_groups(status, storage)
+		.ok_or(linux_errno::EINVAL)
+}
+
+/// Reads the IDs of a process in each of its PID namespaces from
+/// `/proc/<pid>/status`.
+///
+/// The first process ID is `process_id`, and the last is the process's ID
+/// in its own PID namespace. See [`parse_proc_status_nspid`] for details.
+///
+/// The process IDs are written into `storage`. Returns `EINVAL` if the
+/// status file couldn't be parsed, or if the process is nested in more PID
+/// namespaces than fit in `storage`.
+///
+/// [`parse_proc_status_nspid`]: fuse_os_linux::parse_proc_status_nspid
+pub fn namespace_process_ids(
+	process_id: core::num::NonZeroU32,
+	storage: &mut [u32],
+) -> Result<&[u32], linux_errno::Error> {
+	let mut status_buf = [0u8; PAGE_SIZE];
+	let status = read_proc_status(process_id, &mut status_buf)?;
+	fuse_os_linux::parse_proc_status_nspid(status, storage)
+		.ok_or(linux_errno::EINVAL)
+}
+
+fn read_proc_status(
+	process_id: core::num::NonZeroU32,
+	status_buf: &mut [u8; PAGE_SIZE],
+) -> Result<&[u8], linux_errno::Error> {
+	use fuse_os_linux::{proc_status_path, PROC_STATUS_PATH_MAX};
+
+	let mut path_buf = [0u8; PROC_STATUS_PATH_MAX];
+	let path = proc_status_path(process_id, &mut path_buf);
+
+	let fd = unsafe {
+		sys::open(sys::AT_FDCWD, path, sys::O_RDONLY | sys::O_CLOEXEC, 0)?
+	};
+	let mut status_len = 0;
+	let read_result = loop {
+		let buf = &mut status_buf[status_len..];
+		if buf.is_empty() {
+			break Ok(());
+		}
+		match unsafe { sys::read(fd, buf) } {
+			Ok(0) => break Ok(()),
+			Ok(n) => status_len += n,
+			Err(err) => break Err(err),
+		}
+	};
+	let close_result = unsafe { sys::close(fd) };
+	read_result?;
+	close_result?;
+	Ok(&status_buf[..status_len])
+}
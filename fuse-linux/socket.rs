@@ -55,6 +55,9 @@ impl Socket {
 			// Interrupted by signal. Try again.
 			errno::EINTR => Ok(()),
 
+			// The socket is non-blocking and no request is available.
+			errno::EAGAIN => Err(RecvError::WouldBlock(err)),
+
 			// FUSE (but not CUSE) uses ENODEV to signal the clean shutdown of
 			// the connection by the client.
 			errno::ENODEV if self.enodev_is_eof => {
@@ -167,6 +170,54 @@ impl FuseServerSocket {
 		self.socket.fd as u32
 	}
 
+	/// Registers a passthrough backing file with the client.
+	///
+	/// The returned backing file ID may be sent in replies to `FUSE_OPEN`
+	/// and `FUSE_CREATE` to enable passthrough I/O, and must be unregistered
+	/// with [`close_backing`](Self::close_backing) once no longer needed.
+	///
+	/// Passthrough requires the `PASSTHROUGH` init flag, and registering a
+	/// backing file requires the `CAP_SYS_ADMIN` capability.
+	pub fn open_backing(&self, fd: i32) -> Result<u32, Error> {
+		let mut map = fuse::kernel::fuse_backing_map::new();
+		map.fd = fd;
+		let backing_id = unsafe {
+			sys::ioctl(
+				self.socket.fd,
+				fuse::os::linux::FUSE_DEV_IOC_BACKING_OPEN,
+				(&map as *const fuse::kernel::fuse_backing_map).cast(),
+			)?
+		};
+		Ok(backing_id as u32)
+	}
+
+	/// Unregisters a passthrough backing file.
+	///
+	/// Files already opened with the backing file ID are unaffected.
+	pub fn close_backing(&self, backing_id: u32) -> Result<(), Error> {
+		unsafe {
+			sys::ioctl(
+				self.socket.fd,
+				fuse::os::linux::FUSE_DEV_IOC_BACKING_CLOSE,
+				(&backing_id as *const u32).cast(),
+			)?
+		};
+		Ok(())
+	}
+
+	/// Creates a `FuseServerSocket` from an open `/dev/fuse` file descriptor.
+	///
+	/// The file descriptor may have been received from a mount helper or a
+	/// service manager that mounted the filesystem. If the helper has also
+	/// performed the `FUSE_INIT` handshake, the connection should be created
+	/// with [`FuseConnection::from_init_response`].
+	///
+	/// # Safety
+	///
+	/// The file descriptor must be open, and ownership of it is transferred
+	/// to the returned socket, which closes it when dropped.
+	///
+	/// [`FuseConnection::from_init_response`]: fuse::server::FuseConnection::from_init_response
 	#[must_use]
 	pub unsafe fn from_raw_fd(fd: i32) -> FuseServerSocket {
 		let socket = Socket {
@@ -190,3 +241,76 @@ impl server::Socket for FuseServerSocket {
 		self.socket.send(buf)
 	}
 }
+
+/// A [`FuseServerSocket`] for a filesystem mounted at a known mountpoint.
+///
+/// `FuseMount` implements [`FuseUnmount`], so that a server can unmount its
+/// filesystem with [`FuseConnection::unmount`]:
+///
+/// ```no_run
+/// # fn f() -> Result<(), linux_errno::Error> {
+/// use fuse_linux::FuseMount;
+///
+/// let target = c"/mnt/example";
+/// let options = fuse::os::linux::MountOptions::new();
+/// let socket = fuse_linux::mount(target, options)?;
+/// let mount = FuseMount::new(socket, target);
+/// let conn = fuse::server::FuseServer::new().connect(mount).unwrap();
+/// // ...
+/// conn.unmount()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`FuseUnmount`]: fuse::server::FuseUnmount
+/// [`FuseConnection::unmount`]: fuse::server::FuseConnection::unmount
+pub struct FuseMount<'a> {
+	socket: FuseServerSocket,
+	target: &'a ffi::CStr,
+}
+
+impl<'a> FuseMount<'a> {
+	/// Creates a `FuseMount` for the filesystem mounted at `target`.
+	#[must_use]
+	pub fn new(socket: FuseServerSocket, target: &'a ffi::CStr) -> Self {
+		Self { socket, target }
+	}
+
+	/// Returns the socket of the mounted filesystem.
+	#[must_use]
+	pub fn socket(&self) -> &FuseServerSocket {
+		&self.socket
+	}
+
+	/// Returns the mountpoint of the filesystem.
+	#[must_use]
+	pub fn target(&self) -> &'a ffi::CStr {
+		self.target
+	}
+
+	/// Consumes the `FuseMount`, returning its socket.
+	#[must_use]
+	pub fn into_socket(self) -> FuseServerSocket {
+		self.socket
+	}
+}
+
+impl server::FuseSocket for FuseMount<'_> {}
+
+impl server::FuseUnmount for FuseMount<'_> {
+	fn unmount(&self) -> Result<(), Error> {
+		crate::unmount(self.target, 0)
+	}
+}
+
+impl server::Socket for FuseMount<'_> {
+	type Error = Error;
+
+	fn recv(&self, buf: &mut [u8]) -> Result<usize, RecvError<Error>> {
+		self.socket.recv(buf)
+	}
+
+	fn send(&self, buf: SendBuf) -> Result<(), SendError<Error>> {
+		self.socket.send(buf)
+	}
+}
@@ -28,6 +28,8 @@ pub(crate) unsafe fn close(fd: i32) -> Result<(), Error> {
 
 pub(crate) type OpenFlag = u32;
 
+pub(crate) const O_RDONLY: OpenFlag = 0;
+pub(crate) const O_WRONLY: OpenFlag = 1 << 0;
 pub(crate) const O_RDWR: OpenFlag = 1 << 1;
 
 #[cfg(not(any(
@@ -75,6 +77,15 @@ pub(crate) unsafe fn open(
 	).try_isize().map(|fd| fd as i32)
 }
 
+pub(crate) unsafe fn ioctl(
+	fd: i32,
+	cmd: u32,
+	arg: *const ffi::c_void,
+) -> Result<usize, Error> {
+	let rc = syscall!(syscall::SYS_ioctl, fd, cmd, arg);
+	rc.try_usize()
+}
+
 pub(crate) unsafe fn read(fd: i32, buf: &mut [u8]) -> Result<usize, Error> {
 	let rc = syscall!(syscall::SYS_read, fd, buf.as_mut_ptr(), buf.len());
 	rc.try_usize()
@@ -97,6 +108,11 @@ impl<'a> IoVec<'a> {
 	}
 }
 
+pub(crate) unsafe fn write(fd: i32, buf: &[u8]) -> Result<usize, Error> {
+	let rc = syscall!(syscall::SYS_write, fd, buf.as_ptr(), buf.len());
+	rc.try_usize()
+}
+
 pub(crate) unsafe fn writev(fd: i32, iov: &[IoVec]) -> Result<usize, Error> {
 	let rc = syscall!(syscall::SYS_writev, fd, iov.as_ptr(), iov.len());
 	rc.try_usize()
@@ -151,6 +167,10 @@ pub(crate) unsafe fn mount(
 	).check()
 }
 
+pub(crate) unsafe fn umount2(target: &ffi::CStr, flags: u32) -> Result<(), Error> {
+	syscall!(linux_syscall::SYS_umount2, target.as_ptr(), flags).check()
+}
+
 #[repr(C)]
 pub(crate) struct kernel_statx {
 	stx_mask: u32,
@@ -171,8 +191,8 @@ pub(crate) struct kernel_statx {
 	stx_mtime: kernel_statx_timestamp,
 	stx_rdev_major: u32,
 	stx_rdev_minor: u32,
-	stx_dev_major: u32,
-	stx_dev_minor: u32,
+	pub(crate) stx_dev_major: u32,
+	pub(crate) stx_dev_minor: u32,
 	_pad2: [u64; 14],
 }
 
@@ -0,0 +1,179 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+
+use core::mem::size_of;
+use core::num::NonZeroU64;
+use core::time::Duration;
+use std::time::Instant;
+
+use fuse::io::MinReadBuffer;
+use fuse::kernel;
+use fuse::kernel::fuse_opcode;
+use fuse::os::OsError;
+use fuse::server::{self, Socket};
+use fuse::testing::{KernelSocket, RequestBuilder, ServerSocket, SocketPair};
+
+use fuse_std::DeadlineSocket;
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+fn layout() -> server::FuseLayout {
+	let mut init_out = kernel::fuse_init_out::new();
+	init_out.major = kernel::FUSE_KERNEL_VERSION;
+	init_out.minor = kernel::FUSE_KERNEL_MINOR_VERSION;
+	server::FuseLayout::new(&init_out).unwrap()
+}
+
+fn push_request(
+	kernel_socket: KernelSocket,
+	request_id: u64,
+	opcode: fuse_opcode,
+) {
+	let mut builder = RequestBuilder::new(layout());
+	builder.set_request_id(request_id);
+	let mut buf = MinReadBuffer::new();
+	let buf = buf.as_aligned_slice_mut();
+	let request = match opcode {
+		fuse_opcode::FUSE_FORGET => {
+			let forget_in = kernel::fuse_forget_in::new();
+			builder.build(buf, opcode, 1, &[forget_in.as_bytes()])
+		},
+		_ => builder.getattr(buf, 1, &kernel::fuse_getattr_in::new()),
+	};
+	kernel_socket.push_request(request.unwrap().get()).unwrap();
+}
+
+fn recv_request(socket: &DeadlineSocket<ServerSocket>) {
+	let mut buf = [0u8; 256];
+	socket.recv(&mut buf).unwrap();
+}
+
+fn send_reply<S: server::FuseSocket>(
+	socket: &S,
+	request_id: u64,
+) -> Result<(), server::SendError<S::Error>> {
+	let request_id = NonZeroU64::new(request_id).unwrap();
+	server::FuseReplySender::new(socket, layout(), request_id)
+		.err(OsError::NOT_FOUND)
+}
+
+// Returns the request ID and error of each reply received by the kernel
+// socket.
+fn replies(kernel_socket: KernelSocket) -> Vec<(u64, fuse::Error)> {
+	let mut replies = Vec::new();
+	let mut buf = [0u8; 64];
+	while let Ok(len) = kernel_socket.pop_reply(&mut buf) {
+		assert_eq!(len, size_of::<kernel::fuse_out_header>());
+		let header = unsafe {
+			buf.as_ptr().cast::<kernel::fuse_out_header>().read_unaligned()
+		};
+		let error = core::num::NonZeroI32::new(header.error).unwrap();
+		replies.push((header.unique, fuse::Error(error)));
+	}
+	replies
+}
+
+#[test]
+fn expired_request() {
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	push_request(pair.kernel(), 2, fuse_opcode::FUSE_GETATTR);
+
+	let socket = DeadlineSocket::new(pair.server(), Some(TIMEOUT));
+	let start = Instant::now();
+	recv_request(&socket);
+	assert_eq!(socket.pending_len(), 1);
+	let deadline = socket.next_deadline().unwrap();
+	assert!(deadline >= start + TIMEOUT);
+
+	// The client receives an error reply once the deadline has passed,
+	// without waiting for the handler.
+	assert_eq!(socket.expire_fuse(deadline, layout()).unwrap(), 1);
+	assert_eq!(replies(pair.kernel()), [(2, OsError::INTERRUPTED)]);
+	assert_eq!(socket.pending_len(), 0);
+	assert_eq!(socket.next_deadline(), None);
+
+	// The handler's late reply is dropped.
+	send_reply(&socket, 2).unwrap();
+	assert!(replies(pair.kernel()).is_empty());
+}
+
+#[test]
+fn reply_within_deadline() {
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	push_request(pair.kernel(), 2, fuse_opcode::FUSE_GETATTR);
+	push_request(pair.kernel(), 3, fuse_opcode::FUSE_GETATTR);
+
+	let socket = DeadlineSocket::new(pair.server(), Some(TIMEOUT));
+	recv_request(&socket);
+	recv_request(&socket);
+	assert_eq!(socket.pending_len(), 2);
+
+	// Nothing is expired before the deadline.
+	assert_eq!(socket.expire_fuse(Instant::now(), layout()).unwrap(), 0);
+	assert!(replies(pair.kernel()).is_empty());
+
+	send_reply(&socket, 2).unwrap();
+	assert_eq!(replies(pair.kernel()), [(2, OsError::NOT_FOUND)]);
+	assert_eq!(socket.pending_len(), 1);
+
+	// Only the request without a reply is expired.
+	let later = Instant::now() + TIMEOUT * 2;
+	assert_eq!(socket.expire_fuse(later, layout()).unwrap(), 1);
+	assert_eq!(replies(pair.kernel()), [(3, OsError::INTERRUPTED)]);
+}
+
+#[test]
+fn requests_without_deadline() {
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	push_request(pair.kernel(), 2, fuse_opcode::FUSE_FORGET);
+	push_request(pair.kernel(), 3, fuse_opcode::FUSE_GETATTR);
+
+	let mut socket = DeadlineSocket::new(pair.server(), Some(TIMEOUT));
+	socket.set_timeout(fuse_opcode::FUSE_GETATTR, None);
+	recv_request(&socket);
+	recv_request(&socket);
+	assert_eq!(socket.pending_len(), 0);
+
+	let later = Instant::now() + TIMEOUT * 2;
+	assert_eq!(socket.expire_fuse(later, layout()).unwrap(), 0);
+	send_reply(&socket, 3).unwrap();
+	assert_eq!(replies(pair.kernel()), [(3, OsError::NOT_FOUND)]);
+}
+
+#[test]
+fn set_timeout_and_error() {
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	push_request(pair.kernel(), 2, fuse_opcode::FUSE_GETATTR);
+
+	let mut socket = DeadlineSocket::new(pair.server(), None);
+	socket.set_timeout(fuse_opcode::FUSE_GETATTR, Some(TIMEOUT));
+	socket.set_error(OsError::UNAVAILABLE);
+	recv_request(&socket);
+	assert_eq!(socket.pending_len(), 1);
+
+	let later = Instant::now() + TIMEOUT * 2;
+	assert_eq!(socket.expire_fuse(later, layout()).unwrap(), 1);
+	assert_eq!(replies(pair.kernel()), [(2, OsError::UNAVAILABLE)]);
+}
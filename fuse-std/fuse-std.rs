@@ -61,8 +61,15 @@
 	clippy::print_stdout,
 )]
 
+extern crate alloc;
+
+use alloc::collections::VecDeque;
+use core::hash::Hash;
+use core::num::NonZeroU64;
 use std::alloc::Layout;
-use std::sync::mpsc;
+use std::collections::{HashMap, HashSet};
+use std::sync::{mpsc, Mutex, PoisonError};
+use std::time::Instant;
 
 use fuse::kernel::FUSE_MIN_READ_BUFFER;
 use fuse::io::{
@@ -73,6 +80,11 @@ use fuse::io::{
 };
 use fuse::server;
 
+pub mod handles;
+pub mod locks;
+pub mod read_only;
+pub mod shutdown;
+
 fn server_threads() -> usize {
 	// Use `thread::available_parallelism()` to estimate how many hardware
 	// threads might be available. This number is clamped to 16 to avoid
@@ -147,6 +159,83 @@ where
 	err_receiver
 }
 
+/// Serve FUSE requests in a multi-threaded loop until shutdown.
+///
+/// This is equivalent to [`serve_fuse`], but the worker threads also stop
+/// accepting requests once shutdown is requested with `token`. Requests
+/// received after that point are rejected as described by [`ShutdownToken`],
+/// and the worker threads keep running until the connection is closed.
+///
+/// A typical shutdown sequence is to call [`ShutdownToken::drain`] from
+/// another thread, then unmount the filesystem. This function returns once
+/// the unmount has closed the connection.
+///
+/// # Panics
+///
+/// Panics on memory allocation failure. This function allocates
+/// [`conn.recv_buf_len()`] bytes per worker thread, and also calls standard
+/// library APIs such as [`Vec::with_capacity`] that panic on OOM.
+///
+/// [`ShutdownToken`]: shutdown::ShutdownToken
+/// [`ShutdownToken::drain`]: shutdown::ShutdownToken::drain
+/// [`conn.recv_buf_len()`]: server::FuseConnection::recv_buf_len
+pub fn serve_fuse_until<S, H>(
+	conn: &server::FuseConnection<S>,
+	handlers: &H,
+	token: &shutdown::ShutdownToken,
+) -> mpsc::Receiver<server::ServerError<S::Error>>
+where
+	S: server::FuseSocket + Send + Sync,
+	S::Error: Send,
+	H: server::FuseHandlers + Send + Sync,
+{
+	// Pre-allocate receive buffers so that an allocation failure will happen
+	// before any server threads get spawned.
+	let num_threads = server_threads();
+	let mut recv_bufs = Vec::with_capacity(num_threads);
+	let recv_buf_len = conn.recv_buf_len();
+	for _ii in 0..num_threads {
+		recv_bufs.push(AlignedBuf::with_capacity(recv_buf_len));
+	}
+
+	let (err_sender, err_receiver) = mpsc::sync_channel(num_threads);
+	std::thread::scope(|s| {
+		for _ii in 0..num_threads {
+			let err_sender = err_sender.clone();
+			let mut buf = recv_bufs.remove(recv_bufs.len() - 1);
+			s.spawn(move || {
+				while let Err(err) = fuse_serve_until(conn, handlers, token, &mut buf) {
+					let fatal = is_fatal_error(&err);
+					_ = err_sender.send(err);
+					if fatal {
+						return;
+					}
+				}
+			});
+		}
+	});
+
+	err_receiver
+}
+
+fn fuse_serve_until<S: server::FuseSocket>(
+	conn: &server::FuseConnection<S>,
+	handlers: &impl server::FuseHandlers,
+	token: &shutdown::ShutdownToken,
+	buf: &mut AlignedBuf,
+) -> Result<(), server::ServerError<S::Error>> {
+	while let Some(request) = conn.recv(buf.as_aligned_slice_mut())? {
+		let opcode = request.header().opcode();
+		let Some(_in_flight) = token.begin_request(opcode) else {
+			let reply = conn.reply(request.id());
+			reply.err(fuse::os::OsError::INTERRUPTED)?;
+			continue;
+		};
+		handlers.dispatch(request);
+	}
+	Ok(())
+}
+
 /// Serve CUSE requests in a multi-threaded loop.
 ///
 /// This function spawns worker threads to process CUSE requests from the
@@ -283,3 +372,960 @@ impl AsAlignedSliceMut for AlignedBuf {
 		}
 	}
 }
+
+/// Translates request IDs between several upstream connections and a single
+/// downstream connection.
+///
+/// A proxy that multiplexes requests from several upstream FUSE connections
+/// onto one downstream connection can't forward their request IDs unchanged,
+/// because IDs from different upstream connections may collide. The
+/// `RequestIdMap` assigns each forwarded request a fresh downstream ID and
+/// tracks it until the reply is received, so that the reply can be routed
+/// back to the upstream connection that sent the request.
+///
+/// Downstream IDs are allocated sequentially from a random per-map salt, so
+/// that a stale reply from a previous session is unlikely to match an
+/// outstanding request. The lowest bit of downstream IDs is always zero,
+/// which the kernel reserves for identifying `FUSE_INTERRUPT` requests.
+pub struct RequestIdMap<K> {
+	counter: u64,
+	downstream: HashMap<u64, (K, NonZeroU64)>,
+	upstream: HashMap<(K, u64), u64>,
+}
+
+impl<K: Copy + Eq + Hash> RequestIdMap<K> {
+	/// Creates a new `RequestIdMap` with a random salt.
+	#[must_use]
+	pub fn new() -> RequestIdMap<K> {
+		use std::collections::hash_map::RandomState;
+		use core::hash::{BuildHasher, Hasher};
+
+		let salt = RandomState::new().build_hasher().finish();
+		Self::with_salt(salt)
+	}
+
+	/// Creates a new `RequestIdMap` with the given salt.
+	///
+	/// The first downstream ID is the next even number after `salt`,
+	/// skipping zero. This is intended for tests that need deterministic
+	/// downstream IDs.
+	#[must_use]
+	pub fn with_salt(salt: u64) -> RequestIdMap<K> {
+		Self {
+			counter: salt >> 1,
+			downstream: HashMap::new(),
+			upstream: HashMap::new(),
+		}
+	}
+
+	/// Returns the number of outstanding requests.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.downstream.len()
+	}
+
+	/// Returns `true` if there are no outstanding requests.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.downstream.is_empty()
+	}
+
+	/// Assigns a downstream ID to a request received from an upstream
+	/// connection.
+	///
+	/// If the request is already outstanding, its existing downstream ID is
+	/// returned.
+	pub fn insert(&mut self, upstream: K, request_id: NonZeroU64) -> NonZeroU64 {
+		let upstream_key = (upstream, request_id.get());
+		if let Some(&downstream_id) = self.upstream.get(&upstream_key) {
+			return nonzero(downstream_id);
+		}
+		let downstream_id = loop {
+			self.counter = self.counter.wrapping_add(1);
+			let id = self.counter << 1;
+			if id != 0 && !self.downstream.contains_key(&id) {
+				break id;
+			}
+		};
+		self.downstream.insert(downstream_id, (upstream, request_id));
+		self.upstream.insert(upstream_key, downstream_id);
+		nonzero(downstream_id)
+	}
+
+	/// Returns the upstream connection and request ID of an outstanding
+	/// request.
+	#[must_use]
+	pub fn get(&self, downstream_id: NonZeroU64) -> Option<(K, NonZeroU64)> {
+		self.downstream.get(&downstream_id.get()).copied()
+	}
+
+	/// Returns the downstream ID of an outstanding request.
+	///
+	/// This can be used to translate the target of a `FUSE_INTERRUPT`
+	/// request, which identifies the interrupted request by its ID.
+	#[must_use]
+	pub fn downstream_id(
+		&self,
+		upstream: K,
+		request_id: NonZeroU64,
+	) -> Option<NonZeroU64> {
+		let upstream_key = (upstream, request_id.get());
+		self.upstream.get(&upstream_key).map(|&id| nonzero(id))
+	}
+
+	/// Stops tracking an outstanding request, returning its upstream
+	/// connection and request ID.
+	///
+	/// This should be called when the downstream reply is received. Returns
+	/// `None` if the downstream ID doesn't match any outstanding request, in
+	/// which case the reply should be discarded.
+	pub fn remove(
+		&mut self,
+		downstream_id: NonZeroU64,
+	) -> Option<(K, NonZeroU64)> {
+		let (upstream, request_id) =
+			self.downstream.remove(&downstream_id.get())?;
+		self.upstream.remove(&(upstream, request_id.get()));
+		Some((upstream, request_id))
+	}
+
+	/// Stops tracking all outstanding requests from an upstream connection.
+	///
+	/// This should be called when an upstream connection is closed, so that
+	/// late replies to its requests are discarded.
+	pub fn remove_upstream(&mut self, upstream: K) {
+		self.downstream.retain(|_, (k, _)| *k != upstream);
+		self.upstream.retain(|(k, _), _| *k != upstream);
+	}
+}
+
+impl<K: Copy + Eq + Hash> Default for RequestIdMap<K> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[inline]
+fn nonzero(id: u64) -> NonZeroU64 {
+	// Downstream IDs are checked to be non-zero when they're allocated.
+	unsafe { NonZeroU64::new_unchecked(id) }
+}
+
+/// A socket wrapper that drops duplicate replies.
+///
+/// The FUSE protocol requires that each request receive at most one reply.
+/// Sending a second reply with the same request ID can corrupt the client's
+/// state, because the kernel may have already reused the ID for a new
+/// request. Servers that clone request IDs into owned or asynchronous
+/// handlers may violate this rule by mistake.
+///
+/// `ReplyDedupSocket` remembers the IDs of the most recent replies in a
+/// fixed-size ring. A reply whose ID is still in the ring is not sent;
+/// instead, the `on_duplicate` callback is invoked with the duplicate ID and
+/// the send reports success. Notifications, which have a request ID of zero,
+/// are always sent.
+pub struct ReplyDedupSocket<S, F> {
+	socket: S,
+	on_duplicate: F,
+	replied: Mutex<ReplyRing>,
+}
+
+struct ReplyRing {
+	ids: Box<[u64]>,
+	next: usize,
+	set: HashSet<u64>,
+}
+
+impl<S, F> ReplyDedupSocket<S, F>
+where
+	F: Fn(NonZeroU64),
+{
+	/// Creates a new `ReplyDedupSocket` that remembers up to `capacity`
+	/// reply IDs.
+	///
+	/// # Panics
+	///
+	/// Panics if `capacity` is zero.
+	#[must_use]
+	pub fn new(
+		socket: S,
+		capacity: usize,
+		on_duplicate: F,
+	) -> ReplyDedupSocket<S, F> {
+		assert!(capacity > 0, "ReplyDedupSocket capacity must be non-zero");
+		Self {
+			socket,
+			on_duplicate,
+			replied: Mutex::new(ReplyRing {
+				ids: vec![0; capacity].into_boxed_slice(),
+				next: 0,
+				set: HashSet::with_capacity(capacity),
+			}),
+		}
+	}
+
+	/// Returns a reference to the wrapped socket.
+	#[must_use]
+	pub fn socket(&self) -> &S {
+		&self.socket
+	}
+
+	/// Consumes the `ReplyDedupSocket`, returning the wrapped socket.
+	#[must_use]
+	pub fn into_inner(self) -> S {
+		self.socket
+	}
+
+	fn dedup_send<E>(
+		&self,
+		request_id: Option<NonZeroU64>,
+		send: impl FnOnce() -> Result<(), E>,
+	) -> Result<(), E> {
+		let request_id = match request_id {
+			Some(id) => id,
+			None => return send(),
+		};
+
+		let is_new = self.lock().insert(request_id.get());
+		if !is_new {
+			(self.on_duplicate)(request_id);
+			return Ok(());
+		}
+
+		let result = send();
+		if result.is_err() {
+			// The reply wasn't sent, so a retry shouldn't be treated as
+			// a duplicate.
+			self.lock().remove(request_id.get());
+		}
+		result
+	}
+
+	fn lock(&self) -> std::sync::MutexGuard<'_, ReplyRing> {
+		self.replied.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+impl ReplyRing {
+	fn insert(&mut self, id: u64) -> bool {
+		if !self.set.insert(id) {
+			return false;
+		}
+		let evicted = core::mem::replace(&mut self.ids[self.next], id);
+		if evicted != 0 {
+			self.set.remove(&evicted);
+		}
+		self.next = (self.next + 1) % self.ids.len();
+		true
+	}
+
+	fn remove(&mut self, id: u64) -> bool {
+		if !self.set.remove(&id) {
+			return false;
+		}
+		if let Some(slot) = self.ids.iter_mut().find(|slot| **slot == id) {
+			*slot = 0;
+		}
+		true
+	}
+}
+
+fn reply_request_id(buf: &fuse::io::SendBuf) -> Option<NonZeroU64> {
+	use fuse::kernel::fuse_out_header;
+	const UNIQUE_OFFSET: usize = core::mem::offset_of!(fuse_out_header, unique);
+
+	let header = buf.chunks().first()?;
+	let unique = header.get(UNIQUE_OFFSET..UNIQUE_OFFSET + 8)?;
+	let mut bytes = [0u8; 8];
+	bytes.copy_from_slice(unique);
+	NonZeroU64::new(u64::from_ne_bytes(bytes))
+}
+
+impl<S, F> server::Socket for ReplyDedupSocket<S, F>
+where
+	S: server::Socket,
+	F: Fn(NonZeroU64),
+{
+	type Error = S::Error;
+
+	fn recv(
+		&self,
+		buf: &mut [u8],
+	) -> Result<usize, server::RecvError<S::Error>> {
+		self.socket.recv(buf)
+	}
+
+	fn send(
+		&self,
+		buf: fuse::io::SendBuf,
+	) -> Result<(), server::SendError<S::Error>> {
+		let request_id = reply_request_id(&buf);
+		self.dedup_send(request_id, || self.socket.send(buf))
+	}
+
+	fn can_send_fd(&self) -> bool {
+		self.socket.can_send_fd()
+	}
+
+	fn send_with_fd(
+		&self,
+		buf: fuse::io::SendBuf,
+		fd: i32,
+	) -> Result<(), server::SendError<S::Error>> {
+		let request_id = reply_request_id(&buf);
+		self.dedup_send(request_id, || self.socket.send_with_fd(buf, fd))
+	}
+}
+
+impl<S, F> server::FuseSocket for ReplyDedupSocket<S, F>
+where
+	S: server::FuseSocket,
+	F: Fn(NonZeroU64),
+{
+}
+
+impl<S, F> server::FuseUnmount for ReplyDedupSocket<S, F>
+where
+	S: server::FuseUnmount,
+	F: Fn(NonZeroU64),
+{
+	fn unmount(&self) -> Result<(), S::Error> {
+		self.socket.unmount()
+	}
+}
+
+impl<S, F> server::CuseSocket for ReplyDedupSocket<S, F>
+where
+	S: server::CuseSocket,
+	F: Fn(NonZeroU64),
+{
+}
+
+/// A socket wrapper that replays cached replies to duplicate requests.
+///
+/// Some FUSE clients, notably FreeBSD, may send a request again with the
+/// same request ID after it has been interrupted. If the server already
+/// handled the first copy, handling it again may not be safe; a repeated
+/// `FUSE_CREATE` or `FUSE_UNLINK` would fail with a different error.
+///
+/// `ReplyCacheSocket` remembers the most recent replies sent through it,
+/// up to a configured number of entries. When a request is received with
+/// the same request ID, opcode, and node ID as a cached reply, the cached
+/// reply is sent again and the request isn't passed on to the server.
+///
+/// Replies sent with a file descriptor are not cached.
+pub struct ReplyCacheSocket<S> {
+	socket: S,
+	layout: server::FuseLayout,
+	cache: Mutex<ReplyCache>,
+}
+
+struct ReplyCache {
+	capacity: usize,
+	pending: HashMap<u64, (u32, u64)>,
+	order: VecDeque<u64>,
+	replies: HashMap<u64, CachedReply>,
+}
+
+struct CachedReply {
+	opcode: u32,
+	node_id: u64,
+	reply: Vec<u8>,
+}
+
+impl<S> ReplyCacheSocket<S> {
+	/// Creates a new `ReplyCacheSocket` that remembers up to `capacity`
+	/// replies.
+	///
+	/// # Panics
+	///
+	/// Panics if `capacity` is zero.
+	#[must_use]
+	pub fn new(socket: S, capacity: usize) -> ReplyCacheSocket<S> {
+		assert!(capacity > 0, "ReplyCacheSocket capacity must be non-zero");
+
+		// Cached replies are sent as raw bytes, which are not affected by
+		// the negotiated protocol version.
+		let layout = server::FuseLayout::new(&{
+			let mut init_out = fuse::kernel::fuse_init_out::new();
+			init_out.major = fuse::kernel::FUSE_KERNEL_VERSION;
+			init_out.minor = fuse::kernel::FUSE_KERNEL_MINOR_VERSION;
+			init_out
+		});
+		#[allow(clippy::expect_used)]
+		let layout = layout.expect("FuseLayout for the latest version");
+
+		Self {
+			socket,
+			layout,
+			cache: Mutex::new(ReplyCache {
+				capacity,
+				pending: HashMap::new(),
+				order: VecDeque::with_capacity(capacity),
+				replies: HashMap::with_capacity(capacity),
+			}),
+		}
+	}
+
+	/// Returns a reference to the wrapped socket.
+	#[must_use]
+	pub fn socket(&self) -> &S {
+		&self.socket
+	}
+
+	/// Consumes the `ReplyCacheSocket`, returning the wrapped socket.
+	#[must_use]
+	pub fn into_inner(self) -> S {
+		self.socket
+	}
+
+	/// Returns the number of cached replies.
+	#[must_use]
+	pub fn cached_len(&self) -> usize {
+		self.lock().replies.len()
+	}
+
+	fn lock(&self) -> std::sync::MutexGuard<'_, ReplyCache> {
+		self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+impl ReplyCache {
+	// Returns the cached reply to a request, or records the request as
+	// awaiting a reply.
+	fn lookup(&mut self, request: &[u8]) -> Option<Vec<u8>> {
+		use fuse::kernel::fuse_in_header;
+		const UNIQUE_OFFSET: usize =
+			core::mem::offset_of!(fuse_in_header, unique);
+		const OPCODE_OFFSET: usize =
+			core::mem::offset_of!(fuse_in_header, opcode);
+		const NODEID_OFFSET: usize =
+			core::mem::offset_of!(fuse_in_header, nodeid);
+
+		if request.len() < core::mem::size_of::<fuse_in_header>() {
+			return None;
+		}
+		let unique = read_u64(request, UNIQUE_OFFSET);
+		let node_id = read_u64(request, NODEID_OFFSET);
+		let mut opcode = [0u8; 4];
+		opcode.copy_from_slice(&request[OPCODE_OFFSET..OPCODE_OFFSET + 4]);
+		let opcode = u32::from_ne_bytes(opcode);
+		if unique == 0 {
+			return None;
+		}
+
+		if let Some(cached) = self.replies.get(&unique) {
+			if cached.opcode == opcode && cached.node_id == node_id {
+				let reply = cached.reply.clone();
+				self.touch(unique);
+				return Some(reply);
+			}
+			// The request ID has been reused for a different request.
+			self.remove(unique);
+		}
+		self.pending.insert(unique, (opcode, node_id));
+		None
+	}
+
+	fn insert(&mut self, request_id: u64, chunks: &[&[u8]]) {
+		let (opcode, node_id) = match self.pending.remove(&request_id) {
+			Some(request) => request,
+			None => return,
+		};
+		if self.replies.len() >= self.capacity {
+			if let Some(evicted) = self.order.pop_front() {
+				self.replies.remove(&evicted);
+			}
+		}
+		self.replies.insert(request_id, CachedReply {
+			opcode,
+			node_id,
+			reply: chunks.concat(),
+		});
+		self.order.push_back(request_id);
+	}
+
+	fn touch(&mut self, request_id: u64) {
+		if let Some(idx) = self.order.iter().position(|id| *id == request_id) {
+			self.order.remove(idx);
+			self.order.push_back(request_id);
+		}
+	}
+
+	fn remove(&mut self, request_id: u64) {
+		if self.replies.remove(&request_id).is_some() {
+			self.order.retain(|id| *id != request_id);
+		}
+	}
+}
+
+fn read_u64(buf: &[u8], offset: usize) -> u64 {
+	let mut bytes = [0u8; 8];
+	bytes.copy_from_slice(&buf[offset..offset + 8]);
+	u64::from_ne_bytes(bytes)
+}
+
+impl<S: server::FuseSocket> ReplyCacheSocket<S> {
+	fn replay(
+		&self,
+		reply: &[u8],
+	) -> Result<(), server::SendError<S::Error>> {
+		use fuse::kernel::fuse_out_header;
+		const HEADER_LEN: usize = core::mem::size_of::<fuse_out_header>();
+		const UNIQUE_OFFSET: usize =
+			core::mem::offset_of!(fuse_out_header, unique);
+		const ERROR_OFFSET: usize =
+			core::mem::offset_of!(fuse_out_header, error);
+
+		let request_id = nonzero(read_u64(reply, UNIQUE_OFFSET));
+		let mut error = [0u8; 4];
+		error.copy_from_slice(&reply[ERROR_OFFSET..ERROR_OFFSET + 4]);
+		let error = i32::from_ne_bytes(error);
+
+		let sender = server::FuseReplySender::new(
+			&self.socket,
+			self.layout,
+			request_id,
+		);
+		match core::num::NonZeroI32::new(error) {
+			Some(error) => sender.err(fuse::Error(error)),
+			None => sender.ok_buf(&reply[HEADER_LEN..]),
+		}
+	}
+}
+
+impl<S: server::FuseSocket> server::Socket for ReplyCacheSocket<S> {
+	type Error = S::Error;
+
+	fn recv(
+		&self,
+		buf: &mut [u8],
+	) -> Result<usize, server::RecvError<S::Error>> {
+		loop {
+			let len = self.socket.recv(buf)?;
+			let cached = self.lock().lookup(&buf[..len]);
+			let reply = match cached {
+				Some(reply) => reply,
+				None => return Ok(len),
+			};
+			match self.replay(&reply) {
+				Ok(()) | Err(server::SendError::NotFound(_)) => {},
+				Err(server::SendError::Other(err)) => {
+					return Err(server::RecvError::Other(err));
+				},
+				Err(_) => {},
+			}
+		}
+	}
+
+	fn send(
+		&self,
+		buf: fuse::io::SendBuf,
+	) -> Result<(), server::SendError<S::Error>> {
+		let request_id = reply_request_id(&buf);
+		let mut chunks: [&[u8]; fuse::io::SendBuf::MAX_CHUNKS_LEN] =
+			[&[]; fuse::io::SendBuf::MAX_CHUNKS_LEN];
+		let chunks_len = buf.chunks_len();
+		chunks[..chunks_len].copy_from_slice(buf.chunks());
+
+		self.socket.send(buf)?;
+		if let Some(request_id) = request_id {
+			self.lock().insert(request_id.get(), &chunks[..chunks_len]);
+		}
+		Ok(())
+	}
+
+	fn can_send_fd(&self) -> bool {
+		self.socket.can_send_fd()
+	}
+
+	fn send_with_fd(
+		&self,
+		buf: fuse::io::SendBuf,
+		fd: i32,
+	) -> Result<(), server::SendError<S::Error>> {
+		if let Some(request_id) = reply_request_id(&buf) {
+			self.lock().pending.remove(&request_id.get());
+		}
+		self.socket.send_with_fd(buf, fd)
+	}
+}
+
+impl<S: server::FuseSocket> server::FuseSocket for ReplyCacheSocket<S> {}
+
+impl<S: server::FuseUnmount> server::FuseUnmount for ReplyCacheSocket<S> {
+	fn unmount(&self) -> Result<(), S::Error> {
+		self.socket.unmount()
+	}
+}
+
+/// A socket wrapper that enforces per-request deadlines.
+///
+/// Each request received through a `DeadlineSocket` is given a deadline,
+/// based on its opcode. Calling [`expire_fuse`] or [`expire_cuse`] sends an
+/// error reply to every request whose deadline has passed without a reply,
+/// so that slow or hung handlers don't leave the client waiting forever.
+/// The handler isn't interrupted; when it eventually replies, the late reply
+/// is dropped and the send reports success.
+///
+/// The expire methods are typically called from a watchdog thread that
+/// sleeps until [`next_deadline`].
+///
+/// Requests that don't receive replies, such as `FUSE_FORGET`, never have a
+/// deadline.
+///
+/// Only the most recent 1024 expired request IDs are remembered. If a handler
+/// replies after its request was expired and then evicted, the late reply is
+/// sent to the wrapped socket.
+///
+/// [`expire_fuse`]: DeadlineSocket::expire_fuse
+/// [`expire_cuse`]: DeadlineSocket::expire_cuse
+/// [`next_deadline`]: DeadlineSocket::next_deadline
+pub struct DeadlineSocket<S> {
+	socket: S,
+	default_timeout: Option<core::time::Duration>,
+	timeouts: HashMap<u32, Option<core::time::Duration>>,
+	error: fuse::Error,
+	state: Mutex<DeadlineState>,
+}
+
+struct DeadlineState {
+	pending: HashMap<u64, Instant>,
+	expired: ReplyRing,
+}
+
+const DEADLINE_EXPIRED_CAPACITY: usize = 1024;
+
+impl<S> DeadlineSocket<S> {
+	/// Creates a new `DeadlineSocket` with the given default timeout.
+	///
+	/// If `default_timeout` is `None`, only requests with opcodes given a
+	/// timeout by [`set_timeout`](DeadlineSocket::set_timeout) will have a
+	/// deadline.
+	#[must_use]
+	pub fn new(
+		socket: S,
+		default_timeout: Option<core::time::Duration>,
+	) -> DeadlineSocket<S> {
+		Self {
+			socket,
+			default_timeout,
+			timeouts: HashMap::new(),
+			error: fuse::os::OsError::INTERRUPTED,
+			state: Mutex::new(DeadlineState {
+				pending: HashMap::new(),
+				expired: ReplyRing {
+					ids: vec![0; DEADLINE_EXPIRED_CAPACITY].into_boxed_slice(),
+					next: 0,
+					set: HashSet::with_capacity(DEADLINE_EXPIRED_CAPACITY),
+				},
+			}),
+		}
+	}
+
+	/// Sets the timeout for requests with the given opcode, overriding the
+	/// default timeout.
+	///
+	/// A timeout of `None` disables deadlines for the opcode.
+	pub fn set_timeout(
+		&mut self,
+		opcode: fuse::kernel::fuse_opcode,
+		timeout: Option<core::time::Duration>,
+	) {
+		self.timeouts.insert(opcode.0, timeout);
+	}
+
+	/// Sets the error sent to requests that exceed their deadline.
+	///
+	/// The default is [`OsError::INTERRUPTED`](fuse::os::OsError::INTERRUPTED).
+	pub fn set_error(&mut self, error: fuse::Error) {
+		self.error = error;
+	}
+
+	/// Returns a reference to the wrapped socket.
+	#[must_use]
+	pub fn socket(&self) -> &S {
+		&self.socket
+	}
+
+	/// Consumes the `DeadlineSocket`, returning the wrapped socket.
+	#[must_use]
+	pub fn into_inner(self) -> S {
+		self.socket
+	}
+
+	/// Returns the earliest deadline of any request awaiting a reply.
+	#[must_use]
+	pub fn next_deadline(&self) -> Option<Instant> {
+		self.lock().pending.values().min().copied()
+	}
+
+	/// Returns the number of requests with a deadline that are awaiting
+	/// a reply.
+	#[must_use]
+	pub fn pending_len(&self) -> usize {
+		self.lock().pending.len()
+	}
+
+	fn timeout(
+		&self,
+		opcode: fuse::kernel::fuse_opcode,
+	) -> Option<core::time::Duration> {
+		use fuse::kernel::fuse_opcode;
+		match opcode {
+			fuse_opcode::FUSE_FORGET
+			| fuse_opcode::FUSE_BATCH_FORGET
+			| fuse_opcode::FUSE_INTERRUPT
+			| fuse_opcode::FUSE_NOTIFY_REPLY
+			| fuse_opcode::FUSE_INIT
+			| fuse_opcode::CUSE_INIT => return None,
+			_ => {},
+		}
+		match self.timeouts.get(&opcode.0) {
+			Some(timeout) => *timeout,
+			None => self.default_timeout,
+		}
+	}
+
+	fn start_request(&self, buf: &[u8]) {
+		use fuse::kernel::{fuse_in_header, fuse_opcode};
+		const UNIQUE_OFFSET: usize =
+			core::mem::offset_of!(fuse_in_header, unique);
+		const OPCODE_OFFSET: usize =
+			core::mem::offset_of!(fuse_in_header, opcode);
+
+		if buf.len() < core::mem::size_of::<fuse_in_header>() {
+			return;
+		}
+		let mut unique = [0u8; 8];
+		unique.copy_from_slice(&buf[UNIQUE_OFFSET..UNIQUE_OFFSET + 8]);
+		let mut opcode = [0u8; 4];
+		opcode.copy_from_slice(&buf[OPCODE_OFFSET..OPCODE_OFFSET + 4]);
+
+		let unique = u64::from_ne_bytes(unique);
+		let opcode = fuse_opcode(u32::from_ne_bytes(opcode));
+		if unique == 0 {
+			return;
+		}
+		if let Some(timeout) = self.timeout(opcode) {
+			let deadline = Instant::now() + timeout;
+			self.lock().pending.insert(unique, deadline);
+		}
+	}
+
+	// Returns `false` if the request has already been sent a reply by
+	// an expire method.
+	fn finish_request(&self, request_id: Option<NonZeroU64>) -> bool {
+		let request_id = match request_id {
+			Some(id) => id.get(),
+			None => return true,
+		};
+		let mut state = self.lock();
+		if state.pending.remove(&request_id).is_some() {
+			return true;
+		}
+		!state.expired.remove(request_id)
+	}
+
+	fn take_expired(&self, now: Instant) -> Vec<u64> {
+		let mut state = self.lock();
+		let mut expired = Vec::new();
+		state.pending.retain(|&request_id, deadline| {
+			if *deadline > now {
+				return true;
+			}
+			expired.push(request_id);
+			false
+		});
+		for &request_id in &expired {
+			state.expired.insert(request_id);
+		}
+		expired
+	}
+
+	// Sends an expiry reply for each request, continuing after errors. The
+	// first error is returned once every request has been tried.
+	//
+	// A request whose reply couldn't be sent is no longer treated as
+	// expired, so that its handler's reply will be sent.
+	fn send_expired<E>(
+		&self,
+		expired: &[u64],
+		mut send: impl FnMut(NonZeroU64) -> Result<(), E>,
+	) -> Result<usize, E> {
+		let mut first_err = None;
+		for &request_id in expired {
+			if let Err(err) = send(nonzero(request_id)) {
+				self.lock().expired.remove(request_id);
+				first_err.get_or_insert(err);
+			}
+		}
+		match first_err {
+			Some(err) => Err(err),
+			None => Ok(expired.len()),
+		}
+	}
+
+	fn lock(&self) -> std::sync::MutexGuard<'_, DeadlineState> {
+		self.state.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+impl<S: server::FuseSocket> DeadlineSocket<S> {
+	/// Sends an error reply to every FUSE request whose deadline is no later
+	/// than `now`, returning the number of replies sent.
+	///
+	/// If sending a reply fails, the remaining requests are still sent their
+	/// replies and the first error is returned.
+	///
+	/// The `layout` should be the layout of the connection, as returned by
+	/// [`FuseConnection::layout`](server::FuseConnection::layout).
+	pub fn expire_fuse(
+		&self,
+		now: Instant,
+		layout: server::FuseLayout,
+	) -> Result<usize, server::SendError<S::Error>> {
+		let expired = self.take_expired(now);
+		self.send_expired(&expired, |request_id| {
+			server::FuseReplySender::new(&self.socket, layout, request_id)
+				.err(self.error)
+		})
+	}
+}
+
+impl<S: server::CuseSocket> DeadlineSocket<S> {
+	/// Sends an error reply to every CUSE request whose deadline is no later
+	/// than `now`, returning the number of replies sent.
+	///
+	/// If sending a reply fails, the remaining requests are still sent their
+	/// replies and the first error is returned.
+	///
+	/// The `layout` should be the layout of the connection, as returned by
+	/// [`CuseConnection::layout`](server::CuseConnection::layout).
+	pub fn expire_cuse(
+		&self,
+		now: Instant,
+		layout: server::CuseLayout,
+	) -> Result<usize, server::SendError<S::Error>> {
+		let expired = self.take_expired(now);
+		self.send_expired(&expired, |request_id| {
+			server::CuseReplySender::new(&self.socket, layout, request_id)
+				.err(self.error)
+		})
+	}
+}
+
+impl<S: server::Socket> server::Socket for DeadlineSocket<S> {
+	type Error = S::Error;
+
+	fn recv(
+		&self,
+		buf: &mut [u8],
+	) -> Result<usize, server::RecvError<S::Error>> {
+		let len = self.socket.recv(buf)?;
+		self.start_request(&buf[..len]);
+		Ok(len)
+	}
+
+	fn send(
+		&self,
+		buf: fuse::io::SendBuf,
+	) -> Result<(), server::SendError<S::Error>> {
+		if !self.finish_request(reply_request_id(&buf)) {
+			return Ok(());
+		}
+		self.socket.send(buf)
+	}
+
+	fn can_send_fd(&self) -> bool {
+		self.socket.can_send_fd()
+	}
+
+	fn send_with_fd(
+		&self,
+		buf: fuse::io::SendBuf,
+		fd: i32,
+	) -> Result<(), server::SendError<S::Error>> {
+		if !self.finish_request(reply_request_id(&buf)) {
+			return Ok(());
+		}
+		self.socket.send_with_fd(buf, fd)
+	}
+}
+
+impl<S: server::FuseSocket> server::FuseSocket for DeadlineSocket<S> {}
+
+impl<S: server::FuseUnmount> server::FuseUnmount for DeadlineSocket<S> {
+	fn unmount(&self) -> Result<(), S::Error> {
+		self.socket.unmount()
+	}
+}
+
+impl<S: server::CuseSocket> server::CuseSocket for DeadlineSocket<S> {}
+
+/// A handlers wrapper that reports the time taken to handle each request.
+///
+/// Each request is dispatched to the wrapped handlers, and the time spent
+/// in the handler is passed to [`server::Hooks::request_completed`].
+///
+/// Only [`dispatch`](server::FuseHandlers::dispatch) is timed. Calling the
+/// per-operation methods of a `TimedHandlers` directly will reach the
+/// wrapped handlers' `unimplemented` method.
+pub struct TimedHandlers<H, K> {
+	handlers: H,
+	hooks: K,
+}
+
+impl<H, K> TimedHandlers<H, K> {
+	/// Creates a new `TimedHandlers` wrapping the given handlers.
+	#[must_use]
+	pub fn new(handlers: H, hooks: K) -> TimedHandlers<H, K> {
+		Self { handlers, hooks }
+	}
+
+	/// Returns a reference to the wrapped handlers.
+	#[must_use]
+	pub fn handlers(&self) -> &H {
+		&self.handlers
+	}
+
+	/// Returns a reference to the hooks.
+	#[must_use]
+	pub fn hooks(&self) -> &K {
+		&self.hooks
+	}
+}
+
+impl<H, K> server::FuseHandlers for TimedHandlers<H, K>
+where
+	H: server::FuseHandlers,
+	K: server::Hooks,
+{
+	fn unimplemented(&self, request: server::FuseRequest<'_>) {
+		self.handlers.unimplemented(request)
+	}
+
+	fn dispatch(&self, request: server::FuseRequest<'_>) {
+		let start = std::time::Instant::now();
+		self.handlers.dispatch(request);
+		let elapsed = start.elapsed();
+		self.hooks.request_completed(request.header(), elapsed);
+	}
+}
+
+impl<H, K> server::CuseHandlers for TimedHandlers<H, K>
+where
+	H: server::CuseHandlers,
+	K: server::Hooks,
+{
+	fn unimplemented(&self, request: server::CuseRequest<'_>) {
+		self.handlers.unimplemented(request)
+	}
+
+	fn dispatch(&self, request: server::CuseRequest<'_>) {
+		let start = std::time::Instant::now();
+		self.handlers.dispatch(request);
+		let elapsed = start.elapsed();
+		self.hooks.request_completed(request.header(), elapsed);
+	}
+}
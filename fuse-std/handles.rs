@@ -0,0 +1,201 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Allocation of open file handles.
+//!
+//! A [`HandleTable`] assigns the `fh` values returned in replies to
+//! `FUSE_OPEN`, `FUSE_OPENDIR`, and `FUSE_CREATE`, and maps them back to
+//! per-handle state in later requests.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use fuse::os::OsError;
+
+const SHARD_BITS: u32 = 4;
+const SHARD_COUNT: usize = 1 << SHARD_BITS;
+const MAX_SHARD_SLOTS: usize = (u32::MAX >> SHARD_BITS) as usize;
+
+// HandleTable {{{
+
+/// A concurrent table of open handles.
+///
+/// Handles are 64-bit values composed of a slot index in the lower 32 bits
+/// and a generation counter in the upper 32 bits. Slots are reused after
+/// their handle is removed, but the slot's generation is incremented so
+/// that a stale handle doesn't refer to the slot's new value. A handle is
+/// never zero.
+///
+/// Slots are divided among several independently locked shards, so that
+/// concurrent requests for different handles don't contend on a single
+/// lock.
+pub struct HandleTable<T> {
+	shards: Box<[RwLock<Shard<T>>]>,
+	next_shard: AtomicUsize,
+	max_shard_slots: usize,
+}
+
+struct Shard<T> {
+	slots: Vec<Slot<T>>,
+	free: Vec<u32>,
+}
+
+struct Slot<T> {
+	generation: u32,
+	value: Option<T>,
+}
+
+impl<T> HandleTable<T> {
+	/// Creates a new, empty `HandleTable`.
+	#[must_use]
+	pub fn new() -> HandleTable<T> {
+		Self::with_max_len(usize::MAX)
+	}
+
+	/// Creates a new, empty `HandleTable` that holds at most about
+	/// `max_len` handles at once.
+	///
+	/// Slots are divided evenly among the table's 16 shards, so the limit
+	/// is rounded up to a multiple of 16. It is also capped at the
+	/// 2<sup>32</sup> - 16 slots that can be encoded in a handle.
+	#[must_use]
+	pub fn with_max_len(max_len: usize) -> HandleTable<T> {
+		let shards = (0..SHARD_COUNT)
+			.map(|_| RwLock::new(Shard {
+				slots: Vec::new(),
+				free: Vec::new(),
+			}))
+			.collect();
+		Self {
+			shards,
+			next_shard: AtomicUsize::new(0),
+			max_shard_slots: max_len
+				.div_ceil(SHARD_COUNT)
+				.min(MAX_SHARD_SLOTS),
+		}
+	}
+
+	/// Inserts a value into the table, returning its new handle.
+	///
+	/// # Errors
+	///
+	/// Returns [`OsError::TOO_MANY_OPEN_FILES`] if every slot of the table
+	/// is in use.
+	pub fn insert(&self, value: T) -> Result<u64, fuse::Error> {
+		let first_idx = self.next_shard.fetch_add(1, Ordering::Relaxed);
+		for ii in 0..SHARD_COUNT {
+			let shard_idx = first_idx.wrapping_add(ii) % SHARD_COUNT;
+			let mut shard = self.write_shard(shard_idx);
+			let Some(slot_idx) = self.alloc_slot(&mut shard) else {
+				continue;
+			};
+			let slot = &mut shard.slots[slot_idx as usize];
+			slot.value = Some(value);
+			return Ok(encode_handle(shard_idx, slot_idx, slot.generation));
+		}
+		Err(OsError::TOO_MANY_OPEN_FILES)
+	}
+
+	fn alloc_slot(&self, shard: &mut Shard<T>) -> Option<u32> {
+		if let Some(slot_idx) = shard.free.pop() {
+			return Some(slot_idx);
+		}
+		let slot_idx = shard.slots.len();
+		if slot_idx >= self.max_shard_slots {
+			return None;
+		}
+		shard.slots.push(Slot {
+			generation: 0,
+			value: None,
+		});
+		Some(slot_idx as u32)
+	}
+
+	/// Returns a copy of the value for `handle`.
+	///
+	/// Returns `None` if the handle isn't in the table, including if it has
+	/// been removed.
+	#[must_use]
+	pub fn get(&self, handle: u64) -> Option<T>
+	where
+		T: Clone,
+	{
+		let (shard_idx, slot_idx, generation) = decode_handle(handle)?;
+		let shard = self.read_shard(shard_idx);
+		let slot = shard.slots.get(slot_idx as usize)?;
+		if slot.generation != generation {
+			return None;
+		}
+		slot.value.clone()
+	}
+
+	/// Removes `handle` from the table, returning its value.
+	///
+	/// Returns `None` if the handle isn't in the table, including if it has
+	/// already been removed.
+	pub fn remove(&self, handle: u64) -> Option<T> {
+		let (shard_idx, slot_idx, generation) = decode_handle(handle)?;
+		let mut shard = self.write_shard(shard_idx);
+		let slot = shard.slots.get_mut(slot_idx as usize)?;
+		if slot.generation != generation {
+			return None;
+		}
+		let value = slot.value.take()?;
+		slot.generation = slot.generation.wrapping_add(1);
+		shard.free.push(slot_idx);
+		Some(value)
+	}
+
+	/// Returns `true` if `handle` was allocated by this table but has since
+	/// been removed.
+	///
+	/// Because generation counters are 32 bits, a handle may stop being
+	/// detected as stale after its slot has been reused 2<sup>32</sup> times.
+	#[must_use]
+	pub fn is_stale(&self, handle: u64) -> bool {
+		let Some((shard_idx, slot_idx, generation)) = decode_handle(handle)
+		else {
+			return false;
+		};
+		let shard = self.read_shard(shard_idx);
+		match shard.slots.get(slot_idx as usize) {
+			Some(slot) => slot.generation != generation,
+			None => false,
+		}
+	}
+
+	fn read_shard(&self, idx: usize) -> RwLockReadGuard<'_, Shard<T>> {
+		self.shards[idx].read().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	fn write_shard(&self, idx: usize) -> RwLockWriteGuard<'_, Shard<T>> {
+		self.shards[idx].write().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+fn encode_handle(shard_idx: usize, slot_idx: u32, generation: u32) -> u64 {
+	let index = (slot_idx << SHARD_BITS) | (shard_idx as u32);
+	(u64::from(generation) << 32) | u64::from(index + 1)
+}
+
+fn decode_handle(handle: u64) -> Option<(usize, u32, u32)> {
+	let index = (handle as u32).checked_sub(1)?;
+	let generation = (handle >> 32) as u32;
+	let shard_idx = (index as usize) & (SHARD_COUNT - 1);
+	Some((shard_idx, index >> SHARD_BITS, generation))
+}
+
+// }}}
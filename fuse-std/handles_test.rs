@@ -0,0 +1,93 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::HashSet;
+
+use fuse::os::OsError;
+
+use fuse_std::handles::HandleTable;
+
+#[test]
+fn insert_get_remove() {
+	let table = HandleTable::new();
+	let handle_a = table.insert("a").unwrap();
+	let handle_b = table.insert("b").unwrap();
+	assert_ne!(handle_a, 0);
+	assert_ne!(handle_a, handle_b);
+
+	assert_eq!(table.get(handle_a), Some("a"));
+	assert_eq!(table.get(handle_b), Some("b"));
+	assert_eq!(table.remove(handle_a), Some("a"));
+	assert_eq!(table.get(handle_a), None);
+	assert_eq!(table.remove(handle_a), None);
+	assert_eq!(table.get(handle_b), Some("b"));
+}
+
+#[test]
+fn stale_handles() {
+	let table = HandleTable::with_max_len(16);
+	let mut handles: Vec<u64> = (0..16)
+		.map(|ii| table.insert(ii).unwrap())
+		.collect();
+	assert!(!handles.iter().any(|&handle| table.is_stale(handle)));
+
+	// Removing a handle makes it stale.
+	let old_handle = handles.remove(3);
+	assert_eq!(table.remove(old_handle), Some(3));
+	assert!(table.is_stale(old_handle));
+
+	// The slot is reused with a new generation, so the old handle doesn't
+	// refer to the new value.
+	let new_handle = table.insert(100).unwrap();
+	assert_eq!(new_handle as u32, old_handle as u32);
+	assert_ne!(new_handle, old_handle);
+	assert!(table.is_stale(old_handle));
+	assert!(!table.is_stale(new_handle));
+	assert_eq!(table.get(old_handle), None);
+	assert_eq!(table.remove(old_handle), None);
+	assert_eq!(table.get(new_handle), Some(100));
+
+	// Handles that were never allocated aren't stale.
+	assert!(!table.is_stale(0));
+	assert!(!table.is_stale(u64::from(u32::MAX)));
+	assert_eq!(table.get(u64::from(u32::MAX)), None);
+}
+
+#[test]
+fn insert_full_table() {
+	let table = HandleTable::with_max_len(16);
+	let handles: Vec<u64> = (0..16)
+		.map(|ii| table.insert(ii).unwrap())
+		.collect();
+	let unique: HashSet<u64> = handles.iter().copied().collect();
+	assert_eq!(unique.len(), 16);
+	assert_eq!(table.insert(16), Err(OsError::TOO_MANY_OPEN_FILES));
+
+	// Inserting falls back to other shards when the next shard is full.
+	for (ii, &handle) in handles.iter().enumerate().rev() {
+		assert_eq!(table.remove(handle), Some(ii));
+		let new_handle = table.insert(100 + ii).unwrap();
+		assert_eq!(new_handle as u32, handle as u32);
+		assert_eq!(table.get(new_handle), Some(100 + ii));
+		assert_eq!(table.insert(16), Err(OsError::TOO_MANY_OPEN_FILES));
+	}
+}
+
+#[test]
+fn insert_empty_table() {
+	let table = HandleTable::with_max_len(0);
+	assert_eq!(table.insert(()), Err(OsError::TOO_MANY_OPEN_FILES));
+}
@@ -0,0 +1,510 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! In-memory advisory locking.
+//!
+//! A [`LockTable`] implements the semantics of POSIX record locks and
+//! `flock(2)` locks for servers that don't need to forward locks to a
+//! backing store. It handles the `FUSE_GETLK`, `FUSE_SETLK`, and
+//! `FUSE_SETLKW` operations, including splitting and merging of ranges held
+//! by the same owner.
+
+use core::num::NonZeroU64;
+use std::collections::HashMap;
+
+use fuse::os::OsError;
+use fuse::{Lock, LockMode, LockOwner, LockOwnerProcessId, LockRange, NodeId};
+
+#[cfg(target_os = "freebsd")]
+use fuse::os::freebsd::{F_RDLCK, F_UNLCK, F_WRLCK};
+
+#[cfg(target_os = "linux")]
+use fuse::os::linux::{F_RDLCK, F_UNLCK, F_WRLCK};
+
+// LockTable {{{
+
+/// A table of POSIX record locks, indexed by node.
+///
+/// Locks are identified by their [`LockOwner`]. Two locks conflict if they
+/// have different owners, their ranges overlap, and at least one of them is
+/// an exclusive (write) lock. A lock never conflicts with other locks held
+/// by the same owner; instead, it replaces them within its range.
+///
+/// The table also tracks `flock(2)` locks, which cover the whole file and
+/// are identified by the handle they were acquired through. As on Linux,
+/// `flock(2)` locks and POSIX record locks don't conflict with each other.
+///
+/// `LockTable` does not detect deadlocks between waiting requests.
+pub struct LockTable {
+	nodes: HashMap<NodeId, NodeLocks>,
+}
+
+#[derive(Default)]
+struct NodeLocks {
+	held: Vec<HeldLock>,
+	flocks: Vec<HeldFlock>,
+	waiters: Vec<Waiter>,
+}
+
+#[derive(Clone, Copy)]
+struct HeldLock {
+	owner: LockOwner,
+	exclusive: bool,
+	start: u64,
+	// Inclusive. Unbounded ranges end at `u64::MAX`.
+	end: u64,
+	process_id: Option<LockOwnerProcessId>,
+}
+
+#[derive(Clone, Copy)]
+struct HeldFlock {
+	handle: u64,
+	exclusive: bool,
+}
+
+type Callback = Box<dyn FnOnce(Result<(), fuse::Error>) + Send>;
+
+struct Waiter {
+	request_id: NonZeroU64,
+	request: WaitRequest,
+	callback: Callback,
+}
+
+enum WaitRequest {
+	Posix(HeldLock),
+	Flock(HeldFlock),
+}
+
+impl LockTable {
+	/// Creates a new, empty `LockTable`.
+	#[must_use]
+	pub fn new() -> LockTable {
+		Self {
+			nodes: HashMap::new(),
+		}
+	}
+
+	/// Returns the first lock that would conflict with `lock`, if any.
+	///
+	/// This implements `FUSE_GETLK`. If no lock conflicts, the server should
+	/// reply with a lock of mode `F_UNLCK`.
+	#[must_use]
+	pub fn getlk(
+		&self,
+		node_id: NodeId,
+		owner: LockOwner,
+		lock: &Lock,
+	) -> Option<Lock> {
+		let node = self.nodes.get(&node_id)?;
+		let request = HeldLock::new(owner, lock)?;
+		node.conflict(&request).copied().map(HeldLock::to_lock)
+	}
+
+	/// Acquires or releases a lock without waiting.
+	///
+	/// This implements `FUSE_SETLK`.
+	///
+	/// # Errors
+	///
+	/// Returns [`OsError::UNAVAILABLE`] if the lock conflicts with a lock
+	/// held by another owner, or [`OsError::INVALID_ARGUMENT`] if the lock's
+	/// mode isn't recognized.
+	pub fn setlk(
+		&mut self,
+		node_id: NodeId,
+		owner: LockOwner,
+		lock: &Lock,
+	) -> Result<(), fuse::Error> {
+		check_mode(lock.mode())?;
+		let node = self.nodes.entry(node_id).or_default();
+		match HeldLock::new(owner, lock) {
+			None => {
+				node.unlock(owner, lock.range());
+				node.wake_waiters();
+			},
+			Some(request) => {
+				if node.conflict(&request).is_some() {
+					return Err(OsError::UNAVAILABLE);
+				}
+				node.insert(request);
+			},
+		}
+		self.remove_if_empty(node_id);
+		Ok(())
+	}
+
+	/// Acquires or releases a lock, waiting for conflicting locks to be
+	/// released.
+	///
+	/// This implements `FUSE_SETLKW`. The `on_complete` callback is called
+	/// with `Ok(())` when the lock has been acquired, which may happen
+	/// before `setlkw` returns. It is called with an error if the lock's
+	/// mode isn't recognized, or if the request is interrupted before the
+	/// lock is acquired.
+	///
+	/// The callback is called while the `LockTable` is borrowed, so it
+	/// should only send the reply.
+	pub fn setlkw(
+		&mut self,
+		node_id: NodeId,
+		owner: LockOwner,
+		lock: &Lock,
+		request_id: NonZeroU64,
+		on_complete: impl FnOnce(Result<(), fuse::Error>) + Send + 'static,
+	) {
+		if let Err(err) = check_mode(lock.mode()) {
+			on_complete(Err(err));
+			return;
+		}
+		let node = self.nodes.entry(node_id).or_default();
+		let request = match HeldLock::new(owner, lock) {
+			Some(request) => request,
+			None => {
+				node.unlock(owner, lock.range());
+				on_complete(Ok(()));
+				node.wake_waiters();
+				self.remove_if_empty(node_id);
+				return;
+			},
+		};
+		if node.conflict(&request).is_none() {
+			node.insert(request);
+			on_complete(Ok(()));
+			return;
+		}
+		node.waiters.push(Waiter {
+			request_id,
+			request: WaitRequest::Posix(request),
+			callback: Box::new(on_complete),
+		});
+	}
+
+	/// Acquires or releases a `flock(2)` lock without waiting.
+	///
+	/// This implements `FUSE_SETLK` for requests where
+	/// [`SetlkRequest::is_flock`] returns `true`. The lock covers the whole
+	/// file, and is held by `handle` until it is unlocked or released.
+	///
+	/// [`SetlkRequest::is_flock`]: fuse::server::SetlkRequest::is_flock
+	///
+	/// # Errors
+	///
+	/// Returns [`OsError::UNAVAILABLE`] if the lock conflicts with a lock
+	/// held through another handle, or [`OsError::INVALID_ARGUMENT`] if the
+	/// lock mode isn't recognized.
+	pub fn flock(
+		&mut self,
+		node_id: NodeId,
+		handle: u64,
+		mode: LockMode,
+	) -> Result<(), fuse::Error> {
+		check_mode(mode)?;
+		let node = self.nodes.entry(node_id).or_default();
+		match HeldFlock::new(handle, mode) {
+			None => {
+				node.flocks.retain(|held| held.handle != handle);
+				node.wake_waiters();
+			},
+			Some(request) => {
+				if node.flock_conflict(request) {
+					return Err(OsError::UNAVAILABLE);
+				}
+				node.insert_flock(request);
+			},
+		}
+		self.remove_if_empty(node_id);
+		Ok(())
+	}
+
+	/// Acquires or releases a `flock(2)` lock, waiting for conflicting locks
+	/// to be released.
+	///
+	/// This implements `FUSE_SETLKW` for requests where
+	/// [`SetlkRequest::is_flock`] returns `true`. The `on_complete` callback
+	/// is called as described for [`LockTable::setlkw`].
+	///
+	/// [`SetlkRequest::is_flock`]: fuse::server::SetlkRequest::is_flock
+	pub fn flockw(
+		&mut self,
+		node_id: NodeId,
+		handle: u64,
+		mode: LockMode,
+		request_id: NonZeroU64,
+		on_complete: impl FnOnce(Result<(), fuse::Error>) + Send + 'static,
+	) {
+		if let Err(err) = check_mode(mode) {
+			on_complete(Err(err));
+			return;
+		}
+		let node = self.nodes.entry(node_id).or_default();
+		let request = match HeldFlock::new(handle, mode) {
+			Some(request) => request,
+			None => {
+				node.flocks.retain(|held| held.handle != handle);
+				on_complete(Ok(()));
+				node.wake_waiters();
+				self.remove_if_empty(node_id);
+				return;
+			},
+		};
+		if !node.flock_conflict(request) {
+			node.insert_flock(request);
+			on_complete(Ok(()));
+			return;
+		}
+		node.waiters.push(Waiter {
+			request_id,
+			request: WaitRequest::Flock(request),
+			callback: Box::new(on_complete),
+		});
+	}
+
+	/// Cancels a waiting `FUSE_SETLKW` request.
+	///
+	/// The request's callback is called with [`OsError::INTERRUPTED`].
+	/// Returns `false` if no request with the given ID is waiting.
+	pub fn interrupt(&mut self, request_id: NonZeroU64) -> bool {
+		for (node_id, node) in self.nodes.iter_mut() {
+			let pos = node.waiters.iter()
+				.position(|waiter| waiter.request_id == request_id);
+			if let Some(pos) = pos {
+				let waiter = node.waiters.remove(pos);
+				(waiter.callback)(Err(OsError::INTERRUPTED));
+				let node_id = *node_id;
+				self.remove_if_empty(node_id);
+				return true;
+			}
+		}
+		false
+	}
+
+	/// Releases all locks held by an owner on a node.
+	///
+	/// This should be called when the client sends a `FUSE_FLUSH` or
+	/// `FUSE_RELEASE` request with a lock owner, which happens when a
+	/// process closes a file it had locked.
+	pub fn release_owner(&mut self, node_id: NodeId, owner: LockOwner) {
+		if let Some(node) = self.nodes.get_mut(&node_id) {
+			node.held.retain(|held| held.owner != owner);
+			node.wake_waiters();
+			self.remove_if_empty(node_id);
+		}
+	}
+
+	/// Releases the `flock(2)` lock held through a handle, if any.
+	///
+	/// This should be called when the client sends a `FUSE_RELEASE` request
+	/// where [`ReleaseRequest::flock_unlock`] returns `true`.
+	///
+	/// [`ReleaseRequest::flock_unlock`]: fuse::server::ReleaseRequest::flock_unlock
+	pub fn release_handle(&mut self, node_id: NodeId, handle: u64) {
+		if let Some(node) = self.nodes.get_mut(&node_id) {
+			node.flocks.retain(|held| held.handle != handle);
+			node.wake_waiters();
+			self.remove_if_empty(node_id);
+		}
+	}
+
+	/// Returns the POSIX record locks held on a node.
+	pub fn locks(
+		&self,
+		node_id: NodeId,
+	) -> impl Iterator<Item = (LockOwner, Lock)> + '_ {
+		self.nodes.get(&node_id)
+			.into_iter()
+			.flat_map(|node| node.held.iter())
+			.map(|held| (held.owner, held.to_lock()))
+	}
+
+	fn remove_if_empty(&mut self, node_id: NodeId) {
+		if let Some(node) = self.nodes.get(&node_id) {
+			if node.is_empty() {
+				self.nodes.remove(&node_id);
+			}
+		}
+	}
+}
+
+impl Default for LockTable {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+fn check_mode(mode: LockMode) -> Result<(), fuse::Error> {
+	if mode == F_RDLCK || mode == F_WRLCK || mode == F_UNLCK {
+		return Ok(());
+	}
+	Err(OsError::INVALID_ARGUMENT)
+}
+
+impl NodeLocks {
+	fn is_empty(&self) -> bool {
+		self.held.is_empty()
+			&& self.flocks.is_empty()
+			&& self.waiters.is_empty()
+	}
+
+	fn flock_conflict(&self, request: HeldFlock) -> bool {
+		self.flocks.iter().any(|held| {
+			held.handle != request.handle
+				&& (held.exclusive || request.exclusive)
+		})
+	}
+
+	fn insert_flock(&mut self, request: HeldFlock) {
+		self.flocks.retain(|held| held.handle != request.handle);
+		self.flocks.push(request);
+	}
+
+	fn conflict(&self, request: &HeldLock) -> Option<&HeldLock> {
+		self.held.iter().find(|held| {
+			held.owner != request.owner
+				&& (held.exclusive || request.exclusive)
+				&& held.overlaps(request.start, request.end)
+		})
+	}
+
+	// Removes the owner's locks within `range`, splitting locks that extend
+	// past either end.
+	fn unlock(&mut self, owner: LockOwner, range: LockRange) {
+		let (start, end) = range_bounds(range);
+		let mut split = Vec::new();
+		self.held.retain(|held| {
+			if held.owner != owner || !held.overlaps(start, end) {
+				return true;
+			}
+			if held.start < start {
+				split.push(HeldLock { end: start - 1, ..*held });
+			}
+			if held.end > end {
+				split.push(HeldLock { start: end + 1, ..*held });
+			}
+			false
+		});
+		self.held.extend(split);
+	}
+
+	// Adds a lock, replacing the owner's locks within its range and merging
+	// it with adjacent or overlapping locks of the same mode.
+	fn insert(&mut self, mut request: HeldLock) {
+		let range = request.range();
+		let owner = request.owner;
+		self.held.retain(|held| {
+			if held.owner != owner || held.exclusive != request.exclusive {
+				return true;
+			}
+			let adjacent = held.end.checked_add(1) == Some(request.start)
+				|| request.end.checked_add(1) == Some(held.start);
+			if !adjacent && !held.overlaps(request.start, request.end) {
+				return true;
+			}
+			request.start = core::cmp::min(request.start, held.start);
+			request.end = core::cmp::max(request.end, held.end);
+			false
+		});
+		self.unlock(owner, range);
+		self.unlock(owner, request.range());
+		self.held.push(request);
+	}
+
+	// Grants waiting locks that no longer conflict, in the order they were
+	// requested.
+	fn wake_waiters(&mut self) {
+		let mut idx = 0;
+		while idx < self.waiters.len() {
+			let granted = match self.waiters[idx].request {
+				WaitRequest::Posix(request) => {
+					let granted = self.conflict(&request).is_none();
+					if granted {
+						self.insert(request);
+					}
+					granted
+				},
+				WaitRequest::Flock(request) => {
+					let granted = !self.flock_conflict(request);
+					if granted {
+						self.insert_flock(request);
+					}
+					granted
+				},
+			};
+			if !granted {
+				idx += 1;
+				continue;
+			}
+			let waiter = self.waiters.remove(idx);
+			(waiter.callback)(Ok(()));
+		}
+	}
+}
+
+impl HeldLock {
+	// Returns `None` for unlock requests.
+	fn new(owner: LockOwner, lock: &Lock) -> Option<HeldLock> {
+		let exclusive = if lock.mode() == F_WRLCK {
+			true
+		} else if lock.mode() == F_RDLCK {
+			false
+		} else {
+			return None;
+		};
+		let (start, end) = range_bounds(lock.range());
+		Some(HeldLock {
+			owner,
+			exclusive,
+			start,
+			end,
+			process_id: lock.process_id(),
+		})
+	}
+
+	fn overlaps(&self, start: u64, end: u64) -> bool {
+		self.start <= end && start <= self.end
+	}
+
+	fn range(self) -> LockRange {
+		let length = match self.end {
+			u64::MAX => None,
+			end => NonZeroU64::new(end - self.start + 1),
+		};
+		LockRange::new(self.start, length)
+	}
+
+	fn to_lock(self) -> Lock {
+		let mode = if self.exclusive { F_WRLCK } else { F_RDLCK };
+		Lock::new(mode, self.range(), self.process_id)
+	}
+}
+
+impl HeldFlock {
+	// Returns `None` for unlock requests.
+	fn new(handle: u64, mode: LockMode) -> Option<HeldFlock> {
+		if mode == F_WRLCK {
+			Some(HeldFlock { handle, exclusive: true })
+		} else if mode == F_RDLCK {
+			Some(HeldFlock { handle, exclusive: false })
+		} else {
+			None
+		}
+	}
+}
+
+fn range_bounds(range: LockRange) -> (u64, u64) {
+	(range.start(), range.end().unwrap_or(u64::MAX))
+}
+
+// }}}
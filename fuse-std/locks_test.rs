@@ -0,0 +1,293 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::num::NonZeroU64;
+use std::sync::{Arc, Mutex};
+
+use fuse::os::OsError;
+use fuse::{Lock, LockOwner, LockRange, NodeId};
+
+use fuse_std::locks::LockTable;
+
+#[cfg(target_os = "freebsd")]
+use fuse::os::freebsd::{F_RDLCK, F_UNLCK, F_WRLCK};
+
+#[cfg(target_os = "linux")]
+use fuse::os::linux::{F_RDLCK, F_UNLCK, F_WRLCK};
+
+const NODE: NodeId = NodeId::ROOT;
+const OWNER_A: LockOwner = LockOwner(0xA);
+const OWNER_B: LockOwner = LockOwner(0xB);
+
+fn range(start: u64, end: u64) -> LockRange {
+	LockRange::new(start, NonZeroU64::new(end - start + 1))
+}
+
+fn request_id(id: u64) -> NonZeroU64 {
+	NonZeroU64::new(id).unwrap()
+}
+
+// Returns the locks held on `NODE` as `(owner, exclusive, start, end)`.
+fn held(table: &LockTable) -> Vec<(LockOwner, bool, u64, Option<u64>)> {
+	let mut held: Vec<_> = table.locks(NODE)
+		.map(|(owner, lock)| {
+			let exclusive = lock == Lock::write(lock.range());
+			let range = lock.range();
+			(owner, exclusive, range.start(), range.end())
+		})
+		.collect();
+	held.sort();
+	held
+}
+
+type Completions = Arc<Mutex<Vec<Result<(), fuse::Error>>>>;
+
+fn on_complete(
+	completions: &Completions,
+) -> impl FnOnce(Result<(), fuse::Error>) + Send + 'static {
+	let completions = completions.clone();
+	move |result| completions.lock().unwrap().push(result)
+}
+
+#[test]
+fn setlk_unlock_splits_range() {
+	let mut table = LockTable::new();
+	table.setlk(NODE, OWNER_A, &Lock::write(range(0, 99))).unwrap();
+	table.setlk(NODE, OWNER_A, &Lock::unlock(range(40, 59))).unwrap();
+
+	assert_eq!(held(&table), [
+		(OWNER_A, true, 0, Some(39)),
+		(OWNER_A, true, 60, Some(99)),
+	]);
+
+	// Unlocking the remaining ranges removes all of the owner's locks.
+	let unlock = Lock::unlock(LockRange::whole_file());
+	table.setlk(NODE, OWNER_A, &unlock).unwrap();
+	assert!(held(&table).is_empty());
+}
+
+#[test]
+fn setlk_changes_mode_within_range() {
+	let mut table = LockTable::new();
+	table.setlk(NODE, OWNER_A, &Lock::read(range(0, 99))).unwrap();
+	table.setlk(NODE, OWNER_A, &Lock::write(range(20, 29))).unwrap();
+
+	assert_eq!(held(&table), [
+		(OWNER_A, false, 0, Some(19)),
+		(OWNER_A, false, 30, Some(99)),
+		(OWNER_A, true, 20, Some(29)),
+	]);
+}
+
+#[test]
+fn setlk_merges_adjacent_ranges() {
+	let mut table = LockTable::new();
+	table.setlk(NODE, OWNER_A, &Lock::read(range(0, 9))).unwrap();
+	table.setlk(NODE, OWNER_A, &Lock::read(range(20, 29))).unwrap();
+	table.setlk(NODE, OWNER_A, &Lock::read(range(10, 19))).unwrap();
+
+	assert_eq!(held(&table), [(OWNER_A, false, 0, Some(29))]);
+
+	// Adjacent ranges with different modes aren't merged.
+	table.setlk(NODE, OWNER_A, &Lock::write(range(30, 39))).unwrap();
+	assert_eq!(held(&table), [
+		(OWNER_A, false, 0, Some(29)),
+		(OWNER_A, true, 30, Some(39)),
+	]);
+
+	// Overlapping ranges are merged, including unbounded ranges.
+	let lock = Lock::write(LockRange::from_offset(35));
+	table.setlk(NODE, OWNER_A, &lock).unwrap();
+	assert_eq!(held(&table), [
+		(OWNER_A, false, 0, Some(29)),
+		(OWNER_A, true, 30, None),
+	]);
+}
+
+#[test]
+fn setlk_conflicts_across_owners() {
+	let mut table = LockTable::new();
+	table.setlk(NODE, OWNER_A, &Lock::write(range(0, 9))).unwrap();
+
+	// An overlapping lock held by another owner conflicts.
+	let lock = Lock::read(range(5, 14));
+	assert_eq!(table.setlk(NODE, OWNER_B, &lock), Err(OsError::UNAVAILABLE));
+	assert_eq!(
+		table.getlk(NODE, OWNER_B, &lock),
+		Some(Lock::write(range(0, 9))),
+	);
+
+	// Locks held by the same owner never conflict.
+	assert_eq!(table.getlk(NODE, OWNER_A, &lock), None);
+
+	// Shared locks don't conflict with each other.
+	table.setlk(NODE, OWNER_B, &Lock::read(range(10, 19))).unwrap();
+	table.setlk(NODE, OWNER_A, &Lock::read(range(15, 24))).unwrap();
+	let lock = Lock::write(range(12, 12));
+	assert_eq!(table.setlk(NODE, OWNER_A, &lock), Err(OsError::UNAVAILABLE));
+
+	assert_eq!(held(&table), [
+		(OWNER_A, false, 15, Some(24)),
+		(OWNER_A, true, 0, Some(9)),
+		(OWNER_B, false, 10, Some(19)),
+	]);
+}
+
+#[test]
+fn setlk_invalid_mode() {
+	let mut table = LockTable::new();
+	let lock = Lock::new(fuse::LockMode(0xFFFF), range(0, 9), None);
+	assert_eq!(
+		table.setlk(NODE, OWNER_A, &lock),
+		Err(OsError::INVALID_ARGUMENT),
+	);
+}
+
+#[test]
+fn setlkw_wakes_waiter_after_release() {
+	let mut table = LockTable::new();
+	let completions = Completions::default();
+
+	table.setlk(NODE, OWNER_A, &Lock::write(range(0, 9))).unwrap();
+	let lock = Lock::write(range(5, 14));
+	let callback = on_complete(&completions);
+	table.setlkw(NODE, OWNER_B, &lock, request_id(1), callback);
+	assert!(completions.lock().unwrap().is_empty());
+
+	// Unlocking a range that doesn't overlap the waiter's lock doesn't
+	// grant it.
+	table.setlk(NODE, OWNER_A, &Lock::unlock(range(0, 2))).unwrap();
+	assert!(completions.lock().unwrap().is_empty());
+
+	table.release_owner(NODE, OWNER_A);
+	assert_eq!(*completions.lock().unwrap(), [Ok(())]);
+	assert_eq!(held(&table), [(OWNER_B, true, 5, Some(14))]);
+}
+
+#[test]
+fn setlkw_without_conflict_completes_immediately() {
+	let mut table = LockTable::new();
+	let completions = Completions::default();
+
+	let lock = Lock::write(range(0, 9));
+	let callback = on_complete(&completions);
+	table.setlkw(NODE, OWNER_A, &lock, request_id(1), callback);
+	assert_eq!(*completions.lock().unwrap(), [Ok(())]);
+	assert_eq!(held(&table), [(OWNER_A, true, 0, Some(9))]);
+}
+
+#[test]
+fn interrupt_cancels_waiter() {
+	let mut table = LockTable::new();
+	let completions = Completions::default();
+
+	table.setlk(NODE, OWNER_A, &Lock::write(range(0, 9))).unwrap();
+	let lock = Lock::read(range(0, 9));
+	let callback = on_complete(&completions);
+	table.setlkw(NODE, OWNER_B, &lock, request_id(1), callback);
+
+	assert!(!table.interrupt(request_id(2)));
+	assert!(table.interrupt(request_id(1)));
+	assert_eq!(*completions.lock().unwrap(), [Err(OsError::INTERRUPTED)]);
+	assert!(!table.interrupt(request_id(1)));
+
+	// The cancelled request isn't granted when the lock is released.
+	table.release_owner(NODE, OWNER_A);
+	assert_eq!(completions.lock().unwrap().len(), 1);
+	assert!(held(&table).is_empty());
+}
+
+#[test]
+fn flock_shared_and_exclusive() {
+	let mut table = LockTable::new();
+	table.flock(NODE, 1, F_RDLCK).unwrap();
+	table.flock(NODE, 2, F_RDLCK).unwrap();
+	assert_eq!(table.flock(NODE, 3, F_WRLCK), Err(OsError::UNAVAILABLE));
+
+	table.flock(NODE, 1, F_UNLCK).unwrap();
+	table.flock(NODE, 2, F_UNLCK).unwrap();
+	table.flock(NODE, 3, F_WRLCK).unwrap();
+	assert_eq!(table.flock(NODE, 1, F_RDLCK), Err(OsError::UNAVAILABLE));
+	assert_eq!(table.flock(NODE, 2, F_WRLCK), Err(OsError::UNAVAILABLE));
+}
+
+#[test]
+fn flock_upgrade_and_downgrade() {
+	let mut table = LockTable::new();
+	table.flock(NODE, 1, F_RDLCK).unwrap();
+	table.flock(NODE, 2, F_RDLCK).unwrap();
+
+	// A shared lock can't be upgraded while another handle shares it.
+	assert_eq!(table.flock(NODE, 1, F_WRLCK), Err(OsError::UNAVAILABLE));
+	table.flock(NODE, 2, F_UNLCK).unwrap();
+	table.flock(NODE, 1, F_WRLCK).unwrap();
+	assert_eq!(table.flock(NODE, 2, F_RDLCK), Err(OsError::UNAVAILABLE));
+
+	// Downgrading lets other handles acquire shared locks.
+	table.flock(NODE, 1, F_RDLCK).unwrap();
+	table.flock(NODE, 2, F_RDLCK).unwrap();
+}
+
+#[test]
+fn flock_independent_of_record_locks() {
+	let mut table = LockTable::new();
+	table.flock(NODE, 1, F_WRLCK).unwrap();
+	let lock = Lock::write(LockRange::whole_file());
+	table.setlk(NODE, OWNER_B, &lock).unwrap();
+
+	table.release_handle(NODE, 1);
+	table.flock(NODE, 2, F_WRLCK).unwrap();
+	assert_eq!(held(&table), [(OWNER_B, true, 0, None)]);
+}
+
+#[test]
+fn flock_release_handle() {
+	let mut table = LockTable::new();
+	let completions = Completions::default();
+
+	table.flock(NODE, 1, F_WRLCK).unwrap();
+	let callback = on_complete(&completions);
+	table.flockw(NODE, 2, F_WRLCK, request_id(1), callback);
+	assert!(completions.lock().unwrap().is_empty());
+
+	// Releasing another handle doesn't release the lock.
+	table.release_handle(NODE, 3);
+	assert!(completions.lock().unwrap().is_empty());
+
+	// Closing the handle releases its lock and grants the waiter.
+	table.release_handle(NODE, 1);
+	assert_eq!(*completions.lock().unwrap(), [Ok(())]);
+	assert_eq!(table.flock(NODE, 1, F_RDLCK), Err(OsError::UNAVAILABLE));
+
+	table.release_handle(NODE, 2);
+	table.flock(NODE, 1, F_RDLCK).unwrap();
+}
+
+#[test]
+fn flockw_interrupted() {
+	let mut table = LockTable::new();
+	let completions = Completions::default();
+
+	table.flock(NODE, 1, F_RDLCK).unwrap();
+	let callback = on_complete(&completions);
+	table.flockw(NODE, 2, F_WRLCK, request_id(1), callback);
+	assert!(table.interrupt(request_id(1)));
+	assert_eq!(*completions.lock().unwrap(), [Err(OsError::INTERRUPTED)]);
+
+	table.release_handle(NODE, 1);
+	assert_eq!(completions.lock().unwrap().len(), 1);
+	table.flock(NODE, 3, F_WRLCK).unwrap();
+}
@@ -0,0 +1,157 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Read-only views of a filesystem.
+//!
+//! A [`ReadOnly`] wraps a set of handlers and rejects every request that
+//! would modify the filesystem with [`OsError::READ_ONLY`], before the
+//! wrapped handlers see it. Handlers for mutating operations that were left
+//! at their defaults, or that forget to check a read-only setting, can't be
+//! reached through the wrapper:
+//!
+//! ```no_run
+//! # fn f<S, H>(conn: &fuse::server::FuseConnection<S>, handlers: H)
+//! # where
+//! # 	S: fuse::server::FuseSocket + Send + Sync,
+//! # 	S::Error: Send,
+//! # 	H: fuse::server::FuseHandlers + Send + Sync,
+//! # {
+//! use fuse_std::read_only::ReadOnly;
+//!
+//! let handlers = ReadOnly::new(handlers, conn);
+//! let _ = fuse_std::serve_fuse(conn, &handlers);
+//! # }
+//! ```
+//!
+//! The filesystem should usually also be mounted read-only, for example with
+//! `MountOptions::set_read_only` of `fuse-linux` or `fuse-libc`. The kernel
+//! then rejects writes with `EROFS` without sending them to the server, but
+//! the wrapper is still needed because the mount's owner can remount it
+//! read-write.
+//!
+//! [`OsError::READ_ONLY`]: fuse::os::OsError::READ_ONLY
+
+use fuse::kernel::fuse_opcode;
+use fuse::os::OsError;
+use fuse::server;
+
+// ReadOnly {{{
+
+/// A handlers wrapper that rejects requests which would modify the
+/// filesystem.
+///
+/// Requests for which [`is_mutating`] returns `true` are rejected with
+/// [`OsError::READ_ONLY`]. All other requests are dispatched to the wrapped
+/// handlers.
+///
+/// Only [`dispatch`](server::FuseHandlers::dispatch) is filtered. Calling
+/// the per-operation methods of a `ReadOnly` directly will reach the wrapped
+/// handlers' `unimplemented` method.
+///
+/// [`OsError::READ_ONLY`]: fuse::os::OsError::READ_ONLY
+pub struct ReadOnly<'a, H, S> {
+	handlers: H,
+	conn: &'a server::FuseConnection<S>,
+}
+
+impl<'a, H, S> ReadOnly<'a, H, S> {
+	/// Creates a new `ReadOnly` wrapping the given handlers.
+	///
+	/// Rejected requests are replied to through `conn`.
+	#[must_use]
+	pub fn new(
+		handlers: H,
+		conn: &'a server::FuseConnection<S>,
+	) -> ReadOnly<'a, H, S> {
+		Self { handlers, conn }
+	}
+
+	/// Returns a reference to the wrapped handlers.
+	#[must_use]
+	pub fn handlers(&self) -> &H {
+		&self.handlers
+	}
+}
+
+impl<H, S> server::FuseHandlers for ReadOnly<'_, H, S>
+where
+	H: server::FuseHandlers,
+	S: server::FuseSocket,
+{
+	fn unimplemented(&self, request: server::FuseRequest<'_>) {
+		self.handlers.unimplemented(request)
+	}
+
+	fn dispatch(&self, request: server::FuseRequest<'_>) {
+		if !is_mutating(request) {
+			self.handlers.dispatch(request);
+			return;
+		}
+		_ = self.conn.reply(request.id()).err(OsError::READ_ONLY);
+	}
+}
+
+impl<H, S> core::fmt::Debug for ReadOnly<'_, H, S> {
+	fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+		fmt.debug_struct("ReadOnly").finish_non_exhaustive()
+	}
+}
+
+// }}}
+
+/// Returns whether handling the request could modify the filesystem.
+///
+/// This is `true` for requests that create, remove, rename, or link nodes,
+/// that change a node's attributes, extended attributes, or content, and
+/// for `FUSE_OPEN` requests that open a file for writing or with `O_TRUNC`.
+/// A `FUSE_OPEN` request that can't be decoded is also treated as mutating.
+///
+/// Requests that don't modify the filesystem itself, such as `FUSE_FLUSH`,
+/// `FUSE_FSYNC`, and file locking, return `false`. So does `FUSE_IOCTL`,
+/// because the meaning of an ioctl command is chosen by the server.
+#[must_use]
+pub fn is_mutating(request: server::FuseRequest<'_>) -> bool {
+	match request.header().opcode() {
+		fuse_opcode::FUSE_COPY_FILE_RANGE
+		| fuse_opcode::FUSE_CREATE
+		| fuse_opcode::FUSE_FALLOCATE
+		| fuse_opcode::FUSE_LINK
+		| fuse_opcode::FUSE_MKDIR
+		| fuse_opcode::FUSE_MKNOD
+		| fuse_opcode::FUSE_REMOVEXATTR
+		| fuse_opcode::FUSE_RENAME
+		| fuse_opcode::FUSE_RENAME2
+		| fuse_opcode::FUSE_RMDIR
+		| fuse_opcode::FUSE_SETATTR
+		| fuse_opcode::FUSE_SETXATTR
+		| fuse_opcode::FUSE_SYMLINK
+		| fuse_opcode::FUSE_TMPFILE
+		| fuse_opcode::FUSE_UNLINK
+		| fuse_opcode::FUSE_WRITE => true,
+		fuse_opcode::FUSE_OPEN => {
+			let Ok(request) = server::OpenRequest::try_from(request) else {
+				return true;
+			};
+			let flags = request.open_flags();
+			let read_only = matches!(
+				flags.access_mode(),
+				Some(fuse::OpenAccessMode::ReadOnly),
+			);
+			!read_only || flags.truncate()
+		},
+		_ => false,
+	}
+}
@@ -0,0 +1,181 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+
+use core::mem::size_of;
+use core::num::NonZeroU64;
+
+use fuse::io::MinReadBuffer;
+use fuse::kernel;
+use fuse::os::OsError;
+use fuse::server::{self, Socket};
+use fuse::testing::{
+	KernelSocket,
+	RequestBuilder,
+	ServerSocket,
+	SocketError,
+	SocketPair,
+};
+
+use fuse_std::ReplyCacheSocket;
+
+fn layout() -> server::FuseLayout {
+	let mut init_out = kernel::fuse_init_out::new();
+	init_out.major = kernel::FUSE_KERNEL_VERSION;
+	init_out.minor = kernel::FUSE_KERNEL_MINOR_VERSION;
+	server::FuseLayout::new(&init_out).unwrap()
+}
+
+fn push_getattr(kernel_socket: KernelSocket, request_id: u64, node_id: u64) {
+	let mut builder = RequestBuilder::new(layout());
+	builder.set_request_id(request_id);
+	let mut buf = MinReadBuffer::new();
+	let getattr_in = kernel::fuse_getattr_in::new();
+	let request = builder
+		.getattr(buf.as_aligned_slice_mut(), node_id, &getattr_in)
+		.unwrap();
+	kernel_socket.push_request(request.get()).unwrap();
+}
+
+// Returns the request ID of the next request passed on to the server.
+fn recv_request(socket: &ReplyCacheSocket<ServerSocket>) -> Option<u64> {
+	let mut buf = [0u8; 256];
+	match socket.recv(&mut buf) {
+		Ok(len) => {
+			assert!(len >= size_of::<kernel::fuse_in_header>());
+			let header = unsafe {
+				buf.as_ptr().cast::<kernel::fuse_in_header>().read_unaligned()
+			};
+			Some(header.unique)
+		},
+		Err(server::RecvError::ConnectionClosed(_)) => None,
+		Err(err) => panic!("{:?}", err),
+	}
+}
+
+fn send_reply(
+	socket: &ReplyCacheSocket<ServerSocket>,
+	request_id: u64,
+	error: fuse::Error,
+) -> Result<(), server::SendError<SocketError>> {
+	let request_id = NonZeroU64::new(request_id).unwrap();
+	server::FuseReplySender::new(socket, layout(), request_id).err(error)
+}
+
+// Returns the request ID and error of each reply received by the kernel
+// socket.
+fn replies(kernel_socket: KernelSocket) -> Vec<(u64, fuse::Error)> {
+	let mut replies = Vec::new();
+	let mut buf = [0u8; 64];
+	while let Ok(len) = kernel_socket.pop_reply(&mut buf) {
+		assert_eq!(len, size_of::<kernel::fuse_out_header>());
+		let header = unsafe {
+			buf.as_ptr().cast::<kernel::fuse_out_header>().read_unaligned()
+		};
+		let error = core::num::NonZeroI32::new(header.error).unwrap();
+		replies.push((header.unique, fuse::Error(error)));
+	}
+	replies
+}
+
+#[test]
+fn replay_cached_reply() {
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	let socket = ReplyCacheSocket::new(pair.server(), 8);
+
+	push_getattr(pair.kernel(), 2, 1);
+	assert_eq!(recv_request(&socket), Some(2));
+	send_reply(&socket, 2, OsError::NOT_FOUND).unwrap();
+	assert_eq!(socket.cached_len(), 1);
+
+	// The repeated request is answered from the cache, and isn't passed on
+	// to the server.
+	push_getattr(pair.kernel(), 2, 1);
+	push_getattr(pair.kernel(), 3, 1);
+	assert_eq!(recv_request(&socket), Some(3));
+	assert_eq!(
+		replies(pair.kernel()),
+		[(2, OsError::NOT_FOUND), (2, OsError::NOT_FOUND)],
+	);
+}
+
+#[test]
+fn reused_request_id() {
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	let socket = ReplyCacheSocket::new(pair.server(), 8);
+
+	push_getattr(pair.kernel(), 2, 1);
+	assert_eq!(recv_request(&socket), Some(2));
+	send_reply(&socket, 2, OsError::NOT_FOUND).unwrap();
+
+	// A request with the same ID but a different node ID is a new request.
+	push_getattr(pair.kernel(), 2, 100);
+	assert_eq!(recv_request(&socket), Some(2));
+	assert_eq!(socket.cached_len(), 0);
+	send_reply(&socket, 2, OsError::UNAVAILABLE).unwrap();
+	assert_eq!(
+		replies(pair.kernel()),
+		[(2, OsError::NOT_FOUND), (2, OsError::UNAVAILABLE)],
+	);
+}
+
+#[test]
+fn oldest_reply_evicted() {
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	let socket = ReplyCacheSocket::new(pair.server(), 2);
+
+	for request_id in [2, 3, 4] {
+		push_getattr(pair.kernel(), request_id, 1);
+		assert_eq!(recv_request(&socket), Some(request_id));
+		send_reply(&socket, request_id, OsError::NOT_FOUND).unwrap();
+	}
+	assert_eq!(socket.cached_len(), 2);
+	replies(pair.kernel());
+
+	// The reply to request 2 was evicted, so the request is passed on to
+	// the server. The reply to request 4 is still cached.
+	push_getattr(pair.kernel(), 4, 1);
+	push_getattr(pair.kernel(), 2, 1);
+	assert_eq!(recv_request(&socket), Some(2));
+	assert_eq!(replies(pair.kernel()), [(4, OsError::NOT_FOUND)]);
+}
+
+#[test]
+fn send_error_not_cached() {
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 16];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	let socket = ReplyCacheSocket::new(pair.server(), 8);
+
+	// The reply queue is too small for any reply.
+	push_getattr(pair.kernel(), 2, 1);
+	assert_eq!(recv_request(&socket), Some(2));
+	assert!(matches!(
+		send_reply(&socket, 2, OsError::NOT_FOUND),
+		Err(server::SendError::Other(SocketError::QueueFull)),
+	));
+	assert_eq!(socket.cached_len(), 0);
+
+	push_getattr(pair.kernel(), 2, 1);
+	assert_eq!(recv_request(&socket), Some(2));
+	assert_eq!(recv_request(&socket), None);
+}
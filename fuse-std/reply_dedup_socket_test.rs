@@ -0,0 +1,132 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+
+use core::mem::size_of;
+use core::num::NonZeroU64;
+use std::sync::Mutex;
+
+use fuse::io::SendBuf;
+use fuse::kernel;
+use fuse::server::{self, Socket};
+use fuse::testing::{KernelSocket, SocketError, SocketPair};
+
+use fuse_std::ReplyDedupSocket;
+
+fn send_reply<S: Socket>(
+	socket: &S,
+	request_id: u64,
+) -> Result<(), server::SendError<S::Error>> {
+	let mut header = kernel::fuse_out_header::new();
+	header.len = size_of::<kernel::fuse_out_header>() as u32;
+	header.unique = request_id;
+	socket.send(SendBuf::new(header.as_bytes()))
+}
+
+// Returns the request IDs of the replies received by the kernel socket.
+fn replies(kernel_socket: KernelSocket) -> Vec<u64> {
+	let mut request_ids = Vec::new();
+	let mut buf = [0u8; 64];
+	while let Ok(len) = kernel_socket.pop_reply(&mut buf) {
+		assert_eq!(len, size_of::<kernel::fuse_out_header>());
+		let header = unsafe {
+			buf.as_ptr().cast::<kernel::fuse_out_header>().read_unaligned()
+		};
+		request_ids.push(header.unique);
+	}
+	request_ids
+}
+
+#[test]
+fn duplicate_replies_dropped() {
+	let mut request_buf = [0u8; 64];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+
+	let duplicates = Mutex::new(Vec::new());
+	let socket = ReplyDedupSocket::new(pair.server(), 8, |request_id| {
+		duplicates.lock().unwrap().push(request_id);
+	});
+
+	send_reply(&socket, 2).unwrap();
+	send_reply(&socket, 3).unwrap();
+	send_reply(&socket, 2).unwrap();
+	send_reply(&socket, 4).unwrap();
+	send_reply(&socket, 3).unwrap();
+
+	assert_eq!(replies(pair.kernel()), [2, 3, 4]);
+	assert_eq!(
+		*duplicates.lock().unwrap(),
+		[NonZeroU64::new(2).unwrap(), NonZeroU64::new(3).unwrap()],
+	);
+}
+
+#[test]
+fn notifications_not_deduplicated() {
+	let mut request_buf = [0u8; 64];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+
+	let socket = ReplyDedupSocket::new(pair.server(), 8, |request_id| {
+		panic!("unexpected duplicate reply {:?}", request_id);
+	});
+
+	send_reply(&socket, 0).unwrap();
+	send_reply(&socket, 0).unwrap();
+	assert_eq!(replies(pair.kernel()), [0, 0]);
+}
+
+#[test]
+fn oldest_reply_ids_forgotten() {
+	let mut request_buf = [0u8; 64];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+
+	let socket = ReplyDedupSocket::new(pair.server(), 2, |request_id| {
+		panic!("unexpected duplicate reply {:?}", request_id);
+	});
+
+	// Only the two most recent reply IDs are remembered, so the kernel may
+	// reuse request ID 2 once two other requests have been replied to.
+	send_reply(&socket, 2).unwrap();
+	send_reply(&socket, 3).unwrap();
+	send_reply(&socket, 4).unwrap();
+	send_reply(&socket, 2).unwrap();
+	assert_eq!(replies(pair.kernel()), [2, 3, 4, 2]);
+}
+
+#[test]
+fn failed_send_not_remembered() {
+	let mut request_buf = [0u8; 64];
+	let mut reply_buf = [0u8; 20];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+
+	let socket = ReplyDedupSocket::new(pair.server(), 8, |request_id| {
+		panic!("unexpected duplicate reply {:?}", request_id);
+	});
+
+	// The reply queue only has room for one reply.
+	send_reply(&socket, 2).unwrap();
+	assert!(matches!(
+		send_reply(&socket, 3),
+		Err(server::SendError::Other(SocketError::QueueFull)),
+	));
+	assert_eq!(replies(pair.kernel()), [2]);
+
+	// The failed reply may be retried.
+	send_reply(&socket, 3).unwrap();
+	assert_eq!(replies(pair.kernel()), [3]);
+}
@@ -0,0 +1,118 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+
+use core::num::NonZeroU64;
+use std::collections::HashSet;
+
+use fuse_std::RequestIdMap;
+
+fn id(id: u64) -> NonZeroU64 {
+	NonZeroU64::new(id).unwrap()
+}
+
+#[test]
+fn insert_get_remove() {
+	let mut map = RequestIdMap::with_salt(100);
+	assert!(map.is_empty());
+
+	let id_a = map.insert("a", id(1));
+	let id_b = map.insert("b", id(2));
+	assert_eq!(id_a, id(102));
+	assert_eq!(id_b, id(104));
+	assert_eq!(map.len(), 2);
+
+	assert_eq!(map.get(id_a), Some(("a", id(1))));
+	assert_eq!(map.downstream_id("a", id(1)), Some(id_a));
+	assert_eq!(map.remove(id_a), Some(("a", id(1))));
+	assert_eq!(map.get(id_a), None);
+	assert_eq!(map.downstream_id("a", id(1)), None);
+	assert_eq!(map.remove(id_a), None);
+
+	assert_eq!(map.get(id_b), Some(("b", id(2))));
+	assert_eq!(map.remove(id_b), Some(("b", id(2))));
+	assert!(map.is_empty());
+}
+
+#[test]
+fn remove_unknown() {
+	let mut map = RequestIdMap::<u32>::with_salt(0);
+	assert_eq!(map.get(id(2)), None);
+	assert_eq!(map.remove(id(2)), None);
+}
+
+#[test]
+fn remove_upstream() {
+	let mut map = RequestIdMap::with_salt(0);
+	let id_a1 = map.insert("a", id(1));
+	let id_a2 = map.insert("a", id(2));
+	let id_b1 = map.insert("b", id(1));
+
+	map.remove_upstream("a");
+	assert_eq!(map.len(), 1);
+	assert_eq!(map.get(id_a1), None);
+	assert_eq!(map.get(id_a2), None);
+	assert_eq!(map.downstream_id("a", id(1)), None);
+	assert_eq!(map.downstream_id("a", id(2)), None);
+	assert_eq!(map.remove(id_a1), None);
+
+	assert_eq!(map.get(id_b1), Some(("b", id(1))));
+	assert_eq!(map.downstream_id("b", id(1)), Some(id_b1));
+}
+
+#[test]
+fn duplicate_request() {
+	let mut map = RequestIdMap::with_salt(0);
+	let id_a = map.insert("a", id(1));
+	assert_eq!(map.insert("a", id(1)), id_a);
+	assert_eq!(map.len(), 1);
+
+	// The same request ID from another upstream connection is a different
+	// request.
+	let id_b = map.insert("b", id(1));
+	assert_ne!(id_a, id_b);
+	assert_eq!(map.len(), 2);
+	assert_eq!(map.get(id_a), Some(("a", id(1))));
+	assert_eq!(map.get(id_b), Some(("b", id(1))));
+}
+
+#[test]
+fn low_bit_never_set() {
+	for salt in [0, 1, 0x5555_5555_5555_5555, u64::MAX] {
+		let mut map = RequestIdMap::with_salt(salt);
+		let mut seen = HashSet::new();
+		for request_id in 1..=1000 {
+			let downstream_id = map.insert((), id(request_id));
+			assert_eq!(downstream_id.get() & 1, 0);
+			assert!(seen.insert(downstream_id));
+		}
+	}
+
+	let mut map = RequestIdMap::new();
+	for request_id in 1..=1000 {
+		assert_eq!(map.insert((), id(request_id)).get() & 1, 0);
+	}
+}
+
+#[test]
+fn counter_wrap_around() {
+	let mut map = RequestIdMap::with_salt(u64::MAX - 2);
+	assert_eq!(map.insert((), id(1)), id(u64::MAX - 1));
+
+	// Zero is skipped when the counter wraps.
+	assert_eq!(map.insert((), id(2)), id(2));
+	assert_eq!(map.insert((), id(3)), id(4));
+}
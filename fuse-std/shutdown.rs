@@ -0,0 +1,204 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Graceful shutdown of a running server.
+//!
+//! Stopping a FUSE server cleanly takes several steps: new requests must be
+//! turned away, requests that are already being handled must be allowed to
+//! finish, and then the filesystem must be unmounted so that worker threads
+//! blocked in `recv()` are woken by the closed connection.
+//!
+//! A [`ShutdownToken`] is shared between the serve loop (such as
+//! [`serve_fuse_until`](crate::serve_fuse_until)) and the code that
+//! initiates the shutdown, for example a `SIGTERM` handler:
+//!
+//! ```no_run
+//! # fn unmount() {}
+//! # fn f(token: fuse_std::shutdown::ShutdownToken) {
+//! use std::time::Duration;
+//!
+//! // Stop accepting requests, and wait up to 10 seconds for requests that
+//! // are in flight to finish.
+//! if !token.drain(Duration::from_secs(10)) {
+//! 	// Some handlers are still running, and their replies will be lost.
+//! }
+//!
+//! // Unmounting closes the connection, which ends the serve loop.
+//! unmount();
+//! # }
+//! ```
+
+use alloc::sync::Arc;
+use core::time::Duration;
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+
+use fuse::kernel::fuse_opcode;
+
+// ShutdownToken {{{
+
+/// A handle for stopping a serve loop.
+///
+/// Clones of a `ShutdownToken` refer to the same shutdown state.
+///
+/// Once shutdown has been requested, the serve loop rejects new requests
+/// with [`OsError::INTERRUPTED`]. Requests that release kernel state, such
+/// as `FUSE_FORGET` and `FUSE_RELEASE`, are still handled, because the
+/// kernel sends them while the filesystem is being unmounted.
+///
+/// [`OsError::INTERRUPTED`]: fuse::os::OsError::INTERRUPTED
+#[derive(Clone)]
+pub struct ShutdownToken {
+	inner: Arc<Inner>,
+}
+
+struct Inner {
+	state: Mutex<State>,
+	idle: Condvar,
+}
+
+struct State {
+	shutdown: bool,
+	in_flight: usize,
+}
+
+impl ShutdownToken {
+	/// Creates a new `ShutdownToken`.
+	#[must_use]
+	pub fn new() -> ShutdownToken {
+		Self {
+			inner: Arc::new(Inner {
+				state: Mutex::new(State {
+					shutdown: false,
+					in_flight: 0,
+				}),
+				idle: Condvar::new(),
+			}),
+		}
+	}
+
+	/// Requests that the serve loop stop accepting new requests.
+	///
+	/// This doesn't wait for requests in flight to finish. Use
+	/// [`ShutdownToken::drain`] to wait for them.
+	pub fn shutdown(&self) {
+		self.lock().shutdown = true;
+	}
+
+	/// Returns whether shutdown has been requested.
+	#[must_use]
+	pub fn is_shutdown(&self) -> bool {
+		self.lock().shutdown
+	}
+
+	/// Returns the number of requests currently being handled.
+	#[must_use]
+	pub fn in_flight(&self) -> usize {
+		self.lock().in_flight
+	}
+
+	/// Requests shutdown, then waits for the requests in flight to finish.
+	///
+	/// Returns `true` if all requests finished, or `false` if `timeout`
+	/// elapsed first.
+	#[must_use]
+	pub fn drain(&self, timeout: Duration) -> bool {
+		let mut state = self.lock();
+		state.shutdown = true;
+		let (state, _) = self.inner.idle
+			.wait_timeout_while(state, timeout, |state| state.in_flight > 0)
+			.unwrap_or_else(PoisonError::into_inner);
+		state.in_flight == 0
+	}
+
+	/// Starts handling a request with the given opcode.
+	///
+	/// Returns `None` if shutdown has been requested and the request should
+	/// be rejected. Otherwise the request is counted as in flight until the
+	/// returned guard is dropped.
+	#[must_use]
+	pub fn begin_request(&self, opcode: fuse_opcode) -> Option<InFlight> {
+		let mut state = self.lock();
+		if state.shutdown && !is_teardown(opcode) {
+			return None;
+		}
+		state.in_flight += 1;
+		Some(InFlight {
+			inner: Arc::clone(&self.inner),
+		})
+	}
+
+	fn lock(&self) -> MutexGuard<'_, State> {
+		self.inner.state.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+impl Default for ShutdownToken {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl core::fmt::Debug for ShutdownToken {
+	fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+		let state = self.lock();
+		fmt.debug_struct("ShutdownToken")
+			.field("shutdown", &state.shutdown)
+			.field("in_flight", &state.in_flight)
+			.finish()
+	}
+}
+
+fn is_teardown(opcode: fuse_opcode) -> bool {
+	matches!(
+		opcode,
+		fuse_opcode::FUSE_BATCH_FORGET
+			| fuse_opcode::FUSE_DESTROY
+			| fuse_opcode::FUSE_FORGET
+			| fuse_opcode::FUSE_INTERRUPT
+			| fuse_opcode::FUSE_RELEASE
+			| fuse_opcode::FUSE_RELEASEDIR
+	)
+}
+
+// }}}
+
+// InFlight {{{
+
+/// A request being handled, returned by [`ShutdownToken::begin_request`].
+///
+/// Dropping the `InFlight` marks the request as finished.
+pub struct InFlight {
+	inner: Arc<Inner>,
+}
+
+impl Drop for InFlight {
+	fn drop(&mut self) {
+		let mut state = self.inner.state.lock()
+			.unwrap_or_else(PoisonError::into_inner);
+		state.in_flight -= 1;
+		if state.in_flight == 0 {
+			self.inner.idle.notify_all();
+		}
+	}
+}
+
+impl core::fmt::Debug for InFlight {
+	fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+		fmt.debug_struct("InFlight").finish_non_exhaustive()
+	}
+}
+
+// }}}
@@ -0,0 +1,320 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Serve FUSE requests with the [Tokio] runtime.
+//!
+//! The connection's socket is registered with Tokio's I/O driver, and each
+//! request is handled in its own task. The socket must be in non-blocking
+//! mode, such as `fuse_libc::os::linux::NonblockDevFuse` (which implements
+//! [`AsRawFd`] when `fuse-libc` is built with the `std` feature).
+//!
+//! [Tokio]: https://tokio.rs/
+
+#![warn(
+	// API hygiene
+	clippy::exhaustive_enums,
+	clippy::exhaustive_structs,
+	clippy::must_use_candidate,
+
+	// Panic hygiene
+	clippy::expect_used,
+	clippy::todo,
+	clippy::unimplemented,
+	clippy::unwrap_used,
+
+	// Documentation coverage
+	missing_docs,
+	clippy::missing_panics_doc,
+
+	// Explicit casts
+	clippy::fn_to_numeric_cast_any,
+	clippy::ptr_as_ptr,
+
+	// Optimization
+	clippy::trivially_copy_pass_by_ref,
+
+	// Unused symbols
+	clippy::let_underscore_must_use,
+	clippy::no_effect_underscore_binding,
+	clippy::used_underscore_binding,
+
+	// Leftover debugging
+	clippy::print_stderr,
+	clippy::print_stdout,
+)]
+
+use core::future::Future;
+use core::num::NonZeroU64;
+use std::io;
+use std::os::fd::{AsRawFd, RawFd};
+use std::sync::Arc;
+
+use fuse::io::{
+	AlignedSlice,
+	AlignedSliceMut,
+	AsAlignedSlice,
+	AsAlignedSliceMut,
+};
+use fuse::server;
+use fuse::server::{FuseRequest, RecvError, ServerError};
+use fuse_std::shutdown::ShutdownToken;
+use fuse_std::AlignedBuf;
+use tokio::io::unix::AsyncFd;
+use tokio::io::Interest;
+
+// AsyncFuseConnection {{{
+
+// The socket is owned by the `FuseConnection`, so the `AsyncFd` is registered
+// with a copy of its file descriptor.
+struct SocketFd(RawFd);
+
+impl AsRawFd for SocketFd {
+	fn as_raw_fd(&self) -> RawFd {
+		self.0
+	}
+}
+
+/// A FUSE connection driven by the Tokio runtime.
+///
+/// Requests are received once Tokio reports the socket as readable. Replies
+/// are sent synchronously with [`AsyncFuseConnection::reply`], because
+/// writing a reply to `/dev/fuse` never blocks.
+pub struct AsyncFuseConnection<S> {
+	// Declared before `conn` so that the file descriptor is deregistered
+	// from the I/O driver before the socket is closed.
+	async_fd: AsyncFd<SocketFd>,
+	conn: server::FuseConnection<S>,
+}
+
+impl<S: server::FuseSocket + AsRawFd> AsyncFuseConnection<S> {
+	/// Registers the socket of `conn` with the current Tokio runtime.
+	///
+	/// The socket must be in non-blocking mode, and its `recv` method must
+	/// return [`RecvError::WouldBlock`] when no request is available.
+	///
+	/// # Panics
+	///
+	/// Panics if called outside of a Tokio runtime.
+	pub fn new(
+		conn: server::FuseConnection<S>,
+	) -> io::Result<AsyncFuseConnection<S>> {
+		let fd = SocketFd(conn.socket().as_raw_fd());
+		let async_fd = AsyncFd::with_interest(fd, Interest::READABLE)?;
+		Ok(Self { async_fd, conn })
+	}
+}
+
+impl<S: server::FuseSocket> AsyncFuseConnection<S> {
+	/// Returns the underlying FUSE connection.
+	#[must_use]
+	pub fn connection(&self) -> &server::FuseConnection<S> {
+		&self.conn
+	}
+
+	/// Returns the size of a receive buffer that is large enough to hold
+	/// any request from the client.
+	#[must_use]
+	pub fn recv_buf_len(&self) -> usize {
+		self.conn.recv_buf_len()
+	}
+
+	/// Receive a FUSE request from the client.
+	///
+	/// Returns `Ok(None)` if the connection has been closed by the client,
+	/// or if the Tokio runtime is shutting down.
+	pub async fn recv<'a>(
+		&self,
+		mut buf: AlignedSliceMut<'a>,
+	) -> Result<Option<FuseRequest<'a>>, ServerError<S::Error>> {
+		let Some(recv_len) = self.recv_len(buf.get_mut()).await? else {
+			return Ok(None);
+		};
+		let recv_buf = AlignedSlice::from(buf).truncate(recv_len);
+		Ok(Some(FuseRequest::new(recv_buf, self.conn.layout())?))
+	}
+
+	async fn recv_len(
+		&self,
+		buf: &mut [u8],
+	) -> Result<Option<usize>, ServerError<S::Error>> {
+		loop {
+			let Ok(mut guard) = self.async_fd.readable().await else {
+				// The I/O driver has shut down, so the socket will never
+				// become readable.
+				return Ok(None);
+			};
+			match server::Socket::recv(self.conn.socket(), buf) {
+				Ok(len) => return Ok(Some(len)),
+				Err(RecvError::WouldBlock(_)) => guard.clear_ready(),
+				Err(RecvError::ConnectionClosed(_)) => return Ok(None),
+				Err(err) => return Err(err.into()),
+			}
+		}
+	}
+
+	/// Returns a sender for the reply to a request.
+	///
+	/// See [`FuseConnection::reply`](server::FuseConnection::reply).
+	pub fn reply(
+		&self,
+		request_id: NonZeroU64,
+	) -> server::FuseReplySender<'_, S> {
+		self.conn.reply(request_id)
+	}
+}
+
+// }}}
+
+// AsyncFuseHandlers {{{
+
+/// Asynchronous handlers for FUSE requests.
+///
+/// Each request is dispatched in its own task, so handlers that are waiting
+/// on I/O don't prevent other requests from being processed. Handlers will
+/// typically hold an `Arc<AsyncFuseConnection>` for sending replies.
+pub trait AsyncFuseHandlers: Send + Sync + 'static {
+	/// Handle a single FUSE request.
+	///
+	/// The handler is responsible for sending a reply, unless the request
+	/// is one that doesn't expect a reply (such as `FUSE_FORGET`).
+	fn dispatch(
+		&self,
+		request: FuseRequest<'_>,
+	) -> impl Future<Output = ()> + Send;
+}
+
+/// Adapts [`server::FuseHandlers`] to [`AsyncFuseHandlers`].
+///
+/// Requests are dispatched synchronously within their task. Handlers that
+/// perform blocking I/O should move it to a separate thread, for example
+/// with [`tokio::task::spawn_blocking`], to avoid stalling the runtime.
+pub struct SyncHandlers<H> {
+	handlers: H,
+}
+
+impl<H> SyncHandlers<H> {
+	/// Wraps `handlers` for use with [`serve`].
+	#[must_use]
+	pub fn new(handlers: H) -> SyncHandlers<H> {
+		Self { handlers }
+	}
+}
+
+impl<H> AsyncFuseHandlers for SyncHandlers<H>
+where
+	H: server::FuseHandlers + Send + Sync + 'static,
+{
+	async fn dispatch(&self, request: FuseRequest<'_>) {
+		self.handlers.dispatch(request);
+	}
+}
+
+// }}}
+
+/// Serve FUSE requests, spawning a Tokio task for each request.
+///
+/// Each request is copied into a buffer owned by its task, so the receive
+/// buffer can be reused while earlier requests are still being handled.
+///
+/// Returns when the connection is closed, or if an error is encountered
+/// while receiving a request.
+///
+/// # Panics
+///
+/// Panics if called outside of a Tokio runtime, and on memory allocation
+/// failure.
+pub async fn serve<S, H>(
+	conn: &AsyncFuseConnection<S>,
+	handlers: &Arc<H>,
+) -> Result<(), ServerError<S::Error>>
+where
+	S: server::FuseSocket,
+	H: AsyncFuseHandlers,
+{
+	serve_impl(conn, handlers, None).await
+}
+
+/// Serve FUSE requests, spawning a Tokio task for each request, until
+/// shutdown.
+///
+/// This is equivalent to [`serve`], but stops accepting requests once
+/// shutdown is requested with `token`. Requests received after that point
+/// are rejected as described by [`ShutdownToken`]. Tasks handling earlier
+/// requests are counted as in flight until they finish, so that
+/// [`ShutdownToken::drain`] can wait for them.
+///
+/// Returns when the connection is closed, typically by unmounting the
+/// filesystem after the drain has finished.
+///
+/// # Panics
+///
+/// Panics if called outside of a Tokio runtime, and on memory allocation
+/// failure.
+pub async fn serve_until<S, H>(
+	conn: &AsyncFuseConnection<S>,
+	handlers: &Arc<H>,
+	token: &ShutdownToken,
+) -> Result<(), ServerError<S::Error>>
+where
+	S: server::FuseSocket,
+	H: AsyncFuseHandlers,
+{
+	serve_impl(conn, handlers, Some(token)).await
+}
+
+async fn serve_impl<S, H>(
+	conn: &AsyncFuseConnection<S>,
+	handlers: &Arc<H>,
+	token: Option<&ShutdownToken>,
+) -> Result<(), ServerError<S::Error>>
+where
+	S: server::FuseSocket,
+	H: AsyncFuseHandlers,
+{
+	let layout = conn.connection().layout();
+	let mut recv_buf = AlignedBuf::with_capacity(conn.recv_buf_len());
+	loop {
+		let recv_slice = recv_buf.as_aligned_slice_mut();
+		let Some(request) = conn.recv(recv_slice).await? else {
+			return Ok(());
+		};
+		let opcode = request.header().opcode();
+		let in_flight = match token.map(|t| t.begin_request(opcode)) {
+			None => None,
+			Some(Some(in_flight)) => Some(in_flight),
+			Some(None) => {
+				let reply = conn.reply(request.id());
+				reply.err(fuse::os::OsError::INTERRUPTED)?;
+				continue;
+			},
+		};
+		let request_bytes = request.as_bytes();
+		let request_len = request_bytes.len();
+		let mut buf = AlignedBuf::with_capacity(request_len);
+		buf.as_mut_slice()[..request_len].copy_from_slice(request_bytes);
+
+		let handlers = Arc::clone(handlers);
+		tokio::spawn(async move {
+			let request_buf = buf.as_aligned_slice().truncate(request_len);
+			// The request was successfully decoded from the same bytes
+			// before being copied, so decoding it again can't fail.
+			if let Ok(request) = FuseRequest::new(request_buf, layout) {
+				handlers.dispatch(request).await;
+			}
+			drop(in_flight);
+		});
+	}
+}
@@ -16,6 +16,7 @@
 
 #![allow(
 	clippy::new_without_default,
+	clippy::tabs_in_doc_comments,
 )]
 
 #![warn(
@@ -59,10 +60,21 @@
 
 use std::borrow::Cow;
 use std::cmp;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::ffi::CStr;
 use std::num::NonZeroU64;
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{
+	Arc,
+	Mutex,
+	MutexGuard,
+	OnceLock,
+	PoisonError,
+	RwLock,
+	RwLockReadGuard,
+	RwLockWriteGuard,
+	Weak,
+};
 use std::time::Duration;
 
 use fuse::{
@@ -78,6 +90,7 @@ use fuse::server::{
 	FuseRequest,
 	ServerError,
 };
+use fuse_std::handles::HandleTable;
 
 // Node {{{
 
@@ -95,6 +108,15 @@ pub trait Node: Send + Sync {
 		None
 	}
 
+	/// Returns the cache policy for this node, overriding the filesystem's
+	/// policy.
+	///
+	/// For directories, the policy's negative timeout applies to lookups of
+	/// children that don't exist.
+	fn cache_policy(&self) -> Option<CachePolicy> {
+		None
+	}
+
 	fn getattr(
 		&self,
 		header: &RequestHeader,
@@ -124,11 +146,43 @@ pub trait Node: Send + Sync {
 	) -> Result<StatfsResult, Error> {
 		Ok(StatfsResult::new(fuse::StatfsAttributes::new()))
 	}
+
+	/// Persists pending writes for the mount rooted at this node.
+	///
+	/// This is called on the root node, or on the root of a submount.
+	fn syncfs(
+		&self,
+		header: &RequestHeader,
+		request: server::SyncfsRequest<'_>,
+	) -> Result<SyncfsResult, Error> {
+		Ok(SyncfsResult::new())
+	}
+
+	fn bmap(
+		&self,
+		header: &RequestHeader,
+		request: server::BmapRequest<'_>,
+	) -> Result<BmapResult, Error> {
+		Err(OsError::UNIMPLEMENTED)
+	}
+}
+
+pub struct BmapResult {
+	block: u64,
+}
+
+impl BmapResult {
+	#[must_use]
+	pub fn new(block: u64) -> BmapResult {
+		BmapResult { block }
+	}
 }
 
 pub struct GetattrResult {
 	node_attr: fuse::NodeAttr,
-	pub cache_timeout: Duration,
+
+	/// Overrides the attribute timeout of the node's [`CachePolicy`].
+	pub cache_timeout: Option<Duration>,
 }
 
 impl GetattrResult {
@@ -136,48 +190,48 @@ impl GetattrResult {
 	pub fn new(node_attr: fuse::NodeAttr) -> GetattrResult {
 		GetattrResult {
 			node_attr,
-			cache_timeout: Duration::ZERO,
+			cache_timeout: None,
 		}
 	}
 }
 
 pub struct GetxattrResult {
-	buf: Cow<'static, [u8]>,
+	value: Option<Cow<'static, [u8]>>,
+	size: u32,
 }
 
 impl GetxattrResult {
 	#[must_use]
 	pub fn size(size: u32) -> GetxattrResult {
-		let mut reply = fuse::kernel::fuse_getxattr_out::new();
-		reply.size = size;
-		Self {
-			buf: Cow::Owned(reply.as_bytes().to_owned()),
-		}
+		Self { value: None, size }
 	}
 
 	#[must_use]
 	pub fn value(value: impl Into<Cow<'static, [u8]>>) -> GetxattrResult {
-		GetxattrResult { buf: value.into() }
+		Self {
+			value: Some(value.into()),
+			size: 0,
+		}
 	}
 }
 
 pub struct ListxattrResult {
-	buf: Cow<'static, [u8]>,
+	names: Option<Cow<'static, [u8]>>,
+	size: u32,
 }
 
 impl ListxattrResult {
 	#[must_use]
 	pub fn size(size: u32) -> ListxattrResult {
-		let mut reply = fuse::kernel::fuse_getxattr_out::new();
-		reply.size = size;
-		Self {
-			buf: Cow::Owned(reply.as_bytes().to_owned()),
-		}
+		Self { names: None, size }
 	}
 
 	#[must_use]
 	pub fn names(names: impl Into<Cow<'static, [u8]>>) -> ListxattrResult {
-		ListxattrResult { buf: names.into() }
+		Self {
+			names: Some(names.into()),
+			size: 0,
+		}
 	}
 }
 
@@ -192,6 +246,16 @@ impl StatfsResult {
 	}
 }
 
+#[non_exhaustive]
+pub struct SyncfsResult {}
+
+impl SyncfsResult {
+	#[must_use]
+	pub fn new() -> SyncfsResult {
+		Self {}
+	}
+}
+
 // Node }}}
 
 // Directory {{{
@@ -213,11 +277,23 @@ pub trait Directory: Node {
 pub struct LookupResult {
 	node: Option<(Arc<dyn Node>, fuse::NodeAttr)>,
 	pub generation: u64,
-	pub entry_cache_timeout: Duration,
-	pub attr_cache_timeout: Duration,
+
+	/// Overrides the entry timeout (or negative timeout, if the node wasn't
+	/// found) of the [`CachePolicy`].
+	pub entry_cache_timeout: Option<Duration>,
+
+	/// Overrides the attribute timeout of the found node's [`CachePolicy`].
+	pub attr_cache_timeout: Option<Duration>,
 }
 
 impl LookupResult {
+	/// Returns a result for a node that was found.
+	///
+	/// The node ID is taken from `node_attr`. Nodes are tracked by ID, so if
+	/// a node with the same ID is already known then its lookup count is
+	/// incremented and `node` is discarded. Implementations must assign a
+	/// distinct ID to each node, and must not reuse an ID until the kernel
+	/// has forgotten it.
 	#[must_use]
 	pub fn found(
 		node: Arc<dyn Node>,
@@ -226,8 +302,8 @@ impl LookupResult {
 		Self {
 			node: Some((node, node_attr)),
 			generation: 0,
-			entry_cache_timeout: Duration::ZERO,
-			attr_cache_timeout: Duration::ZERO,
+			entry_cache_timeout: None,
+			attr_cache_timeout: None,
 		}
 	}
 
@@ -236,15 +312,28 @@ impl LookupResult {
 		Self {
 			node: None,
 			generation: 0,
-			entry_cache_timeout: Duration::ZERO,
-			attr_cache_timeout: Duration::ZERO,
+			entry_cache_timeout: None,
+			attr_cache_timeout: None,
+		}
+	}
+
+	/// Returns a result for a node that wasn't found, which the kernel may
+	/// cache for `cache_timeout`.
+	///
+	/// This overrides the negative timeout of the directory's
+	/// [`CachePolicy`].
+	#[must_use]
+	pub fn not_found_cached(cache_timeout: Duration) -> LookupResult {
+		Self {
+			entry_cache_timeout: Some(cache_timeout),
+			..Self::not_found()
 		}
 	}
 }
 
 pub struct OpendirResult {
 	handle: Arc<dyn DirectoryHandle>,
-	pub open_flags: fuse::OpenFlags,
+	pub open_flags: fuse::OpendirResponseFlags,
 }
 
 impl OpendirResult {
@@ -252,7 +341,7 @@ impl OpendirResult {
 	pub fn new(handle: Arc<dyn DirectoryHandle>) -> OpendirResult {
 		Self {
 			handle,
-			open_flags: 0,
+			open_flags: fuse::OpendirResponseFlags::new(),
 		}
 	}
 }
@@ -270,6 +359,10 @@ pub trait DirectoryHandle: Send + Sync {
 		None
 	}
 
+	fn as_list_directory_handle(&self) -> Option<&dyn ListDirectoryHandle> {
+		None
+	}
+
 	#[allow(unused_variables)]
 	fn releasedir(
 		&self,
@@ -321,6 +414,7 @@ pub trait ReaddirplusHandle: ReaddirHandle {
 
 pub struct ReaddirplusResult {
 	entries: Cow<'static, [u8]>,
+	nodes: HashMap<NodeId, Arc<dyn Node>>,
 }
 
 impl ReaddirplusResult {
@@ -328,6 +422,202 @@ impl ReaddirplusResult {
 	pub fn new(entries: impl Into<Cow<'static, [u8]>>) -> ReaddirplusResult {
 		Self {
 			entries: entries.into(),
+			nodes: HashMap::new(),
+		}
+	}
+
+	/// Provides the node for an entry, so that later requests for its node
+	/// ID can be served without first performing a `FUSE_LOOKUP`.
+	///
+	/// Lookup counts are incremented for every entry sent to the kernel.
+	/// Nodes that haven't been provided here, and aren't already known from
+	/// an earlier lookup, are ignored.
+	pub fn add_node(&mut self, node_id: NodeId, node: Arc<dyn Node>) {
+		self.nodes.insert(node_id, node);
+	}
+}
+
+/// A directory handle that lists its entries with a single callback.
+///
+/// With the [`READDIRPLUS_AUTO`] init flag the kernel chooses between
+/// `FUSE_READDIR` and `FUSE_READDIRPLUS` for each request, so a directory
+/// handle must serve both. If a handle doesn't provide a [`ReaddirHandle`]
+/// or [`ReaddirplusHandle`], the [`Filesystem`] serves that request by
+/// calling [`list`](ListDirectoryHandle::list) and encoding the entries in
+/// the request's format.
+///
+/// [`READDIRPLUS_AUTO`]: fuse::FuseInitFlag::READDIRPLUS_AUTO
+pub trait ListDirectoryHandle: DirectoryHandle {
+	/// Pushes the directory's entries following `offset` to `entries`,
+	/// stopping when it is full.
+	fn list(
+		&self,
+		header: &RequestHeader,
+		offset: Option<NonZeroU64>,
+		entries: &mut DirectoryEntries<'_>,
+	) -> Result<(), Error>;
+}
+
+/// An entry pushed to [`DirectoryEntries`].
+pub struct DirectoryEntry<'a> {
+	name: &'a NodeName,
+	offset: NonZeroU64,
+	node_attr: fuse::NodeAttr,
+	node: Option<Arc<dyn Node>>,
+	pub generation: u64,
+
+	/// Overrides the entry timeout of the node's [`CachePolicy`].
+	pub entry_cache_timeout: Option<Duration>,
+
+	/// Overrides the attribute timeout of the node's [`CachePolicy`].
+	pub attr_cache_timeout: Option<Duration>,
+}
+
+impl<'a> DirectoryEntry<'a> {
+	/// Returns an entry for the node with attributes `node_attr`.
+	///
+	/// `FUSE_READDIR` entries contain only the node ID and file type, so
+	/// the other attributes are only needed if
+	/// [`DirectoryEntries::is_readdirplus`] is true.
+	#[must_use]
+	pub fn new(
+		name: &'a NodeName,
+		offset: NonZeroU64,
+		node_attr: fuse::NodeAttr,
+	) -> DirectoryEntry<'a> {
+		Self {
+			name,
+			offset,
+			node_attr,
+			node: None,
+			generation: 0,
+			entry_cache_timeout: None,
+			attr_cache_timeout: None,
+		}
+	}
+
+	/// Provides the entry's node, as with [`ReaddirplusResult::add_node`].
+	pub fn set_node(&mut self, node: Arc<dyn Node>) {
+		self.node = Some(node);
+	}
+}
+
+/// Directory entries being encoded for a `FUSE_READDIR` or
+/// `FUSE_READDIRPLUS` reply.
+pub struct DirectoryEntries<'a> {
+	writer: DirentWriter<'a>,
+	nodes: HashMap<NodeId, Arc<dyn Node>>,
+	cache_policy: CachePolicy,
+}
+
+enum DirentWriter<'a> {
+	Readdir(server::ReaddirEntriesWriter<'a>),
+	Readdirplus(server::ReaddirplusEntriesWriter<'a>),
+	Collect(Vec<CollectedEntry>),
+}
+
+// An entry of a directory listed with `DirectoryEntries::collect()`.
+struct CollectedEntry {
+	name: Vec<u8>,
+	node_attr: fuse::NodeAttr,
+	node: Option<Arc<dyn Node>>,
+	generation: u64,
+	entry_cache_timeout: Option<Duration>,
+	attr_cache_timeout: Option<Duration>,
+}
+
+impl DirectoryEntries<'static> {
+	// Returns entries that collect every pushed entry, with its attributes,
+	// instead of encoding them.
+	fn collect() -> DirectoryEntries<'static> {
+		DirectoryEntries {
+			writer: DirentWriter::Collect(Vec::new()),
+			nodes: HashMap::new(),
+			cache_policy: CachePolicy::new(),
+		}
+	}
+
+	fn into_collected(self) -> Vec<CollectedEntry> {
+		match self.writer {
+			DirentWriter::Collect(collected) => collected,
+			_ => Vec::new(),
+		}
+	}
+}
+
+impl DirectoryEntries<'_> {
+	/// Returns `true` if the entries are for a `FUSE_READDIRPLUS` reply,
+	/// which includes each entry's attributes and cache timeouts.
+	#[must_use]
+	pub fn is_readdirplus(&self) -> bool {
+		matches!(
+			self.writer,
+			DirentWriter::Readdirplus(_) | DirentWriter::Collect(_),
+		)
+	}
+
+	/// Appends an entry, returning `false` if there isn't enough space
+	/// remaining for it.
+	#[must_use]
+	pub fn try_push(&mut self, entry: DirectoryEntry<'_>) -> bool {
+		let node_id = entry.node_attr.node_id();
+		match &mut self.writer {
+			DirentWriter::Readdir(writer) => {
+				let mut dirent = server::ReaddirEntry::new(
+					node_id,
+					entry.name,
+					entry.offset,
+				);
+				let file_mode = entry.node_attr.mode();
+				if let Some(file_type) = fuse::FileType::from_mode(file_mode) {
+					dirent.set_file_type(file_type);
+				}
+				writer.try_push(&dirent).is_ok()
+			},
+			DirentWriter::Readdirplus(writer) => {
+				let policy = entry.node.as_ref()
+					.and_then(|node| node.cache_policy())
+					.unwrap_or(self.cache_policy);
+				let mut out_entry = fuse::Entry::new(entry.node_attr);
+				out_entry.set_generation(entry.generation);
+				out_entry.set_cache_timeout(
+					entry.entry_cache_timeout.unwrap_or(policy.entry_timeout),
+				);
+				out_entry.set_attribute_cache_timeout(
+					entry.attr_cache_timeout.unwrap_or(policy.attr_timeout),
+				);
+				let dirent = server::ReaddirplusEntry::new(
+					entry.name,
+					entry.offset,
+					out_entry,
+				);
+				if writer.try_push(&dirent).is_err() {
+					return false;
+				}
+				if let Some(node) = entry.node {
+					self.nodes.insert(node_id, node);
+				}
+				true
+			},
+			DirentWriter::Collect(collected) => {
+				collected.push(CollectedEntry {
+					name: entry.name.as_bytes().to_vec(),
+					node_attr: entry.node_attr,
+					node: entry.node,
+					generation: entry.generation,
+					entry_cache_timeout: entry.entry_cache_timeout,
+					attr_cache_timeout: entry.attr_cache_timeout,
+				});
+				true
+			},
+		}
+	}
+
+	fn position(&self) -> usize {
+		match &self.writer {
+			DirentWriter::Readdir(writer) => writer.position(),
+			DirentWriter::Readdirplus(writer) => writer.position(),
+			DirentWriter::Collect(_) => 0,
 		}
 	}
 }
@@ -346,7 +636,7 @@ pub trait File: Node {
 
 pub struct OpenResult {
 	handle: Arc<dyn FileHandle>,
-	pub open_flags: fuse::OpenFlags,
+	pub open_flags: fuse::OpenResponseFlags,
 }
 
 impl OpenResult {
@@ -354,7 +644,7 @@ impl OpenResult {
 	pub fn new(handle: Arc<dyn FileHandle>) -> OpenResult {
 		Self {
 			handle,
-			open_flags: 0,
+			open_flags: fuse::OpenResponseFlags::new(),
 		}
 	}
 }
@@ -457,12 +747,147 @@ impl ReadlinkResult {
 
 // Symlink }}}
 
+// CachePolicy {{{
+
+/// Cache timeouts for replies that don't set their own.
+///
+/// A [`Filesystem`] applies its policy to lookup and getattr replies, unless
+/// the node provides its own policy with [`Node::cache_policy`]. All timeouts
+/// default to zero, which disables caching.
+#[derive(Clone, Copy)]
+#[non_exhaustive]
+pub struct CachePolicy {
+	/// How long the kernel may cache a directory entry.
+	pub entry_timeout: Duration,
+
+	/// How long the kernel may cache a node's attributes.
+	pub attr_timeout: Duration,
+
+	/// How long the kernel may cache a lookup that found no node.
+	pub negative_timeout: Duration,
+
+	/// Keep a file's cached data when it's opened, instead of discarding it.
+	///
+	/// This only has an effect if the [`AUTO_INVAL_DATA`] init flag was
+	/// negotiated, in which case the kernel discards cached data when it
+	/// sees the file's modification time change.
+	///
+	/// [`AUTO_INVAL_DATA`]: fuse::FuseInitFlag::AUTO_INVAL_DATA
+	pub auto_invalidate_data: bool,
+}
+
+impl CachePolicy {
+	#[must_use]
+	pub const fn new() -> CachePolicy {
+		Self {
+			entry_timeout: Duration::ZERO,
+			attr_timeout: Duration::ZERO,
+			negative_timeout: Duration::ZERO,
+			auto_invalidate_data: false,
+		}
+	}
+}
+
+// CachePolicy }}}
+
+// InodeAllocator {{{
+
+/// Assigns node IDs and generation numbers to nodes found by lookup.
+///
+/// By default, a [`Filesystem`] uses the node ID in each node's
+/// [`NodeAttr`](fuse::NodeAttr). If an allocator is set with
+/// [`Filesystem::set_inode_allocator`], the filesystem instead assigns an
+/// ID to each distinct `Arc<dyn Node>` when it is first looked up, and
+/// releases the ID after the kernel has forgotten the node.
+pub trait InodeAllocator: Send + Sync {
+	/// Allocates a node ID and generation number for a newly found node.
+	///
+	/// The pair must not have been returned by an earlier call, so that
+	/// clients holding exported handles (such as NFS file handles) never see
+	/// a reused ID with the same generation.
+	fn allocate(&self) -> Result<(NodeId, u64), Error>;
+
+	/// Releases a node ID that was forgotten by the kernel.
+	fn release(&self, node_id: NodeId, generation: u64);
+}
+
+/// An [`InodeAllocator`] that assigns node IDs sequentially.
+///
+/// Released IDs are reused in the order they were released, with their
+/// generation number incremented.
+pub struct SequentialInodeAllocator {
+	state: Mutex<SequentialInodeState>,
+}
+
+struct SequentialInodeState {
+	next_id: Option<NodeId>,
+	released: VecDeque<(NodeId, u64)>,
+}
+
+impl SequentialInodeAllocator {
+	#[must_use]
+	pub fn new() -> SequentialInodeAllocator {
+		Self {
+			state: Mutex::new(SequentialInodeState {
+				next_id: NodeId::new(NodeId::ROOT.get() + 1),
+				released: VecDeque::new(),
+			}),
+		}
+	}
+}
+
+impl InodeAllocator for SequentialInodeAllocator {
+	fn allocate(&self) -> Result<(NodeId, u64), Error> {
+		let mut state = self.state.lock()
+			.unwrap_or_else(PoisonError::into_inner);
+		if let Some(reused) = state.released.pop_front() {
+			return Ok(reused);
+		}
+		let node_id = state.next_id.ok_or(OsError::OVERFLOW)?;
+		state.next_id = node_id.get().checked_add(1).and_then(NodeId::new);
+		Ok((node_id, 0))
+	}
+
+	fn release(&self, node_id: NodeId, generation: u64) {
+		// A generation that would wrap around is retired rather than reused.
+		if let Some(generation) = generation.checked_add(1) {
+			let mut state = self.state.lock()
+				.unwrap_or_else(PoisonError::into_inner);
+			state.released.push_back((node_id, generation));
+		}
+	}
+}
+
+// Node IDs assigned by an `InodeAllocator`, keyed by the address of each
+// node. Lookups and forgets are serialized on this lock so that a node ID is
+// never released while a concurrent lookup is handing it out.
+struct InodeIds {
+	allocator: Box<dyn InodeAllocator>,
+	assigned: Mutex<HashMap<usize, (NodeId, u64)>>,
+}
+
+impl InodeIds {
+	fn lock(&self) -> MutexGuard<'_, HashMap<usize, (NodeId, u64)>> {
+		self.assigned.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+fn node_key(node: &Arc<dyn Node>) -> usize {
+	Arc::as_ptr(node).cast::<()>() as usize
+}
+
+// InodeAllocator }}}
+
 // Filesystem {{{
 
 pub struct Filesystem<'a, S> {
 	conn: &'a server::FuseConnection<S>,
 	nodes: Arc<NodeMap>,
-	handles: Arc<RwLock<HandlesMap>>,
+	file_handles: HandleTable<Arc<dyn FileHandle>>,
+	dir_handles: HandleTable<Arc<dyn DirectoryHandle>>,
+	inode_ids: Option<InodeIds>,
+	cache_policy: CachePolicy,
+	read_only: bool,
 }
 
 impl<'a, S> Filesystem<'a, S> {
@@ -474,8 +899,86 @@ impl<'a, S> Filesystem<'a, S> {
 		Self {
 			conn,
 			nodes: Arc::new(NodeMap::new(root)),
-			handles: Arc::new(RwLock::new(HandlesMap::new())),
+			file_handles: HandleTable::new(),
+			dir_handles: HandleTable::new(),
+			inode_ids: None,
+			cache_policy: CachePolicy::new(),
+			read_only: false,
+		}
+	}
+
+	/// Sets the cache policy for nodes that don't provide their own.
+	pub fn set_cache_policy(&mut self, cache_policy: CachePolicy) {
+		self.cache_policy = cache_policy;
+	}
+
+	/// Sets whether the filesystem rejects requests that would modify it.
+	///
+	/// A read-only filesystem replies to every request for which
+	/// [`fuse_std::read_only::is_mutating`] returns `true` with
+	/// [`OsError::READ_ONLY`], including requests that
+	/// [`dispatch`](Self::dispatch) would otherwise leave to the caller.
+	/// Nodes and handles are never asked to write, even if they support it.
+	pub fn set_read_only(&mut self, read_only: bool) {
+		self.read_only = read_only;
+	}
+
+	fn node_cache_policy(&self, node: &dyn Node) -> CachePolicy {
+		node.cache_policy().unwrap_or(self.cache_policy)
+	}
+
+	fn list_directory(
+		&self,
+		header: &RequestHeader,
+		handle: &dyn ListDirectoryHandle,
+		offset: Option<NonZeroU64>,
+		size: u32,
+		readdirplus: bool,
+	) -> Result<ReaddirplusResult, Error> {
+		let size = cmp::min(size, u32::from(u16::MAX));
+		let mut buf = vec![0u8; size as usize];
+		let writer = if readdirplus {
+			DirentWriter::Readdirplus(server::ReaddirplusEntriesWriter::new(
+				&mut buf,
+			))
+		} else {
+			DirentWriter::Readdir(server::ReaddirEntriesWriter::new(&mut buf))
+		};
+		let mut entries = DirectoryEntries {
+			writer,
+			nodes: HashMap::new(),
+			cache_policy: self.cache_policy,
+		};
+		handle.list(header, offset, &mut entries)?;
+		let buf_len = entries.position();
+		let nodes = entries.nodes;
+		buf.truncate(buf_len);
+		Ok(ReaddirplusResult {
+			entries: buf.into(),
+			nodes,
+		})
+	}
+
+	/// Assigns node IDs with `allocator` rather than using the node IDs in
+	/// the attributes returned by each node.
+	///
+	/// Nodes are identified by the address of their `Arc<dyn Node>`, so a
+	/// directory should return the same `Arc` for repeated lookups of the
+	/// same node. The `FUSE_READDIRPLUS` operation is not supported when an
+	/// allocator is set, because its entries contain node IDs chosen by the
+	/// directory handle.
+	pub fn set_inode_allocator(
+		&mut self,
+		allocator: impl InodeAllocator + 'static,
+	) {
+		let mut assigned = HashMap::new();
+		if let Ok(root) = self.nodes.get(NodeId::ROOT) {
+			assigned.insert(node_key(&root), (NodeId::ROOT, 0));
 		}
+		self.inode_ids = Some(InodeIds {
+			allocator: Box::new(allocator),
+			assigned: Mutex::new(assigned),
+		});
 	}
 }
 
@@ -486,6 +989,10 @@ impl<S: fuse::server::FuseSocket> Filesystem<'_, S> {
 		request: FuseRequest<'_>,
 	) -> Option<Result<(), ServerError<S::Error>>> {
 		use kernel::fuse_opcode as op;
+		if self.read_only && fuse_std::read_only::is_mutating(request) {
+			let send_reply = self.conn.reply(request.id());
+			return Some(send_reply.err(OsError::READ_ONLY).map_err(Into::into));
+		}
 		let opcode = request.header().opcode();
 		if opcode == op::FUSE_READ {
 			return Some(self.read(request));
@@ -495,6 +1002,7 @@ impl<S: fuse::server::FuseSocket> Filesystem<'_, S> {
 		}
 		Some(match request.header().opcode() {
 			op::FUSE_BATCH_FORGET => self.forget(request),
+			op::FUSE_BMAP => self.bmap(request),
 			op::FUSE_FORGET => self.forget(request),
 			op::FUSE_GETATTR => self.getattr(request),
 			op::FUSE_GETXATTR => self.getxattr(request),
@@ -508,16 +1016,50 @@ impl<S: fuse::server::FuseSocket> Filesystem<'_, S> {
 			op::FUSE_RELEASE => self.release(request),
 			op::FUSE_RELEASEDIR => self.releasedir(request),
 			op::FUSE_STATFS => self.statfs(request),
+			op::FUSE_SYNCFS => self.syncfs(request),
 			_ => return None,
 		})
 	}
 
+	fn bmap(
+		&self,
+		request: FuseRequest<'_>,
+	) -> Result<(), ServerError<S::Error>> {
+		let send_reply = self.conn.reply(request.id());
+		let header = request.header();
+		let request = server::BmapRequest::try_from(request)?;
+
+		let node = match self.nodes.get(request.node_id()) {
+			Ok(node) => node,
+			Err(err) => return Ok(send_reply.err(err)?),
+		};
+		let result = match node.bmap(header, request) {
+			Ok(result) => result,
+			Err(err) => return Ok(send_reply.err(err)?),
+		};
+		let reply = server::BmapResponse::new(result.block);
+		Ok(send_reply.ok(&reply)?)
+	}
+
 	fn forget(
 		&self,
 		request: FuseRequest<'_>,
 	) -> Result<(), ServerError<S::Error>> {
 		let request = server::ForgetRequest::try_from(request)?;
-		self.nodes.forget(request.items());
+		let Some(inode_ids) = &self.inode_ids else {
+			self.nodes.forget(request.items(), |_, _| {});
+			return Ok(());
+		};
+		let mut assigned = inode_ids.lock();
+		self.nodes.forget(request.items(), |node_id, node| {
+			let key = node_key(node);
+			if let Some(&(assigned_id, generation)) = assigned.get(&key) {
+				if assigned_id == node_id {
+					assigned.remove(&key);
+					inode_ids.allocator.release(node_id, generation);
+				}
+			}
+		});
 		Ok(())
 	}
 
@@ -528,8 +1070,9 @@ impl<S: fuse::server::FuseSocket> Filesystem<'_, S> {
 		let send_reply = self.conn.reply(request.id());
 		let header = request.header();
 		let request = server::GetattrRequest::try_from(request)?;
+		let node_id = request.node_id();
 
-		let node = match self.nodes.get(request.node_id()) {
+		let node = match self.nodes.get(node_id) {
 			Ok(node) => node,
 			Err(err) => return Ok(send_reply.err(err)?),
 		};
@@ -538,12 +1081,14 @@ impl<S: fuse::server::FuseSocket> Filesystem<'_, S> {
 			Err(err) => return Ok(send_reply.err(err)?),
 		};
 
-		let mut reply = kernel::fuse_attr_out::new();
-		reply.attr = *result.node_attr.raw();
-		split_duration(
-			result.cache_timeout,
-			&mut reply.attr_valid,
-			&mut reply.attr_valid_nsec,
+		let mut node_attr = result.node_attr;
+		if self.inode_ids.is_some() {
+			node_attr.set_node_id(node_id);
+		}
+		let policy = self.node_cache_policy(&*node);
+		let mut reply = server::GetattrResponse::new(node_attr);
+		reply.set_cache_timeout(
+			result.cache_timeout.unwrap_or(policy.attr_timeout),
 		);
 		Ok(send_reply.ok(&reply)?)
 	}
@@ -555,6 +1100,7 @@ impl<S: fuse::server::FuseSocket> Filesystem<'_, S> {
 		let send_reply = self.conn.reply(request.id());
 		let header = request.header();
 		let request = server::GetxattrRequest::try_from(request)?;
+		let requested_size = request.size();
 
 		let node = match self.nodes.get(request.node_id()) {
 			Ok(node) => node,
@@ -564,7 +1110,19 @@ impl<S: fuse::server::FuseSocket> Filesystem<'_, S> {
 			Ok(result) => result,
 			Err(err) => return Ok(send_reply.err(err)?),
 		};
-		Ok(send_reply.ok_buf(&result.buf)?)
+		let reply = match &result.value {
+			Some(value) => {
+				match server::GetxattrResponse::from_value(
+					value,
+					requested_size,
+				) {
+					Ok(reply) => reply,
+					Err(err) => return Ok(send_reply.err(err)?),
+				}
+			},
+			None => server::GetxattrResponse::new_size(result.size),
+		};
+		Ok(send_reply.ok(&reply)?)
 	}
 
 	fn listxattr(
@@ -583,7 +1141,13 @@ impl<S: fuse::server::FuseSocket> Filesystem<'_, S> {
 			Ok(result) => result,
 			Err(err) => return Ok(send_reply.err(err)?),
 		};
-		Ok(send_reply.ok_buf(&result.buf)?)
+		match &result.names {
+			Some(names) => Ok(send_reply.ok_buf(names)?),
+			None => {
+				let reply = server::ListxattrResponse::new_size(result.size);
+				Ok(send_reply.ok(&reply)?)
+			},
+		}
 	}
 
 	fn lookup(
@@ -603,34 +1167,74 @@ impl<S: fuse::server::FuseSocket> Filesystem<'_, S> {
 			None => return Ok(send_reply.err(OsError::NOT_DIRECTORY)?),
 		};
 
-		let mut reply = kernel::fuse_entry_out::new();
 		let result = match parent_dir.lookup(header, request) {
 			Ok(result) => result,
 			Err(err) => return Ok(send_reply.err(err)?),
 		};
 
-		split_duration(
-			result.entry_cache_timeout,
-			&mut reply.entry_valid,
-			&mut reply.entry_valid_nsec,
-		);
+		let Some((node, node_attr)) = result.node else {
+			let policy = self.node_cache_policy(&*parent);
+			let cache_timeout = result.entry_cache_timeout
+				.unwrap_or(policy.negative_timeout);
+			let reply = server::LookupResponse::new_negative(cache_timeout);
+			return Ok(send_reply.ok(&reply)?);
+		};
 
-		if let Some((node, node_attr)) = result.node {
-			let node_id = node_attr.node_id();
-			reply.nodeid = node_id.get();
-			reply.attr = *node_attr.raw();
-			reply.generation = result.generation;
-			split_duration(
-				result.attr_cache_timeout,
-				&mut reply.attr_valid,
-				&mut reply.attr_valid_nsec,
+		let policy = self.node_cache_policy(&*node);
+		let new_entry = |node_attr, generation| {
+			let mut entry = fuse::Entry::new(node_attr);
+			entry.set_generation(generation);
+			entry.set_cache_timeout(
+				result.entry_cache_timeout.unwrap_or(policy.entry_timeout),
 			);
-			send_reply.ok(&reply)?;
+			entry.set_attribute_cache_timeout(
+				result.attr_cache_timeout.unwrap_or(policy.attr_timeout),
+			);
+			entry
+		};
+
+		let Some(inode_ids) = &self.inode_ids else {
+			let node_id = node_attr.node_id();
+			let entry = new_entry(node_attr, result.generation);
+			send_reply.ok(&server::LookupResponse::new(entry))?;
 			self.nodes.add(node_id, node);
 			return Ok(());
-		}
+		};
 
-		Ok(send_reply.ok(&reply)?)
+		let mut assigned = inode_ids.lock();
+		let key = node_key(&node);
+		let (node_id, generation) = match assigned.get(&key) {
+			Some(&assigned_id) => assigned_id,
+			None => match inode_ids.allocator.allocate() {
+				Ok(assigned_id) => assigned_id,
+				Err(err) => {
+					drop(assigned);
+					return Ok(send_reply.err(err)?);
+				},
+			},
+		};
+		let allocated = !assigned.contains_key(&key);
+
+		// The entry is built after the node ID is assigned, so that the
+		// reply's node ID and the ID in its attributes agree.
+		let mut node_attr = node_attr;
+		node_attr.set_node_id(node_id);
+		let entry = new_entry(node_attr, generation);
+
+		// The node is added only if the kernel received the entry. The lock
+		// is held until then, so that a forget of the new node ID can't be
+		// handled before the node is added.
+		if let Err(err) = send_reply.ok(&server::LookupResponse::new(entry)) {
+			if allocated {
+				inode_ids.allocator.release(node_id, generation);
+			}
+			return Err(err.into());
+		}
+		if allocated {
+			assigned.insert(key, (node_id, generation));
+		}
+		self.nodes.add(node_id, node);
+		Ok(())
 	}
 
 	fn open(
@@ -654,14 +1258,21 @@ impl<S: fuse::server::FuseSocket> Filesystem<'_, S> {
 			Err(err) => return Ok(send_reply.err(err)?),
 		};
 
-		let handle_id = {
-			#[allow(clippy::unwrap_used)]
-			self.handles.write().unwrap().open_file(result.handle)
+		let handle_id = match self.file_handles.insert(result.handle) {
+			Ok(handle_id) => handle_id,
+			Err(err) => return Ok(send_reply.err(err)?),
 		};
 
-		let mut reply = kernel::fuse_open_out::new();
-		reply.fh = handle_id;
-		reply.open_flags = result.open_flags;
+		let mut reply = server::OpenResponse::new();
+		reply.set_handle(handle_id);
+		reply.set_flags(result.open_flags);
+		let policy = self.node_cache_policy(&*node);
+		let flags = self.conn.info().flags();
+		if policy.auto_invalidate_data
+			&& flags.get(fuse::FuseInitFlag::AUTO_INVAL_DATA)
+		{
+			reply.set_keep_cache(true);
+		}
 		Ok(send_reply.ok(&reply)?)
 	}
 
@@ -688,14 +1299,14 @@ impl<S: fuse::server::FuseSocket> Filesystem<'_, S> {
 			Err(err) => return Ok(send_reply.err(err)?),
 		};
 
-		let handle_id = {
-			#[allow(clippy::unwrap_used)]
-			self.handles.write().unwrap().open_dir(result.handle)
+		let handle_id = match self.dir_handles.insert(result.handle) {
+			Ok(handle_id) => handle_id,
+			Err(err) => return Ok(send_reply.err(err)?),
 		};
 
-		let mut reply = kernel::fuse_open_out::new();
-		reply.fh = handle_id;
-		reply.open_flags = result.open_flags;
+		let mut reply = server::OpendirResponse::new();
+		reply.set_handle(handle_id);
+		reply.set_flags(result.open_flags);
 		Ok(send_reply.ok(&reply)?)
 	}
 
@@ -707,14 +1318,9 @@ impl<S: fuse::server::FuseSocket> Filesystem<'_, S> {
 		let header = request.header();
 		let request = server::ReadRequest::try_from(request)?;
 
-		let got_file_handle = {
-			#[allow(clippy::unwrap_used)]
-			let handles = self.handles.read().unwrap();
-			handles.get_file(request.node_id(), request.handle()).cloned()
-		};
-		let file_handle = match got_file_handle {
-			Ok(handle) => handle,
-			Err(err) => return Ok(send_reply.err(err)?),
+		let file_handle = match self.file_handles.get(request.handle()) {
+			Some(handle) => handle,
+			None => return Ok(send_reply.err(OsError::INVALID_ARGUMENT)?),
 		};
 
 		let handle = match file_handle.as_read_handle() {
@@ -736,20 +1342,24 @@ impl<S: fuse::server::FuseSocket> Filesystem<'_, S> {
 		let header = request.header();
 		let request = server::ReaddirRequest::try_from(request)?;
 
-		let got_dir_handle = {
-			#[allow(clippy::unwrap_used)]
-			let handles = self.handles.read().unwrap();
-			handles.get_dir(request.node_id(), request.handle()).cloned()
-		};
-		let dir_handle = match got_dir_handle {
-			Ok(handle) => handle,
-			Err(err) => return Ok(send_reply.err(err)?),
-		};
-		let handle = match dir_handle.as_readdir_handle() {
+		let dir_handle = match self.dir_handles.get(request.handle()) {
 			Some(handle) => handle,
-			None => return Ok(send_reply.err(OsError::NOT_SUPPORTED)?),
+			None => return Ok(send_reply.err(OsError::INVALID_ARGUMENT)?),
+		};
+		let result = match dir_handle.as_readdir_handle() {
+			Some(handle) => handle.readdir(header, request),
+			None => match dir_handle.as_list_directory_handle() {
+				Some(handle) => self.list_directory(
+					header,
+					handle,
+					request.offset(),
+					request.size(),
+					false,
+				).map(|result| ReaddirResult::new(result.entries)),
+				None => return Ok(send_reply.err(OsError::NOT_SUPPORTED)?),
+			},
 		};
-		let result = match handle.readdir(header, request) {
+		let result = match result {
 			Ok(result) => result,
 			Err(err) => return Ok(send_reply.err(err)?),
 		};
@@ -763,25 +1373,38 @@ impl<S: fuse::server::FuseSocket> Filesystem<'_, S> {
 		let send_reply = self.conn.reply(request.id());
 		let header = request.header();
 		let request = server::ReaddirplusRequest::try_from(request)?;
+		if self.inode_ids.is_some() {
+			return Ok(send_reply.err(OsError::NOT_SUPPORTED)?);
+		}
 
-		let got_dir_handle = {
-			#[allow(clippy::unwrap_used)]
-			let handles = self.handles.read().unwrap();
-			handles.get_dir(request.node_id(), request.handle()).cloned()
-		};
-		let dir_handle = match got_dir_handle {
-			Ok(handle) => handle,
-			Err(err) => return Ok(send_reply.err(err)?),
-		};
-		let handle = match dir_handle.as_readdirplus_handle() {
+		let dir_handle = match self.dir_handles.get(request.handle()) {
 			Some(handle) => handle,
-			None => return Ok(send_reply.err(OsError::NOT_SUPPORTED)?),
+			None => return Ok(send_reply.err(OsError::INVALID_ARGUMENT)?),
+		};
+		let result = match dir_handle.as_readdirplus_handle() {
+			Some(handle) => handle.readdirplus(header, request),
+			None => match dir_handle.as_list_directory_handle() {
+				Some(handle) => self.list_directory(
+					header,
+					handle,
+					request.offset(),
+					request.size(),
+					true,
+				),
+				None => return Ok(send_reply.err(OsError::NOT_SUPPORTED)?),
+			},
 		};
-		let result = match handle.readdirplus(header, request) {
+		let result = match result {
 			Ok(result) => result,
 			Err(err) => return Ok(send_reply.err(err)?),
 		};
-		Ok(send_reply.ok_buf(&result.entries)?)
+		let entries = match server::ReaddirplusEntries::parse(&result.entries) {
+			Ok(entries) => entries,
+			Err(_) => return Ok(send_reply.err(OsError::INVALID_ARGUMENT)?),
+		};
+		send_reply.ok_buf(entries.as_bytes())?;
+		self.nodes.add_lookups(entries.lookups(), &result.nodes);
+		Ok(())
 	}
 
 	fn readlink(
@@ -804,7 +1427,10 @@ impl<S: fuse::server::FuseSocket> Filesystem<'_, S> {
 			Ok(result) => result,
 			Err(err) => return Ok(send_reply.err(err)?),
 		};
-		Ok(send_reply.ok_buf(result.target.to_bytes())?)
+		match server::ReadlinkResponse::new(result.target.to_bytes()) {
+			Ok(reply) => Ok(send_reply.ok(&reply)?),
+			Err(_) => Ok(send_reply.err(OsError::INVALID_ARGUMENT)?),
+		}
 	}
 
 	fn release(
@@ -814,24 +1440,16 @@ impl<S: fuse::server::FuseSocket> Filesystem<'_, S> {
 		let send_reply = self.conn.reply(request.id());
 		let header = request.header();
 		let request = server::ReleaseRequest::try_from(request)?;
-		let node_id = request.node_id();
 		let handle_id = request.handle();
 
-		#[allow(clippy::unwrap_used)]
-		let mut handles = self.handles.write().unwrap();
-		let file_handle = match handles.get_file(node_id, handle_id) {
-			Ok(handle) => handle,
-			Err(err) => {
-				core::mem::drop(handles);
-				return Ok(send_reply.err(err)?);
-			},
+		let file_handle = match self.file_handles.get(handle_id) {
+			Some(handle) => handle,
+			None => return Ok(send_reply.err(OsError::INVALID_ARGUMENT)?),
 		};
 		if let Err(err) = file_handle.release(header, request) {
-			core::mem::drop(handles);
 			return Ok(send_reply.err(err)?);
 		};
-		handles.close_file(node_id, handle_id);
-		core::mem::drop(handles);
+		self.file_handles.remove(handle_id);
 		Ok(send_reply.ok_empty()?)
 	}
 
@@ -842,24 +1460,16 @@ impl<S: fuse::server::FuseSocket> Filesystem<'_, S> {
 		let send_reply = self.conn.reply(request.id());
 		let header = request.header();
 		let request = server::ReleasedirRequest::try_from(request)?;
-		let node_id = request.node_id();
 		let handle_id = request.handle();
 
-		#[allow(clippy::unwrap_used)]
-		let mut handles = self.handles.write().unwrap();
-		let dir_handle = match handles.get_dir(node_id, handle_id) {
-			Ok(handle) => handle,
-			Err(err) => {
-				core::mem::drop(handles);
-				return Ok(send_reply.err(err)?);
-			},
+		let dir_handle = match self.dir_handles.get(handle_id) {
+			Some(handle) => handle,
+			None => return Ok(send_reply.err(OsError::INVALID_ARGUMENT)?),
 		};
 		if let Err(err) = dir_handle.releasedir(header, request) {
-			core::mem::drop(handles);
 			return Ok(send_reply.err(err)?);
 		};
-		handles.close_dir(node_id, handle_id);
-		core::mem::drop(handles);
+		self.dir_handles.remove(handle_id);
 		Ok(send_reply.ok_empty()?)
 	}
 
@@ -879,39 +1489,49 @@ impl<S: fuse::server::FuseSocket> Filesystem<'_, S> {
 			Ok(result) => result,
 			Err(err) => return Ok(send_reply.err(err)?),
 		};
-		let mut reply = fuse::kernel::fuse_statfs_out::new();
-		reply.st = *result.statfs_attr.raw();
+		let reply = server::StatfsResponse::new(result.statfs_attr);
 		Ok(send_reply.ok(&reply)?)
 	}
 
-	fn write(
+	fn syncfs(
 		&self,
 		request: FuseRequest<'_>,
 	) -> Result<(), ServerError<S::Error>> {
 		let send_reply = self.conn.reply(request.id());
 		let header = request.header();
-		let request = server::WriteRequest::try_from(request)?;
+		let request = server::SyncfsRequest::try_from(request)?;
 
-		let got_file_handle = {
-			#[allow(clippy::unwrap_used)]
-			let handles = self.handles.read().unwrap();
-			handles.get_file(request.node_id(), request.handle()).cloned()
-		};
-		let file_handle = match got_file_handle {
-			Ok(handle) => handle,
+		let node = match self.nodes.get(request.node_id()) {
+			Ok(node) => node,
 			Err(err) => return Ok(send_reply.err(err)?),
 		};
-		let handle = match file_handle.as_write_handle() {
-			Some(handle) => handle,
-			None => return Ok(send_reply.err(OsError::NOT_SUPPORTED)?),
-		};
-		let result = match handle.write(header, request) {
-			Ok(result) => result,
+		if let Err(err) = node.syncfs(header, request) {
+			return Ok(send_reply.err(err)?);
+		}
+		Ok(send_reply.ok(&server::SyncfsResponse::new())?)
+	}
+
+	fn write(
+		&self,
+		request: FuseRequest<'_>,
+	) -> Result<(), ServerError<S::Error>> {
+		let send_reply = self.conn.reply(request.id());
+		let header = request.header();
+		let request = server::WriteRequest::try_from(request)?;
+
+		let file_handle = match self.file_handles.get(request.handle()) {
+			Some(handle) => handle,
+			None => return Ok(send_reply.err(OsError::INVALID_ARGUMENT)?),
+		};
+		let handle = match file_handle.as_write_handle() {
+			Some(handle) => handle,
+			None => return Ok(send_reply.err(OsError::NOT_SUPPORTED)?),
+		};
+		let result = match handle.write(header, request) {
+			Ok(result) => result,
 			Err(err) => return Ok(send_reply.err(err)?),
 		};
-		let mut reply = fuse::kernel::fuse_write_out::new();
-		reply.size = result.size;
-		Ok(send_reply.ok(&reply)?)
+		Ok(send_reply.ok(&server::WriteResponse::new(result.size))?)
 	}
 }
 
@@ -919,41 +1539,86 @@ impl<S: fuse::server::FuseSocket> Filesystem<'_, S> {
 
 // NodeMap {{{
 
+const NODE_MAP_SHARDS: usize = 16;
+
+// Nodes are divided among shards by node ID. Lookups of known nodes take
+// only a read lock on their shard and update the lookup count atomically,
+// so concurrent lookups don't serialize.
 struct NodeMap {
-	nodes: RwLock<HashMap<NodeId, NodeLookup>>,
+	shards: Box<[RwLock<NodeShard>]>,
 }
 
 struct NodeLookup {
 	node: Arc<dyn Node>,
-	lookup_count: u64,
+	lookup_count: AtomicU64,
 }
 
+type NodeShard = HashMap<NodeId, Arc<NodeLookup>>;
+
 impl NodeMap {
 	fn new(root: Arc<dyn Node>) -> NodeMap {
-		let mut nodes = HashMap::new();
-		nodes.insert(NodeId::ROOT, NodeLookup {
+		let shards = (0..NODE_MAP_SHARDS)
+			.map(|_| RwLock::new(HashMap::new()))
+			.collect();
+		let nodes = Self { shards };
+		let root = Arc::new(NodeLookup {
 			node: root,
-			lookup_count: 0,
+			lookup_count: AtomicU64::new(0),
 		});
-		Self {
-			nodes: RwLock::new(nodes),
-		}
+		nodes.write_shard(NodeId::ROOT).insert(NodeId::ROOT, root);
+		nodes
+	}
+
+	fn shard(&self, node_id: NodeId) -> &RwLock<NodeShard> {
+		&self.shards[(node_id.get() % NODE_MAP_SHARDS as u64) as usize]
+	}
+
+	fn read_shard(&self, node_id: NodeId) -> RwLockReadGuard<'_, NodeShard> {
+		self.shard(node_id).read().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	fn write_shard(&self, node_id: NodeId) -> RwLockWriteGuard<'_, NodeShard> {
+		self.shard(node_id).write().unwrap_or_else(PoisonError::into_inner)
 	}
 
 	fn add(&self, node_id: NodeId, node: Arc<dyn Node>) {
+		self.add_lookup(node_id, || Some(node));
+	}
+
+	fn add_lookups(
+		&self,
+		node_ids: impl Iterator<Item = NodeId>,
+		new_nodes: &HashMap<NodeId, Arc<dyn Node>>,
+	) {
+		for node_id in node_ids {
+			self.add_lookup(node_id, || new_nodes.get(&node_id).cloned());
+		}
+	}
+
+	fn add_lookup(
+		&self,
+		node_id: NodeId,
+		new_node: impl FnOnce() -> Option<Arc<dyn Node>>,
+	) {
 		use std::collections::hash_map::Entry;
 
-		#[allow(clippy::unwrap_used)]
-		let mut nodes = self.nodes.write().unwrap();
-		match nodes.entry(node_id) {
-			Entry::Occupied(mut entry) => {
-				entry.get_mut().lookup_count += 1;
+		if let Some(entry) = self.read_shard(node_id).get(&node_id) {
+			entry.lookup_count.fetch_add(1, Ordering::Relaxed);
+			return;
+		}
+
+		let mut shard = self.write_shard(node_id);
+		match shard.entry(node_id) {
+			Entry::Occupied(entry) => {
+				entry.get().lookup_count.fetch_add(1, Ordering::Relaxed);
 			},
 			Entry::Vacant(entry) => {
-				entry.insert(NodeLookup {
-					node,
-					lookup_count: 1,
-				});
+				if let Some(node) = new_node() {
+					entry.insert(Arc::new(NodeLookup {
+						node,
+						lookup_count: AtomicU64::new(1),
+					}));
+				}
 			},
 		}
 	}
@@ -961,26 +1626,44 @@ impl NodeMap {
 	fn forget(
 		&self,
 		forgets: impl Iterator<Item = fuse::server::ForgetRequestItem>,
+		mut on_remove: impl FnMut(NodeId, &Arc<dyn Node>),
 	) {
-		#[allow(clippy::unwrap_used)]
-		let mut nodes = self.nodes.write().unwrap();
 		for forget in forgets {
 			let node_id = forget.node_id();
-			if let Some(entry) = nodes.get_mut(&node_id) {
-				let new_count = entry.lookup_count.saturating_sub(1);
-				if new_count == 0 {
-					nodes.remove(&node_id);
-				} else {
-					entry.lookup_count = new_count;
-				}
+			let entry = match self.read_shard(node_id).get(&node_id) {
+				Some(entry) => entry.clone(),
+				None => continue,
+			};
+			let count = forget.lookup_count();
+			let prev = entry.lookup_count.fetch_update(
+				Ordering::Relaxed,
+				Ordering::Relaxed,
+				|current| Some(current.saturating_sub(count)),
+			);
+			let new_count = match prev {
+				Ok(prev) | Err(prev) => prev.saturating_sub(count),
+			};
+			if new_count > 0 || node_id.is_root() {
+				continue;
+			}
+
+			// A concurrent lookup may have found the node between the
+			// decrement and acquiring the write lock, so recheck the count
+			// before removing it.
+			let mut shard = self.write_shard(node_id);
+			let unused = shard.get(&node_id).is_some_and(|current| {
+				Arc::ptr_eq(current, &entry)
+					&& current.lookup_count.load(Ordering::Relaxed) == 0
+			});
+			if unused {
+				shard.remove(&node_id);
+				on_remove(node_id, &entry.node);
 			}
 		}
 	}
 
 	fn get(&self, node_id: NodeId) -> Result<Arc<dyn Node>, Error> {
-		#[allow(clippy::unwrap_used)]
-		let nodes = self.nodes.read().unwrap();
-		match nodes.get(&node_id) {
+		match self.read_shard(node_id).get(&node_id) {
 			Some(entry) => Ok(entry.node.clone()),
 			None => Err(OsError::INVALID_ARGUMENT),
 		}
@@ -989,76 +1672,6 @@ impl NodeMap {
 
 // NodeMap }}}
 
-// HandlesMap {{{
-
-struct HandlesMap {
-	next_handle_id: u64,
-	open_dirs: HashMap<u64, Arc<dyn DirectoryHandle>>,
-	open_files: HashMap<u64, Arc<dyn FileHandle>>,
-}
-
-impl HandlesMap {
-	fn new() -> HandlesMap {
-		Self {
-			next_handle_id: 1,
-			open_dirs: HashMap::new(),
-			open_files: HashMap::new(),
-		}
-	}
-
-	fn open_file(&mut self, open_file: Arc<dyn FileHandle>) -> u64 {
-		let handle_id = self.next_handle_id;
-		self.next_handle_id += 1;
-		self.open_files.insert(handle_id, open_file);
-		handle_id
-	}
-
-	fn open_dir(&mut self, open_dir: Arc<dyn DirectoryHandle>) -> u64 {
-		let handle_id = self.next_handle_id;
-		self.next_handle_id += 1;
-		self.open_dirs.insert(handle_id, open_dir);
-		handle_id
-	}
-
-	fn get_file(
-		&self,
-		_node_id: NodeId,
-		handle: u64,
-	) -> Result<&Arc<dyn FileHandle>, Error> {
-		match self.open_files.get(&handle) {
-			Some(entry) => Ok(entry),
-			None => Err(OsError::INVALID_ARGUMENT),
-		}
-	}
-
-	fn get_dir(
-		&self,
-		_node_id: NodeId,
-		handle: u64,
-	) -> Result<&Arc<dyn DirectoryHandle>, Error> {
-		match self.open_dirs.get(&handle) {
-			Some(entry) => Ok(entry),
-			None => Err(OsError::INVALID_ARGUMENT),
-		}
-	}
-
-	fn close_file(&mut self, _node_id: NodeId, handle: u64) {
-		use std::collections::hash_map::Entry;
-		if let Entry::Occupied(entry) = self.open_files.entry(handle) {
-			entry.remove();
-		}
-	}
-
-	fn close_dir(&mut self, _node_id: NodeId, handle: u64) {
-		use std::collections::hash_map::Entry;
-		if let Entry::Occupied(entry) = self.open_dirs.entry(handle) {
-			entry.remove();
-		}
-	}
-}
-
-// HandlesMap }}}
-
 // StaticDirectoryHandle {{{
 
 pub struct StaticDirectoryHandle {
@@ -1104,6 +1717,41 @@ impl DirectoryHandle for StaticDirectoryHandle {
 	fn as_readdirplus_handle(&self) -> Option<&dyn ReaddirplusHandle> {
 		Some(self)
 	}
+
+	fn as_list_directory_handle(&self) -> Option<&dyn ListDirectoryHandle> {
+		Some(self)
+	}
+}
+
+impl ListDirectoryHandle for StaticDirectoryHandle {
+	fn list(
+		&self,
+		_header: &RequestHeader,
+		offset: Option<NonZeroU64>,
+		entries: &mut DirectoryEntries<'_>,
+	) -> Result<(), Error> {
+		let start_offset = offset.map_or(0, |o| o.get());
+		if start_offset >= self.entries.len() as u64 {
+			return Ok(());
+		}
+
+		let mut offset = NonZeroU64::MIN.saturating_add(start_offset);
+		for entry in &self.entries[start_offset as usize..] {
+			let mut dirent = DirectoryEntry::new(
+				entry.name,
+				offset,
+				entry.node_attr,
+			);
+			dirent.generation = entry.generation;
+			dirent.entry_cache_timeout = Some(entry.entry_cache_timeout);
+			dirent.attr_cache_timeout = Some(entry.attr_cache_timeout);
+			offset = offset.saturating_add(1);
+			if !entries.try_push(dirent) {
+				break;
+			}
+		}
+		Ok(())
+	}
 }
 
 impl ReaddirHandle for StaticDirectoryHandle {
@@ -1181,14 +1829,829 @@ impl ReaddirplusHandle for StaticDirectoryHandle {
 
 // StaticDirectoryHandle }}}
 
-fn split_duration(d: Duration, out_sec: &mut u64, out_nsec: &mut u32) {
-	if d.is_zero() {
-		return;
+// StreamFileHandle {{{
+
+type GenerateFn = dyn Fn(&RequestHeader) -> Result<Vec<u8>, Error>
+	+ Send
+	+ Sync;
+
+/// A handle to a file whose content is generated when it's read.
+///
+/// Files such as those in `/proc` don't have a fixed size: their content is
+/// generated on demand, and may be different each time the file is opened.
+/// A `StreamFileHandle` generates its content on the first read, and serves
+/// later reads at any offset from that buffer, so each open file sees a
+/// consistent snapshot. The buffer is dropped when the handle is released.
+///
+/// The node should report a size of zero. Handles opened with
+/// [`StreamFileHandle::into_open_result`] use direct I/O, so the kernel
+/// reads until it reaches the end of the content instead of stopping at the
+/// reported size.
+///
+/// ```
+/// use fuse::{Error, RequestHeader};
+/// use fuse::server;
+/// use fuse_vfs::{File, GetattrResult, Node, OpenResult, StreamFileHandle};
+///
+/// struct Uptime {
+/// 	attr: fuse::NodeAttr,
+/// }
+///
+/// impl Node for Uptime {
+/// 	fn as_file(&self) -> Option<&dyn File> {
+/// 		Some(self)
+/// 	}
+///
+/// 	fn getattr(
+/// 		&self,
+/// 		_header: &RequestHeader,
+/// 		_request: server::GetattrRequest<'_>,
+/// 	) -> Result<GetattrResult, Error> {
+/// 		// `self.attr` has a size of zero.
+/// 		Ok(GetattrResult::new(self.attr))
+/// 	}
+/// }
+///
+/// impl File for Uptime {
+/// 	fn open(
+/// 		&self,
+/// 		_header: &RequestHeader,
+/// 		_request: server::OpenRequest<'_>,
+/// 	) -> Result<OpenResult, Error> {
+/// 		let started = std::time::Instant::now();
+/// 		let handle = StreamFileHandle::new(move |_header| {
+/// 			let uptime = started.elapsed().as_secs();
+/// 			Ok(format!("{}\n", uptime).into_bytes())
+/// 		});
+/// 		Ok(handle.into_open_result())
+/// 	}
+/// }
+/// ```
+pub struct StreamFileHandle {
+	generate: Box<GenerateFn>,
+	content: Mutex<Option<Vec<u8>>>,
+}
+
+impl StreamFileHandle {
+	/// Creates a new `StreamFileHandle` that generates its content with
+	/// `generate`.
+	///
+	/// The header passed to `generate` is that of the first read request.
+	/// If `generate` returns an error, the read fails and the next read
+	/// calls `generate` again.
+	#[must_use]
+	pub fn new<F>(generate: F) -> StreamFileHandle
+	where
+		F: Fn(&RequestHeader) -> Result<Vec<u8>, Error>
+			+ Send
+			+ Sync
+			+ 'static,
+	{
+		Self {
+			generate: Box::new(generate),
+			content: Mutex::new(None),
+		}
+	}
+
+	/// Returns an [`OpenResult`] for this handle, with the [`DIRECT_IO`]
+	/// flag set.
+	///
+	/// [`DIRECT_IO`]: fuse::OpenResponseFlag::DIRECT_IO
+	#[must_use]
+	pub fn into_open_result(self) -> OpenResult {
+		let mut result = OpenResult::new(Arc::new(self));
+		result.open_flags.set(fuse::OpenResponseFlag::DIRECT_IO);
+		result
+	}
+
+	fn lock(&self) -> MutexGuard<'_, Option<Vec<u8>>> {
+		self.content.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+impl FileHandle for StreamFileHandle {
+	fn as_read_handle(&self) -> Option<&dyn ReadHandle> {
+		Some(self)
+	}
+
+	fn release(
+		&self,
+		_header: &RequestHeader,
+		_request: server::ReleaseRequest<'_>,
+	) -> Result<ReleaseResult, Error> {
+		*self.lock() = None;
+		Ok(ReleaseResult::new())
+	}
+}
+
+impl ReadHandle for StreamFileHandle {
+	fn read(
+		&self,
+		header: &RequestHeader,
+		request: server::ReadRequest<'_>,
+	) -> Result<ReadResult, Error> {
+		let mut content = self.lock();
+		let content = match &mut *content {
+			Some(content) => content,
+			None => content.insert((self.generate)(header)?),
+		};
+
+		let offset = usize::try_from(request.offset()).unwrap_or(usize::MAX);
+		let start = cmp::min(offset, content.len());
+		let size = request.size() as usize;
+		let end = cmp::min(start.saturating_add(size), content.len());
+		Ok(ReadResult::new(content[start..end].to_vec()))
+	}
+}
+
+// StreamFileHandle }}}
+
+// UnionDirectory {{{
+
+/// Hooks that control how a [`UnionDirectory`] merges and modifies its
+/// layers.
+///
+/// The default methods recognize whiteouts in the format used by Linux's
+/// overlayfs, treat no directory as opaque, and reject copy-up with
+/// [`OsError::READ_ONLY`].
+#[allow(unused_variables)]
+pub trait UnionHooks: Send + Sync {
+	/// Returns whether an entry with attributes `node_attr` is a whiteout,
+	/// which hides entries with the same name in lower layers.
+	///
+	/// The default recognizes a character device with device number 0.
+	fn is_whiteout(&self, node_attr: &fuse::NodeAttr) -> bool {
+		let file_type = fuse::FileType::from_mode(node_attr.mode());
+		file_type == Some(fuse::FileType::CharacterDevice)
+			&& node_attr.device_number() == 0
+	}
+
+	/// Returns whether the directory `node` is opaque, which hides the
+	/// contents of directories with the same name in lower layers.
+	fn is_opaque(&self, node: &dyn Node, node_attr: &fuse::NodeAttr) -> bool {
+		false
+	}
+
+	/// Copies the node `lower` into the upper layer directory `parent`.
+	///
+	/// The `path` contains the names of the node and its parents, starting
+	/// from the root [`UnionDirectory`], so its last element is the name of
+	/// the new entry in `parent`.
+	///
+	/// This is called when a file that exists only in lower layers is opened
+	/// for writing, and for each of its parent directories that doesn't yet
+	/// exist in the upper layer. Directories are copied without their
+	/// entries. The returned node replaces `lower` for later requests.
+	fn copy_up(
+		&self,
+		header: &RequestHeader,
+		parent: &dyn Node,
+		path: &[&NodeName],
+		lower: &Arc<dyn Node>,
+		lower_attr: &fuse::NodeAttr,
+	) -> Result<Arc<dyn Node>, Error> {
+		Err(OsError::READ_ONLY)
+	}
+}
+
+struct DefaultUnionHooks;
+
+impl UnionHooks for DefaultUnionHooks {}
+
+/// A directory that merges the entries of directories in several layers.
+///
+/// Layers are ordered from top to bottom. An entry in a higher layer hides
+/// entries with the same name in lower layers, except that directories
+/// found in several layers are merged into a child `UnionDirectory`.
+/// Whiteouts and opaque directories are recognized by the [`UnionHooks`].
+///
+/// The first layer is the upper layer. Files from lower layers are copied
+/// up into it with [`UnionHooks::copy_up`] when they're opened for writing,
+/// so nodes of lower layers are never modified. Other requests are served by
+/// the highest layer containing the node.
+///
+/// Listing a union directory requires the [`DirectoryHandle`] of each layer
+/// to provide a [`ListDirectoryHandle`]. The merged entries are listed when
+/// the directory is read from the start, and served from that snapshot for
+/// later reads of the same handle.
+///
+/// Layers usually assign node IDs independently, so a union should be served
+/// by a [`Filesystem`] with an [`InodeAllocator`]. Nodes created by a union
+/// are reused while the kernel holds a reference to them, so each is
+/// assigned a single node ID.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # fn f<S>(
+/// # 	conn: &fuse::server::FuseConnection<S>,
+/// # 	upper: Arc<dyn fuse_vfs::Node>,
+/// # 	lower: Arc<dyn fuse_vfs::Node>,
+/// # ) {
+/// use fuse_vfs::{Filesystem, SequentialInodeAllocator, UnionDirectory};
+///
+/// let root = UnionDirectory::new(vec![upper, lower]);
+/// let mut fs = Filesystem::new(conn, root);
+/// fs.set_inode_allocator(SequentialInodeAllocator::new());
+/// # }
+/// ```
+pub struct UnionDirectory {
+	this: Weak<UnionDirectory>,
+	parent: Option<(Arc<UnionDirectory>, Vec<u8>)>,
+	hooks: Arc<dyn UnionHooks>,
+	upper: OnceLock<Arc<dyn Node>>,
+	lowers: Vec<Arc<dyn Node>>,
+	lower_attr: Option<fuse::NodeAttr>,
+	copy_up_lock: Mutex<()>,
+	// Entries are replaced when a name is looked up again after its node was
+	// dropped, so the map holds at most one entry per name ever found.
+	children: Mutex<HashMap<Vec<u8>, Weak<dyn Node>>>,
+}
+
+// A node found in one layer of a `UnionDirectory`.
+#[derive(Clone)]
+struct LayerEntry {
+	node: Option<Arc<dyn Node>>,
+	node_attr: fuse::NodeAttr,
+	upper: bool,
+}
+
+impl UnionDirectory {
+	/// Creates a new `UnionDirectory` of the directories in `layers`, with
+	/// the default [`UnionHooks`].
+	#[must_use]
+	pub fn new(layers: Vec<Arc<dyn Node>>) -> Arc<UnionDirectory> {
+		Self::with_hooks(layers, Arc::new(DefaultUnionHooks))
+	}
+
+	/// Creates a new `UnionDirectory` of the directories in `layers`, with
+	/// the given hooks.
+	#[must_use]
+	pub fn with_hooks(
+		layers: Vec<Arc<dyn Node>>,
+		hooks: Arc<dyn UnionHooks>,
+	) -> Arc<UnionDirectory> {
+		let mut layers = layers.into_iter();
+		let upper = layers.next();
+		Self::new_node(None, hooks, upper, layers.collect(), None)
+	}
+
+	fn new_node(
+		parent: Option<(Arc<UnionDirectory>, Vec<u8>)>,
+		hooks: Arc<dyn UnionHooks>,
+		upper: Option<Arc<dyn Node>>,
+		lowers: Vec<Arc<dyn Node>>,
+		lower_attr: Option<fuse::NodeAttr>,
+	) -> Arc<UnionDirectory> {
+		Arc::new_cyclic(|this| Self {
+			this: this.clone(),
+			parent,
+			hooks,
+			upper: upper.map_or_else(OnceLock::new, OnceLock::from),
+			lowers,
+			lower_attr,
+			copy_up_lock: Mutex::new(()),
+			children: Mutex::new(HashMap::new()),
+		})
+	}
+
+	/// Returns the directory's node in the upper layer, if it exists.
+	#[must_use]
+	pub fn upper(&self) -> Option<&Arc<dyn Node>> {
+		self.upper.get()
+	}
+
+	/// Returns the directory's nodes in lower layers, from top to bottom.
+	#[must_use]
+	pub fn lowers(&self) -> &[Arc<dyn Node>] {
+		&self.lowers
+	}
+
+	/// Returns the directory's node in the upper layer, first copying it up
+	/// along with any of its parents that don't exist in the upper layer.
+	pub fn copy_up(
+		&self,
+		header: &RequestHeader,
+	) -> Result<&Arc<dyn Node>, Error> {
+		let _guard = self.copy_up_lock.lock()
+			.unwrap_or_else(PoisonError::into_inner);
+		if let Some(upper) = self.upper.get() {
+			return Ok(upper);
+		}
+		let (Some((parent, name)), Some(lower), Some(lower_attr)) =
+			(&self.parent, self.lowers.first(), &self.lower_attr)
+		else {
+			return Err(OsError::READ_ONLY);
+		};
+		let parent_upper = parent.copy_up(header)?;
+		let mut path = Vec::new();
+		parent.push_path(&mut path)?;
+		path.push(union_name(name)?);
+		let upper = self.hooks.copy_up(
+			header,
+			&**parent_upper,
+			&path,
+			lower,
+			lower_attr,
+		)?;
+		Ok(self.upper.get_or_init(|| upper))
+	}
+
+	fn push_path<'a>(
+		&'a self,
+		path: &mut Vec<&'a NodeName>,
+	) -> Result<(), Error> {
+		if let Some((parent, name)) = &self.parent {
+			parent.push_path(path)?;
+			path.push(union_name(name)?);
+		}
+		Ok(())
+	}
+
+	fn node(&self) -> Result<&Arc<dyn Node>, Error> {
+		match self.upper.get() {
+			Some(upper) => Ok(upper),
+			None => self.lowers.first().ok_or(OsError::NOT_FOUND),
+		}
+	}
+
+	// Returns the directory's nodes from top to bottom, and whether each is
+	// in the upper layer.
+	fn layers(&self) -> impl Iterator<Item = (&Arc<dyn Node>, bool)> {
+		let upper = self.upper.get().map(|upper| (upper, true));
+		upper.into_iter().chain(self.lowers.iter().map(|lower| (lower, false)))
+	}
+
+	// Adds an entry found in the next lower layer, returning `false` if the
+	// entry hides the layers below it.
+	fn merge(&self, found: &mut Vec<LayerEntry>, entry: LayerEntry) -> bool {
+		if self.hooks.is_whiteout(&entry.node_attr) {
+			return false;
+		}
+		if !is_directory(&entry.node_attr) {
+			if found.is_empty() {
+				found.push(entry);
+			}
+			return false;
+		}
+		let opaque = match &entry.node {
+			Some(node) => self.hooks.is_opaque(&**node, &entry.node_attr),
+			None => false,
+		};
+		found.push(entry);
+		!opaque
+	}
+
+	// Returns the node for the entry `name`, which was found in `found`.
+	//
+	// Returns `None` if a layer didn't provide the node of its entry.
+	fn child(
+		&self,
+		name: &NodeName,
+		found: &[LayerEntry],
+	) -> Option<Arc<dyn Node>> {
+		let first = found.first()?;
+		let is_dir = is_directory(&first.node_attr);
+		let mut children = self.children.lock()
+			.unwrap_or_else(PoisonError::into_inner);
+		if let Some(child) = children.get(name.as_bytes()) {
+			if let Some(child) = child.upgrade() {
+				if child.as_directory().is_some() == is_dir {
+					return Some(child);
+				}
+			}
+		}
+		if first.upper && !is_dir {
+			return first.node.clone();
+		}
+
+		let parent = Some((self.this.upgrade()?, name.as_bytes().to_vec()));
+		let child: Arc<dyn Node> = if is_dir {
+			let mut nodes = Vec::with_capacity(found.len());
+			for entry in found {
+				nodes.push(entry.node.clone()?);
+			}
+			let upper = first.upper.then(|| nodes.remove(0));
+			let lower_attr = found.iter()
+				.find(|entry| !entry.upper)
+				.map(|entry| entry.node_attr);
+			Self::new_node(
+				parent,
+				self.hooks.clone(),
+				upper,
+				nodes,
+				lower_attr,
+			)
+		} else {
+			Arc::new(UnionFile {
+				parent,
+				lower: first.node.clone()?,
+				lower_attr: first.node_attr,
+				upper: OnceLock::new(),
+				copy_up_lock: Mutex::new(()),
+			})
+		};
+		children.insert(name.as_bytes().to_vec(), Arc::downgrade(&child));
+		Some(child)
 	}
-	let (seconds, nanos) = match i64::try_from(d.as_secs()) {
-		Ok(seconds) => (seconds, d.subsec_nanos()),
-		Err(_) => (i64::MAX, 0),
-	};
-	*out_sec = seconds as u64;
-	*out_nsec = nanos;
 }
+
+fn is_directory(node_attr: &fuse::NodeAttr) -> bool {
+	let file_type = fuse::FileType::from_mode(node_attr.mode());
+	file_type == Some(fuse::FileType::Directory)
+}
+
+fn union_name(name: &[u8]) -> Result<&NodeName, Error> {
+	NodeName::from_bytes(name).map_err(|_| OsError::INVALID_ARGUMENT)
+}
+
+impl Node for UnionDirectory {
+	fn as_directory(&self) -> Option<&dyn Directory> {
+		Some(self)
+	}
+
+	fn cache_policy(&self) -> Option<CachePolicy> {
+		self.node().ok()?.cache_policy()
+	}
+
+	fn getattr(
+		&self,
+		header: &RequestHeader,
+		request: server::GetattrRequest<'_>,
+	) -> Result<GetattrResult, Error> {
+		self.node()?.getattr(header, request)
+	}
+
+	fn getxattr(
+		&self,
+		header: &RequestHeader,
+		request: server::GetxattrRequest<'_>,
+	) -> Result<GetxattrResult, Error> {
+		self.node()?.getxattr(header, request)
+	}
+
+	fn listxattr(
+		&self,
+		header: &RequestHeader,
+		request: server::ListxattrRequest<'_>,
+	) -> Result<ListxattrResult, Error> {
+		self.node()?.listxattr(header, request)
+	}
+
+	fn statfs(
+		&self,
+		header: &RequestHeader,
+		request: server::StatfsRequest<'_>,
+	) -> Result<StatfsResult, Error> {
+		self.node()?.statfs(header, request)
+	}
+
+	fn syncfs(
+		&self,
+		header: &RequestHeader,
+		request: server::SyncfsRequest<'_>,
+	) -> Result<SyncfsResult, Error> {
+		self.node()?.syncfs(header, request)
+	}
+}
+
+impl Directory for UnionDirectory {
+	fn lookup(
+		&self,
+		header: &RequestHeader,
+		request: server::LookupRequest<'_>,
+	) -> Result<LookupResult, Error> {
+		let mut found = Vec::new();
+		let mut top = None;
+		for (layer, upper) in self.layers() {
+			let Some(dir) = layer.as_directory() else {
+				continue;
+			};
+			let result = match dir.lookup(header, request) {
+				Ok(result) => result,
+				Err(err) if err == OsError::NOT_FOUND => continue,
+				Err(err) => return Err(err),
+			};
+			let Some((node, node_attr)) = result.node else {
+				continue;
+			};
+			let was_empty = found.is_empty();
+			let more = self.merge(&mut found, LayerEntry {
+				node: Some(node),
+				node_attr,
+				upper,
+			});
+			if was_empty && !found.is_empty() {
+				top = Some((
+					result.generation,
+					result.entry_cache_timeout,
+					result.attr_cache_timeout,
+				));
+			}
+			if !more {
+				break;
+			}
+		}
+
+		let Some((generation, entry_timeout, attr_timeout)) = top else {
+			return Ok(LookupResult::not_found());
+		};
+		let Some(node) = self.child(request.name(), &found) else {
+			return Ok(LookupResult::not_found());
+		};
+		let mut result = LookupResult::found(node, found[0].node_attr);
+		result.generation = generation;
+		result.entry_cache_timeout = entry_timeout;
+		result.attr_cache_timeout = attr_timeout;
+		Ok(result)
+	}
+
+	fn opendir(
+		&self,
+		header: &RequestHeader,
+		request: server::OpendirRequest<'_>,
+	) -> Result<OpendirResult, Error> {
+		let Some(dir) = self.this.upgrade() else {
+			return Err(OsError::NOT_FOUND);
+		};
+		let mut handles = Vec::new();
+		for (layer, upper) in self.layers() {
+			let Some(layer) = layer.as_directory() else {
+				continue;
+			};
+			handles.push((layer.opendir(header, request)?.handle, upper));
+		}
+		Ok(OpendirResult::new(Arc::new(UnionDirectoryHandle {
+			dir,
+			handles,
+			entries: Mutex::new(None),
+		})))
+	}
+}
+
+// UnionDirectory }}}
+
+// UnionDirectoryHandle {{{
+
+struct UnionDirectoryHandle {
+	dir: Arc<UnionDirectory>,
+	// The handle of each layer, and whether it's in the upper layer.
+	handles: Vec<(Arc<dyn DirectoryHandle>, bool)>,
+	entries: Mutex<Option<Vec<UnionEntry>>>,
+}
+
+struct UnionEntry {
+	name: Vec<u8>,
+	found: Vec<LayerEntry>,
+	generation: u64,
+	entry_cache_timeout: Option<Duration>,
+	attr_cache_timeout: Option<Duration>,
+}
+
+impl UnionDirectoryHandle {
+	fn merge_entries(
+		&self,
+		header: &RequestHeader,
+	) -> Result<Vec<UnionEntry>, Error> {
+		let mut names = Vec::new();
+		let mut seen = HashSet::new();
+		let mut layers = Vec::with_capacity(self.handles.len());
+		for (handle, upper) in &self.handles {
+			let Some(list_handle) = handle.as_list_directory_handle() else {
+				return Err(OsError::NOT_SUPPORTED);
+			};
+			let mut entries = DirectoryEntries::collect();
+			list_handle.list(header, None, &mut entries)?;
+			let mut layer = HashMap::new();
+			for entry in entries.into_collected() {
+				if seen.insert(entry.name.clone()) {
+					names.push(entry.name.clone());
+				}
+				layer.entry(entry.name.clone()).or_insert(entry);
+			}
+			layers.push((layer, *upper));
+		}
+
+		let mut merged = Vec::new();
+		for name in names {
+			let mut found = Vec::new();
+			let mut top = None;
+			for (layer, upper) in &mut layers {
+				let Some(entry) = layer.remove(&name) else {
+					continue;
+				};
+				let was_empty = found.is_empty();
+				let more = self.dir.merge(&mut found, LayerEntry {
+					node: entry.node,
+					node_attr: entry.node_attr,
+					upper: *upper,
+				});
+				if was_empty && !found.is_empty() {
+					top = Some((
+						entry.generation,
+						entry.entry_cache_timeout,
+						entry.attr_cache_timeout,
+					));
+				}
+				if !more {
+					break;
+				}
+			}
+			let Some((generation, entry_timeout, attr_timeout)) = top else {
+				continue;
+			};
+			merged.push(UnionEntry {
+				name,
+				found,
+				generation,
+				entry_cache_timeout: entry_timeout,
+				attr_cache_timeout: attr_timeout,
+			});
+		}
+		Ok(merged)
+	}
+}
+
+impl DirectoryHandle for UnionDirectoryHandle {
+	fn as_list_directory_handle(&self) -> Option<&dyn ListDirectoryHandle> {
+		Some(self)
+	}
+
+	fn releasedir(
+		&self,
+		header: &RequestHeader,
+		request: server::ReleasedirRequest<'_>,
+	) -> Result<ReleasedirResult, Error> {
+		let mut result = Ok(ReleasedirResult::new());
+		for (handle, _) in &self.handles {
+			if let Err(err) = handle.releasedir(header, request) {
+				if result.is_ok() {
+					result = Err(err);
+				}
+			}
+		}
+		result
+	}
+}
+
+impl ListDirectoryHandle for UnionDirectoryHandle {
+	fn list(
+		&self,
+		header: &RequestHeader,
+		offset: Option<NonZeroU64>,
+		entries: &mut DirectoryEntries<'_>,
+	) -> Result<(), Error> {
+		let mut merged = self.entries.lock()
+			.unwrap_or_else(PoisonError::into_inner);
+		let merged = match (&mut *merged, offset) {
+			(Some(merged), Some(_)) => merged,
+			(merged, _) => merged.insert(self.merge_entries(header)?),
+		};
+
+		let start_offset = offset.map_or(0, |o| o.get());
+		let start = usize::try_from(start_offset).unwrap_or(usize::MAX);
+		let mut offset = NonZeroU64::MIN.saturating_add(start_offset);
+		for entry in merged.iter().skip(start) {
+			let entry_offset = offset;
+			offset = offset.saturating_add(1);
+			let Ok(name) = NodeName::from_bytes(&entry.name) else {
+				continue;
+			};
+			let node_attr = entry.found[0].node_attr;
+			let mut dirent = DirectoryEntry::new(name, entry_offset, node_attr);
+			dirent.generation = entry.generation;
+			dirent.entry_cache_timeout = entry.entry_cache_timeout;
+			dirent.attr_cache_timeout = entry.attr_cache_timeout;
+			let is_dot = name == NodeName::DOT || name == NodeName::DOT_DOT;
+			if entries.is_readdirplus() && !is_dot {
+				if let Some(node) = self.dir.child(name, &entry.found) {
+					dirent.set_node(node);
+				}
+			}
+			if !entries.try_push(dirent) {
+				break;
+			}
+		}
+		Ok(())
+	}
+}
+
+// UnionDirectoryHandle }}}
+
+// UnionFile {{{
+
+// A node from a lower layer of a `UnionDirectory` that isn't a directory,
+// which is copied up to the upper layer when it's opened for writing.
+struct UnionFile {
+	parent: Option<(Arc<UnionDirectory>, Vec<u8>)>,
+	lower: Arc<dyn Node>,
+	lower_attr: fuse::NodeAttr,
+	upper: OnceLock<Arc<dyn Node>>,
+	copy_up_lock: Mutex<()>,
+}
+
+impl UnionFile {
+	fn node(&self) -> &Arc<dyn Node> {
+		self.upper.get().unwrap_or(&self.lower)
+	}
+
+	fn copy_up(&self, header: &RequestHeader) -> Result<&Arc<dyn Node>, Error> {
+		let _guard = self.copy_up_lock.lock()
+			.unwrap_or_else(PoisonError::into_inner);
+		if let Some(upper) = self.upper.get() {
+			return Ok(upper);
+		}
+		let Some((parent, name)) = &self.parent else {
+			return Err(OsError::READ_ONLY);
+		};
+		let parent_upper = parent.copy_up(header)?;
+		let mut path = Vec::new();
+		parent.push_path(&mut path)?;
+		path.push(union_name(name)?);
+		let upper = parent.hooks.copy_up(
+			header,
+			&**parent_upper,
+			&path,
+			&self.lower,
+			&self.lower_attr,
+		)?;
+		Ok(self.upper.get_or_init(|| upper))
+	}
+}
+
+impl Node for UnionFile {
+	fn as_file(&self) -> Option<&dyn File> {
+		self.node().as_file()?;
+		Some(self)
+	}
+
+	fn as_symlink(&self) -> Option<&dyn Symlink> {
+		self.node().as_symlink()
+	}
+
+	fn cache_policy(&self) -> Option<CachePolicy> {
+		self.node().cache_policy()
+	}
+
+	fn getattr(
+		&self,
+		header: &RequestHeader,
+		request: server::GetattrRequest<'_>,
+	) -> Result<GetattrResult, Error> {
+		self.node().getattr(header, request)
+	}
+
+	fn getxattr(
+		&self,
+		header: &RequestHeader,
+		request: server::GetxattrRequest<'_>,
+	) -> Result<GetxattrResult, Error> {
+		self.node().getxattr(header, request)
+	}
+
+	fn listxattr(
+		&self,
+		header: &RequestHeader,
+		request: server::ListxattrRequest<'_>,
+	) -> Result<ListxattrResult, Error> {
+		self.node().listxattr(header, request)
+	}
+
+	fn statfs(
+		&self,
+		header: &RequestHeader,
+		request: server::StatfsRequest<'_>,
+	) -> Result<StatfsResult, Error> {
+		self.node().statfs(header, request)
+	}
+
+	fn bmap(
+		&self,
+		header: &RequestHeader,
+		request: server::BmapRequest<'_>,
+	) -> Result<BmapResult, Error> {
+		self.node().bmap(header, request)
+	}
+}
+
+impl File for UnionFile {
+	fn open(
+		&self,
+		header: &RequestHeader,
+		request: server::OpenRequest<'_>,
+	) -> Result<OpenResult, Error> {
+		let flags = request.open_flags();
+		let access_mode = flags.access_mode();
+		let read_only = access_mode == Some(fuse::OpenAccessMode::ReadOnly);
+		let node = if read_only && !flags.truncate() {
+			self.node()
+		} else {
+			self.copy_up(header)?
+		};
+		match node.as_file() {
+			Some(file) => file.open(header, request),
+			None => Err(OsError::NOT_SUPPORTED),
+		}
+	}
+}
+
+// UnionFile }}}
@@ -0,0 +1,294 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::mem::size_of;
+use std::sync::{Arc, Mutex, Weak};
+
+use fuse::kernel;
+use fuse::kernel::fuse_opcode;
+use fuse::os::OsError;
+use fuse::server;
+use fuse::testing::{RequestBuilder, SocketPair};
+use fuse::{Error, NodeAttr, NodeId, RequestHeader};
+
+use fuse_vfs::{
+	Directory,
+	Filesystem,
+	GetattrResult,
+	InodeAllocator,
+	LookupResult,
+	Node,
+	OpendirResult,
+	SequentialInodeAllocator,
+};
+
+struct TestFile;
+
+impl Node for TestFile {
+	fn getattr(
+		&self,
+		_header: &RequestHeader,
+		_request: server::GetattrRequest<'_>,
+	) -> Result<GetattrResult, Error> {
+		Ok(GetattrResult::new(NodeAttr::new(NodeId::new(100).unwrap())))
+	}
+}
+
+// A root directory containing a file named "file", and a link to itself
+// named "root".
+struct TestDir {
+	this: Weak<TestDir>,
+	file: Arc<dyn Node>,
+}
+
+impl TestDir {
+	fn new() -> Arc<TestDir> {
+		Arc::new_cyclic(|this| TestDir {
+			this: this.clone(),
+			file: Arc::new(TestFile),
+		})
+	}
+}
+
+impl Node for TestDir {
+	fn as_directory(&self) -> Option<&dyn Directory> {
+		Some(self)
+	}
+
+	fn getattr(
+		&self,
+		_header: &RequestHeader,
+		_request: server::GetattrRequest<'_>,
+	) -> Result<GetattrResult, Error> {
+		Ok(GetattrResult::new(NodeAttr::new(NodeId::ROOT)))
+	}
+}
+
+impl Directory for TestDir {
+	fn lookup(
+		&self,
+		_header: &RequestHeader,
+		request: server::LookupRequest<'_>,
+	) -> Result<LookupResult, Error> {
+		let (node, node_id): (Arc<dyn Node>, _) =
+			match request.name().as_bytes() {
+				b"file" => (self.file.clone(), NodeId::new(100).unwrap()),
+				b"root" => (self.this.upgrade().unwrap(), NodeId::ROOT),
+				_ => return Ok(LookupResult::not_found()),
+			};
+		Ok(LookupResult::found(node, NodeAttr::new(node_id)))
+	}
+
+	fn opendir(
+		&self,
+		_header: &RequestHeader,
+		_request: server::OpendirRequest<'_>,
+	) -> Result<OpendirResult, Error> {
+		Err(OsError::UNIMPLEMENTED)
+	}
+}
+
+#[derive(Default)]
+struct Recording {
+	allocated: Mutex<Vec<(u64, u64)>>,
+	released: Mutex<Vec<(u64, u64)>>,
+}
+
+struct RecordingAllocator {
+	inner: SequentialInodeAllocator,
+	recording: Arc<Recording>,
+}
+
+impl InodeAllocator for RecordingAllocator {
+	fn allocate(&self) -> Result<(NodeId, u64), Error> {
+		let (node_id, generation) = self.inner.allocate()?;
+		let mut allocated = self.recording.allocated.lock().unwrap();
+		allocated.push((node_id.get(), generation));
+		Ok((node_id, generation))
+	}
+
+	fn release(&self, node_id: NodeId, generation: u64) {
+		let mut released = self.recording.released.lock().unwrap();
+		released.push((node_id.get(), generation));
+		self.inner.release(node_id, generation);
+	}
+}
+
+enum Request {
+	Lookup(&'static str),
+	Forget(u64, u64),
+}
+
+fn layout() -> server::FuseLayout {
+	let mut init_out = kernel::fuse_init_out::new();
+	init_out.major = kernel::FUSE_KERNEL_VERSION;
+	init_out.minor = kernel::FUSE_KERNEL_MINOR_VERSION;
+	server::FuseLayout::new(&init_out).unwrap()
+}
+
+// Serves the requests with an allocator-backed filesystem, returning the
+// result of each dispatched request and the entries of its replies.
+fn serve(
+	reply_buf: &mut [u8],
+	recording: &Arc<Recording>,
+	requests: &[Request],
+) -> (Vec<bool>, Vec<kernel::fuse_entry_out>) {
+	let mut request_buf = [0u8; 4096];
+	let pair = SocketPair::new(&mut request_buf, reply_buf);
+	let kernel_socket = pair.kernel();
+
+	let mut builder = RequestBuilder::new(layout());
+	let mut buf = fuse::io::MinReadBuffer::new();
+	let mut init_in = kernel::fuse_init_in::new();
+	init_in.major = kernel::FUSE_KERNEL_VERSION;
+	init_in.minor = kernel::FUSE_KERNEL_MINOR_VERSION;
+	let init = builder.init(buf.as_aligned_slice_mut(), &init_in).unwrap();
+	kernel_socket.push_request(init.get()).unwrap();
+
+	for (ii, request) in requests.iter().enumerate() {
+		builder.set_request_id(ii as u64 + 2);
+		let buf = buf.as_aligned_slice_mut();
+		let encoded = match request {
+			Request::Lookup(name) => {
+				let name = format!("{name}\0");
+				let body: &[&[u8]] = &[name.as_bytes()];
+				builder.build(buf, fuse_opcode::FUSE_LOOKUP, 1, body)
+			},
+			Request::Forget(node_id, nlookup) => {
+				let mut forget_in = kernel::fuse_forget_in::new();
+				forget_in.nlookup = *nlookup;
+				let body: &[&[u8]] = &[forget_in.as_bytes()];
+				builder.build(buf, fuse_opcode::FUSE_FORGET, *node_id, body)
+			},
+		};
+		kernel_socket.push_request(encoded.unwrap().get()).unwrap();
+	}
+
+	let conn = server::FuseConnection::connect(pair.server(), |_, _| {})
+		.unwrap();
+	let mut reply = [0u8; 512];
+	kernel_socket.pop_reply(&mut reply).unwrap();
+
+	let mut fs = Filesystem::new(&conn, TestDir::new());
+	fs.set_inode_allocator(RecordingAllocator {
+		inner: SequentialInodeAllocator::new(),
+		recording: recording.clone(),
+	});
+
+	let mut results = Vec::new();
+	while let Some(request) = conn.recv(buf.as_aligned_slice_mut()).unwrap() {
+		results.push(fs.dispatch(request).unwrap().is_ok());
+	}
+
+	let mut entries = Vec::new();
+	let header_len = size_of::<kernel::fuse_out_header>();
+	let entry_len = size_of::<kernel::fuse_entry_out>();
+	while let Ok(reply_len) = kernel_socket.pop_reply(&mut reply) {
+		assert_eq!(reply_len, header_len + entry_len);
+		let entry = unsafe {
+			reply[header_len..]
+				.as_ptr()
+				.cast::<kernel::fuse_entry_out>()
+				.read_unaligned()
+		};
+		entries.push(entry);
+	}
+	(results, entries)
+}
+
+#[test]
+fn lookup_assigns_node_ids() {
+	let recording = Arc::new(Recording::default());
+	let (_, entries) = serve(&mut [0u8; 1024], &recording, &[
+		Request::Lookup("file"),
+		Request::Lookup("file"),
+	]);
+
+	assert_eq!(entries.len(), 2);
+	for entry in &entries {
+		assert_eq!(entry.nodeid, 2);
+		assert_eq!(entry.attr.ino, 2);
+		assert_eq!(entry.generation, 0);
+	}
+	assert_eq!(*recording.allocated.lock().unwrap(), [(2, 0)]);
+}
+
+#[test]
+fn lookup_root_is_preregistered() {
+	let recording = Arc::new(Recording::default());
+	let (_, entries) = serve(&mut [0u8; 1024], &recording, &[
+		Request::Lookup("root"),
+		Request::Forget(1, 1),
+	]);
+
+	assert_eq!(entries.len(), 1);
+	assert_eq!(entries[0].nodeid, NodeId::ROOT.get());
+	assert_eq!(entries[0].attr.ino, NodeId::ROOT.get());
+	assert_eq!(entries[0].generation, 0);
+	assert!(recording.allocated.lock().unwrap().is_empty());
+	assert!(recording.released.lock().unwrap().is_empty());
+}
+
+#[test]
+fn forget_releases_node_id() {
+	let recording = Arc::new(Recording::default());
+	let (_, entries) = serve(&mut [0u8; 1024], &recording, &[
+		Request::Lookup("file"),
+		Request::Lookup("file"),
+		Request::Forget(2, 1),
+		Request::Lookup("file"),
+		Request::Forget(2, 2),
+	]);
+
+	// The node ID isn't released until every lookup has been forgotten.
+	assert_eq!(entries.len(), 3);
+	for entry in &entries {
+		assert_eq!((entry.nodeid, entry.generation), (2, 0));
+	}
+	assert_eq!(*recording.allocated.lock().unwrap(), [(2, 0)]);
+	assert_eq!(*recording.released.lock().unwrap(), [(2, 0)]);
+}
+
+#[test]
+fn forgotten_node_id_reused_with_new_generation() {
+	let recording = Arc::new(Recording::default());
+	let (_, entries) = serve(&mut [0u8; 1024], &recording, &[
+		Request::Lookup("file"),
+		Request::Forget(2, 1),
+		Request::Lookup("file"),
+	]);
+
+	assert_eq!(entries.len(), 2);
+	assert_eq!((entries[0].nodeid, entries[0].generation), (2, 0));
+	assert_eq!((entries[1].nodeid, entries[1].generation), (2, 1));
+	assert_eq!(entries[1].attr.ino, 2);
+	assert_eq!(*recording.allocated.lock().unwrap(), [(2, 0), (2, 1)]);
+}
+
+#[test]
+fn lookup_send_error_releases_node_id() {
+	// Large enough for the `FUSE_INIT` reply, but not for a lookup entry.
+	let mut reply_buf = [0u8; 100];
+	let recording = Arc::new(Recording::default());
+	let (results, entries) = serve(&mut reply_buf, &recording, &[
+		Request::Lookup("file"),
+	]);
+
+	assert_eq!(results, [false]);
+	assert!(entries.is_empty());
+	assert_eq!(*recording.allocated.lock().unwrap(), [(2, 0)]);
+	assert_eq!(*recording.released.lock().unwrap(), [(2, 0)]);
+}
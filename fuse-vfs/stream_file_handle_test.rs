@@ -0,0 +1,342 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+
+use core::mem::size_of;
+use core::num::NonZeroI32;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use fuse::io::MinReadBuffer;
+use fuse::kernel;
+use fuse::kernel::fuse_opcode;
+use fuse::os::OsError;
+use fuse::server;
+use fuse::testing::{KernelSocket, RequestBuilder, ServerSocket, SocketPair};
+use fuse::{Error, FileType, NodeAttr, NodeId, RequestHeader};
+
+use fuse_vfs::{
+	Directory,
+	File,
+	FileHandle,
+	Filesystem,
+	GetattrResult,
+	LookupResult,
+	Node,
+	OpenResult,
+	OpendirResult,
+	ReadHandle,
+	SequentialInodeAllocator,
+	StreamFileHandle,
+};
+
+// Counts calls to the content generator, and fails them on request.
+#[derive(Default)]
+struct Generator {
+	calls: AtomicUsize,
+	fail: AtomicBool,
+}
+
+impl Generator {
+	fn handle(self: &Arc<Self>) -> StreamFileHandle {
+		let generator = self.clone();
+		StreamFileHandle::new(move |_header| {
+			let call = generator.calls.fetch_add(1, Ordering::SeqCst) + 1;
+			if generator.fail.load(Ordering::SeqCst) {
+				return Err(OsError::UNAVAILABLE);
+			}
+			Ok(format!("content {call}\n").into_bytes())
+		})
+	}
+
+	fn calls(&self) -> usize {
+		self.calls.load(Ordering::SeqCst)
+	}
+}
+
+// A root directory containing a stream file named "stream".
+struct TestDir {
+	stream: Arc<dyn Node>,
+}
+
+impl Node for TestDir {
+	fn as_directory(&self) -> Option<&dyn Directory> {
+		Some(self)
+	}
+
+	fn getattr(
+		&self,
+		_header: &RequestHeader,
+		_request: server::GetattrRequest<'_>,
+	) -> Result<GetattrResult, Error> {
+		Ok(GetattrResult::new(NodeAttr::new(NodeId::ROOT)))
+	}
+}
+
+impl Directory for TestDir {
+	fn lookup(
+		&self,
+		_header: &RequestHeader,
+		request: server::LookupRequest<'_>,
+	) -> Result<LookupResult, Error> {
+		if request.name().as_bytes() != b"stream" {
+			return Ok(LookupResult::not_found());
+		}
+		let mut attr = NodeAttr::new(NodeId::new(2).unwrap());
+		attr.set_mode(FileType::Regular.as_mode() | 0o444);
+		Ok(LookupResult::found(self.stream.clone(), attr))
+	}
+
+	fn opendir(
+		&self,
+		_header: &RequestHeader,
+		_request: server::OpendirRequest<'_>,
+	) -> Result<OpendirResult, Error> {
+		Err(OsError::UNIMPLEMENTED)
+	}
+}
+
+struct StreamFile(Arc<Generator>);
+
+impl Node for StreamFile {
+	fn as_file(&self) -> Option<&dyn File> {
+		Some(self)
+	}
+
+	fn getattr(
+		&self,
+		_header: &RequestHeader,
+		_request: server::GetattrRequest<'_>,
+	) -> Result<GetattrResult, Error> {
+		Ok(GetattrResult::new(NodeAttr::new(NodeId::new(2).unwrap())))
+	}
+}
+
+impl File for StreamFile {
+	fn open(
+		&self,
+		_header: &RequestHeader,
+		_request: server::OpenRequest<'_>,
+	) -> Result<OpenResult, Error> {
+		Ok(self.0.handle().into_open_result())
+	}
+}
+
+fn layout() -> server::FuseLayout {
+	let mut init_out = kernel::fuse_init_out::new();
+	init_out.major = kernel::FUSE_KERNEL_VERSION;
+	init_out.minor = kernel::FUSE_KERNEL_MINOR_VERSION;
+	server::FuseLayout::new(&init_out).unwrap()
+}
+
+// Session {{{
+
+struct Session<'b, 'a> {
+	kernel: KernelSocket<'b, 'a>,
+	conn: &'b server::FuseConnection<ServerSocket<'b, 'a>>,
+	fs: Filesystem<'b, ServerSocket<'b, 'a>>,
+	builder: RequestBuilder,
+	request_id: u64,
+	node_id: u64,
+}
+
+// Serves a filesystem containing a stream file backed by `generator`,
+// sending the requests made by `test` one at a time.
+fn serve(
+	generator: &Arc<Generator>,
+	test: impl FnOnce(&mut Session<'_, '_>),
+) {
+	let mut request_buf = [0u8; 4096];
+	let mut reply_buf = [0u8; 4096];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	let kernel_socket = pair.kernel();
+
+	let builder = RequestBuilder::new(layout());
+	let mut buf = MinReadBuffer::new();
+	let mut init_in = kernel::fuse_init_in::new();
+	init_in.major = kernel::FUSE_KERNEL_VERSION;
+	init_in.minor = kernel::FUSE_KERNEL_MINOR_VERSION;
+	let init = builder.init(buf.as_aligned_slice_mut(), &init_in).unwrap();
+	kernel_socket.push_request(init.get()).unwrap();
+
+	let conn = server::FuseConnection::connect(pair.server(), |_, _| {})
+		.unwrap();
+	let mut reply = [0u8; 512];
+	kernel_socket.pop_reply(&mut reply).unwrap();
+
+	let root = Arc::new(TestDir {
+		stream: Arc::new(StreamFile(generator.clone())),
+	});
+	let mut fs = Filesystem::new(&conn, root);
+	fs.set_inode_allocator(SequentialInodeAllocator::new());
+	let mut session = Session {
+		kernel: kernel_socket,
+		conn: &conn,
+		fs,
+		builder,
+		request_id: 1,
+		node_id: 0,
+	};
+
+	let body: &[&[u8]] = &[b"stream\0"];
+	let entry = session.call(fuse_opcode::FUSE_LOOKUP, 1, body).unwrap();
+	let entry = unsafe {
+		entry.as_ptr().cast::<kernel::fuse_entry_out>().read_unaligned()
+	};
+	session.node_id = entry.nodeid;
+	test(&mut session);
+}
+
+impl Session<'_, '_> {
+	// Sends a request, returning the body of its reply.
+	fn call(
+		&mut self,
+		opcode: fuse_opcode,
+		node_id: u64,
+		body: &[&[u8]],
+	) -> Result<Vec<u8>, Error> {
+		self.request_id += 1;
+		self.builder.set_request_id(self.request_id);
+		let mut buf = MinReadBuffer::new();
+		let request = self.builder
+			.build(buf.as_aligned_slice_mut(), opcode, node_id, body)
+			.unwrap();
+		self.kernel.push_request(request.get()).unwrap();
+
+		let mut buf = MinReadBuffer::new();
+		let request = self.conn.recv(buf.as_aligned_slice_mut()).unwrap();
+		self.fs.dispatch(request.unwrap()).unwrap().unwrap();
+
+		let mut reply = vec![0u8; 4096];
+		let reply_len = self.kernel.pop_reply(&mut reply).unwrap();
+		assert!(self.kernel.replies_done());
+		let header = unsafe {
+			reply.as_ptr().cast::<kernel::fuse_out_header>().read_unaligned()
+		};
+		if let Some(err) = NonZeroI32::new(header.error) {
+			return Err(Error(err));
+		}
+		reply.truncate(reply_len);
+		Ok(reply.split_off(size_of::<kernel::fuse_out_header>()))
+	}
+
+	fn open(&mut self) -> u64 {
+		let open_in = kernel::fuse_open_in::new();
+		let body: &[&[u8]] = &[open_in.as_bytes()];
+		let body = self.call(fuse_opcode::FUSE_OPEN, self.node_id, body);
+		let body = body.unwrap();
+		let open_out = unsafe {
+			body.as_ptr().cast::<kernel::fuse_open_out>().read_unaligned()
+		};
+		assert_eq!(open_out.open_flags, kernel::FOPEN_DIRECT_IO);
+		open_out.fh
+	}
+
+	fn read(
+		&mut self,
+		handle: u64,
+		offset: u64,
+		size: u32,
+	) -> Result<Vec<u8>, Error> {
+		let mut read_in = kernel::fuse_read_in::new();
+		read_in.fh = handle;
+		read_in.offset = offset;
+		read_in.size = size;
+		let body: &[&[u8]] = &[read_in.as_bytes()];
+		self.call(fuse_opcode::FUSE_READ, self.node_id, body)
+	}
+}
+
+// }}}
+
+#[test]
+fn content_generated_once_per_handle() {
+	let generator = Arc::new(Generator::default());
+	serve(&generator, |s| {
+		let handle_a = s.open();
+		assert_eq!(generator.calls(), 0);
+		assert_eq!(s.read(handle_a, 0, 4).unwrap(), b"cont");
+		assert_eq!(s.read(handle_a, 4, 100).unwrap(), b"ent 1\n");
+		assert_eq!(s.read(handle_a, 0, 100).unwrap(), b"content 1\n");
+		assert_eq!(generator.calls(), 1);
+
+		// Each handle has its own snapshot of the content.
+		let handle_b = s.open();
+		assert_eq!(s.read(handle_b, 0, 100).unwrap(), b"content 2\n");
+		assert_eq!(s.read(handle_a, 0, 100).unwrap(), b"content 1\n");
+		assert_eq!(generator.calls(), 2);
+	});
+}
+
+#[test]
+fn read_past_end() {
+	let generator = Arc::new(Generator::default());
+	serve(&generator, |s| {
+		let handle = s.open();
+		assert_eq!(s.read(handle, 8, 100).unwrap(), b"1\n");
+		assert_eq!(s.read(handle, 10, 100).unwrap(), b"");
+		assert_eq!(s.read(handle, 100, 100).unwrap(), b"");
+		assert_eq!(s.read(handle, u64::MAX, u32::MAX).unwrap(), b"");
+		assert_eq!(s.read(handle, 9, u32::MAX).unwrap(), b"\n");
+		assert_eq!(generator.calls(), 1);
+	});
+}
+
+#[test]
+fn generate_error_retried() {
+	let generator = Arc::new(Generator::default());
+	generator.fail.store(true, Ordering::SeqCst);
+	serve(&generator, |s| {
+		let handle = s.open();
+		assert_eq!(s.read(handle, 0, 100), Err(OsError::UNAVAILABLE));
+		assert_eq!(generator.calls(), 1);
+
+		generator.fail.store(false, Ordering::SeqCst);
+		assert_eq!(s.read(handle, 0, 100).unwrap(), b"content 2\n");
+		assert_eq!(generator.calls(), 2);
+	});
+}
+
+#[test]
+fn release_drops_content() {
+	let generator = Arc::new(Generator::default());
+	let handle = generator.handle();
+	let builder = RequestBuilder::new(layout());
+
+	let mut read_buf = MinReadBuffer::new();
+	let mut read_in = kernel::fuse_read_in::new();
+	read_in.size = 100;
+	let read = builder.read(read_buf.as_aligned_slice_mut(), 2, &read_in);
+	let read = server::FuseRequest::new(read.unwrap(), layout()).unwrap();
+
+	let mut release_buf = MinReadBuffer::new();
+	let release_in = kernel::fuse_release_in::new();
+	let release = builder
+		.release(release_buf.as_aligned_slice_mut(), 2, &release_in)
+		.unwrap();
+	let release = server::FuseRequest::new(release, layout()).unwrap();
+
+	let read_request = || server::ReadRequest::try_from(read).unwrap();
+	handle.read(read.header(), read_request()).unwrap();
+	handle.read(read.header(), read_request()).unwrap();
+	assert_eq!(generator.calls(), 1);
+
+	// The content is generated again after the handle is released.
+	let release_request = server::ReleaseRequest::try_from(release).unwrap();
+	handle.release(release.header(), release_request).unwrap();
+	handle.read(read.header(), read_request()).unwrap();
+	assert_eq!(generator.calls(), 2);
+}
@@ -0,0 +1,666 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::mem::size_of;
+use core::num::{NonZeroI32, NonZeroU64};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use fuse::io::MinReadBuffer;
+use fuse::kernel;
+use fuse::kernel::fuse_opcode;
+use fuse::os::OsError;
+use fuse::server;
+use fuse::testing::{KernelSocket, RequestBuilder, ServerSocket, SocketPair};
+use fuse::{Error, FileMode, FileType, NodeAttr, NodeId, NodeName};
+use fuse::RequestHeader;
+
+use fuse_vfs::{
+	Directory,
+	DirectoryEntries,
+	DirectoryHandle,
+	File,
+	FileHandle,
+	Filesystem,
+	GetattrResult,
+	ListDirectoryHandle,
+	LookupResult,
+	Node,
+	OpenResult,
+	OpendirResult,
+	SequentialInodeAllocator,
+	StaticDirectoryEntry,
+	StaticDirectoryHandle,
+	UnionDirectory,
+	UnionHooks,
+};
+
+// Linux values of the open flags used by these tests.
+const O_RDONLY: u32 = 0o0;
+const O_WRONLY: u32 = 0o1;
+const O_TRUNC: u32 = 0o1000;
+
+// The Linux value of `DT_REG`.
+const DT_REG: u32 = 8;
+
+fn node_attr(node_id: u64, file_type: FileType) -> NodeAttr {
+	let mut attr = NodeAttr::new(NodeId::new(node_id).unwrap());
+	attr.set_mode(FileMode::new(file_type.as_mode().get() | 0o755));
+	attr
+}
+
+// Nodes {{{
+
+type TestEntries = Arc<Mutex<Vec<(&'static str, Arc<dyn Node>, NodeAttr)>>>;
+
+struct TestDir {
+	attr: NodeAttr,
+	entries: TestEntries,
+}
+
+impl TestDir {
+	fn new(node_id: u64) -> Arc<TestDir> {
+		Arc::new(TestDir {
+			attr: node_attr(node_id, FileType::Directory),
+			entries: Arc::new(Mutex::new(Vec::new())),
+		})
+	}
+
+	fn add(&self, name: &'static str, node: Arc<dyn Node>, attr: NodeAttr) {
+		self.entries.lock().unwrap().push((name, node, attr));
+	}
+
+	fn add_dir(&self, name: &'static str, dir: &Arc<TestDir>) {
+		self.add(name, dir.clone(), dir.attr);
+	}
+
+	fn add_file(&self, name: &'static str, file: &Arc<TestFile>) {
+		self.add(name, file.clone(), file.attr);
+	}
+
+	fn add_whiteout(&self, name: &'static str, node_id: u64) {
+		let attr = node_attr(node_id, FileType::CharacterDevice);
+		self.add(name, Arc::new(Whiteout(attr)), attr);
+	}
+}
+
+impl Node for TestDir {
+	fn as_directory(&self) -> Option<&dyn Directory> {
+		Some(self)
+	}
+
+	fn getattr(
+		&self,
+		_header: &RequestHeader,
+		_request: server::GetattrRequest<'_>,
+	) -> Result<GetattrResult, Error> {
+		Ok(GetattrResult::new(self.attr))
+	}
+}
+
+impl Directory for TestDir {
+	fn lookup(
+		&self,
+		_header: &RequestHeader,
+		request: server::LookupRequest<'_>,
+	) -> Result<LookupResult, Error> {
+		let entries = self.entries.lock().unwrap();
+		for (name, node, attr) in entries.iter() {
+			if name.as_bytes() == request.name().as_bytes() {
+				return Ok(LookupResult::found(node.clone(), *attr));
+			}
+		}
+		Ok(LookupResult::not_found())
+	}
+
+	fn opendir(
+		&self,
+		_header: &RequestHeader,
+		_request: server::OpendirRequest<'_>,
+	) -> Result<OpendirResult, Error> {
+		let handle = TestDirHandle(self.entries.clone());
+		Ok(OpendirResult::new(Arc::new(handle)))
+	}
+}
+
+// Lists the current entries of a `TestDir`, including those added after
+// it was opened.
+struct TestDirHandle(TestEntries);
+
+impl DirectoryHandle for TestDirHandle {
+	fn as_list_directory_handle(&self) -> Option<&dyn ListDirectoryHandle> {
+		Some(self)
+	}
+}
+
+impl ListDirectoryHandle for TestDirHandle {
+	fn list(
+		&self,
+		header: &RequestHeader,
+		offset: Option<NonZeroU64>,
+		entries: &mut DirectoryEntries<'_>,
+	) -> Result<(), Error> {
+		let dir_entries = self.0.lock().unwrap();
+		let dir_entries = dir_entries.iter().map(|(name, _, attr)| {
+			let name = NodeName::new(name).unwrap();
+			let mut entry = StaticDirectoryEntry::new(name, attr.node_id());
+			entry.node_attr = *attr;
+			entry
+		});
+		let handle = StaticDirectoryHandle::new(dir_entries.collect());
+		handle.list(header, offset, entries)
+	}
+}
+
+struct TestFile {
+	attr: NodeAttr,
+	opens: AtomicUsize,
+}
+
+impl TestFile {
+	fn new(node_id: u64) -> Arc<TestFile> {
+		Arc::new(TestFile {
+			attr: node_attr(node_id, FileType::Regular),
+			opens: AtomicUsize::new(0),
+		})
+	}
+
+	fn opens(&self) -> usize {
+		self.opens.load(Ordering::SeqCst)
+	}
+}
+
+impl Node for TestFile {
+	fn as_file(&self) -> Option<&dyn File> {
+		Some(self)
+	}
+
+	fn getattr(
+		&self,
+		_header: &RequestHeader,
+		_request: server::GetattrRequest<'_>,
+	) -> Result<GetattrResult, Error> {
+		Ok(GetattrResult::new(self.attr))
+	}
+}
+
+impl File for TestFile {
+	fn open(
+		&self,
+		_header: &RequestHeader,
+		_request: server::OpenRequest<'_>,
+	) -> Result<OpenResult, Error> {
+		self.opens.fetch_add(1, Ordering::SeqCst);
+		Ok(OpenResult::new(Arc::new(TestFileHandle)))
+	}
+}
+
+struct TestFileHandle;
+
+impl FileHandle for TestFileHandle {}
+
+struct Whiteout(NodeAttr);
+
+impl Node for Whiteout {
+	fn getattr(
+		&self,
+		_header: &RequestHeader,
+		_request: server::GetattrRequest<'_>,
+	) -> Result<GetattrResult, Error> {
+		Ok(GetattrResult::new(self.0))
+	}
+}
+
+// }}}
+
+// TestHooks {{{
+
+// Hooks that treat the directories in `opaque` as opaque, and copy nodes
+// up into the upper layer rooted at `upper`.
+struct TestHooks {
+	opaque: Vec<u64>,
+	// Directories of the upper layer, by path.
+	upper_dirs: Mutex<HashMap<String, Arc<TestDir>>>,
+	// The path of each copied node, and whether it was copied into the
+	// expected parent.
+	copies: Mutex<Vec<(String, bool)>>,
+	copied_files: Mutex<Vec<Arc<TestFile>>>,
+}
+
+impl TestHooks {
+	fn new(upper: &Arc<TestDir>, opaque: Vec<u64>) -> Arc<TestHooks> {
+		let mut upper_dirs = HashMap::new();
+		upper_dirs.insert(String::new(), upper.clone());
+		Arc::new(TestHooks {
+			opaque,
+			upper_dirs: Mutex::new(upper_dirs),
+			copies: Mutex::new(Vec::new()),
+			copied_files: Mutex::new(Vec::new()),
+		})
+	}
+
+	fn copies(&self) -> Vec<(String, bool)> {
+		self.copies.lock().unwrap().clone()
+	}
+}
+
+impl UnionHooks for TestHooks {
+	fn is_opaque(&self, _node: &dyn Node, node_attr: &NodeAttr) -> bool {
+		self.opaque.contains(&node_attr.node_id().get())
+	}
+
+	fn copy_up(
+		&self,
+		_header: &RequestHeader,
+		parent: &dyn Node,
+		path: &[&NodeName],
+		_lower: &Arc<dyn Node>,
+		lower_attr: &NodeAttr,
+	) -> Result<Arc<dyn Node>, Error> {
+		let names: Vec<&str> = path.iter()
+			.map(|name| std::str::from_utf8(name.as_bytes()).unwrap())
+			.collect();
+		let (name, parent_names) = names.split_last().unwrap();
+		let name: &'static str = String::leak(name.to_string());
+
+		let mut upper_dirs = self.upper_dirs.lock().unwrap();
+		let upper_parent = upper_dirs[&parent_names.join("/")].clone();
+		let parent_ok = core::ptr::addr_eq(parent, Arc::as_ptr(&upper_parent));
+		self.copies.lock().unwrap().push((names.join("/"), parent_ok));
+
+		let node_id = 1000 + lower_attr.node_id().get();
+		if FileType::from_mode(lower_attr.mode()) == Some(FileType::Directory) {
+			let dir = TestDir::new(node_id);
+			upper_parent.add_dir(name, &dir);
+			upper_dirs.insert(names.join("/"), dir.clone());
+			Ok(dir)
+		} else {
+			let file = TestFile::new(node_id);
+			upper_parent.add_file(name, &file);
+			self.copied_files.lock().unwrap().push(file.clone());
+			Ok(file)
+		}
+	}
+}
+
+// }}}
+
+// Session {{{
+
+struct Session<'b, 'a> {
+	kernel: KernelSocket<'b, 'a>,
+	conn: &'b server::FuseConnection<ServerSocket<'b, 'a>>,
+	fs: Filesystem<'b, ServerSocket<'b, 'a>>,
+	builder: RequestBuilder,
+	request_id: u64,
+}
+
+// A directory entry from a `FUSE_READDIR` reply.
+#[derive(Debug, PartialEq)]
+struct Dirent {
+	name: String,
+	offset: u64,
+	file_type: u32,
+}
+
+fn layout() -> server::FuseLayout {
+	let mut init_out = kernel::fuse_init_out::new();
+	init_out.major = kernel::FUSE_KERNEL_VERSION;
+	init_out.minor = kernel::FUSE_KERNEL_MINOR_VERSION;
+	server::FuseLayout::new(&init_out).unwrap()
+}
+
+// Serves `root` with an allocator-backed filesystem, sending the requests
+// made by `test` one at a time.
+fn serve(root: Arc<dyn Node>, test: impl FnOnce(&mut Session<'_, '_>)) {
+	let mut request_buf = [0u8; 4096];
+	let mut reply_buf = [0u8; 4096];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	let kernel_socket = pair.kernel();
+
+	let builder = RequestBuilder::new(layout());
+	let mut buf = MinReadBuffer::new();
+	let mut init_in = kernel::fuse_init_in::new();
+	init_in.major = kernel::FUSE_KERNEL_VERSION;
+	init_in.minor = kernel::FUSE_KERNEL_MINOR_VERSION;
+	let init = builder.init(buf.as_aligned_slice_mut(), &init_in).unwrap();
+	kernel_socket.push_request(init.get()).unwrap();
+
+	let conn = server::FuseConnection::connect(pair.server(), |_, _| {})
+		.unwrap();
+	let mut reply = [0u8; 512];
+	kernel_socket.pop_reply(&mut reply).unwrap();
+
+	let mut fs = Filesystem::new(&conn, root);
+	fs.set_inode_allocator(SequentialInodeAllocator::new());
+	test(&mut Session {
+		kernel: kernel_socket,
+		conn: &conn,
+		fs,
+		builder,
+		request_id: 1,
+	});
+}
+
+impl Session<'_, '_> {
+	// Sends a request, returning the body of its reply.
+	fn call(
+		&mut self,
+		opcode: fuse_opcode,
+		node_id: u64,
+		body: &[&[u8]],
+	) -> Result<Vec<u8>, Error> {
+		self.request_id += 1;
+		self.builder.set_request_id(self.request_id);
+		let mut buf = MinReadBuffer::new();
+		let request = self.builder
+			.build(buf.as_aligned_slice_mut(), opcode, node_id, body)
+			.unwrap();
+		self.kernel.push_request(request.get()).unwrap();
+
+		let mut buf = MinReadBuffer::new();
+		let request = self.conn.recv(buf.as_aligned_slice_mut()).unwrap();
+		self.fs.dispatch(request.unwrap()).unwrap().unwrap();
+
+		let mut reply = vec![0u8; 4096];
+		let reply_len = self.kernel.pop_reply(&mut reply).unwrap();
+		assert!(self.kernel.replies_done());
+		let header = unsafe {
+			reply.as_ptr().cast::<kernel::fuse_out_header>().read_unaligned()
+		};
+		if let Some(err) = NonZeroI32::new(header.error) {
+			return Err(Error(err));
+		}
+		reply.truncate(reply_len);
+		Ok(reply.split_off(size_of::<kernel::fuse_out_header>()))
+	}
+
+	// Looks up `name`, returning `None` for a negative entry.
+	fn lookup(
+		&mut self,
+		parent: u64,
+		name: &str,
+	) -> Option<kernel::fuse_entry_out> {
+		let name = format!("{name}\0");
+		let body: &[&[u8]] = &[name.as_bytes()];
+		let body = self.call(fuse_opcode::FUSE_LOOKUP, parent, body).unwrap();
+		assert_eq!(body.len(), size_of::<kernel::fuse_entry_out>());
+		let entry = unsafe {
+			body.as_ptr().cast::<kernel::fuse_entry_out>().read_unaligned()
+		};
+		(entry.nodeid != 0).then_some(entry)
+	}
+
+	fn open(&mut self, node_id: u64, flags: u32) -> Result<(), Error> {
+		let mut open_in = kernel::fuse_open_in::new();
+		open_in.flags = flags;
+		let body: &[&[u8]] = &[open_in.as_bytes()];
+		self.call(fuse_opcode::FUSE_OPEN, node_id, body)?;
+		Ok(())
+	}
+
+	fn opendir(&mut self, node_id: u64) -> Result<u64, Error> {
+		let open_in = kernel::fuse_open_in::new();
+		let body: &[&[u8]] = &[open_in.as_bytes()];
+		let body = self.call(fuse_opcode::FUSE_OPENDIR, node_id, body)?;
+		let open_out = unsafe {
+			body.as_ptr().cast::<kernel::fuse_open_out>().read_unaligned()
+		};
+		Ok(open_out.fh)
+	}
+
+	fn readdir(
+		&mut self,
+		node_id: u64,
+		handle: u64,
+		offset: u64,
+	) -> Result<Vec<Dirent>, Error> {
+		let mut read_in = kernel::fuse_read_in::new();
+		read_in.fh = handle;
+		read_in.offset = offset;
+		read_in.size = 4096;
+		let body: &[&[u8]] = &[read_in.as_bytes()];
+		let body = self.call(fuse_opcode::FUSE_READDIR, node_id, body)?;
+
+		let mut dirents = Vec::new();
+		let mut buf = &body[..];
+		while !buf.is_empty() {
+			let u64_at = |ii: usize| {
+				u64::from_ne_bytes(buf[ii..ii + 8].try_into().unwrap())
+			};
+			let u32_at = |ii: usize| {
+				u32::from_ne_bytes(buf[ii..ii + 4].try_into().unwrap())
+			};
+			let name_len = u32_at(16) as usize;
+			dirents.push(Dirent {
+				name: String::from_utf8(buf[24..24 + name_len].to_vec())
+					.unwrap(),
+				offset: u64_at(8),
+				file_type: u32_at(20),
+			});
+			let dirent_len = (24 + name_len).next_multiple_of(8);
+			buf = &buf[dirent_len..];
+		}
+		Ok(dirents)
+	}
+
+	fn readdir_names(&mut self, node_id: u64) -> Vec<String> {
+		let handle = self.opendir(node_id).unwrap();
+		let dirents = self.readdir(node_id, handle, 0).unwrap();
+		dirents.into_iter().map(|dirent| dirent.name).collect()
+	}
+}
+
+// }}}
+
+#[test]
+fn whiteout_hides_lower_entry() {
+	let upper = TestDir::new(1);
+	upper.add_whiteout("hidden", 2);
+	let lower = TestDir::new(1);
+	lower.add_file("hidden", &TestFile::new(3));
+	lower.add_file("visible", &TestFile::new(4));
+
+	serve(UnionDirectory::new(vec![upper, lower]), |s| {
+		assert!(s.lookup(1, "hidden").is_none());
+		assert!(s.lookup(1, "visible").is_some());
+		assert_eq!(s.readdir_names(1), ["visible"]);
+	});
+}
+
+#[test]
+fn opaque_directory_hides_lower_entries() {
+	let upper = TestDir::new(1);
+	let upper_opaque = TestDir::new(2);
+	upper_opaque.add_file("both", &TestFile::new(3));
+	upper.add_dir("opaque", &upper_opaque);
+	let upper_merged = TestDir::new(4);
+	upper_merged.add_file("both", &TestFile::new(5));
+	upper.add_dir("merged", &upper_merged);
+
+	let lower = TestDir::new(1);
+	let lower_opaque = TestDir::new(12);
+	lower_opaque.add_file("both", &TestFile::new(13));
+	lower_opaque.add_file("lower", &TestFile::new(14));
+	lower.add_dir("opaque", &lower_opaque);
+	let lower_merged = TestDir::new(15);
+	lower_merged.add_file("both", &TestFile::new(16));
+	lower_merged.add_file("lower", &TestFile::new(17));
+	lower.add_dir("merged", &lower_merged);
+
+	let hooks = TestHooks::new(&upper, vec![2]);
+	let root = UnionDirectory::with_hooks(vec![upper, lower], hooks);
+	serve(root, |s| {
+		let opaque = s.lookup(1, "opaque").unwrap().nodeid;
+		assert!(s.lookup(opaque, "both").is_some());
+		assert!(s.lookup(opaque, "lower").is_none());
+		assert_eq!(s.readdir_names(opaque), ["both"]);
+
+		let merged = s.lookup(1, "merged").unwrap().nodeid;
+		assert!(s.lookup(merged, "lower").is_some());
+		assert_eq!(s.readdir_names(merged), ["both", "lower"]);
+	});
+}
+
+#[test]
+fn upper_file_hides_lower_directory() {
+	let upper = TestDir::new(1);
+	let upper_file = TestFile::new(2);
+	upper.add_file("name", &upper_file);
+	let lower = TestDir::new(1);
+	let lower_dir = TestDir::new(12);
+	lower_dir.add_file("child", &TestFile::new(13));
+	lower.add_dir("name", &lower_dir);
+
+	serve(UnionDirectory::new(vec![upper, lower]), |s| {
+		let entry = s.lookup(1, "name").unwrap();
+		let file_type = FileType::from_mode(FileMode::new(entry.attr.mode));
+		assert_eq!(file_type, Some(FileType::Regular));
+		let err = s.opendir(entry.nodeid).unwrap_err();
+		assert_eq!(err, OsError::NOT_DIRECTORY);
+
+		// The upper file is served directly, so it can be written without
+		// being copied up.
+		s.open(entry.nodeid, O_WRONLY).unwrap();
+		assert_eq!(upper_file.opens(), 1);
+
+		let handle = s.opendir(1).unwrap();
+		let dirents = s.readdir(1, handle, 0).unwrap();
+		assert_eq!(dirents, [Dirent {
+			name: "name".into(),
+			offset: 1,
+			file_type: DT_REG,
+		}]);
+	});
+}
+
+#[test]
+fn copy_up_on_write() {
+	let upper = TestDir::new(1);
+	let lower = TestDir::new(1);
+	let lower_dir = TestDir::new(2);
+	let lower_file = TestFile::new(3);
+	lower_dir.add_file("file", &lower_file);
+	lower.add_dir("dir", &lower_dir);
+
+	let hooks = TestHooks::new(&upper, Vec::new());
+	let root = UnionDirectory::with_hooks(vec![upper, lower], hooks.clone());
+	serve(root, |s| {
+		let dir = s.lookup(1, "dir").unwrap().nodeid;
+		let file = s.lookup(dir, "file").unwrap().nodeid;
+
+		// The parent directory doesn't exist in the upper layer, so it's
+		// copied up before the file.
+		s.open(file, O_WRONLY).unwrap();
+		assert_eq!(hooks.copies(), [
+			("dir".to_string(), true),
+			("dir/file".to_string(), true),
+		]);
+		let copied = hooks.copied_files.lock().unwrap()[0].clone();
+		assert_eq!(copied.opens(), 1);
+		assert_eq!(lower_file.opens(), 0);
+
+		// Later opens are served by the copy, even if read-only.
+		s.open(file, O_RDONLY).unwrap();
+		assert_eq!(hooks.copies().len(), 2);
+		assert_eq!(copied.opens(), 2);
+		assert_eq!(lower_file.opens(), 0);
+	});
+}
+
+#[test]
+fn copy_up_on_truncate() {
+	let upper = TestDir::new(1);
+	let lower = TestDir::new(1);
+	let lower_file = TestFile::new(2);
+	lower.add_file("file", &lower_file);
+
+	let hooks = TestHooks::new(&upper, Vec::new());
+	let root = UnionDirectory::with_hooks(vec![upper, lower], hooks.clone());
+	serve(root, |s| {
+		let file = s.lookup(1, "file").unwrap().nodeid;
+
+		s.open(file, O_RDONLY).unwrap();
+		assert!(hooks.copies().is_empty());
+		assert_eq!(lower_file.opens(), 1);
+
+		s.open(file, O_RDONLY | O_TRUNC).unwrap();
+		assert_eq!(hooks.copies(), [("file".to_string(), true)]);
+		let copied = hooks.copied_files.lock().unwrap()[0].clone();
+		assert_eq!(copied.opens(), 1);
+		assert_eq!(lower_file.opens(), 1);
+	});
+}
+
+#[test]
+fn default_hooks_copy_up_read_only() {
+	let upper = TestDir::new(1);
+	let lower = TestDir::new(1);
+	let lower_file = TestFile::new(2);
+	lower.add_file("file", &lower_file);
+
+	serve(UnionDirectory::new(vec![upper.clone(), lower]), |s| {
+		let file = s.lookup(1, "file").unwrap().nodeid;
+		assert_eq!(s.open(file, O_WRONLY).unwrap_err(), OsError::READ_ONLY);
+		assert_eq!(lower_file.opens(), 0);
+		assert!(upper.entries.lock().unwrap().is_empty());
+
+		s.open(file, O_RDONLY).unwrap();
+		assert_eq!(lower_file.opens(), 1);
+	});
+}
+
+#[test]
+fn list_snapshot() {
+	let upper = TestDir::new(1);
+	upper.add_file("a", &TestFile::new(2));
+	let lower = TestDir::new(1);
+	lower.add_file("b", &TestFile::new(3));
+	lower.add_file("a", &TestFile::new(4));
+	lower.add_file("c", &TestFile::new(5));
+
+	let root = UnionDirectory::new(vec![upper, lower.clone()]);
+	serve(root, |s| {
+		let handle = s.opendir(1).unwrap();
+		let names_at = |s: &mut Session<'_, '_>, offset| {
+			let dirents = s.readdir(1, handle, offset).unwrap();
+			dirents.into_iter()
+				.map(|dirent| (dirent.name, dirent.offset))
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(names_at(s, 0), [
+			("a".to_string(), 1),
+			("b".to_string(), 2),
+			("c".to_string(), 3),
+		]);
+
+		// Reads from a later offset are served from the snapshot.
+		lower.add_file("d", &TestFile::new(6));
+		assert_eq!(names_at(s, 1), [
+			("b".to_string(), 2),
+			("c".to_string(), 3),
+		]);
+
+		// Reading from the start lists the entries again.
+		assert_eq!(names_at(s, 0), [
+			("a".to_string(), 1),
+			("b".to_string(), 2),
+			("c".to_string(), 3),
+			("d".to_string(), 4),
+		]);
+	});
+}
@@ -18,6 +18,22 @@
 
 pub mod io;
 
+use core::cell::Cell;
+use core::cmp;
+use core::mem::{size_of, MaybeUninit};
+use core::num::{NonZeroI32, NonZeroU64};
+use core::ptr;
+
+use crate::internal::compat;
+use crate::io::SendBuf;
+use crate::kernel;
+use crate::server::{
+	CuseLayout,
+	FuseLayout,
+	ReaddirEntries,
+	ReaddirplusEntries,
+};
+
 // ClientError {{{
 
 /// Errors that may be encountered by a CUSE or FUSE client.
@@ -32,6 +48,13 @@ pub enum ClientError<IoError> {
 
 	/// The socket encountered an I/O error when sending a request.
 	SendError(IoError),
+
+	/// The request's size would exceed the FUSE protocol's maximum limit
+	/// of [`u32::MAX`] bytes.
+	RequestTooBig(u64),
+
+	/// The server responded to the request with an error.
+	ErrorResponse(crate::Error),
 }
 
 impl<E> From<ResponseError> for ClientError<E> {
@@ -50,9 +73,10 @@ impl<E> From<io::RecvError<E>> for ClientError<E> {
 
 impl<E> From<io::SendError<E>> for ClientError<E> {
 	fn from(err: io::SendError<E>) -> Self {
-		Self::SendError(match err {
-			io::SendError::Other(io_err) => io_err,
-		})
+		match err {
+			io::SendError::RequestTooBig(len) => Self::RequestTooBig(len),
+			io::SendError::Other(io_err) => Self::SendError(io_err),
+		}
 	}
 }
 
@@ -64,6 +88,785 @@ impl<E> From<io::SendError<E>> for ClientError<E> {
 #[non_exhaustive]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum ResponseError {
+	/// The response's request ID doesn't match the request that was sent.
+	RequestIdMismatch,
+
+	/// The length in the response header doesn't match the number of bytes
+	/// received.
+	LengthMismatch,
+
+	/// The response is too short to contain the expected data.
+	UnexpectedEof,
+
+	/// The response contains an error number outside the range accepted by
+	/// the FUSE protocol.
+	InvalidError,
+
+	/// The response contains node attributes with a node ID of zero.
+	MissingNodeId,
+
+	/// The server doesn't support a compatible protocol version.
+	UnsupportedVersion(crate::Version),
+
+	/// The response contains a malformed directory entry.
+	///
+	/// See [`ReaddirEntries::parse`] for the requirements of a valid entry.
+	///
+	/// [`ReaddirEntries::parse`]: crate::server::ReaddirEntries::parse
+	InvalidDirent,
+}
+
+// }}}
+
+// RequestSender {{{
+
+/// Trait for request types that can be sent to a CUSE server.
+pub trait CuseRequest {
+	/// Encode this request and send it with the given request sender.
+	fn send_to<S: io::CuseSocket>(
+		&self,
+		request_sender: CuseRequestSender<'_, S>,
+	) -> Result<(), io::SendError<S::Error>>;
+}
+
+/// Trait for request types that can be sent to a FUSE server.
+pub trait FuseRequest {
+	/// Encode this request and send it with the given request sender.
+	fn send_to<S: io::FuseSocket>(
+		&self,
+		request_sender: FuseRequestSender<'_, S>,
+	) -> Result<(), io::SendError<S::Error>>;
+}
+
+pub(crate) struct RequestSender<'a, S> {
+	pub(crate) socket: &'a S,
+	pub(crate) header: kernel::fuse_in_header,
+}
+
+impl<'a, S: io::Socket> RequestSender<'a, S> {
+	fn new(socket: &'a S, request_id: NonZeroU64) -> RequestSender<'a, S> {
+		RequestSender {
+			socket,
+			header: new!(kernel::fuse_in_header {
+				unique: request_id.get(),
+			}),
+		}
+	}
+
+	fn header(
+		&self,
+		opcode: kernel::fuse_opcode,
+		node_id: u64,
+		chunks: &[&[u8]],
+	) -> Result<kernel::fuse_in_header, io::SendError<S::Error>> {
+		let mut len = size_of::<kernel::fuse_in_header>() as u64;
+		for chunk in chunks {
+			len = len.saturating_add(chunk.len() as u64);
+		}
+		if len > u64::from(u32::MAX) {
+			return Err(io::SendError::RequestTooBig(len));
+		}
+		let mut header = self.header;
+		header.len = len as u32;
+		header.opcode = opcode;
+		header.nodeid = node_id;
+		Ok(header)
+	}
+
+	// Sets the length of the request extensions, which must be sent as the
+	// last chunk of the request. Extensions are a multiple of 8 bytes long.
+	pub(crate) fn set_extensions_len(&mut self, extensions_len: usize) {
+		self.header.total_extlen = (extensions_len / 8) as u16;
+	}
+
+	pub(crate) fn send_0(
+		self,
+		opcode: kernel::fuse_opcode,
+		node_id: u64,
+	) -> Result<(), io::SendError<S::Error>> {
+		let header = self.header(opcode, node_id, &[])?;
+		self.socket.send(SendBuf::new_1(
+			header.len as usize,
+			header.as_bytes(),
+		))
+	}
+
+	pub(crate) fn send_1(
+		self,
+		opcode: kernel::fuse_opcode,
+		node_id: u64,
+		bytes_1: &[u8],
+	) -> Result<(), io::SendError<S::Error>> {
+		let header = self.header(opcode, node_id, &[bytes_1])?;
+		self.socket.send(SendBuf::new_2(
+			header.len as usize,
+			header.as_bytes(),
+			bytes_1,
+		))
+	}
+
+	pub(crate) fn send_2(
+		self,
+		opcode: kernel::fuse_opcode,
+		node_id: u64,
+		bytes_1: &[u8],
+		bytes_2: &[u8],
+	) -> Result<(), io::SendError<S::Error>> {
+		let header = self.header(opcode, node_id, &[bytes_1, bytes_2])?;
+		self.socket.send(SendBuf::new_3(
+			header.len as usize,
+			header.as_bytes(),
+			bytes_1,
+			bytes_2,
+		))
+	}
+
+	pub(crate) fn send_3(
+		self,
+		opcode: kernel::fuse_opcode,
+		node_id: u64,
+		bytes_1: &[u8],
+		bytes_2: &[u8],
+		bytes_3: &[u8],
+	) -> Result<(), io::SendError<S::Error>> {
+		let header = self.header(opcode, node_id, &[
+			bytes_1,
+			bytes_2,
+			bytes_3,
+		])?;
+		self.socket.send(SendBuf::new_4(
+			header.len as usize,
+			header.as_bytes(),
+			bytes_1,
+			bytes_2,
+			bytes_3,
+		))
+	}
+
+	pub(crate) fn send_4(
+		self,
+		opcode: kernel::fuse_opcode,
+		node_id: u64,
+		bytes_1: &[u8],
+		bytes_2: &[u8],
+		bytes_3: &[u8],
+		bytes_4: &[u8],
+	) -> Result<(), io::SendError<S::Error>> {
+		let header = self.header(opcode, node_id, &[
+			bytes_1,
+			bytes_2,
+			bytes_3,
+			bytes_4,
+		])?;
+		self.socket.send(SendBuf::new_5(
+			header.len as usize,
+			header.as_bytes(),
+			bytes_1,
+			bytes_2,
+			bytes_3,
+			bytes_4,
+		))
+	}
+
+	#[allow(clippy::too_many_arguments)]
+	pub(crate) fn send_5(
+		self,
+		opcode: kernel::fuse_opcode,
+		node_id: u64,
+		bytes_1: &[u8],
+		bytes_2: &[u8],
+		bytes_3: &[u8],
+		bytes_4: &[u8],
+		bytes_5: &[u8],
+	) -> Result<(), io::SendError<S::Error>> {
+		let header = self.header(opcode, node_id, &[
+			bytes_1,
+			bytes_2,
+			bytes_3,
+			bytes_4,
+			bytes_5,
+		])?;
+		self.socket.send(SendBuf::new_6(
+			header.len as usize,
+			header.as_bytes(),
+			bytes_1,
+			bytes_2,
+			bytes_3,
+			bytes_4,
+			bytes_5,
+		))
+	}
+}
+
+/// Sends requests to a CUSE server.
+///
+/// The request body is encoded according to the session's [`CuseLayout`].
+#[must_use]
+pub struct CuseRequestSender<'a, S> {
+	pub(crate) inner: RequestSender<'a, S>,
+	pub(crate) layout: CuseLayout,
+}
+
+impl<'a, S: io::CuseSocket> CuseRequestSender<'a, S> {
+	/// Create a new `CuseRequestSender` for the given request ID.
+	pub fn new(
+		socket: &'a S,
+		layout: CuseLayout,
+		request_id: NonZeroU64,
+	) -> CuseRequestSender<'a, S> {
+		CuseRequestSender {
+			inner: RequestSender::new(socket, request_id),
+			layout,
+		}
+	}
+
+	/// Set the user ID of the process that initiated the request.
+	pub fn set_user_id(&mut self, user_id: u32) {
+		self.inner.header.uid = user_id;
+	}
+
+	/// Set the group ID of the process that initiated the request.
+	pub fn set_group_id(&mut self, group_id: u32) {
+		self.inner.header.gid = group_id;
+	}
+
+	/// Set the process ID of the process that initiated the request.
+	pub fn set_process_id(&mut self, process_id: u32) {
+		self.inner.header.pid = process_id;
+	}
+
+	/// Send a request to the CUSE server.
+	pub fn send(
+		self,
+		request: &impl CuseRequest,
+	) -> Result<(), io::SendError<S::Error>> {
+		request.send_to(self)
+	}
+}
+
+/// Sends requests to a FUSE server.
+///
+/// The request body is encoded according to the session's [`FuseLayout`].
+#[must_use]
+pub struct FuseRequestSender<'a, S> {
+	pub(crate) inner: RequestSender<'a, S>,
+	pub(crate) layout: FuseLayout,
+}
+
+impl<'a, S: io::FuseSocket> FuseRequestSender<'a, S> {
+	/// Create a new `FuseRequestSender` for the given request ID.
+	pub fn new(
+		socket: &'a S,
+		layout: FuseLayout,
+		request_id: NonZeroU64,
+	) -> FuseRequestSender<'a, S> {
+		FuseRequestSender {
+			inner: RequestSender::new(socket, request_id),
+			layout,
+		}
+	}
+
+	/// Set the user ID of the process that initiated the request.
+	pub fn set_user_id(&mut self, user_id: u32) {
+		self.inner.header.uid = user_id;
+	}
+
+	/// Set the group ID of the process that initiated the request.
+	pub fn set_group_id(&mut self, group_id: u32) {
+		self.inner.header.gid = group_id;
+	}
+
+	/// Set the process ID of the process that initiated the request.
+	pub fn set_process_id(&mut self, process_id: u32) {
+		self.inner.header.pid = process_id;
+	}
+
+	/// Send a request to the FUSE server.
+	pub fn send(
+		self,
+		request: &impl FuseRequest,
+	) -> Result<(), io::SendError<S::Error>> {
+		request.send_to(self)
+	}
+}
+
+// }}}
+
+// FuseConnection {{{
+
+const RESPONSE_BUF_LEN: usize = 256;
+
+/// Represents an active connection to a FUSE server.
+///
+/// Requests are sent one at a time. Each method sends a request and then
+/// waits for the server's response, so a connection must not be shared with
+/// other senders while a request is in progress.
+pub struct FuseConnection<S> {
+	socket: S,
+	layout: FuseLayout,
+	flags: crate::FuseInitFlags,
+	init_out: kernel::fuse_init_out,
+	next_request_id: Cell<u64>,
+}
+
+impl<S: io::FuseSocket> FuseConnection<S> {
+	/// Perform a FUSE connection handshake.
+	///
+	/// The client sends a [`FuseInitRequest`] for the latest supported
+	/// protocol version, with the given `max_readahead` and `flags`. The
+	/// server's [`FuseInitResponse`] determines the protocol version and
+	/// features of the connection.
+	///
+	/// [`FuseInitRequest`]: crate::server::FuseInitRequest
+	/// [`FuseInitResponse`]: crate::server::FuseInitResponse
+	pub fn connect(
+		socket: S,
+		max_readahead: u32,
+		flags: crate::FuseInitFlags,
+	) -> Result<FuseConnection<S>, ClientError<S::Error>> {
+		use crate::server::{FuseInitRequest, FuseInitResponse};
+
+		let request_id = NonZeroU64::MIN;
+		let layout = FuseLayout {
+			version_minor: kernel::FUSE_KERNEL_MINOR_VERSION as u16,
+			features: 0,
+		};
+		let request = FuseInitRequest::new(max_readahead, flags);
+		FuseRequestSender::new(&socket, layout, request_id).send(&request)?;
+
+		let mut buf = [0u8; RESPONSE_BUF_LEN];
+		let body = recv_response(&socket, request_id, &mut buf)?;
+		let init_out: kernel::fuse_init_out = unsafe {
+			decode_response(body, kernel::FUSE_COMPAT_INIT_OUT_SIZE)?
+		};
+		let version = crate::Version::new(init_out.major, init_out.minor);
+		let layout = match FuseLayout::new(&init_out) {
+			Ok(layout) => layout,
+			Err(_) => {
+				return Err(ResponseError::UnsupportedVersion(version).into());
+			},
+		};
+		let response = FuseInitResponse { raw: init_out };
+
+		Ok(Self {
+			socket,
+			layout,
+			flags: flags & response.flags(),
+			init_out,
+			next_request_id: Cell::new(request_id.get() + 1),
+		})
+	}
+
+	/// Look up a directory entry by name.
+	///
+	/// Returns `None` if the server responded with a negative entry, which
+	/// has a node ID of zero.
+	pub fn lookup(
+		&self,
+		parent_id: crate::NodeId,
+		name: &crate::NodeName,
+	) -> Result<Option<crate::Entry>, ClientError<S::Error>> {
+		let request_id = self.next_request_id();
+		self.request_sender(request_id).inner.send_2(
+			kernel::fuse_opcode::FUSE_LOOKUP,
+			parent_id.get(),
+			name.as_bytes(),
+			b"\0",
+		)?;
+
+		let mut buf = [0u8; RESPONSE_BUF_LEN];
+		let body = recv_response(&self.socket, request_id, &mut buf)?;
+		let raw: kernel::fuse_entry_out = unsafe {
+			decode_response(body, self.entry_out_len())?
+		};
+		if raw.nodeid == 0 {
+			return Ok(None);
+		}
+		if raw.attr.ino == 0 {
+			return Err(ResponseError::MissingNodeId.into());
+		}
+		Ok(Some(unsafe { crate::Entry::from_raw(raw) }))
+	}
+
+	/// Get the attributes of a node.
+	pub fn getattr(
+		&self,
+		node_id: crate::NodeId,
+	) -> Result<crate::NodeAttr, ClientError<S::Error>> {
+		let request_id = self.next_request_id();
+		let sender = self.request_sender(request_id).inner;
+		if self.layout.version_minor() < 9 {
+			sender.send_0(kernel::fuse_opcode::FUSE_GETATTR, node_id.get())?;
+		} else {
+			let body = kernel::fuse_getattr_in::new();
+			sender.send_1(
+				kernel::fuse_opcode::FUSE_GETATTR,
+				node_id.get(),
+				body.as_bytes(),
+			)?;
+		}
+
+		let mut buf = [0u8; RESPONSE_BUF_LEN];
+		let body = recv_response(&self.socket, request_id, &mut buf)?;
+		let raw: kernel::fuse_attr_out = unsafe {
+			decode_response(body, self.attr_out_len())?
+		};
+		if raw.attr.ino == 0 {
+			return Err(ResponseError::MissingNodeId.into());
+		}
+		Ok(crate::NodeAttr { raw: raw.attr })
+	}
+
+	/// Open a file.
+	///
+	/// Returns the file handle assigned by the server.
+	pub fn open(
+		&self,
+		node_id: crate::NodeId,
+		open_flags: crate::OpenFlags,
+	) -> Result<u64, ClientError<S::Error>> {
+		let opcode = kernel::fuse_opcode::FUSE_OPEN;
+		self.open_impl(opcode, node_id, open_flags)
+	}
+
+	/// Open a directory.
+	///
+	/// Returns the file handle assigned by the server.
+	pub fn opendir(
+		&self,
+		node_id: crate::NodeId,
+		open_flags: crate::OpenFlags,
+	) -> Result<u64, ClientError<S::Error>> {
+		let opcode = kernel::fuse_opcode::FUSE_OPENDIR;
+		self.open_impl(opcode, node_id, open_flags)
+	}
+
+	fn open_impl(
+		&self,
+		opcode: kernel::fuse_opcode,
+		node_id: crate::NodeId,
+		open_flags: crate::OpenFlags,
+	) -> Result<u64, ClientError<S::Error>> {
+		let request_id = self.next_request_id();
+		let body = kernel::fuse_open_in {
+			flags: open_flags.get(),
+			..kernel::fuse_open_in::new()
+		};
+		self.request_sender(request_id).inner.send_1(
+			opcode,
+			node_id.get(),
+			body.as_bytes(),
+		)?;
+
+		let mut buf = [0u8; RESPONSE_BUF_LEN];
+		let body = recv_response(&self.socket, request_id, &mut buf)?;
+		let raw: kernel::fuse_open_out = unsafe {
+			decode_response(body, size_of::<kernel::fuse_open_out>())?
+		};
+		Ok(raw.fh)
+	}
+
+	/// Read data from an open file.
+	///
+	/// The response is received into `buf`, which must also have space for
+	/// the response header. At most `buf.len()` minus the size of
+	/// [`fuse_out_header`] bytes are requested. Returns the portion of `buf`
+	/// containing the data that was read.
+	///
+	/// [`fuse_out_header`]: kernel::fuse_out_header
+	pub fn read<'a>(
+		&self,
+		node_id: crate::NodeId,
+		handle: u64,
+		offset: u64,
+		buf: &'a mut [u8],
+	) -> Result<&'a [u8], ClientError<S::Error>> {
+		let opcode = kernel::fuse_opcode::FUSE_READ;
+		self.read_impl(opcode, node_id, handle, offset, buf)
+	}
+
+	/// Read entries from an open directory.
+	///
+	/// The response is received into `buf` as by [`FuseConnection::read`],
+	/// and the returned entries borrow their names from it. The `offset` is
+	/// zero for the start of the directory, or the offset of the last entry
+	/// that was read. An empty response means that there are no more
+	/// entries.
+	pub fn readdir<'a>(
+		&self,
+		node_id: crate::NodeId,
+		handle: u64,
+		offset: u64,
+		buf: &'a mut [u8],
+	) -> Result<ReaddirEntries<'a>, ClientError<S::Error>> {
+		let opcode = kernel::fuse_opcode::FUSE_READDIR;
+		let data = self.read_impl(opcode, node_id, handle, offset, buf)?;
+		match ReaddirEntries::parse(data) {
+			Ok(entries) => Ok(entries),
+			Err(_) => Err(ResponseError::InvalidDirent.into()),
+		}
+	}
+
+	/// Read entries and their attributes from an open directory.
+	///
+	/// This is equivalent to [`FuseConnection::readdir`], but sends a
+	/// `FUSE_READDIRPLUS` request. The server will only accept it if the
+	/// [`DO_READDIRPLUS`] init flag is enabled.
+	///
+	/// [`DO_READDIRPLUS`]: crate::FuseInitFlag::DO_READDIRPLUS
+	pub fn readdirplus<'a>(
+		&self,
+		node_id: crate::NodeId,
+		handle: u64,
+		offset: u64,
+		buf: &'a mut [u8],
+	) -> Result<ReaddirplusEntries<'a>, ClientError<S::Error>> {
+		let opcode = kernel::fuse_opcode::FUSE_READDIRPLUS;
+		let data = self.read_impl(opcode, node_id, handle, offset, buf)?;
+		match ReaddirplusEntries::parse(data) {
+			Ok(entries) => Ok(entries),
+			Err(_) => Err(ResponseError::InvalidDirent.into()),
+		}
+	}
+
+	fn read_impl<'a>(
+		&self,
+		opcode: kernel::fuse_opcode,
+		node_id: crate::NodeId,
+		handle: u64,
+		offset: u64,
+		buf: &'a mut [u8],
+	) -> Result<&'a [u8], ClientError<S::Error>> {
+		const HEADER_LEN: usize = size_of::<kernel::fuse_out_header>();
+		let size = buf.len().saturating_sub(HEADER_LEN);
+		let size = cmp::min(size, u32::MAX as usize) as u32;
+
+		let request_id = self.next_request_id();
+		let body = new!(kernel::fuse_read_in {
+			fh: handle,
+			offset: offset,
+			size: size,
+		});
+		let mut body_bytes = body.as_bytes();
+		if self.layout.version_minor() < 9 {
+			let len = size_of::<compat::fuse_read_in_v7p1>();
+			body_bytes = &body_bytes[..len];
+		}
+		self.request_sender(request_id).inner.send_1(
+			opcode,
+			node_id.get(),
+			body_bytes,
+		)?;
+
+		let data = recv_response(&self.socket, request_id, buf)?;
+		if data.len() > size as usize {
+			return Err(ResponseError::LengthMismatch.into());
+		}
+		Ok(data)
+	}
+
+	/// Write data to an open file.
+	///
+	/// Returns the number of bytes written.
+	pub fn write(
+		&self,
+		node_id: crate::NodeId,
+		handle: u64,
+		offset: u64,
+		data: &[u8],
+	) -> Result<u32, ClientError<S::Error>> {
+		let size = match u32::try_from(data.len()) {
+			Ok(size) => size,
+			Err(_) => {
+				return Err(ClientError::RequestTooBig(data.len() as u64));
+			},
+		};
+
+		let request_id = self.next_request_id();
+		let body = new!(kernel::fuse_write_in {
+			fh: handle,
+			offset: offset,
+			size: size,
+		});
+		let mut body_bytes = body.as_bytes();
+		if self.layout.version_minor() < 9 {
+			body_bytes = &body_bytes[..kernel::FUSE_COMPAT_WRITE_IN_SIZE];
+		}
+		self.request_sender(request_id).inner.send_2(
+			kernel::fuse_opcode::FUSE_WRITE,
+			node_id.get(),
+			body_bytes,
+			data,
+		)?;
+
+		let mut buf = [0u8; RESPONSE_BUF_LEN];
+		let body = recv_response(&self.socket, request_id, &mut buf)?;
+		let raw: kernel::fuse_write_out = unsafe {
+			decode_response(body, size_of::<kernel::fuse_write_out>())?
+		};
+		Ok(raw.size)
+	}
+
+	/// Release an open file.
+	pub fn release(
+		&self,
+		node_id: crate::NodeId,
+		handle: u64,
+		open_flags: crate::OpenFlags,
+	) -> Result<(), ClientError<S::Error>> {
+		let opcode = kernel::fuse_opcode::FUSE_RELEASE;
+		self.release_impl(opcode, node_id, handle, open_flags)
+	}
+
+	/// Release an open directory.
+	pub fn releasedir(
+		&self,
+		node_id: crate::NodeId,
+		handle: u64,
+		open_flags: crate::OpenFlags,
+	) -> Result<(), ClientError<S::Error>> {
+		let opcode = kernel::fuse_opcode::FUSE_RELEASEDIR;
+		self.release_impl(opcode, node_id, handle, open_flags)
+	}
+
+	fn release_impl(
+		&self,
+		opcode: kernel::fuse_opcode,
+		node_id: crate::NodeId,
+		handle: u64,
+		open_flags: crate::OpenFlags,
+	) -> Result<(), ClientError<S::Error>> {
+		let request_id = self.next_request_id();
+		let body = kernel::fuse_release_in {
+			fh: handle,
+			flags: open_flags.get(),
+			..kernel::fuse_release_in::new()
+		};
+		let mut body_bytes = body.as_bytes();
+		if self.layout.version_minor() < 8 {
+			let len = size_of::<compat::fuse_release_in_v7p1>();
+			body_bytes = &body_bytes[..len];
+		}
+		self.request_sender(request_id).inner.send_1(
+			opcode,
+			node_id.get(),
+			body_bytes,
+		)?;
+
+		let mut buf = [0u8; RESPONSE_BUF_LEN];
+		recv_response(&self.socket, request_id, &mut buf)?;
+		Ok(())
+	}
+
+	fn next_request_id(&self) -> NonZeroU64 {
+		let request_id = self.next_request_id.get();
+		let next = request_id.checked_add(1).unwrap_or(1);
+		self.next_request_id.set(next);
+		NonZeroU64::new(request_id).unwrap_or(NonZeroU64::MIN)
+	}
+
+	fn request_sender(
+		&self,
+		request_id: NonZeroU64,
+	) -> FuseRequestSender<'_, S> {
+		FuseRequestSender::new(&self.socket, self.layout, request_id)
+	}
+
+	fn entry_out_len(&self) -> usize {
+		compat::fuse_entry_out_len(self.layout.version_minor())
+	}
+
+	fn attr_out_len(&self) -> usize {
+		compat::fuse_attr_out_len(self.layout.version_minor())
+	}
+}
+
+impl<S> FuseConnection<S> {
+	/// Returns a reference to the underlying [`Socket`] for this connection.
+	///
+	/// [`Socket`]: io::Socket
+	#[inline]
+	#[must_use]
+	pub fn socket(&self) -> &S {
+		&self.socket
+	}
+
+	#[allow(missing_docs)] // TODO
+	#[inline]
+	#[must_use]
+	pub fn layout(&self) -> FuseLayout {
+		self.layout
+	}
+
+	/// Returns the protocol version negotiated with the server.
+	#[inline]
+	#[must_use]
+	pub fn version(&self) -> crate::Version {
+		crate::Version::new(self.init_out.major, self.init_out.minor)
+	}
+
+	/// Returns the flags that were requested by the client and enabled by
+	/// the server.
+	#[inline]
+	#[must_use]
+	pub fn flags(&self) -> crate::FuseInitFlags {
+		self.flags
+	}
+
+	/// Returns the server's maximum size of a write request.
+	#[inline]
+	#[must_use]
+	pub fn max_write(&self) -> u32 {
+		self.init_out.max_write
+	}
+}
+
+fn recv_response<'a, S: io::Socket>(
+	socket: &S,
+	request_id: NonZeroU64,
+	buf: &'a mut [u8],
+) -> Result<&'a [u8], ClientError<S::Error>> {
+	const HEADER_LEN: usize = size_of::<kernel::fuse_out_header>();
+
+	let recv_len = socket.recv(buf)?;
+	let buf = &buf[..cmp::min(recv_len, buf.len())];
+	if buf.len() < HEADER_LEN {
+		return Err(ResponseError::UnexpectedEof.into());
+	}
+	let header: kernel::fuse_out_header = unsafe {
+		ptr::read_unaligned(buf.as_ptr().cast())
+	};
+	if header.len as usize != buf.len() {
+		return Err(ResponseError::LengthMismatch.into());
+	}
+	if header.unique != request_id.get() {
+		return Err(ResponseError::RequestIdMismatch.into());
+	}
+	if header.error != 0 {
+		if header.error <= -512 || header.error > 0 {
+			return Err(ResponseError::InvalidError.into());
+		}
+		let error = unsafe { NonZeroI32::new_unchecked(header.error) };
+		return Err(ClientError::ErrorResponse(crate::Error(error)));
+	}
+	Ok(&buf[HEADER_LEN..])
+}
+
+// Decodes a response body into a zero-initialized `T`, accepting bodies
+// truncated to `min_len` bytes for compatibility with older servers.
+//
+// `T` must be a kernel type, for which any byte pattern is valid.
+unsafe fn decode_response<T>(
+	body: &[u8],
+	min_len: usize,
+) -> Result<T, ResponseError> {
+	if body.len() < min_len {
+		return Err(ResponseError::UnexpectedEof);
+	}
+	let mut out = MaybeUninit::<T>::zeroed();
+	let len = cmp::min(body.len(), size_of::<T>());
+	ptr::copy_nonoverlapping(body.as_ptr(), out.as_mut_ptr().cast::<u8>(), len);
+	Ok(out.assume_init())
 }
 
 // }}}
@@ -0,0 +1,377 @@
+// Copyright 2021 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::mem::size_of;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+use fuse::client;
+use fuse::client::{ClientError, FuseConnection, ResponseError};
+use fuse::kernel;
+
+use fuse_testutil as testutil;
+use fuse_testutil::{decode_request, MessageBuilder, SendBufToVec};
+
+struct FakeServer {
+	requests: RefCell<Vec<Vec<u8>>>,
+	responses: RefCell<VecDeque<Vec<u8>>>,
+}
+
+impl FakeServer {
+	fn new() -> FakeServer {
+		Self {
+			requests: RefCell::new(Vec::new()),
+			responses: RefCell::new(VecDeque::new()),
+		}
+	}
+
+	fn push_response(&self, request_id: u64, body: &[u8]) {
+		self.push_raw_response(request_id, 0, body);
+	}
+
+	fn push_raw_response(&self, request_id: u64, error: i32, body: &[u8]) {
+		let header = testutil::new!(kernel::fuse_out_header {
+			len: (size_of::<kernel::fuse_out_header>() + body.len()) as u32,
+			error: error,
+			unique: request_id,
+		});
+		let response = MessageBuilder::new()
+			.push_sized(&header)
+			.push_bytes(body)
+			.build();
+		self.responses.borrow_mut().push_back(response);
+	}
+
+	fn last_request(&self) -> fuse::io::MinReadBuffer {
+		let requests = self.requests.borrow();
+		let request = requests.last().unwrap();
+		let mut buf = fuse::io::MinReadBuffer::new();
+		buf.as_slice_mut()[..request.len()].copy_from_slice(request);
+		buf
+	}
+}
+
+impl client::io::Socket for FakeServer {
+	type Error = ();
+
+	fn send(
+		&self,
+		buf: fuse::io::SendBuf,
+	) -> Result<(), client::io::SendError<()>> {
+		self.requests.borrow_mut().push(buf.to_vec());
+		Ok(())
+	}
+
+	fn recv(&self, buf: &mut [u8]) -> Result<usize, client::io::RecvError<()>> {
+		let response = self.responses.borrow_mut().pop_front().unwrap();
+		buf[..response.len()].copy_from_slice(&response);
+		Ok(response.len())
+	}
+}
+
+impl client::io::FuseSocket for FakeServer {}
+
+fn connect(server: &FakeServer) -> FuseConnection<&FakeServer> {
+	let init_out = testutil::new!(kernel::fuse_init_out {
+		major: 7,
+		minor: kernel::FUSE_KERNEL_MINOR_VERSION,
+		max_write: 4096,
+	});
+	server.push_response(1, init_out.as_bytes());
+	FuseConnection::connect(server, 0, fuse::FuseInitFlags::new()).unwrap()
+}
+
+fn node_attr(node_id: u64) -> fuse::NodeAttr {
+	let mut attr = fuse::NodeAttr::new(fuse::NodeId::new(node_id).unwrap());
+	attr.set_size(123);
+	attr.set_mode(fuse::FileMode::S_IFREG | 0o644);
+	attr
+}
+
+#[test]
+fn connect_handshake() {
+	let server = FakeServer::new();
+
+	let mut init_out = testutil::new!(kernel::fuse_init_out {
+		major: 7,
+		minor: 23,
+		max_write: 8192,
+	});
+	init_out.flags = kernel::FUSE_ASYNC_READ | kernel::FUSE_POSIX_LOCKS;
+	server.push_response(1, &init_out.as_bytes()[..24]);
+
+	let flags = fuse::FuseInitFlag::ASYNC_READ | fuse::FuseInitFlag::BIG_WRITES;
+	let conn = FuseConnection::connect(&server, 4096, flags).unwrap();
+
+	let buf = server.last_request();
+	let request = decode_request!(server::FuseInitRequest, buf);
+	assert_eq!(request.version(), fuse::Version::new(
+		kernel::FUSE_KERNEL_VERSION,
+		kernel::FUSE_KERNEL_MINOR_VERSION,
+	));
+	assert_eq!(request.max_readahead(), 4096);
+	assert_eq!(request.flags(), flags);
+
+	assert_eq!(conn.version(), fuse::Version::new(7, 23));
+	assert_eq!(conn.flags(), fuse::FuseInitFlag::ASYNC_READ);
+	assert_eq!(conn.max_write(), 8192);
+}
+
+#[test]
+fn connect_unsupported_version() {
+	let server = FakeServer::new();
+	let init_out = testutil::new!(kernel::fuse_init_out {
+		major: 8,
+		minor: 0,
+	});
+	server.push_response(1, init_out.as_bytes());
+
+	let flags = fuse::FuseInitFlags::new();
+	let err = FuseConnection::connect(&server, 0, flags).err().unwrap();
+	assert_eq!(
+		err,
+		ClientError::ResponseError(ResponseError::UnsupportedVersion(
+			fuse::Version::new(8, 0),
+		)),
+	);
+}
+
+#[test]
+fn lookup() {
+	let server = FakeServer::new();
+	let conn = connect(&server);
+
+	let mut entry = fuse::Entry::new(node_attr(11));
+	entry.set_generation(22);
+	server.push_response(2, entry.raw().as_bytes());
+
+	let parent_id = fuse::NodeId::ROOT;
+	let name = fuse::NodeName::new("hello.txt").unwrap();
+	let got = conn.lookup(parent_id, name).unwrap().unwrap();
+	assert_eq!(got.attributes().node_id().get(), 11);
+	assert_eq!(got.attributes().size(), 123);
+	assert_eq!(got.generation(), 22);
+
+	let buf = server.last_request();
+	let request = decode_request!(server::LookupRequest, buf);
+	assert_eq!(request.parent_id(), parent_id);
+	assert_eq!(request.name(), "hello.txt");
+}
+
+#[test]
+fn lookup_negative() {
+	let server = FakeServer::new();
+	let conn = connect(&server);
+
+	let entry_out = kernel::fuse_entry_out::new();
+	server.push_response(2, entry_out.as_bytes());
+
+	let name = fuse::NodeName::new("missing.txt").unwrap();
+	let got = conn.lookup(fuse::NodeId::ROOT, name).unwrap();
+	assert!(got.is_none());
+}
+
+#[test]
+fn getattr() {
+	let server = FakeServer::new();
+	let conn = connect(&server);
+
+	let attr_out = testutil::new!(kernel::fuse_attr_out {
+		attr: *node_attr(11).raw(),
+	});
+	server.push_response(2, attr_out.as_bytes());
+
+	let node_id = fuse::NodeId::new(11).unwrap();
+	let got = conn.getattr(node_id).unwrap();
+	assert_eq!(got.node_id(), node_id);
+	assert_eq!(got.size(), 123);
+
+	let buf = server.last_request();
+	let request = decode_request!(server::GetattrRequest, buf);
+	assert_eq!(request.node_id(), node_id);
+	assert_eq!(request.handle(), None);
+}
+
+#[test]
+fn getattr_missing_node_id() {
+	let server = FakeServer::new();
+	let conn = connect(&server);
+
+	let attr_out = kernel::fuse_attr_out::new();
+	server.push_response(2, attr_out.as_bytes());
+
+	let err = conn.getattr(fuse::NodeId::ROOT).unwrap_err();
+	assert_eq!(
+		err,
+		ClientError::ResponseError(ResponseError::MissingNodeId),
+	);
+}
+
+#[test]
+fn open_read_write_release() {
+	let server = FakeServer::new();
+	let conn = connect(&server);
+	let node_id = fuse::NodeId::new(11).unwrap();
+
+	let open_out = testutil::new!(kernel::fuse_open_out {
+		fh: 1234,
+	});
+	server.push_response(2, open_out.as_bytes());
+	let handle = conn.open(node_id, fuse::OpenFlags::new(0o2)).unwrap();
+	assert_eq!(handle, 1234);
+
+	let buf = server.last_request();
+	let request = decode_request!(server::OpenRequest, buf);
+	assert_eq!(request.node_id(), node_id);
+	assert_eq!(request.open_flags().get(), 0o2);
+
+	server.push_response(3, b"hello");
+	let mut read_buf = [0u8; 64];
+	let data = conn.read(node_id, handle, 10, &mut read_buf).unwrap();
+	assert_eq!(data, b"hello");
+
+	let buf = server.last_request();
+	let request = decode_request!(server::ReadRequest, buf);
+	assert_eq!(request.handle(), 1234);
+	assert_eq!(request.offset(), 10);
+	assert_eq!(request.size(), 48);
+
+	let write_out = testutil::new!(kernel::fuse_write_out {
+		size: 5,
+	});
+	server.push_response(4, write_out.as_bytes());
+	let written = conn.write(node_id, handle, 20, b"world").unwrap();
+	assert_eq!(written, 5);
+
+	let buf = server.last_request();
+	let request = decode_request!(server::WriteRequest, buf);
+	assert_eq!(request.handle(), 1234);
+	assert_eq!(request.offset(), 20);
+	assert_eq!(request.value(), b"world");
+
+	server.push_response(5, b"");
+	conn.release(node_id, handle, fuse::OpenFlags::new(0o2)).unwrap();
+
+	let buf = server.last_request();
+	let request = decode_request!(server::ReleaseRequest, buf);
+	assert_eq!(request.handle(), 1234);
+	assert_eq!(request.open_flags().get(), 0o2);
+}
+
+#[test]
+fn opendir_readdir_releasedir() {
+	let server = FakeServer::new();
+	let conn = connect(&server);
+	let node_id = fuse::NodeId::new(11).unwrap();
+
+	let open_out = testutil::new!(kernel::fuse_open_out {
+		fh: 1234,
+	});
+	server.push_response(2, open_out.as_bytes());
+	let handle = conn.opendir(node_id, fuse::OpenFlags::new(0)).unwrap();
+	assert_eq!(handle, 1234);
+
+	let buf = server.last_request();
+	let request = decode_request!(server::OpendirRequest, buf);
+	assert_eq!(request.node_id(), node_id);
+
+	let mut entries_buf = [0u8; 256];
+	let mut writer = fuse::server::ReaddirEntriesWriter::new(&mut entries_buf);
+	for (ino, name, offset) in [(100, "hello.txt", 1), (101, "world.txt", 2)] {
+		writer.try_push(&fuse::server::ReaddirEntry::new(
+			fuse::NodeId::new(ino).unwrap(),
+			fuse::NodeName::new(name).unwrap(),
+			core::num::NonZeroU64::new(offset).unwrap(),
+		)).unwrap();
+	}
+	server.push_response(3, writer.into_entries().as_bytes());
+
+	let mut read_buf = [0u8; 256];
+	let entries = conn.readdir(node_id, handle, 0, &mut read_buf).unwrap();
+	let names: Vec<&[u8]> = entries.iter().map(|entry| {
+		entry.name().as_bytes()
+	}).collect();
+	assert_eq!(names, [&b"hello.txt"[..], b"world.txt"]);
+
+	let buf = server.last_request();
+	let request = decode_request!(server::ReaddirRequest, buf);
+	assert_eq!(request.handle(), 1234);
+	assert_eq!(request.offset(), None);
+	assert_eq!(request.size(), 240);
+
+	// Malformed entries are rejected.
+	server.push_response(4, &[0u8; 9]);
+	let err = conn.readdir(node_id, handle, 2, &mut read_buf).unwrap_err();
+	assert_eq!(
+		err,
+		ClientError::ResponseError(ResponseError::InvalidDirent),
+	);
+
+	server.push_response(5, b"");
+	conn.releasedir(node_id, handle, fuse::OpenFlags::new(0)).unwrap();
+
+	let buf = server.last_request();
+	let request = decode_request!(server::ReleasedirRequest, buf);
+	assert_eq!(request.handle(), 1234);
+}
+
+#[test]
+fn error_response() {
+	let server = FakeServer::new();
+	let conn = connect(&server);
+
+	server.push_raw_response(2, -2, b"");
+	let name = fuse::NodeName::new("hello.txt").unwrap();
+	let err = conn.lookup(fuse::NodeId::ROOT, name).unwrap_err();
+	let expect = fuse::Error(core::num::NonZeroI32::new(-2).unwrap());
+	assert_eq!(err, ClientError::ErrorResponse(expect));
+
+	server.push_raw_response(3, -512, b"");
+	let err = conn.lookup(fuse::NodeId::ROOT, name).unwrap_err();
+	assert_eq!(err, ClientError::ResponseError(ResponseError::InvalidError));
+}
+
+#[test]
+fn response_errors() {
+	let server = FakeServer::new();
+	let conn = connect(&server);
+	let node_id = fuse::NodeId::ROOT;
+
+	// Response to a different request.
+	server.push_response(1, b"");
+	let err = conn.release(node_id, 0, fuse::OpenFlags::new(0)).unwrap_err();
+	assert_eq!(
+		err,
+		ClientError::ResponseError(ResponseError::RequestIdMismatch),
+	);
+
+	// Response body too short.
+	server.push_response(3, &[0u8; 8]);
+	let err = conn.getattr(node_id).unwrap_err();
+	assert_eq!(
+		err,
+		ClientError::ResponseError(ResponseError::UnexpectedEof),
+	);
+
+	// Header length doesn't match the received length.
+	server.push_response(4, b"");
+	server.responses.borrow_mut()[0].push(0);
+	let err = conn.release(node_id, 0, fuse::OpenFlags::new(0)).unwrap_err();
+	assert_eq!(
+		err,
+		ClientError::ResponseError(ResponseError::LengthMismatch),
+	);
+}
@@ -16,6 +16,8 @@
 
 //! Client-specific I/O types.
 
+use crate::io::SendBuf;
+
 /// Errors that may be encountered when receiving a response.
 #[non_exhaustive]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -28,6 +30,44 @@ pub enum RecvError<IoError> {
 #[non_exhaustive]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum SendError<IoError> {
+	/// The request's size would exceed the FUSE protocol's maximum limit
+	/// of [`u32::MAX`] bytes.
+	RequestTooBig(u64),
+
 	/// The socket encountered an error not otherwise specified.
 	Other(IoError),
 }
+
+/// Trait for sockets that can send requests and receive responses.
+pub trait Socket {
+	/// Type of errors that may be returned from this socket's I/O methods.
+	type Error;
+
+	/// Send a single serialised request to the server.
+	fn send(&self, buf: SendBuf) -> Result<(), SendError<Self::Error>>;
+
+	/// Receive a single serialised response from the server.
+	fn recv(&self, buf: &mut [u8]) -> Result<usize, RecvError<Self::Error>>;
+}
+
+impl<S: Socket> Socket for &S {
+	type Error = S::Error;
+
+	fn send(&self, buf: SendBuf) -> Result<(), SendError<S::Error>> {
+		(*self).send(buf)
+	}
+
+	fn recv(&self, buf: &mut [u8]) -> Result<usize, RecvError<S::Error>> {
+		(*self).recv(buf)
+	}
+}
+
+/// Marker trait for CUSE sockets.
+pub trait CuseSocket: Socket {}
+
+impl<S: CuseSocket> CuseSocket for &S {}
+
+/// Marker trait for FUSE sockets.
+pub trait FuseSocket: Socket {}
+
+impl<S: FuseSocket> FuseSocket for &S {}
@@ -14,9 +14,27 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+//! CUSE device names and helpers for implementing character devices.
+//!
+//! Many character devices (pipes, ttys, serial ports) buffer data between
+//! writers and readers, and notify pollers when the buffer becomes readable
+//! or writable. The [`RingBuffer`] and [`PollWaiters`] types implement these
+//! patterns without allocation.
+
 use core::fmt;
 
 use crate::internal::debug;
+use crate::server::{
+	CuseConnection,
+	CuseReplySender,
+	CuseSocket,
+	PollHandle,
+	PollRequest,
+	PollResponse,
+	SendError,
+	WriteRequest,
+	WriteResponse,
+};
 
 // DeviceNameError {{{
 
@@ -164,3 +182,271 @@ pub struct DeviceNumber {
 }
 
 // }}}
+
+// RingBuffer {{{
+
+/// A fixed-capacity queue of bytes, stored in a borrowed buffer.
+///
+/// Data written to the device is appended with [`push_write`], and sent to
+/// readers with [`send_read_reply`]. Buffered data is sent directly from the
+/// ring's storage, without being copied into a temporary buffer.
+///
+/// [`push_write`]: RingBuffer::push_write
+/// [`send_read_reply`]: RingBuffer::send_read_reply
+pub struct RingBuffer<'a> {
+	buf: &'a mut [u8],
+	start: usize,
+	len: usize,
+}
+
+impl<'a> RingBuffer<'a> {
+	/// Creates an empty `RingBuffer` that stores data in `buf`.
+	#[must_use]
+	pub fn new(buf: &'a mut [u8]) -> RingBuffer<'a> {
+		Self {
+			buf,
+			start: 0,
+			len: 0,
+		}
+	}
+
+	/// Returns the maximum number of bytes that can be buffered.
+	#[inline]
+	#[must_use]
+	pub fn capacity(&self) -> usize {
+		self.buf.len()
+	}
+
+	/// Returns the number of bytes currently buffered.
+	#[inline]
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Returns the number of bytes that can be pushed before the buffer is
+	/// full.
+	#[inline]
+	#[must_use]
+	pub fn available(&self) -> usize {
+		self.buf.len() - self.len
+	}
+
+	/// Returns whether the buffer contains no data.
+	#[inline]
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Returns whether the buffer has no space for more data.
+	#[inline]
+	#[must_use]
+	pub fn is_full(&self) -> bool {
+		self.len == self.buf.len()
+	}
+
+	/// Returns the buffered data, in order, as a pair of slices.
+	///
+	/// The second slice is non-empty only if the data wraps around the end
+	/// of the buffer's storage.
+	#[must_use]
+	pub fn as_slices(&self) -> (&[u8], &[u8]) {
+		let head_len = self.len.min(self.buf.len() - self.start);
+		let head = &self.buf[self.start..self.start + head_len];
+		let tail = &self.buf[..self.len - head_len];
+		(head, tail)
+	}
+
+	/// Appends as much of `data` as fits, returning the number of bytes
+	/// appended.
+	pub fn push(&mut self, data: &[u8]) -> usize {
+		let count = data.len().min(self.available());
+		let mut end = self.start + self.len;
+		if end >= self.buf.len() {
+			end -= self.buf.len();
+		}
+		let head_len = count.min(self.buf.len() - end);
+		self.buf[end..end + head_len].copy_from_slice(&data[..head_len]);
+		self.buf[..count - head_len].copy_from_slice(&data[head_len..count]);
+		self.len += count;
+		count
+	}
+
+	/// Removes data from the front of the buffer into `dst`, returning the
+	/// number of bytes removed.
+	pub fn pop(&mut self, dst: &mut [u8]) -> usize {
+		let (head, tail) = self.as_slices();
+		let head_len = head.len().min(dst.len());
+		let tail_len = tail.len().min(dst.len() - head_len);
+		dst[..head_len].copy_from_slice(&head[..head_len]);
+		dst[head_len..head_len + tail_len].copy_from_slice(&tail[..tail_len]);
+		let count = head_len + tail_len;
+		self.consume(count);
+		count
+	}
+
+	/// Discards up to `count` bytes from the front of the buffer.
+	pub fn consume(&mut self, count: usize) {
+		let count = count.min(self.len);
+		self.len -= count;
+		if self.len == 0 {
+			self.start = 0;
+			return;
+		}
+		self.start += count;
+		if self.start >= self.buf.len() {
+			self.start -= self.buf.len();
+		}
+	}
+
+	/// Discards all buffered data.
+	pub fn clear(&mut self) {
+		self.start = 0;
+		self.len = 0;
+	}
+
+	/// Appends the data of a write request, returning a response for the
+	/// number of bytes appended.
+	///
+	/// If the buffer doesn't have room for all of the request's data then
+	/// the response will be a short write. Check [`RingBuffer::available`]
+	/// beforehand to reject or defer writes to a full buffer.
+	pub fn push_write(&mut self, request: &WriteRequest) -> WriteResponse {
+		let count = self.push(request.value());
+		WriteResponse::new(count as u32)
+	}
+
+	/// Replies to a read request with buffered data, returning the number of
+	/// bytes sent.
+	///
+	/// The reply contains up to `size` bytes, which are removed from the
+	/// buffer only if the reply was sent successfully. The size is usually
+	/// [`ReadRequest::size`], and can be saved to reply to a read request
+	/// after more data has been written.
+	///
+	/// An empty buffer is sent as an empty reply, which the reader will see
+	/// as end-of-file. Check [`RingBuffer::is_empty`] beforehand to reject or
+	/// defer reads from an empty buffer.
+	///
+	/// [`ReadRequest::size`]: crate::server::ReadRequest::size
+	pub fn send_read_reply<S: CuseSocket>(
+		&mut self,
+		size: u32,
+		reply: CuseReplySender<'_, S>,
+	) -> Result<usize, SendError<S::Error>> {
+		let max_len = usize::try_from(size).unwrap_or(usize::MAX);
+		let (head, tail) = self.as_slices();
+		let head = &head[..head.len().min(max_len)];
+		let tail = &tail[..tail.len().min(max_len - head.len())];
+		let count = head.len() + tail.len();
+		reply.inner.send_2(head, tail)?;
+		self.consume(count);
+		Ok(count)
+	}
+}
+
+impl fmt::Debug for RingBuffer<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("RingBuffer")
+			.field("capacity", &self.capacity())
+			.field("len", &self.len())
+			.finish()
+	}
+}
+
+// }}}
+
+// PollWaiters {{{
+
+/// A fixed-capacity set of pollers waiting for a device to become ready.
+///
+/// When a poll request finds the device isn't ready, the client may ask to
+/// be notified when the device's readiness changes. `PollWaiters` records
+/// the handles of such requests, and [`notify`] sends the notifications.
+///
+/// [`notify`]: PollWaiters::notify
+pub struct PollWaiters<const N: usize> {
+	handles: [Option<PollHandle>; N],
+}
+
+impl<const N: usize> PollWaiters<N> {
+	/// Creates an empty `PollWaiters`.
+	#[must_use]
+	pub const fn new() -> PollWaiters<N> {
+		Self { handles: [None; N] }
+	}
+
+	/// Returns whether there are no pollers waiting for a notification.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.handles.iter().all(Option::is_none)
+	}
+
+	/// Returns a response to a poll request, given the events for which the
+	/// device is currently ready.
+	///
+	/// If none of the requested events are ready and the client asked to be
+	/// notified, the request's poll handle is recorded. If there is no room
+	/// to record it then every requested event is reported as ready, so
+	/// that the client retries its I/O instead of waiting forever.
+	pub fn poll(
+		&mut self,
+		request: &PollRequest,
+		ready: crate::PollEvents,
+	) -> PollResponse {
+		let requested = request.poll_events();
+		if ready & requested != 0 {
+			return PollResponse::new(ready & requested);
+		}
+		let flags = request.flags();
+		if !flags.get(crate::PollRequestFlag::SCHEDULE_NOTIFY) {
+			return PollResponse::new(0);
+		}
+		if !self.insert(request.poll_handle()) {
+			return PollResponse::new(requested);
+		}
+		PollResponse::new(0)
+	}
+
+	fn insert(&mut self, handle: PollHandle) -> bool {
+		if self.handles.contains(&Some(handle)) {
+			return true;
+		}
+		match self.handles.iter_mut().find(|slot| slot.is_none()) {
+			Some(slot) => {
+				*slot = Some(handle);
+				true
+			},
+			None => false,
+		}
+	}
+
+	/// Notifies every waiting poller, which will then poll the device again.
+	///
+	/// Pollers are removed from the set once their notification is sent.
+	pub fn notify<S: CuseSocket>(
+		&mut self,
+		conn: &CuseConnection<S>,
+	) -> Result<(), SendError<S::Error>> {
+		for slot in &mut self.handles {
+			if let Some(handle) = *slot {
+				conn.notify_poll(crate::NotifyPoll::new(handle))?;
+				*slot = None;
+			}
+		}
+		Ok(())
+	}
+}
+
+impl<const N: usize> fmt::Debug for PollWaiters<N> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		let waiting = self.handles.iter().flatten().count();
+		fmt.debug_struct("PollWaiters")
+			.field("capacity", &N)
+			.field("waiting", &waiting)
+			.finish()
+	}
+}
+
+// }}}
@@ -0,0 +1,341 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+use core::mem::size_of;
+
+use fuse::cuse::{PollWaiters, RingBuffer};
+use fuse::kernel;
+use fuse::kernel::fuse_opcode;
+use fuse::server;
+use fuse::testing::{KernelSocket, ServerSocket, SocketPair};
+
+use fuse_testutil as testutil;
+use fuse_testutil::MessageBuilder;
+
+const POLLIN: u32 = 0x1;
+const POLLOUT: u32 = 0x4;
+
+fn connect<'b, 'a>(
+	pair: &'b SocketPair<'a>,
+) -> server::CuseConnection<ServerSocket<'b, 'a>> {
+	let init = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_opcode::CUSE_INIT;
+			h.unique = 1;
+		})
+		.push_sized(&testutil::new!(kernel::cuse_init_in {
+			major: kernel::FUSE_KERNEL_VERSION,
+			minor: kernel::FUSE_KERNEL_MINOR_VERSION,
+		}))
+		.build();
+	pair.kernel().push_request(&init).unwrap();
+
+	let device_name = fuse::CuseDeviceName::new("loopback").unwrap();
+	let device_number = fuse::CuseDeviceNumber { major: 10, minor: 20 };
+	let conn = server::CuseConnection::connect(
+		pair.server(),
+		device_name,
+		device_number,
+		|_, _| {},
+	).unwrap();
+
+	let mut reply = [0u8; 512];
+	pair.kernel().pop_reply(&mut reply).unwrap();
+	conn
+}
+
+fn request(opcode: fuse_opcode, unique: u64, body: &[&[u8]]) -> Vec<u8> {
+	let mut builder = MessageBuilder::new().set_header(|h| {
+		h.opcode = opcode;
+		h.unique = unique;
+		h.nodeid = 1;
+	});
+	for chunk in body {
+		builder = builder.push_bytes(chunk);
+	}
+	builder.build()
+}
+
+fn as_bytes<T>(value: &T) -> &[u8] {
+	let ptr = (value as *const T).cast::<u8>();
+	unsafe { core::slice::from_raw_parts(ptr, size_of::<T>()) }
+}
+
+fn pop_reply(kernel: &KernelSocket) -> (kernel::fuse_out_header, Vec<u8>) {
+	let mut reply = [0u8; 512];
+	let reply_len = kernel.pop_reply(&mut reply).unwrap();
+	let reply = &reply[..reply_len];
+	let header_len = size_of::<kernel::fuse_out_header>();
+	let header = unsafe {
+		reply.as_ptr().cast::<kernel::fuse_out_header>().read_unaligned()
+	};
+	assert_eq!(header.len as usize, reply_len);
+	(header, reply[header_len..].to_vec())
+}
+
+#[test]
+fn ring_buffer() {
+	let mut storage = [0u8; 8];
+	let mut ring = RingBuffer::new(&mut storage);
+	assert_eq!(ring.capacity(), 8);
+	assert!(ring.is_empty());
+
+	assert_eq!(ring.push(b"abcdef"), 6);
+	assert_eq!(ring.len(), 6);
+	assert_eq!(ring.available(), 2);
+
+	let mut buf = [0u8; 4];
+	assert_eq!(ring.pop(&mut buf), 4);
+	assert_eq!(&buf, b"abcd");
+
+	// Data wraps around the end of the storage.
+	assert_eq!(ring.push(b"ghijklmnop"), 6);
+	assert!(ring.is_full());
+	assert_eq!(ring.as_slices(), (&b"efgh"[..], &b"ijkl"[..]));
+
+	let mut buf = [0u8; 16];
+	assert_eq!(ring.pop(&mut buf), 8);
+	assert_eq!(&buf[..8], b"efghijkl");
+	assert!(ring.is_empty());
+	assert_eq!(ring.as_slices(), (&b""[..], &b""[..]));
+
+	assert_eq!(ring.push(b"xyz"), 3);
+	ring.consume(2);
+	assert_eq!(ring.as_slices(), (&b"z"[..], &b""[..]));
+	ring.consume(10);
+	assert!(ring.is_empty());
+
+	ring.push(b"abc");
+	ring.clear();
+	assert!(ring.is_empty());
+	assert_eq!(ring.available(), 8);
+}
+
+#[test]
+fn ring_buffer_zero_capacity() {
+	let mut ring = RingBuffer::new(&mut []);
+	assert!(ring.is_empty());
+	assert!(ring.is_full());
+	assert_eq!(ring.push(b"abc"), 0);
+	assert_eq!(ring.pop(&mut [0u8; 4]), 0);
+	assert_eq!(ring.as_slices(), (&b""[..], &b""[..]));
+}
+
+#[test]
+fn ring_buffer_read_write() {
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	let conn = connect(&pair);
+	let kernel_socket = pair.kernel();
+
+	let write_in = testutil::new!(kernel::fuse_write_in {
+		size: 10,
+	});
+	let read_in = testutil::new!(kernel::fuse_read_in {
+		size: 4,
+	});
+	let requests = [
+		request(fuse_opcode::FUSE_WRITE, 2, &[
+			as_bytes(&write_in),
+			b"0123456789",
+		]),
+		request(fuse_opcode::FUSE_READ, 3, &[as_bytes(&read_in)]),
+		request(fuse_opcode::FUSE_WRITE, 4, &[
+			as_bytes(&write_in),
+			b"abcdefghij",
+		]),
+		request(fuse_opcode::FUSE_READ, 5, &[as_bytes(&read_in)]),
+	];
+	for request in &requests {
+		kernel_socket.push_request(request).unwrap();
+	}
+
+	let mut storage = [0u8; 16];
+	let mut ring = RingBuffer::new(&mut storage);
+	let mut buf = fuse::io::MinReadBuffer::new();
+
+	// Write 10 bytes.
+	let request = conn.recv(buf.as_aligned_slice_mut()).unwrap();
+	let write = server::WriteRequest::try_from(request).unwrap();
+	let response = ring.push_write(&write);
+	assert_eq!(response.size(), 10);
+	conn.reply(request.id()).ok(&response).unwrap();
+
+	// Read 4 bytes.
+	let request = conn.recv(buf.as_aligned_slice_mut()).unwrap();
+	let read = server::ReadRequest::try_from(request).unwrap();
+	let sent = ring.send_read_reply(read.size(), conn.reply(request.id()));
+	assert_eq!(sent, Ok(4));
+
+	// Write 10 bytes, filling the buffer.
+	let request = conn.recv(buf.as_aligned_slice_mut()).unwrap();
+	let write = server::WriteRequest::try_from(request).unwrap();
+	let response = ring.push_write(&write);
+	assert_eq!(response.size(), 10);
+	assert!(ring.is_full());
+	conn.reply(request.id()).ok(&response).unwrap();
+
+	// Read 4 bytes.
+	let request = conn.recv(buf.as_aligned_slice_mut()).unwrap();
+	let read = server::ReadRequest::try_from(request).unwrap();
+	let sent = ring.send_read_reply(read.size(), conn.reply(request.id()));
+	assert_eq!(sent, Ok(4));
+	assert_eq!(ring.as_slices(), (&b"89abcdef"[..], &b"ghij"[..]));
+
+	let (header, body) = pop_reply(&kernel_socket);
+	assert_eq!(header.unique, 2);
+	assert_eq!(body.len(), size_of::<kernel::fuse_write_out>());
+	let (header, body) = pop_reply(&kernel_socket);
+	assert_eq!(header.unique, 3);
+	assert_eq!(body, b"0123");
+	let (header, _) = pop_reply(&kernel_socket);
+	assert_eq!(header.unique, 4);
+	let (header, body) = pop_reply(&kernel_socket);
+	assert_eq!(header.unique, 5);
+	assert_eq!(body, b"4567");
+	assert!(kernel_socket.replies_done());
+}
+
+#[test]
+fn ring_buffer_read_wrapped() {
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	let conn = connect(&pair);
+	let kernel_socket = pair.kernel();
+
+	let read_in = testutil::new!(kernel::fuse_read_in {
+		size: 100,
+	});
+	kernel_socket.push_request(&request(fuse_opcode::FUSE_READ, 2, &[
+		as_bytes(&read_in),
+	])).unwrap();
+
+	let mut storage = [0u8; 8];
+	let mut ring = RingBuffer::new(&mut storage);
+	ring.push(b"abcdef");
+	ring.consume(4);
+	ring.push(b"ghijkl");
+	assert_eq!(ring.as_slices(), (&b"efgh"[..], &b"ijkl"[..]));
+
+	let mut buf = fuse::io::MinReadBuffer::new();
+	let request = conn.recv(buf.as_aligned_slice_mut()).unwrap();
+	let read = server::ReadRequest::try_from(request).unwrap();
+	let sent = ring.send_read_reply(read.size(), conn.reply(request.id()));
+	assert_eq!(sent, Ok(8));
+	assert!(ring.is_empty());
+
+	let (header, body) = pop_reply(&kernel_socket);
+	assert_eq!(header.unique, 2);
+	assert_eq!(body, b"efghijkl");
+}
+
+#[test]
+fn poll_waiters() {
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	let conn = connect(&pair);
+	let kernel_socket = pair.kernel();
+
+	let poll_in = |kh: u64, flags: u32| {
+		testutil::new!(kernel::fuse_poll_in {
+			kh: kh,
+			flags: flags,
+			events: POLLIN,
+		})
+	};
+	let schedule = kernel::FUSE_POLL_SCHEDULE_NOTIFY;
+	let requests = [
+		// Not ready, no notification requested.
+		poll_in(10, 0),
+		// Not ready, notification scheduled.
+		poll_in(11, schedule),
+		poll_in(11, schedule),
+		poll_in(12, schedule),
+		// Not ready, no room for another waiter.
+		poll_in(13, schedule),
+	];
+	for (ii, poll_in) in requests.iter().enumerate() {
+		let request_id = ii as u64 + 2;
+		kernel_socket.push_request(&request(
+			fuse_opcode::FUSE_POLL,
+			request_id,
+			&[as_bytes(poll_in)],
+		)).unwrap();
+	}
+
+	let mut waiters = PollWaiters::<2>::new();
+	assert!(waiters.is_empty());
+	let mut buf = fuse::io::MinReadBuffer::new();
+	let mut events = Vec::new();
+	for _ in 0..requests.len() {
+		let request = conn.recv(buf.as_aligned_slice_mut()).unwrap();
+		let poll = server::PollRequest::try_from(request).unwrap();
+		let response = waiters.poll(&poll, POLLOUT);
+		events.push(response.poll_events());
+		conn.reply(request.id()).ok(&response).unwrap();
+	}
+	assert_eq!(events, [0, 0, 0, 0, POLLIN]);
+	assert!(!waiters.is_empty());
+
+	waiters.notify(&conn).unwrap();
+	assert!(waiters.is_empty());
+
+	for _ in 0..requests.len() {
+		pop_reply(&kernel_socket);
+	}
+	let mut notified = Vec::new();
+	for _ in 0..2 {
+		let (header, body) = pop_reply(&kernel_socket);
+		assert_eq!(header.unique, 0);
+		let notify_code = kernel::fuse_notify_code::FUSE_NOTIFY_POLL;
+		assert_eq!(header.error, notify_code.0 as i32);
+		let notify_out = unsafe {
+			body.as_ptr()
+				.cast::<kernel::fuse_notify_poll_wakeup_out>()
+				.read_unaligned()
+		};
+		notified.push(notify_out.kh);
+	}
+	assert_eq!(notified, [11, 12]);
+	assert!(kernel_socket.replies_done());
+}
+
+#[test]
+fn poll_waiters_ready() {
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	let conn = connect(&pair);
+
+	let poll_in = testutil::new!(kernel::fuse_poll_in {
+		kh: 10,
+		flags: kernel::FUSE_POLL_SCHEDULE_NOTIFY,
+		events: POLLIN | POLLOUT,
+	});
+	pair.kernel().push_request(&request(fuse_opcode::FUSE_POLL, 2, &[
+		as_bytes(&poll_in),
+	])).unwrap();
+
+	let mut waiters = PollWaiters::<1>::new();
+	let mut buf = fuse::io::MinReadBuffer::new();
+	let request = conn.recv(buf.as_aligned_slice_mut()).unwrap();
+	let poll = server::PollRequest::try_from(request).unwrap();
+	let response = waiters.poll(&poll, POLLOUT | 0x8);
+	assert_eq!(response.poll_events(), POLLOUT);
+	assert!(waiters.is_empty());
+}
@@ -44,6 +44,12 @@ impl Entry {
 		}
 	}
 
+	#[inline]
+	#[must_use]
+	pub(crate) unsafe fn from_raw(raw: kernel::fuse_entry_out) -> Entry {
+		Self { raw }
+	}
+
 	#[inline]
 	#[must_use]
 	pub(crate) unsafe fn from_ref(raw: &kernel::fuse_entry_out) -> &Self {
@@ -129,6 +135,25 @@ impl fmt::Debug for Entry {
 	}
 }
 
+serde_struct!(Entry, Entry::new(NodeAttr::new(crate::NodeId::ROOT)), {
+	generation: u64 = (Entry::generation, Entry::set_generation),
+	attributes: NodeAttr = (
+		Entry::attributes,
+		|entry: &mut Entry, attr: NodeAttr| {
+			entry.raw.nodeid = attr.raw.ino;
+			entry.raw.attr = attr.raw;
+		},
+	),
+	cache_timeout: time::Duration = (
+		Entry::cache_timeout,
+		Entry::set_cache_timeout,
+	),
+	attribute_cache_timeout: time::Duration = (
+		Entry::attribute_cache_timeout,
+		Entry::set_attribute_cache_timeout,
+	),
+});
+
 impl server::FuseReply for Entry {
 	#[inline]
 	fn send_to<S: server::FuseSocket>(
@@ -0,0 +1,459 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Portable error numbers.
+//!
+//! When the `std` feature is enabled, this module also provides conversions
+//! between [`crate::Error`] and [`std::io::Error`].
+
+use core::fmt;
+
+#[cfg(target_os = "freebsd")]
+use freebsd_errno as os_errno;
+
+#[cfg(target_os = "linux")]
+use linux_errno as os_errno;
+
+// Errno {{{
+
+/// Portable error numbers.
+///
+/// The numeric values of error numbers vary between operating systems, and
+/// on Linux they also vary between architectures. An `Errno` names an error
+/// without reference to its value, and is converted to a [`crate::Error`]
+/// with the correct value for the target platform.
+///
+/// Conversion into [`crate::Error`] is only available on platforms supported
+/// by [`fuse::os`](crate::os). Reply methods that accept an error, such as
+/// [`FuseReplySender::err`](crate::server::FuseReplySender::err), accept an
+/// `Errno` directly.
+///
+/// ```
+/// # #[cfg(any(target_os = "freebsd", target_os = "linux"))]
+/// # {
+/// let err: fuse::Error = fuse::Errno::ENOENT.into();
+/// assert_eq!(err, fuse::os::OsError::NOT_FOUND);
+/// # }
+/// ```
+#[allow(clippy::upper_case_acronyms)]
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Errno {
+	/// The operation is not permitted.
+	EPERM,
+
+	/// The requested file or directory does not exist.
+	ENOENT,
+
+	/// The operation was interrupted.
+	EINTR,
+
+	/// An I/O error occurred.
+	EIO,
+
+	/// The device or address does not exist.
+	ENXIO,
+
+	/// An argument list or value is too long.
+	E2BIG,
+
+	/// A file handle is not valid.
+	EBADF,
+
+	/// The resource is temporarily unavailable.
+	EAGAIN,
+
+	/// Not enough memory is available.
+	ENOMEM,
+
+	/// Permission to access the node was denied.
+	EACCES,
+
+	/// An address is not valid.
+	EFAULT,
+
+	/// The device or resource is busy.
+	EBUSY,
+
+	/// The file or directory already exists.
+	EEXIST,
+
+	/// The operation would cross a device boundary.
+	EXDEV,
+
+	/// The device does not exist.
+	ENODEV,
+
+	/// The node is not a directory.
+	ENOTDIR,
+
+	/// The node is a directory.
+	EISDIR,
+
+	/// An argument is not valid.
+	EINVAL,
+
+	/// Too many files are open in the system.
+	ENFILE,
+
+	/// Too many files are open in the process.
+	EMFILE,
+
+	/// The ioctl is not appropriate for the device.
+	ENOTTY,
+
+	/// The file is an executable that is being run.
+	ETXTBSY,
+
+	/// The file is too large.
+	EFBIG,
+
+	/// No space is left on the device.
+	ENOSPC,
+
+	/// The file does not support seeking.
+	ESPIPE,
+
+	/// The filesystem is read-only.
+	EROFS,
+
+	/// The node has too many links.
+	EMLINK,
+
+	/// The other end of a pipe or socket was closed.
+	EPIPE,
+
+	/// A result is out of range.
+	ERANGE,
+
+	/// The operation would cause a deadlock.
+	EDEADLK,
+
+	/// A file name is too long.
+	ENAMETOOLONG,
+
+	/// No locks are available.
+	ENOLCK,
+
+	/// The operation is not implemented.
+	ENOSYS,
+
+	/// The directory is not empty.
+	ENOTEMPTY,
+
+	/// Too many levels of symbolic links were encountered.
+	ELOOP,
+
+	/// The extended attribute does not exist.
+	///
+	/// This maps to `ENODATA` on Linux.
+	ENOATTR,
+
+	/// A value is too large to store in its data type.
+	EOVERFLOW,
+
+	/// The operation is not supported.
+	EOPNOTSUPP,
+
+	/// The operation timed out.
+	ETIMEDOUT,
+
+	/// A file handle is stale.
+	ESTALE,
+
+	/// The disk quota was exceeded.
+	EDQUOT,
+
+	/// A protocol error occurred.
+	EPROTO,
+
+	/// The operation was canceled.
+	ECANCELED,
+
+	/// A byte sequence is not valid.
+	EILSEQ,
+
+	/// The socket or connection is not connected.
+	ENOTCONN,
+}
+
+impl Errno {
+	/// Alias for [`Errno::EINTR`], matching [`OsError::INTERRUPTED`].
+	///
+	/// [`OsError::INTERRUPTED`]: crate::os::OsError
+	pub const INTERRUPTED: Errno = Errno::EINTR;
+
+	/// Alias for [`Errno::EINVAL`], matching [`OsError::INVALID_ARGUMENT`].
+	///
+	/// [`OsError::INVALID_ARGUMENT`]: crate::os::OsError
+	pub const INVALID_ARGUMENT: Errno = Errno::EINVAL;
+
+	/// Alias for [`Errno::EISDIR`], matching [`OsError::IS_DIRECTORY`].
+	///
+	/// [`OsError::IS_DIRECTORY`]: crate::os::OsError
+	pub const IS_DIRECTORY: Errno = Errno::EISDIR;
+
+	/// Alias for [`Errno::ENOTDIR`], matching [`OsError::NOT_DIRECTORY`].
+	///
+	/// [`OsError::NOT_DIRECTORY`]: crate::os::OsError
+	pub const NOT_DIRECTORY: Errno = Errno::ENOTDIR;
+
+	/// Alias for [`Errno::ENOENT`], matching [`OsError::NOT_FOUND`].
+	///
+	/// [`OsError::NOT_FOUND`]: crate::os::OsError
+	pub const NOT_FOUND: Errno = Errno::ENOENT;
+
+	/// Alias for [`Errno::EOPNOTSUPP`], matching [`OsError::NOT_SUPPORTED`].
+	///
+	/// [`OsError::NOT_SUPPORTED`]: crate::os::OsError
+	pub const NOT_SUPPORTED: Errno = Errno::EOPNOTSUPP;
+
+	/// Alias for [`Errno::EOVERFLOW`], matching [`OsError::OVERFLOW`].
+	///
+	/// [`OsError::OVERFLOW`]: crate::os::OsError
+	pub const OVERFLOW: Errno = Errno::EOVERFLOW;
+
+	/// Alias for [`Errno::EPROTO`], matching [`OsError::PROTOCOL_ERROR`].
+	///
+	/// [`OsError::PROTOCOL_ERROR`]: crate::os::OsError
+	pub const PROTOCOL_ERROR: Errno = Errno::EPROTO;
+
+	/// Alias for [`Errno::EAGAIN`], matching [`OsError::UNAVAILABLE`].
+	///
+	/// [`OsError::UNAVAILABLE`]: crate::os::OsError
+	pub const UNAVAILABLE: Errno = Errno::EAGAIN;
+
+	/// Alias for [`Errno::ENOSYS`], matching [`OsError::UNIMPLEMENTED`].
+	///
+	/// [`OsError::UNIMPLEMENTED`]: crate::os::OsError
+	pub const UNIMPLEMENTED: Errno = Errno::ENOSYS;
+
+	/// Alias for [`Errno::ENOATTR`], matching [`OsError::XATTR_NOT_FOUND`].
+	///
+	/// [`OsError::XATTR_NOT_FOUND`]: crate::os::OsError
+	pub const XATTR_NOT_FOUND: Errno = Errno::ENOATTR;
+
+	/// Alias for [`Errno::E2BIG`], matching [`OsError::XATTR_TOO_BIG`].
+	///
+	/// [`OsError::XATTR_TOO_BIG`]: crate::os::OsError
+	pub const XATTR_TOO_BIG: Errno = Errno::E2BIG;
+}
+
+#[cfg(any(target_os = "freebsd", target_os = "linux"))]
+impl Errno {
+	/// Returns the `Errno` with the same value as a FUSE error.
+	///
+	/// Returns `None` if the error's value isn't the value of any `Errno`
+	/// on the target platform.
+	#[must_use]
+	pub fn from_error(err: crate::Error) -> Option<Errno> {
+		ALL.iter().copied().find(|&errno| crate::Error::from(errno) == err)
+	}
+}
+
+impl fmt::Display for Errno {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt::Debug::fmt(self, fmt)
+	}
+}
+
+#[cfg(any(target_os = "freebsd", target_os = "linux"))]
+const ALL: [Errno; 45] = [
+	Errno::EPERM,
+	Errno::ENOENT,
+	Errno::EINTR,
+	Errno::EIO,
+	Errno::ENXIO,
+	Errno::E2BIG,
+	Errno::EBADF,
+	Errno::EAGAIN,
+	Errno::ENOMEM,
+	Errno::EACCES,
+	Errno::EFAULT,
+	Errno::EBUSY,
+	Errno::EEXIST,
+	Errno::EXDEV,
+	Errno::ENODEV,
+	Errno::ENOTDIR,
+	Errno::EISDIR,
+	Errno::EINVAL,
+	Errno::ENFILE,
+	Errno::EMFILE,
+	Errno::ENOTTY,
+	Errno::ETXTBSY,
+	Errno::EFBIG,
+	Errno::ENOSPC,
+	Errno::ESPIPE,
+	Errno::EROFS,
+	Errno::EMLINK,
+	Errno::EPIPE,
+	Errno::ERANGE,
+	Errno::EDEADLK,
+	Errno::ENAMETOOLONG,
+	Errno::ENOLCK,
+	Errno::ENOSYS,
+	Errno::ENOTEMPTY,
+	Errno::ELOOP,
+	Errno::ENOATTR,
+	Errno::EOVERFLOW,
+	Errno::EOPNOTSUPP,
+	Errno::ETIMEDOUT,
+	Errno::ESTALE,
+	Errno::EDQUOT,
+	Errno::EPROTO,
+	Errno::ECANCELED,
+	Errno::EILSEQ,
+	Errno::ENOTCONN,
+];
+
+#[cfg(any(target_os = "freebsd", target_os = "linux"))]
+impl From<Errno> for crate::os::OsError {
+	#[inline]
+	fn from(errno: Errno) -> crate::os::OsError {
+		crate::os::OsError(os_errno(errno))
+	}
+}
+
+#[cfg(any(target_os = "freebsd", target_os = "linux"))]
+impl From<Errno> for crate::Error {
+	#[inline]
+	fn from(errno: Errno) -> crate::Error {
+		crate::os::OsError::from(errno).into()
+	}
+}
+
+#[cfg(target_os = "freebsd")]
+const XATTR_NOT_FOUND: os_errno::Error = os_errno::ENOATTR;
+
+#[cfg(target_os = "linux")]
+const XATTR_NOT_FOUND: os_errno::Error = os_errno::ENODATA;
+
+#[cfg(any(target_os = "freebsd", target_os = "linux"))]
+const fn os_errno(errno: Errno) -> os_errno::Error {
+	match errno {
+		Errno::EPERM        => os_errno::EPERM,
+		Errno::ENOENT       => os_errno::ENOENT,
+		Errno::EINTR        => os_errno::EINTR,
+		Errno::EIO          => os_errno::EIO,
+		Errno::ENXIO        => os_errno::ENXIO,
+		Errno::E2BIG        => os_errno::E2BIG,
+		Errno::EBADF        => os_errno::EBADF,
+		Errno::EAGAIN       => os_errno::EAGAIN,
+		Errno::ENOMEM       => os_errno::ENOMEM,
+		Errno::EACCES       => os_errno::EACCES,
+		Errno::EFAULT       => os_errno::EFAULT,
+		Errno::EBUSY        => os_errno::EBUSY,
+		Errno::EEXIST       => os_errno::EEXIST,
+		Errno::EXDEV        => os_errno::EXDEV,
+		Errno::ENODEV       => os_errno::ENODEV,
+		Errno::ENOTDIR      => os_errno::ENOTDIR,
+		Errno::EISDIR       => os_errno::EISDIR,
+		Errno::EINVAL       => os_errno::EINVAL,
+		Errno::ENFILE       => os_errno::ENFILE,
+		Errno::EMFILE       => os_errno::EMFILE,
+		Errno::ENOTTY       => os_errno::ENOTTY,
+		Errno::ETXTBSY      => os_errno::ETXTBSY,
+		Errno::EFBIG        => os_errno::EFBIG,
+		Errno::ENOSPC       => os_errno::ENOSPC,
+		Errno::ESPIPE       => os_errno::ESPIPE,
+		Errno::EROFS        => os_errno::EROFS,
+		Errno::EMLINK       => os_errno::EMLINK,
+		Errno::EPIPE        => os_errno::EPIPE,
+		Errno::ERANGE       => os_errno::ERANGE,
+		Errno::EDEADLK      => os_errno::EDEADLK,
+		Errno::ENAMETOOLONG => os_errno::ENAMETOOLONG,
+		Errno::ENOLCK       => os_errno::ENOLCK,
+		Errno::ENOSYS       => os_errno::ENOSYS,
+		Errno::ENOTEMPTY    => os_errno::ENOTEMPTY,
+		Errno::ELOOP        => os_errno::ELOOP,
+		Errno::ENOATTR      => XATTR_NOT_FOUND,
+		Errno::EOVERFLOW    => os_errno::EOVERFLOW,
+		Errno::EOPNOTSUPP   => os_errno::EOPNOTSUPP,
+		Errno::ETIMEDOUT    => os_errno::ETIMEDOUT,
+		Errno::ESTALE       => os_errno::ESTALE,
+		Errno::EDQUOT       => os_errno::EDQUOT,
+		Errno::EPROTO       => os_errno::EPROTO,
+		Errno::ECANCELED    => os_errno::ECANCELED,
+		Errno::EILSEQ       => os_errno::EILSEQ,
+		Errno::ENOTCONN     => os_errno::ENOTCONN,
+	}
+}
+
+// }}}
+
+// std::io::Error {{{
+
+/// Converts an I/O error into a FUSE error.
+///
+/// If the I/O error has a raw OS error number, it is used directly.
+/// Otherwise the error number is chosen based on the error's
+/// [`ErrorKind`](std::io::ErrorKind), falling back to `EIO`.
+#[cfg(all(
+	feature = "std",
+	any(target_os = "freebsd", target_os = "linux"),
+))]
+impl From<std::io::Error> for crate::Error {
+	fn from(err: std::io::Error) -> crate::Error {
+		use core::num::NonZeroI32;
+
+		if let Some(errno) = err.raw_os_error() {
+			if errno > 0 && errno < 512 {
+				let errno_neg = errno.wrapping_neg();
+				return crate::Error(unsafe {
+					NonZeroI32::new_unchecked(errno_neg)
+				});
+			}
+		}
+		io_error_kind_errno(err.kind()).into()
+	}
+}
+
+/// Converts a FUSE error into an I/O error.
+///
+/// The FUSE error number is assumed to be an error number of the current
+/// platform, which is true for errors constructed from [`Errno`] or
+/// [`OsError`](crate::os::OsError).
+#[cfg(feature = "std")]
+impl From<crate::Error> for std::io::Error {
+	fn from(err: crate::Error) -> std::io::Error {
+		std::io::Error::from_raw_os_error(err.0.get().wrapping_neg())
+	}
+}
+
+#[cfg(all(
+	feature = "std",
+	any(target_os = "freebsd", target_os = "linux"),
+))]
+fn io_error_kind_errno(kind: std::io::ErrorKind) -> Errno {
+	use std::io::ErrorKind;
+	match kind {
+		ErrorKind::NotFound => Errno::ENOENT,
+		ErrorKind::PermissionDenied => Errno::EACCES,
+		ErrorKind::AlreadyExists => Errno::EEXIST,
+		ErrorKind::WouldBlock => Errno::EAGAIN,
+		ErrorKind::InvalidInput => Errno::EINVAL,
+		ErrorKind::InvalidData => Errno::EINVAL,
+		ErrorKind::TimedOut => Errno::ETIMEDOUT,
+		ErrorKind::Interrupted => Errno::EINTR,
+		ErrorKind::Unsupported => Errno::EOPNOTSUPP,
+		ErrorKind::OutOfMemory => Errno::ENOMEM,
+		ErrorKind::BrokenPipe => Errno::EPIPE,
+		ErrorKind::NotConnected => Errno::ENOTCONN,
+		_ => Errno::EIO,
+	}
+}
+
+// }}}
@@ -0,0 +1,119 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+
+use core::num::NonZeroI32;
+
+use fuse::Errno;
+
+fn fuse_error(errno: i32) -> fuse::Error {
+	fuse::Error(NonZeroI32::new(-errno).unwrap())
+}
+
+#[cfg(target_os = "freebsd")]
+const ENOATTR: i32 = libc::ENOATTR;
+
+#[cfg(target_os = "linux")]
+const ENOATTR: i32 = libc::ENODATA;
+
+#[test]
+fn errno_values() {
+	let errnos = [
+		(Errno::EPERM, libc::EPERM),
+		(Errno::ENOENT, libc::ENOENT),
+		(Errno::EINTR, libc::EINTR),
+		(Errno::EIO, libc::EIO),
+		(Errno::ENXIO, libc::ENXIO),
+		(Errno::E2BIG, libc::E2BIG),
+		(Errno::EBADF, libc::EBADF),
+		(Errno::EAGAIN, libc::EAGAIN),
+		(Errno::ENOMEM, libc::ENOMEM),
+		(Errno::EACCES, libc::EACCES),
+		(Errno::EFAULT, libc::EFAULT),
+		(Errno::EBUSY, libc::EBUSY),
+		(Errno::EEXIST, libc::EEXIST),
+		(Errno::EXDEV, libc::EXDEV),
+		(Errno::ENODEV, libc::ENODEV),
+		(Errno::ENOTDIR, libc::ENOTDIR),
+		(Errno::EISDIR, libc::EISDIR),
+		(Errno::EINVAL, libc::EINVAL),
+		(Errno::ENFILE, libc::ENFILE),
+		(Errno::EMFILE, libc::EMFILE),
+		(Errno::ENOTTY, libc::ENOTTY),
+		(Errno::ETXTBSY, libc::ETXTBSY),
+		(Errno::EFBIG, libc::EFBIG),
+		(Errno::ENOSPC, libc::ENOSPC),
+		(Errno::ESPIPE, libc::ESPIPE),
+		(Errno::EROFS, libc::EROFS),
+		(Errno::EMLINK, libc::EMLINK),
+		(Errno::EPIPE, libc::EPIPE),
+		(Errno::ERANGE, libc::ERANGE),
+		(Errno::EDEADLK, libc::EDEADLK),
+		(Errno::ENAMETOOLONG, libc::ENAMETOOLONG),
+		(Errno::ENOLCK, libc::ENOLCK),
+		(Errno::ENOSYS, libc::ENOSYS),
+		(Errno::ENOTEMPTY, libc::ENOTEMPTY),
+		(Errno::ELOOP, libc::ELOOP),
+		(Errno::ENOATTR, ENOATTR),
+		(Errno::EOVERFLOW, libc::EOVERFLOW),
+		(Errno::EOPNOTSUPP, libc::EOPNOTSUPP),
+		(Errno::ETIMEDOUT, libc::ETIMEDOUT),
+		(Errno::ESTALE, libc::ESTALE),
+		(Errno::EDQUOT, libc::EDQUOT),
+		(Errno::EPROTO, libc::EPROTO),
+		(Errno::ECANCELED, libc::ECANCELED),
+		(Errno::EILSEQ, libc::EILSEQ),
+		(Errno::ENOTCONN, libc::ENOTCONN),
+	];
+	for (errno, value) in errnos {
+		assert_eq!(fuse::Error::from(errno), fuse_error(value), "{}", errno);
+		assert_eq!(Errno::from_error(fuse_error(value)), Some(errno));
+	}
+}
+
+#[test]
+fn errno_aliases() {
+	use fuse::os::OsError;
+
+	let aliases = [
+		(Errno::INTERRUPTED, OsError::INTERRUPTED),
+		(Errno::INVALID_ARGUMENT, OsError::INVALID_ARGUMENT),
+		(Errno::IS_DIRECTORY, OsError::IS_DIRECTORY),
+		(Errno::NOT_DIRECTORY, OsError::NOT_DIRECTORY),
+		(Errno::NOT_FOUND, OsError::NOT_FOUND),
+		(Errno::NOT_SUPPORTED, OsError::NOT_SUPPORTED),
+		(Errno::OVERFLOW, OsError::OVERFLOW),
+		(Errno::PROTOCOL_ERROR, OsError::PROTOCOL_ERROR),
+		(Errno::UNAVAILABLE, OsError::UNAVAILABLE),
+		(Errno::UNIMPLEMENTED, OsError::UNIMPLEMENTED),
+		(Errno::XATTR_NOT_FOUND, OsError::XATTR_NOT_FOUND),
+		(Errno::XATTR_TOO_BIG, OsError::XATTR_TOO_BIG),
+	];
+	for (errno, err) in aliases {
+		assert_eq!(fuse::Error::from(errno), err, "{}", errno);
+	}
+}
+
+#[test]
+fn errno_names() {
+	let err = fuse_error(libc::ENOENT);
+	let errno = Errno::from_error(err).unwrap();
+	assert_eq!(format!("{}", errno), "ENOENT");
+	assert_eq!(format!("{:?}", errno), "ENOENT");
+
+	// Valid error numbers without a corresponding `Errno` aren't named.
+	assert_eq!(Errno::from_error(fuse_error(500)), None);
+}
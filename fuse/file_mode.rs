@@ -18,6 +18,9 @@ use core::fmt;
 use core::ops;
 
 const S_IFMT: u32 = 0xF000;
+const S_ISUID: u32 = 0o4000;
+const S_ISGID: u32 = 0o2000;
+const S_IXGRP: u32 = 0o0010;
 
 /// Representation of Unix file modes.
 ///
@@ -80,6 +83,40 @@ impl FileMode {
 		self.bits & 0o777
 	}
 
+	/// Returns the mode with permission bits in `umask` cleared.
+	///
+	/// Bits of `umask` outside the permission bits are ignored, so the file
+	/// type and flags such as the sticky bit are unaffected.
+	#[inline]
+	#[must_use]
+	pub const fn with_umask(self, umask: u32) -> FileMode {
+		Self {
+			bits: self.bits & !(umask & 0o777),
+		}
+	}
+
+	/// Returns the mode with its setuid and setgid bits cleared, as
+	/// required when a request's `kill_suidgid()` is set.
+	///
+	/// The setuid bit is always cleared. The setgid bit is only cleared if
+	/// the group execute bit is set, because setgid without group execute
+	/// marks a file as a candidate for mandatory locking rather than
+	/// granting privileges. This matches the [`HANDLE_KILLPRIV_V2`] rules
+	/// and the Linux VFS.
+	///
+	/// [`HANDLE_KILLPRIV_V2`]: crate::FuseInitFlag::HANDLE_KILLPRIV_V2
+	#[inline]
+	#[must_use]
+	pub const fn without_suidgid(self) -> FileMode {
+		let mut kill = S_ISUID;
+		if self.bits & S_IXGRP != 0 {
+			kill |= S_ISGID;
+		}
+		Self {
+			bits: self.bits & !kill,
+		}
+	}
+
 	#[inline]
 	#[must_use]
 	pub(crate) const fn type_bits(self) -> u32 {
@@ -93,6 +130,26 @@ impl fmt::Debug for FileMode {
 	}
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for FileMode {
+	fn serialize<S: serde::Serializer>(
+		&self,
+		serializer: S,
+	) -> Result<S::Ok, S::Error> {
+		serializer.serialize_u32(self.bits)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FileMode {
+	fn deserialize<D: serde::Deserializer<'de>>(
+		deserializer: D,
+	) -> Result<FileMode, D::Error> {
+		let bits = serde::Deserialize::deserialize(deserializer)?;
+		Ok(FileMode { bits })
+	}
+}
+
 impl fmt::Binary for FileMode {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		self.bits.fmt(fmt)
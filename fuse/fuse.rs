@@ -66,10 +66,16 @@
 	clippy::print_stdout,
 )]
 
+#[cfg(feature = "std")]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+extern crate std;
+
 #[macro_use]
 mod internal;
 
-mod cuse;
+pub mod cuse;
 pub use cuse::{
 	// FIXME
 	DeviceName as CuseDeviceName,
@@ -83,12 +89,18 @@ pub use node_id::NodeId;
 mod file_mode;
 pub use file_mode::{FileMode, FileType};
 
+mod open_flags;
+pub use open_flags::{OpenAccessMode, OpenFlags};
+
 mod node_attr;
 pub use node_attr::NodeAttr;
 
 mod entry;
 pub use entry::Entry;
 
+mod submount;
+pub use submount::{SubmountDevices, SubmountDevicesError};
+
 /// Types and constants defined by the FUSE kernel interface.
 ///
 /// This module is automatically generated from [`fuse.h`] in the Linux kernel
@@ -131,9 +143,13 @@ pub use lock::{
 };
 
 mod unix_time;
-pub use unix_time::UnixTime;
+pub use unix_time::{UnixTime, UnixTimeRangeError};
+
+mod errno;
+pub use errno::Errno;
 
 pub mod client;
+pub mod fuzzing;
 pub mod io;
 
 pub(crate) mod operations;
@@ -142,19 +158,47 @@ pub use operations::{
 		CopyFileRangeRequestFlag,
 		CopyFileRangeRequestFlags,
 	},
-	create::{CreateRequestFlag, CreateRequestFlags},
+	create::{
+		CreateRequestFlag,
+		CreateRequestFlags,
+		CreateResponseFlag,
+		CreateResponseFlags,
+	},
 	cuse_init::{CuseInitFlag, CuseInitFlags},
+	fallocate::{FallocateFlag, FallocateFlags},
 	fsync::{FsyncRequestFlag, FsyncRequestFlags},
 	fsyncdir::{FsyncdirRequestFlag, FsyncdirRequestFlags},
 	fuse_init::{FuseInitFlag, FuseInitFlags},
+	ioctl::{IoctlRequestFlag, IoctlRequestFlags},
 	lseek::LseekWhence,
-	open::{OpenRequestFlag, OpenRequestFlags},
+	open::{
+		OpenRequestFlag,
+		OpenRequestFlags,
+		OpenResponseFlag,
+		OpenResponseFlags,
+	},
+	opendir::{
+		OpendirRequestFlag,
+		OpendirRequestFlags,
+		OpendirResponseFlag,
+		OpendirResponseFlags,
+	},
+	poll::{PollRequestFlag, PollRequestFlags},
+	setxattr::{SetxattrRequestFlag, SetxattrRequestFlags},
 	statfs::StatfsAttributes,
+	statx::StatxAttributes,
 	write::{WriteRequestFlag, WriteRequestFlags},
 };
 
+#[cfg(target_has_atomic = "64")]
+pub mod metrics;
 pub mod os;
+pub mod perm;
+pub mod proxy;
 pub mod server;
+pub mod testing;
+pub mod trace;
+pub mod xattr;
 
 /// The error type for FUSE operations.
 ///
@@ -183,6 +227,11 @@ pub struct RequestHeader(kernel::fuse_in_header);
 impl RequestHeader {
 	/// Returns the raw [`fuse_in_header`] for this request.
 	///
+	/// The layout of `fuse_in_header` is defined by the FUSE protocol and
+	/// won't change, but new fields may be carved out of its padding in
+	/// future protocol versions. Each field has a corresponding accessor on
+	/// `RequestHeader`, which should be preferred.
+	///
 	/// [`fuse_in_header`]: kernel::fuse_in_header
 	#[inline]
 	#[must_use]
@@ -244,11 +293,43 @@ impl RequestHeader {
 	/// A request might not have a process ID, for example if it was generated
 	/// internally by the kernel, or if the client's PID isn't visible in the
 	/// server's PID namespace.
+	///
+	/// On Linux, the process ID is in the PID namespace of the process that
+	/// mounted the filesystem. Servers running in a container can translate
+	/// it to the process's own PID namespace with
+	/// [`parse_proc_status_nspid`], for example when reporting the owner of
+	/// a lock to the process that initiated a `FUSE_GETLK` request.
+	///
+	/// [`parse_proc_status_nspid`]: crate::os::linux::parse_proc_status_nspid
 	#[inline]
 	#[must_use]
 	pub fn process_id(&self) -> Option<core::num::NonZeroU32> {
 		core::num::NonZeroU32::new(self.0.pid)
 	}
+
+	/// Returns the length of the request extensions that follow the
+	/// request body, in bytes.
+	///
+	/// Extensions are only sent by clients that support FUSE protocol
+	/// version 7.38 or later.
+	#[inline]
+	#[must_use]
+	pub fn extensions_len(&self) -> usize {
+		usize::from(self.0.total_extlen) * 8
+	}
+
+	/// Returns the credentials of the process that initiated this request.
+	///
+	/// The request header doesn't contain the process's supplementary groups,
+	/// so the returned credentials have none. On Linux they can be resolved
+	/// from the process ID by reading `/proc/<pid>/status`.
+	#[inline]
+	#[must_use]
+	pub fn credentials(&self) -> Credentials<'static> {
+		let mut creds = Credentials::new(self.0.uid, self.0.gid);
+		creds.set_process_id(self.process_id());
+		creds
+	}
 }
 
 impl core::fmt::Debug for RequestHeader {
@@ -265,6 +346,146 @@ impl core::fmt::Debug for RequestHeader {
 	}
 }
 
+serde_struct!(RequestHeader, RequestHeader(kernel::fuse_in_header::new()), {
+	request_id: core::num::NonZeroU64 = (
+		RequestHeader::request_id,
+		|h: &mut RequestHeader, id: core::num::NonZeroU64| {
+			h.0.unique = id.get();
+		},
+	),
+	request_len: core::num::NonZeroU32 = (
+		RequestHeader::request_len,
+		|h: &mut RequestHeader, len: core::num::NonZeroU32| {
+			h.0.len = len.get();
+		},
+	),
+	opcode: kernel::fuse_opcode = (
+		RequestHeader::opcode,
+		|h: &mut RequestHeader, opcode| h.0.opcode = opcode,
+	),
+	node_id: Option<NodeId> = (
+		RequestHeader::node_id,
+		|h: &mut RequestHeader, id: Option<NodeId>| {
+			h.0.nodeid = id.map_or(0, |id| id.get());
+		},
+	),
+	user_id: u32 = (
+		RequestHeader::user_id,
+		|h: &mut RequestHeader, uid| h.0.uid = uid,
+	),
+	group_id: u32 = (
+		RequestHeader::group_id,
+		|h: &mut RequestHeader, gid| h.0.gid = gid,
+	),
+	process_id: Option<core::num::NonZeroU32> = (
+		RequestHeader::process_id,
+		|h: &mut RequestHeader, pid: Option<core::num::NonZeroU32>| {
+			h.0.pid = pid.map_or(0, |pid| pid.get());
+		},
+	),
+});
+
+// }}}
+
+// Credentials {{{
+
+/// The credentials of a process that initiated a request.
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub struct Credentials<'a> {
+	user_id: u32,
+	group_id: u32,
+	process_id: Option<core::num::NonZeroU32>,
+	supplementary_groups: &'a [u32],
+}
+
+impl<'a> Credentials<'a> {
+	/// Creates a new `Credentials` with the given user ID and group ID.
+	///
+	/// The initial process ID is `None`, and there are no supplementary
+	/// groups.
+	#[inline]
+	#[must_use]
+	pub const fn new(user_id: u32, group_id: u32) -> Credentials<'a> {
+		Self {
+			user_id,
+			group_id,
+			process_id: None,
+			supplementary_groups: &[],
+		}
+	}
+
+	/// Returns the user ID of the process.
+	#[inline]
+	#[must_use]
+	pub fn user_id(&self) -> u32 {
+		self.user_id
+	}
+
+	/// Sets the user ID of the process.
+	#[inline]
+	pub fn set_user_id(&mut self, user_id: u32) {
+		self.user_id = user_id;
+	}
+
+	/// Returns the group ID of the process.
+	#[inline]
+	#[must_use]
+	pub fn group_id(&self) -> u32 {
+		self.group_id
+	}
+
+	/// Sets the group ID of the process.
+	#[inline]
+	pub fn set_group_id(&mut self, group_id: u32) {
+		self.group_id = group_id;
+	}
+
+	/// Returns the process ID of the process, if known.
+	#[inline]
+	#[must_use]
+	pub fn process_id(&self) -> Option<core::num::NonZeroU32> {
+		self.process_id
+	}
+
+	/// Sets the process ID of the process.
+	#[inline]
+	pub fn set_process_id(&mut self, process_id: Option<core::num::NonZeroU32>) {
+		self.process_id = process_id;
+	}
+
+	/// Returns the supplementary group IDs of the process.
+	#[inline]
+	#[must_use]
+	pub fn supplementary_groups(&self) -> &'a [u32] {
+		self.supplementary_groups
+	}
+
+	/// Sets the supplementary group IDs of the process.
+	#[inline]
+	pub fn set_supplementary_groups(&mut self, supplementary_groups: &'a [u32]) {
+		self.supplementary_groups = supplementary_groups;
+	}
+
+	/// Returns whether the process is a member of the given group, either as
+	/// its primary group or as a supplementary group.
+	#[must_use]
+	pub fn in_group(&self, group_id: u32) -> bool {
+		self.group_id == group_id
+			|| self.supplementary_groups.contains(&group_id)
+	}
+}
+
+impl core::fmt::Debug for Credentials<'_> {
+	fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+		fmt.debug_struct("Credentials")
+			.field("user_id", &self.user_id)
+			.field("group_id", &self.group_id)
+			.field("process_id", &format_args!("{:?}", self.process_id))
+			.field("supplementary_groups", &self.supplementary_groups)
+			.finish()
+	}
+}
+
 // }}}
 
 // ResponseHeader {{{
@@ -360,12 +581,6 @@ impl core::fmt::Debug for ResponseHeader {
 
 // }}}
 
-/// OS-specific flags passed to `fallocate()`.
-pub type FallocateFlags = u32;
-
-/// OS-specific flags passed to `open()`.
-pub type OpenFlags = u32;
-
 /// OS-specific flags passed to `renameat2()`.
 pub type RenameFlags = u32;
 
@@ -0,0 +1,28 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+#![no_main]
+
+use fuse::kernel::fuse_opcode;
+
+// The first four bytes of the input are the opcode, and the remainder is
+// the request body.
+libfuzzer_sys::fuzz_target!(|data: &[u8]| {
+	let Some((opcode, body)) = data.split_first_chunk::<4>() else {
+		return;
+	};
+	let opcode = fuse_opcode(u32::from_le_bytes(*opcode));
+	fuse::fuzzing::decode_any(opcode, body);
+});
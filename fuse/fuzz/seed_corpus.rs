@@ -0,0 +1,54 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//! Writes the seed corpus for the fuzz targets.
+//!
+//! Run from this directory with `cargo run --bin seed_corpus`, then start
+//! fuzzing with `cargo fuzz run decode_any`.
+
+use std::fs;
+use std::path::Path;
+
+fn main() -> std::io::Result<()> {
+	let any_dir = Path::new("corpus/decode_any");
+	let message_dir = Path::new("corpus/decode_message");
+	fs::create_dir_all(any_dir)?;
+	fs::create_dir_all(message_dir)?;
+
+	let mut result = Ok(());
+	fuse::fuzzing::seed_corpus(|opcode, body| {
+		if result.is_err() {
+			return;
+		}
+		let name = format!("seed-{}", opcode.0);
+
+		let mut any_input = opcode.0.to_le_bytes().to_vec();
+		any_input.extend_from_slice(body);
+		result = fs::write(any_dir.join(&name), any_input);
+		if result.is_err() {
+			return;
+		}
+
+		let mut message = vec![0u8; 40];
+		let message_len = (message.len() + body.len()) as u32;
+		message[0..4].copy_from_slice(&message_len.to_ne_bytes());
+		message[4..8].copy_from_slice(&opcode.0.to_ne_bytes());
+		message[8..16].copy_from_slice(&1u64.to_ne_bytes()); // unique
+		message[16..24].copy_from_slice(&1u64.to_ne_bytes()); // nodeid
+		message.extend_from_slice(body);
+		result = fs::write(message_dir.join(&name), message);
+	});
+	result
+}
@@ -0,0 +1,462 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Entry points for fuzzing request decoding.
+//!
+//! Requests are sent by the kernel, or in the case of virtio-fs by a guest
+//! VM, and a server must not trust them to be well-formed. The functions in
+//! this module pass arbitrary bytes through every request parser, so that
+//! a fuzzer such as `cargo-fuzz` can search for inputs that cause a panic.
+//!
+//! Each request is decoded with several protocol versions, and every
+//! successfully decoded request is formatted with [`fmt::Debug`] so that its
+//! accessors are exercised. Errors are ignored; the only failure mode that
+//! a fuzzer should detect is a panic or a crash.
+//!
+//! A seed corpus of well-formed requests can be generated with
+//! [`seed_corpus`].
+
+use core::fmt;
+use core::mem::size_of;
+
+use crate::io::{AlignedSlice, MinReadBuffer};
+use crate::kernel;
+use crate::kernel::fuse_opcode;
+use crate::server;
+use crate::server::{
+	AccessRequest,
+	BmapRequest,
+	CopyFileRangeRequest,
+	CreateRequest,
+	CuseInitRequest,
+	CuseLayout,
+	FallocateRequest,
+	FlushRequest,
+	ForgetRequest,
+	FsyncRequest,
+	FsyncdirRequest,
+	FuseInitRequest,
+	FuseLayout,
+	GetattrRequest,
+	GetlkRequest,
+	GetxattrRequest,
+	InterruptRequest,
+	IoctlRequest,
+	LinkRequest,
+	ListxattrRequest,
+	LookupRequest,
+	LseekRequest,
+	MkdirRequest,
+	MknodRequest,
+	NotifyReplyRequest,
+	OpenRequest,
+	OpendirRequest,
+	PollRequest,
+	ReadRequest,
+	ReaddirRequest,
+	ReaddirplusRequest,
+	ReadlinkRequest,
+	ReleaseRequest,
+	ReleasedirRequest,
+	RemovexattrRequest,
+	RenameRequest,
+	RmdirRequest,
+	SetattrRequest,
+	SetlkRequest,
+	SetxattrRequest,
+	StatfsRequest,
+	StatxRequest,
+	SymlinkRequest,
+	SyncfsRequest,
+	TmpfileRequest,
+	UnlinkRequest,
+	WriteRequest,
+};
+use crate::testing::RequestBuilder;
+
+const HEADER_LEN: usize = size_of::<kernel::fuse_in_header>();
+
+// Protocol versions with distinct request layouts.
+const VERSIONS: &[u16] = &[
+	1,  // oldest supported version
+	9,  // larger `fuse_write_in`, `fuse_read_in`, and `fuse_getattr_in`
+	12, // larger `fuse_mknod_in` and `fuse_create_in`
+	kernel::FUSE_KERNEL_MINOR_VERSION as u16,
+];
+
+/// Decodes the given request body with every applicable request parser.
+///
+/// The body is prefixed with a request header for `opcode` and the root
+/// node ID. Bodies that don't fit in a [`MinReadBuffer`] along with the
+/// header are ignored.
+pub fn decode_any(opcode: fuse_opcode, body: &[u8]) {
+	let mut buf = MinReadBuffer::new();
+	let builder = RequestBuilder::new(FuseLayout {
+		version_minor: kernel::FUSE_KERNEL_MINOR_VERSION as u16,
+		features: 0,
+	});
+	let root = kernel::FUSE_ROOT_ID;
+	let request = builder.build(buf.as_aligned_slice_mut(), opcode, root, &[
+		body,
+	]);
+	if let Some(request) = request {
+		decode_request(request);
+	}
+}
+
+/// Decodes the given message, including its request header, with every
+/// applicable request parser.
+///
+/// Messages longer than [`MinReadBuffer::LEN`] are ignored.
+pub fn decode_message(message: &[u8]) {
+	let mut buf = MinReadBuffer::new();
+	let Some(dst) = buf.as_slice_mut().get_mut(..message.len()) else {
+		return;
+	};
+	dst.copy_from_slice(message);
+	decode_request(buf.as_aligned_slice().truncate(message.len()));
+}
+
+fn decode_request(buf: AlignedSlice) {
+	for &version_minor in VERSIONS {
+		let layout = FuseLayout {
+			version_minor,
+			features: 0,
+		};
+		decode_fuse(buf, layout);
+		decode_fuse(buf, FuseLayout {
+			features: server::FEATURE_SETXATTR_EXT
+				| server::FEATURE_SECURITY_CTX,
+			..layout
+		});
+		decode_cuse(buf, CuseLayout { version_minor });
+	}
+}
+
+fn decode_fuse(buf: AlignedSlice, layout: FuseLayout) {
+	let Ok(request) = server::FuseRequest::new(buf, layout) else {
+		return;
+	};
+	inspect(&request);
+
+	macro_rules! decode {
+		($t:ty) => {
+			inspect(&<$t>::try_from(request))
+		};
+	}
+
+	match request.header().opcode() {
+		fuse_opcode::FUSE_ACCESS => decode!(AccessRequest),
+		fuse_opcode::FUSE_BATCH_FORGET => decode!(ForgetRequest),
+		fuse_opcode::FUSE_BMAP => decode!(BmapRequest),
+		fuse_opcode::FUSE_COPY_FILE_RANGE => decode!(CopyFileRangeRequest),
+		fuse_opcode::FUSE_CREATE => decode!(CreateRequest),
+		fuse_opcode::FUSE_FALLOCATE => decode!(FallocateRequest),
+		fuse_opcode::FUSE_FLUSH => decode!(FlushRequest),
+		fuse_opcode::FUSE_FORGET => decode!(ForgetRequest),
+		fuse_opcode::FUSE_FSYNC => decode!(FsyncRequest),
+		fuse_opcode::FUSE_FSYNCDIR => decode!(FsyncdirRequest),
+		fuse_opcode::FUSE_GETATTR => decode!(GetattrRequest),
+		fuse_opcode::FUSE_GETLK => decode!(GetlkRequest),
+		fuse_opcode::FUSE_GETXATTR => decode!(GetxattrRequest),
+		fuse_opcode::FUSE_INIT => decode!(FuseInitRequest),
+		fuse_opcode::FUSE_INTERRUPT => decode!(InterruptRequest),
+		fuse_opcode::FUSE_IOCTL => decode!(IoctlRequest),
+		fuse_opcode::FUSE_LINK => decode!(LinkRequest),
+		fuse_opcode::FUSE_LISTXATTR => decode!(ListxattrRequest),
+		fuse_opcode::FUSE_LOOKUP => decode!(LookupRequest),
+		fuse_opcode::FUSE_LSEEK => decode!(LseekRequest),
+		fuse_opcode::FUSE_MKDIR => decode!(MkdirRequest),
+		fuse_opcode::FUSE_MKNOD => {
+			let result = MknodRequest::try_from(request);
+			inspect(&result);
+			if let Ok(request) = result {
+				inspect(&request.kind());
+			}
+		},
+		fuse_opcode::FUSE_NOTIFY_REPLY => decode!(NotifyReplyRequest),
+		fuse_opcode::FUSE_OPEN => decode!(OpenRequest),
+		fuse_opcode::FUSE_OPENDIR => decode!(OpendirRequest),
+		fuse_opcode::FUSE_POLL => decode!(PollRequest),
+		fuse_opcode::FUSE_READ => decode!(ReadRequest),
+		fuse_opcode::FUSE_READDIR => decode!(ReaddirRequest),
+		fuse_opcode::FUSE_READDIRPLUS => decode!(ReaddirplusRequest),
+		fuse_opcode::FUSE_READLINK => decode!(ReadlinkRequest),
+		fuse_opcode::FUSE_RELEASE => decode!(ReleaseRequest),
+		fuse_opcode::FUSE_RELEASEDIR => decode!(ReleasedirRequest),
+		fuse_opcode::FUSE_REMOVEXATTR => decode!(RemovexattrRequest),
+		fuse_opcode::FUSE_RENAME => decode!(RenameRequest),
+		fuse_opcode::FUSE_RENAME2 => decode!(RenameRequest),
+		fuse_opcode::FUSE_RMDIR => decode!(RmdirRequest),
+		fuse_opcode::FUSE_SETATTR => decode!(SetattrRequest),
+		fuse_opcode::FUSE_SETLK => decode!(SetlkRequest),
+		fuse_opcode::FUSE_SETLKW => decode!(SetlkRequest),
+		fuse_opcode::FUSE_SETXATTR => decode!(SetxattrRequest),
+		fuse_opcode::FUSE_STATFS => decode!(StatfsRequest),
+		fuse_opcode::FUSE_STATX => decode!(StatxRequest),
+		fuse_opcode::FUSE_SYMLINK => decode!(SymlinkRequest),
+		fuse_opcode::FUSE_SYNCFS => decode!(SyncfsRequest),
+		fuse_opcode::FUSE_TMPFILE => decode!(TmpfileRequest),
+		fuse_opcode::FUSE_UNLINK => decode!(UnlinkRequest),
+		fuse_opcode::FUSE_WRITE => decode!(WriteRequest),
+		_ => {},
+	}
+}
+
+fn decode_cuse(buf: AlignedSlice, layout: CuseLayout) {
+	let Ok(request) = server::CuseRequest::new(buf, layout) else {
+		return;
+	};
+	inspect(&request);
+
+	macro_rules! decode {
+		($t:ty) => {
+			inspect(&<$t>::try_from(request))
+		};
+	}
+
+	match request.header().opcode() {
+		fuse_opcode::CUSE_INIT => decode!(CuseInitRequest),
+		fuse_opcode::FUSE_FLUSH => decode!(FlushRequest),
+		fuse_opcode::FUSE_FSYNC => decode!(FsyncRequest),
+		fuse_opcode::FUSE_INTERRUPT => decode!(InterruptRequest),
+		fuse_opcode::FUSE_IOCTL => decode!(IoctlRequest),
+		fuse_opcode::FUSE_OPEN => decode!(OpenRequest),
+		fuse_opcode::FUSE_POLL => decode!(PollRequest),
+		fuse_opcode::FUSE_READ => decode!(ReadRequest),
+		fuse_opcode::FUSE_RELEASE => decode!(ReleaseRequest),
+		fuse_opcode::FUSE_WRITE => decode!(WriteRequest),
+		_ => {},
+	}
+}
+
+// Formats a value into a sink that discards the output.
+fn inspect(value: &impl fmt::Debug) {
+	struct Discard;
+
+	impl fmt::Write for Discard {
+		fn write_str(&mut self, _s: &str) -> fmt::Result {
+			Ok(())
+		}
+	}
+
+	fmt::write(&mut Discard, format_args!("{:?}", value)).unwrap_or(());
+}
+
+/// Generates a seed corpus of well-formed requests.
+///
+/// The callback is invoked with the opcode and body of a request for each
+/// opcode that a FUSE or CUSE client may send. The arguments are suitable
+/// for passing to [`decode_any`].
+pub fn seed_corpus(mut f: impl FnMut(fuse_opcode, &[u8])) {
+	let mut buf = MinReadBuffer::new();
+	let builder = RequestBuilder::new(FuseLayout {
+		version_minor: kernel::FUSE_KERNEL_MINOR_VERSION as u16,
+		features: 0,
+	});
+	let root = kernel::FUSE_ROOT_ID;
+
+	let mut emit = |request: Option<AlignedSlice>| {
+		if let Some(request) = request {
+			let header = request.get()[..HEADER_LEN].as_ptr();
+			let header = header.cast::<kernel::fuse_in_header>();
+			let opcode = unsafe { header.read_unaligned() }.opcode;
+			f(opcode, &request.get()[HEADER_LEN..]);
+		}
+	};
+
+	macro_rules! seed {
+		($opcode:ident, [ $( $chunk:expr ),* $(,)? ]) => {
+			emit(builder.build(
+				buf.as_aligned_slice_mut(),
+				fuse_opcode::$opcode,
+				root,
+				&[ $( $chunk ),* ],
+			))
+		};
+	}
+
+	let name = unsafe { crate::NodeName::new_unchecked("hello.txt") };
+
+	emit(builder.init(buf.as_aligned_slice_mut(), &new!(kernel::fuse_init_in {
+		major: kernel::FUSE_KERNEL_VERSION,
+		minor: kernel::FUSE_KERNEL_MINOR_VERSION,
+		max_readahead: 4096,
+	})));
+	seed!(CUSE_INIT, [new!(kernel::cuse_init_in {
+		major: kernel::FUSE_KERNEL_VERSION,
+		minor: kernel::FUSE_KERNEL_MINOR_VERSION,
+	}).as_bytes()]);
+
+	seed!(FUSE_LOOKUP, [name.as_bytes(), b"\0"]);
+	seed!(FUSE_FORGET, [new!(kernel::fuse_forget_in {
+		nlookup: 1,
+	}).as_bytes()]);
+	seed!(FUSE_BATCH_FORGET, [
+		new!(kernel::fuse_batch_forget_in { count: 2, }).as_bytes(),
+		new!(kernel::fuse_forget_one { nodeid: 2, nlookup: 1, }).as_bytes(),
+		new!(kernel::fuse_forget_one { nodeid: 3, nlookup: 1, }).as_bytes(),
+	]);
+	emit(builder.getattr(
+		buf.as_aligned_slice_mut(),
+		root,
+		&kernel::fuse_getattr_in::new(),
+	));
+	seed!(FUSE_SETATTR, [new!(kernel::fuse_setattr_in {
+		valid: kernel::FATTR_SIZE | kernel::FATTR_MTIME,
+		size: 5,
+		mtime: 1,
+	}).as_bytes()]);
+	seed!(FUSE_READLINK, []);
+	seed!(FUSE_SYMLINK, [name.as_bytes(), b"\0", b"target.txt\0"]);
+	emit(builder.mknod(
+		buf.as_aligned_slice_mut(),
+		root,
+		&new!(kernel::fuse_mknod_in {
+			mode: crate::FileMode::S_IFREG.get() | 0o644,
+		}),
+		name,
+	));
+	seed!(FUSE_MKDIR, [
+		new!(kernel::fuse_mkdir_in { mode: 0o755, }).as_bytes(),
+		name.as_bytes(),
+		b"\0",
+	]);
+	seed!(FUSE_UNLINK, [name.as_bytes(), b"\0"]);
+	seed!(FUSE_RMDIR, [name.as_bytes(), b"\0"]);
+	seed!(FUSE_RENAME, [
+		new!(kernel::fuse_rename_in { newdir: root, }).as_bytes(),
+		name.as_bytes(),
+		b"\0new.txt\0",
+	]);
+	seed!(FUSE_RENAME2, [
+		new!(kernel::fuse_rename2_in { newdir: root, }).as_bytes(),
+		name.as_bytes(),
+		b"\0new.txt\0",
+	]);
+	seed!(FUSE_LINK, [
+		new!(kernel::fuse_link_in { oldnodeid: 2, }).as_bytes(),
+		name.as_bytes(),
+		b"\0",
+	]);
+	seed!(FUSE_OPEN, [kernel::fuse_open_in::new().as_bytes()]);
+	seed!(FUSE_OPENDIR, [kernel::fuse_open_in::new().as_bytes()]);
+	let read_in = new!(kernel::fuse_read_in {
+		fh: 1,
+		size: 4096,
+	});
+	emit(builder.read(buf.as_aligned_slice_mut(), root, &read_in));
+	emit(builder.readdir(buf.as_aligned_slice_mut(), root, &read_in));
+	emit(builder.readdirplus(buf.as_aligned_slice_mut(), root, &read_in));
+	emit(builder.write(
+		buf.as_aligned_slice_mut(),
+		root,
+		&new!(kernel::fuse_write_in {
+			fh: 1,
+			size: 5,
+		}),
+		b"hello",
+	));
+	seed!(FUSE_STATFS, []);
+	let release_in = new!(kernel::fuse_release_in { fh: 1, });
+	emit(builder.release(buf.as_aligned_slice_mut(), root, &release_in));
+	emit(builder.releasedir(buf.as_aligned_slice_mut(), root, &release_in));
+	seed!(FUSE_FSYNC, [new!(kernel::fuse_fsync_in { fh: 1, }).as_bytes()]);
+	seed!(FUSE_FSYNCDIR, [new!(kernel::fuse_fsync_in { fh: 1, }).as_bytes()]);
+	emit(builder.setxattr(
+		buf.as_aligned_slice_mut(),
+		root,
+		&new!(kernel::fuse_setxattr_in { size: 5, }),
+		c"user.name",
+		b"value",
+	));
+	seed!(FUSE_GETXATTR, [
+		new!(kernel::fuse_getxattr_in { size: 10, }).as_bytes(),
+		b"user.name\0",
+	]);
+	seed!(FUSE_LISTXATTR, [new!(kernel::fuse_getxattr_in {
+		size: 10,
+	}).as_bytes()]);
+	seed!(FUSE_REMOVEXATTR, [b"user.name\0"]);
+	seed!(FUSE_FLUSH, [new!(kernel::fuse_flush_in { fh: 1, }).as_bytes()]);
+	let lk_in = new!(kernel::fuse_lk_in {
+		fh: 1,
+		owner: 2,
+		lk: new!(kernel::fuse_file_lock {
+			end: 100,
+			r#type: 1, // F_WRLCK
+			pid: 3,
+		}),
+	});
+	seed!(FUSE_GETLK, [lk_in.as_bytes()]);
+	seed!(FUSE_SETLK, [lk_in.as_bytes()]);
+	seed!(FUSE_SETLKW, [lk_in.as_bytes()]);
+	seed!(FUSE_ACCESS, [new!(kernel::fuse_access_in { mask: 4, }).as_bytes()]);
+	emit(builder.create(
+		buf.as_aligned_slice_mut(),
+		root,
+		&new!(kernel::fuse_create_in {
+			mode: crate::FileMode::S_IFREG.get() | 0o644,
+		}),
+		name,
+	));
+	seed!(FUSE_INTERRUPT, [new!(kernel::fuse_interrupt_in {
+		unique: 1,
+	}).as_bytes()]);
+	seed!(FUSE_BMAP, [new!(kernel::fuse_bmap_in {
+		block: 1,
+		blocksize: 512,
+	}).as_bytes()]);
+	seed!(FUSE_DESTROY, []);
+	seed!(FUSE_IOCTL, [
+		new!(kernel::fuse_ioctl_in {
+			fh: 1,
+			cmd: 0x5401, // TCGETS
+			in_size: 4,
+			out_size: 60,
+		}).as_bytes(),
+		&[1, 2, 3, 4],
+	]);
+	seed!(FUSE_POLL, [new!(kernel::fuse_poll_in { fh: 1, kh: 2, }).as_bytes()]);
+	seed!(FUSE_NOTIFY_REPLY, [
+		new!(kernel::fuse_notify_retrieve_in { size: 5, }).as_bytes(),
+		b"hello",
+	]);
+	seed!(FUSE_FALLOCATE, [new!(kernel::fuse_fallocate_in {
+		fh: 1,
+		length: 100,
+	}).as_bytes()]);
+	seed!(FUSE_LSEEK, [new!(kernel::fuse_lseek_in {
+		fh: 1,
+		offset: 10,
+		whence: 3, // SEEK_DATA
+	}).as_bytes()]);
+	seed!(FUSE_COPY_FILE_RANGE, [new!(kernel::fuse_copy_file_range_in {
+		fh_in: 1,
+		nodeid_out: 2,
+		fh_out: 3,
+		len: 100,
+	}).as_bytes()]);
+	seed!(FUSE_SYNCFS, [kernel::fuse_syncfs_in::new().as_bytes()]);
+	seed!(FUSE_TMPFILE, [
+		new!(kernel::fuse_create_in { mode: 0o600, }).as_bytes(),
+		name.as_bytes(),
+		b"\0",
+	]);
+	seed!(FUSE_STATX, [new!(kernel::fuse_statx_in {
+		sx_mask: 0x7FF, // STATX_BASIC_STATS
+	}).as_bytes()]);
+}
@@ -0,0 +1,90 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::mem::size_of;
+
+use fuse::fuzzing;
+use fuse::kernel;
+use fuse::kernel::fuse_opcode;
+
+#[test]
+fn seed_corpus() {
+	let mut opcodes = Vec::new();
+	fuzzing::seed_corpus(|opcode, body| {
+		opcodes.push(opcode);
+		fuzzing::decode_any(opcode, body);
+	});
+
+	assert!(opcodes.contains(&fuse_opcode::FUSE_INIT));
+	assert!(opcodes.contains(&fuse_opcode::CUSE_INIT));
+	assert!(opcodes.contains(&fuse_opcode::FUSE_LOOKUP));
+	assert!(opcodes.contains(&fuse_opcode::FUSE_STATX));
+}
+
+#[test]
+fn truncated_seeds() {
+	fuzzing::seed_corpus(|opcode, body| {
+		for len in 0..body.len() {
+			fuzzing::decode_any(opcode, &body[..len]);
+		}
+	});
+}
+
+#[test]
+fn mutated_seeds() {
+	fuzzing::seed_corpus(|opcode, body| {
+		let mut body = body.to_vec();
+		for ii in 0..body.len() {
+			let orig = body[ii];
+			for value in [0x00, 0x01, 0x7F, 0x80, 0xFF] {
+				body[ii] = value;
+				fuzzing::decode_any(opcode, &body);
+			}
+			body[ii] = orig;
+		}
+	});
+}
+
+#[test]
+fn decode_message() {
+	// Empty and truncated headers.
+	fuzzing::decode_message(&[]);
+	fuzzing::decode_message(&[0xFF; 8]);
+
+	let header_len = size_of::<kernel::fuse_in_header>();
+	let mut message = vec![0u8; header_len + 16];
+
+	// Header length greater than message length.
+	message[0..4].copy_from_slice(&u32::MAX.to_ne_bytes());
+	fuzzing::decode_message(&message);
+
+	// Header length less than header size.
+	message[0..4].copy_from_slice(&4u32.to_ne_bytes());
+	fuzzing::decode_message(&message);
+
+	// Every opcode, with a zeroed body.
+	let message_len = message.len() as u32;
+	message[0..4].copy_from_slice(&message_len.to_ne_bytes());
+	for opcode in 0..64u32 {
+		message[4..8].copy_from_slice(&opcode.to_ne_bytes());
+		fuzzing::decode_message(&message);
+	}
+	message[4..8].copy_from_slice(&fuse_opcode::CUSE_INIT.0.to_ne_bytes());
+	fuzzing::decode_message(&message);
+
+	// Oversized messages are ignored.
+	fuzzing::decode_message(&vec![0u8; 1 << 24]);
+}
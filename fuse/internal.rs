@@ -17,11 +17,8 @@
 #[macro_use]
 mod bitflags;
 
-pub(crate) mod compat;
-pub(crate) mod debug;
-pub(crate) mod dirent;
-
-pub(crate) mod timestamp;
+#[macro_use]
+pub(crate) mod serde;
 
 macro_rules! new {
 	($t:ty { $( $field:ident : $value:expr , )+ }) => {{
@@ -33,6 +30,12 @@ macro_rules! new {
 	}}
 }
 
+pub(crate) mod compat;
+pub(crate) mod debug;
+pub(crate) mod dirent;
+
+pub(crate) mod timestamp;
+
 macro_rules! try_from_cuse_request {
 	($t:ty, |$request:ident| $try_from:tt) => {
 		impl<'a> TryFrom<crate::server::CuseRequest<'a>> for $t {
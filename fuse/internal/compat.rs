@@ -14,6 +14,8 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use core::mem::size_of;
+
 use crate::kernel;
 
 #[derive(Clone, Copy)]
@@ -22,6 +24,29 @@ pub(crate) struct Versioned<U> {
 	u: U,
 }
 
+// fuse_attr_out {{{
+
+/// Returns the size of `fuse_attr_out` for the given protocol version.
+///
+/// Protocol version 7.9 added `blksize` to `fuse_attr`.
+#[inline]
+pub(crate) fn fuse_attr_out_len(version_minor: u32) -> usize {
+	if version_minor < 9 {
+		return kernel::FUSE_COMPAT_ATTR_OUT_SIZE;
+	}
+	size_of::<kernel::fuse_attr_out>()
+}
+
+#[inline]
+pub(crate) fn fuse_attr_out_bytes(
+	version_minor: u32,
+	raw: &kernel::fuse_attr_out,
+) -> &[u8] {
+	&raw.as_bytes()[..fuse_attr_out_len(version_minor)]
+}
+
+// }}}
+
 // fuse_create_in {{{
 
 #[derive(Clone, Copy)]
@@ -72,6 +97,39 @@ impl<'a> Versioned<fuse_create_in<'a>> {
 		}
 		None
 	}
+
+	#[inline]
+	pub(crate) fn to_v7p12(self) -> kernel::fuse_create_in {
+		match self.as_v7p12() {
+			Some(v7p12) => *v7p12,
+			None => new!(kernel::fuse_create_in {
+				flags: self.as_v7p1().flags,
+			}),
+		}
+	}
+}
+
+// }}}
+
+// fuse_entry_out {{{
+
+/// Returns the size of `fuse_entry_out` for the given protocol version.
+///
+/// Protocol version 7.9 added `blksize` to `fuse_attr`.
+#[inline]
+pub(crate) fn fuse_entry_out_len(version_minor: u32) -> usize {
+	if version_minor < 9 {
+		return kernel::FUSE_COMPAT_ENTRY_OUT_SIZE;
+	}
+	size_of::<kernel::fuse_entry_out>()
+}
+
+#[inline]
+pub(crate) fn fuse_entry_out_bytes(
+	version_minor: u32,
+	raw: &kernel::fuse_entry_out,
+) -> &[u8] {
+	&raw.as_bytes()[..fuse_entry_out_len(version_minor)]
 }
 
 // }}}
@@ -114,6 +172,51 @@ impl<'a> Versioned<fuse_getattr_in<'a>> {
 		}
 		None
 	}
+
+	#[inline]
+	pub(crate) fn to_v7p9(self) -> kernel::fuse_getattr_in {
+		match self.as_v7p9() {
+			Some(v7p9) => *v7p9,
+			None => kernel::fuse_getattr_in::new(),
+		}
+	}
+}
+
+// }}}
+
+// fuse_init_in {{{
+
+#[repr(C)]
+pub(crate) struct fuse_init_in_v7p1 {
+	pub(crate) major: u32,
+	pub(crate) minor: u32,
+}
+
+#[repr(C)]
+pub(crate) struct fuse_init_in_v7p6 {
+	pub(crate) major: u32,
+	pub(crate) minor: u32,
+	pub(crate) max_readahead: u32,
+	pub(crate) flags: u32,
+}
+
+// }}}
+
+// fuse_init_out {{{
+
+/// Returns the size of `fuse_init_out` for the given protocol version.
+///
+/// Protocol version 7.5 added `flags` and `max_write`. Protocol version 7.23
+/// added `time_gran` and the reserved space after it.
+#[inline]
+pub(crate) fn fuse_init_out_len(version_minor: u32) -> usize {
+	if version_minor < 5 {
+		return kernel::FUSE_COMPAT_INIT_OUT_SIZE;
+	}
+	if version_minor < 23 {
+		return kernel::FUSE_COMPAT_22_INIT_OUT_SIZE;
+	}
+	size_of::<kernel::fuse_init_out>()
 }
 
 // }}}
@@ -168,6 +271,90 @@ impl<'a> Versioned<fuse_mknod_in<'a>> {
 		}
 		None
 	}
+
+	#[inline]
+	pub(crate) fn to_v7p12(self) -> kernel::fuse_mknod_in {
+		match self.as_v7p12() {
+			Some(v7p12) => *v7p12,
+			None => {
+				let v7p1 = self.as_v7p1();
+				new!(kernel::fuse_mknod_in {
+					mode: v7p1.mode,
+					rdev: v7p1.rdev,
+				})
+			},
+		}
+	}
+}
+
+// }}}
+
+// fuse_open_out {{{
+
+/// Returns the subset of `fuse_open_out::open_flags` that is understood by
+/// a client with the given protocol version.
+#[inline]
+pub(crate) fn fuse_open_out_flags_mask(version_minor: u32) -> u32 {
+	let mut mask = kernel::FOPEN_DIRECT_IO | kernel::FOPEN_KEEP_CACHE;
+	if version_minor >= 10 {
+		mask |= kernel::FOPEN_NONSEEKABLE;
+	}
+	if version_minor >= 28 {
+		mask |= kernel::FOPEN_CACHE_DIR;
+	}
+	// FOPEN_STREAM isn't listed in the protocol changelog, it was added
+	// in Linux 5.2 alongside protocol version 7.30.
+	if version_minor >= 30 {
+		mask |= kernel::FOPEN_STREAM;
+	}
+	if version_minor >= 35 {
+		mask |= kernel::FOPEN_NOFLUSH;
+	}
+	if version_minor >= 38 {
+		mask |= kernel::FOPEN_PARALLEL_DIRECT_WRITES;
+	}
+	if version_minor >= 40 {
+		mask |= kernel::FOPEN_PASSTHROUGH;
+	}
+	mask
+}
+
+/// Returns a copy of `open_out` with any flags (and associated fields)
+/// that aren't supported by the given protocol version cleared.
+#[inline]
+pub(crate) fn fuse_open_out_for_version(
+	version_minor: u32,
+	open_out: &kernel::fuse_open_out,
+) -> kernel::fuse_open_out {
+	let mut out = *open_out;
+	out.open_flags &= fuse_open_out_flags_mask(version_minor);
+	if out.open_flags & kernel::FOPEN_PASSTHROUGH == 0 {
+		out.backing_id = 0;
+	}
+	out
+}
+
+// }}}
+
+// fuse_statfs_out {{{
+
+/// Returns the size of `fuse_statfs_out` for the given protocol version.
+///
+/// Protocol version 7.4 added `frsize` and padding to `fuse_kstatfs`.
+#[inline]
+pub(crate) fn fuse_statfs_out_len(version_minor: u32) -> usize {
+	if version_minor < 4 {
+		return kernel::FUSE_COMPAT_STATFS_SIZE;
+	}
+	size_of::<kernel::fuse_statfs_out>()
+}
+
+#[inline]
+pub(crate) fn fuse_statfs_out_bytes(
+	version_minor: u32,
+	raw: &kernel::fuse_statfs_out,
+) -> &[u8] {
+	&raw.as_bytes()[..fuse_statfs_out_len(version_minor)]
 }
 
 // }}}
@@ -220,6 +407,20 @@ impl<'a> Versioned<fuse_setxattr_in<'a>> {
 		}
 		None
 	}
+
+	#[inline]
+	pub(crate) fn to_v7p33(self) -> kernel::fuse_setxattr_in {
+		match self.as_v7p33() {
+			Some(v7p33) => *v7p33,
+			None => {
+				let v7p1 = self.as_v7p1();
+				new!(kernel::fuse_setxattr_in {
+					size: v7p1.size,
+					flags: v7p1.flags,
+				})
+			},
+		}
+	}
 }
 
 // }}}
@@ -276,6 +477,21 @@ impl<'a> Versioned<fuse_read_in<'a>> {
 		}
 		None
 	}
+
+	#[inline]
+	pub(crate) fn to_v7p9(self) -> kernel::fuse_read_in {
+		match self.as_v7p9() {
+			Some(v7p9) => *v7p9,
+			None => {
+				let v7p1 = self.as_v7p1();
+				new!(kernel::fuse_read_in {
+					fh: v7p1.fh,
+					offset: v7p1.offset,
+					size: v7p1.size,
+				})
+			},
+		}
+	}
 }
 
 // }}}
@@ -331,6 +547,20 @@ impl<'a> Versioned<fuse_release_in<'a>> {
 		}
 		None
 	}
+
+	#[inline]
+	pub(crate) fn to_v7p8(self) -> kernel::fuse_release_in {
+		match self.as_v7p8() {
+			Some(v7p8) => *v7p8,
+			None => {
+				let v7p1 = self.as_v7p1();
+				new!(kernel::fuse_release_in {
+					fh: v7p1.fh,
+					flags: v7p1.flags,
+				})
+			},
+		}
+	}
 }
 
 // }}}
@@ -69,16 +69,16 @@ pub(crate) unsafe fn read_unchecked<T: Dirent>(
 pub(crate) unsafe fn write_unchecked<T: Dirent>(
 	dirent: T,
 	name: &crate::NodeName,
-	buf: &mut [u8],
+	buf: &mut [mem::MaybeUninit<u8>],
 ) {
-	let buf_ptr = buf.as_mut_ptr();
+	let buf_ptr = buf.as_mut_ptr().cast::<u8>();
 	let dirent_dst = buf_ptr.cast::<T>();
 	let name_dst = buf_ptr.add(mem::size_of::<T>());
 
 	let name = name.as_bytes();
 	let padding_len = (8 - (name.len() % 8)) % 8;
 
-	dirent_dst.write(dirent);
+	dirent_dst.write_unaligned(dirent);
 	ptr::copy_nonoverlapping(name.as_ptr(), name_dst, name.len());
 	if padding_len > 0 {
 		let padding_dst = name_dst.add(name.len());
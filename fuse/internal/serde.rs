@@ -0,0 +1,190 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+// Implements `Serialize` and `Deserialize` for a struct whose fields are
+// accessed through getter and setter functions.
+//
+// Deserialization starts from `$init` and applies each field's setter. As
+// with `#[derive(Deserialize)]`, every field is required, duplicate fields
+// are rejected, and unknown fields are ignored.
+macro_rules! serde_struct {
+	($t:ty, $init:expr, {
+		$( $field:ident : $field_ty:ty = ($get:expr, $set:expr $(,)?) , )+
+	}) => {
+		#[cfg(feature = "serde")]
+		const _: () = {
+			use ::serde::de;
+			use ::serde::ser::SerializeStruct;
+
+			const NAME: &str = stringify!($t);
+			const FIELDS: &[&str] = &[$( stringify!($field) ),+];
+
+			impl ::serde::Serialize for $t {
+				fn serialize<S: ::serde::Serializer>(
+					&self,
+					serializer: S,
+				) -> Result<S::Ok, S::Error> {
+					let len = FIELDS.len();
+					let mut s = serializer.serialize_struct(NAME, len)?;
+					$(
+						s.serialize_field(stringify!($field), &($get)(self))?;
+					)+
+					s.end()
+				}
+			}
+
+			impl<'de> ::serde::Deserialize<'de> for $t {
+				fn deserialize<D: ::serde::Deserializer<'de>>(
+					deserializer: D,
+				) -> Result<$t, D::Error> {
+					deserializer.deserialize_struct(NAME, FIELDS, Visitor)
+				}
+			}
+
+			struct Visitor;
+
+			impl<'de> de::Visitor<'de> for Visitor {
+				type Value = $t;
+
+				fn expecting(
+					&self,
+					fmt: &mut core::fmt::Formatter,
+				) -> core::fmt::Result {
+					write!(fmt, "struct {}", NAME)
+				}
+
+				fn visit_seq<A: de::SeqAccess<'de>>(
+					self,
+					mut seq: A,
+				) -> Result<$t, A::Error> {
+					use crate::internal::serde::next_element;
+
+					let mut value = $init;
+					let mut index = 0;
+					$(
+						let v: $field_ty =
+							next_element(&mut seq, &mut index, &self)?;
+						($set)(&mut value, v);
+					)+
+					Ok(value)
+				}
+
+				fn visit_map<A: de::MapAccess<'de>>(
+					self,
+					mut map: A,
+				) -> Result<$t, A::Error> {
+					use crate::internal::serde::FieldKey;
+
+					let mut value = $init;
+					let mut seen = [false; FIELDS.len()];
+					while let Some(key) = map.next_key_seed(FieldKey(FIELDS))? {
+						let Some(index) = key else {
+							map.next_value::<de::IgnoredAny>()?;
+							continue;
+						};
+						let name = FIELDS[index];
+						if seen[index] {
+							return Err(de::Error::duplicate_field(name));
+						}
+						seen[index] = true;
+						$(
+							if name == stringify!($field) {
+								let v = map.next_value::<$field_ty>()?;
+								($set)(&mut value, v);
+							}
+						)+
+					}
+					for (index, &name) in FIELDS.iter().enumerate() {
+						if !seen[index] {
+							return Err(de::Error::missing_field(name));
+						}
+					}
+					Ok(value)
+				}
+			}
+		};
+	};
+}
+
+// Deserializes a struct field name into its index, or `None` if the field
+// name is unknown.
+#[cfg(feature = "serde")]
+pub(crate) struct FieldKey(pub(crate) &'static [&'static str]);
+
+#[cfg(feature = "serde")]
+impl<'de> serde::de::DeserializeSeed<'de> for FieldKey {
+	type Value = Option<usize>;
+
+	fn deserialize<D: serde::Deserializer<'de>>(
+		self,
+		deserializer: D,
+	) -> Result<Option<usize>, D::Error> {
+		deserializer.deserialize_identifier(self)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::de::Visitor<'de> for FieldKey {
+	type Value = Option<usize>;
+
+	fn expecting(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+		fmt.write_str("field identifier")
+	}
+
+	fn visit_u64<E: serde::de::Error>(
+		self,
+		value: u64,
+	) -> Result<Option<usize>, E> {
+		match usize::try_from(value) {
+			Ok(index) if index < self.0.len() => Ok(Some(index)),
+			_ => Ok(None),
+		}
+	}
+
+	fn visit_str<E: serde::de::Error>(
+		self,
+		value: &str,
+	) -> Result<Option<usize>, E> {
+		Ok(self.0.iter().position(|&name| name == value))
+	}
+
+	fn visit_bytes<E: serde::de::Error>(
+		self,
+		value: &[u8],
+	) -> Result<Option<usize>, E> {
+		Ok(self.0.iter().position(|&name| name.as_bytes() == value))
+	}
+}
+
+// Deserializes the next element of a sequence, which must be present.
+#[cfg(feature = "serde")]
+pub(crate) fn next_element<'de, A, T>(
+	seq: &mut A,
+	index: &mut usize,
+	expected: &dyn serde::de::Expected,
+) -> Result<T, A::Error>
+where
+	A: serde::de::SeqAccess<'de>,
+	T: serde::Deserialize<'de>,
+{
+	match seq.next_element()? {
+		Some(value) => {
+			*index += 1;
+			Ok(value)
+		},
+		None => Err(serde::de::Error::invalid_length(*index, expected)),
+	}
+}
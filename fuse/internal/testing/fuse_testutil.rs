@@ -17,6 +17,7 @@
 use std::mem::size_of;
 use std::slice;
 
+use fuse::client;
 use fuse::kernel;
 use fuse::server::{
 	CuseSocket,
@@ -99,6 +100,30 @@ impl MessageBuilder {
 	}
 }
 
+// Returns the length of the message in `buf`, from its request header.
+pub fn message_len(buf: &fuse::io::MinReadBuffer) -> usize {
+	let header_ptr = buf.as_slice().as_ptr().cast::<kernel::fuse_in_header>();
+	let header = unsafe { header_ptr.read_unaligned() };
+	header.len as usize
+}
+
+// Returns a `fuse_init_out` for the given version, with init flags above the
+// lower 32 bits sent in `flags2`.
+pub fn init_out(
+	protocol_version: (u32, u32),
+	init_flags: u64,
+) -> kernel::fuse_init_out {
+	let mut init_out = kernel::fuse_init_out::new();
+	init_out.major = protocol_version.0;
+	init_out.minor = protocol_version.1;
+	init_out.flags = init_flags as u32;
+	init_out.flags2 = (init_flags >> 32) as u32;
+	if init_out.flags2 != 0 {
+		init_out.flags |= kernel::FUSE_INIT_EXT;
+	}
+	init_out
+}
+
 #[macro_export]
 macro_rules! decode_request {
 	($t:ty, $buf: ident) => {
@@ -113,9 +138,8 @@ macro_rules! decode_request {
 		let opts = $crate::decode_request_opts!($opts);
 		let request_len = $buf.as_slice().len();
 
-		let mut fuse_init_out = fuse::kernel::fuse_init_out::new();
-		fuse_init_out.major = opts.protocol_version.0;
-		fuse_init_out.minor = opts.protocol_version.1;
+		let fuse_init_out =
+			$crate::init_out(opts.protocol_version, opts.init_flags);
 		let layout = FuseLayout::new(&fuse_init_out).unwrap();
 
 		let req_buf = $buf.as_aligned_slice().truncate(request_len);
@@ -126,6 +150,7 @@ macro_rules! decode_request {
 
 pub struct DecodeRequestOpts {
 	pub protocol_version: (u32, u32),
+	pub init_flags: u64,
 }
 
 #[macro_export]
@@ -136,6 +161,7 @@ macro_rules! decode_request_opts {
 				fuse::kernel::FUSE_KERNEL_VERSION,
 				fuse::kernel::FUSE_KERNEL_MINOR_VERSION,
 			),
+			init_flags: 0,
 		}
 	};
 	({
@@ -143,6 +169,18 @@ macro_rules! decode_request_opts {
 	}) => {
 		DecodeRequestOpts {
 			protocol_version: $version,
+			init_flags: 0,
+		}
+	};
+	({
+		init_flags: $flags:expr,
+	}) => {
+		DecodeRequestOpts {
+			protocol_version: (
+				fuse::kernel::FUSE_KERNEL_VERSION,
+				fuse::kernel::FUSE_KERNEL_MINOR_VERSION,
+			),
+			init_flags: u64::from($flags),
 		}
 	};
 }
@@ -190,6 +228,64 @@ impl CuseSocket for FakeSocket {}
 
 impl FuseSocket for FakeSocket {}
 
+pub struct FakeClientSocket(std::cell::Cell<Vec<u8>>);
+
+impl FakeClientSocket {
+	pub fn new() -> FakeClientSocket {
+		Self(std::cell::Cell::new(Vec::new()))
+	}
+
+	pub fn into_vec(self) -> Vec<u8> {
+		self.0.into_inner()
+	}
+}
+
+impl client::io::Socket for FakeClientSocket {
+	type Error = ();
+
+	fn send(
+		&self,
+		buf: fuse::io::SendBuf,
+	) -> Result<(), client::io::SendError<()>> {
+		self.0.set(buf.to_vec());
+		Ok(())
+	}
+
+	fn recv(&self, _buf: &mut [u8]) -> Result<usize, client::io::RecvError<()>> {
+		// Requests are only encoded, so there is never a response to receive.
+		Err(client::io::RecvError::Other(()))
+	}
+}
+
+impl client::io::CuseSocket for FakeClientSocket {}
+
+impl client::io::FuseSocket for FakeClientSocket {}
+
+#[macro_export]
+macro_rules! encode_request {
+	($request:expr) => {
+		$crate::encode_request!($request, {})
+	};
+	($request:expr, $opts:tt $(,)?) => {{
+		use fuse::client::FuseRequestSender;
+		use fuse::server::FuseLayout;
+		use $crate::EncodeRequestOpts;
+
+		let opts = $crate::encode_request_opts!($opts);
+
+		let fuse_init_out =
+			$crate::init_out(opts.protocol_version, opts.init_flags);
+		let layout = FuseLayout::new(&fuse_init_out).unwrap();
+
+		let request_id = core::num::NonZeroU64::new(0xAABBCCDD).unwrap();
+		let socket = $crate::FakeClientSocket::new();
+		FuseRequestSender::new(&socket, layout, request_id)
+			.send(&$request)
+			.unwrap();
+		socket.into_vec()
+	}};
+}
+
 #[macro_export]
 macro_rules! encode_response {
 	($reply:expr) => {
@@ -201,20 +297,20 @@ macro_rules! encode_response {
 
 		let opts = $crate::encode_request_opts!($opts);
 
-		let mut fuse_init_out = fuse::kernel::fuse_init_out::new();
-		fuse_init_out.major = opts.protocol_version.0;
-		fuse_init_out.minor = opts.protocol_version.1;
+		let fuse_init_out =
+			$crate::init_out(opts.protocol_version, opts.init_flags);
 		let layout = FuseLayout::new(&fuse_init_out).unwrap();
 
 		let request_id = core::num::NonZeroU64::new(0xAABBCCDD).unwrap();
 		let socket = $crate::FakeSocket::new();
-		FuseReplySender::new(socket, request_id, layout).ok($reply).unwrap();
+		FuseReplySender::new(&socket, layout, request_id).ok($reply).unwrap();
 		socket.into_vec()
 	}};
 }
 
 pub struct EncodeRequestOpts {
 	pub protocol_version: (u32, u32),
+	pub init_flags: u64,
 }
 
 #[macro_export]
@@ -225,6 +321,7 @@ macro_rules! encode_request_opts {
 				fuse::kernel::FUSE_KERNEL_VERSION,
 				fuse::kernel::FUSE_KERNEL_MINOR_VERSION,
 			),
+			init_flags: 0,
 		}
 	};
 	({
@@ -232,6 +329,18 @@ macro_rules! encode_request_opts {
 	}) => {
 		EncodeRequestOpts {
 			protocol_version: $version,
+			init_flags: 0,
+		}
+	};
+	({
+		init_flags: $flags:expr,
+	}) => {
+		EncodeRequestOpts {
+			protocol_version: (
+				fuse::kernel::FUSE_KERNEL_VERSION,
+				fuse::kernel::FUSE_KERNEL_MINOR_VERSION,
+			),
+			init_flags: u64::from($flags),
 		}
 	};
 }
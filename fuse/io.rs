@@ -84,6 +84,12 @@ impl<'a> AlignedSlice<'a> {
 	}
 }
 
+impl AsAlignedSlice for AlignedSlice<'_> {
+	fn as_aligned_slice(&self) -> AlignedSlice<'_> {
+		*self
+	}
+}
+
 // }}}
 
 // AlignedSliceMut {{{
@@ -158,6 +164,18 @@ impl<'a> From<AlignedSliceMut<'a>> for AlignedSlice<'a> {
 	}
 }
 
+impl AsAlignedSlice for AlignedSliceMut<'_> {
+	fn as_aligned_slice(&self) -> AlignedSlice<'_> {
+		AlignedSlice { inner: self.inner }
+	}
+}
+
+impl AsAlignedSliceMut for AlignedSliceMut<'_> {
+	fn as_aligned_slice_mut(&mut self) -> AlignedSliceMut<'_> {
+		AlignedSliceMut { inner: self.inner }
+	}
+}
+
 // }}}
 
 // MinReadBuffer {{{
@@ -247,6 +265,144 @@ impl AsAlignedSliceMut for MinReadBuffer {
 
 // }}}
 
+// VecBuffer {{{
+
+/// A heap-allocated aligned buffer.
+///
+/// A [`MinReadBuffer`] is only large enough for connections with the default
+/// `max_write`, and is usually allocated on the stack. Servers that raise
+/// `max_write` (up to 1 MiB on Linux) should allocate receive buffers of
+/// the connection's [`recv_buf_len`] on the heap instead.
+///
+/// ```rust
+/// # fn f<S: fuse::server::FuseSocket>(
+/// # 	conn: &fuse::server::FuseConnection<S>,
+/// # ) -> Result<(), fuse::server::ServerError<S::Error>> {
+/// let mut buf = fuse::io::VecBuffer::with_capacity(conn.recv_buf_len());
+/// while let Some(request) = conn.recv(buf.as_aligned_slice_mut())? {
+/// 	// ...
+/// 	# let _ = request;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`recv_buf_len`]: crate::server::FuseConnection::recv_buf_len
+#[cfg(feature = "std")]
+#[derive(Clone)]
+pub struct VecBuffer {
+	// Storing the buffer as `u64` words guarantees its alignment.
+	words: alloc::vec::Vec<u64>,
+	len: usize,
+}
+
+#[cfg(feature = "std")]
+impl VecBuffer {
+	/// Allocates a new `VecBuffer` of [`MinReadBuffer::LEN`] bytes.
+	#[must_use]
+	pub fn new() -> VecBuffer {
+		Self::with_capacity(MinReadBuffer::LEN)
+	}
+
+	/// Allocates a new `VecBuffer` of at least `len` bytes, initialized with
+	/// zeros.
+	///
+	/// Buffers smaller than [`MinReadBuffer::LEN`] are rounded up to that
+	/// length, because the client may reject reads into smaller buffers.
+	///
+	/// # Panics
+	///
+	/// Panics on memory allocation failure.
+	#[must_use]
+	pub fn with_capacity(len: usize) -> VecBuffer {
+		let len = core::cmp::max(len, MinReadBuffer::LEN);
+		let word_len = len.div_ceil(mem::size_of::<u64>());
+		Self {
+			words: alloc::vec![0u64; word_len],
+			len,
+		}
+	}
+
+	/// Returns the length of the buffer, in bytes.
+	#[inline]
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Returns `true` if the buffer has a length of zero.
+	///
+	/// This is always `false`, because a `VecBuffer` is never smaller than
+	/// [`MinReadBuffer::LEN`].
+	#[inline]
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Borrows the buffer as a byte slice.
+	#[inline]
+	#[must_use]
+	pub fn as_slice(&self) -> &[u8] {
+		let ptr = self.words.as_ptr().cast::<u8>();
+		unsafe { core::slice::from_raw_parts(ptr, self.len) }
+	}
+
+	/// Borrows the buffer as a mutable byte slice.
+	#[inline]
+	#[must_use]
+	pub fn as_slice_mut(&mut self) -> &mut [u8] {
+		let ptr = self.words.as_mut_ptr().cast::<u8>();
+		unsafe { core::slice::from_raw_parts_mut(ptr, self.len) }
+	}
+
+	/// Borrows the buffer as an aligned byte slice.
+	#[inline]
+	#[must_use]
+	pub fn as_aligned_slice(&self) -> AlignedSlice<'_> {
+		AlignedSlice { inner: self.as_slice() }
+	}
+
+	/// Borrows the buffer as a mutable aligned byte slice.
+	#[inline]
+	#[must_use]
+	pub fn as_aligned_slice_mut(&mut self) -> AlignedSliceMut<'_> {
+		AlignedSliceMut { inner: self.as_slice_mut() }
+	}
+}
+
+#[cfg(feature = "std")]
+impl Default for VecBuffer {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(feature = "std")]
+impl core::fmt::Debug for VecBuffer {
+	fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+		fmt.debug_struct("VecBuffer")
+			.field("len", &self.len)
+			.finish_non_exhaustive()
+	}
+}
+
+#[cfg(feature = "std")]
+impl AsAlignedSlice for VecBuffer {
+	fn as_aligned_slice(&self) -> AlignedSlice<'_> {
+		self.as_aligned_slice()
+	}
+}
+
+#[cfg(feature = "std")]
+impl AsAlignedSliceMut for VecBuffer {
+	fn as_aligned_slice_mut(&mut self) -> AlignedSliceMut<'_> {
+		self.as_aligned_slice_mut()
+	}
+}
+
+// }}}
+
 // SendBuf {{{
 
 /// A buffer of sendable data split into chunks.
@@ -256,6 +412,11 @@ impl AsAlignedSliceMut for MinReadBuffer {
 /// converting a `SendBuf` to an array of [`IoSlice`] (or equivalent) without
 /// performing dynamic allocation.
 ///
+/// Sockets that wrap another socket, or that are used to test one, can
+/// construct a `SendBuf` with [`SendBuf::new`] or [`SendBuf::from_chunks`].
+/// Transports without vectored writes can flatten a `SendBuf` into their own
+/// buffer with [`SendBuf::copy_to`].
+///
 /// [`MAX_CHUNKS_LEN`]: SendBuf::MAX_CHUNKS_LEN
 /// [`IoSlice`]: https://doc.rust-lang.org/std/io/struct.IoSlice.html
 pub struct SendBuf<'a> {
@@ -269,7 +430,7 @@ impl SendBuf<'_> {
 	///
 	/// This constant is intended to be opaque, and its exact value should not
 	/// be hardcoded into calling code.
-	pub const MAX_CHUNKS_LEN: usize = 5;
+	pub const MAX_CHUNKS_LEN: usize = 6;
 }
 
 impl<'a> SendBuf<'a> {
@@ -278,7 +439,7 @@ impl<'a> SendBuf<'a> {
 	pub(crate) fn new_1(len: usize, chunk_1: &'a [u8]) -> Self {
 		Self {
 			len,
-			chunks: [chunk_1, b"", b"", b"", b""],
+			chunks: [chunk_1, b"", b"", b"", b"", b""],
 			chunks_len: 1,
 		}
 	}
@@ -292,7 +453,7 @@ impl<'a> SendBuf<'a> {
 	) -> Self {
 		Self {
 			len,
-			chunks: [chunk_1, chunk_2, b"", b"", b""],
+			chunks: [chunk_1, chunk_2, b"", b"", b"", b""],
 			chunks_len: 2,
 		}
 	}
@@ -307,7 +468,7 @@ impl<'a> SendBuf<'a> {
 	) -> Self {
 		Self {
 			len,
-			chunks: [chunk_1, chunk_2, chunk_3, b"", b""],
+			chunks: [chunk_1, chunk_2, chunk_3, b"", b"", b""],
 			chunks_len: 3,
 		}
 	}
@@ -323,7 +484,7 @@ impl<'a> SendBuf<'a> {
 	) -> Self {
 		Self {
 			len,
-			chunks: [chunk_1, chunk_2, chunk_3, chunk_4, b""],
+			chunks: [chunk_1, chunk_2, chunk_3, chunk_4, b"", b""],
 			chunks_len: 4,
 		}
 	}
@@ -340,13 +501,85 @@ impl<'a> SendBuf<'a> {
 	) -> Self {
 		Self {
 			len,
-			chunks: [chunk_1, chunk_2, chunk_3, chunk_4, chunk_5],
+			chunks: [chunk_1, chunk_2, chunk_3, chunk_4, chunk_5, b""],
 			chunks_len: 5,
 		}
 	}
+
+	#[inline]
+	#[must_use]
+	pub(crate) fn new_6(
+		len: usize,
+		chunk_1: &'a [u8],
+		chunk_2: &'a [u8],
+		chunk_3: &'a [u8],
+		chunk_4: &'a [u8],
+		chunk_5: &'a [u8],
+		chunk_6: &'a [u8],
+	) -> Self {
+		Self {
+			len,
+			chunks: [chunk_1, chunk_2, chunk_3, chunk_4, chunk_5, chunk_6],
+			chunks_len: 6,
+		}
+	}
 }
 
 impl<'a> SendBuf<'a> {
+	/// Creates a new `SendBuf` containing a single chunk.
+	#[inline]
+	#[must_use]
+	pub fn new(chunk: &'a [u8]) -> SendBuf<'a> {
+		Self::new_1(chunk.len(), chunk)
+	}
+
+	/// Creates a new `SendBuf` from a sequence of chunks.
+	///
+	/// Returns `None` if there are more than [`MAX_CHUNKS_LEN`] chunks, or if
+	/// their total length would overflow `usize`.
+	///
+	/// Sockets that wrap another socket can use this to add chunks to a
+	/// reply, for example to prepend a transport-specific frame header:
+	///
+	/// ```rust
+	/// use fuse::io::SendBuf;
+	///
+	/// fn framed<'a>(
+	/// 	frame: &'a [u8],
+	/// 	buf: &SendBuf<'a>,
+	/// ) -> Option<SendBuf<'a>> {
+	/// 	let chunks = buf.chunks().iter().copied();
+	/// 	SendBuf::from_chunks(core::iter::once(frame).chain(chunks))
+	/// }
+	///
+	/// let buf = SendBuf::new(b"reply");
+	/// let framed = framed(b"len=5;", &buf).unwrap();
+	/// assert_eq!(framed.chunks(), [&b"len=5;"[..], &b"reply"[..]]);
+	/// assert_eq!(framed.len(), 11);
+	/// ```
+	///
+	/// [`MAX_CHUNKS_LEN`]: SendBuf::MAX_CHUNKS_LEN
+	#[must_use]
+	pub fn from_chunks<I>(chunks: I) -> Option<SendBuf<'a>>
+	where
+		I: IntoIterator<Item = &'a [u8]>,
+	{
+		let mut buf = Self {
+			len: 0,
+			chunks: [b""; SendBuf::MAX_CHUNKS_LEN],
+			chunks_len: 0,
+		};
+		for chunk in chunks {
+			if buf.chunks_len == SendBuf::MAX_CHUNKS_LEN {
+				return None;
+			}
+			buf.len = buf.len.checked_add(chunk.len())?;
+			buf.chunks[buf.chunks_len] = chunk;
+			buf.chunks_len += 1;
+		}
+		Some(buf)
+	}
+
 	/// Returns the chunks contained within this `SendBuf` as a slice.
 	///
 	/// The slice length will not exceed [`MAX_CHUNKS_LEN`].
@@ -379,6 +612,43 @@ impl<'a> SendBuf<'a> {
 		self.len
 	}
 
+	/// Copies the contents of this `SendBuf` into a contiguous buffer.
+	///
+	/// Returns the number of bytes copied, which is the lesser of
+	/// [`SendBuf::len`] and the length of `buf`. This is intended for
+	/// transports that can't send a message from multiple chunks, such as
+	/// a shared memory ring with fixed-size slots.
+	///
+	/// # Examples
+	///
+	/// ```rust
+	/// use fuse::io::SendBuf;
+	///
+	/// let chunks: [&[u8]; 3] = [b"hello", b", ", b"world"];
+	/// let buf = SendBuf::from_chunks(chunks).unwrap();
+	/// let mut out = [0u8; 64];
+	/// let len = buf.copy_to(&mut out);
+	/// assert_eq!(&out[..len], b"hello, world");
+	///
+	/// let mut short = [0u8; 8];
+	/// assert_eq!(buf.copy_to(&mut short), 8);
+	/// assert_eq!(&short, b"hello, w");
+	/// ```
+	#[must_use]
+	pub fn copy_to(&self, buf: &mut [u8]) -> usize {
+		let mut copied = 0;
+		for chunk in self.chunks() {
+			let dst = &mut buf[copied..];
+			let n = core::cmp::min(chunk.len(), dst.len());
+			dst[..n].copy_from_slice(&chunk[..n]);
+			copied += n;
+			if copied == buf.len() {
+				break;
+			}
+		}
+		copied
+	}
+
 	/// Call a closure on each chunk, placing the results into provided storage.
 	///
 	/// The returned slice contains the portion of the storage that was
@@ -14,6 +14,7 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::internal::compat;
 use crate::kernel;
 use crate::server;
 
@@ -59,10 +60,8 @@ impl server::FuseReply for kernel::fuse_attr_out {
 		&self,
 		reply_sender: server::FuseReplySender<'_, S>,
 	) -> Result<(), server::SendError<S::Error>> {
-		let mut buf = self.as_bytes();
-		if reply_sender.layout.version_minor() < 9 {
-			buf = &buf[..kernel::FUSE_COMPAT_ATTR_OUT_SIZE];
-		}
+		let version_minor = reply_sender.layout.version_minor();
+		let buf = compat::fuse_attr_out_bytes(version_minor, self);
 		reply_sender.inner.send_1(buf)
 	}
 }
@@ -72,10 +71,9 @@ impl server::FuseReply for kernel::fuse_entry_out {
 		&self,
 		reply_sender: server::FuseReplySender<'_, S>,
 	) -> Result<(), server::SendError<S::Error>> {
-		let mut buf = self.as_bytes();
-		if reply_sender.layout.version_minor() < 9 {
-			buf = &buf[..kernel::FUSE_COMPAT_ENTRY_OUT_SIZE];
-		}
+		reply_sender.check_attr(&self.attr)?;
+		let version_minor = reply_sender.layout.version_minor();
+		let buf = compat::fuse_entry_out_bytes(version_minor, self);
 		reply_sender.inner.send_1(buf)
 	}
 }
@@ -85,13 +83,13 @@ impl server::FuseReply for kernel::fuse_init_out {
 		&self,
 		reply_sender: server::FuseReplySender<'_, S>,
 	) -> Result<(), server::SendError<S::Error>> {
-		let mut buf = self.as_bytes();
-		if self.minor < 5 {
-			buf = &buf[..kernel::FUSE_COMPAT_INIT_OUT_SIZE];
-		} else if self.minor < 23 {
-			buf = &buf[..kernel::FUSE_COMPAT_22_INIT_OUT_SIZE];
+		// The client ignores `flags2` unless `FUSE_INIT_EXT` is set.
+		let mut init_out = *self;
+		if init_out.flags & kernel::FUSE_INIT_EXT == 0 {
+			init_out.flags2 = 0;
 		}
-		reply_sender.inner.send_1(buf)
+		let buf_len = compat::fuse_init_out_len(self.minor);
+		reply_sender.inner.send_1(&init_out.as_bytes()[..buf_len])
 	}
 }
 
@@ -100,10 +98,28 @@ impl server::FuseReply for kernel::fuse_statfs_out {
 		&self,
 		reply_sender: server::FuseReplySender<'_, S>,
 	) -> Result<(), server::SendError<S::Error>> {
-		let mut buf = self.as_bytes();
-		if reply_sender.layout.version_minor() < 4 {
-			buf = &buf[..kernel::FUSE_COMPAT_STATFS_SIZE];
-		}
+		let version_minor = reply_sender.layout.version_minor();
+		let buf = compat::fuse_statfs_out_bytes(version_minor, self);
 		reply_sender.inner.send_1(buf)
 	}
 }
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for kernel::fuse_opcode {
+	fn serialize<S: serde::Serializer>(
+		&self,
+		serializer: S,
+	) -> Result<S::Ok, S::Error> {
+		serializer.serialize_u32(self.0)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for kernel::fuse_opcode {
+	fn deserialize<D: serde::Deserializer<'de>>(
+		deserializer: D,
+	) -> Result<kernel::fuse_opcode, D::Error> {
+		let opcode = serde::Deserialize::deserialize(deserializer)?;
+		Ok(kernel::fuse_opcode(opcode))
+	}
+}
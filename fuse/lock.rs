@@ -74,6 +74,44 @@ impl LockRange {
 		}
 	}
 
+	/// Creates a new `LockRange` covering an entire file.
+	#[inline]
+	#[must_use]
+	pub const fn whole_file() -> LockRange {
+		Self {
+			start: 0,
+			length: None,
+		}
+	}
+
+	/// Creates a new unbounded `LockRange` starting at the given offset.
+	#[inline]
+	#[must_use]
+	pub const fn from_offset(start: u64) -> LockRange {
+		Self {
+			start,
+			length: None,
+		}
+	}
+
+	/// Returns `true` if the range contains the given offset.
+	#[must_use]
+	pub const fn contains(&self, offset: u64) -> bool {
+		if offset < self.start {
+			return false;
+		}
+		match self.end() {
+			None => true,
+			Some(end) => offset <= end,
+		}
+	}
+
+	/// Returns `true` if the range has any offsets in common with `other`.
+	#[must_use]
+	pub const fn overlaps(&self, other: &LockRange) -> bool {
+		self.contains(other.start) || other.contains(self.start)
+	}
+
 	pub(crate) fn decode(
 		raw: &kernel::fuse_file_lock,
 	) -> Result<LockRange, LockError> {
@@ -141,8 +179,16 @@ impl fmt::Debug for LockRange {
 /// [`RequestHeader::process_id`](crate::RequestHeader::process_id), although
 /// on some platforms they may be equivalent.
 ///
+/// On Linux, process IDs in lock requests and responses are in the same PID
+/// namespace as [`RequestHeader::process_id`], which may differ from the PID
+/// namespace of the server. See [`parse_proc_status_nspid`] for translating
+/// process IDs between namespaces.
+///
 /// For representing lock ownership the [`LockOwner`] type should be used
 /// instead.
+///
+/// [`RequestHeader::process_id`]: crate::RequestHeader::process_id
+/// [`parse_proc_status_nspid`]: crate::os::linux::parse_proc_status_nspid
 #[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct ProcessId {
 	pid: num::NonZeroU32,
@@ -180,6 +226,10 @@ impl fmt::Debug for ProcessId {
 pub enum LockError {
 	/// A record lock's range is zero-length.
 	EmptyRange,
+	/// A record lock's range starts before the beginning of the file.
+	InvalidRange,
+	/// A record lock's offset isn't relative to the start of the file.
+	UnsupportedWhence,
 }
 
 /// Whether a lock is an exclusive (write) or shared (read) lock.
@@ -197,7 +247,7 @@ const F_RDLCK: LockMode = crate::os::freebsd::F_RDLCK;
 const F_WRLCK: LockMode = crate::os::freebsd::F_WRLCK;
 
 #[cfg(target_os = "freebsd")]
-const F_UNLCK: LockMode = crate::os::freebsd::F_UNLCK;
+pub(crate) const F_UNLCK: LockMode = crate::os::freebsd::F_UNLCK;
 
 #[cfg(target_os = "linux")]
 const F_RDLCK: LockMode = crate::os::linux::F_RDLCK;
@@ -206,7 +256,7 @@ const F_RDLCK: LockMode = crate::os::linux::F_RDLCK;
 const F_WRLCK: LockMode = crate::os::linux::F_WRLCK;
 
 #[cfg(target_os = "linux")]
-const F_UNLCK: LockMode = crate::os::linux::F_UNLCK;
+pub(crate) const F_UNLCK: LockMode = crate::os::linux::F_UNLCK;
 
 impl fmt::Debug for LockMode {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
@@ -244,6 +294,88 @@ impl Lock {
 		Self { mode, range, process_id }
 	}
 
+	/// Creates a new shared (read) `Lock` with the given range.
+	#[cfg(any(target_os = "freebsd", target_os = "linux"))]
+	#[inline]
+	#[must_use]
+	pub fn read(range: LockRange) -> Lock {
+		Self::new(F_RDLCK, range, None)
+	}
+
+	/// Creates a new exclusive (write) `Lock` with the given range.
+	#[cfg(any(target_os = "freebsd", target_os = "linux"))]
+	#[inline]
+	#[must_use]
+	pub fn write(range: LockRange) -> Lock {
+		Self::new(F_WRLCK, range, None)
+	}
+
+	/// Creates a new `Lock` that removes locks in the given range.
+	#[cfg(any(target_os = "freebsd", target_os = "linux"))]
+	#[inline]
+	#[must_use]
+	pub fn unlock(range: LockRange) -> Lock {
+		Self::new(F_UNLCK, range, None)
+	}
+
+	/// Converts a [`libc::flock`] into a `Lock`.
+	///
+	/// # Errors
+	///
+	/// Returns an error if `l_whence` isn't `SEEK_SET`, or if the range
+	/// would start before the beginning of the file.
+	#[cfg(feature = "libc")]
+	#[allow(clippy::unnecessary_cast)] // field types vary between platforms
+	pub fn from_flock(flock: &libc::flock) -> Result<Lock, LockError> {
+		if flock.l_whence as i32 != libc::SEEK_SET {
+			return Err(LockError::UnsupportedWhence);
+		}
+		let start = flock.l_start as i64;
+		let len = flock.l_len as i64;
+		if start < 0 {
+			return Err(LockError::InvalidRange);
+		}
+		let range = if len >= 0 {
+			LockRange::new(start as u64, num::NonZeroU64::new(len as u64))
+		} else {
+			// A negative length covers the bytes before `l_start`.
+			let range_start = start + len;
+			if range_start < 0 {
+				return Err(LockError::InvalidRange);
+			}
+			let length = num::NonZeroU64::new(len.unsigned_abs());
+			LockRange::new(range_start as u64, length)
+		};
+		let process_id = u32::try_from(flock.l_pid).ok()
+			.and_then(ProcessId::new);
+		let mode = LockMode(flock.l_type as u32);
+		Ok(Lock { mode, range, process_id })
+	}
+
+	/// Converts the `Lock` into a [`libc::flock`], with `l_whence` set to
+	/// `SEEK_SET`.
+	///
+	/// Offsets and lengths too large for `off_t` are saturated.
+	#[cfg(feature = "libc")]
+	#[must_use]
+	#[allow(clippy::unnecessary_cast)] // field types vary between platforms
+	pub fn to_flock(self) -> libc::flock {
+		let start = core::cmp::min(self.range.start, OFFSET_MAX);
+		let len = match self.range.length {
+			None => 0,
+			Some(length) => core::cmp::min(length.get(), OFFSET_MAX),
+		};
+		let pid = self.process_id.map_or(0, ProcessId::get);
+
+		let mut flock: libc::flock = unsafe { core::mem::zeroed() };
+		flock.l_type = self.mode.0 as libc::c_short;
+		flock.l_whence = libc::SEEK_SET as libc::c_short;
+		flock.l_start = start as libc::off_t;
+		flock.l_len = len as libc::off_t;
+		flock.l_pid = pid as libc::pid_t;
+		flock
+	}
+
 	/// Returns the lock's mode (exclusive or shared).
 	#[inline]
 	#[must_use]
@@ -273,6 +405,15 @@ impl Lock {
 		let process_id = ProcessId::new(raw.pid);
 		Ok(Lock { mode, range, process_id })
 	}
+
+	pub(crate) fn encode(&self) -> kernel::fuse_file_lock {
+		kernel::fuse_file_lock {
+			start: self.range.start,
+			end: self.range.end().unwrap_or(OFFSET_MAX),
+			r#type: self.mode.0,
+			pid: self.process_id.map_or(0, ProcessId::get),
+		}
+	}
 }
 
 impl fmt::Debug for Lock {
@@ -0,0 +1,310 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Request metrics for FUSE servers.
+//!
+//! A [`Collector`] implements [`server::Hooks`], counting requests by opcode,
+//! replies by error number, and recording a histogram of request latencies.
+//! The current values can be read at any time with [`Collector::snapshot`]
+//! and exported in whatever format the server's monitoring system uses.
+//!
+//! Counters are updated with relaxed atomic operations, so a snapshot taken
+//! while requests are in flight may be slightly inconsistent between
+//! counters.
+
+use core::fmt;
+use core::num::NonZeroU64;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+
+use crate::kernel::fuse_opcode;
+use crate::server;
+
+/// The number of opcodes with their own request counter.
+///
+/// Requests with an opcode of `OPCODE_COUNT` or greater, such as
+/// `CUSE_INIT`, are counted together by [`Snapshot::other_requests`].
+pub const OPCODE_COUNT: usize = 64;
+
+/// The number of error numbers with their own reply counter.
+///
+/// FUSE clients reject error numbers outside the interval `[1, 512)`, so
+/// every valid error number has its own counter.
+pub const ERROR_COUNT: usize = 512;
+
+/// The number of buckets in the latency histogram.
+pub const LATENCY_BUCKETS: usize = 24;
+
+// Collector {{{
+
+/// A [`server::Hooks`] implementation that collects request metrics.
+///
+/// Request counts are recorded by [`Hooks::request`], reply counts by
+/// [`Hooks::reply`], and latencies by [`Hooks::request_completed`].
+///
+/// [`Hooks::request`]: server::Hooks::request
+/// [`Hooks::reply`]: server::Hooks::reply
+/// [`Hooks::request_completed`]: server::Hooks::request_completed
+pub struct Collector {
+	requests: [AtomicU64; OPCODE_COUNT],
+	other_requests: AtomicU64,
+	request_nanos: [AtomicU64; OPCODE_COUNT],
+	replies: [AtomicU64; ERROR_COUNT],
+	other_replies: AtomicU64,
+	latency: [AtomicU64; LATENCY_BUCKETS],
+	latency_nanos: AtomicU64,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicU64 = AtomicU64::new(0);
+
+impl Collector {
+	/// Creates a new `Collector` with all counters set to zero.
+	#[must_use]
+	pub const fn new() -> Collector {
+		Collector {
+			requests: [ZERO; OPCODE_COUNT],
+			other_requests: ZERO,
+			request_nanos: [ZERO; OPCODE_COUNT],
+			replies: [ZERO; ERROR_COUNT],
+			other_replies: ZERO,
+			latency: [ZERO; LATENCY_BUCKETS],
+			latency_nanos: ZERO,
+		}
+	}
+
+	/// Returns a copy of the current counter values.
+	#[must_use]
+	pub fn snapshot(&self) -> Snapshot {
+		let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+		Snapshot {
+			requests: self.requests.each_ref().map(load),
+			other_requests: load(&self.other_requests),
+			request_nanos: self.request_nanos.each_ref().map(load),
+			replies: self.replies.each_ref().map(load),
+			other_replies: load(&self.other_replies),
+			latency: self.latency.each_ref().map(load),
+			latency_nanos: load(&self.latency_nanos),
+		}
+	}
+}
+
+impl fmt::Debug for Collector {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		self.snapshot().fmt(fmt)
+	}
+}
+
+impl server::Hooks for Collector {
+	fn request(&self, header: &crate::RequestHeader) {
+		let counter = match opcode_index(header.opcode()) {
+			Some(idx) => &self.requests[idx],
+			None => &self.other_requests,
+		};
+		counter.fetch_add(1, Ordering::Relaxed);
+	}
+
+	fn reply(
+		&self,
+		request_id: NonZeroU64,
+		error: Option<crate::Error>,
+		len: usize,
+	) {
+		let _ = (request_id, len);
+		let errno = error.map_or(0, |err| err.0.get().unsigned_abs());
+		let counter = match self.replies.get(errno as usize) {
+			Some(counter) => counter,
+			None => &self.other_replies,
+		};
+		counter.fetch_add(1, Ordering::Relaxed);
+	}
+
+	fn request_completed(
+		&self,
+		header: &crate::RequestHeader,
+		duration: Duration,
+	) {
+		let nanos = duration_nanos(duration);
+		if let Some(idx) = opcode_index(header.opcode()) {
+			self.request_nanos[idx].fetch_add(nanos, Ordering::Relaxed);
+		}
+		self.latency[latency_bucket(duration)]
+			.fetch_add(1, Ordering::Relaxed);
+		self.latency_nanos.fetch_add(nanos, Ordering::Relaxed);
+	}
+}
+
+fn opcode_index(opcode: fuse_opcode) -> Option<usize> {
+	let idx = opcode.0 as usize;
+	if idx < OPCODE_COUNT {
+		return Some(idx);
+	}
+	None
+}
+
+fn duration_nanos(duration: Duration) -> u64 {
+	u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+fn latency_bucket(duration: Duration) -> usize {
+	let micros = duration.as_micros();
+	for idx in 0..LATENCY_BUCKETS - 1 {
+		if micros < (1u128 << idx) {
+			return idx;
+		}
+	}
+	LATENCY_BUCKETS - 1
+}
+
+// }}}
+
+// Snapshot {{{
+
+/// A copy of the counters in a [`Collector`].
+#[derive(Clone)]
+pub struct Snapshot {
+	requests: [u64; OPCODE_COUNT],
+	other_requests: u64,
+	request_nanos: [u64; OPCODE_COUNT],
+	replies: [u64; ERROR_COUNT],
+	other_replies: u64,
+	latency: [u64; LATENCY_BUCKETS],
+	latency_nanos: u64,
+}
+
+impl Snapshot {
+	/// Returns the number of requests received with the given opcode.
+	///
+	/// Returns zero for opcodes that don't have their own counter.
+	#[must_use]
+	pub fn requests(&self, opcode: fuse_opcode) -> u64 {
+		match opcode_index(opcode) {
+			Some(idx) => self.requests[idx],
+			None => 0,
+		}
+	}
+
+	/// Returns the number of requests received with an opcode that doesn't
+	/// have its own counter.
+	#[inline]
+	#[must_use]
+	pub fn other_requests(&self) -> u64 {
+		self.other_requests
+	}
+
+	/// Returns the total number of requests received.
+	#[must_use]
+	pub fn total_requests(&self) -> u64 {
+		self.requests.iter().sum::<u64>() + self.other_requests
+	}
+
+	/// Returns the total time spent handling requests with the given opcode.
+	///
+	/// Returns zero for opcodes that don't have their own counter.
+	#[must_use]
+	pub fn request_duration(&self, opcode: fuse_opcode) -> Duration {
+		match opcode_index(opcode) {
+			Some(idx) => Duration::from_nanos(self.request_nanos[idx]),
+			None => Duration::ZERO,
+		}
+	}
+
+	/// Returns the number of successful replies sent.
+	#[inline]
+	#[must_use]
+	pub fn ok_replies(&self) -> u64 {
+		self.replies[0]
+	}
+
+	/// Returns the number of error replies sent with the given error.
+	///
+	/// Errors outside the interval `[1, 512)` share a single counter.
+	#[must_use]
+	pub fn error_replies(&self, error: crate::Error) -> u64 {
+		let errno = error.0.get().unsigned_abs() as usize;
+		match self.replies.get(errno) {
+			Some(count) => *count,
+			None => self.other_replies,
+		}
+	}
+
+	/// Returns an iterator over the error numbers and counts of error
+	/// replies, skipping error numbers with no replies.
+	///
+	/// The error numbers are positive, as in the OS error constants.
+	pub fn errors(&self) -> impl Iterator<Item = (u16, u64)> + '_ {
+		self.replies
+			.iter()
+			.enumerate()
+			.skip(1)
+			.filter(|(_, count)| **count > 0)
+			.map(|(errno, count)| (errno as u16, *count))
+	}
+
+	/// Returns the total number of replies sent, including errors.
+	#[must_use]
+	pub fn total_replies(&self) -> u64 {
+		self.replies.iter().sum::<u64>() + self.other_replies
+	}
+
+	/// Returns the number of requests in a latency histogram bucket.
+	///
+	/// Buckets are not cumulative. Returns zero if `bucket` is not less than
+	/// [`LATENCY_BUCKETS`].
+	#[must_use]
+	pub fn latency_bucket(&self, bucket: usize) -> u64 {
+		self.latency.get(bucket).copied().unwrap_or(0)
+	}
+
+	/// Returns the exclusive upper bound of a latency histogram bucket.
+	///
+	/// Bucket `i` counts requests that took less than `2^i` microseconds.
+	/// The last bucket has no upper bound, and `None` is returned for it.
+	#[must_use]
+	pub fn latency_bucket_bound(bucket: usize) -> Option<Duration> {
+		if bucket >= LATENCY_BUCKETS - 1 {
+			return None;
+		}
+		Some(Duration::from_micros(1 << bucket))
+	}
+
+	/// Returns the number of requests in the latency histogram.
+	#[must_use]
+	pub fn latency_count(&self) -> u64 {
+		self.latency.iter().sum()
+	}
+
+	/// Returns the total time spent handling requests.
+	#[inline]
+	#[must_use]
+	pub fn latency_sum(&self) -> Duration {
+		Duration::from_nanos(self.latency_nanos)
+	}
+}
+
+impl fmt::Debug for Snapshot {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("Snapshot")
+			.field("total_requests", &self.total_requests())
+			.field("total_replies", &self.total_replies())
+			.field("ok_replies", &self.ok_replies())
+			.field("latency_count", &self.latency_count())
+			.field("latency_sum", &self.latency_sum())
+			.finish()
+	}
+}
+
+// }}}
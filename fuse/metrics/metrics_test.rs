@@ -0,0 +1,124 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::num::NonZeroU64;
+use core::time::Duration;
+
+use fuse::kernel;
+use fuse::kernel::fuse_opcode;
+use fuse::metrics::{Collector, Snapshot, LATENCY_BUCKETS};
+use fuse::os::OsError;
+use fuse::server::{self, Hooks};
+use fuse::testing::{RequestBuilder, SocketPair};
+
+use fuse_testutil as testutil;
+
+fn layout() -> server::FuseLayout {
+	server::FuseLayout::new(&testutil::new!(kernel::fuse_init_out {
+		major: kernel::FUSE_KERNEL_VERSION,
+		minor: kernel::FUSE_KERNEL_MINOR_VERSION,
+	}))
+	.unwrap()
+}
+
+fn request_id(id: u64) -> NonZeroU64 {
+	NonZeroU64::new(id).unwrap()
+}
+
+#[test]
+fn collector_counts() {
+	let mut buf = fuse::io::MinReadBuffer::new();
+	let mut builder = RequestBuilder::new(layout());
+	builder.set_request_id(2);
+	let request = builder
+		.getattr(
+			buf.as_aligned_slice_mut(),
+			fuse::NodeId::ROOT.get(),
+			&kernel::fuse_getattr_in::new(),
+		)
+		.unwrap();
+	let request = server::FuseRequest::new(request, layout()).unwrap();
+	let header = request.header();
+
+	let collector = Collector::new();
+	collector.request(header);
+	collector.request(header);
+	collector.reply(request_id(2), None, 16);
+	collector.reply(request_id(3), Some(OsError::NOT_FOUND), 16);
+	collector.request_completed(header, Duration::from_micros(3));
+	collector.request_completed(header, Duration::from_secs(3600));
+
+	let snapshot = collector.snapshot();
+	assert_eq!(snapshot.requests(fuse_opcode::FUSE_GETATTR), 2);
+	assert_eq!(snapshot.requests(fuse_opcode::FUSE_LOOKUP), 0);
+	assert_eq!(snapshot.total_requests(), 2);
+	assert_eq!(snapshot.ok_replies(), 1);
+	assert_eq!(snapshot.error_replies(OsError::NOT_FOUND), 1);
+	assert_eq!(snapshot.total_replies(), 2);
+
+	let not_found = OsError::NOT_FOUND.0.get().unsigned_abs() as u16;
+	assert_eq!(snapshot.errors().collect::<Vec<_>>(), [(not_found, 1)]);
+
+	// 3µs falls into the bucket for [2µs, 4µs).
+	assert_eq!(snapshot.latency_bucket(2), 1);
+	assert_eq!(snapshot.latency_bucket(LATENCY_BUCKETS - 1), 1);
+	assert_eq!(snapshot.latency_count(), 2);
+	let total = Duration::from_secs(3600) + Duration::from_micros(3);
+	assert_eq!(snapshot.latency_sum(), total);
+	assert_eq!(snapshot.request_duration(fuse_opcode::FUSE_GETATTR), total);
+}
+
+#[test]
+fn latency_bucket_bounds() {
+	assert_eq!(
+		Snapshot::latency_bucket_bound(0),
+		Some(Duration::from_micros(1)),
+	);
+	assert_eq!(
+		Snapshot::latency_bucket_bound(10),
+		Some(Duration::from_micros(1024)),
+	);
+	assert_eq!(Snapshot::latency_bucket_bound(LATENCY_BUCKETS - 1), None);
+}
+
+#[test]
+fn collector_with_hooks_socket() {
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	let kernel_socket = pair.kernel();
+
+	let mut buf = fuse::io::MinReadBuffer::new();
+	let init = RequestBuilder::new(layout())
+		.init(
+			buf.as_aligned_slice_mut(),
+			&testutil::new!(kernel::fuse_init_in {
+				major: kernel::FUSE_KERNEL_VERSION,
+				minor: kernel::FUSE_KERNEL_MINOR_VERSION,
+			}),
+		)
+		.unwrap();
+	kernel_socket.push_request(init.get()).unwrap();
+
+	let collector = Collector::new();
+	let socket = server::HooksSocket::new(pair.server(), &collector);
+	let conn = server::FuseConnection::connect(socket, |_, _| {}).unwrap();
+	drop(conn);
+
+	let snapshot = collector.snapshot();
+	assert_eq!(snapshot.requests(fuse_opcode::FUSE_INIT), 1);
+	assert_eq!(snapshot.ok_replies(), 1);
+}
@@ -39,6 +39,74 @@ impl NodeAttr {
 		}
 	}
 
+	/// Creates a new `NodeAttr` from the metadata of a file.
+	///
+	/// The mode, size, timestamps, link count, owner, device number, and
+	/// block counts are copied from `metadata`. The inode number of
+	/// `metadata` is ignored in favor of `node_id`.
+	#[cfg(all(feature = "std", unix))]
+	#[must_use]
+	pub fn from_metadata(
+		node_id: NodeId,
+		metadata: &std::fs::Metadata,
+	) -> NodeAttr {
+		use std::os::unix::fs::MetadataExt;
+		Self::from_stat_fields(node_id, &StatFields {
+			mode: metadata.mode(),
+			size: metadata.size(),
+			atime: (metadata.atime(), metadata.atime_nsec()),
+			mtime: (metadata.mtime(), metadata.mtime_nsec()),
+			ctime: (metadata.ctime(), metadata.ctime_nsec()),
+			nlink: metadata.nlink(),
+			uid: metadata.uid(),
+			gid: metadata.gid(),
+			rdev: metadata.rdev(),
+			blocks: metadata.blocks(),
+			blksize: metadata.blksize(),
+		})
+	}
+
+	/// Creates a new `NodeAttr` from a `stat` structure.
+	///
+	/// The mode, size, timestamps, link count, owner, device number, and
+	/// block counts are copied from `stat`. The inode number of `stat` is
+	/// ignored in favor of `node_id`.
+	#[cfg(feature = "libc")]
+	#[must_use]
+	#[allow(clippy::unnecessary_cast)] // field types vary between platforms
+	pub fn from_stat(node_id: NodeId, stat: &libc::stat) -> NodeAttr {
+		Self::from_stat_fields(node_id, &StatFields {
+			mode: stat.st_mode as u32,
+			size: stat.st_size as u64,
+			atime: (stat.st_atime as i64, stat.st_atime_nsec as i64),
+			mtime: (stat.st_mtime as i64, stat.st_mtime_nsec as i64),
+			ctime: (stat.st_ctime as i64, stat.st_ctime_nsec as i64),
+			nlink: stat.st_nlink as u64,
+			uid: stat.st_uid,
+			gid: stat.st_gid,
+			rdev: stat.st_rdev as u64,
+			blocks: stat.st_blocks as u64,
+			blksize: stat.st_blksize as u64,
+		})
+	}
+
+	#[cfg(any(feature = "libc", all(feature = "std", unix)))]
+	fn from_stat_fields(node_id: NodeId, stat: &StatFields) -> NodeAttr {
+		let mut attr = NodeAttr::new(node_id);
+		attr.set_mode(FileMode::new(stat.mode));
+		attr.set_size(stat.size);
+		attr.set_atime(stat_time(stat.atime));
+		attr.set_mtime(stat_time(stat.mtime));
+		attr.set_ctime(stat_time(stat.ctime));
+		attr.set_link_count(u32::try_from(stat.nlink).unwrap_or(u32::MAX));
+		attr.set_user_id(stat.uid);
+		attr.set_group_id(stat.gid);
+		attr.set_device_number(encode_device_number(stat.rdev));
+		attr.set_block_count(stat.blocks);
+		attr.set_block_size(u32::try_from(stat.blksize).unwrap_or(u32::MAX));
+		attr
+	}
+
 	/// Returns the raw [`fuse_attr`] for the node attributes.
 	///
 	/// [`fuse_attr`]: kernel::fuse_attr
@@ -71,6 +139,12 @@ impl NodeAttr {
 		unsafe { NodeId::new_unchecked(self.raw.ino) }
 	}
 
+	/// Sets the per-mount unique identifier of the node.
+	#[inline]
+	pub fn set_node_id(&mut self, node_id: NodeId) {
+		self.raw.ino = node_id.get();
+	}
+
 	/// Returns the node's mode, including type and permissions.
 	#[inline]
 	#[must_use]
@@ -308,6 +382,69 @@ impl fmt::Debug for NodeAttr {
 	}
 }
 
+serde_struct!(NodeAttr, NodeAttr::new(NodeId::ROOT), {
+	node_id: NodeId = (NodeAttr::node_id, NodeAttr::set_node_id),
+	mode: FileMode = (NodeAttr::mode, NodeAttr::set_mode),
+	size: u64 = (NodeAttr::size, NodeAttr::set_size),
+	atime: crate::UnixTime = (NodeAttr::atime, NodeAttr::set_atime),
+	mtime: crate::UnixTime = (NodeAttr::mtime, NodeAttr::set_mtime),
+	ctime: crate::UnixTime = (NodeAttr::ctime, NodeAttr::set_ctime),
+	link_count: u32 = (NodeAttr::link_count, NodeAttr::set_link_count),
+	user_id: u32 = (NodeAttr::user_id, NodeAttr::set_user_id),
+	group_id: u32 = (NodeAttr::group_id, NodeAttr::set_group_id),
+	device_number: u32 = (
+		NodeAttr::device_number,
+		NodeAttr::set_device_number,
+	),
+	block_count: u64 = (NodeAttr::block_count, NodeAttr::set_block_count),
+	block_size: u32 = (NodeAttr::block_size, NodeAttr::set_block_size),
+	flags: u32 = (
+		|attr: &NodeAttr| attr.raw.flags,
+		|attr: &mut NodeAttr, flags| attr.raw.flags = flags,
+	),
+});
+
+// The fields of `struct stat` used to fill a `NodeAttr`, normalized to
+// platform-independent types.
+#[cfg(any(feature = "libc", all(feature = "std", unix)))]
+struct StatFields {
+	mode: u32,
+	size: u64,
+	atime: (i64, i64),
+	mtime: (i64, i64),
+	ctime: (i64, i64),
+	nlink: u64,
+	uid: u32,
+	gid: u32,
+	rdev: u64,
+	blocks: u64,
+	blksize: u64,
+}
+
+#[cfg(any(feature = "libc", all(feature = "std", unix)))]
+fn stat_time((seconds, nanos): (i64, i64)) -> crate::UnixTime {
+	let nanos = u32::try_from(nanos).unwrap_or(0);
+	match crate::UnixTime::new(seconds, nanos) {
+		Some(t) => t,
+		None => crate::UnixTime::from_seconds(seconds),
+	}
+}
+
+// Converts a `dev_t` into the 32-bit encoding used by the FUSE protocol.
+//
+// Linux decodes `fuse_attr::rdev` with `new_decode_dev()`, which differs from
+// the 64-bit `dev_t` encoding used by glibc and musl.
+#[cfg(any(feature = "libc", all(feature = "std", unix)))]
+fn encode_device_number(rdev: u64) -> u32 {
+	if cfg!(target_os = "linux") {
+		let major = ((rdev >> 32) & 0xFFFF_F000) | ((rdev >> 8) & 0x0FFF);
+		let minor = ((rdev >> 12) & 0xFFFF_FF00) | (rdev & 0x00FF);
+		let encoded = (minor & 0xFF) | (major << 8) | ((minor & !0xFF) << 12);
+		return encoded as u32;
+	}
+	rdev as u32
+}
+
 /// Optional flags set on [`NodeAttr`].
 #[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
 struct AttributeFlags {
@@ -76,6 +76,26 @@ impl fmt::Debug for NodeId {
 	}
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for NodeId {
+	fn serialize<S: serde::Serializer>(
+		&self,
+		serializer: S,
+	) -> Result<S::Ok, S::Error> {
+		serde::Serialize::serialize(&self.bits, serializer)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for NodeId {
+	fn deserialize<D: serde::Deserializer<'de>>(
+		deserializer: D,
+	) -> Result<NodeId, D::Error> {
+		let bits = serde::Deserialize::deserialize(deserializer)?;
+		Ok(NodeId { bits })
+	}
+}
+
 impl fmt::Binary for NodeId {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		self.bits.fmt(fmt)
@@ -45,6 +45,16 @@ pub struct NodeName {
 }
 
 impl NodeName {
+	/// The name `"."`, which refers to a directory itself.
+	pub const DOT: &'static NodeName = unsafe {
+		NodeName::from_bytes_unchecked(b".")
+	};
+
+	/// The name `".."`, which refers to a directory's parent.
+	pub const DOT_DOT: &'static NodeName = unsafe {
+		NodeName::from_bytes_unchecked(b"..")
+	};
+
 	/// Attempts to reborrow a string as a node name.
 	///
 	/// # Errors
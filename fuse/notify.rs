@@ -196,6 +196,25 @@ impl<'a> InvalidateEntry<'a> {
 	pub fn name(&self) -> &crate::NodeName {
 		self.name
 	}
+
+	/// Returns whether the entry should only be marked as expired.
+	#[must_use]
+	pub fn expire_only(&self) -> bool {
+		self.raw.flags & kernel::FUSE_EXPIRE_ONLY != 0
+	}
+
+	/// Sets whether the entry should only be marked as expired.
+	///
+	/// An expired entry is revalidated with `FUSE_LOOKUP` on next access,
+	/// but is not removed from the dentry cache. This requires the
+	/// `HAS_EXPIRE_ONLY` init flag to be negotiated.
+	pub fn set_expire_only(&mut self, expire_only: bool) {
+		if expire_only {
+			self.raw.flags |= kernel::FUSE_EXPIRE_ONLY;
+		} else {
+			self.raw.flags &= !kernel::FUSE_EXPIRE_ONLY;
+		}
+	}
 }
 
 impl fmt::Debug for InvalidateEntry<'_> {
@@ -203,6 +222,7 @@ impl fmt::Debug for InvalidateEntry<'_> {
 		fmt.debug_struct("InvalidateEntry")
 			.field("parent_id", &self.parent_id())
 			.field("name", &self.name())
+			.field("expire_only", &self.expire_only())
 			.finish()
 	}
 }
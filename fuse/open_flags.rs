@@ -0,0 +1,313 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::fmt;
+
+/// Flags passed to `open()`.
+///
+/// `OpenFlags` stores the raw flags sent by the client. The values of
+/// `open()` flags are platform-specific, so accessors such as
+/// [`append`](Self::append) interpret them using the current platform's
+/// values. On platforms other than FreeBSD and Linux, only the access mode
+/// is interpreted.
+///
+/// The client may remove some flags before sending a request. For example,
+/// Linux doesn't send `O_CREAT`, `O_EXCL`, or `O_NOCTTY` in `FUSE_OPEN`.
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct OpenFlags {
+	bits: u32,
+}
+
+/// The access mode of an [`OpenFlags`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[non_exhaustive]
+pub enum OpenAccessMode {
+	/// The file is open for reading only (`O_RDONLY`).
+	ReadOnly,
+
+	/// The file is open for writing only (`O_WRONLY`).
+	WriteOnly,
+
+	/// The file is open for reading and writing (`O_RDWR`).
+	ReadWrite,
+}
+
+impl OpenFlags {
+	/// Creates a new `OpenFlags` with the given value.
+	#[inline]
+	#[must_use]
+	pub const fn new(flags: u32) -> OpenFlags {
+		Self { bits: flags }
+	}
+
+	/// Returns the flags as a primitive integer.
+	#[inline]
+	#[must_use]
+	pub const fn get(self) -> u32 {
+		self.bits
+	}
+
+	/// Returns the access mode (`O_ACCMODE`).
+	///
+	/// Returns `None` if the access mode bits have a value other than
+	/// `O_RDONLY`, `O_WRONLY`, or `O_RDWR`.
+	#[inline]
+	#[must_use]
+	pub const fn access_mode(self) -> Option<OpenAccessMode> {
+		match self.bits & sys::O_ACCMODE {
+			sys::O_RDONLY => Some(OpenAccessMode::ReadOnly),
+			sys::O_WRONLY => Some(OpenAccessMode::WriteOnly),
+			sys::O_RDWR => Some(OpenAccessMode::ReadWrite),
+			_ => None,
+		}
+	}
+
+	/// Returns whether writes are appended to the end of the file
+	/// (`O_APPEND`).
+	#[inline]
+	#[must_use]
+	pub const fn append(self) -> bool {
+		self.has(sys::O_APPEND)
+	}
+
+	/// Returns whether the file should be created if it doesn't exist
+	/// (`O_CREAT`).
+	#[inline]
+	#[must_use]
+	pub const fn create(self) -> bool {
+		self.has(sys::O_CREAT)
+	}
+
+	/// Returns whether I/O should bypass the page cache (`O_DIRECT`).
+	#[inline]
+	#[must_use]
+	pub const fn direct(self) -> bool {
+		self.has(sys::O_DIRECT)
+	}
+
+	/// Returns whether the open should fail if the node isn't a directory
+	/// (`O_DIRECTORY`).
+	#[inline]
+	#[must_use]
+	pub const fn directory(self) -> bool {
+		self.has(sys::O_DIRECTORY)
+	}
+
+	/// Returns whether the open should fail if the file already exists
+	/// (`O_EXCL`).
+	#[inline]
+	#[must_use]
+	pub const fn exclusive(self) -> bool {
+		self.has(sys::O_EXCL)
+	}
+
+	/// Returns whether the open should fail if the node is a symlink
+	/// (`O_NOFOLLOW`).
+	#[inline]
+	#[must_use]
+	pub const fn no_follow(self) -> bool {
+		self.has(sys::O_NOFOLLOW)
+	}
+
+	/// Returns whether I/O should not block (`O_NONBLOCK`).
+	#[inline]
+	#[must_use]
+	pub const fn non_blocking(self) -> bool {
+		self.has(sys::O_NONBLOCK)
+	}
+
+	/// Returns whether writes should complete with file integrity (`O_SYNC`).
+	#[inline]
+	#[must_use]
+	pub const fn sync(self) -> bool {
+		self.has(sys::O_SYNC)
+	}
+
+	/// Returns whether the file should be truncated to zero length
+	/// (`O_TRUNC`).
+	#[inline]
+	#[must_use]
+	pub const fn truncate(self) -> bool {
+		self.has(sys::O_TRUNC)
+	}
+
+	#[inline]
+	#[must_use]
+	const fn has(self, mask: u32) -> bool {
+		mask != 0 && self.bits & mask == mask
+	}
+}
+
+impl fmt::Debug for OpenFlags {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		// 8 hex digits + 2 for leading "0x".
+		write!(fmt, "{:#010X}", self.bits)
+	}
+}
+
+impl fmt::Binary for OpenFlags {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		self.bits.fmt(fmt)
+	}
+}
+
+impl fmt::LowerHex for OpenFlags {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		self.bits.fmt(fmt)
+	}
+}
+
+impl fmt::UpperHex for OpenFlags {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		self.bits.fmt(fmt)
+	}
+}
+
+// The access mode is the same on all supported platforms.
+#[allow(dead_code)]
+mod accmode {
+	pub(super) const O_ACCMODE: u32 = 0o3;
+	pub(super) const O_RDONLY: u32 = 0o0;
+	pub(super) const O_WRONLY: u32 = 0o1;
+	pub(super) const O_RDWR: u32 = 0o2;
+}
+
+#[cfg(target_os = "freebsd")]
+mod sys {
+	pub(super) use super::accmode::*;
+
+	pub(super) const O_NONBLOCK: u32 = 0x0000_0004;
+	pub(super) const O_APPEND: u32 = 0x0000_0008;
+	pub(super) const O_SYNC: u32 = 0x0000_0080;
+	pub(super) const O_NOFOLLOW: u32 = 0x0000_0100;
+	pub(super) const O_CREAT: u32 = 0x0000_0200;
+	pub(super) const O_TRUNC: u32 = 0x0000_0400;
+	pub(super) const O_EXCL: u32 = 0x0000_0800;
+	pub(super) const O_DIRECT: u32 = 0x0001_0000;
+	pub(super) const O_DIRECTORY: u32 = 0x0002_0000;
+}
+
+// Most Linux architectures use the values in `asm-generic/fcntl.h`, with
+// overrides for a few flags on some architectures.
+#[cfg(target_os = "linux")]
+mod sys {
+	pub(super) use super::accmode::*;
+
+	#[cfg(not(any(
+		target_arch = "mips",
+		target_arch = "mips64",
+		target_arch = "sparc",
+		target_arch = "sparc64",
+	)))]
+	mod arch_common {
+		pub(crate) const O_CREAT: u32 = 0o100;
+		pub(crate) const O_EXCL: u32 = 0o200;
+		pub(crate) const O_TRUNC: u32 = 0o1000;
+		pub(crate) const O_APPEND: u32 = 0o2000;
+		pub(crate) const O_NONBLOCK: u32 = 0o4000;
+		pub(crate) const O_DSYNC: u32 = 0o10000;
+		pub(crate) const O_DIRECT: u32 = 0o40000;
+		pub(crate) const O_DIRECTORY: u32 = 0o200000;
+		pub(crate) const O_NOFOLLOW: u32 = 0o400000;
+		pub(crate) const __O_SYNC: u32 = 0o4000000;
+	}
+
+	#[cfg(not(any(
+		target_arch = "arm",
+		target_arch = "aarch64",
+		target_arch = "m68k",
+		target_arch = "mips",
+		target_arch = "mips64",
+		target_arch = "powerpc",
+		target_arch = "powerpc64",
+		target_arch = "sparc",
+		target_arch = "sparc64",
+	)))]
+	use arch_common as arch;
+
+	#[cfg(any(
+		target_arch = "arm",
+		target_arch = "aarch64",
+		target_arch = "m68k",
+	))]
+	mod arch {
+		pub(crate) use super::arch_common::*;
+		pub(crate) const O_DIRECTORY: u32 = 0o40000;
+		pub(crate) const O_NOFOLLOW: u32 = 0o100000;
+		pub(crate) const O_DIRECT: u32 = 0o200000;
+	}
+
+	#[cfg(any(target_arch = "powerpc", target_arch = "powerpc64"))]
+	mod arch {
+		pub(crate) use super::arch_common::*;
+		pub(crate) const O_DIRECTORY: u32 = 0o40000;
+		pub(crate) const O_NOFOLLOW: u32 = 0o100000;
+		pub(crate) const O_DIRECT: u32 = 0o400000;
+	}
+
+	#[cfg(any(target_arch = "mips", target_arch = "mips64"))]
+	mod arch {
+		pub(crate) const O_APPEND: u32 = 0x0008;
+		pub(crate) const O_DSYNC: u32 = 0x0010;
+		pub(crate) const O_NONBLOCK: u32 = 0x0080;
+		pub(crate) const O_CREAT: u32 = 0x0100;
+		pub(crate) const O_TRUNC: u32 = 0x0200;
+		pub(crate) const O_EXCL: u32 = 0x0400;
+		pub(crate) const __O_SYNC: u32 = 0x4000;
+		pub(crate) const O_DIRECT: u32 = 0x8000;
+		pub(crate) const O_DIRECTORY: u32 = 0o200000;
+		pub(crate) const O_NOFOLLOW: u32 = 0o400000;
+	}
+
+	#[cfg(any(target_arch = "sparc", target_arch = "sparc64"))]
+	mod arch {
+		pub(crate) const O_APPEND: u32 = 0x0008;
+		pub(crate) const O_CREAT: u32 = 0x0200;
+		pub(crate) const O_TRUNC: u32 = 0x0400;
+		pub(crate) const O_EXCL: u32 = 0x0800;
+		pub(crate) const O_DSYNC: u32 = 0x2000;
+		pub(crate) const O_NONBLOCK: u32 = 0x4000;
+		pub(crate) const O_DIRECTORY: u32 = 0x10000;
+		pub(crate) const O_NOFOLLOW: u32 = 0x20000;
+		pub(crate) const O_DIRECT: u32 = 0x100000;
+		pub(crate) const __O_SYNC: u32 = 0x800000;
+	}
+
+	pub(super) const O_APPEND: u32 = arch::O_APPEND;
+	pub(super) const O_CREAT: u32 = arch::O_CREAT;
+	pub(super) const O_DIRECT: u32 = arch::O_DIRECT;
+	pub(super) const O_DIRECTORY: u32 = arch::O_DIRECTORY;
+	pub(super) const O_EXCL: u32 = arch::O_EXCL;
+	pub(super) const O_NOFOLLOW: u32 = arch::O_NOFOLLOW;
+	pub(super) const O_NONBLOCK: u32 = arch::O_NONBLOCK;
+	pub(super) const O_SYNC: u32 = arch::__O_SYNC | arch::O_DSYNC;
+	pub(super) const O_TRUNC: u32 = arch::O_TRUNC;
+}
+
+#[cfg(not(any(target_os = "freebsd", target_os = "linux")))]
+mod sys {
+	pub(super) use super::accmode::*;
+
+	pub(super) const O_APPEND: u32 = 0;
+	pub(super) const O_CREAT: u32 = 0;
+	pub(super) const O_DIRECT: u32 = 0;
+	pub(super) const O_DIRECTORY: u32 = 0;
+	pub(super) const O_EXCL: u32 = 0;
+	pub(super) const O_NOFOLLOW: u32 = 0;
+	pub(super) const O_NONBLOCK: u32 = 0;
+	pub(super) const O_SYNC: u32 = 0;
+	pub(super) const O_TRUNC: u32 = 0;
+}
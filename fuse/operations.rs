@@ -38,6 +38,7 @@ pub(crate) mod lookup;
 pub(crate) mod lseek;
 pub(crate) mod mkdir;
 pub(crate) mod mknod;
+pub(crate) mod notify_reply;
 pub(crate) mod open;
 pub(crate) mod opendir;
 pub(crate) mod poll;
@@ -50,10 +51,14 @@ pub(crate) mod releasedir;
 pub(crate) mod removexattr;
 pub(crate) mod rename;
 pub(crate) mod rmdir;
+pub(crate) mod security_ctx;
 pub(crate) mod setattr;
 pub(crate) mod setlk;
 pub(crate) mod setxattr;
 pub(crate) mod statfs;
+pub(crate) mod statx;
 pub(crate) mod symlink;
+pub(crate) mod syncfs;
+pub(crate) mod tmpfile;
 pub(crate) mod unlink;
 pub(crate) mod write;
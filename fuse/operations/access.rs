@@ -17,6 +17,7 @@
 use core::fmt;
 use core::marker::PhantomData;
 
+use crate::client;
 use crate::kernel;
 use crate::server::decode;
 
@@ -53,6 +54,22 @@ try_from_fuse_request!(AccessRequest<'a>, |request| {
 	})
 });
 
+impl client::FuseRequest for AccessRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let body = new!(kernel::fuse_access_in {
+			mask: self.mask,
+		});
+		request_sender.inner.send_1(
+			kernel::fuse_opcode::FUSE_ACCESS,
+			self.node_id.get(),
+			body.as_bytes(),
+		)
+	}
+}
+
 impl fmt::Debug for AccessRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("AccessRequest")
@@ -93,8 +93,7 @@ where
 		attr.set_mode(fuse::FileMode::S_IFREG | 0o755);
 		attr.set_link_count(1);
 
-		let mut reply = fuse::kernel::fuse_attr_out::new();
-		reply.attr = *attr.raw();
+		let reply = server::GetattrResponse::new(attr);
 		send_reply.ok(&reply).unwrap();
 	}
 }
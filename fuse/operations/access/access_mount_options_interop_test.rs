@@ -0,0 +1,277 @@
+// Copyright 2022 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+// Tests for how the `allow_other` and `default_permissions` mount options
+// affect which requests reach the server, and with which credentials.
+//
+// * Without `allow_other`, the kernel rejects all access by users other than
+//   the user that mounted the filesystem. No request is sent to the server.
+//
+// * With `allow_other`, other users may access the filesystem. Their user
+//   and group IDs are available from the request header.
+//
+// * Without `default_permissions`, the kernel sends `FUSE_ACCESS` for calls
+//   to `access()`, and sends `FUSE_OPEN` without checking the file mode. The
+//   server is responsible for any permission checks.
+//
+// * With `default_permissions`, the kernel checks the file mode itself.
+//   `FUSE_ACCESS` is not sent, and `FUSE_OPEN` is sent only if the caller
+//   passes the kernel's permission checks.
+
+use std::panic;
+use std::sync::mpsc;
+
+use fuse::server;
+use fuse::server::FuseRequest;
+
+use interop_testutil::{
+	errno,
+	fork_as_user,
+	fuse_interop_test,
+	libc_errno,
+	path_cstr,
+	OsError,
+};
+
+const NOBODY_UID: libc::uid_t = 65534;
+const NOBODY_GID: libc::gid_t = 65534;
+
+struct TestFS {
+	requests: mpsc::Sender<String>,
+	allow_other: bool,
+	default_permissions: bool,
+}
+
+struct TestHandlers<'a, S> {
+	fs: &'a TestFS,
+	conn: &'a server::FuseConnection<S>,
+}
+
+impl interop_testutil::TestFS for TestFS {
+	fn dispatch_request(
+		&self,
+		conn: &server::FuseConnection<interop_testutil::DevFuse>,
+		request: FuseRequest<'_>,
+	) {
+		use fuse::server::FuseHandlers;
+		(TestHandlers{fs: self, conn}).dispatch(request);
+	}
+
+	#[cfg(target_os = "linux")]
+	fn linux_mount_options(
+		&self,
+		mount_options: &mut fuse::os::linux::MountOptions,
+	) {
+		mount_options.set_allow_other(self.allow_other);
+		mount_options.set_default_permissions(self.default_permissions);
+	}
+
+	#[cfg(target_os = "freebsd")]
+	fn freebsd_mount_options(
+		&self,
+		mount_options: &mut fuse::os::freebsd::MountOptions,
+	) {
+		mount_options.set_allow_other(self.allow_other);
+		mount_options.set_default_permissions(self.default_permissions);
+	}
+}
+
+fn node_attr(node_id: fuse::NodeId) -> fuse::NodeAttr {
+	let mut attr = fuse::NodeAttr::new(node_id);
+	if node_id.is_root() {
+		attr.set_mode(fuse::FileMode::S_IFDIR | 0o755);
+		attr.set_link_count(2);
+	} else {
+		attr.set_mode(fuse::FileMode::S_IFREG | 0o640);
+		attr.set_link_count(1);
+	}
+	attr
+}
+
+impl<'a, S> server::FuseHandlers for TestHandlers<'a, S>
+where
+	S: server::FuseSocket,
+	S::Error: core::fmt::Debug,
+{
+	fn unimplemented(&self, request: FuseRequest<'_>) {
+		self.conn.reply(request.id()).err(OsError::UNIMPLEMENTED).unwrap();
+	}
+
+	fn lookup(&self, request: FuseRequest<'_>) {
+		let send_reply = self.conn.reply(request.id());
+		let request = server::LookupRequest::try_from(request).unwrap();
+
+		if !request.parent_id().is_root() {
+			return send_reply.err(OsError::NOT_FOUND).unwrap();
+		}
+		if request.name() != "file.txt" {
+			return send_reply.err(OsError::NOT_FOUND).unwrap();
+		}
+
+		let attr = node_attr(fuse::NodeId::new(2).unwrap());
+		let mut entry = fuse::Entry::new(attr);
+		entry.set_cache_timeout(std::time::Duration::from_secs(60));
+
+		send_reply.ok(&entry).unwrap();
+	}
+
+	fn getattr(&self, request: FuseRequest<'_>) {
+		let send_reply = self.conn.reply(request.id());
+		let request = server::GetattrRequest::try_from(request).unwrap();
+
+		let attr = node_attr(request.node_id());
+		let reply = server::GetattrResponse::new(attr);
+		send_reply.ok(&reply).unwrap();
+	}
+
+	fn access(&self, request: FuseRequest<'_>) {
+		let send_reply = self.conn.reply(request.id());
+		let header = request.header();
+		let request_str = format!(
+			"FUSE_ACCESS uid={} gid={}",
+			header.user_id(),
+			header.group_id(),
+		);
+		self.fs.requests.send(request_str).unwrap();
+		send_reply.ok_empty().unwrap();
+	}
+
+	fn open(&self, request: FuseRequest<'_>) {
+		let send_reply = self.conn.reply(request.id());
+		let header = request.header();
+		let request_str = format!(
+			"FUSE_OPEN uid={} gid={}",
+			header.user_id(),
+			header.group_id(),
+		);
+		self.fs.requests.send(request_str).unwrap();
+
+		let mut reply = server::OpenResponse::new();
+		reply.set_handle(1002);
+		send_reply.ok(&reply).unwrap();
+	}
+}
+
+fn mount_options_test(
+	allow_other: bool,
+	default_permissions: bool,
+	test_fn: impl FnOnce(&std::path::Path) + panic::UnwindSafe,
+) -> Vec<String> {
+	let (request_send, request_recv) = mpsc::channel();
+	let fs = TestFS {
+		requests: request_send,
+		allow_other,
+		default_permissions,
+	};
+	fuse_interop_test(fs, test_fn);
+	request_recv.iter().collect()
+}
+
+fn open_rdonly(path: &std::ffi::CStr) -> libc::c_int {
+	let fd = unsafe { libc::open(path.as_ptr(), libc::O_RDONLY) };
+	if fd != -1 {
+		unsafe { libc::close(fd) };
+	}
+	fd
+}
+
+#[test]
+fn owner_without_default_permissions() {
+	let requests = mount_options_test(false, false, |root| {
+		let path = path_cstr(root.join("file.txt"));
+
+		let rc = unsafe { libc::access(path.as_ptr(), libc::R_OK) };
+		assert_eq!(rc, 0);
+
+		assert_ne!(open_rdonly(&path), -1);
+	});
+
+	#[cfg(target_os = "linux")]
+	assert_eq!(requests, &[
+		"FUSE_ACCESS uid=0 gid=0",
+		"FUSE_OPEN uid=0 gid=0",
+	]);
+}
+
+#[test]
+fn owner_with_default_permissions() {
+	let requests = mount_options_test(false, true, |root| {
+		let path = path_cstr(root.join("file.txt"));
+
+		let rc = unsafe { libc::access(path.as_ptr(), libc::R_OK) };
+		assert_eq!(rc, 0);
+
+		assert_ne!(open_rdonly(&path), -1);
+	});
+
+	#[cfg(target_os = "linux")]
+	assert_eq!(requests, &[
+		"FUSE_OPEN uid=0 gid=0",
+	]);
+}
+
+#[test]
+fn other_user_without_allow_other() {
+	let requests = mount_options_test(false, false, |root| {
+		let path = path_cstr(root.join("file.txt"));
+
+		let err = fork_as_user(NOBODY_UID, NOBODY_GID, || open_rdonly(&path));
+		assert_eq!(err, errno::EACCES.get() as libc::c_int);
+
+		// The mounting user is unaffected.
+		assert_ne!(open_rdonly(&path), -1, "errno {}", libc_errno());
+	});
+
+	#[cfg(target_os = "linux")]
+	assert_eq!(requests, &[
+		"FUSE_OPEN uid=0 gid=0",
+	]);
+}
+
+#[test]
+fn other_user_with_allow_other() {
+	let requests = mount_options_test(true, false, |root| {
+		let path = path_cstr(root.join("file.txt"));
+
+		// The file mode doesn't grant read access to other users, but the
+		// kernel leaves permission checks to the server.
+		let err = fork_as_user(NOBODY_UID, NOBODY_GID, || open_rdonly(&path));
+		assert_eq!(err, 0);
+	});
+
+	#[cfg(target_os = "linux")]
+	assert_eq!(requests, &[
+		"FUSE_OPEN uid=65534 gid=65534",
+	]);
+}
+
+#[test]
+fn other_user_with_allow_other_and_default_permissions() {
+	let requests = mount_options_test(true, true, |root| {
+		let path = path_cstr(root.join("file.txt"));
+
+		let err = fork_as_user(NOBODY_UID, NOBODY_GID, || open_rdonly(&path));
+		assert_eq!(err, errno::EACCES.get() as libc::c_int);
+
+		let err = fork_as_user(NOBODY_UID, NOBODY_GID, || unsafe {
+			libc::access(path.as_ptr(), libc::R_OK)
+		});
+		assert_eq!(err, errno::EACCES.get() as libc::c_int);
+	});
+
+	#[cfg(target_os = "linux")]
+	assert!(requests.is_empty(), "{:?}", requests);
+}
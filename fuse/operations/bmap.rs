@@ -16,12 +16,17 @@
 
 use core::fmt;
 
+use crate::client;
 use crate::kernel;
+use crate::server;
 use crate::server::decode;
 
 // BmapRequest {{{
 
 /// Request type for `FUSE_BMAP`.
+///
+/// The kernel sends `FUSE_BMAP` for the `FIBMAP` ioctl and when activating
+/// a swap file. It is only sent for filesystems mounted as `fuseblk`.
 #[derive(Clone, Copy)]
 pub struct BmapRequest<'a> {
 	header: &'a kernel::fuse_in_header,
@@ -29,16 +34,19 @@ pub struct BmapRequest<'a> {
 }
 
 impl BmapRequest<'_> {
+	/// Returns the ID of the file whose block should be mapped.
 	#[must_use]
 	pub fn node_id(&self) -> crate::NodeId {
 		unsafe { crate::NodeId::new_unchecked(self.header.nodeid) }
 	}
 
+	/// Returns the index of the block within the file.
 	#[must_use]
 	pub fn block(&self) -> u64 {
 		self.body.block
 	}
 
+	/// Returns the block size, as set by the `blksize` mount option.
 	#[must_use]
 	pub fn block_size(&self) -> u32 {
 		self.body.blocksize
@@ -55,6 +63,19 @@ try_from_fuse_request!(BmapRequest<'a>, |request| {
 	Ok(Self { header, body })
 });
 
+impl client::FuseRequest for BmapRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.inner.send_1(
+			kernel::fuse_opcode::FUSE_BMAP,
+			self.header.nodeid,
+			self.body.as_bytes(),
+		)
+	}
+}
+
 impl fmt::Debug for BmapRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("BmapRequest")
@@ -66,3 +87,53 @@ impl fmt::Debug for BmapRequest<'_> {
 }
 
 // }}}
+
+// BmapResponse {{{
+
+/// Response type for `FUSE_BMAP`.
+pub struct BmapResponse {
+	raw: kernel::fuse_bmap_out,
+}
+
+impl BmapResponse {
+	/// Creates a new `BmapResponse` mapping to the given device block.
+	#[inline]
+	#[must_use]
+	pub fn new(block: u64) -> BmapResponse {
+		Self {
+			raw: kernel::fuse_bmap_out { block },
+		}
+	}
+
+	/// Returns the index of the block within the underlying device.
+	#[inline]
+	#[must_use]
+	pub fn block(&self) -> u64 {
+		self.raw.block
+	}
+
+	/// Sets the index of the block within the underlying device.
+	#[inline]
+	pub fn set_block(&mut self, block: u64) {
+		self.raw.block = block;
+	}
+}
+
+impl fmt::Debug for BmapResponse {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("BmapResponse")
+			.field("block", &self.block())
+			.finish()
+	}
+}
+
+impl server::FuseReply for BmapResponse {
+	fn send_to<S: server::FuseSocket>(
+		&self,
+		reply_sender: server::FuseReplySender<'_, S>,
+	) -> Result<(), server::SendError<S::Error>> {
+		reply_sender.inner.send_1(self.raw.as_bytes())
+	}
+}
+
+// }}}
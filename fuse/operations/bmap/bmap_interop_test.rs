@@ -81,8 +81,7 @@ where
 
 		self.fs.requests.send(format!("{:#?}", request)).unwrap();
 
-		let mut reply = fuse::kernel::fuse_bmap_out::new();
-		reply.block = 5678;
+		let reply = server::BmapResponse::new(5678);
 		send_reply.ok(&reply).unwrap();
 	}
 
@@ -112,8 +111,8 @@ where
 		if request.header().raw().nodeid != 2 {
 			return send_reply.err(OsError::NOT_FOUND).unwrap();
 		}
-		let mut reply = fuse::kernel::fuse_open_out::new();
-		reply.fh = 10;
+		let mut reply = server::OpenResponse::new();
+		reply.set_handle(10);
 		send_reply.ok(&reply).unwrap();
 	}
 
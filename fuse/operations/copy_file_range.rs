@@ -14,14 +14,21 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use core::cmp;
 use core::fmt;
 
+use crate::client;
 use crate::kernel;
+use crate::server;
 use crate::server::decode;
 
 // CopyFileRangeRequest {{{
 
 /// Request type for `FUSE_COPY_FILE_RANGE`.
+///
+/// The kernel sends `FUSE_COPY_FILE_RANGE` for the `copy_file_range()`
+/// system call when both files belong to the same FUSE connection. Servers
+/// may implement it by cloning the range, or by copying the data directly.
 #[derive(Clone, Copy)]
 pub struct CopyFileRangeRequest<'a> {
 	header: &'a kernel::fuse_in_header,
@@ -29,41 +36,63 @@ pub struct CopyFileRangeRequest<'a> {
 }
 
 impl CopyFileRangeRequest<'_> {
+	/// Returns the ID of the file being copied from.
 	#[must_use]
 	pub fn input_node_id(&self) -> crate::NodeId {
 		unsafe { crate::NodeId::new_unchecked(self.header.nodeid) }
 	}
 
+	/// Returns the handle of the file being copied from.
 	#[must_use]
 	pub fn input_handle(&self) -> u64 {
 		self.body.fh_in
 	}
 
+	/// Returns the offset within the input file at which to start copying.
 	#[must_use]
 	pub fn input_offset(&self) -> u64 {
 		self.body.off_in
 	}
 
+	/// Returns the ID of the file being copied to.
 	#[must_use]
 	pub fn output_node_id(&self) -> crate::NodeId {
 		unsafe { crate::NodeId::new_unchecked(self.body.nodeid_out) }
 	}
 
+	/// Returns the handle of the file being copied to.
 	#[must_use]
 	pub fn output_handle(&self) -> u64 {
 		self.body.fh_out
 	}
 
+	/// Returns the offset within the output file at which to start writing.
 	#[must_use]
 	pub fn output_offset(&self) -> u64 {
 		self.body.off_out
 	}
 
+	/// Returns the number of bytes to copy.
+	///
+	/// Older kernels may request more bytes than can be reported in a
+	/// [`CopyFileRangeResponse`]; see [`max_copy_len`](Self::max_copy_len).
 	#[must_use]
 	pub fn len(&self) -> u64 {
 		self.body.len
 	}
 
+	/// Returns the number of bytes to copy, limited to the largest count
+	/// that can be reported in a [`CopyFileRangeResponse`].
+	///
+	/// Servers should copy at most this many bytes. Copying fewer bytes than
+	/// requested is not an error; `copy_file_range()` returns the count to
+	/// its caller, which may retry with the remainder.
+	#[must_use]
+	pub fn max_copy_len(&self) -> u32 {
+		cmp::min(self.body.len, u64::from(u32::MAX)) as u32
+	}
+
+	/// Returns the request's flags.
 	#[must_use]
 	pub fn flags(&self) -> CopyFileRangeRequestFlags {
 		CopyFileRangeRequestFlags {
@@ -86,6 +115,19 @@ try_from_fuse_request!(CopyFileRangeRequest<'a>, |request| {
 	Ok(Self { header, body })
 });
 
+impl client::FuseRequest for CopyFileRangeRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.inner.send_1(
+			kernel::fuse_opcode::FUSE_COPY_FILE_RANGE,
+			self.header.nodeid,
+			self.body.as_bytes(),
+		)
+	}
+}
+
 impl fmt::Debug for CopyFileRangeRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("CopyFileRangeRequest")
@@ -103,6 +145,55 @@ impl fmt::Debug for CopyFileRangeRequest<'_> {
 
 // }}}
 
+// CopyFileRangeResponse {{{
+
+/// Response type for `FUSE_COPY_FILE_RANGE`.
+pub struct CopyFileRangeResponse {
+	raw: kernel::fuse_write_out,
+}
+
+impl CopyFileRangeResponse {
+	/// Creates a new `CopyFileRangeResponse` for a copy of `bytes_copied`
+	/// bytes.
+	///
+	/// The count may be less than the request's length, for example if the
+	/// end of the input file was reached. A count of zero indicates that no
+	/// bytes were copied.
+	#[inline]
+	#[must_use]
+	pub fn new(bytes_copied: u32) -> CopyFileRangeResponse {
+		let mut raw = kernel::fuse_write_out::new();
+		raw.size = bytes_copied;
+		Self { raw }
+	}
+
+	/// Returns the number of bytes copied.
+	#[inline]
+	#[must_use]
+	pub fn bytes_copied(&self) -> u32 {
+		self.raw.size
+	}
+}
+
+impl fmt::Debug for CopyFileRangeResponse {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("CopyFileRangeResponse")
+			.field("bytes_copied", &self.bytes_copied())
+			.finish()
+	}
+}
+
+impl server::FuseReply for CopyFileRangeResponse {
+	fn send_to<S: server::FuseSocket>(
+		&self,
+		reply_sender: server::FuseReplySender<'_, S>,
+	) -> Result<(), server::SendError<S::Error>> {
+		reply_sender.inner.send_1(self.raw.as_bytes())
+	}
+}
+
+// }}}
+
 // CopyFileRangeRequestFlags {{{
 
 /// Optional flags set on [`CopyFileRangeRequest`].
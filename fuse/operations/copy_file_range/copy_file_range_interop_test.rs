@@ -63,8 +63,7 @@ where
 		let request = server::CopyFileRangeRequest::try_from(request).unwrap();
 		self.fs.requests.send(format!("{:#?}", request)).unwrap();
 
-		let mut reply = fuse::kernel::fuse_write_out::new();
-		reply.size = 500;
+		let reply = server::CopyFileRangeResponse::new(500);
 		send_reply.ok(&reply).unwrap();
 	}
 
@@ -102,11 +101,11 @@ where
 		let request = server::OpenRequest::try_from(request).unwrap();
 		self.fs.requests.send(format!("{:#?}", request)).unwrap();
 
-		let mut reply = fuse::kernel::fuse_open_out::new();
+		let mut reply = server::OpenResponse::new();
 		if node_id == 2 {
-			reply.fh = 10;
+			reply.set_handle(10);
 		} else if node_id == 3 {
-			reply.fh = 20;
+			reply.set_handle(20);
 		} else {
 			return send_reply.err(OsError::NOT_FOUND).unwrap();
 		}
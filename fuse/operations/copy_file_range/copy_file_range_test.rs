@@ -14,11 +14,13 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use core::mem::size_of;
+
 use fuse::kernel;
-use fuse::server::CopyFileRangeRequest;
+use fuse::server::{CopyFileRangeRequest, CopyFileRangeResponse};
 
 use fuse_testutil as testutil;
-use fuse_testutil::{decode_request, MessageBuilder};
+use fuse_testutil::{decode_request, encode_response, MessageBuilder};
 
 #[test]
 fn request() {
@@ -46,9 +48,29 @@ fn request() {
 	assert_eq!(req.output_handle(), 14);
 	assert_eq!(req.output_offset(), 15);
 	assert_eq!(req.len(), 16);
+	assert_eq!(req.max_copy_len(), 16);
 	assert_eq!(req.flags(), fuse::CopyFileRangeRequestFlags::new());
 }
 
+#[test]
+fn request_len_exceeds_u32() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_COPY_FILE_RANGE;
+			h.nodeid = 10;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_copy_file_range_in {
+			nodeid_out: 13,
+			len: u64::MAX,
+		}))
+		.build_aligned();
+
+	let req = decode_request!(CopyFileRangeRequest, buf);
+
+	assert_eq!(req.len(), u64::MAX);
+	assert_eq!(req.max_copy_len(), u32::MAX);
+}
+
 #[test]
 fn request_impl_debug() {
 	let buf;
@@ -83,3 +105,38 @@ fn request_impl_debug() {
 		),
 	);
 }
+
+#[test]
+fn response() {
+	let resp = CopyFileRangeResponse::new(1234);
+	assert_eq!(resp.bytes_copied(), 1234);
+
+	let encoded = encode_response!(&resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>()
+					+ size_of::<kernel::fuse_write_out>()) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_sized(&testutil::new!(kernel::fuse_write_out {
+				size: 1234,
+			}))
+			.build()
+	);
+}
+
+#[test]
+fn response_impl_debug() {
+	let resp = CopyFileRangeResponse::new(1234);
+	assert_eq!(
+		format!("{:#?}", resp),
+		concat!(
+			"CopyFileRangeResponse {\n",
+			"    bytes_copied: 1234,\n",
+			"}",
+		),
+	);
+}
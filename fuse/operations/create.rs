@@ -15,10 +15,12 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use core::fmt;
+use core::mem::size_of;
 
+use crate::client;
 use crate::internal::compat;
-use crate::internal::debug;
 use crate::kernel;
+use crate::operations::security_ctx::SecurityContexts;
 use crate::server;
 use crate::server::decode;
 
@@ -30,9 +32,10 @@ pub struct CreateRequest<'a> {
 	header: &'a kernel::fuse_in_header,
 	body: compat::Versioned<compat::fuse_create_in<'a>>,
 	name: &'a crate::NodeName,
+	security_ctx: SecurityContexts<'a>,
 }
 
-impl CreateRequest<'_> {
+impl<'a> CreateRequest<'a> {
 	#[must_use]
 	pub fn node_id(&self) -> crate::NodeId {
 		unsafe { crate::NodeId::new_unchecked(self.header.nodeid) }
@@ -55,7 +58,19 @@ impl CreateRequest<'_> {
 
 	#[must_use]
 	pub fn open_flags(&self) -> crate::OpenFlags {
-		self.body.as_v7p1().flags
+		crate::OpenFlags::new(self.body.as_v7p1().flags)
+	}
+
+	/// Returns whether the server should clear the setuid and setgid bits
+	/// of an existing file that is truncated by `O_TRUNC`.
+	///
+	/// See [`OpenRequest::kill_suidgid`] for details.
+	///
+	/// [`OpenRequest::kill_suidgid`]: crate::server::OpenRequest::kill_suidgid
+	#[must_use]
+	pub fn kill_suidgid(&self) -> bool {
+		let open_flags = self.flags().bits;
+		open_flags & kernel::FUSE_OPEN_KILL_SUIDGID != 0
 	}
 
 	#[must_use]
@@ -66,6 +81,9 @@ impl CreateRequest<'_> {
 		crate::FileMode::new(0)
 	}
 
+	/// Returns the umask of the process that created the file.
+	///
+	/// The umask is zero for protocol versions older than v7.12.
 	#[must_use]
 	pub fn umask(&self) -> u32 {
 		if let Some(body) = self.body.as_v7p12() {
@@ -73,6 +91,32 @@ impl CreateRequest<'_> {
 		}
 		0
 	}
+
+	/// Returns the mode of the new file, with the [`umask`] applied.
+	///
+	/// Unless the [`DONT_MASK`] init flag was negotiated, the kernel applies
+	/// the umask to the mode before sending the request. Otherwise the
+	/// server is responsible for applying it, for example so that a default
+	/// ACL of the parent directory can be used instead. Applying the umask is
+	/// idempotent, so servers that don't implement default ACLs should
+	/// always use this method rather than [`mode`].
+	///
+	/// [`umask`]: Self::umask
+	/// [`mode`]: Self::mode
+	/// [`DONT_MASK`]: crate::FuseInitFlag::DONT_MASK
+	#[must_use]
+	pub fn effective_mode(&self) -> crate::FileMode {
+		self.mode().with_umask(self.umask())
+	}
+
+	/// Returns the security contexts to apply to the new node.
+	///
+	/// Security contexts are only present if the `SECURITY_CTX` init flag
+	/// was negotiated.
+	#[must_use]
+	pub fn security_contexts(&self) -> SecurityContexts<'a> {
+		self.security_ctx
+	}
 }
 
 try_from_fuse_request!(CreateRequest<'a>, |request| {
@@ -92,17 +136,47 @@ try_from_fuse_request!(CreateRequest<'a>, |request| {
 	};
 
 	let name = dec.next_node_name()?;
-
-	Ok(Self { header, body, name })
+	let security_ctx = SecurityContexts::decode(&mut dec, request.layout)?;
+
+	Ok(Self {
+		header,
+		body,
+		name,
+		security_ctx,
+	})
 });
 
+impl client::FuseRequest for CreateRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let body = self.body.to_v7p12();
+		let mut body_bytes = body.as_bytes();
+		if request_sender.layout.version_minor() < 12 {
+			body_bytes = &body_bytes[..size_of::<compat::fuse_create_in_v7p1>()];
+		}
+		let ext = self.security_ctx.extension(request_sender.layout);
+		let mut inner = request_sender.inner;
+		inner.set_extensions_len(ext.len());
+		inner.send_4(
+			kernel::fuse_opcode::FUSE_CREATE,
+			self.header.nodeid,
+			body_bytes,
+			self.name.as_bytes(),
+			b"\0",
+			ext,
+		)
+	}
+}
+
 impl fmt::Debug for CreateRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("CreateRequest")
 			.field("node_id", &self.node_id())
 			.field("name", &self.name())
 			.field("flags", &self.flags())
-			.field("open_flags", &debug::hex_u32(self.open_flags()))
+			.field("open_flags", &self.open_flags())
 			.field("mode", &self.mode())
 			.field("umask", &self.umask())
 			.finish()
@@ -113,7 +187,14 @@ impl fmt::Debug for CreateRequest<'_> {
 
 // CreateResponse {{{
 
-/// Response type for `FUSE_CREATE`.
+/// Response type for `FUSE_CREATE` and `FUSE_TMPFILE`.
+///
+/// Flags that aren't supported by the client's negotiated protocol version
+/// are cleared when the response is sent, so it's safe to set them without
+/// first checking the version. For example [`PASSTHROUGH`] requires at least
+/// protocol version 7.40, and is ignored for older clients.
+///
+/// [`PASSTHROUGH`]: CreateResponseFlag::PASSTHROUGH
 pub struct CreateResponse {
 	entry: crate::Entry,
 	open_out: kernel::fuse_open_out,
@@ -169,6 +250,107 @@ impl CreateResponse {
 		f(&mut flags);
 		self.set_flags(flags)
 	}
+
+	/// Sets the [`DIRECT_IO`] flag, which bypasses the client's page
+	/// cache for the opened file.
+	///
+	/// [`DIRECT_IO`]: CreateResponseFlag::DIRECT_IO
+	#[inline]
+	pub fn set_direct_io(&mut self, direct_io: bool) {
+		self.set_open_flag(kernel::FOPEN_DIRECT_IO, direct_io);
+	}
+
+	/// Sets the [`KEEP_CACHE`] flag, which keeps the client's cached data
+	/// for the opened file.
+	///
+	/// [`KEEP_CACHE`]: CreateResponseFlag::KEEP_CACHE
+	#[inline]
+	pub fn set_keep_cache(&mut self, keep_cache: bool) {
+		self.set_open_flag(kernel::FOPEN_KEEP_CACHE, keep_cache);
+	}
+
+	/// Sets the [`NONSEEKABLE`] flag, which marks the opened file as not
+	/// seekable.
+	///
+	/// [`NONSEEKABLE`]: CreateResponseFlag::NONSEEKABLE
+	#[inline]
+	pub fn set_nonseekable(&mut self, nonseekable: bool) {
+		self.set_open_flag(kernel::FOPEN_NONSEEKABLE, nonseekable);
+	}
+
+	/// Sets the [`CACHE_DIR`] flag, which allows the client to cache the
+	/// opened directory's entries.
+	///
+	/// [`CACHE_DIR`]: CreateResponseFlag::CACHE_DIR
+	#[inline]
+	pub fn set_cache_dir(&mut self, cache_dir: bool) {
+		self.set_open_flag(kernel::FOPEN_CACHE_DIR, cache_dir);
+	}
+
+	/// Sets the [`STREAM`] flag, which marks the opened file as stream-like
+	/// (no file position).
+	///
+	/// [`STREAM`]: CreateResponseFlag::STREAM
+	#[inline]
+	pub fn set_stream(&mut self, stream: bool) {
+		self.set_open_flag(kernel::FOPEN_STREAM, stream);
+	}
+
+	/// Sets the [`NOFLUSH`] flag, which skips flushing the client's cached
+	/// data when the opened file is closed.
+	///
+	/// [`NOFLUSH`]: CreateResponseFlag::NOFLUSH
+	#[inline]
+	pub fn set_noflush(&mut self, noflush: bool) {
+		self.set_open_flag(kernel::FOPEN_NOFLUSH, noflush);
+	}
+
+	/// Sets the [`PARALLEL_DIRECT_WRITES`] flag, which allows concurrent
+	/// direct writes to the opened file.
+	///
+	/// [`PARALLEL_DIRECT_WRITES`]: CreateResponseFlag::PARALLEL_DIRECT_WRITES
+	#[inline]
+	pub fn set_parallel_direct_writes(&mut self, parallel_direct_writes: bool) {
+		self.set_open_flag(
+			kernel::FOPEN_PARALLEL_DIRECT_WRITES,
+			parallel_direct_writes,
+		);
+	}
+
+	#[inline]
+	fn set_open_flag(&mut self, mask: u32, set: bool) {
+		if set {
+			self.open_out.open_flags |= mask;
+		} else {
+			self.open_out.open_flags &= !mask;
+		}
+	}
+
+	/// Returns the backing file ID for passthrough I/O, if the
+	/// [`PASSTHROUGH`] flag is set.
+	///
+	/// [`PASSTHROUGH`]: CreateResponseFlag::PASSTHROUGH
+	#[inline]
+	#[must_use]
+	pub fn backing_id(&self) -> Option<u32> {
+		if self.open_out.open_flags & kernel::FOPEN_PASSTHROUGH == 0 {
+			return None;
+		}
+		Some(self.open_out.backing_id as u32)
+	}
+
+	/// Sets the backing file ID for passthrough I/O, and sets the
+	/// [`PASSTHROUGH`] flag.
+	///
+	/// See [`OpenResponse::set_backing_id`] for details.
+	///
+	/// [`PASSTHROUGH`]: CreateResponseFlag::PASSTHROUGH
+	/// [`OpenResponse::set_backing_id`]: crate::server::OpenResponse::set_backing_id
+	#[inline]
+	pub fn set_backing_id(&mut self, backing_id: u32) {
+		self.open_out.backing_id = backing_id as i32;
+		self.open_out.open_flags |= kernel::FOPEN_PASSTHROUGH;
+	}
 }
 
 impl fmt::Debug for CreateResponse {
@@ -177,6 +359,7 @@ impl fmt::Debug for CreateResponse {
 			.field("entry", &self.entry())
 			.field("handle", &self.handle())
 			.field("flags", &self.flags())
+			.field("backing_id", &format_args!("{:?}", self.backing_id()))
 			.finish()
 	}
 }
@@ -186,11 +369,15 @@ impl server::FuseReply for CreateResponse {
 		&self,
 		reply_sender: server::FuseReplySender<'_, S>,
 	) -> Result<(), server::SendError<S::Error>> {
-		let mut entry_bytes = self.entry.raw().as_bytes();
-		if reply_sender.layout.version_minor < 9 {
-			entry_bytes = &entry_bytes[..kernel::FUSE_COMPAT_ENTRY_OUT_SIZE];
-		}
-		reply_sender.inner.send_2(entry_bytes, self.open_out.as_bytes())
+		reply_sender.check_attr(&self.entry.raw().attr)?;
+		let version_minor = reply_sender.layout.version_minor();
+		let entry_bytes =
+			compat::fuse_entry_out_bytes(version_minor, self.entry.raw());
+		let open_out = compat::fuse_open_out_for_version(
+			version_minor,
+			&self.open_out,
+		);
+		reply_sender.inner.send_2(entry_bytes, open_out.as_bytes())
 	}
 }
 
@@ -200,7 +387,7 @@ impl server::FuseReply for CreateResponse {
 
 #[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct CreateRequestFlags {
-	bits: u32,
+	pub(crate) bits: u32,
 }
 
 #[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -239,6 +426,8 @@ mod response_flags {
 		CACHE_DIR = kernel::FOPEN_CACHE_DIR;
 		STREAM = kernel::FOPEN_STREAM;
 		NOFLUSH = kernel::FOPEN_NOFLUSH;
+		PARALLEL_DIRECT_WRITES = kernel::FOPEN_PARALLEL_DIRECT_WRITES;
+		PASSTHROUGH = kernel::FOPEN_PASSTHROUGH;
 	});
 }
 
@@ -14,11 +14,18 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use core::mem::size_of;
+
 use fuse::kernel;
-use fuse::server::CreateRequest;
+use fuse::server::{CreateRequest, CreateResponse};
 
 use fuse_testutil as testutil;
-use fuse_testutil::{decode_request, MessageBuilder};
+use fuse_testutil::{
+	decode_request,
+	encode_request,
+	encode_response,
+	MessageBuilder,
+};
 
 #[test]
 fn request_v7p1() {
@@ -38,9 +45,15 @@ fn request_v7p1() {
 
 	assert_eq!(req.name(), "hello.world!");
 	assert_eq!(req.flags(), fuse::CreateRequestFlags::new());
-	assert_eq!(req.open_flags(), 0xFF);
+	assert_eq!(req.open_flags().get(), 0xFF);
 	assert_eq!(req.mode(), fuse::FileMode::new(0));
 	assert_eq!(req.umask(), 0);
+	assert_eq!(req.effective_mode(), fuse::FileMode::new(0));
+
+	let encoded = encode_request!(req, {
+		protocol_version: (7, 1),
+	});
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
 }
 
 #[test]
@@ -65,9 +78,59 @@ fn request_v7p12() {
 
 	assert_eq!(req.name(), "hello.world!");
 	assert_eq!(req.flags(), fuse::CreateRequestFlags::new());
-	assert_eq!(req.open_flags(), 0xFF);
+	assert_eq!(req.open_flags().get(), 0xFF);
 	assert_eq!(req.mode(), fuse::FileMode::new(0xEE));
 	assert_eq!(req.umask(), 0xDD);
+	assert_eq!(req.effective_mode(), fuse::FileMode::new(0x22));
+
+	let encoded = encode_request!(req, {
+		protocol_version: (7, 12),
+	});
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
+}
+
+#[test]
+fn request_security_contexts() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_CREATE;
+			h.nodeid = 123;
+			h.total_extlen = 5;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_create_in {
+			flags: 0xFF,
+			mode: 0xEE,
+			umask: 0xDD,
+			open_flags: 0,
+		}))
+		.push_bytes(b"hello.world!\x00")
+		.push_sized(&testutil::new!(kernel::fuse_secctx_header {
+			size: 40,
+			nr_secctx: 1,
+		}))
+		.push_sized(&testutil::new!(kernel::fuse_secctx {
+			size: 6,
+		}))
+		.push_bytes(b"security.selinux\x00")
+		.push_bytes(b"label\x00")
+		.push_bytes(&[0; 1])
+		.build_aligned();
+
+	let req = decode_request!(CreateRequest, buf, {
+		init_flags: kernel::FUSE_SECURITY_CTX,
+	});
+	assert_eq!(req.name(), "hello.world!");
+
+	let mut contexts = req.security_contexts();
+	let context = contexts.next().unwrap();
+	assert_eq!(context.name(), c"security.selinux");
+	assert_eq!(context.value(), b"label\x00");
+	assert!(contexts.next().is_none());
+
+	let encoded = encode_request!(req, {
+		init_flags: kernel::FUSE_SECURITY_CTX,
+	});
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
 }
 
 #[test]
@@ -101,3 +164,45 @@ fn request_impl_debug() {
 		),
 	);
 }
+
+#[test]
+fn response_flags_v7p9() {
+	let attr = fuse::NodeAttr::new(fuse::NodeId::new(11).unwrap());
+	let mut resp = CreateResponse::new(fuse::Entry::new(attr));
+	resp.set_handle(123);
+	resp.set_keep_cache(true);
+	resp.set_nonseekable(true);
+	resp.set_backing_id(456);
+	assert_eq!(
+		resp.flags(),
+		fuse::CreateResponseFlag::KEEP_CACHE
+			| fuse::CreateResponseFlag::NONSEEKABLE
+			| fuse::CreateResponseFlag::PASSTHROUGH,
+	);
+
+	let encoded = encode_response!(&resp, {
+		protocol_version: (7, 9),
+	});
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>()
+					+ size_of::<kernel::fuse_entry_out>()
+					+ size_of::<kernel::fuse_open_out>()) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_sized(&testutil::new!(kernel::fuse_entry_out {
+				nodeid: 11,
+				attr: testutil::new!(kernel::fuse_attr {
+					ino: 11,
+				}),
+			}))
+			.push_sized(&testutil::new!(kernel::fuse_open_out {
+				fh: 123,
+				open_flags: kernel::FOPEN_KEEP_CACHE,
+			}))
+			.build()
+	);
+}
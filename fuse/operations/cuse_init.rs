@@ -17,6 +17,7 @@
 use core::fmt;
 use core::marker::PhantomData;
 
+use crate::client;
 use crate::{
 	CuseDeviceName,
 	CuseDeviceNumber,
@@ -59,6 +60,24 @@ try_from_cuse_request!(CuseInitRequest<'a>, |request| {
 	})
 });
 
+impl client::CuseRequest for CuseInitRequest<'_> {
+	fn send_to<S: client::io::CuseSocket>(
+		&self,
+		request_sender: client::CuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let body = new!(kernel::cuse_init_in {
+			major: self.version.major(),
+			minor: self.version.minor(),
+			flags: self.flags.bits,
+		});
+		request_sender.inner.send_1(
+			kernel::fuse_opcode::CUSE_INIT,
+			0,
+			body.as_bytes(),
+		)
+	}
+}
+
 impl fmt::Debug for CuseInitRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("CuseInitRequest")
@@ -61,8 +61,8 @@ where
 		let request = server::OpenRequest::try_from(request).unwrap();
 		self.dev.requests.send(format!("{:#?}", request)).unwrap();
 
-		let mut reply = fuse::kernel::fuse_open_out::new();
-		reply.fh = 12345;
+		let mut reply = server::OpenResponse::new();
+		reply.set_handle(12345);
 		send_reply.ok(&reply).unwrap();
 	}
 
@@ -107,8 +107,7 @@ where
 
 		self.dev.requests.send(request_str).unwrap();
 
-		let mut reply = fuse::kernel::fuse_write_out::new();
-		reply.size = request.value().len() as u32;
+		let reply = server::WriteResponse::new(request.value().len() as u32);
 		send_reply.ok(&reply).unwrap();
 	}
 }
@@ -16,7 +16,7 @@
 
 use core::fmt;
 
-use crate::internal::debug;
+use crate::client;
 use crate::kernel;
 use crate::server::decode;
 
@@ -50,9 +50,15 @@ impl FallocateRequest<'_> {
 		self.body.length
 	}
 
+	/// Returns the allocation mode flags.
+	///
+	/// A mode without flags allocates the range, extending the file size if
+	/// the range ends past the end of the file.
 	#[must_use]
-	pub fn fallocate_flags(&self) -> crate::FallocateFlags {
-		self.body.mode
+	pub fn mode(&self) -> FallocateFlags {
+		FallocateFlags {
+			bits: self.body.mode,
+		}
 	}
 }
 
@@ -66,6 +72,19 @@ try_from_fuse_request!(FallocateRequest<'a>, |request| {
 	Ok(Self { header, body })
 });
 
+impl client::FuseRequest for FallocateRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.inner.send_1(
+			kernel::fuse_opcode::FUSE_FALLOCATE,
+			self.header.nodeid,
+			self.body.as_bytes(),
+		)
+	}
+}
+
 impl fmt::Debug for FallocateRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("FallocateRequest")
@@ -73,9 +92,63 @@ impl fmt::Debug for FallocateRequest<'_> {
 			.field("handle", &self.handle())
 			.field("offset", &self.offset())
 			.field("length", &self.length())
-			.field("fallocate_flags", &debug::hex_u32(self.fallocate_flags()))
+			.field("mode", &self.mode())
 			.finish()
 	}
 }
 
 // }}}
+
+// FallocateFlags {{{
+
+/// Mode flags set on [`FallocateRequest`].
+///
+/// These are the mode flags of the Linux `fallocate()` system call, which
+/// the FUSE protocol uses on all platforms.
+///
+/// | Platform | Sent for                                                 |
+/// |----------|----------------------------------------------------------|
+/// | FreeBSD  | `posix_fallocate()`, `fspacectl(SPACECTL_DEALLOC)`       |
+/// | Linux    | `fallocate()`, `posix_fallocate()`                       |
+///
+/// On FreeBSD, `fspacectl(SPACECTL_DEALLOC)` is sent as
+/// `PUNCH_HOLE | KEEP_SIZE` and `posix_fallocate()` is sent without flags.
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct FallocateFlags {
+	bits: u32,
+}
+
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct FallocateFlag {
+	mask: u32,
+}
+
+mod request_flags {
+	const FALLOC_FL_KEEP_SIZE: u32 = 0x01;
+	const FALLOC_FL_PUNCH_HOLE: u32 = 0x02;
+	const FALLOC_FL_COLLAPSE_RANGE: u32 = 0x08;
+	const FALLOC_FL_ZERO_RANGE: u32 = 0x10;
+	const FALLOC_FL_INSERT_RANGE: u32 = 0x20;
+
+	bitflags!(FallocateFlag, FallocateFlags, u32, {
+		/// Don't change the file size, even if the range extends past the
+		/// end of the file.
+		KEEP_SIZE = FALLOC_FL_KEEP_SIZE;
+
+		/// Deallocate the range. Always combined with `KEEP_SIZE`.
+		PUNCH_HOLE = FALLOC_FL_PUNCH_HOLE;
+
+		/// Remove the range, shifting the data after it towards the start
+		/// of the file.
+		COLLAPSE_RANGE = FALLOC_FL_COLLAPSE_RANGE;
+
+		/// Zero the range, allocating any holes within it.
+		ZERO_RANGE = FALLOC_FL_ZERO_RANGE;
+
+		/// Insert a hole at the start of the range, shifting the data after
+		/// it towards the end of the file.
+		INSERT_RANGE = FALLOC_FL_INSERT_RANGE;
+	});
+}
+
+// }}}
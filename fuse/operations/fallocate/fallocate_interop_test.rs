@@ -79,8 +79,8 @@ where
 
 	fn open(&self, request: FuseRequest<'_>) {
 		let send_reply = self.conn.reply(request.id());
-		let mut reply = fuse::kernel::fuse_open_out::new();
-		reply.fh = 12345;
+		let mut reply = server::OpenResponse::new();
+		reply.set_handle(12345);
 		send_reply.ok(&reply).unwrap();
 	}
 
@@ -124,7 +124,7 @@ fn fallocate() {
     handle: 12345,
     offset: 1024,
     length: 4096,
-    fallocate_flags: 0x00000000,
+    mode: FallocateFlags {},
 }"#;
 	if let Some(diff) = diff_str(expect, &requests[0]) {
 		println!("{}", diff);
@@ -152,7 +152,7 @@ fn posix_fallocate() {
     handle: 12345,
     offset: 1024,
     length: 4096,
-    fallocate_flags: 0x00000000,
+    mode: FallocateFlags {},
 }"#;
 	if let Some(diff) = diff_str(expect, &requests[0]) {
 		println!("{}", diff);
@@ -182,7 +182,9 @@ fn fallocate_keep_size() {
     handle: 12345,
     offset: 1024,
     length: 4096,
-    fallocate_flags: 0x00000001,
+    mode: FallocateFlags {
+        KEEP_SIZE,
+    },
 }"#;
 	if let Some(diff) = diff_str(expect, &requests[0]) {
 		println!("{}", diff);
@@ -217,7 +219,10 @@ fn fallocate_punch_hole() {
     handle: 12345,
     offset: 1024,
     length: 4096,
-    fallocate_flags: 0x00000003,
+    mode: FallocateFlags {
+        KEEP_SIZE,
+        PUNCH_HOLE,
+    },
 }"#;
 	if let Some(diff) = diff_str(expect, &requests[0]) {
 		println!("{}", diff);
@@ -40,7 +40,12 @@ fn request() {
 	assert_eq!(req.handle(), 12);
 	assert_eq!(req.offset(), 34);
 	assert_eq!(req.length(), 56);
-	assert_eq!(req.fallocate_flags(), 0b11);
+	assert_eq!(
+		req.mode(),
+		fuse::FallocateFlag::KEEP_SIZE | fuse::FallocateFlag::PUNCH_HOLE,
+	);
+	assert!(req.mode().get(fuse::FallocateFlag::PUNCH_HOLE));
+	assert!(!req.mode().get(fuse::FallocateFlag::ZERO_RANGE));
 }
 
 #[test]
@@ -67,7 +72,10 @@ fn request_impl_debug() {
 			"    handle: 123,\n",
 			"    offset: 1024,\n",
 			"    length: 4096,\n",
-			"    fallocate_flags: 0x00000003,\n",
+			"    mode: FallocateFlags {\n",
+			"        KEEP_SIZE,\n",
+			"        PUNCH_HOLE,\n",
+			"    },\n",
 			"}",
 		),
 	);
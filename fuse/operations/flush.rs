@@ -17,6 +17,7 @@
 use core::fmt;
 use core::marker::PhantomData;
 
+use crate::client;
 use crate::kernel;
 use crate::server;
 use crate::server::decode;
@@ -81,6 +82,43 @@ impl<'a> FlushRequest<'a> {
 	}
 }
 
+impl client::CuseRequest for FlushRequest<'_> {
+	fn send_to<S: client::io::CuseSocket>(
+		&self,
+		request_sender: client::CuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		self.send(request_sender.inner, true)
+	}
+}
+
+impl client::FuseRequest for FlushRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		self.send(request_sender.inner, false)
+	}
+}
+
+impl FlushRequest<'_> {
+	fn send<S: client::io::Socket>(
+		&self,
+		request_sender: client::RequestSender<'_, S>,
+		is_cuse: bool,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let node_id = if is_cuse { 0 } else { self.node_id.get() };
+		let body = new!(kernel::fuse_flush_in {
+			fh: self.handle,
+			lock_owner: self.lock_owner.0,
+		});
+		request_sender.send_1(
+			kernel::fuse_opcode::FUSE_FLUSH,
+			node_id,
+			body.as_bytes(),
+		)
+	}
+}
+
 impl fmt::Debug for FlushRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("FlushRequest")
@@ -87,11 +87,11 @@ where
 
 	fn open(&self, request: FuseRequest<'_>) {
 		let send_reply = self.conn.reply(request.id());
-		let mut reply = fuse::kernel::fuse_open_out::new();
+		let mut reply = server::OpenResponse::new();
 		if request.header().raw().nodeid == 2 {
-			reply.fh = 1002;
+			reply.set_handle(1002);
 		} else {
-			reply.fh = 1003;
+			reply.set_handle(1003);
 		}
 		send_reply.ok(&reply).unwrap();
 	}
@@ -14,10 +14,12 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use core::cmp;
 use core::fmt;
-use core::mem::size_of;
+use core::mem::{size_of, size_of_val};
 use core::slice;
 
+use crate::client;
 use crate::kernel;
 
 // ForgetRequest {{{
@@ -47,6 +49,7 @@ impl ForgetRequestItem {
 pub struct ForgetRequest<'a> {
 	forget: Option<kernel::fuse_forget_one>,
 	batch_forgets: &'a [kernel::fuse_forget_one],
+	dropped_items: u32,
 }
 
 impl<'a> ForgetRequest<'a> {
@@ -54,10 +57,58 @@ impl<'a> ForgetRequest<'a> {
 		self.items_impl()
 	}
 
+	/// Returns the number of items in the request.
+	///
+	/// Items with a node ID of zero are counted, but are skipped by
+	/// [`items`](Self::items).
+	#[must_use]
+	pub fn len(&self) -> usize {
+		match self.forget {
+			Some(_) => 1,
+			None => self.batch_forgets.len(),
+		}
+	}
+
+	/// Returns `true` if the request contains no items.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Returns the number of items that were missing from a
+	/// `FUSE_BATCH_FORGET` request.
+	///
+	/// The count in a batch forget's header may exceed the number of items
+	/// that fit in the request body. Items that are present are decoded
+	/// normally, and the remainder are reported here. The lookup counts of
+	/// the missing items are unknown, so a non-zero value means some node
+	/// references can no longer be released.
+	#[must_use]
+	pub fn dropped_items(&self) -> u32 {
+		self.dropped_items
+	}
+
+	/// Calls `f` with the node ID and lookup count of each item.
+	///
+	/// This is equivalent to iterating over [`items`](Self::items), but
+	/// compiles to a simple loop over the request body. Servers that receive
+	/// large `FUSE_BATCH_FORGET` requests may prefer it for that reason.
+	pub fn for_each_item(&self, mut f: impl FnMut(crate::NodeId, u64)) {
+		let items = match &self.forget {
+			Some(item) => slice::from_ref(item),
+			None => self.batch_forgets,
+		};
+		for item in items {
+			if let Some(node_id) = crate::NodeId::new(item.nodeid) {
+				f(node_id, item.nlookup);
+			}
+		}
+	}
+
 	fn items_impl(&self) -> ForgetRequestIter<'a> {
 		match self.forget {
 			Some(item) => ForgetRequestIter::One(Some(item)),
-			None => ForgetRequestIter::Batch(self.batch_forgets),
+			None => ForgetRequestIter::Batch(self.batch_forgets.iter()),
 		}
 	}
 }
@@ -67,18 +118,20 @@ try_from_fuse_request!(ForgetRequest<'a>, |request| {
 	let header = dec.header();
 	if header.opcode == kernel::fuse_opcode::FUSE_BATCH_FORGET {
 		let raw: &'a kernel::fuse_batch_forget_in = dec.next_sized()?;
-		let batch_size =
-			raw.count * size_of::<kernel::fuse_forget_one>() as u32;
-		let batch_bytes = dec.next_bytes(batch_size)?;
+		let batch_bytes = dec.next_remaining();
+		let available = batch_bytes.len()
+			/ size_of::<kernel::fuse_forget_one>();
+		let count = cmp::min(raw.count as usize, available);
 		let batch_forgets: &'a [kernel::fuse_forget_one] = unsafe {
 			slice::from_raw_parts(
 				batch_bytes.as_ptr().cast::<kernel::fuse_forget_one>(),
-				raw.count as usize,
+				count,
 			)
 		};
 		return Ok(Self {
 			forget: None,
 			batch_forgets,
+			dropped_items: raw.count - count as u32,
 		});
 	}
 
@@ -90,9 +143,44 @@ try_from_fuse_request!(ForgetRequest<'a>, |request| {
 			nlookup: raw.nlookup,
 		}),
 		batch_forgets: &[],
+		dropped_items: 0,
 	})
 });
 
+impl client::FuseRequest for ForgetRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		if let Some(forget) = self.forget {
+			let body = new!(kernel::fuse_forget_in {
+				nlookup: forget.nlookup,
+			});
+			return request_sender.inner.send_1(
+				kernel::fuse_opcode::FUSE_FORGET,
+				forget.nodeid,
+				body.as_bytes(),
+			);
+		}
+
+		let body = new!(kernel::fuse_batch_forget_in {
+			count: self.batch_forgets.len() as u32,
+		});
+		let batch_bytes = unsafe {
+			slice::from_raw_parts(
+				self.batch_forgets.as_ptr().cast::<u8>(),
+				size_of_val(self.batch_forgets),
+			)
+		};
+		request_sender.inner.send_2(
+			kernel::fuse_opcode::FUSE_BATCH_FORGET,
+			0,
+			body.as_bytes(),
+			batch_bytes,
+		)
+	}
+}
+
 impl fmt::Debug for ForgetRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("ForgetRequest")
@@ -107,14 +195,14 @@ impl fmt::Debug for ForgetRequest<'_> {
 
 enum ForgetRequestIter<'a> {
 	One(Option<kernel::fuse_forget_one>),
-	Batch(&'a [kernel::fuse_forget_one]),
+	Batch(slice::Iter<'a, kernel::fuse_forget_one>),
 }
 
 impl ForgetRequestIter<'_> {
 	fn clone(&self) -> Self {
 		match self {
 			Self::One(x) => Self::One(*x),
-			Self::Batch(x) => Self::Batch(x),
+			Self::Batch(x) => Self::Batch(x.clone()),
 		}
 	}
 }
@@ -134,33 +222,13 @@ impl Iterator for ForgetRequestIter<'_> {
 					lookup_count: item.nlookup,
 				})
 			},
-			Self::Batch(items) => {
-				let (head, tail) = next_batch_item(items);
-				*self = Self::Batch(tail);
-				head
-			},
-		}
-	}
-}
-
-fn next_batch_item(
-	mut items: &[kernel::fuse_forget_one],
-) -> (Option<ForgetRequestItem>, &[kernel::fuse_forget_one]) {
-	loop {
-		match items.split_first() {
-			None => return (None, &[]),
-			Some((head, tail)) => match crate::NodeId::new(head.nodeid) {
-				None => {
-					items = tail;
-				},
-				Some(node_id) => {
-					let next = Some(ForgetRequestItem {
-						node_id,
-						lookup_count: head.nlookup,
-					});
-					return (next, tail);
-				},
-			},
+			// Items with a node ID of zero are skipped.
+			Self::Batch(items) => items.find_map(|item| {
+				Some(ForgetRequestItem {
+					node_id: crate::NodeId::new(item.nodeid)?,
+					lookup_count: item.nlookup,
+				})
+			}),
 		}
 	}
 }
@@ -15,10 +15,14 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use fuse::kernel;
-use fuse::server::{ForgetRequest, ForgetRequestItem};
+use fuse::server::{
+	ForgetRequest,
+	ForgetRequestItem,
+	RequestClass,
+};
 
 use fuse_testutil as testutil;
-use fuse_testutil::{decode_request, MessageBuilder};
+use fuse_testutil::{decode_request, encode_request, MessageBuilder};
 
 #[test]
 fn request_single() {
@@ -38,6 +42,9 @@ fn request_single() {
 	assert_eq!(items.len(), 1);
 	assert_eq!(items[0].node_id(), fuse::NodeId::new(123).unwrap());
 	assert_eq!(items[0].lookup_count(), 456);
+
+	let encoded = encode_request!(req);
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
 }
 
 #[test]
@@ -58,6 +65,8 @@ fn request_batch() {
 		.build_aligned();
 
 	let req = decode_request!(ForgetRequest, buf);
+	assert_eq!(req.len(), 2);
+	assert_eq!(req.dropped_items(), 0);
 
 	let items: Vec<ForgetRequestItem> = req.items().collect();
 	assert_eq!(items.len(), 2);
@@ -65,6 +74,98 @@ fn request_batch() {
 	assert_eq!(items[0].lookup_count(), 34);
 	assert_eq!(items[1].node_id(), fuse::NodeId::new(56).unwrap());
 	assert_eq!(items[1].lookup_count(), 78);
+
+	let encoded = encode_request!(req);
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
+}
+
+#[test]
+fn request_batch_truncated() {
+	let buf = MessageBuilder::new()
+		.set_opcode(kernel::fuse_opcode::FUSE_BATCH_FORGET)
+		.push_sized(&testutil::new!(kernel::fuse_batch_forget_in {
+			count: 3,
+		}))
+		.push_sized(&testutil::new!(kernel::fuse_forget_one {
+			nodeid: 12,
+			nlookup: 34,
+		}))
+		.push_sized(&testutil::new!(kernel::fuse_forget_one {
+			nodeid: 56,
+			nlookup: 78,
+		}))
+		.build_aligned();
+
+	let req = decode_request!(ForgetRequest, buf);
+	assert_eq!(req.len(), 2);
+	assert_eq!(req.dropped_items(), 1);
+
+	let items: Vec<ForgetRequestItem> = req.items().collect();
+	assert_eq!(items.len(), 2);
+	assert_eq!(items[0].node_id(), fuse::NodeId::new(12).unwrap());
+	assert_eq!(items[1].node_id(), fuse::NodeId::new(56).unwrap());
+}
+
+#[test]
+fn request_batch_for_each_item() {
+	let buf = MessageBuilder::new()
+		.set_opcode(kernel::fuse_opcode::FUSE_BATCH_FORGET)
+		.push_sized(&testutil::new!(kernel::fuse_batch_forget_in {
+			count: 3,
+		}))
+		.push_sized(&testutil::new!(kernel::fuse_forget_one {
+			nodeid: 12,
+			nlookup: 34,
+		}))
+		.push_sized(&testutil::new!(kernel::fuse_forget_one {
+			nodeid: 0,
+			nlookup: 1,
+		}))
+		.push_sized(&testutil::new!(kernel::fuse_forget_one {
+			nodeid: 56,
+			nlookup: 78,
+		}))
+		.build_aligned();
+
+	let req = decode_request!(ForgetRequest, buf);
+	assert_eq!(req.len(), 3);
+
+	// Items with a node ID of zero are skipped.
+	let mut items = Vec::new();
+	req.for_each_item(|node_id, count| items.push((node_id.get(), count)));
+	assert_eq!(items, [(12, 34), (56, 78)]);
+
+	let iter_items: Vec<_> = req
+		.items()
+		.map(|item| (item.node_id().get(), item.lookup_count()))
+		.collect();
+	assert_eq!(iter_items, items);
+}
+
+#[test]
+fn request_class() {
+	let buf = MessageBuilder::new()
+		.set_opcode(kernel::fuse_opcode::FUSE_BATCH_FORGET)
+		.push_sized(&testutil::new!(kernel::fuse_batch_forget_in {
+			count: 0,
+		}))
+		.build_aligned();
+	let request = decode_request!(FuseRequest, buf);
+	assert_eq!(request.class(), RequestClass::Forget);
+
+	let class = RequestClass::from_opcode;
+	assert_eq!(class(kernel::fuse_opcode::FUSE_FORGET), RequestClass::Forget);
+	assert_eq!(
+		class(kernel::fuse_opcode::FUSE_INTERRUPT),
+		RequestClass::Interrupt,
+	);
+	assert_eq!(class(kernel::fuse_opcode::FUSE_READ), RequestClass::Io);
+	assert_eq!(class(kernel::fuse_opcode::FUSE_READDIR), RequestClass::Io);
+	assert_eq!(
+		class(kernel::fuse_opcode::FUSE_LOOKUP),
+		RequestClass::Metadata,
+	);
+	assert_eq!(class(kernel::fuse_opcode(0xFFFF)), RequestClass::Metadata);
 }
 
 #[test]
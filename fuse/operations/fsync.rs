@@ -16,6 +16,7 @@
 
 use core::fmt;
 
+use crate::client;
 use crate::kernel;
 use crate::server;
 use crate::server::decode;
@@ -73,6 +74,37 @@ impl<'a> FsyncRequest<'a> {
 	}
 }
 
+impl client::CuseRequest for FsyncRequest<'_> {
+	fn send_to<S: client::io::CuseSocket>(
+		&self,
+		request_sender: client::CuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		self.send(request_sender.inner)
+	}
+}
+
+impl client::FuseRequest for FsyncRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		self.send(request_sender.inner)
+	}
+}
+
+impl FsyncRequest<'_> {
+	fn send<S: client::io::Socket>(
+		&self,
+		request_sender: client::RequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.send_1(
+			kernel::fuse_opcode::FUSE_FSYNC,
+			self.header.nodeid,
+			self.body.as_bytes(),
+		)
+	}
+}
+
 impl fmt::Debug for FsyncRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("FsyncRequest")
@@ -79,8 +79,8 @@ where
 
 	fn open(&self, request: FuseRequest<'_>) {
 		let send_reply = self.conn.reply(request.id());
-		let mut reply = fuse::kernel::fuse_open_out::new();
-		reply.fh = 12345;
+		let mut reply = server::OpenResponse::new();
+		reply.set_handle(12345);
 		send_reply.ok(&reply).unwrap();
 	}
 
@@ -16,6 +16,7 @@
 
 use core::fmt;
 
+use crate::client;
 use crate::kernel;
 use crate::server::decode;
 
@@ -57,6 +58,19 @@ try_from_fuse_request!(FsyncdirRequest<'a>, |request| {
 	Ok(Self { header, body })
 });
 
+impl client::FuseRequest for FsyncdirRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.inner.send_1(
+			kernel::fuse_opcode::FUSE_FSYNCDIR,
+			self.header.nodeid,
+			self.body.as_bytes(),
+		)
+	}
+}
+
 impl fmt::Debug for FsyncdirRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("FsyncdirRequest")
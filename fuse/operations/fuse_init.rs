@@ -18,6 +18,8 @@ use core::fmt;
 use core::marker::PhantomData;
 
 use crate::Version;
+use crate::client;
+use crate::internal::compat;
 use crate::kernel;
 
 // FuseInitRequest {{{
@@ -31,21 +33,16 @@ pub struct FuseInitRequest<'a> {
 	flags: FuseInitFlags,
 }
 
-#[repr(C)]
-struct fuse_init_in_v7p1 {
-	major: u32,
-	minor: u32,
-}
-
-#[repr(C)]
-struct fuse_init_in_v7p6 {
-	pub major:         u32,
-	pub minor:         u32,
-	pub max_readahead: u32,
-	pub flags:         u32,
-}
-
 impl FuseInitRequest<'_> {
+	pub(crate) fn new(max_readahead: u32, flags: FuseInitFlags) -> Self {
+		Self {
+			phantom: PhantomData,
+			version: Version::LATEST,
+			max_readahead,
+			flags,
+		}
+	}
+
 	#[must_use]
 	pub fn version(&self) -> Version {
 		self.version
@@ -76,7 +73,7 @@ try_from_fuse_request!(FuseInitRequest<'a>, |request| {
 	//   parsing layer. Per the version negotiation docs, a newer major
 	//   version from the kernel should be rejected by sending a response
 	//   containing the library's major version.
-	let raw_v7p1: &'a fuse_init_in_v7p1 = dec.peek_sized()?;
+	let raw_v7p1: &'a compat::fuse_init_in_v7p1 = dec.peek_sized()?;
 	if raw_v7p1.minor < 6 || raw_v7p1.major != kernel::FUSE_KERNEL_VERSION {
 		return Ok(FuseInitRequest {
 			phantom: PhantomData,
@@ -87,7 +84,7 @@ try_from_fuse_request!(FuseInitRequest<'a>, |request| {
 	}
 
 	if raw_v7p1.minor < 36 {
-		let raw: &'a fuse_init_in_v7p6 = dec.next_sized()?;
+		let raw: &'a compat::fuse_init_in_v7p6 = dec.next_sized()?;
 		return Ok(FuseInitRequest {
 			phantom: PhantomData,
 			version: Version::new(raw.major, raw.minor),
@@ -109,6 +106,37 @@ try_from_fuse_request!(FuseInitRequest<'a>, |request| {
 	})
 });
 
+impl client::FuseRequest for FuseInitRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let flags2 = (self.flags.bits >> 32) as u32;
+		let mut flags = self.flags.bits as u32;
+		if flags2 != 0 {
+			flags |= kernel::FUSE_INIT_EXT;
+		}
+		let body = new!(kernel::fuse_init_in {
+			major: self.version.major(),
+			minor: self.version.minor(),
+			max_readahead: self.max_readahead,
+			flags: flags,
+			flags2: flags2,
+		});
+		let mut body_bytes = body.as_bytes();
+		if body.minor < 6 || body.major != kernel::FUSE_KERNEL_VERSION {
+			body_bytes = &body_bytes[..size_of::<compat::fuse_init_in_v7p1>()];
+		} else if body.minor < 36 {
+			body_bytes = &body_bytes[..size_of::<compat::fuse_init_in_v7p6>()];
+		}
+		request_sender.inner.send_1(
+			kernel::fuse_opcode::FUSE_INIT,
+			0,
+			body_bytes,
+		)
+	}
+}
+
 impl fmt::Debug for FuseInitRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("FuseInitRequest")
@@ -136,6 +164,27 @@ impl FuseInitResponse {
 		}
 	}
 
+	/// Creates a `FuseInitResponse` from a raw [`fuse_init_out`].
+	///
+	/// This can be used to restore a response that was sent by another
+	/// process, such as a mount helper that performed the connection
+	/// handshake before passing the `/dev/fuse` file descriptor on.
+	///
+	/// [`fuse_init_out`]: kernel::fuse_init_out
+	#[must_use]
+	pub fn from_raw(raw: kernel::fuse_init_out) -> FuseInitResponse {
+		Self { raw }
+	}
+
+	/// Returns the raw [`fuse_init_out`] for the response.
+	///
+	/// [`fuse_init_out`]: kernel::fuse_init_out
+	#[inline]
+	#[must_use]
+	pub fn raw(&self) -> &kernel::fuse_init_out {
+		&self.raw
+	}
+
 	#[must_use]
 	pub fn version(&self) -> Version {
 		Version::new(self.raw.major, self.raw.minor)
@@ -153,9 +202,18 @@ impl FuseInitResponse {
 		FuseInitFlags { bits }
 	}
 
+	/// Set the response's init flags.
+	///
+	/// Flags above the lower 32 bits are sent in the `flags2` field, which
+	/// the client only reads if [`FuseInitFlag::INIT_EXT`] is also set. This
+	/// method sets `INIT_EXT` if any such flags are present.
 	pub fn set_flags(&mut self, flags: FuseInitFlags) {
+		let flags2 = (flags.bits >> 32) as u32;
 		self.raw.flags = (flags.bits & u64::from(u32::MAX)) as u32;
-		self.raw.flags2 = (flags.bits >> 32) as u32;
+		self.raw.flags2 = flags2;
+		if flags2 != 0 {
+			self.raw.flags |= kernel::FUSE_INIT_EXT;
+		}
 	}
 
 	#[inline]
@@ -209,6 +267,39 @@ impl FuseInitResponse {
 	pub fn set_time_granularity(&mut self, granularity: u32) {
 		self.raw.time_gran = granularity;
 	}
+
+	/// The maximum number of pages in a single request.
+	///
+	/// This value is only used by the client if the [`MAX_PAGES`] init flag
+	/// is set. Otherwise, the client's default of 32 pages is used, which
+	/// may limit the effective [`max_write`].
+	///
+	/// [`MAX_PAGES`]: FuseInitFlag::MAX_PAGES
+	/// [`max_write`]: FuseInitResponse::max_write
+	#[must_use]
+	pub fn max_pages(&self) -> u16 {
+		self.raw.max_pages
+	}
+
+	pub fn set_max_pages(&mut self, max_pages: u16) {
+		self.raw.max_pages = max_pages;
+	}
+
+	/// The maximum stacking depth of filesystems backing passthrough files.
+	///
+	/// The client only enables passthrough if the [`PASSTHROUGH`] init flag
+	/// is set and the maximum stacking depth is non-zero. A depth of 1
+	/// permits backing files on non-stacked filesystems.
+	///
+	/// [`PASSTHROUGH`]: FuseInitFlag::PASSTHROUGH
+	#[must_use]
+	pub fn max_stack_depth(&self) -> u32 {
+		self.raw.max_stack_depth
+	}
+
+	pub fn set_max_stack_depth(&mut self, max_stack_depth: u32) {
+		self.raw.max_stack_depth = max_stack_depth;
+	}
 }
 
 impl fmt::Debug for FuseInitResponse {
@@ -276,6 +367,12 @@ mod flags {
 		INIT_RESERVED = kernel::FUSE_INIT_RESERVED;
 		SECURITY_CTX = kernel::FUSE_SECURITY_CTX;
 		HAS_INODE_DAX = kernel::FUSE_HAS_INODE_DAX;
+		CREATE_SUPP_GROUP = kernel::FUSE_CREATE_SUPP_GROUP;
+		HAS_EXPIRE_ONLY = kernel::FUSE_HAS_EXPIRE_ONLY;
+		DIRECT_IO_ALLOW_MMAP = kernel::FUSE_DIRECT_IO_ALLOW_MMAP;
+		PASSTHROUGH = kernel::FUSE_PASSTHROUGH;
+		NO_EXPORT_SUPPORT = kernel::FUSE_NO_EXPORT_SUPPORT;
+		HAS_RESEND = kernel::FUSE_HAS_RESEND;
 	});
 }
 
@@ -14,11 +14,13 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use core::mem::size_of;
+
 use fuse::kernel;
-use fuse::server::FuseInitRequest;
+use fuse::server::{ConnectionInfo, FuseInitRequest, FuseInitResponse};
 
 use fuse_testutil as testutil;
-use fuse_testutil::{decode_request, MessageBuilder};
+use fuse_testutil::{decode_request, encode_request, MessageBuilder};
 
 #[test]
 fn request_v7p1() {
@@ -33,6 +35,9 @@ fn request_v7p1() {
 	assert_eq!(req.version().minor(), 1);
 	assert_eq!(req.max_readahead(), 0);
 	assert_eq!(req.flags(), fuse::FuseInitFlags::new());
+
+	let encoded = encode_request!(req);
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
 }
 
 #[test]
@@ -50,6 +55,9 @@ fn request_v7p6() {
 	assert_eq!(req.version().minor(), 6);
 	assert_eq!(req.max_readahead(), 9);
 	assert_eq!(req.flags(), fuse::FuseInitFlag::ASYNC_READ);
+
+	let encoded = encode_request!(req);
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
 }
 
 #[test]
@@ -60,7 +68,7 @@ fn request_v7p36() {
 			major: 7,
 			minor: 36,
 			max_readahead: 9,
-			flags: kernel::FUSE_ASYNC_READ,
+			flags: kernel::FUSE_ASYNC_READ | kernel::FUSE_INIT_EXT,
 			flags2: (kernel::FUSE_HAS_INODE_DAX >> 32) as u32,
 		}))
 		.build_aligned();
@@ -71,8 +79,13 @@ fn request_v7p36() {
 	assert_eq!(req.max_readahead(), 9);
 	assert_eq!(
 		req.flags(),
-		fuse::FuseInitFlag::ASYNC_READ | fuse::FuseInitFlag::HAS_INODE_DAX,
+		fuse::FuseInitFlag::ASYNC_READ
+			| fuse::FuseInitFlag::INIT_EXT
+			| fuse::FuseInitFlag::HAS_INODE_DAX,
 	);
+
+	let encoded = encode_request!(req);
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
 }
 
 #[test]
@@ -124,6 +137,69 @@ fn init_flags() {
 	);
 }
 
+#[test]
+fn response_flags2() {
+	let mut resp = FuseInitResponse::new();
+	resp.update_flags(|flags| {
+		flags.set(fuse::FuseInitFlag::ASYNC_READ);
+		flags.set(fuse::FuseInitFlag::SECURITY_CTX);
+	});
+
+	// Setting a flag in the upper 32 bits also sets INIT_EXT, without which
+	// the client would ignore `flags2`.
+	assert_eq!(
+		resp.flags(),
+		fuse::FuseInitFlag::ASYNC_READ
+			| fuse::FuseInitFlag::INIT_EXT
+			| fuse::FuseInitFlag::SECURITY_CTX,
+	);
+
+	let mut flags = fuse::FuseInitFlags::new();
+	flags.set(fuse::FuseInitFlag::ASYNC_READ);
+	resp.set_flags(flags);
+	assert_eq!(resp.flags(), fuse::FuseInitFlag::ASYNC_READ);
+}
+
+#[test]
+fn response_encode_flags2() {
+	let mut init_out = testutil::new!(kernel::fuse_init_out {
+		major: 7,
+		minor: 36,
+		flags: kernel::FUSE_ASYNC_READ,
+		flags2: (kernel::FUSE_SECURITY_CTX >> 32) as u32,
+	});
+
+	// `flags2` is only sent if INIT_EXT is set.
+	let encoded = testutil::encode_response!(&init_out);
+	let mut expect = init_out;
+	expect.flags2 = 0;
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>()
+					+ size_of::<kernel::fuse_init_out>()) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_sized(&expect)
+			.build()
+	);
+
+	init_out.flags |= kernel::FUSE_INIT_EXT;
+	let encoded = testutil::encode_response!(&init_out);
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>()
+					+ size_of::<kernel::fuse_init_out>()) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_sized(&init_out)
+			.build()
+	);
+}
+
 #[test]
 fn request_impl_debug() {
 	let buf = MessageBuilder::new()
@@ -153,3 +229,96 @@ fn request_impl_debug() {
 		),
 	);
 }
+
+#[test]
+fn connection_info_diff_against_libfuse() {
+	let buf = MessageBuilder::new()
+		.set_opcode(kernel::fuse_opcode::FUSE_INIT)
+		.push_sized(&7u32) // fuse_init_in::major
+		.push_sized(&6u32) // fuse_init_in::minor
+		.push_sized(&4096u32) // fuse_init_in::max_readahead
+		.push_sized(&(kernel::FUSE_ASYNC_READ | kernel::FUSE_DONT_MASK))
+		.build_aligned();
+	let req = decode_request!(FuseInitRequest, buf);
+
+	let mut resp = FuseInitResponse::new();
+	resp.set_version(fuse::Version::new(7, 6));
+	resp.set_max_readahead(4096);
+	resp.set_max_write(256 * 4096);
+	resp.set_time_granularity(1);
+	resp.update_flags(|flags| flags.set(fuse::FuseInitFlag::ASYNC_READ));
+
+	let info = ConnectionInfo::new(&req, &resp);
+	assert!(info.diff_against_libfuse().is_empty());
+	assert_eq!(format!("{}", info.diff_against_libfuse()), "");
+
+	resp.set_flags(fuse::FuseInitFlags::new());
+	resp.update_flags(|flags| flags.set(fuse::FuseInitFlag::DONT_MASK));
+	resp.set_max_background(16);
+	resp.set_max_write(4096);
+
+	let info = ConnectionInfo::new(&req, &resp);
+	assert_eq!(info.flags(), fuse::FuseInitFlag::DONT_MASK);
+	assert!(!info.diff_against_libfuse().is_empty());
+	assert_eq!(
+		format!("{}", info.diff_against_libfuse()),
+		concat!(
+			"ASYNC_READ: libfuse enabled, negotiated disabled\n",
+			"max_background: libfuse 0, negotiated 16\n",
+			"max_write: libfuse 1048576, negotiated 4096\n",
+		),
+	);
+}
+
+#[test]
+fn connection_info_max_pages() {
+	let buf = MessageBuilder::new()
+		.set_opcode(kernel::fuse_opcode::FUSE_INIT)
+		.push_sized(&testutil::new!(kernel::fuse_init_in {
+			major: 7,
+			minor: 36,
+			flags: kernel::FUSE_MAX_PAGES,
+		}))
+		.build_aligned();
+	let req = decode_request!(FuseInitRequest, buf);
+
+	let mut resp = FuseInitResponse::new();
+	resp.set_version(fuse::Version::new(7, 36));
+	resp.set_max_write(1 << 20);
+	resp.set_max_pages(256);
+
+	// `max_pages` is ignored unless the MAX_PAGES flag is enabled.
+	let info = ConnectionInfo::new(&req, &resp);
+	assert_eq!(info.max_pages(), None);
+
+	resp.update_flags(|flags| flags.set(fuse::FuseInitFlag::MAX_PAGES));
+	let info = ConnectionInfo::new(&req, &resp);
+	assert_eq!(info.max_pages(), Some(256));
+}
+
+#[test]
+fn connection_info_max_stack_depth() {
+	let buf = MessageBuilder::new()
+		.set_opcode(kernel::fuse_opcode::FUSE_INIT)
+		.push_sized(&testutil::new!(kernel::fuse_init_in {
+			major: 7,
+			minor: 40,
+			flags: kernel::FUSE_INIT_EXT,
+			flags2: (kernel::FUSE_PASSTHROUGH >> 32) as u32,
+		}))
+		.build_aligned();
+	let req = decode_request!(FuseInitRequest, buf);
+
+	let mut resp = FuseInitResponse::new();
+	resp.set_version(fuse::Version::new(7, 40));
+	resp.set_max_stack_depth(1);
+	assert_eq!(resp.max_stack_depth(), 1);
+
+	// `max_stack_depth` is ignored unless the PASSTHROUGH flag is enabled.
+	let info = ConnectionInfo::new(&req, &resp);
+	assert_eq!(info.max_stack_depth(), None);
+
+	resp.update_flags(|flags| flags.set(fuse::FuseInitFlag::PASSTHROUGH));
+	let info = ConnectionInfo::new(&req, &resp);
+	assert_eq!(info.max_stack_depth(), Some(1));
+}
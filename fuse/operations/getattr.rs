@@ -15,9 +15,13 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use core::fmt;
+use core::time;
 
+use crate::client;
 use crate::internal::compat;
+use crate::internal::timestamp;
 use crate::kernel;
+use crate::server;
 use crate::server::decode;
 
 // GetattrRequest {{{
@@ -63,6 +67,26 @@ try_from_fuse_request!(GetattrRequest<'a>, |request| {
 	Ok(Self { header, body })
 });
 
+impl client::FuseRequest for GetattrRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		if request_sender.layout.version_minor() < 9 {
+			return request_sender.inner.send_0(
+				kernel::fuse_opcode::FUSE_GETATTR,
+				self.header.nodeid,
+			);
+		}
+		let body = self.body.to_v7p9();
+		request_sender.inner.send_1(
+			kernel::fuse_opcode::FUSE_GETATTR,
+			self.header.nodeid,
+			body.as_bytes(),
+		)
+	}
+}
+
 impl fmt::Debug for GetattrRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("GetattrRequest")
@@ -73,3 +97,74 @@ impl fmt::Debug for GetattrRequest<'_> {
 }
 
 // }}}
+
+// GetattrResponse {{{
+
+/// Response type for `FUSE_GETATTR`.
+pub struct GetattrResponse {
+	raw: kernel::fuse_attr_out,
+}
+
+impl GetattrResponse {
+	/// Creates a new `GetattrResponse` with the given node attributes.
+	#[inline]
+	#[must_use]
+	pub fn new(attributes: crate::NodeAttr) -> GetattrResponse {
+		Self {
+			raw: new!(kernel::fuse_attr_out {
+				attr: attributes.raw,
+			}),
+		}
+	}
+
+	/// Returns the node's attributes.
+	#[inline]
+	#[must_use]
+	pub fn attributes(&self) -> &crate::NodeAttr {
+		unsafe { crate::NodeAttr::from_ref(&self.raw.attr) }
+	}
+
+	/// Returns a mutable reference to the node's attributes.
+	#[inline]
+	#[must_use]
+	pub fn attributes_mut(&mut self) -> &mut crate::NodeAttr {
+		unsafe { crate::NodeAttr::from_ref_mut(&mut self.raw.attr) }
+	}
+
+	/// Returns the attribute cache timeout.
+	#[inline]
+	#[must_use]
+	pub fn cache_timeout(&self) -> time::Duration {
+		timestamp::new_duration(self.raw.attr_valid, self.raw.attr_valid_nsec)
+	}
+
+	/// Sets the attribute cache timeout.
+	#[inline]
+	pub fn set_cache_timeout(&mut self, timeout: time::Duration) {
+		let (seconds, nanos) = timestamp::split_duration(timeout);
+		self.raw.attr_valid = seconds;
+		self.raw.attr_valid_nsec = nanos;
+	}
+}
+
+impl fmt::Debug for GetattrResponse {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("GetattrResponse")
+			.field("attributes", self.attributes())
+			.field("cache_timeout", &self.cache_timeout())
+			.finish()
+	}
+}
+
+impl server::FuseReply for GetattrResponse {
+	fn send_to<S: server::FuseSocket>(
+		&self,
+		reply_sender: server::FuseReplySender<'_, S>,
+	) -> Result<(), server::SendError<S::Error>> {
+		let version_minor = reply_sender.layout.version_minor();
+		let buf = compat::fuse_attr_out_bytes(version_minor, &self.raw);
+		reply_sender.inner.send_1(buf)
+	}
+}
+
+// }}}
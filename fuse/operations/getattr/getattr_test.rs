@@ -14,11 +14,19 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use core::mem::size_of;
+use core::time::Duration;
+
 use fuse::kernel;
-use fuse::server::GetattrRequest;
+use fuse::server::{GetattrRequest, GetattrResponse};
 
 use fuse_testutil as testutil;
-use fuse_testutil::{decode_request, MessageBuilder};
+use fuse_testutil::{
+	decode_request,
+	encode_request,
+	encode_response,
+	MessageBuilder,
+};
 
 #[test]
 fn request_v7p1() {
@@ -34,6 +42,11 @@ fn request_v7p1() {
 	});
 
 	assert_eq!(req.handle(), None);
+
+	let encoded = encode_request!(req, {
+		protocol_version: (7, 1),
+	});
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
 }
 
 #[test]
@@ -71,6 +84,11 @@ fn request_v7p9_with_handle() {
 	});
 
 	assert_eq!(req.handle(), Some(123));
+
+	let encoded = encode_request!(req, {
+		protocol_version: (7, 9),
+	});
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
 }
 
 #[test]
@@ -97,3 +115,64 @@ fn request_impl_debug() {
 		),
 	);
 }
+
+#[test]
+fn response() {
+	let mut attr = fuse::NodeAttr::new(fuse::NodeId::new(11).unwrap());
+	attr.set_size(123);
+	let mut resp = GetattrResponse::new(attr);
+	resp.set_cache_timeout(Duration::new(12, 34));
+	assert_eq!(resp.attributes().size(), 123);
+	assert_eq!(resp.cache_timeout(), Duration::new(12, 34));
+
+	let encoded = encode_response!(&resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>()
+					+ size_of::<kernel::fuse_attr_out>()) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_sized(&testutil::new!(kernel::fuse_attr_out {
+				attr_valid: 12,
+				attr_valid_nsec: 34,
+				attr: testutil::new!(kernel::fuse_attr {
+					ino: 11,
+					size: 123,
+				}),
+			}))
+			.build()
+	);
+}
+
+#[test]
+fn response_v7p8() {
+	let attr = fuse::NodeAttr::new(fuse::NodeId::new(11).unwrap());
+	let resp = GetattrResponse::new(attr);
+
+	let encoded = encode_response!(&resp, {
+		protocol_version: (7, 8),
+	});
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>()
+					+ kernel::FUSE_COMPAT_ATTR_OUT_SIZE) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_sized(&testutil::new!(kernel::fuse_attr_out {
+				attr: testutil::new!(kernel::fuse_attr {
+					ino: 11,
+				}),
+			}))
+			.unpush(
+				size_of::<kernel::fuse_attr_out>()
+					- kernel::FUSE_COMPAT_ATTR_OUT_SIZE,
+			)
+			.build()
+	);
+}
@@ -16,7 +16,9 @@
 
 use core::fmt;
 
+use crate::client;
 use crate::kernel;
+use crate::server;
 use crate::server::decode;
 
 // GetlkRequest {{{
@@ -73,6 +75,19 @@ try_from_fuse_request!(GetlkRequest<'a>, |request| {
 	Ok(Self { header, body, lock_range })
 });
 
+impl client::FuseRequest for GetlkRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.inner.send_1(
+			kernel::fuse_opcode::FUSE_GETLK,
+			self.header.nodeid,
+			self.body.as_bytes(),
+		)
+	}
+}
+
 impl fmt::Debug for GetlkRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("GetlkRequest")
@@ -86,3 +101,51 @@ impl fmt::Debug for GetlkRequest<'_> {
 }
 
 // }}}
+
+// GetlkResponse {{{
+
+/// Response type for `FUSE_GETLK`.
+pub struct GetlkResponse {
+	lock: crate::Lock,
+}
+
+impl GetlkResponse {
+	/// Creates a new `GetlkResponse` with the given conflicting lock.
+	///
+	/// If no lock would conflict with the requested lock, the response
+	/// should contain a lock with mode `F_UNLCK`.
+	#[inline]
+	#[must_use]
+	pub fn new(lock: crate::Lock) -> GetlkResponse {
+		Self { lock }
+	}
+
+	/// Returns the conflicting lock.
+	#[inline]
+	#[must_use]
+	pub fn lock(&self) -> crate::Lock {
+		self.lock
+	}
+}
+
+impl fmt::Debug for GetlkResponse {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("GetlkResponse")
+			.field("lock", &self.lock)
+			.finish()
+	}
+}
+
+impl server::FuseReply for GetlkResponse {
+	fn send_to<S: server::FuseSocket>(
+		&self,
+		reply_sender: server::FuseReplySender<'_, S>,
+	) -> Result<(), server::SendError<S::Error>> {
+		let raw = new!(kernel::fuse_lk_out {
+			lk: self.lock.encode(),
+		});
+		reply_sender.inner.send_1(raw.as_bytes())
+	}
+}
+
+// }}}
@@ -102,8 +102,8 @@ where
 
 	fn open(&self, request: FuseRequest<'_>) {
 		let send_reply = self.conn.reply(request.id());
-		let mut reply = fuse::kernel::fuse_open_out::new();
-		reply.fh = 1000 + request.header().raw().nodeid;
+		let mut reply = server::OpenResponse::new();
+		reply.set_handle(1000 + request.header().raw().nodeid);
 		send_reply.ok(&reply).unwrap();
 	}
 
@@ -134,15 +134,7 @@ where
 			fuse::Lock::new(F_UNLCK, fuse::LockRange::new(0, None), None)
 		};
 
-		// FIXME
-		let mut reply = fuse::kernel::fuse_lk_out::new();
-		reply.lk.start = lock.range().start();
-		reply.lk.end = lock.range().end().unwrap_or(i64::MAX as u64);
-		reply.lk.r#type = lock.mode().0;
-		if lock.mode() != F_UNLCK {
-			reply.lk.pid = pid.unwrap().get();
-		}
-
+		let reply = server::GetlkResponse::new(lock);
 		send_reply.ok(&reply).unwrap();
 	}
 }
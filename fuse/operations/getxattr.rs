@@ -17,7 +17,10 @@
 use core::fmt;
 use core::num;
 
+use crate::client;
+use crate::internal::debug;
 use crate::kernel;
+use crate::server;
 use crate::server::decode;
 
 // GetxattrRequest {{{
@@ -63,6 +66,20 @@ try_from_fuse_request!(GetxattrRequest<'a>, |request| {
 	Ok(Self { header, body, name })
 });
 
+impl client::FuseRequest for GetxattrRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.inner.send_2(
+			kernel::fuse_opcode::FUSE_GETXATTR,
+			self.header.nodeid,
+			self.body.as_bytes(),
+			self.name.to_bytes_with_nul(),
+		)
+	}
+}
+
 impl fmt::Debug for GetxattrRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("GetxattrRequest")
@@ -74,3 +91,116 @@ impl fmt::Debug for GetxattrRequest<'_> {
 }
 
 // }}}
+
+// GetxattrResponse {{{
+
+/// Response type for `FUSE_GETXATTR`.
+///
+/// If the request's [`size`] is `None` then the client is querying the
+/// size of the attribute value, and the response should be created with
+/// [`GetxattrResponse::new_size`]. [`GetxattrResponse::from_value`] handles
+/// both cases.
+///
+/// [`size`]: GetxattrRequest::size
+pub struct GetxattrResponse<'a> {
+	value: Option<&'a [u8]>,
+	raw: kernel::fuse_getxattr_out,
+}
+
+impl<'a> GetxattrResponse<'a> {
+	/// Creates a new `GetxattrResponse` containing the attribute value.
+	#[inline]
+	#[must_use]
+	pub fn new(value: &'a [u8]) -> GetxattrResponse<'a> {
+		Self {
+			value: Some(value),
+			raw: kernel::fuse_getxattr_out::new(),
+		}
+	}
+
+	/// Creates a new `GetxattrResponse` containing the size of the
+	/// attribute value.
+	#[inline]
+	#[must_use]
+	pub fn new_size(size: u32) -> GetxattrResponse<'a> {
+		let mut raw = kernel::fuse_getxattr_out::new();
+		raw.size = size;
+		Self { value: None, raw }
+	}
+
+	/// Creates a new `GetxattrResponse` for a request with the given
+	/// [`size`](GetxattrRequest::size).
+	///
+	/// If `requested_size` is `None`, the response contains the size of
+	/// `value`. Otherwise it contains `value` itself.
+	///
+	/// # Errors
+	///
+	/// Returns [`Errno::ERANGE`] if `value` is larger than the requested
+	/// size, or [`Errno::E2BIG`] if it's larger than the platform's limit
+	/// on the size of an attribute value.
+	///
+	/// [`Errno::ERANGE`]: crate::Errno::ERANGE
+	/// [`Errno::E2BIG`]: crate::Errno::E2BIG
+	pub fn from_value(
+		value: &'a [u8],
+		requested_size: Option<num::NonZeroUsize>,
+	) -> Result<GetxattrResponse<'a>, crate::Errno> {
+		let max_size = match crate::os::XATTR_SIZE_MAX {
+			Some(size_max) => size_max.min(u32::MAX as usize),
+			None => u32::MAX as usize,
+		};
+		if value.len() > max_size {
+			return Err(crate::Errno::E2BIG);
+		}
+		match requested_size {
+			None => Ok(Self::new_size(value.len() as u32)),
+			Some(size) if value.len() > size.get() => {
+				Err(crate::Errno::ERANGE)
+			},
+			Some(_) => Ok(Self::new(value)),
+		}
+	}
+
+	/// Returns the attribute value, if present.
+	#[inline]
+	#[must_use]
+	pub fn value(&self) -> Option<&'a [u8]> {
+		self.value
+	}
+
+	/// Returns the size of the attribute value.
+	#[inline]
+	#[must_use]
+	pub fn size(&self) -> usize {
+		match self.value {
+			Some(value) => value.len(),
+			None => self.raw.size as usize,
+		}
+	}
+}
+
+impl fmt::Debug for GetxattrResponse<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		let mut out = fmt.debug_struct("GetxattrResponse");
+		match self.value {
+			Some(value) => out.field("value", &debug::bytes(value)),
+			None => out.field("size", &self.size()),
+		};
+		out.finish()
+	}
+}
+
+impl server::FuseReply for GetxattrResponse<'_> {
+	fn send_to<S: server::FuseSocket>(
+		&self,
+		reply_sender: server::FuseReplySender<'_, S>,
+	) -> Result<(), server::SendError<S::Error>> {
+		match self.value {
+			Some(value) => reply_sender.inner.send_1(value),
+			None => reply_sender.inner.send_1(self.raw.as_bytes()),
+		}
+	}
+}
+
+// }}}
@@ -89,8 +89,8 @@ where
 
 			match request.size() {
 				None => {
-					let mut reply = fuse::kernel::fuse_getxattr_out::new();
-					reply.size = value.len() as u32;
+					let size = value.len() as u32;
+					let reply = server::GetxattrResponse::new_size(size);
 					return send_reply.ok(&reply).unwrap();
 				},
 				Some(request_size) => {
@@ -100,7 +100,8 @@ where
 				},
 			};
 
-			return send_reply.ok_buf(value).unwrap();
+			let reply = server::GetxattrResponse::new(value);
+			return send_reply.ok(&reply).unwrap();
 		}
 
 		if request.name() == c"user.xattr_toobig" {
@@ -14,13 +14,14 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use core::mem::size_of;
 use core::num;
 
 use fuse::kernel;
-use fuse::server::GetxattrRequest;
+use fuse::server::{GetxattrRequest, GetxattrResponse};
 
 use fuse_testutil as testutil;
-use fuse_testutil::{decode_request, MessageBuilder};
+use fuse_testutil::{decode_request, encode_response, MessageBuilder};
 
 #[test]
 fn request_sized() {
@@ -83,3 +84,92 @@ fn request_impl_debug() {
 		),
 	);
 }
+
+#[test]
+fn response_value() {
+	let resp = GetxattrResponse::new(b"attr value");
+	assert_eq!(resp.value(), Some(&b"attr value"[..]));
+	assert_eq!(resp.size(), 10);
+
+	let encoded = encode_response!(&resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>() + 10) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_bytes(b"attr value")
+			.build()
+	);
+}
+
+#[test]
+fn response_size() {
+	let resp = GetxattrResponse::new_size(10);
+	assert_eq!(resp.value(), None);
+	assert_eq!(resp.size(), 10);
+
+	let encoded = encode_response!(&resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>()
+					+ size_of::<kernel::fuse_getxattr_out>()) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_sized(&testutil::new!(kernel::fuse_getxattr_out {
+				size: 10,
+			}))
+			.build()
+	);
+}
+
+#[test]
+fn response_from_value() {
+	let value = b"some\x00value";
+
+	let resp = GetxattrResponse::from_value(value, None).unwrap();
+	assert_eq!(resp.value(), None);
+	assert_eq!(resp.size(), 10);
+
+	let size = num::NonZeroUsize::new(10);
+	let resp = GetxattrResponse::from_value(value, size).unwrap();
+	assert_eq!(resp.value(), Some(&value[..]));
+
+	let size = num::NonZeroUsize::new(9);
+	let err = GetxattrResponse::from_value(value, size).unwrap_err();
+	assert_eq!(err, fuse::Errno::ERANGE);
+
+	if let Some(size_max) = fuse::os::XATTR_SIZE_MAX {
+		let value = vec![0u8; size_max + 1];
+		let err = GetxattrResponse::from_value(&value, None).unwrap_err();
+		assert_eq!(err, fuse::Errno::E2BIG);
+	}
+}
+
+#[test]
+fn response_impl_debug() {
+	let resp = GetxattrResponse::new(b"attr value");
+	assert_eq!(
+		format!("{:#?}", resp),
+		concat!(
+			"GetxattrResponse {\n",
+			"    value: \"attr value\",\n",
+			"}",
+		),
+	);
+
+	let resp = GetxattrResponse::new_size(10);
+	assert_eq!(
+		format!("{:#?}", resp),
+		concat!(
+			"GetxattrResponse {\n",
+			"    size: 10,\n",
+			"}",
+		),
+	);
+}
@@ -17,6 +17,7 @@
 use core::fmt;
 use core::num;
 
+use crate::client;
 use crate::kernel;
 use crate::server;
 
@@ -57,6 +58,32 @@ impl<'a> InterruptRequest<'a> {
 	}
 }
 
+impl client::CuseRequest for InterruptRequest<'_> {
+	fn send_to<S: client::io::CuseSocket>(
+		&self,
+		request_sender: client::CuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.inner.send_1(
+			kernel::fuse_opcode::FUSE_INTERRUPT,
+			0,
+			self.body.as_bytes(),
+		)
+	}
+}
+
+impl client::FuseRequest for InterruptRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.inner.send_1(
+			kernel::fuse_opcode::FUSE_INTERRUPT,
+			0,
+			self.body.as_bytes(),
+		)
+	}
+}
+
 impl fmt::Debug for InterruptRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("InterruptRequest")
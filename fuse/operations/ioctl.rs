@@ -18,6 +18,7 @@ use core::fmt;
 use core::marker::PhantomData;
 use core::mem::size_of;
 
+use crate::client;
 use crate::internal::debug;
 use crate::kernel;
 use crate::server;
@@ -26,6 +27,18 @@ use crate::server::decode;
 // IoctlRequest {{{
 
 /// Request type for `FUSE_IOCTL`.
+///
+/// The kernel sends `FUSE_IOCTL` for `ioctl()` calls on a FUSE file or CUSE
+/// device. For ordinary ("restricted") ioctls the kernel decodes the input
+/// and output sizes from the command number, and the request contains the
+/// full input data.
+///
+/// Unrestricted ioctls, which are only available to CUSE devices that have
+/// negotiated [`CuseInitFlag::UNRESTRICTED_IOCTL`], may instead be answered
+/// with a retry response listing the memory regions the server wishes to
+/// read or write. See [`IoctlResponse::new_retry`] for details.
+///
+/// [`CuseInitFlag::UNRESTRICTED_IOCTL`]: crate::CuseInitFlag::UNRESTRICTED_IOCTL
 #[derive(Clone, Copy)]
 pub struct IoctlRequest<'a> {
 	header: &'a kernel::fuse_in_header,
@@ -34,41 +47,59 @@ pub struct IoctlRequest<'a> {
 }
 
 impl<'a> IoctlRequest<'a> {
+	/// Returns the ID of the node the ioctl was performed on.
+	///
+	/// CUSE requests are not associated with a node, so this method returns
+	/// [`NodeId::ROOT`](crate::NodeId::ROOT) for them.
 	#[must_use]
 	pub fn node_id(&self) -> crate::NodeId {
 		crate::NodeId::new(self.header.nodeid).unwrap_or(crate::NodeId::ROOT)
 	}
 
+	/// Returns the handle of the open file.
 	#[must_use]
 	pub const fn handle(&self) -> u64 {
 		self.body.fh
 	}
 
+	/// Returns the ioctl command number.
 	#[must_use]
 	pub const fn command(&self) -> IoctlCmd {
 		IoctlCmd { cmd: self.body.cmd }
 	}
 
+	/// Returns the ioctl argument.
+	///
+	/// For ioctls that take a pointer, this is an address in the memory of
+	/// the calling process.
 	#[must_use]
 	pub const fn arg(&self) -> IoctlArg {
 		IoctlArg { arg: self.body.arg }
 	}
 
+	/// Returns the input data copied from the calling process.
 	#[must_use]
 	pub const fn input(&self) -> IoctlInput<'a> {
 		IoctlInput { bytes: self.input }
 	}
 
+	/// Returns the length of the input data, in bytes.
 	#[must_use]
 	pub const fn input_len(&self) -> u32 {
 		self.body.in_size
 	}
 
+	/// Returns the maximum length of output data the kernel will accept,
+	/// in bytes.
+	///
+	/// An [`IoctlResponse`] with more output than this is rejected by the
+	/// kernel.
 	#[must_use]
 	pub const fn output_len(&self) -> u32 {
 		self.body.out_size
 	}
 
+	/// Returns the flags set on the request.
 	#[must_use]
 	pub fn flags(&self) -> IoctlRequestFlags {
 		IoctlRequestFlags {
@@ -109,6 +140,38 @@ impl<'a> IoctlRequest<'a> {
 	}
 }
 
+impl client::CuseRequest for IoctlRequest<'_> {
+	fn send_to<S: client::io::CuseSocket>(
+		&self,
+		request_sender: client::CuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		self.send(request_sender.inner)
+	}
+}
+
+impl client::FuseRequest for IoctlRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		self.send(request_sender.inner)
+	}
+}
+
+impl IoctlRequest<'_> {
+	fn send<S: client::io::Socket>(
+		&self,
+		request_sender: client::RequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.send_2(
+			kernel::fuse_opcode::FUSE_IOCTL,
+			self.header.nodeid,
+			self.body.as_bytes(),
+			self.input,
+		)
+	}
+}
+
 impl fmt::Debug for IoctlRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("IoctlRequest")
@@ -123,17 +186,20 @@ impl fmt::Debug for IoctlRequest<'_> {
 	}
 }
 
+/// An ioctl command number.
 #[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct IoctlCmd {
 	cmd: u32,
 }
 
 impl IoctlCmd {
+	/// Creates a new `IoctlCmd` from a raw command number.
 	#[must_use]
 	pub const fn new(cmd: u32) -> Self {
 		IoctlCmd { cmd }
 	}
 
+	/// Returns the raw command number.
 	#[must_use]
 	pub const fn get(self) -> u32 {
 		self.cmd
@@ -148,22 +214,27 @@ impl fmt::Debug for IoctlCmd {
 	}
 }
 
+/// An ioctl argument.
 #[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct IoctlArg {
 	arg: u64,
 }
 
 impl IoctlArg {
+	/// Creates a new `IoctlArg` from a raw argument value.
 	#[must_use]
 	pub const fn new(arg: u64) -> Self {
 		Self { arg }
 	}
 
+	/// Returns the raw argument value.
 	#[must_use]
 	pub const fn get(self) -> u64 {
 		self.arg
 	}
 
+	/// Interprets the argument as a pointer to a `T` in the memory of the
+	/// calling process.
 	#[must_use]
 	pub const fn as_ptr<T>(self) -> IoctlPtr<T> {
 		IoctlPtr::new(self.arg)
@@ -178,6 +249,10 @@ impl fmt::Debug for IoctlArg {
 	}
 }
 
+/// A typed address in the memory of the process that performed an ioctl.
+///
+/// An `IoctlPtr` can't be dereferenced by the server. It is used to build
+/// [`IoctlSlice`] values for a retry response.
 #[repr(C)]
 pub struct IoctlPtr<T> {
 	addr: u64,
@@ -185,6 +260,7 @@ pub struct IoctlPtr<T> {
 }
 
 impl<T> IoctlPtr<T> {
+	/// Creates a new `IoctlPtr` from an address.
 	#[must_use]
 	pub const fn new(addr: u64) -> IoctlPtr<T> {
 		IoctlPtr {
@@ -193,12 +269,27 @@ impl<T> IoctlPtr<T> {
 		}
 	}
 
+	/// Returns the address.
 	#[must_use]
 	pub const fn addr(&self) -> u64 {
 		self.addr
 	}
+
+	/// Returns an [`IoctlSlice`] covering the `T` this pointer refers to.
+	#[must_use]
+	pub const fn as_slice(&self) -> IoctlSlice {
+		IoctlSlice::new(self.addr, size_of::<T>() as u64)
+	}
 }
 
+impl<T> Clone for IoctlPtr<T> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<T> Copy for IoctlPtr<T> {}
+
 impl<T> fmt::Debug for IoctlPtr<T> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("IoctlPtr")
@@ -208,6 +299,7 @@ impl<T> fmt::Debug for IoctlPtr<T> {
 	}
 }
 
+/// Input data for an ioctl.
 #[derive(Clone, Copy)]
 pub struct IoctlInput<'a> {
 	bytes: &'a [u8],
@@ -220,33 +312,50 @@ impl fmt::Debug for IoctlInput<'_> {
 }
 
 impl<'a> IoctlInput<'a> {
+	/// Creates a new `IoctlInput` from raw bytes.
 	#[must_use]
 	pub const fn new(bytes: &'a [u8]) -> Self {
 		Self { bytes }
 	}
 
+	/// Returns the input data as a byte slice.
 	#[must_use]
 	pub const fn as_bytes(&self) -> &'a [u8] {
 		self.bytes
 	}
 
+	/// Returns a reader over the input data.
+	///
+	/// If the request is a retry, the input data contains the contents of
+	/// each input slice in order. The reader can be used to split it back
+	/// into separate values.
 	#[must_use]
 	pub const fn reader(&self) -> IoctlInputReader<'a> {
 		IoctlInputReader { bytes: self.bytes }
 	}
 }
 
+/// Sequential reader for [`IoctlInput`].
 pub struct IoctlInputReader<'a> {
 	bytes: &'a [u8],
 }
 
+/// Errors that may occur when reading [`IoctlInput`].
 #[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
 #[non_exhaustive]
 pub enum IoctlInputError {
+	/// The input data is shorter than the requested length.
 	UnexpectedEof,
 }
 
 impl<'a> IoctlInputReader<'a> {
+	/// Returns the number of unread bytes.
+	#[must_use]
+	pub const fn remaining(&self) -> usize {
+		self.bytes.len()
+	}
+
+	/// Reads the next `len` bytes.
 	pub fn read(&mut self, len: usize) -> Result<&'a [u8], IoctlInputError> {
 		if len == self.bytes.len() {
 			let out = self.bytes;
@@ -261,6 +370,7 @@ impl<'a> IoctlInputReader<'a> {
 		Ok(out)
 	}
 
+	/// Reads the next `N` bytes as an array.
 	pub fn read_array<const N: usize>(
 		&mut self,
 	) -> Result<&'a [u8; N], IoctlInputError> {
@@ -268,7 +378,13 @@ impl<'a> IoctlInputReader<'a> {
 		Ok(unsafe { &*(bytes.as_ptr().cast::<[u8; N]>()) })
 	}
 
-	#[allow(clippy::missing_safety_doc)] // TODO
+	/// Reads the next `size_of::<T>()` bytes as a `T`.
+	///
+	/// # Safety
+	///
+	/// The caller must ensure that any sequence of `size_of::<T>()` bytes is
+	/// a valid `T`, and that the input data is aligned for `T`. The input
+	/// data of a request is aligned to 8 bytes.
 	pub unsafe fn read_transmute<T>(
 		&mut self,
 	) -> Result<&'a T, IoctlInputError> {
@@ -294,6 +410,10 @@ enum IoctlResponseOutput<'a> {
 }
 
 impl<'a> IoctlResponse<'a> {
+	/// Creates a new `IoctlResponse` with the given output data.
+	///
+	/// The output must not be longer than the request's
+	/// [`output_len`](IoctlRequest::output_len).
 	#[must_use]
 	pub fn new(output: &'a [u8]) -> IoctlResponse<'a> {
 		Self {
@@ -303,6 +423,18 @@ impl<'a> IoctlResponse<'a> {
 		}
 	}
 
+	/// Creates a new `IoctlResponse` asking the kernel to retry the ioctl.
+	///
+	/// The kernel will copy the input slices from the calling process and
+	/// send a new `FUSE_IOCTL` request with their contents as input data.
+	/// The output slices determine the [`output_len`] of the new request,
+	/// and the output data of its response is copied into them.
+	///
+	/// Retry responses are only accepted for requests with the
+	/// [`IOCTL_UNRESTRICTED`] flag set.
+	///
+	/// [`output_len`]: IoctlRequest::output_len
+	/// [`IOCTL_UNRESTRICTED`]: IoctlRequestFlag::IOCTL_UNRESTRICTED
 	#[must_use]
 	pub fn new_retry(retry: IoctlRetry<'a>) -> IoctlResponse<'a> {
 		Self {
@@ -320,11 +452,13 @@ impl<'a> IoctlResponse<'a> {
 		}
 	}
 
+	/// Returns the value to be returned from `ioctl()`.
 	#[must_use]
 	pub const fn result(&self) -> i32 {
 		self.raw.result
 	}
 
+	/// Sets the value to be returned from `ioctl()`.
 	pub fn set_result(&mut self, result: i32) {
 		self.raw.result = result;
 		self.set_result = true;
@@ -402,6 +536,7 @@ impl server::CuseReply for IoctlResponse<'_> {
 	}
 }
 
+/// A region of memory in the process that performed an ioctl.
 #[derive(Clone, Copy, Eq, Ord, PartialEq, PartialOrd)]
 #[repr(C)]
 pub struct IoctlSlice {
@@ -410,10 +545,23 @@ pub struct IoctlSlice {
 }
 
 impl IoctlSlice {
+	/// Creates a new `IoctlSlice` with the given base address and length.
 	#[must_use]
 	pub const fn new(base: u64, len: u64) -> IoctlSlice {
 		Self { base, len }
 	}
+
+	/// Returns the base address.
+	#[must_use]
+	pub const fn base(&self) -> u64 {
+		self.base
+	}
+
+	/// Returns the length, in bytes.
+	#[must_use]
+	pub const fn len(&self) -> u64 {
+		self.len
+	}
 }
 
 impl fmt::Debug for IoctlSlice {
@@ -425,19 +573,29 @@ impl fmt::Debug for IoctlSlice {
 	}
 }
 
+/// Errors that may occur when building an [`IoctlRetry`].
 #[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
 #[non_exhaustive]
 pub enum IoctlRetryError {
+	/// The total number of input and output slices exceeds
+	/// [`FUSE_IOCTL_MAX_IOV`](kernel::FUSE_IOCTL_MAX_IOV).
 	TooManySlices,
 }
 
-#[derive(Debug)]
+/// Input and output slices for a retry response.
+#[derive(Clone, Copy, Debug)]
 pub struct IoctlRetry<'a> {
 	input_slices: &'a [IoctlSlice],
 	output_slices: &'a [IoctlSlice],
 }
 
 impl<'a> IoctlRetry<'a> {
+	/// Creates a new `IoctlRetry` from the given input and output slices.
+	///
+	/// # Errors
+	///
+	/// Returns [`IoctlRetryError::TooManySlices`] if the total number of
+	/// slices exceeds [`FUSE_IOCTL_MAX_IOV`](kernel::FUSE_IOCTL_MAX_IOV).
 	pub const fn new(
 		input_slices: &'a [IoctlSlice],
 		output_slices: &'a [IoctlSlice],
@@ -460,17 +618,23 @@ impl<'a> IoctlRetry<'a> {
 		})
 	}
 
+	/// Returns the input slices.
 	#[must_use]
 	pub const fn input_slices(&self) -> &'a [IoctlSlice] {
 		self.input_slices
 	}
 
+	/// Returns the output slices.
 	#[must_use]
 	pub const fn output_slices(&self) -> &'a [IoctlSlice] {
 		self.output_slices
 	}
 }
 
+/// A fixed-capacity buffer for building an [`IoctlRetry`].
+///
+/// The buffer has space for [`FUSE_IOCTL_MAX_IOV`](kernel::FUSE_IOCTL_MAX_IOV)
+/// slices, shared between input and output.
 pub struct IoctlRetryBuf {
 	input_slices: [IoctlSlice; kernel::FUSE_IOCTL_MAX_IOV],
 	output_slices: [IoctlSlice; kernel::FUSE_IOCTL_MAX_IOV],
@@ -479,6 +643,7 @@ pub struct IoctlRetryBuf {
 }
 
 impl IoctlRetryBuf {
+	/// Creates a new, empty `IoctlRetryBuf`.
 	#[must_use]
 	pub const fn new() -> IoctlRetryBuf {
 		let zero = IoctlSlice::new(0, 0);
@@ -490,16 +655,19 @@ impl IoctlRetryBuf {
 		}
 	}
 
+	/// Returns the input slices added so far.
 	#[must_use]
 	pub fn input_slices(&self) -> &[IoctlSlice] {
 		&self.input_slices[..self.input_slices_len]
 	}
 
+	/// Returns the output slices added so far.
 	#[must_use]
 	pub fn output_slices(&self) -> &[IoctlSlice] {
 		&self.output_slices[..self.output_slices_len]
 	}
 
+	/// Returns an [`IoctlRetry`] borrowing the slices in this buffer.
 	#[must_use]
 	pub fn borrow(&self) -> IoctlRetry {
 		IoctlRetry {
@@ -517,17 +685,29 @@ impl IoctlRetryBuf {
 		Ok(())
 	}
 
+	/// Removes all slices from the buffer.
+	pub fn clear(&mut self) {
+		self.input_slices_len = 0;
+		self.output_slices_len = 0;
+	}
+
+	/// Adds an input slice covering the `T` that `ptr` refers to.
+	///
+	/// # Errors
+	///
+	/// Returns [`IoctlRetryError::TooManySlices`] if the buffer is full.
 	pub fn add_input_ptr<T>(
 		&mut self,
 		ptr: IoctlPtr<T>,
 	) -> Result<(), IoctlRetryError> {
-		self.check_add()?;
-		let slice = IoctlSlice::new(ptr.addr(), size_of::<T>() as u64);
-		self.input_slices[self.input_slices_len] = slice;
-		self.input_slices_len += 1;
-		Ok(())
+		self.add_input_slice(ptr.as_slice())
 	}
 
+	/// Adds an input slice.
+	///
+	/// # Errors
+	///
+	/// Returns [`IoctlRetryError::TooManySlices`] if the buffer is full.
 	pub fn add_input_slice(
 		&mut self,
 		slice: IoctlSlice,
@@ -538,17 +718,23 @@ impl IoctlRetryBuf {
 		Ok(())
 	}
 
+	/// Adds an output slice covering the `T` that `ptr` refers to.
+	///
+	/// # Errors
+	///
+	/// Returns [`IoctlRetryError::TooManySlices`] if the buffer is full.
 	pub fn add_output_ptr<T>(
 		&mut self,
 		ptr: IoctlPtr<T>,
 	) -> Result<(), IoctlRetryError> {
-		self.check_add()?;
-		let slice = IoctlSlice::new(ptr.addr(), size_of::<T>() as u64);
-		self.output_slices[self.output_slices_len] = slice;
-		self.output_slices_len += 1;
-		Ok(())
+		self.add_output_slice(ptr.as_slice())
 	}
 
+	/// Adds an output slice.
+	///
+	/// # Errors
+	///
+	/// Returns [`IoctlRetryError::TooManySlices`] if the buffer is full.
 	pub fn add_output_slice(
 		&mut self,
 		slice: IoctlSlice,
@@ -127,8 +127,8 @@ where
 
 	fn open(&self, request: CuseRequest<'_>) {
 		let send_reply = self.conn.reply(request.id());
-		let mut reply = fuse::kernel::fuse_open_out::new();
-		reply.fh = 1002;
+		let mut reply = server::OpenResponse::new();
+		reply.set_handle(1002);
 		send_reply.ok(&reply).unwrap();
 	}
 }
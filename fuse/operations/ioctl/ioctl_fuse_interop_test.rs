@@ -87,16 +87,14 @@ where
 		if request.node_id().is_root() {
 			attr.set_mode(fuse::FileMode::S_IFDIR | 0o755);
 			attr.set_link_count(2);
-			let mut reply = fuse::kernel::fuse_attr_out::new();
-			reply.attr = *attr.raw();
+			let reply = server::GetattrResponse::new(attr);
 			return send_reply.ok(&reply).unwrap();
 		}
 
 		if request.node_id() == fuse::NodeId::new(2).unwrap() {
 			attr.set_mode(fuse::FileMode::S_IFREG | 0o644);
 			attr.set_link_count(1);
-			let mut reply = fuse::kernel::fuse_attr_out::new();
-			reply.attr = *attr.raw();
+			let reply = server::GetattrResponse::new(attr);
 			return send_reply.ok(&reply).unwrap();
 		}
 
@@ -154,9 +152,9 @@ where
 		let send_reply = self.conn.reply(request.id());
 		let request = server::OpenRequest::try_from(request).unwrap();
 		println!("{:#?}", request);
-		let mut reply = fuse::kernel::fuse_open_out::new();
+		let mut reply = server::OpenResponse::new();
 		if request.node_id() == fuse::NodeId::new(2).unwrap() {
-			reply.fh = 1002;
+			reply.set_handle(1002);
 			return send_reply.ok(&reply).unwrap();
 		}
 		send_reply.err(OsError::NOT_FOUND).unwrap();
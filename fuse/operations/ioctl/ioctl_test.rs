@@ -0,0 +1,286 @@
+// Copyright 2023 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::mem::size_of;
+
+use fuse::kernel;
+use fuse::server::{
+	IoctlInputError,
+	IoctlPtr,
+	IoctlRequest,
+	IoctlResponse,
+	IoctlRetry,
+	IoctlRetryBuf,
+	IoctlRetryError,
+	IoctlSlice,
+};
+
+use fuse_testutil as testutil;
+use fuse_testutil::{
+	decode_request,
+	encode_request,
+	encode_response,
+	MessageBuilder,
+};
+
+#[test]
+fn request() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_IOCTL;
+			h.nodeid = 123;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_ioctl_in {
+			fh: 12,
+			flags: kernel::FUSE_IOCTL_UNRESTRICTED,
+			cmd: 0x5413,
+			arg: 0x7FFF_0000_1000,
+			in_size: 8,
+			out_size: 16,
+		}))
+		.push_bytes(b"\x01\x00\x00\x00\x02\x00\x03\x00")
+		.build_aligned();
+
+	let req = decode_request!(IoctlRequest, buf);
+
+	assert_eq!(req.node_id(), fuse::NodeId::new(123).unwrap());
+	assert_eq!(req.handle(), 12);
+	assert_eq!(req.command().get(), 0x5413);
+	assert_eq!(req.arg().get(), 0x7FFF_0000_1000);
+	assert_eq!(
+		req.flags(),
+		fuse::IoctlRequestFlag::IOCTL_UNRESTRICTED,
+	);
+	assert_eq!(req.input_len(), 8);
+	assert_eq!(req.output_len(), 16);
+	assert_eq!(req.input().as_bytes(), b"\x01\x00\x00\x00\x02\x00\x03\x00");
+
+	let encoded = encode_request!(req);
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
+}
+
+#[test]
+fn request_input_reader() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_IOCTL;
+			h.nodeid = 123;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_ioctl_in {
+			in_size: 8,
+		}))
+		.push_bytes(b"\x01\x00\x00\x00\x02\x00\x03\x00")
+		.build_aligned();
+
+	let req = decode_request!(IoctlRequest, buf);
+	let mut reader = req.input().reader();
+	assert_eq!(reader.remaining(), 8);
+
+	let value: &u32 = unsafe { reader.read_transmute() }.unwrap();
+	assert_eq!(u32::from_le(*value), 1);
+	assert_eq!(reader.read_array::<2>(), Ok(&[2, 0]));
+	assert_eq!(reader.remaining(), 2);
+	assert_eq!(reader.read(4), Err(IoctlInputError::UnexpectedEof));
+	assert_eq!(reader.read(2), Ok(&b"\x03\x00"[..]));
+	assert_eq!(reader.remaining(), 0);
+}
+
+#[test]
+fn request_impl_debug() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_IOCTL;
+			h.nodeid = 123;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_ioctl_in {
+			fh: 12,
+			flags: kernel::FUSE_IOCTL_UNRESTRICTED,
+			cmd: 0x5413,
+			arg: 0x1000,
+			in_size: 2,
+			out_size: 16,
+		}))
+		.push_bytes(b"\x01\x02")
+		.build_aligned();
+	let request = decode_request!(IoctlRequest, buf);
+
+	assert_eq!(
+		format!("{:#?}", request),
+		concat!(
+			"IoctlRequest {\n",
+			"    node_id: 123,\n",
+			"    handle: 12,\n",
+			"    command: 0x00005413,\n",
+			"    arg: 0x0000000000001000,\n",
+			"    output_len: 16,\n",
+			"    flags: IoctlRequestFlags {\n",
+			"        IOCTL_UNRESTRICTED,\n",
+			"    },\n",
+			"    input: [\n",
+			"        1,\n",
+			"        2,\n",
+			"    ],\n",
+			"}",
+		),
+	);
+}
+
+#[test]
+fn response() {
+	let mut resp = IoctlResponse::new(b"output");
+	resp.set_result(123);
+	assert_eq!(resp.result(), 123);
+
+	let encoded = encode_response!(&resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>()
+					+ size_of::<kernel::fuse_ioctl_out>()
+					+ 6) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_sized(&testutil::new!(kernel::fuse_ioctl_out {
+				result: 123,
+			}))
+			.push_bytes(b"output")
+			.build()
+	);
+}
+
+#[test]
+fn response_retry() {
+	let mut retry = IoctlRetryBuf::new();
+	retry.add_input_slice(IoctlSlice::new(0x1000, 4)).unwrap();
+	retry.add_output_ptr(IoctlPtr::<u64>::new(0x2000)).unwrap();
+	retry.add_output_slice(IoctlSlice::new(0x3000, 12)).unwrap();
+
+	let resp = IoctlResponse::new_retry(retry.borrow());
+	let encoded = encode_response!(&resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>()
+					+ size_of::<kernel::fuse_ioctl_out>()
+					+ size_of::<kernel::fuse_ioctl_iovec>() * 3) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_sized(&testutil::new!(kernel::fuse_ioctl_out {
+				flags: kernel::FUSE_IOCTL_RETRY,
+				in_iovs: 1,
+				out_iovs: 2,
+			}))
+			.push_sized(&testutil::new!(kernel::fuse_ioctl_iovec {
+				base: 0x1000,
+				len: 4,
+			}))
+			.push_sized(&testutil::new!(kernel::fuse_ioctl_iovec {
+				base: 0x2000,
+				len: 8,
+			}))
+			.push_sized(&testutil::new!(kernel::fuse_ioctl_iovec {
+				base: 0x3000,
+				len: 12,
+			}))
+			.build()
+	);
+}
+
+#[test]
+fn response_impl_debug() {
+	let resp = IoctlResponse::new(b"output");
+	assert_eq!(
+		format!("{:#?}", resp),
+		concat!(
+			"IoctlResponse {\n",
+			"    result: 0,\n",
+			"    output: [\n",
+			"        111,\n",
+			"        117,\n",
+			"        116,\n",
+			"        112,\n",
+			"        117,\n",
+			"        116,\n",
+			"    ],\n",
+			"}",
+		),
+	);
+
+	let input = [IoctlSlice::new(0x1000, 4)];
+	let retry = IoctlRetry::new(&input, &[]).unwrap();
+	let resp = IoctlResponse::new_retry(retry);
+	assert_eq!(
+		format!("{:#?}", resp),
+		concat!(
+			"IoctlResponse {\n",
+			"    retry: IoctlRetry {\n",
+			"        input_slices: [\n",
+			"            IoctlSlice {\n",
+			"                base: 0x0000000000001000,\n",
+			"                len: 4,\n",
+			"            },\n",
+			"        ],\n",
+			"        output_slices: [],\n",
+			"    },\n",
+			"}",
+		),
+	);
+}
+
+#[test]
+fn retry_max_iov() {
+	let slices = [IoctlSlice::new(0, 0); kernel::FUSE_IOCTL_MAX_IOV + 1];
+	let max = kernel::FUSE_IOCTL_MAX_IOV;
+	assert!(IoctlRetry::new(&slices[..max], &[]).is_ok());
+	assert!(IoctlRetry::new(&[], &slices[..max]).is_ok());
+	assert_eq!(
+		IoctlRetry::new(&slices, &[]).unwrap_err(),
+		IoctlRetryError::TooManySlices,
+	);
+	assert_eq!(
+		IoctlRetry::new(&slices[..max / 2], &slices[..max / 2 + 1])
+			.unwrap_err(),
+		IoctlRetryError::TooManySlices,
+	);
+
+	let mut retry = IoctlRetryBuf::new();
+	for ii in 0..max {
+		let slice = IoctlSlice::new(ii as u64, 1);
+		if ii % 2 == 0 {
+			retry.add_input_slice(slice).unwrap();
+		} else {
+			retry.add_output_slice(slice).unwrap();
+		}
+	}
+	assert_eq!(retry.input_slices().len(), max / 2);
+	assert_eq!(retry.output_slices().len(), max / 2);
+	assert_eq!(
+		retry.add_input_slice(IoctlSlice::new(0, 1)),
+		Err(IoctlRetryError::TooManySlices),
+	);
+	assert_eq!(
+		retry.add_output_ptr(IoctlPtr::<u32>::new(0)),
+		Err(IoctlRetryError::TooManySlices),
+	);
+
+	retry.clear();
+	assert!(retry.input_slices().is_empty());
+	assert!(retry.output_slices().is_empty());
+}
@@ -14,6 +14,7 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::client;
 use crate::kernel;
 use crate::server::decode;
 
@@ -57,4 +58,22 @@ try_from_fuse_request!(LinkRequest<'a>, |request| {
 	})
 });
 
+impl client::FuseRequest for LinkRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let body = new!(kernel::fuse_link_in {
+			oldnodeid: self.node_id.get(),
+		});
+		request_sender.inner.send_3(
+			kernel::fuse_opcode::FUSE_LINK,
+			self.new_parent_id.get(),
+			body.as_bytes(),
+			self.new_name.as_bytes(),
+			b"\0",
+		)
+	}
+}
+
 // }}}
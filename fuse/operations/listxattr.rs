@@ -19,6 +19,7 @@ use core::fmt;
 use core::num;
 use core::ptr;
 
+use crate::client;
 use crate::kernel;
 use crate::server;
 use crate::server::decode;
@@ -58,6 +59,19 @@ try_from_fuse_request!(ListxattrRequest<'a>, |request| {
 	Ok(Self { header, body })
 });
 
+impl client::FuseRequest for ListxattrRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.inner.send_1(
+			kernel::fuse_opcode::FUSE_LISTXATTR,
+			self.header.nodeid,
+			self.body.as_bytes(),
+		)
+	}
+}
+
 impl fmt::Debug for ListxattrRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("ListxattrRequest")
@@ -69,6 +83,84 @@ impl fmt::Debug for ListxattrRequest<'_> {
 
 // }}}
 
+// ListxattrResponse {{{
+
+/// Response type for `FUSE_LISTXATTR`.
+///
+/// If the request's [`size`] is `None` then the client is querying the
+/// total size of the attribute names, and the response should be created
+/// with [`ListxattrResponse::new_size`].
+///
+/// [`size`]: ListxattrRequest::size
+pub struct ListxattrResponse<'a> {
+	names: Option<ListxattrNames<'a>>,
+	raw: kernel::fuse_getxattr_out,
+}
+
+impl<'a> ListxattrResponse<'a> {
+	/// Creates a new `ListxattrResponse` containing attribute names.
+	#[inline]
+	#[must_use]
+	pub fn new(names: ListxattrNames<'a>) -> ListxattrResponse<'a> {
+		Self {
+			names: Some(names),
+			raw: kernel::fuse_getxattr_out::new(),
+		}
+	}
+
+	/// Creates a new `ListxattrResponse` containing the total size of
+	/// the attribute names.
+	#[inline]
+	#[must_use]
+	pub fn new_size(size: u32) -> ListxattrResponse<'a> {
+		let mut raw = kernel::fuse_getxattr_out::new();
+		raw.size = size;
+		Self { names: None, raw }
+	}
+
+	/// Returns the attribute names, if present.
+	#[inline]
+	#[must_use]
+	pub fn names(&self) -> Option<ListxattrNames<'a>> {
+		self.names
+	}
+
+	/// Returns the total size of the attribute names.
+	#[inline]
+	#[must_use]
+	pub fn size(&self) -> usize {
+		match self.names {
+			Some(names) => names.buf.len(),
+			None => self.raw.size as usize,
+		}
+	}
+}
+
+impl fmt::Debug for ListxattrResponse<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		let mut out = fmt.debug_struct("ListxattrResponse");
+		match self.names {
+			Some(ref names) => out.field("names", names),
+			None => out.field("size", &self.size()),
+		};
+		out.finish()
+	}
+}
+
+impl server::FuseReply for ListxattrResponse<'_> {
+	fn send_to<S: server::FuseSocket>(
+		&self,
+		reply_sender: server::FuseReplySender<'_, S>,
+	) -> Result<(), server::SendError<S::Error>> {
+		match self.names {
+			Some(names) => reply_sender.inner.send_1(names.buf),
+			None => reply_sender.inner.send_1(self.raw.as_bytes()),
+		}
+	}
+}
+
+// }}}
+
 // ListxattrNames {{{
 
 #[derive(Copy, Clone)]
@@ -137,9 +229,21 @@ impl<'a> core::iter::Iterator for XattrNamesIter<'a> {
 #[non_exhaustive]
 pub struct ListxattrCapacityError {}
 
+/// Writes extended attribute names for a [`ListxattrResponse`].
+///
+/// A writer created with [`ListxattrNamesWriter::for_request`] handles both
+/// phases of the `listxattr()` protocol. If the client is querying the total
+/// size of the names, the names are only measured, and [`into_response`]
+/// returns a size response. Otherwise the names are written to the buffer,
+/// and [`into_response`] returns `ERANGE` if they didn't fit within the
+/// requested size.
+///
+/// [`into_response`]: ListxattrNamesWriter::into_response
 pub struct ListxattrNamesWriter<'a> {
 	buf: &'a mut [u8],
 	position: usize,
+	size_probe: bool,
+	error: Option<crate::Errno>,
 }
 
 impl<'a> ListxattrNamesWriter<'a> {
@@ -151,12 +255,53 @@ impl<'a> ListxattrNamesWriter<'a> {
 				buf = &mut buf[..max_size];
 			}
 		}
-		Self { buf, position: 0 }
+		Self {
+			buf,
+			position: 0,
+			size_probe: false,
+			error: None,
+		}
+	}
+
+	/// Creates a writer for the response to `request`.
+	///
+	/// If the request has a [`size`], then names are written to `buf`, which
+	/// is truncated to the requested size. The buffer should be at least
+	/// that large, or [`XATTR_LIST_MAX`] bytes if the platform has a limit.
+	///
+	/// If the request's size is `None`, then `buf` is unused and may be
+	/// empty.
+	///
+	/// [`size`]: ListxattrRequest::size
+	/// [`XATTR_LIST_MAX`]: crate::os::XATTR_LIST_MAX
+	#[must_use]
+	pub fn for_request(
+		request: &ListxattrRequest,
+		buf: &'a mut [u8],
+	) -> ListxattrNamesWriter<'a> {
+		let Some(size) = request.size() else {
+			let mut writer = Self::new(&mut []);
+			writer.size_probe = true;
+			return writer;
+		};
+		let buf_len = buf.len().min(size.get());
+		Self::new(&mut buf[..buf_len])
+	}
+
+	/// Returns `true` if the writer is only measuring the total size of
+	/// the names.
+	#[inline]
+	#[must_use]
+	pub fn is_size_probe(&self) -> bool {
+		self.size_probe
 	}
 
 	#[inline]
 	#[must_use]
 	pub fn capacity(&self) -> usize {
+		if self.size_probe {
+			return max_list_size();
+		}
 		self.buf.len()
 	}
 
@@ -174,18 +319,48 @@ impl<'a> ListxattrNamesWriter<'a> {
 		}
 	}
 
+	/// Returns the response containing the written names, or their total
+	/// size if the writer is a size probe.
+	///
+	/// # Errors
+	///
+	/// Returns [`Errno::ERANGE`] if a name didn't fit within the requested
+	/// size, or [`Errno::E2BIG`] if the names exceed the platform's limit
+	/// on the size of an attribute list.
+	///
+	/// [`Errno::ERANGE`]: crate::Errno::ERANGE
+	/// [`Errno::E2BIG`]: crate::Errno::E2BIG
+	pub fn into_response(self) -> Result<ListxattrResponse<'a>, crate::Errno> {
+		if let Some(err) = self.error {
+			return Err(err);
+		}
+		if self.size_probe {
+			return Ok(ListxattrResponse::new_size(self.position as u32));
+		}
+		Ok(ListxattrResponse::new(self.into_names()))
+	}
+
 	pub fn try_push(
 		&mut self,
 		name: &CStr,
 	) -> Result<(), ListxattrCapacityError> {
 		let name = name.to_bytes_with_nul();
-		let remaining_capacity = self.buf.len() - self.position;
+		let remaining_capacity = self.capacity() - self.position;
 		if name.len() > remaining_capacity {
+			let total_len = self.position.saturating_add(name.len());
+			self.error = Some(if total_len > max_list_size() {
+				crate::Errno::E2BIG
+			} else {
+				crate::Errno::ERANGE
+			});
 			return Err(ListxattrCapacityError {});
 		}
 
 		let name_start = self.position;
 		self.position += name.len();
+		if self.size_probe {
+			return Ok(());
+		}
 
 		unsafe {
 			let dst = self.buf.get_unchecked_mut(name_start..self.position);
@@ -199,6 +374,15 @@ impl<'a> ListxattrNamesWriter<'a> {
 	}
 }
 
+// The maximum total size of the names in a `FUSE_LISTXATTR` response.
+fn max_list_size() -> usize {
+	let max_size = u32::MAX as usize;
+	match crate::os::XATTR_LIST_MAX {
+		Some(list_max) => list_max.min(max_size),
+		None => max_size,
+	}
+}
+
 impl<'a> From<ListxattrNamesWriter<'a>> for ListxattrNames<'a> {
 	#[inline]
 	fn from(w: ListxattrNamesWriter<'a>) -> ListxattrNames<'a> {
@@ -101,8 +101,8 @@ where
 			None => {
 				let mut need_size = xattr_small.to_bytes_with_nul().len();
 				need_size += xattr_toobig.to_bytes_with_nul().len();
-				let mut reply = fuse::kernel::fuse_getxattr_out::new();
-				reply.size = need_size as u32;
+				let size = need_size as u32;
+				let reply = server::ListxattrResponse::new_size(size);
 				return send_reply.ok(&reply).unwrap();
 			},
 			Some(request_size) => request_size,
@@ -118,7 +118,8 @@ where
 			return send_reply.err(OsError(errno::ERANGE)).unwrap();
 		}
 
-		send_reply.ok(&names.into_names()).unwrap();
+		let reply = server::ListxattrResponse::new(names.into_names());
+		send_reply.ok(&reply).unwrap();
 	}
 }
 
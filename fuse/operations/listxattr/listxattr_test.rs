@@ -14,16 +14,18 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use core::mem::size_of;
 use core::num;
 
 use fuse::kernel;
 use fuse::server::{
 	ListxattrNamesWriter,
 	ListxattrRequest,
+	ListxattrResponse,
 };
 
 use fuse_testutil as testutil;
-use fuse_testutil::{decode_request, MessageBuilder};
+use fuse_testutil::{decode_request, encode_response, MessageBuilder};
 
 #[test]
 fn request_sized() {
@@ -101,3 +103,102 @@ fn listxattr_names() {
 	let names = names.into_names();
 	assert_eq!(names.as_bytes(), b"123\x00456\x00")
 }
+
+#[test]
+fn response_names() {
+	let mut buf = [0u8; 10];
+	let mut names = ListxattrNamesWriter::new(&mut buf);
+	names.try_push(c"123").unwrap();
+	names.try_push(c"456").unwrap();
+	let resp = ListxattrResponse::new(names.into_names());
+	assert_eq!(resp.size(), 8);
+
+	let encoded = encode_response!(&resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>() + 8) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_bytes(b"123\x00456\x00")
+			.build()
+	);
+}
+
+#[test]
+fn response_size() {
+	let resp = ListxattrResponse::new_size(8);
+	assert!(resp.names().is_none());
+	assert_eq!(resp.size(), 8);
+
+	let encoded = encode_response!(&resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>()
+					+ size_of::<kernel::fuse_getxattr_out>()) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_sized(&testutil::new!(kernel::fuse_getxattr_out {
+				size: 8,
+			}))
+			.build()
+	);
+}
+
+fn listxattr_request(size: u32) -> fuse::io::MinReadBuffer {
+	MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_LISTXATTR;
+			h.nodeid = 123;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_getxattr_in {
+			size: size,
+		}))
+		.build_aligned()
+}
+
+#[test]
+fn names_writer_size_probe() {
+	let buf = listxattr_request(0);
+	let req = decode_request!(ListxattrRequest, buf);
+
+	let mut names = ListxattrNamesWriter::for_request(&req, &mut []);
+	assert!(names.is_size_probe());
+	names.try_push(c"user.a").unwrap();
+	names.try_push(c"user.bcd").unwrap();
+	assert_eq!(names.position(), 16);
+
+	let resp = names.into_response().unwrap();
+	assert!(resp.names().is_none());
+	assert_eq!(resp.size(), 16);
+}
+
+#[test]
+fn names_writer_sized() {
+	let buf = listxattr_request(16);
+	let req = decode_request!(ListxattrRequest, buf);
+
+	// The buffer is truncated to the requested size.
+	let mut buf = [0u8; 64];
+	let mut names = ListxattrNamesWriter::for_request(&req, &mut buf);
+	assert!(!names.is_size_probe());
+	assert_eq!(names.capacity(), 16);
+	names.try_push(c"user.a").unwrap();
+	names.try_push(c"user.bcd").unwrap();
+
+	let resp = names.into_response().unwrap();
+	let names = resp.names().unwrap();
+	assert_eq!(names.as_bytes(), b"user.a\x00user.bcd\x00");
+
+	// Names that don't fit result in `ERANGE`, even if later names fit.
+	let mut buf = [0u8; 64];
+	let mut names = ListxattrNamesWriter::for_request(&req, &mut buf);
+	names.try_push(c"user.abcdefghijklmnop").unwrap_err();
+	names.try_push(c"user.a").unwrap();
+	assert_eq!(names.into_response().unwrap_err(), fuse::Errno::ERANGE);
+}
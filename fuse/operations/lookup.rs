@@ -14,7 +14,13 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use core::fmt;
+use core::time;
+
+use crate::client;
+use crate::internal::timestamp;
 use crate::kernel;
+use crate::server;
 use crate::server::decode;
 
 // LookupRequest {{{
@@ -36,6 +42,34 @@ impl LookupRequest<'_> {
 	pub fn name(&self) -> &crate::NodeName {
 		self.name
 	}
+
+	/// Returns the name being looked up, distinguishing `"."` and `".."`.
+	///
+	/// The client only sends lookups of `"."` and `".."` if the
+	/// [`EXPORT_SUPPORT`] init flag was negotiated, when resolving NFS file
+	/// handles.
+	///
+	/// [`EXPORT_SUPPORT`]: crate::FuseInitFlag::EXPORT_SUPPORT
+	#[must_use]
+	pub fn name_or_dots(&self) -> LookupName<'_> {
+		match self.name.as_bytes() {
+			b"." => LookupName::Dot,
+			b".." => LookupName::DotDot,
+			_ => LookupName::Name(self.name),
+		}
+	}
+}
+
+/// The name in a [`LookupRequest`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum LookupName<'a> {
+	/// A directory entry name.
+	Name(&'a crate::NodeName),
+	/// The name `"."`, referring to the directory itself.
+	Dot,
+	/// The name `".."`, referring to the directory's parent.
+	DotDot,
 }
 
 try_from_fuse_request!(LookupRequest<'a>, |request| {
@@ -47,4 +81,94 @@ try_from_fuse_request!(LookupRequest<'a>, |request| {
 	})
 });
 
+impl client::FuseRequest for LookupRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.inner.send_2(
+			kernel::fuse_opcode::FUSE_LOOKUP,
+			self.parent_id.get(),
+			self.name.as_bytes(),
+			b"\0",
+		)
+	}
+}
+
+// }}}
+
+// LookupResponse {{{
+
+/// Response type for `FUSE_LOOKUP`.
+#[derive(Clone, Copy)]
+pub struct LookupResponse {
+	raw: kernel::fuse_entry_out,
+}
+
+impl LookupResponse {
+	/// Creates a new `LookupResponse` for a node that was found.
+	#[inline]
+	#[must_use]
+	pub fn new(entry: crate::Entry) -> LookupResponse {
+		Self { raw: *entry.raw() }
+	}
+
+	/// Creates a new `LookupResponse` for a name that doesn't exist.
+	///
+	/// The client may cache the absence of the name for `cache_timeout`,
+	/// during which lookups of the same name fail with `ENOENT` without
+	/// being sent to the server. A zero timeout is equivalent to replying
+	/// with [`OsError::NOT_FOUND`].
+	///
+	/// [`OsError::NOT_FOUND`]: crate::os::OsError::NOT_FOUND
+	#[must_use]
+	pub fn new_negative(cache_timeout: time::Duration) -> LookupResponse {
+		let (seconds, nanos) = timestamp::split_duration(cache_timeout);
+		Self {
+			raw: kernel::fuse_entry_out {
+				entry_valid: seconds,
+				entry_valid_nsec: nanos,
+				..kernel::fuse_entry_out::new()
+			},
+		}
+	}
+
+	/// Returns the entry for the node that was found, or `None` if this is a
+	/// negative response.
+	#[inline]
+	#[must_use]
+	pub fn entry(&self) -> Option<&crate::Entry> {
+		if self.raw.nodeid == 0 {
+			return None;
+		}
+		Some(unsafe { crate::Entry::from_ref(&self.raw) })
+	}
+
+	/// Returns the lookup cache timeout for this response.
+	#[inline]
+	#[must_use]
+	pub fn cache_timeout(&self) -> time::Duration {
+		timestamp::new_duration(self.raw.entry_valid, self.raw.entry_valid_nsec)
+	}
+}
+
+impl fmt::Debug for LookupResponse {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("LookupResponse")
+			.field("entry", &self.entry())
+			.field("cache_timeout", &self.cache_timeout())
+			.finish()
+	}
+}
+
+impl server::FuseReply for LookupResponse {
+	#[inline]
+	fn send_to<S: server::FuseSocket>(
+		&self,
+		reply_sender: server::FuseReplySender<'_, S>,
+	) -> Result<(), server::SendError<S::Error>> {
+		self.raw.send_to(reply_sender)
+	}
+}
+
 // }}}
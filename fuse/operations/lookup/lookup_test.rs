@@ -14,10 +14,14 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use core::mem::size_of;
+use core::time::Duration;
+
 use fuse::kernel;
-use fuse::server::LookupRequest;
+use fuse::server::{LookupName, LookupRequest, LookupResponse};
 
-use fuse_testutil::{decode_request, MessageBuilder};
+use fuse_testutil as testutil;
+use fuse_testutil::{decode_request, encode_response, MessageBuilder};
 
 #[test]
 fn request() {
@@ -33,6 +37,27 @@ fn request() {
 	assert_eq!(req.name(), "hello.world!");
 }
 
+#[test]
+fn request_name_or_dots() {
+	fn lookup_name(name: &[u8], f: impl FnOnce(LookupName)) {
+		let buf = MessageBuilder::new()
+			.set_header(|h| {
+				h.opcode = kernel::fuse_opcode::FUSE_LOOKUP;
+				h.nodeid = 123;
+			})
+			.push_bytes(name)
+			.build_aligned();
+		let req = decode_request!(LookupRequest, buf);
+		f(req.name_or_dots());
+	}
+
+	lookup_name(b".\x00", |name| assert_eq!(name, LookupName::Dot));
+	lookup_name(b"..\x00", |name| assert_eq!(name, LookupName::DotDot));
+	lookup_name(b"...\x00", |name| {
+		assert_eq!(name, LookupName::Name(fuse::NodeName::new("...").unwrap()));
+	});
+}
+
 #[test]
 fn request_impl_debug() {
 	let buf = MessageBuilder::new()
@@ -54,3 +79,75 @@ fn request_impl_debug() {
 		),
 	);
 }
+
+#[test]
+fn response() {
+	let mut attr = fuse::NodeAttr::new(fuse::NodeId::new(11).unwrap());
+	attr.set_size(123);
+	let mut entry = fuse::Entry::new(attr);
+	entry.set_generation(22);
+	entry.set_cache_timeout(Duration::from_secs(33));
+	let resp = LookupResponse::new(entry);
+	assert_eq!(resp.entry().unwrap().generation(), 22);
+	assert_eq!(resp.cache_timeout(), Duration::from_secs(33));
+
+	let encoded = encode_response!(&resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>()
+					+ size_of::<kernel::fuse_entry_out>()) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_sized(&testutil::new!(kernel::fuse_entry_out {
+				nodeid: 11,
+				generation: 22,
+				entry_valid: 33,
+				attr: testutil::new!(kernel::fuse_attr {
+					ino: 11,
+					size: 123,
+				}),
+			}))
+			.build()
+	);
+}
+
+#[test]
+fn response_negative() {
+	let resp = LookupResponse::new_negative(Duration::new(12, 34));
+	assert!(resp.entry().is_none());
+	assert_eq!(resp.cache_timeout(), Duration::new(12, 34));
+
+	let encoded = encode_response!(&resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>()
+					+ size_of::<kernel::fuse_entry_out>()) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_sized(&testutil::new!(kernel::fuse_entry_out {
+				entry_valid: 12,
+				entry_valid_nsec: 34,
+			}))
+			.build()
+	);
+}
+
+#[test]
+fn response_impl_debug() {
+	let resp = LookupResponse::new_negative(Duration::from_secs(5));
+	assert_eq!(
+		format!("{:#?}", resp),
+		concat!(
+			"LookupResponse {\n",
+			"    entry: None,\n",
+			"    cache_timeout: 5s,\n",
+			"}",
+		),
+	);
+}
@@ -16,7 +16,9 @@
 
 use core::fmt;
 
+use crate::client;
 use crate::kernel;
+use crate::server;
 use crate::server::decode;
 
 // LseekRequest {{{
@@ -44,9 +46,10 @@ impl LseekRequest<'_> {
 		self.raw.offset
 	}
 
+	/// Returns how [`offset`](Self::offset) should be interpreted.
 	#[must_use]
 	pub fn whence(&self) -> LseekWhence {
-		LseekWhence(self.raw.whence)
+		LseekWhence::from_raw(self.raw.whence)
 	}
 }
 
@@ -60,31 +63,141 @@ try_from_fuse_request!(LseekRequest<'a>, |request| {
 	})
 });
 
+impl client::FuseRequest for LseekRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.inner.send_1(
+			kernel::fuse_opcode::FUSE_LSEEK,
+			self.node_id.get(),
+			self.raw.as_bytes(),
+		)
+	}
+}
+
 impl fmt::Debug for LseekRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("LseekRequest")
 			.field("node_id", &self.node_id)
 			.field("handle", &self.raw.fh)
 			.field("offset", &self.raw.offset)
-			.field("whence", &LseekWhence(self.raw.whence))
+			.field("whence", &self.whence())
 			.finish()
 	}
 }
 
-#[derive(Eq, PartialEq)]
-pub struct LseekWhence(u32);
+// }}}
+
+// LseekResponse {{{
+
+/// Response type for `FUSE_LSEEK`.
+pub struct LseekResponse {
+	raw: kernel::fuse_lseek_out,
+}
+
+impl LseekResponse {
+	/// Creates a new `LseekResponse` with the resulting file offset.
+	#[inline]
+	#[must_use]
+	pub fn new(offset: u64) -> LseekResponse {
+		Self {
+			raw: kernel::fuse_lseek_out { offset },
+		}
+	}
+
+	/// Returns the resulting file offset.
+	#[inline]
+	#[must_use]
+	pub fn offset(&self) -> u64 {
+		self.raw.offset
+	}
+}
+
+impl fmt::Debug for LseekResponse {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("LseekResponse")
+			.field("offset", &self.offset())
+			.finish()
+	}
+}
+
+impl server::FuseReply for LseekResponse {
+	fn send_to<S: server::FuseSocket>(
+		&self,
+		reply_sender: server::FuseReplySender<'_, S>,
+	) -> Result<(), server::SendError<S::Error>> {
+		reply_sender.inner.send_1(self.raw.as_bytes())
+	}
+}
+
+// }}}
+
+// LseekWhence {{{
+
+// These values are the same on FreeBSD and Linux.
+const SEEK_SET: u32 = 0;
+const SEEK_CUR: u32 = 1;
+const SEEK_END: u32 = 2;
+const SEEK_DATA: u32 = 3;
+const SEEK_HOLE: u32 = 4;
+
+/// The `whence` argument of `lseek()`.
+///
+/// The client handles `SEEK_SET`, `SEEK_CUR`, and `SEEK_END` itself, so
+/// `FUSE_LSEEK` requests sent by the kernel contain only `SEEK_DATA` or
+/// `SEEK_HOLE`.
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum LseekWhence {
+	/// `SEEK_SET`: the offset is relative to the start of the file.
+	Set,
+
+	/// `SEEK_CUR`: the offset is relative to the current file offset.
+	Cur,
+
+	/// `SEEK_END`: the offset is relative to the end of the file.
+	End,
+
+	/// `SEEK_DATA`: seek to the first byte of data at or after the offset.
+	Data,
+
+	/// `SEEK_HOLE`: seek to the first hole at or after the offset.
+	Hole,
+
+	/// A value without a symbolic name on this platform.
+	Other(u32),
+}
 
 impl LseekWhence {
-	pub const SEEK_DATA: LseekWhence = LseekWhence(3);
-	pub const SEEK_HOLE: LseekWhence = LseekWhence(4);
+	/// Alias for [`LseekWhence::Data`].
+	pub const SEEK_DATA: LseekWhence = LseekWhence::Data;
+
+	/// Alias for [`LseekWhence::Hole`].
+	pub const SEEK_HOLE: LseekWhence = LseekWhence::Hole;
+
+	#[must_use]
+	fn from_raw(raw: u32) -> LseekWhence {
+		match raw {
+			SEEK_SET => Self::Set,
+			SEEK_CUR => Self::Cur,
+			SEEK_END => Self::End,
+			SEEK_DATA => Self::Data,
+			SEEK_HOLE => Self::Hole,
+			_ => Self::Other(raw),
+		}
+	}
 }
 
 impl fmt::Debug for LseekWhence {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-		match self.0 {
-			3 => fmt.write_str("SEEK_DATA"),
-			4 => fmt.write_str("SEEK_HOLE"),
-			_ => self.0.fmt(fmt),
+		match self {
+			Self::Set => fmt.write_str("SEEK_SET"),
+			Self::Cur => fmt.write_str("SEEK_CUR"),
+			Self::End => fmt.write_str("SEEK_END"),
+			Self::Data => fmt.write_str("SEEK_DATA"),
+			Self::Hole => fmt.write_str("SEEK_HOLE"),
+			Self::Other(raw) => raw.fmt(fmt),
 		}
 	}
 }
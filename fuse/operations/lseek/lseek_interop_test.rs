@@ -79,8 +79,8 @@ where
 
 	fn open(&self, request: FuseRequest<'_>) {
 		let send_reply = self.conn.reply(request.id());
-		let mut reply = fuse::kernel::fuse_open_out::new();
-		reply.fh = 12345;
+		let mut reply = server::OpenResponse::new();
+		reply.set_handle(12345);
 		send_reply.ok(&reply).unwrap();
 	}
 
@@ -89,9 +89,7 @@ where
 		let request = server::LseekRequest::try_from(request).unwrap();
 		self.fs.requests.send(format!("{:#?}", request)).unwrap();
 
-		let mut reply = fuse::kernel::fuse_lseek_out::new();
-		reply.offset = 4096;
-		send_reply.ok(&reply).unwrap();
+		send_reply.ok(&server::LseekResponse::new(4096)).unwrap();
 	}
 }
 
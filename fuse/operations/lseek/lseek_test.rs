@@ -14,11 +14,14 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use core::mem::size_of;
+
 use fuse::kernel;
-use fuse::server::LseekRequest;
+use fuse::server::{LseekRequest, LseekResponse};
+use fuse::LseekWhence;
 
 use fuse_testutil as testutil;
-use fuse_testutil::{decode_request, MessageBuilder};
+use fuse_testutil::{decode_request, encode_response, MessageBuilder};
 
 #[test]
 fn request() {
@@ -41,6 +44,30 @@ fn request() {
 	assert_eq!(req.whence(), fuse::LseekWhence::SEEK_DATA);
 }
 
+#[test]
+fn request_whence() {
+	fn decode_whence(whence: u32) -> LseekWhence {
+		let buf = MessageBuilder::new()
+			.set_header(|h| {
+				h.opcode = kernel::fuse_opcode::FUSE_LSEEK;
+				h.nodeid = 123;
+			})
+			.push_sized(&testutil::new!(kernel::fuse_lseek_in {
+				whence: whence,
+			}))
+			.build_aligned();
+		decode_request!(LseekRequest, buf).whence()
+	}
+
+	assert_eq!(decode_whence(0), LseekWhence::Set);
+	assert_eq!(decode_whence(1), LseekWhence::Cur);
+	assert_eq!(decode_whence(2), LseekWhence::End);
+	assert_eq!(decode_whence(3), LseekWhence::Data);
+	assert_eq!(decode_whence(4), LseekWhence::Hole);
+	assert_eq!(decode_whence(5), LseekWhence::Other(5));
+	assert_eq!(format!("{:?}", LseekWhence::Other(5)), "5");
+}
+
 #[test]
 fn request_impl_debug() {
 	let buf;
@@ -68,3 +95,38 @@ fn request_impl_debug() {
 		),
 	);
 }
+
+#[test]
+fn response() {
+	let resp = LseekResponse::new(4096);
+	assert_eq!(resp.offset(), 4096);
+
+	let encoded = encode_response!(&resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>()
+					+ size_of::<kernel::fuse_lseek_out>()) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_sized(&testutil::new!(kernel::fuse_lseek_out {
+				offset: 4096,
+			}))
+			.build()
+	);
+}
+
+#[test]
+fn response_impl_debug() {
+	let resp = LseekResponse::new(4096);
+	assert_eq!(
+		format!("{:#?}", resp),
+		concat!(
+			"LseekResponse {\n",
+			"    offset: 4096,\n",
+			"}",
+		),
+	);
+}
@@ -16,7 +16,9 @@
 
 use core::fmt;
 
+use crate::client;
 use crate::kernel;
+use crate::operations::security_ctx::SecurityContexts;
 use crate::server::decode;
 
 // MkdirRequest {{{
@@ -27,9 +29,10 @@ pub struct MkdirRequest<'a> {
 	parent_id: crate::NodeId,
 	name: &'a crate::NodeName,
 	raw: kernel::fuse_mkdir_in,
+	security_ctx: SecurityContexts<'a>,
 }
 
-impl MkdirRequest<'_> {
+impl<'a> MkdirRequest<'a> {
 	#[must_use]
 	pub fn parent_id(&self) -> crate::NodeId {
 		self.parent_id
@@ -45,10 +48,37 @@ impl MkdirRequest<'_> {
 		crate::FileMode::new(self.raw.mode)
 	}
 
+	/// Returns the umask of the process that created the directory.
 	#[must_use]
 	pub fn umask(&self) -> u32 {
 		self.raw.umask
 	}
+
+	/// Returns the mode of the new directory, with the [`umask`] applied.
+	///
+	/// Unless the [`DONT_MASK`] init flag was negotiated, the kernel applies
+	/// the umask to the mode before sending the request. Otherwise the
+	/// server is responsible for applying it, for example so that a default
+	/// ACL of the parent directory can be used instead. Applying the umask is
+	/// idempotent, so servers that don't implement default ACLs should
+	/// always use this method rather than [`mode`].
+	///
+	/// [`umask`]: Self::umask
+	/// [`mode`]: Self::mode
+	/// [`DONT_MASK`]: crate::FuseInitFlag::DONT_MASK
+	#[must_use]
+	pub fn effective_mode(&self) -> crate::FileMode {
+		self.mode().with_umask(self.umask())
+	}
+
+	/// Returns the security contexts to apply to the new node.
+	///
+	/// Security contexts are only present if the `SECURITY_CTX` init flag
+	/// was negotiated.
+	#[must_use]
+	pub fn security_contexts(&self) -> SecurityContexts<'a> {
+		self.security_ctx
+	}
 }
 
 try_from_fuse_request!(MkdirRequest<'a>, |request| {
@@ -57,13 +87,34 @@ try_from_fuse_request!(MkdirRequest<'a>, |request| {
 
 	let raw: &kernel::fuse_mkdir_in = dec.next_sized()?;
 	let name = dec.next_node_name()?;
+	let security_ctx = SecurityContexts::decode(&mut dec, request.layout)?;
 	Ok(Self {
 		parent_id: decode::node_id(dec.header().nodeid)?,
 		name,
 		raw: *raw,
+		security_ctx,
 	})
 });
 
+impl client::FuseRequest for MkdirRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let ext = self.security_ctx.extension(request_sender.layout);
+		let mut inner = request_sender.inner;
+		inner.set_extensions_len(ext.len());
+		inner.send_4(
+			kernel::fuse_opcode::FUSE_MKDIR,
+			self.parent_id.get(),
+			self.raw.as_bytes(),
+			self.name.as_bytes(),
+			b"\0",
+			ext,
+		)
+	}
+}
+
 impl fmt::Debug for MkdirRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("MkdirRequest")
@@ -18,7 +18,7 @@ use fuse::kernel;
 use fuse::server::MkdirRequest;
 
 use fuse_testutil as testutil;
-use fuse_testutil::{decode_request, MessageBuilder};
+use fuse_testutil::{decode_request, encode_request, MessageBuilder};
 
 #[test]
 fn request() {
@@ -40,6 +40,7 @@ fn request() {
 	assert_eq!(req.name(), "hello.world!");
 	assert_eq!(req.mode(), fuse::FileMode::new(0o755));
 	assert_eq!(req.umask(), 0o111);
+	assert_eq!(req.effective_mode(), fuse::FileMode::new(0o644));
 }
 
 #[test]
@@ -69,3 +70,173 @@ fn request_impl_debug() {
 		),
 	);
 }
+
+#[test]
+fn request_security_contexts() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_MKDIR;
+			h.nodeid = 100;
+			h.total_extlen = 12;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_mkdir_in {
+			mode: 0o755,
+			umask: 0o111,
+		}))
+		.push_bytes(b"hello.world!\x00")
+		.push_sized(&testutil::new!(kernel::fuse_secctx_header {
+			size: 96,
+			nr_secctx: 2,
+		}))
+		.push_sized(&testutil::new!(kernel::fuse_secctx {
+			size: 30,
+		}))
+		.push_bytes(b"security.selinux\x00")
+		.push_bytes(b"system_u:object_r:fusefs_t:s0\x00")
+		.push_bytes(&[0; 1])
+		.push_sized(&testutil::new!(kernel::fuse_secctx {
+			size: 3,
+		}))
+		.push_bytes(b"security.test\x00")
+		.push_bytes(b"abc")
+		.push_bytes(&[0; 7])
+		.build_aligned();
+
+	let req = decode_request!(MkdirRequest, buf, {
+		init_flags: kernel::FUSE_SECURITY_CTX,
+	});
+	assert_eq!(req.name(), "hello.world!");
+
+	let contexts = req.security_contexts();
+	assert_eq!(contexts.len(), 2);
+
+	let contexts: Vec<_> = contexts.collect();
+	assert_eq!(contexts[0].name(), c"security.selinux");
+	assert_eq!(contexts[0].value(), b"system_u:object_r:fusefs_t:s0\x00");
+	assert_eq!(contexts[1].name(), c"security.test");
+	assert_eq!(contexts[1].value(), b"abc");
+
+	let encoded = encode_request!(req, {
+		init_flags: kernel::FUSE_SECURITY_CTX,
+	});
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
+}
+
+#[test]
+fn request_security_contexts_empty() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_MKDIR;
+			h.nodeid = 100;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_mkdir_in {
+			mode: 0o755,
+			umask: 0o111,
+		}))
+		.push_bytes(b"hello.world!\x00")
+		.push_sized(&testutil::new!(kernel::fuse_secctx_header {
+			size: 8,
+			nr_secctx: 0,
+		}))
+		.build_aligned();
+
+	let req = decode_request!(MkdirRequest, buf, {
+		init_flags: kernel::FUSE_SECURITY_CTX,
+	});
+	assert!(req.security_contexts().is_empty());
+	assert!(req.security_contexts().next().is_none());
+}
+
+#[test]
+fn request_security_contexts_not_negotiated() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_MKDIR;
+			h.nodeid = 100;
+			h.total_extlen = 5;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_mkdir_in {
+			mode: 0o755,
+			umask: 0o111,
+		}))
+		.push_bytes(b"hello.world!\x00")
+		.push_sized(&testutil::new!(kernel::fuse_secctx_header {
+			size: 40,
+			nr_secctx: 1,
+		}))
+		.push_sized(&testutil::new!(kernel::fuse_secctx {
+			size: 6,
+		}))
+		.push_bytes(b"security.selinux\x00")
+		.push_bytes(b"label\x00")
+		.push_bytes(&[0; 1])
+		.build_aligned();
+
+	// Security contexts are ignored unless `SECURITY_CTX` was negotiated.
+	let req = decode_request!(MkdirRequest, buf);
+	assert_eq!(req.name(), "hello.world!");
+	assert!(req.security_contexts().is_empty());
+
+	// They also aren't sent to a server that didn't negotiate it.
+	let req = decode_request!(MkdirRequest, buf, {
+		init_flags: kernel::FUSE_SECURITY_CTX,
+	});
+	assert_eq!(req.security_contexts().len(), 1);
+	let encoded = encode_request!(req);
+	let expect = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_MKDIR;
+			h.nodeid = 100;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_mkdir_in {
+			mode: 0o755,
+			umask: 0o111,
+		}))
+		.push_bytes(b"hello.world!\x00")
+		.build();
+	assert_eq!(encoded, expect);
+}
+
+#[test]
+fn request_extensions() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_MKDIR;
+			h.nodeid = 100;
+			h.total_extlen = 8;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_mkdir_in {
+			mode: 0o755,
+			umask: 0o111,
+		}))
+		.push_bytes(b"hello.world!\x00")
+		.push_sized(&testutil::new!(kernel::fuse_secctx_header {
+			size: 48,
+			nr_secctx: 1,
+		}))
+		.push_sized(&testutil::new!(kernel::fuse_secctx {
+			size: 10,
+		}))
+		.push_bytes(b"security.selinux\x00")
+		.push_bytes(b"unlabeled\x00")
+		.push_bytes(&[0; 5])
+		.push_sized(&testutil::new!(kernel::fuse_ext_header {
+			size: 16,
+			r#type: kernel::fuse_ext_type::FUSE_EXT_GROUPS,
+		}))
+		.push_sized(&testutil::new!(kernel::fuse_supp_groups {
+			nr_groups: 1,
+		}))
+		.push_sized(&1000u32)
+		.build_aligned();
+
+	let req = decode_request!(MkdirRequest, buf, {
+		init_flags: kernel::FUSE_SECURITY_CTX,
+	});
+	assert_eq!(req.name(), "hello.world!");
+
+	let contexts: Vec<_> = req.security_contexts().collect();
+	assert_eq!(contexts.len(), 1);
+	assert_eq!(contexts[0].name(), c"security.selinux");
+	assert_eq!(contexts[0].value(), b"unlabeled\x00");
+}
@@ -16,8 +16,11 @@
 
 use core::fmt;
 
+use crate::client;
 use crate::internal::compat;
 use crate::kernel;
+use crate::operations::security_ctx::SecurityContexts;
+use crate::server;
 use crate::server::decode;
 
 // MknodRequest {{{
@@ -28,9 +31,10 @@ pub struct MknodRequest<'a> {
 	header: &'a kernel::fuse_in_header,
 	body: compat::Versioned<compat::fuse_mknod_in<'a>>,
 	name: &'a crate::NodeName,
+	security_ctx: SecurityContexts<'a>,
 }
 
-impl MknodRequest<'_> {
+impl<'a> MknodRequest<'a> {
 	#[must_use]
 	pub fn parent_id(&self) -> crate::NodeId {
 		unsafe { crate::NodeId::new_unchecked(self.header.nodeid) }
@@ -46,6 +50,9 @@ impl MknodRequest<'_> {
 		crate::FileMode::new(self.body.as_v7p1().mode)
 	}
 
+	/// Returns the umask of the process that created the node.
+	///
+	/// The umask is zero for protocol versions older than v7.12.
 	#[must_use]
 	pub fn umask(&self) -> u32 {
 		if let Some(body) = self.body.as_v7p12() {
@@ -54,6 +61,23 @@ impl MknodRequest<'_> {
 		0
 	}
 
+	/// Returns the mode of the new node, with the [`umask`] applied.
+	///
+	/// Unless the [`DONT_MASK`] init flag was negotiated, the kernel applies
+	/// the umask to the mode before sending the request. Otherwise the
+	/// server is responsible for applying it, for example so that a default
+	/// ACL of the parent directory can be used instead. Applying the umask is
+	/// idempotent, so servers that don't implement default ACLs should
+	/// always use this method rather than [`mode`].
+	///
+	/// [`umask`]: Self::umask
+	/// [`mode`]: Self::mode
+	/// [`DONT_MASK`]: crate::FuseInitFlag::DONT_MASK
+	#[must_use]
+	pub fn effective_mode(&self) -> crate::FileMode {
+		self.mode().with_umask(self.umask())
+	}
+
 	#[must_use]
 	pub fn device_number(&self) -> Option<u32> {
 		use crate::FileType as T;
@@ -63,6 +87,26 @@ impl MknodRequest<'_> {
 			_ => None,
 		}
 	}
+
+	/// Returns the kind of node to be created, including its device number
+	/// if the node is a character or block device.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the request's mode has a file type that can't be
+	/// created by `mknod()`.
+	pub fn kind(&self) -> Result<MknodKind, MknodError> {
+		MknodKind::new(self.mode(), self.body.as_v7p1().rdev)
+	}
+
+	/// Returns the security contexts to apply to the new node.
+	///
+	/// Security contexts are only present if the `SECURITY_CTX` init flag
+	/// was negotiated.
+	#[must_use]
+	pub fn security_contexts(&self) -> SecurityContexts<'a> {
+		self.security_ctx
+	}
 }
 
 try_from_fuse_request!(MknodRequest<'a>, |request| {
@@ -82,10 +126,40 @@ try_from_fuse_request!(MknodRequest<'a>, |request| {
 	};
 
 	let name = dec.next_node_name()?;
+	let security_ctx = SecurityContexts::decode(&mut dec, request.layout)?;
 
-	Ok(Self { header, body, name })
+	Ok(Self {
+		header,
+		body,
+		name,
+		security_ctx,
+	})
 });
 
+impl client::FuseRequest for MknodRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let body = self.body.to_v7p12();
+		let mut body_bytes = body.as_bytes();
+		if request_sender.layout.version_minor() < 12 {
+			body_bytes = &body_bytes[..kernel::FUSE_COMPAT_MKNOD_IN_SIZE];
+		}
+		let ext = self.security_ctx.extension(request_sender.layout);
+		let mut inner = request_sender.inner;
+		inner.set_extensions_len(ext.len());
+		inner.send_4(
+			kernel::fuse_opcode::FUSE_MKNOD,
+			self.header.nodeid,
+			body_bytes,
+			self.name.as_bytes(),
+			b"\0",
+			ext,
+		)
+	}
+}
+
 impl fmt::Debug for MknodRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("MknodRequest")
@@ -99,3 +173,173 @@ impl fmt::Debug for MknodRequest<'_> {
 }
 
 // }}}
+
+// MknodResponse {{{
+
+/// Response type for `FUSE_MKNOD`.
+#[derive(Clone, Copy)]
+pub struct MknodResponse {
+	raw: kernel::fuse_entry_out,
+}
+
+impl MknodResponse {
+	/// Creates a new `MknodResponse` for a node of the given kind.
+	///
+	/// The file type and device number of the entry's attributes are set
+	/// from `kind`, as by [`MknodKind::update_attributes`].
+	#[must_use]
+	pub fn new(kind: MknodKind, mut entry: crate::Entry) -> MknodResponse {
+		kind.update_attributes(entry.attributes_mut());
+		Self { raw: *entry.raw() }
+	}
+
+	/// Returns the entry for the created node.
+	#[inline]
+	#[must_use]
+	pub fn entry(&self) -> &crate::Entry {
+		unsafe { crate::Entry::from_ref(&self.raw) }
+	}
+}
+
+impl fmt::Debug for MknodResponse {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("MknodResponse")
+			.field("entry", self.entry())
+			.finish()
+	}
+}
+
+impl server::FuseReply for MknodResponse {
+	#[inline]
+	fn send_to<S: server::FuseSocket>(
+		&self,
+		reply_sender: server::FuseReplySender<'_, S>,
+	) -> Result<(), server::SendError<S::Error>> {
+		self.raw.send_to(reply_sender)
+	}
+}
+
+// }}}
+
+// MknodKind {{{
+
+/// The kind of node created by a `FUSE_MKNOD` request.
+///
+/// Device numbers are only present for character and block devices. Their
+/// encoding is platform-specific, and matches the `rdev` field of
+/// [`NodeAttr`](crate::NodeAttr).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum MknodKind {
+	/// A regular file (`S_IFREG`).
+	Regular,
+
+	/// A named pipe (`S_IFIFO`).
+	NamedPipe,
+
+	/// A Unix domain socket (`S_IFSOCK`).
+	Socket,
+
+	/// A character device (`S_IFCHR`) with the given device number.
+	CharacterDevice(u32),
+
+	/// A block device (`S_IFBLK`) with the given device number.
+	BlockDevice(u32),
+}
+
+impl MknodKind {
+	/// Returns the `MknodKind` for a file mode and device number.
+	///
+	/// A mode without file type bits is treated as a regular file, matching
+	/// the behavior of `mknod()`. The device number is ignored unless the
+	/// mode is for a character or block device.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the mode has a file type that can't be created
+	/// by `mknod()`.
+	pub fn new(
+		mode: crate::FileMode,
+		device_number: u32,
+	) -> Result<MknodKind, MknodError> {
+		use crate::FileType as T;
+		if mode.type_bits() == 0 {
+			return Ok(MknodKind::Regular);
+		}
+		match crate::FileType::from_mode(mode) {
+			Some(T::Regular) => Ok(MknodKind::Regular),
+			Some(T::NamedPipe) => Ok(MknodKind::NamedPipe),
+			Some(T::Socket) => Ok(MknodKind::Socket),
+			Some(T::CharacterDevice) => {
+				Ok(MknodKind::CharacterDevice(device_number))
+			},
+			Some(T::BlockDevice) => Ok(MknodKind::BlockDevice(device_number)),
+			_ => Err(MknodError::InvalidFileType(mode)),
+		}
+	}
+
+	/// Returns the file type of the created node.
+	#[must_use]
+	pub fn file_type(&self) -> crate::FileType {
+		match self {
+			MknodKind::Regular => crate::FileType::Regular,
+			MknodKind::NamedPipe => crate::FileType::NamedPipe,
+			MknodKind::Socket => crate::FileType::Socket,
+			MknodKind::CharacterDevice(_) => crate::FileType::CharacterDevice,
+			MknodKind::BlockDevice(_) => crate::FileType::BlockDevice,
+		}
+	}
+
+	/// Returns the device number of the created node, if it is a character
+	/// or block device.
+	#[must_use]
+	pub fn device_number(&self) -> Option<u32> {
+		match *self {
+			MknodKind::CharacterDevice(dev) => Some(dev),
+			MknodKind::BlockDevice(dev) => Some(dev),
+			_ => None,
+		}
+	}
+
+	/// Updates node attributes to match this kind of node.
+	///
+	/// The file type bits of the attributes' mode are replaced and the
+	/// permission bits are preserved. The device number is set for character
+	/// and block devices, and cleared otherwise.
+	pub fn update_attributes(&self, attrs: &mut crate::NodeAttr) {
+		let mode = attrs.mode();
+		let other_bits = mode.get() ^ mode.type_bits();
+		attrs.set_mode(self.file_type().as_mode() | other_bits);
+		attrs.set_device_number(self.device_number().unwrap_or(0));
+	}
+}
+
+// }}}
+
+// MknodError {{{
+
+/// Errors that may occur when validating a `FUSE_MKNOD` request.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum MknodError {
+	/// The mode has a file type that can't be created by `mknod()`, such
+	/// as a directory or symlink.
+	InvalidFileType(crate::FileMode),
+}
+
+impl fmt::Display for MknodError {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::InvalidFileType(mode) => write!(
+				fmt,
+				"file type of mode {:#o} can't be created by mknod()",
+				mode.get(),
+			),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl core::error::Error for MknodError {}
+
+// }}}
@@ -14,13 +14,21 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use core::mem::size_of;
+
 use fuse::kernel;
 use fuse::server::MknodRequest;
 
 use fuse_testutil as testutil;
-use fuse_testutil::{decode_request, MessageBuilder};
+use fuse_testutil::{
+	decode_request,
+	encode_request,
+	encode_response,
+	MessageBuilder,
+};
 
 const S_IFBLK: u32 = 0o60000;
+const S_IFCHR: u32 = 0o20000;
 
 #[test]
 fn request_v7p1() {
@@ -42,7 +50,13 @@ fn request_v7p1() {
 	assert_eq!(req.name(), "hello.world!");
 	assert_eq!(req.mode(), fuse::FileMode::new(0o644));
 	assert_eq!(req.umask(), 0);
+	assert_eq!(req.effective_mode(), fuse::FileMode::new(0o644));
 	assert_eq!(req.device_number(), None);
+
+	let encoded = encode_request!(req, {
+		protocol_version: (7, 1),
+	});
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
 }
 
 #[test]
@@ -67,7 +81,56 @@ fn request_v7p12() {
 	assert_eq!(req.name(), "hello.world!");
 	assert_eq!(req.mode(), fuse::FileMode::new(0o644));
 	assert_eq!(req.umask(), 0o111);
+	assert_eq!(req.effective_mode(), fuse::FileMode::new(0o644));
 	assert_eq!(req.device_number(), None);
+
+	let encoded = encode_request!(req, {
+		protocol_version: (7, 12),
+	});
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
+}
+
+
+#[test]
+fn request_security_contexts() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_MKNOD;
+			h.nodeid = 100;
+			h.total_extlen = 5;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_mknod_in {
+			mode: 0o644,
+			umask: 0o111,
+		}))
+		.push_bytes(b"hello.world!\x00")
+		.push_sized(&testutil::new!(kernel::fuse_secctx_header {
+			size: 40,
+			nr_secctx: 1,
+		}))
+		.push_sized(&testutil::new!(kernel::fuse_secctx {
+			size: 6,
+		}))
+		.push_bytes(b"security.selinux\x00")
+		.push_bytes(b"label\x00")
+		.push_bytes(&[0; 1])
+		.build_aligned();
+
+	let req = decode_request!(MknodRequest, buf, {
+		init_flags: kernel::FUSE_SECURITY_CTX,
+	});
+	assert_eq!(req.name(), "hello.world!");
+
+	let mut contexts = req.security_contexts();
+	let context = contexts.next().unwrap();
+	assert_eq!(context.name(), c"security.selinux");
+	assert_eq!(context.value(), b"label\x00");
+	assert!(contexts.next().is_none());
+
+	let encoded = encode_request!(req, {
+		init_flags: kernel::FUSE_SECURITY_CTX,
+	});
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
 }
 
 #[test]
@@ -97,6 +160,29 @@ fn request_device_number() {
 	assert_eq!(req.device_number(), Some(123));
 }
 
+#[test]
+fn request_effective_mode() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_MKNOD;
+			h.nodeid = 100;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_mknod_in {
+			mode: S_IFBLK | 0o4777,
+			rdev: 123,
+			umask: 0o7022,
+		}))
+		.push_bytes(b"hello.world!\x00")
+		.build_aligned();
+
+	let req = decode_request!(MknodRequest, buf, {
+		protocol_version: (7, 12),
+	});
+
+	// Only the permission bits are affected by the umask.
+	assert_eq!(req.effective_mode(), fuse::FileMode::new(S_IFBLK | 0o4755));
+}
+
 #[test]
 fn request_impl_debug() {
 	let buf = MessageBuilder::new()
@@ -126,3 +212,105 @@ fn request_impl_debug() {
 		),
 	);
 }
+
+#[test]
+fn request_kind() {
+	use fuse::server::{MknodError, MknodKind};
+
+	fn request_kind(mode: u32, rdev: u32) -> Result<MknodKind, MknodError> {
+		let buf = MessageBuilder::new()
+			.set_header(|h| {
+				h.opcode = kernel::fuse_opcode::FUSE_MKNOD;
+				h.nodeid = 100;
+			})
+			.push_sized(&testutil::new!(kernel::fuse_mknod_in {
+				mode: mode,
+				rdev: rdev,
+			}))
+			.push_bytes(b"hello.world!\x00")
+			.build_aligned();
+		let req = decode_request!(MknodRequest, buf, {
+			protocol_version: (7, 12),
+		});
+		req.kind()
+	}
+
+	assert_eq!(request_kind(0o644, 123), Ok(MknodKind::Regular));
+	assert_eq!(request_kind(0o100644, 123), Ok(MknodKind::Regular));
+	assert_eq!(request_kind(0o010644, 123), Ok(MknodKind::NamedPipe));
+	assert_eq!(request_kind(0o140644, 123), Ok(MknodKind::Socket));
+	assert_eq!(
+		request_kind(0o020644, 123),
+		Ok(MknodKind::CharacterDevice(123)),
+	);
+	assert_eq!(
+		request_kind(S_IFBLK | 0o644, 123),
+		Ok(MknodKind::BlockDevice(123)),
+	);
+	assert_eq!(
+		request_kind(0o040755, 123),
+		Err(MknodError::InvalidFileType(fuse::FileMode::new(0o040755))),
+	);
+}
+
+#[test]
+fn kind_update_attributes() {
+	use fuse::server::MknodKind;
+
+	let mut attrs = fuse::NodeAttr::new(fuse::NodeId::new(2).unwrap());
+	attrs.set_mode(fuse::FileMode::S_IFREG | 0o644);
+	attrs.set_device_number(456);
+
+	MknodKind::BlockDevice(123).update_attributes(&mut attrs);
+	assert_eq!(attrs.mode(), fuse::FileMode::new(S_IFBLK | 0o644));
+	assert_eq!(attrs.device_number(), 123);
+
+	MknodKind::NamedPipe.update_attributes(&mut attrs);
+	assert_eq!(attrs.mode(), fuse::FileMode::S_IFIFO | 0o644);
+	assert_eq!(attrs.device_number(), 0);
+}
+
+#[test]
+fn response() {
+	use fuse::server::{MknodKind, MknodResponse};
+
+	let mut attr = fuse::NodeAttr::new(fuse::NodeId::new(11).unwrap());
+	attr.set_mode(fuse::FileMode::S_IFREG | 0o644);
+	let mut entry = fuse::Entry::new(attr);
+	entry.set_generation(22);
+	let resp = MknodResponse::new(MknodKind::CharacterDevice(123), entry);
+	assert_eq!(resp.entry().generation(), 22);
+
+	let encoded = encode_response!(&resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>()
+					+ size_of::<kernel::fuse_entry_out>()) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_sized(&testutil::new!(kernel::fuse_entry_out {
+				nodeid: 11,
+				generation: 22,
+				attr: testutil::new!(kernel::fuse_attr {
+					ino: 11,
+					mode: S_IFCHR | 0o644,
+					rdev: 123,
+				}),
+			}))
+			.build()
+	);
+}
+
+#[test]
+fn error_impl_display() {
+	use fuse::server::MknodError;
+
+	let err = MknodError::InvalidFileType(fuse::FileMode::new(0o040755));
+	assert_eq!(
+		format!("{}", err),
+		"file type of mode 0o40755 can't be created by mknod()",
+	);
+}
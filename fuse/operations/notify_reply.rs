@@ -0,0 +1,107 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::fmt;
+
+use crate::client;
+use crate::internal::debug;
+use crate::kernel;
+use crate::server::decode;
+
+// NotifyReplyRequest {{{
+
+/// Request type for `FUSE_NOTIFY_REPLY`.
+///
+/// The kernel sends `FUSE_NOTIFY_REPLY` in response to a
+/// `FUSE_NOTIFY_RETRIEVE` notification, with the retrieved contents of the
+/// node's page cache. Its request ID is the `notify_unique` value that was
+/// chosen by the server, and it doesn't expect a reply.
+#[derive(Clone, Copy)]
+pub struct NotifyReplyRequest<'a> {
+	header: &'a kernel::fuse_in_header,
+	body: &'a kernel::fuse_notify_retrieve_in,
+	data: &'a [u8],
+}
+
+impl NotifyReplyRequest<'_> {
+	/// Returns the `notify_unique` value of the `FUSE_NOTIFY_RETRIEVE`
+	/// notification that this request is a reply to.
+	#[must_use]
+	pub fn notify_unique(&self) -> u64 {
+		self.header.unique
+	}
+
+	/// Returns the ID of the node whose data was retrieved.
+	#[must_use]
+	pub fn node_id(&self) -> crate::NodeId {
+		unsafe { crate::NodeId::new_unchecked(self.header.nodeid) }
+	}
+
+	/// Returns the offset of the retrieved data within the node.
+	#[must_use]
+	pub fn offset(&self) -> u64 {
+		self.body.offset
+	}
+
+	/// Returns the retrieved data.
+	///
+	/// The kernel only returns data that is in the page cache, so this may
+	/// be shorter than the size requested by the notification.
+	#[must_use]
+	pub fn data(&self) -> &[u8] {
+		self.data
+	}
+}
+
+try_from_fuse_request!(NotifyReplyRequest<'a>, |request| {
+	let mut dec = request.decoder();
+	dec.expect_opcode(kernel::fuse_opcode::FUSE_NOTIFY_REPLY)?;
+
+	let header = dec.header();
+	decode::node_id(header.nodeid)?;
+	let body: &kernel::fuse_notify_retrieve_in = dec.next_sized()?;
+	let data = dec.next_bytes(body.size)?;
+	Ok(Self { header, body, data })
+});
+
+impl client::FuseRequest for NotifyReplyRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let mut inner = request_sender.inner;
+		inner.header.unique = self.header.unique;
+		inner.send_2(
+			kernel::fuse_opcode::FUSE_NOTIFY_REPLY,
+			self.header.nodeid,
+			self.body.as_bytes(),
+			self.data,
+		)
+	}
+}
+
+impl fmt::Debug for NotifyReplyRequest<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("NotifyReplyRequest")
+			.field("notify_unique", &self.notify_unique())
+			.field("node_id", &self.node_id())
+			.field("offset", &self.offset())
+			.field("data", &debug::bytes(self.data))
+			.finish()
+	}
+}
+
+// }}}
@@ -0,0 +1,78 @@
+// Copyright 2023 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use fuse::kernel;
+use fuse::server::NotifyReplyRequest;
+
+use fuse_testutil as testutil;
+use fuse_testutil::{decode_request, encode_request, MessageBuilder};
+
+#[test]
+fn request() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_NOTIFY_REPLY;
+			h.unique = 456;
+			h.nodeid = 123;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_notify_retrieve_in {
+			offset: 4096,
+			size: 5,
+		}))
+		.push_bytes(b"hello")
+		.build_aligned();
+
+	let req = decode_request!(NotifyReplyRequest, buf);
+
+	assert_eq!(req.notify_unique(), 456);
+	assert_eq!(req.node_id(), fuse::NodeId::new(123).unwrap());
+	assert_eq!(req.offset(), 4096);
+	assert_eq!(req.data(), b"hello");
+
+	// The request ID is the `notify_unique` chosen by the server, rather
+	// than one assigned by the request sender.
+	let encoded = encode_request!(req);
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
+}
+
+#[test]
+fn request_impl_debug() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_NOTIFY_REPLY;
+			h.unique = 456;
+			h.nodeid = 123;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_notify_retrieve_in {
+			offset: 4096,
+			size: 5,
+		}))
+		.push_bytes(b"hello")
+		.build_aligned();
+	let request = decode_request!(NotifyReplyRequest, buf);
+
+	assert_eq!(
+		format!("{:#?}", request),
+		concat!(
+			"NotifyReplyRequest {\n",
+			"    notify_unique: 456,\n",
+			"    node_id: 123,\n",
+			"    offset: 4096,\n",
+			"    data: \"hello\",\n",
+			"}",
+		),
+	);
+}
@@ -16,7 +16,8 @@
 
 use core::fmt;
 
-use crate::internal::debug;
+use crate::client;
+use crate::internal::compat;
 use crate::kernel;
 use crate::server;
 use crate::server::decode;
@@ -46,7 +47,21 @@ impl OpenRequest<'_> {
 	#[must_use]
 	#[allow(clippy::misnamed_getters)]
 	pub fn open_flags(&self) -> crate::OpenFlags {
-		self.body.flags
+		crate::OpenFlags::new(self.body.flags)
+	}
+
+	/// Returns whether the server should clear the setuid and setgid bits
+	/// of the file, along with its `security.capability` xattr.
+	///
+	/// This is only set for opens with `O_TRUNC`, and only if the
+	/// [`HANDLE_KILLPRIV_V2`] init flag was negotiated. See
+	/// [`FileMode::without_suidgid`] for which bits should be cleared.
+	///
+	/// [`HANDLE_KILLPRIV_V2`]: crate::FuseInitFlag::HANDLE_KILLPRIV_V2
+	/// [`FileMode::without_suidgid`]: crate::FileMode::without_suidgid
+	#[must_use]
+	pub fn kill_suidgid(&self) -> bool {
+		self.body.open_flags & kernel::FUSE_OPEN_KILL_SUIDGID != 0
 	}
 }
 
@@ -75,18 +90,242 @@ impl<'a> OpenRequest<'a> {
 	}
 }
 
+impl client::CuseRequest for OpenRequest<'_> {
+	fn send_to<S: client::io::CuseSocket>(
+		&self,
+		request_sender: client::CuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		self.send(request_sender.inner)
+	}
+}
+
+impl client::FuseRequest for OpenRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		self.send(request_sender.inner)
+	}
+}
+
+impl OpenRequest<'_> {
+	fn send<S: client::io::Socket>(
+		&self,
+		request_sender: client::RequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.send_1(
+			kernel::fuse_opcode::FUSE_OPEN,
+			self.header.nodeid,
+			self.body.as_bytes(),
+		)
+	}
+}
+
 impl fmt::Debug for OpenRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("OpenRequest")
 			.field("node_id", &self.node_id())
 			.field("flags", &self.flags())
-			.field("open_flags", &debug::hex_u32(self.open_flags()))
+			.field("open_flags", &self.open_flags())
 			.finish()
 	}
 }
 
 // }}}
 
+// OpenResponse {{{
+
+/// Response type for `FUSE_OPEN`.
+///
+/// Flags that aren't supported by the client's negotiated protocol version
+/// are cleared when the response is sent, so it's safe to set them without
+/// first checking the version. For example [`PASSTHROUGH`] requires at least
+/// protocol version 7.40, and is ignored for older clients.
+///
+/// [`PASSTHROUGH`]: OpenResponseFlag::PASSTHROUGH
+pub struct OpenResponse {
+	raw: kernel::fuse_open_out,
+}
+
+impl OpenResponse {
+	#[inline]
+	#[must_use]
+	pub fn new() -> OpenResponse {
+		Self {
+			raw: kernel::fuse_open_out::new(),
+		}
+	}
+
+	#[inline]
+	#[must_use]
+	pub fn handle(&self) -> u64 {
+		self.raw.fh
+	}
+
+	#[inline]
+	pub fn set_handle(&mut self, handle: u64) {
+		self.raw.fh = handle;
+	}
+
+	#[inline]
+	#[must_use]
+	pub fn flags(&self) -> OpenResponseFlags {
+		OpenResponseFlags {
+			bits: self.raw.open_flags,
+		}
+	}
+
+	#[inline]
+	pub fn set_flags(&mut self, flags: OpenResponseFlags) {
+		self.raw.open_flags = flags.bits;
+	}
+
+	#[inline]
+	pub fn update_flags(&mut self, f: impl FnOnce(&mut OpenResponseFlags)) {
+		let mut flags = self.flags();
+		f(&mut flags);
+		self.set_flags(flags)
+	}
+
+	/// Sets the [`DIRECT_IO`] flag, which bypasses the client's page
+	/// cache for the opened file.
+	///
+	/// [`DIRECT_IO`]: OpenResponseFlag::DIRECT_IO
+	#[inline]
+	pub fn set_direct_io(&mut self, direct_io: bool) {
+		self.set_open_flag(kernel::FOPEN_DIRECT_IO, direct_io);
+	}
+
+	/// Sets the [`KEEP_CACHE`] flag, which keeps the client's cached data
+	/// for the opened file.
+	///
+	/// [`KEEP_CACHE`]: OpenResponseFlag::KEEP_CACHE
+	#[inline]
+	pub fn set_keep_cache(&mut self, keep_cache: bool) {
+		self.set_open_flag(kernel::FOPEN_KEEP_CACHE, keep_cache);
+	}
+
+	/// Sets the [`NONSEEKABLE`] flag, which marks the opened file as not
+	/// seekable.
+	///
+	/// [`NONSEEKABLE`]: OpenResponseFlag::NONSEEKABLE
+	#[inline]
+	pub fn set_nonseekable(&mut self, nonseekable: bool) {
+		self.set_open_flag(kernel::FOPEN_NONSEEKABLE, nonseekable);
+	}
+
+	/// Sets the [`CACHE_DIR`] flag, which allows the client to cache the
+	/// opened directory's entries.
+	///
+	/// [`CACHE_DIR`]: OpenResponseFlag::CACHE_DIR
+	#[inline]
+	pub fn set_cache_dir(&mut self, cache_dir: bool) {
+		self.set_open_flag(kernel::FOPEN_CACHE_DIR, cache_dir);
+	}
+
+	/// Sets the [`STREAM`] flag, which marks the opened file as stream-like
+	/// (no file position).
+	///
+	/// [`STREAM`]: OpenResponseFlag::STREAM
+	#[inline]
+	pub fn set_stream(&mut self, stream: bool) {
+		self.set_open_flag(kernel::FOPEN_STREAM, stream);
+	}
+
+	/// Sets the [`NOFLUSH`] flag, which skips flushing the client's cached
+	/// data when the opened file is closed.
+	///
+	/// [`NOFLUSH`]: OpenResponseFlag::NOFLUSH
+	#[inline]
+	pub fn set_noflush(&mut self, noflush: bool) {
+		self.set_open_flag(kernel::FOPEN_NOFLUSH, noflush);
+	}
+
+	/// Sets the [`PARALLEL_DIRECT_WRITES`] flag, which allows concurrent
+	/// direct writes to the opened file.
+	///
+	/// [`PARALLEL_DIRECT_WRITES`]: OpenResponseFlag::PARALLEL_DIRECT_WRITES
+	#[inline]
+	pub fn set_parallel_direct_writes(&mut self, parallel_direct_writes: bool) {
+		self.set_open_flag(
+			kernel::FOPEN_PARALLEL_DIRECT_WRITES,
+			parallel_direct_writes,
+		);
+	}
+
+	#[inline]
+	fn set_open_flag(&mut self, mask: u32, set: bool) {
+		if set {
+			self.raw.open_flags |= mask;
+		} else {
+			self.raw.open_flags &= !mask;
+		}
+	}
+
+	/// Returns the backing file ID for passthrough I/O, if the
+	/// [`PASSTHROUGH`] flag is set.
+	///
+	/// [`PASSTHROUGH`]: OpenResponseFlag::PASSTHROUGH
+	#[inline]
+	#[must_use]
+	pub fn backing_id(&self) -> Option<u32> {
+		if self.raw.open_flags & kernel::FOPEN_PASSTHROUGH == 0 {
+			return None;
+		}
+		Some(self.raw.backing_id as u32)
+	}
+
+	/// Sets the backing file ID for passthrough I/O, and sets the
+	/// [`PASSTHROUGH`] flag.
+	///
+	/// Reads and writes to the opened file will be performed by the client
+	/// directly on the backing file, without sending `FUSE_READ` or
+	/// `FUSE_WRITE`. Backing file IDs are registered with the client by
+	/// an OS-specific socket method, such as
+	/// `fuse_linux::FuseServerSocket::open_backing`.
+	///
+	/// [`PASSTHROUGH`]: OpenResponseFlag::PASSTHROUGH
+	#[inline]
+	pub fn set_backing_id(&mut self, backing_id: u32) {
+		self.raw.backing_id = backing_id as i32;
+		self.raw.open_flags |= kernel::FOPEN_PASSTHROUGH;
+	}
+}
+
+impl fmt::Debug for OpenResponse {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("OpenResponse")
+			.field("handle", &self.handle())
+			.field("flags", &self.flags())
+			.field("backing_id", &format_args!("{:?}", self.backing_id()))
+			.finish()
+	}
+}
+
+impl server::CuseReply for OpenResponse {
+	fn send_to<S: server::CuseSocket>(
+		&self,
+		reply_sender: server::CuseReplySender<'_, S>,
+	) -> Result<(), server::SendError<S::Error>> {
+		reply_sender.inner.send_1(self.raw.as_bytes())
+	}
+}
+
+impl server::FuseReply for OpenResponse {
+	fn send_to<S: server::FuseSocket>(
+		&self,
+		reply_sender: server::FuseReplySender<'_, S>,
+	) -> Result<(), server::SendError<S::Error>> {
+		let raw = compat::fuse_open_out_for_version(
+			reply_sender.layout.version_minor(),
+			&self.raw,
+		);
+		reply_sender.inner.send_1(raw.as_bytes())
+	}
+}
+
+// }}}
+
 // OpenRequestFlags {{{
 
 #[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -110,6 +349,7 @@ mod request_flags {
 
 // OpenResponseFlags {{{
 
+/// Optional flags set on [`OpenResponse`].
 #[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct OpenResponseFlags {
 	bits: u32,
@@ -129,6 +369,8 @@ mod response_flags {
 		CACHE_DIR = kernel::FOPEN_CACHE_DIR;
 		STREAM = kernel::FOPEN_STREAM;
 		NOFLUSH = kernel::FOPEN_NOFLUSH;
+		PARALLEL_DIRECT_WRITES = kernel::FOPEN_PARALLEL_DIRECT_WRITES;
+		PASSTHROUGH = kernel::FOPEN_PASSTHROUGH;
 	});
 }
 
@@ -14,11 +14,13 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use core::mem::size_of;
+
 use fuse::kernel;
-use fuse::server::OpenRequest;
+use fuse::server::{OpenRequest, OpenResponse};
 
 use fuse_testutil as testutil;
-use fuse_testutil::{decode_request, MessageBuilder};
+use fuse_testutil::{decode_request, encode_response, MessageBuilder};
 
 #[test]
 fn request() {
@@ -36,7 +38,55 @@ fn request() {
 	let req = decode_request!(OpenRequest, buf);
 
 	assert_eq!(req.flags().get(fuse::OpenRequestFlag::KILL_SUIDGID), true);
-	assert_eq!(req.open_flags(), 0xFF);
+	assert_eq!(req.kill_suidgid(), true);
+	assert_eq!(req.open_flags().get(), 0xFF);
+}
+
+#[test]
+fn request_open_flags() {
+	use fuse::{OpenAccessMode, OpenFlags};
+
+	assert_eq!(
+		OpenFlags::new(0o0).access_mode(),
+		Some(OpenAccessMode::ReadOnly),
+	);
+	assert_eq!(
+		OpenFlags::new(0o1).access_mode(),
+		Some(OpenAccessMode::WriteOnly),
+	);
+	assert_eq!(
+		OpenFlags::new(0o2).access_mode(),
+		Some(OpenAccessMode::ReadWrite),
+	);
+	assert_eq!(OpenFlags::new(0o3).access_mode(), None);
+
+	#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+	{
+		// O_WRONLY | O_APPEND | O_TRUNC | O_NOFOLLOW
+		let flags = OpenFlags::new(0o403001);
+		assert!(flags.append());
+		assert!(flags.truncate());
+		assert!(flags.no_follow());
+		assert!(!flags.create());
+		assert!(!flags.direct());
+		assert!(!flags.sync());
+
+		// O_DSYNC alone is not O_SYNC.
+		assert!(!OpenFlags::new(0o10000).sync());
+		assert!(OpenFlags::new(0o4010000).sync());
+	}
+
+	#[cfg(target_os = "freebsd")]
+	{
+		// O_WRONLY | O_APPEND | O_TRUNC | O_NOFOLLOW
+		let flags = OpenFlags::new(0x0001 | 0x0008 | 0x0400 | 0x0100);
+		assert!(flags.append());
+		assert!(flags.truncate());
+		assert!(flags.no_follow());
+		assert!(!flags.create());
+		assert!(!flags.direct());
+		assert!(!flags.sync());
+	}
 }
 
 #[test]
@@ -66,3 +116,113 @@ fn request_impl_debug() {
 		),
 	);
 }
+
+#[test]
+fn response() {
+	let mut resp = OpenResponse::new();
+	resp.set_handle(123);
+	resp.update_flags(|flags| {
+		flags.set(fuse::OpenResponseFlag::KEEP_CACHE);
+	});
+	assert_eq!(resp.backing_id(), None);
+
+	resp.set_backing_id(456);
+	assert_eq!(resp.backing_id(), Some(456));
+	assert_eq!(
+		resp.flags(),
+		fuse::OpenResponseFlag::KEEP_CACHE
+			| fuse::OpenResponseFlag::PASSTHROUGH,
+	);
+
+	let encoded = encode_response!(&resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>()
+					+ size_of::<kernel::fuse_open_out>()) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_sized(&testutil::new!(kernel::fuse_open_out {
+				fh: 123,
+				open_flags: kernel::FOPEN_KEEP_CACHE
+					| kernel::FOPEN_PASSTHROUGH,
+				backing_id: 456,
+			}))
+			.build()
+	);
+}
+
+#[test]
+fn response_flag_setters() {
+	let mut resp = OpenResponse::new();
+	resp.set_direct_io(true);
+	resp.set_keep_cache(true);
+	resp.set_nonseekable(true);
+	resp.set_cache_dir(true);
+	resp.set_stream(true);
+	resp.set_noflush(true);
+	resp.set_parallel_direct_writes(true);
+	resp.set_keep_cache(false);
+	assert_eq!(
+		resp.flags(),
+		fuse::OpenResponseFlag::DIRECT_IO
+			| fuse::OpenResponseFlag::NONSEEKABLE
+			| fuse::OpenResponseFlag::CACHE_DIR
+			| fuse::OpenResponseFlag::STREAM
+			| fuse::OpenResponseFlag::NOFLUSH
+			| fuse::OpenResponseFlag::PARALLEL_DIRECT_WRITES,
+	);
+}
+
+#[test]
+fn response_flags_v7p28() {
+	let mut resp = OpenResponse::new();
+	resp.set_handle(123);
+	resp.set_direct_io(true);
+	resp.set_cache_dir(true);
+	resp.set_stream(true);
+	resp.set_noflush(true);
+	resp.set_backing_id(456);
+
+	let encoded = encode_response!(&resp, {
+		protocol_version: (7, 28),
+	});
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>()
+					+ size_of::<kernel::fuse_open_out>()) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_sized(&testutil::new!(kernel::fuse_open_out {
+				fh: 123,
+				open_flags: kernel::FOPEN_DIRECT_IO
+					| kernel::FOPEN_CACHE_DIR,
+			}))
+			.build()
+	);
+}
+
+#[test]
+fn response_impl_debug() {
+	let mut resp = OpenResponse::new();
+	resp.set_handle(123);
+	resp.set_backing_id(456);
+
+	assert_eq!(
+		format!("{:#?}", resp),
+		concat!(
+			"OpenResponse {\n",
+			"    handle: 123,\n",
+			"    flags: OpenResponseFlags {\n",
+			"        PASSTHROUGH,\n",
+			"    },\n",
+			"    backing_id: Some(456),\n",
+			"}",
+		),
+	);
+}
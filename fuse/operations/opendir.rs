@@ -16,8 +16,10 @@
 
 use core::fmt;
 
-use crate::internal::debug;
+use crate::client;
+use crate::internal::compat;
 use crate::kernel;
+use crate::server;
 use crate::server::decode;
 
 // OpendirRequest {{{
@@ -45,7 +47,7 @@ impl OpendirRequest<'_> {
 	#[must_use]
 	#[allow(clippy::misnamed_getters)]
 	pub fn open_flags(&self) -> crate::OpenFlags {
-		self.body.flags
+		crate::OpenFlags::new(self.body.flags)
 	}
 }
 
@@ -59,18 +61,109 @@ try_from_fuse_request!(OpendirRequest<'a>, |request| {
 	Ok(Self { header, body })
 });
 
+impl client::FuseRequest for OpendirRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.inner.send_1(
+			kernel::fuse_opcode::FUSE_OPENDIR,
+			self.header.nodeid,
+			self.body.as_bytes(),
+		)
+	}
+}
+
 impl fmt::Debug for OpendirRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("OpendirRequest")
 			.field("node_id", &self.node_id())
 			.field("flags", &self.flags())
-			.field("open_flags", &debug::hex_u32(self.open_flags()))
+			.field("open_flags", &self.open_flags())
 			.finish()
 	}
 }
 
 // }}}
 
+// OpendirResponse {{{
+
+/// Response type for `FUSE_OPENDIR`.
+///
+/// Flags that aren't supported by the client's negotiated protocol version
+/// are cleared when the response is sent.
+pub struct OpendirResponse {
+	raw: kernel::fuse_open_out,
+}
+
+impl OpendirResponse {
+	#[inline]
+	#[must_use]
+	pub fn new() -> OpendirResponse {
+		Self {
+			raw: kernel::fuse_open_out::new(),
+		}
+	}
+
+	#[inline]
+	#[must_use]
+	pub fn handle(&self) -> u64 {
+		self.raw.fh
+	}
+
+	#[inline]
+	pub fn set_handle(&mut self, handle: u64) {
+		self.raw.fh = handle;
+	}
+
+	#[inline]
+	#[must_use]
+	pub fn flags(&self) -> OpendirResponseFlags {
+		OpendirResponseFlags {
+			bits: self.raw.open_flags,
+		}
+	}
+
+	#[inline]
+	pub fn set_flags(&mut self, flags: OpendirResponseFlags) {
+		self.raw.open_flags = flags.bits;
+	}
+
+	#[inline]
+	pub fn update_flags(
+		&mut self,
+		f: impl FnOnce(&mut OpendirResponseFlags),
+	) {
+		let mut flags = self.flags();
+		f(&mut flags);
+		self.set_flags(flags)
+	}
+}
+
+impl fmt::Debug for OpendirResponse {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("OpendirResponse")
+			.field("handle", &self.handle())
+			.field("flags", &self.flags())
+			.finish()
+	}
+}
+
+impl server::FuseReply for OpendirResponse {
+	fn send_to<S: server::FuseSocket>(
+		&self,
+		reply_sender: server::FuseReplySender<'_, S>,
+	) -> Result<(), server::SendError<S::Error>> {
+		let raw = compat::fuse_open_out_for_version(
+			reply_sender.layout.version_minor(),
+			&self.raw,
+		);
+		reply_sender.inner.send_1(raw.as_bytes())
+	}
+}
+
+// }}}
+
 // OpendirRequestFlags {{{
 
 #[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -94,6 +187,7 @@ mod request_flags {
 
 // OpendirResponseFlags {{{
 
+/// Optional flags set on [`OpendirResponse`].
 #[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct OpendirResponseFlags {
 	bits: u32,
@@ -113,6 +207,7 @@ mod response_flags {
 		CACHE_DIR = kernel::FOPEN_CACHE_DIR;
 		STREAM = kernel::FOPEN_STREAM;
 		NOFLUSH = kernel::FOPEN_NOFLUSH;
+		PARALLEL_DIRECT_WRITES = kernel::FOPEN_PARALLEL_DIRECT_WRITES;
 	});
 }
 
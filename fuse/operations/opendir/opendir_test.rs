@@ -14,11 +14,13 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use core::mem::size_of;
+
 use fuse::kernel;
-use fuse::server::OpendirRequest;
+use fuse::server::{OpendirRequest, OpendirResponse};
 
 use fuse_testutil as testutil;
-use fuse_testutil::{decode_request, MessageBuilder};
+use fuse_testutil::{decode_request, encode_response, MessageBuilder};
 
 #[test]
 fn request() {
@@ -35,7 +37,7 @@ fn request() {
 
 	let req = decode_request!(OpendirRequest, buf);
 
-	assert_eq!(req.open_flags(), 0xFF);
+	assert_eq!(req.open_flags().get(), 0xFF);
 }
 
 #[test]
@@ -62,3 +64,50 @@ fn request_impl_debug() {
 		),
 	);
 }
+
+#[test]
+fn response() {
+	let mut resp = OpendirResponse::new();
+	resp.set_handle(123);
+	resp.update_flags(|flags| {
+		flags.set(fuse::OpendirResponseFlag::CACHE_DIR);
+	});
+
+	let encoded = encode_response!(&resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>()
+					+ size_of::<kernel::fuse_open_out>()) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_sized(&testutil::new!(kernel::fuse_open_out {
+				fh: 123,
+				open_flags: kernel::FOPEN_CACHE_DIR,
+			}))
+			.build()
+	);
+}
+
+#[test]
+fn response_impl_debug() {
+	let mut resp = OpendirResponse::new();
+	resp.set_handle(123);
+	resp.update_flags(|flags| {
+		flags.set(fuse::OpendirResponseFlag::CACHE_DIR);
+	});
+
+	assert_eq!(
+		format!("{:#?}", resp),
+		concat!(
+			"OpendirResponse {\n",
+			"    handle: 123,\n",
+			"    flags: OpendirResponseFlags {\n",
+			"        CACHE_DIR,\n",
+			"    },\n",
+			"}",
+		),
+	);
+}
@@ -16,6 +16,7 @@
 
 use core::fmt;
 
+use crate::client;
 use crate::internal::debug;
 use crate::kernel;
 use crate::server;
@@ -111,6 +112,37 @@ impl<'a> server::FuseRequest<'a> for PollRequest<'a> {
 
 */
 
+impl client::CuseRequest for PollRequest<'_> {
+	fn send_to<S: client::io::CuseSocket>(
+		&self,
+		request_sender: client::CuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		self.send(request_sender.inner)
+	}
+}
+
+impl client::FuseRequest for PollRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		self.send(request_sender.inner)
+	}
+}
+
+impl PollRequest<'_> {
+	fn send<S: client::io::Socket>(
+		&self,
+		request_sender: client::RequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.send_1(
+			kernel::fuse_opcode::FUSE_POLL,
+			self.header.nodeid,
+			self.body.as_bytes(),
+		)
+	}
+}
+
 impl fmt::Debug for PollRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("PollRequest")
@@ -124,6 +156,61 @@ impl fmt::Debug for PollRequest<'_> {
 
 // }}}
 
+// PollResponse {{{
+
+/// Response type for `FUSE_POLL`.
+pub struct PollResponse {
+	raw: kernel::fuse_poll_out,
+}
+
+impl PollResponse {
+	/// Creates a new `PollResponse` with the given ready events.
+	#[inline]
+	#[must_use]
+	pub fn new(poll_events: crate::PollEvents) -> PollResponse {
+		Self {
+			raw: new!(kernel::fuse_poll_out {
+				revents: poll_events,
+			}),
+		}
+	}
+
+	/// Returns the ready events.
+	#[inline]
+	#[must_use]
+	pub fn poll_events(&self) -> crate::PollEvents {
+		self.raw.revents
+	}
+}
+
+impl fmt::Debug for PollResponse {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("PollResponse")
+			.field("poll_events", &debug::hex_u32(self.poll_events()))
+			.finish()
+	}
+}
+
+impl server::CuseReply for PollResponse {
+	fn send_to<S: server::CuseSocket>(
+		&self,
+		reply_sender: server::CuseReplySender<'_, S>,
+	) -> Result<(), server::SendError<S::Error>> {
+		reply_sender.inner.send_1(self.raw.as_bytes())
+	}
+}
+
+impl server::FuseReply for PollResponse {
+	fn send_to<S: server::FuseSocket>(
+		&self,
+		reply_sender: server::FuseReplySender<'_, S>,
+	) -> Result<(), server::SendError<S::Error>> {
+		reply_sender.inner.send_1(self.raw.as_bytes())
+	}
+}
+
+// }}}
+
 // PollRequestFlags {{{
 
 /// Optional flags set on [`PollRequest`].
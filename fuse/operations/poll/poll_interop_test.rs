@@ -85,8 +85,8 @@ where
 			return send_reply.err(OsError::NOT_FOUND).unwrap();
 		}
 
-		let mut reply = fuse::kernel::fuse_open_out::new();
-		reply.fh = 10;
+		let mut reply = server::OpenResponse::new();
+		reply.set_handle(10);
 		send_reply.ok(&reply).unwrap();
 	}
 
@@ -109,13 +109,14 @@ where
 		const POLLIN: u32 = libc::POLLIN as u32;
 		const POLLOUT: u32 = libc::POLLOUT as u32;
 
-		let mut reply = fuse::kernel::fuse_poll_out::new();
+		let mut poll_events = 0;
 		if (request.poll_events() & POLLIN) > 0 {
-			reply.revents = POLLIN;
+			poll_events = POLLIN;
 		}
 		if (request.poll_events() & POLLOUT) > 0 {
-			reply.revents = POLLOUT;
+			poll_events = POLLOUT;
 		}
+		let reply = server::PollResponse::new(poll_events);
 		send_reply.ok(&reply).unwrap();
 	}
 
@@ -15,9 +15,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use core::fmt;
+use core::mem::size_of;
 
+use crate::client;
 use crate::internal::compat;
-use crate::internal::debug;
 use crate::kernel;
 use crate::server;
 use crate::server::decode;
@@ -67,9 +68,9 @@ impl ReadRequest<'_> {
 	#[must_use]
 	pub fn open_flags(&self) -> crate::OpenFlags {
 		if let Some(body) = self.body.as_v7p9() {
-			return body.flags;
+			return crate::OpenFlags::new(body.flags);
 		}
-		0
+		crate::OpenFlags::new(0)
 	}
 }
 
@@ -109,6 +110,45 @@ impl<'a> ReadRequest<'a> {
 	}
 }
 
+impl client::CuseRequest for ReadRequest<'_> {
+	fn send_to<S: client::io::CuseSocket>(
+		&self,
+		request_sender: client::CuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let version_minor = request_sender.layout.version_minor();
+		self.send(request_sender.inner, version_minor)
+	}
+}
+
+impl client::FuseRequest for ReadRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let version_minor = request_sender.layout.version_minor();
+		self.send(request_sender.inner, version_minor)
+	}
+}
+
+impl ReadRequest<'_> {
+	fn send<S: client::io::Socket>(
+		&self,
+		request_sender: client::RequestSender<'_, S>,
+		version_minor: u32,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let body = self.body.to_v7p9();
+		let mut body_bytes = body.as_bytes();
+		if version_minor < 9 {
+			body_bytes = &body_bytes[..size_of::<compat::fuse_read_in_v7p1>()];
+		}
+		request_sender.send_1(
+			kernel::fuse_opcode::FUSE_READ,
+			self.header.nodeid,
+			body_bytes,
+		)
+	}
+}
+
 impl fmt::Debug for ReadRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("ReadRequest")
@@ -117,7 +157,7 @@ impl fmt::Debug for ReadRequest<'_> {
 			.field("offset", &self.offset())
 			.field("handle", &self.handle())
 			.field("lock_owner", &format_args!("{:?}", &self.lock_owner()))
-			.field("open_flags", &debug::hex_u32(self.open_flags()))
+			.field("open_flags", &self.open_flags())
 			.finish()
 	}
 }
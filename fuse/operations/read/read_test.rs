@@ -18,7 +18,7 @@ use fuse::kernel;
 use fuse::server::ReadRequest;
 
 use fuse_testutil as testutil;
-use fuse_testutil::{decode_request, MessageBuilder};
+use fuse_testutil::{decode_request, encode_request, MessageBuilder};
 
 const DUMMY_READ_FLAG: u32 = 0x80000000;
 
@@ -43,7 +43,12 @@ fn request_v7p1() {
 	assert_eq!(req.offset(), 45);
 	assert_eq!(req.size(), 12);
 	assert_eq!(req.lock_owner(), None);
-	assert_eq!(req.open_flags(), 0);
+	assert_eq!(req.open_flags().get(), 0);
+
+	let encoded = encode_request!(req, {
+		protocol_version: (7, 1),
+	});
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
 }
 
 #[test]
@@ -69,7 +74,12 @@ fn request_v7p9() {
 	assert_eq!(req.offset(), 45);
 	assert_eq!(req.size(), 12);
 	assert_eq!(req.lock_owner(), None);
-	assert_eq!(req.open_flags(), 67);
+	assert_eq!(req.open_flags().get(), 67);
+
+	let encoded = encode_request!(req, {
+		protocol_version: (7, 9),
+	});
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
 }
 
 #[test]
@@ -124,3 +134,38 @@ fn request_impl_debug() {
 		),
 	);
 }
+
+#[test]
+fn request_encode_compat() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_READ;
+			h.nodeid = 123;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_read_in {
+			fh: 123,
+			offset: 45,
+			size: 12,
+			flags: 67,
+		}))
+		.build_aligned();
+
+	let req = decode_request!(ReadRequest, buf, {
+		protocol_version: (7, 9),
+	});
+
+	let encoded = encode_request!(req, {
+		protocol_version: (7, 1),
+	});
+	let expect = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_READ;
+			h.nodeid = 123;
+		})
+		.push_sized(&123u64) // fuse_read_in::fh
+		.push_sized(&45u64) // fuse_read_in::offset
+		.push_sized(&12u32) // fuse_read_in::size
+		.push_sized(&0u32) // fuse_read_in::padding
+		.build();
+	assert_eq!(encoded, expect);
+}
@@ -16,10 +16,13 @@
 
 use core::convert::TryFrom;
 use core::fmt;
+use core::mem::{size_of, MaybeUninit};
 use core::num;
+use core::ptr;
+use core::slice;
 
+use crate::client;
 use crate::internal::compat;
-use crate::internal::debug;
 use crate::internal::dirent;
 use crate::kernel;
 use crate::server;
@@ -61,9 +64,9 @@ impl ReaddirRequest<'_> {
 	#[must_use]
 	pub fn open_flags(&self) -> crate::OpenFlags {
 		if let Some(body) = self.body.as_v7p9() {
-			return body.flags;
+			return crate::OpenFlags::new(body.flags);
 		}
-		0
+		crate::OpenFlags::new(0)
 	}
 }
 
@@ -86,6 +89,24 @@ try_from_fuse_request!(ReaddirRequest<'a>, |request| {
 	Ok(Self { header, body })
 });
 
+impl client::FuseRequest for ReaddirRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let body = self.body.to_v7p9();
+		let mut body_bytes = body.as_bytes();
+		if request_sender.layout.version_minor() < 9 {
+			body_bytes = &body_bytes[..size_of::<compat::fuse_read_in_v7p1>()];
+		}
+		request_sender.inner.send_1(
+			kernel::fuse_opcode::FUSE_READDIR,
+			self.header.nodeid,
+			body_bytes,
+		)
+	}
+}
+
 impl fmt::Debug for ReaddirRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("ReaddirRequest")
@@ -93,7 +114,7 @@ impl fmt::Debug for ReaddirRequest<'_> {
 			.field("size", &self.size())
 			.field("offset", &format_args!("{:?}", self.offset()))
 			.field("handle", &self.handle())
-			.field("open_flags", &debug::hex_u32(self.open_flags()))
+			.field("open_flags", &self.open_flags())
 			.finish()
 	}
 }
@@ -127,6 +148,34 @@ impl<'a> ReaddirEntry<'a> {
 		}
 	}
 
+	/// Creates a new `ReaddirEntry` for the `"."` entry of the directory
+	/// with the given node ID.
+	#[inline]
+	#[must_use]
+	pub fn dot(
+		node_id: crate::NodeId,
+		offset: num::NonZeroU64,
+	) -> ReaddirEntry<'static> {
+		let name = crate::NodeName::DOT;
+		let mut entry = ReaddirEntry::new(node_id, name, offset);
+		entry.set_file_type(crate::FileType::Directory);
+		entry
+	}
+
+	/// Creates a new `ReaddirEntry` for the `".."` entry of a directory
+	/// whose parent has the given node ID.
+	#[inline]
+	#[must_use]
+	pub fn dot_dot(
+		parent_id: crate::NodeId,
+		offset: num::NonZeroU64,
+	) -> ReaddirEntry<'static> {
+		let name = crate::NodeName::DOT_DOT;
+		let mut entry = ReaddirEntry::new(parent_id, name, offset);
+		entry.set_file_type(crate::FileType::Directory);
+		entry
+	}
+
 	#[inline]
 	#[must_use]
 	pub fn node_id(&self) -> crate::NodeId {
@@ -135,7 +184,7 @@ impl<'a> ReaddirEntry<'a> {
 
 	#[inline]
 	#[must_use]
-	pub fn name(&self) -> &crate::NodeName {
+	pub fn name(&self) -> &'a crate::NodeName {
 		self.name
 	}
 
@@ -178,11 +227,46 @@ pub struct ReaddirEntries<'a> {
 }
 
 impl<'a> ReaddirEntries<'a> {
+	/// Parses a block of encoded entries, such as the reply to a
+	/// `FUSE_READDIR` request.
+	///
+	/// The parsed entries borrow their names from `buf`.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the block isn't a sequence of well-formed entries
+	/// with non-zero node IDs and strictly increasing offsets.
+	pub fn parse(
+		buf: &'a [u8],
+	) -> Result<ReaddirEntries<'a>, ReaddirEncodedError> {
+		if buf.len() & 7 != 0 {
+			return Err(ReaddirEncodedError::Misaligned);
+		}
+		validate_encoded(buf)?;
+		Ok(Self { buf })
+	}
+
 	#[inline]
 	#[must_use]
 	pub fn as_bytes(&self) -> &'a [u8] {
 		self.buf
 	}
+
+	/// Returns an iterator over the entries.
+	#[inline]
+	#[must_use]
+	pub fn iter(&self) -> ReaddirEntriesIter<'a> {
+		ReaddirEntriesIter::new(self)
+	}
+}
+
+impl<'a> IntoIterator for ReaddirEntries<'a> {
+	type Item = ReaddirEntry<'a>;
+	type IntoIter = ReaddirEntriesIter<'a>;
+
+	fn into_iter(self) -> ReaddirEntriesIter<'a> {
+		ReaddirEntriesIter::new(&self)
+	}
 }
 
 impl fmt::Debug for ReaddirEntries<'_> {
@@ -202,6 +286,55 @@ impl server::FuseReply for ReaddirEntries<'_> {
 	}
 }
 
+/// Errors that may be returned by [`ReaddirEntries::parse`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ReaddirEncodedError {
+	/// The length of the encoded entries isn't a multiple of 8 bytes.
+	Misaligned,
+
+	/// An encoded entry is truncated, has an invalid name, or has a node ID
+	/// of zero.
+	InvalidEntry,
+
+	/// An entry's offset isn't greater than the offset of the entry that
+	/// precedes it.
+	OffsetNotIncreasing,
+}
+
+fn validate_encoded(mut buf: &[u8]) -> Result<(), ReaddirEncodedError> {
+	use kernel::fuse_dirent as T;
+	const HEADER_LEN: usize = size_of::<T>();
+
+	let mut last_offset = 0;
+	while !buf.is_empty() {
+		if buf.len() < HEADER_LEN {
+			return Err(ReaddirEncodedError::InvalidEntry);
+		}
+		let header: T = unsafe { ptr::read_unaligned(buf.as_ptr().cast()) };
+		if header.ino == 0 {
+			return Err(ReaddirEncodedError::InvalidEntry);
+		}
+		let name_len = header.namelen as usize;
+		let name = buf[HEADER_LEN..]
+			.get(..name_len)
+			.ok_or(ReaddirEncodedError::InvalidEntry)?;
+		let name = crate::NodeName::from_bytes(name)
+			.map_err(|_| ReaddirEncodedError::InvalidEntry)?;
+		let entry_size = dirent::entry_size::<T>(name);
+		if entry_size > buf.len() {
+			return Err(ReaddirEncodedError::InvalidEntry);
+		}
+
+		if header.off <= last_offset {
+			return Err(ReaddirEncodedError::OffsetNotIncreasing);
+		}
+		last_offset = header.off;
+		buf = &buf[entry_size..];
+	}
+	Ok(())
+}
+
 // }}}
 
 // ReaddirEntriesWriter {{{
@@ -211,14 +344,34 @@ impl server::FuseReply for ReaddirEntries<'_> {
 pub struct ReaddirCapacityError {}
 
 pub struct ReaddirEntriesWriter<'a> {
-	buf: &'a mut [u8],
+	buf: &'a mut [MaybeUninit<u8>],
 	position: usize,
 }
 
 impl<'a> ReaddirEntriesWriter<'a> {
 	#[inline]
 	#[must_use]
-	pub fn new(mut buf: &'a mut [u8]) -> ReaddirEntriesWriter<'a> {
+	pub fn new(buf: &'a mut [u8]) -> ReaddirEntriesWriter<'a> {
+		// The writer only ever stores initialized bytes into `buf`,
+		// so the caller's view of it remains valid.
+		let buf = unsafe {
+			&mut *(buf as *mut [u8] as *mut [MaybeUninit<u8>])
+		};
+		Self::new_uninit(buf)
+	}
+
+	/// Creates a writer that encodes entries into uninitialized memory.
+	///
+	/// This avoids zero-filling the buffer before each reply, so a server
+	/// may keep a single buffer (for example on the stack, or in the spare
+	/// capacity of a reused `Vec`) and encode entries directly into it.
+	/// The returned entries borrow only the portion of `buf` that has been
+	/// written.
+	#[inline]
+	#[must_use]
+	pub fn new_uninit(
+		mut buf: &'a mut [MaybeUninit<u8>],
+	) -> ReaddirEntriesWriter<'a> {
 		let max_len = usize::from(u16::MAX);
 		if buf.len() > max_len {
 			buf = &mut buf[..max_len];
@@ -242,7 +395,12 @@ impl<'a> ReaddirEntriesWriter<'a> {
 	#[must_use]
 	pub fn into_entries(self) -> ReaddirEntries<'a> {
 		ReaddirEntries {
-			buf: unsafe { self.buf.get_unchecked(..self.position) },
+			buf: unsafe {
+				slice::from_raw_parts(
+					self.buf.as_ptr().cast::<u8>(),
+					self.position,
+				)
+			},
 		}
 	}
 
@@ -275,9 +433,189 @@ impl<'a> ReaddirEntriesWriter<'a> {
 
 // }}}
 
+// DirCookie {{{
+
+/// An opaque directory position, as stored in a directory entry's offset.
+///
+/// The kernel returns the offset of the last entry it consumed in the next
+/// [`ReaddirRequest`], and userspace may save and restore offsets with
+/// `telldir()` and `seekdir()`. A `DirCookie` therefore identifies the
+/// position just after the entry it was attached to.
+///
+/// Cookies are created by a [`ReaddirCursor`].
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DirCookie {
+	raw: num::NonZeroU64,
+}
+
+impl DirCookie {
+	/// Creates a `DirCookie` from a raw directory entry offset.
+	#[inline]
+	#[must_use]
+	pub const fn new(raw: num::NonZeroU64) -> DirCookie {
+		Self { raw }
+	}
+
+	/// Returns the raw directory entry offset.
+	#[inline]
+	#[must_use]
+	pub const fn get(self) -> num::NonZeroU64 {
+		self.raw
+	}
+
+	#[inline]
+	#[must_use]
+	const fn generation(self) -> u32 {
+		(self.raw.get() >> 32) as u32
+	}
+
+	#[inline]
+	#[must_use]
+	const fn position(self) -> u32 {
+		self.raw.get() as u32
+	}
+}
+
+impl fmt::Debug for DirCookie {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("DirCookie")
+			.field("generation", &self.generation())
+			.field("position", &self.position())
+			.finish()
+	}
+}
+
+impl From<DirCookie> for num::NonZeroU64 {
+	fn from(cookie: DirCookie) -> num::NonZeroU64 {
+		cookie.raw
+	}
+}
+
+/// Errors that may occur when resuming a [`ReaddirCursor`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[non_exhaustive]
+pub enum DirCookieError {
+	/// The cookie was issued for a different generation of the directory.
+	Stale,
+}
+
+// }}}
+
+// ReaddirCursor {{{
+
+/// Maps directory positions to [`DirCookie`] offsets.
+///
+/// A directory position is the index of the next entry to be returned, in
+/// whatever stable order the server lists the directory. Each cookie also
+/// carries a server-chosen generation number, so that a server can detect
+/// offsets that were issued before the directory was modified and may no
+/// longer refer to the same entry.
+///
+/// Cookies issued with generation zero are equal to `position`, which keeps
+/// offsets small for directories that never change.
+///
+/// # Examples
+///
+/// ```rust
+/// # use fuse::server::{ReaddirCursor, ReaddirEntry, ReaddirRequest};
+/// # fn f(request: &ReaddirRequest, names: &[&fuse::NodeName], generation: u32) {
+/// let mut cursor = match ReaddirCursor::seek(generation, request.offset()) {
+/// 	Ok(cursor) => cursor,
+/// 	Err(_) => ReaddirCursor::new(generation), // or reply with an error
+/// };
+/// for name in names.iter().skip(cursor.position() as usize) {
+/// 	let node_id = fuse::NodeId::new(100).unwrap();
+/// 	let entry = ReaddirEntry::new(node_id, name, cursor.advance().get());
+/// 	// ... push `entry` into a ReaddirEntriesWriter ...
+/// }
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ReaddirCursor {
+	generation: u32,
+	position: u32,
+}
+
+impl ReaddirCursor {
+	/// Creates a cursor at the start of a directory.
+	#[inline]
+	#[must_use]
+	pub const fn new(generation: u32) -> ReaddirCursor {
+		Self {
+			generation,
+			position: 0,
+		}
+	}
+
+	/// Creates a cursor at the position identified by a request offset,
+	/// as by `seekdir()`.
+	///
+	/// An offset of `None` is the start of the directory.
+	///
+	/// # Errors
+	///
+	/// Returns [`DirCookieError::Stale`] if the offset was issued for a
+	/// different `generation`.
+	pub fn seek(
+		generation: u32,
+		offset: Option<num::NonZeroU64>,
+	) -> Result<ReaddirCursor, DirCookieError> {
+		let cookie = match offset {
+			Some(raw) => DirCookie::new(raw),
+			None => return Ok(Self::new(generation)),
+		};
+		if cookie.generation() != generation {
+			return Err(DirCookieError::Stale);
+		}
+		Ok(Self {
+			generation,
+			position: cookie.position(),
+		})
+	}
+
+	/// Returns the generation of the directory.
+	#[inline]
+	#[must_use]
+	pub const fn generation(&self) -> u32 {
+		self.generation
+	}
+
+	/// Returns the index of the next entry to be returned.
+	#[inline]
+	#[must_use]
+	pub const fn position(&self) -> u32 {
+		self.position
+	}
+
+	/// Returns the cookie for the current position, as by `telldir()`.
+	///
+	/// The start of the directory has no cookie.
+	#[inline]
+	#[must_use]
+	pub fn tell(&self) -> Option<DirCookie> {
+		let raw = (u64::from(self.generation) << 32) | u64::from(self.position);
+		num::NonZeroU64::new(raw).map(DirCookie::new)
+	}
+
+	/// Moves past the entry at the current position, returning the cookie
+	/// to store as that entry's offset.
+	///
+	/// The position saturates at `u32::MAX`.
+	#[inline]
+	pub fn advance(&mut self) -> DirCookie {
+		self.position = self.position.saturating_add(1);
+		let raw = (u64::from(self.generation) << 32) | u64::from(self.position);
+		DirCookie::new(unsafe { num::NonZeroU64::new_unchecked(raw) })
+	}
+}
+
+// }}}
+
 // ReaddirEntriesIter {{{
 
-struct ReaddirEntriesIter<'a> {
+/// Iterator over the entries of a [`ReaddirEntries`].
+#[derive(Clone)]
+pub struct ReaddirEntriesIter<'a> {
 	buf: &'a [u8],
 }
 
@@ -308,4 +646,10 @@ impl<'a> Iterator for ReaddirEntriesIter<'a> {
 	}
 }
 
+impl fmt::Debug for ReaddirEntriesIter<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_list().entries(self.clone()).finish()
+	}
+}
+
 // }}}
@@ -14,11 +14,16 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
-use core::mem::size_of;
+use core::mem::{size_of, MaybeUninit};
 use core::num;
 
 use fuse::kernel;
 use fuse::server::{
+	DirCookie,
+	DirCookieError,
+	ReaddirCursor,
+	ReaddirEncodedError,
+	ReaddirEntries,
 	ReaddirEntriesWriter,
 	ReaddirEntry,
 	ReaddirRequest,
@@ -70,7 +75,7 @@ fn readdir_request_v7p9() {
 
 	assert_eq!(req.handle(), 123);
 	assert_eq!(req.offset(), num::NonZeroU64::new(45));
-	assert_eq!(req.open_flags(), 67);
+	assert_eq!(req.open_flags().get(), 67);
 	assert_eq!(req.size(), 4096);
 }
 
@@ -155,6 +160,78 @@ fn readdir_entries() {
 	);
 }
 
+#[test]
+fn readdir_entries_dots() {
+	let mut buf = vec![0u8; 1024];
+	let mut writer = ReaddirEntriesWriter::new(&mut buf);
+
+	let node_id = fuse::NodeId::new(100).unwrap();
+	let parent_id = fuse::NodeId::new(200).unwrap();
+	let dot = ReaddirEntry::dot(node_id, num::NonZeroU64::new(1).unwrap());
+	let dot_dot =
+		ReaddirEntry::dot_dot(parent_id, num::NonZeroU64::new(2).unwrap());
+	assert_eq!(dot.name(), ".");
+	assert_eq!(dot_dot.name(), "..");
+	assert!(writer.try_push(&dot).is_ok());
+	assert!(writer.try_push(&dot_dot).is_ok());
+
+	let entries = writer.into_entries();
+
+	assert_eq!(
+		entries.as_bytes(),
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_dirent {
+				ino: 100,
+				off: 1,
+				namelen: 1,
+				r#type: 4,
+			}))
+			.push_bytes(b".\0\0\0\0\0\0\0")
+			.push_sized(&testutil::new!(kernel::fuse_dirent {
+				ino: 200,
+				off: 2,
+				namelen: 2,
+				r#type: 4,
+			}))
+			.push_bytes(b"..\0\0\0\0\0\0")
+			.build()
+	);
+}
+
+#[test]
+fn readdir_entries_uninit() {
+	let mut buf = [MaybeUninit::<u8>::uninit(); 1024];
+
+	// The writer doesn't require its buffer to be aligned.
+	let mut writer = ReaddirEntriesWriter::new_uninit(&mut buf[1..]);
+	assert_eq!(writer.capacity(), 1023);
+
+	{
+		let node_id = fuse::NodeId::new(100).unwrap();
+		let name = fuse::NodeName::new("foobar").unwrap();
+		let offset = num::NonZeroU64::new(1).unwrap();
+
+		let mut entry = ReaddirEntry::new(node_id, name, offset);
+		entry.set_file_type(fuse::FileType::Regular);
+		assert!(writer.try_push(&entry).is_ok());
+	}
+
+	let entries = writer.into_entries();
+
+	assert_eq!(
+		entries.as_bytes(),
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_dirent {
+				ino: 100,
+				off: 1,
+				namelen: 6,
+				r#type: 8,
+			}))
+			.push_bytes(b"foobar\0\0")
+			.build()
+	);
+}
+
 #[test]
 fn readdir_entries_debug() {
 	let mut buf = vec![0u8; 1024];
@@ -200,3 +277,130 @@ fn readdir_entries_debug() {
 		),
 	);
 }
+
+fn encoded_dirent(ino: u64, off: u64, name: &[u8]) -> Vec<u8> {
+	let padding = [0u8; 8];
+	MessageBuilder::new()
+		.push_sized(&testutil::new!(kernel::fuse_dirent {
+			ino: ino,
+			off: off,
+			namelen: name.len() as u32,
+			r#type: 8,
+		}))
+		.push_bytes(name)
+		.push_bytes(&padding[..(8 - name.len() % 8) % 8])
+		.build()
+}
+
+#[test]
+fn readdir_entries_parse() {
+	let mut encoded = encoded_dirent(100, 1, b"hello.txt");
+	encoded.extend(encoded_dirent(101, 5, b"world.txt"));
+
+	let entries = ReaddirEntries::parse(&encoded).unwrap();
+	let names: Vec<&[u8]> = entries.iter().map(|entry| {
+		entry.name().as_bytes()
+	}).collect();
+	assert_eq!(names, [&b"hello.txt"[..], b"world.txt"]);
+	// Names are borrowed from the encoded buffer.
+	assert!(encoded.as_ptr_range().contains(&names[0].as_ptr()));
+
+	let entry = entries.into_iter().nth(1).unwrap();
+	assert_eq!(entry.node_id().get(), 101);
+	assert_eq!(entry.offset().get(), 5);
+	assert_eq!(entry.file_type(), Some(fuse::FileType::Regular));
+
+	assert!(ReaddirEntries::parse(b"").unwrap().iter().next().is_none());
+}
+
+#[test]
+fn readdir_entries_parse_invalid() {
+	let encoded = encoded_dirent(100, 1, b"hello.txt");
+
+	assert_eq!(
+		ReaddirEntries::parse(&encoded[..encoded.len() - 1]).unwrap_err(),
+		ReaddirEncodedError::Misaligned,
+	);
+	assert_eq!(
+		ReaddirEntries::parse(&encoded[..encoded.len() - 8]).unwrap_err(),
+		ReaddirEncodedError::InvalidEntry,
+	);
+	assert_eq!(
+		ReaddirEntries::parse(&encoded_dirent(0, 1, b"a")).unwrap_err(),
+		ReaddirEncodedError::InvalidEntry,
+	);
+	assert_eq!(
+		ReaddirEntries::parse(&encoded_dirent(100, 1, b"a/b")).unwrap_err(),
+		ReaddirEncodedError::InvalidEntry,
+	);
+	assert_eq!(
+		ReaddirEntries::parse(&encoded_dirent(100, 1, b"")).unwrap_err(),
+		ReaddirEncodedError::InvalidEntry,
+	);
+
+	// Offsets must be non-zero and strictly increasing.
+	assert_eq!(
+		ReaddirEntries::parse(&encoded_dirent(100, 0, b"a")).unwrap_err(),
+		ReaddirEncodedError::OffsetNotIncreasing,
+	);
+	let mut encoded = encoded_dirent(100, 2, b"a");
+	encoded.extend(encoded_dirent(101, 2, b"b"));
+	assert_eq!(
+		ReaddirEntries::parse(&encoded).unwrap_err(),
+		ReaddirEncodedError::OffsetNotIncreasing,
+	);
+}
+
+#[test]
+fn readdir_cursor() {
+	let mut cursor = ReaddirCursor::new(0);
+	assert_eq!(cursor.position(), 0);
+	assert_eq!(cursor.tell(), None);
+
+	// Generation zero produces cookies equal to the position.
+	let cookie = cursor.advance();
+	assert_eq!(cookie.get().get(), 1);
+	assert_eq!(cursor.position(), 1);
+	assert_eq!(cursor.tell(), Some(cookie));
+
+	let mut cursor = ReaddirCursor::new(7);
+	cursor.advance();
+	let cookie = cursor.advance();
+	assert_eq!(cookie.get().get(), (7 << 32) | 2);
+	assert_eq!(
+		format!("{:?}", cookie),
+		"DirCookie { generation: 7, position: 2 }",
+	);
+}
+
+#[test]
+fn readdir_cursor_seek() {
+	let cursor = ReaddirCursor::seek(7, None).unwrap();
+	assert_eq!(cursor, ReaddirCursor::new(7));
+
+	let mut cursor = ReaddirCursor::new(7);
+	cursor.advance();
+	cursor.advance();
+	let telldir = cursor.tell().unwrap();
+	cursor.advance();
+
+	// seekdir() to a previously returned cookie
+	let cursor = ReaddirCursor::seek(7, Some(telldir.get())).unwrap();
+	assert_eq!(cursor.generation(), 7);
+	assert_eq!(cursor.position(), 2);
+
+	// The directory has since been modified.
+	assert_eq!(
+		ReaddirCursor::seek(8, Some(telldir.get())),
+		Err(DirCookieError::Stale),
+	);
+
+	let offset = num::NonZeroU64::new(3).unwrap();
+	assert_eq!(
+		ReaddirCursor::seek(7, Some(offset)),
+		Err(DirCookieError::Stale),
+	);
+	let cursor = ReaddirCursor::seek(0, Some(offset)).unwrap();
+	assert_eq!(cursor.position(), 3);
+	assert_eq!(cursor.tell(), Some(DirCookie::new(offset)));
+}
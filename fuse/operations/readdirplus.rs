@@ -16,10 +16,13 @@
 
 use core::convert::TryFrom;
 use core::fmt;
+use core::mem::{size_of, MaybeUninit};
 use core::num;
+use core::ptr;
+use core::slice;
 
+use crate::client;
 use crate::internal::compat;
-use crate::internal::debug;
 use crate::internal::dirent;
 use crate::kernel;
 use crate::server::decode;
@@ -60,9 +63,9 @@ impl ReaddirplusRequest<'_> {
 	#[must_use]
 	pub fn open_flags(&self) -> crate::OpenFlags {
 		if let Some(body) = self.body.as_v7p9() {
-			return body.flags;
+			return crate::OpenFlags::new(body.flags);
 		}
-		0
+		crate::OpenFlags::new(0)
 	}
 }
 
@@ -85,6 +88,24 @@ try_from_fuse_request!(ReaddirplusRequest<'a>, |request| {
 	Ok(Self { header, body })
 });
 
+impl client::FuseRequest for ReaddirplusRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let body = self.body.to_v7p9();
+		let mut body_bytes = body.as_bytes();
+		if request_sender.layout.version_minor() < 9 {
+			body_bytes = &body_bytes[..size_of::<compat::fuse_read_in_v7p1>()];
+		}
+		request_sender.inner.send_1(
+			kernel::fuse_opcode::FUSE_READDIRPLUS,
+			self.header.nodeid,
+			body_bytes,
+		)
+	}
+}
+
 impl fmt::Debug for ReaddirplusRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("ReaddirplusRequest")
@@ -92,7 +113,7 @@ impl fmt::Debug for ReaddirplusRequest<'_> {
 			.field("size", &self.size())
 			.field("offset", &format_args!("{:?}", self.offset()))
 			.field("handle", &self.handle())
-			.field("open_flags", &debug::hex_u32(self.open_flags()))
+			.field("open_flags", &self.open_flags())
 			.finish()
 	}
 }
@@ -134,7 +155,7 @@ impl<'a> ReaddirplusEntry<'a> {
 
 	#[inline]
 	#[must_use]
-	pub fn name(&self) -> &crate::NodeName {
+	pub fn name(&self) -> &'a crate::NodeName {
 		self.name
 	}
 
@@ -171,11 +192,63 @@ pub struct ReaddirplusEntries<'a> {
 }
 
 impl<'a> ReaddirplusEntries<'a> {
+	/// Parses a block of encoded entries, such as the reply to a
+	/// `FUSE_READDIRPLUS` request.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the block isn't a sequence of well-formed entries
+	/// with strictly increasing offsets.
+	pub fn parse(
+		buf: &'a [u8],
+	) -> Result<ReaddirplusEntries<'a>, ReaddirplusEncodedError> {
+		if buf.len() & 7 != 0 {
+			return Err(ReaddirplusEncodedError::Misaligned);
+		}
+		validate_encoded(buf, 0)?;
+		Ok(Self { buf })
+	}
+
 	#[inline]
 	#[must_use]
 	pub fn as_bytes(&self) -> &'a [u8] {
 		self.buf
 	}
+
+	/// Returns an iterator over the entries.
+	#[inline]
+	#[must_use]
+	pub fn iter(&self) -> ReaddirplusEntriesIter<'a> {
+		ReaddirplusEntriesIter::new(self)
+	}
+
+	/// Returns the node IDs for which the kernel will increment its lookup
+	/// count when it receives these entries.
+	///
+	/// Each entry of a `FUSE_READDIRPLUS` reply counts as a lookup of its
+	/// node, which the kernel will eventually release with `FUSE_FORGET`.
+	/// The exceptions are entries with a node ID of zero, and the `"."` and
+	/// `".."` entries, which the kernel skips. Filesystems that track lookup
+	/// counts should increment them for each node ID returned here once the
+	/// reply has been sent.
+	///
+	/// A node ID is returned once for each entry that refers to it.
+	#[inline]
+	#[must_use]
+	pub fn lookups(&self) -> ReaddirplusLookups<'a> {
+		ReaddirplusLookups {
+			entries: ReaddirplusEntriesIter::new(self),
+		}
+	}
+}
+
+impl<'a> IntoIterator for ReaddirplusEntries<'a> {
+	type Item = ReaddirplusEntry<'a>;
+	type IntoIter = ReaddirplusEntriesIter<'a>;
+
+	fn into_iter(self) -> ReaddirplusEntriesIter<'a> {
+		ReaddirplusEntriesIter::new(&self)
+	}
 }
 
 impl fmt::Debug for ReaddirplusEntries<'_> {
@@ -194,20 +267,65 @@ impl fmt::Debug for ReaddirplusEntries<'_> {
 #[non_exhaustive]
 pub struct ReaddirplusCapacityError {}
 
+/// Errors that may be returned by
+/// [`ReaddirplusEntriesWriter::try_push_encoded`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ReaddirplusEncodedError {
+	/// The writer doesn't have enough remaining capacity for the entries.
+	Capacity,
+
+	/// The length of the encoded entries isn't a multiple of 8 bytes.
+	Misaligned,
+
+	/// An encoded entry is truncated, has an invalid name, or has a node ID
+	/// of zero.
+	InvalidEntry,
+
+	/// An entry's offset isn't greater than the offset of the entry that
+	/// precedes it.
+	OffsetNotIncreasing,
+}
+
 pub struct ReaddirplusEntriesWriter<'a> {
-	buf: &'a mut [u8],
+	buf: &'a mut [MaybeUninit<u8>],
 	position: usize,
+	last_offset: u64,
 }
 
 impl<'a> ReaddirplusEntriesWriter<'a> {
 	#[inline]
 	#[must_use]
-	pub fn new(mut buf: &'a mut [u8]) -> ReaddirplusEntriesWriter<'a> {
+	pub fn new(buf: &'a mut [u8]) -> ReaddirplusEntriesWriter<'a> {
+		// The writer only ever stores initialized bytes into `buf`,
+		// so the caller's view of it remains valid.
+		let buf = unsafe {
+			&mut *(buf as *mut [u8] as *mut [MaybeUninit<u8>])
+		};
+		Self::new_uninit(buf)
+	}
+
+	/// Creates a writer that encodes entries into uninitialized memory.
+	///
+	/// This avoids zero-filling the buffer before each reply, so a server
+	/// may keep a single buffer (for example on the stack, or in the spare
+	/// capacity of a reused `Vec`) and encode entries directly into it.
+	/// The returned entries borrow only the portion of `buf` that has been
+	/// written.
+	#[inline]
+	#[must_use]
+	pub fn new_uninit(
+		mut buf: &'a mut [MaybeUninit<u8>],
+	) -> ReaddirplusEntriesWriter<'a> {
 		let max_len = usize::from(u16::MAX);
 		if buf.len() > max_len {
 			buf = &mut buf[..max_len];
 		}
-		Self { buf, position: 0 }
+		Self {
+			buf,
+			position: 0,
+			last_offset: 0,
+		}
 	}
 
 	#[inline]
@@ -226,7 +344,12 @@ impl<'a> ReaddirplusEntriesWriter<'a> {
 	#[must_use]
 	pub fn into_entries(self) -> ReaddirplusEntries<'a> {
 		ReaddirplusEntries {
-			buf: unsafe { self.buf.get_unchecked(..self.position) },
+			buf: unsafe {
+				slice::from_raw_parts(
+					self.buf.as_ptr().cast::<u8>(),
+					self.position,
+				)
+			},
 		}
 	}
 
@@ -253,15 +376,128 @@ impl<'a> ReaddirplusEntriesWriter<'a> {
 			let dst = self.buf.get_unchecked_mut(entry_start..self.position);
 			dirent::write_unchecked(entry.dirent, entry.name, dst);
 		};
+		self.last_offset = entry.dirent.dirent.off;
 		Ok(())
 	}
+
+	/// Appends a block of pre-encoded entries, such as the bytes of a
+	/// [`ReaddirplusEntries`] retained by a directory listing cache.
+	///
+	/// The block is validated before being copied into the writer's buffer.
+	/// Each entry must be well-formed, and entry offsets must be strictly
+	/// increasing, both within the block and relative to the last entry
+	/// previously pushed to this writer.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the block is invalid, or if it doesn't fit in the
+	/// writer's remaining capacity. On error, the writer is unchanged.
+	pub fn try_push_encoded(
+		&mut self,
+		encoded: &[u8],
+	) -> Result<(), ReaddirplusEncodedError> {
+		if encoded.len() & 7 != 0 {
+			return Err(ReaddirplusEncodedError::Misaligned);
+		}
+		let remaining_capacity = self.capacity() - self.position();
+		if encoded.len() > remaining_capacity {
+			return Err(ReaddirplusEncodedError::Capacity);
+		}
+		let last_offset = validate_encoded(encoded, self.last_offset)?;
+
+		let entry_start = self.position;
+		self.position += encoded.len();
+		unsafe {
+			ptr::copy_nonoverlapping(
+				encoded.as_ptr(),
+				self.buf.as_mut_ptr().add(entry_start).cast::<u8>(),
+				encoded.len(),
+			);
+		}
+		self.last_offset = last_offset;
+		Ok(())
+	}
+}
+
+fn validate_encoded(
+	mut buf: &[u8],
+	mut last_offset: u64,
+) -> Result<u64, ReaddirplusEncodedError> {
+	use kernel::fuse_direntplus as T;
+	const HEADER_LEN: usize = size_of::<T>();
+
+	while !buf.is_empty() {
+		if buf.len() < HEADER_LEN {
+			return Err(ReaddirplusEncodedError::InvalidEntry);
+		}
+		let header: T = unsafe { ptr::read_unaligned(buf.as_ptr().cast()) };
+		if header.dirent.ino == 0 || header.entry_out.attr.ino == 0 {
+			return Err(ReaddirplusEncodedError::InvalidEntry);
+		}
+		let name_len = header.dirent.namelen as usize;
+		let name = buf[HEADER_LEN..]
+			.get(..name_len)
+			.ok_or(ReaddirplusEncodedError::InvalidEntry)?;
+		let name = crate::NodeName::from_bytes(name)
+			.map_err(|_| ReaddirplusEncodedError::InvalidEntry)?;
+		let entry_size = dirent::entry_size::<T>(name);
+		if entry_size > buf.len() {
+			return Err(ReaddirplusEncodedError::InvalidEntry);
+		}
+
+		let offset = header.dirent.off;
+		if offset <= last_offset {
+			return Err(ReaddirplusEncodedError::OffsetNotIncreasing);
+		}
+		last_offset = offset;
+		buf = &buf[entry_size..];
+	}
+	Ok(last_offset)
+}
+
+// }}}
+
+// ReaddirplusLookups {{{
+
+/// Iterator over the node IDs looked up by a [`ReaddirplusEntries`].
+///
+/// See [`ReaddirplusEntries::lookups`] for details.
+#[derive(Clone)]
+pub struct ReaddirplusLookups<'a> {
+	entries: ReaddirplusEntriesIter<'a>,
+}
+
+impl Iterator for ReaddirplusLookups<'_> {
+	type Item = crate::NodeId;
+
+	fn next(&mut self) -> Option<crate::NodeId> {
+		for entry in &mut self.entries {
+			let name = entry.name.as_bytes();
+			if name == b"." || name == b".." {
+				continue;
+			}
+			let node_id = entry.dirent.entry_out.nodeid;
+			if let Some(node_id) = crate::NodeId::new(node_id) {
+				return Some(node_id);
+			}
+		}
+		None
+	}
+}
+
+impl fmt::Debug for ReaddirplusLookups<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_list().entries(self.clone()).finish()
+	}
 }
 
 // }}}
 
 // ReaddirplusEntriesIter {{{
 
-struct ReaddirplusEntriesIter<'a> {
+/// Iterator over the entries of a [`ReaddirplusEntries`].
+#[derive(Clone)]
+pub struct ReaddirplusEntriesIter<'a> {
 	buf: &'a [u8],
 }
 
@@ -292,4 +528,10 @@ impl<'a> Iterator for ReaddirplusEntriesIter<'a> {
 	}
 }
 
+impl fmt::Debug for ReaddirplusEntriesIter<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_list().entries(self.clone()).finish()
+	}
+}
+
 // }}}
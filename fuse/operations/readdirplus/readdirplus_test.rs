@@ -0,0 +1,230 @@
+// Copyright 2022 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::mem::{size_of, MaybeUninit};
+use core::num;
+
+use fuse::kernel;
+use fuse::server::{
+	ReaddirplusEncodedError,
+	ReaddirplusEntries,
+	ReaddirplusEntriesWriter,
+	ReaddirplusEntry,
+};
+
+fn push_entry(
+	writer: &mut ReaddirplusEntriesWriter,
+	node_id: u64,
+	name: &str,
+	offset: u64,
+) {
+	let node_id = fuse::NodeId::new(node_id).unwrap();
+	let name = fuse::NodeName::new(name).unwrap();
+	let offset = num::NonZeroU64::new(offset).unwrap();
+
+	let mut attr = fuse::NodeAttr::new(node_id);
+	attr.set_mode(fuse::FileMode::S_IFREG | 0o644);
+	let entry = ReaddirplusEntry::new(name, offset, fuse::Entry::new(attr));
+	writer.try_push(&entry).unwrap();
+}
+
+fn encoded_entries(entries: &[(u64, &str, u64)]) -> Vec<u8> {
+	let mut buf = vec![0u8; 4096];
+	let mut writer = ReaddirplusEntriesWriter::new(&mut buf);
+	for &(node_id, name, offset) in entries {
+		push_entry(&mut writer, node_id, name, offset);
+	}
+	writer.into_entries().as_bytes().to_vec()
+}
+
+#[test]
+fn push_encoded() {
+	let cached = encoded_entries(&[
+		(100, "hello.txt", 2),
+		(101, "world.txt", 3),
+	]);
+
+	let mut buf = vec![0u8; 4096];
+	let mut writer = ReaddirplusEntriesWriter::new(&mut buf);
+	push_entry(&mut writer, 99, "first.txt", 1);
+	assert!(writer.try_push_encoded(&cached).is_ok());
+	assert!(writer.try_push_encoded(&[]).is_ok());
+
+	let expect = encoded_entries(&[
+		(99, "first.txt", 1),
+		(100, "hello.txt", 2),
+		(101, "world.txt", 3),
+	]);
+	assert_eq!(writer.into_entries().as_bytes(), expect);
+}
+
+#[test]
+fn writer_uninit() {
+	let mut buf = [MaybeUninit::<u8>::uninit(); 4096];
+
+	// The writer doesn't require its buffer to be aligned.
+	let mut writer = ReaddirplusEntriesWriter::new_uninit(&mut buf[1..]);
+	assert_eq!(writer.capacity(), 4095);
+	push_entry(&mut writer, 100, "hello.txt", 1);
+	push_entry(&mut writer, 101, "world.txt", 2);
+
+	let expect = encoded_entries(&[
+		(100, "hello.txt", 1),
+		(101, "world.txt", 2),
+	]);
+	assert_eq!(writer.into_entries().as_bytes(), expect);
+}
+
+#[test]
+fn push_encoded_capacity() {
+	let cached = encoded_entries(&[
+		(100, "hello.txt", 1),
+		(101, "world.txt", 2),
+	]);
+
+	let mut buf = vec![0u8; cached.len() - 8];
+	let mut writer = ReaddirplusEntriesWriter::new(&mut buf);
+	assert_eq!(
+		writer.try_push_encoded(&cached),
+		Err(ReaddirplusEncodedError::Capacity),
+	);
+	assert_eq!(writer.position(), 0);
+}
+
+#[test]
+fn push_encoded_misaligned() {
+	let cached = encoded_entries(&[(100, "hello.txt", 1)]);
+
+	let mut buf = vec![0u8; 4096];
+	let mut writer = ReaddirplusEntriesWriter::new(&mut buf);
+	assert_eq!(
+		writer.try_push_encoded(&cached[..cached.len() - 1]),
+		Err(ReaddirplusEncodedError::Misaligned),
+	);
+	assert_eq!(writer.position(), 0);
+}
+
+#[test]
+fn push_encoded_invalid_entry() {
+	let cached = encoded_entries(&[(100, "hello.txt", 1)]);
+	let mut buf = vec![0u8; 4096];
+	let mut writer = ReaddirplusEntriesWriter::new(&mut buf);
+
+	// Truncated name.
+	let truncated = &cached[..size_of::<kernel::fuse_direntplus>() + 8];
+	assert_eq!(
+		writer.try_push_encoded(truncated),
+		Err(ReaddirplusEncodedError::InvalidEntry),
+	);
+
+	// Name containing a slash.
+	let mut bad_name = cached.clone();
+	bad_name[size_of::<kernel::fuse_direntplus>()] = b'/';
+	assert_eq!(
+		writer.try_push_encoded(&bad_name),
+		Err(ReaddirplusEncodedError::InvalidEntry),
+	);
+
+	assert_eq!(writer.position(), 0);
+}
+
+#[test]
+fn push_encoded_offset_not_increasing() {
+	let mut buf = vec![0u8; 4096];
+	let mut writer = ReaddirplusEntriesWriter::new(&mut buf);
+
+	// Offsets must increase within the block.
+	let cached = encoded_entries(&[
+		(100, "hello.txt", 2),
+		(101, "world.txt", 2),
+	]);
+	assert_eq!(
+		writer.try_push_encoded(&cached),
+		Err(ReaddirplusEncodedError::OffsetNotIncreasing),
+	);
+	assert_eq!(writer.position(), 0);
+
+	// Offsets must increase relative to previously pushed entries.
+	let cached = encoded_entries(&[(100, "hello.txt", 2)]);
+	push_entry(&mut writer, 99, "first.txt", 5);
+	let position = writer.position();
+	assert_eq!(
+		writer.try_push_encoded(&cached),
+		Err(ReaddirplusEncodedError::OffsetNotIncreasing),
+	);
+	assert_eq!(writer.position(), position);
+}
+
+#[test]
+fn parse() {
+	let encoded = encoded_entries(&[
+		(100, "hello.txt", 2),
+		(101, "world.txt", 3),
+	]);
+	let entries = ReaddirplusEntries::parse(&encoded).unwrap();
+	assert_eq!(entries.as_bytes(), encoded);
+
+	let names: Vec<&[u8]> = entries.iter().map(|entry| {
+		entry.name().as_bytes()
+	}).collect();
+	assert_eq!(names, [&b"hello.txt"[..], b"world.txt"]);
+	let offsets: Vec<u64> = entries.into_iter().map(|entry| {
+		entry.offset().get()
+	}).collect();
+	assert_eq!(offsets, [2, 3]);
+
+	// Entries must have a non-zero inode number.
+	let mut zero_ino = encoded.clone();
+	let dirent_offset = size_of::<kernel::fuse_entry_out>();
+	zero_ino[dirent_offset..dirent_offset + 8].copy_from_slice(&[0; 8]);
+	assert_eq!(
+		ReaddirplusEntries::parse(&zero_ino).unwrap_err(),
+		ReaddirplusEncodedError::InvalidEntry,
+	);
+
+	assert_eq!(
+		ReaddirplusEntries::parse(&encoded[..8]).unwrap_err(),
+		ReaddirplusEncodedError::InvalidEntry,
+	);
+	assert_eq!(
+		ReaddirplusEntries::parse(&encoded[..9]).unwrap_err(),
+		ReaddirplusEncodedError::Misaligned,
+	);
+}
+
+#[test]
+fn lookups() {
+	let mut buf = vec![0u8; 4096];
+	let mut writer = ReaddirplusEntriesWriter::new(&mut buf);
+	push_entry(&mut writer, 10, ".", 1);
+	push_entry(&mut writer, 1, "..", 2);
+	push_entry(&mut writer, 100, "hello.txt", 3);
+	push_entry(&mut writer, 101, "world.txt", 4);
+	push_entry(&mut writer, 100, "hello-link.txt", 5);
+	push_entry(&mut writer, 102, "...", 6);
+
+	// Entries with a node ID of zero aren't looked up.
+	let mut zero_node_id = encoded_entries(&[(103, "zero.txt", 7)]);
+	zero_node_id[..8].copy_from_slice(&[0; 8]);
+	writer.try_push_encoded(&zero_node_id).unwrap();
+
+	let entries = writer.into_entries();
+
+	let node_ids: Vec<u64> = entries.lookups().map(|id| id.get()).collect();
+	assert_eq!(node_ids, &[100, 101, 100, 102]);
+
+	assert_eq!(format!("{:?}", entries.lookups()), "[100, 101, 100, 102]");
+}
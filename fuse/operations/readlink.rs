@@ -17,7 +17,10 @@
 use core::fmt;
 use core::marker::PhantomData;
 
+use crate::client;
+use crate::internal::debug;
 use crate::kernel;
+use crate::server;
 use crate::server::decode;
 
 // ReadlinkRequest {{{
@@ -45,6 +48,18 @@ try_from_fuse_request!(ReadlinkRequest<'a>, |request| {
 	})
 });
 
+impl client::FuseRequest for ReadlinkRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.inner.send_0(
+			kernel::fuse_opcode::FUSE_READLINK,
+			self.node_id.get(),
+		)
+	}
+}
+
 impl fmt::Debug for ReadlinkRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("ReadlinkRequest")
@@ -54,3 +69,89 @@ impl fmt::Debug for ReadlinkRequest<'_> {
 }
 
 // }}}
+
+// ReadlinkResponse {{{
+
+/// Response type for `FUSE_READLINK`.
+#[derive(Clone, Copy)]
+pub struct ReadlinkResponse<'a> {
+	target: &'a [u8],
+}
+
+impl<'a> ReadlinkResponse<'a> {
+	/// The maximum length of a symlink target, in bytes.
+	///
+	/// The client reads the target into a single buffer of `PATH_MAX` bytes,
+	/// which must also hold a terminating `NUL`.
+	pub const MAX_TARGET_LEN: usize = 4095;
+
+	/// Creates a new `ReadlinkResponse` with the given target.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the target is empty, contains `NUL`, or is longer
+	/// than [`MAX_TARGET_LEN`](Self::MAX_TARGET_LEN).
+	pub fn new(target: &'a [u8]) -> Result<Self, ReadlinkTargetError> {
+		if target.is_empty() {
+			return Err(ReadlinkTargetError::Empty);
+		}
+		if target.len() > Self::MAX_TARGET_LEN {
+			return Err(ReadlinkTargetError::TooLong);
+		}
+		if target.contains(&0) {
+			return Err(ReadlinkTargetError::ContainsNul);
+		}
+		Ok(Self { target })
+	}
+
+	/// Creates a new `ReadlinkResponse` that targets a node in the same
+	/// directory as the symlink.
+	#[must_use]
+	pub fn from_node_name(target: &'a crate::NodeName) -> Self {
+		Self {
+			target: target.as_bytes(),
+		}
+	}
+
+	/// Returns the symlink target.
+	#[inline]
+	#[must_use]
+	pub fn target(&self) -> &'a [u8] {
+		self.target
+	}
+}
+
+impl fmt::Debug for ReadlinkResponse<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("ReadlinkResponse")
+			.field("target", &debug::bytes(self.target))
+			.finish()
+	}
+}
+
+impl server::FuseReply for ReadlinkResponse<'_> {
+	fn send_to<S: server::FuseSocket>(
+		&self,
+		reply_sender: server::FuseReplySender<'_, S>,
+	) -> Result<(), server::SendError<S::Error>> {
+		reply_sender.inner.send_1(self.target)
+	}
+}
+
+// }}}
+
+// ReadlinkTargetError {{{
+
+/// Errors that may occur when validating a symlink target.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ReadlinkTargetError {
+	/// The target is empty.
+	Empty,
+	/// The target contains `NUL`.
+	ContainsNul,
+	/// The target is longer than [`ReadlinkResponse::MAX_TARGET_LEN`].
+	TooLong,
+}
+
+// }}}
@@ -0,0 +1,83 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::mem::size_of;
+
+use fuse::kernel;
+use fuse::server::{ReadlinkResponse, ReadlinkTargetError};
+
+use fuse_testutil as testutil;
+use fuse_testutil::{encode_response, MessageBuilder};
+
+#[test]
+fn response() {
+	let resp = ReadlinkResponse::new(b"../target").unwrap();
+	assert_eq!(resp.target(), b"../target");
+
+	let encoded = encode_response!(&resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>() + 9) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_bytes(b"../target")
+			.build()
+	);
+}
+
+#[test]
+fn response_from_node_name() {
+	let name = fuse::NodeName::new("target").unwrap();
+	let resp = ReadlinkResponse::from_node_name(name);
+	assert_eq!(resp.target(), b"target");
+}
+
+#[test]
+fn response_target_errors() {
+	assert_eq!(
+		ReadlinkResponse::new(b"").unwrap_err(),
+		ReadlinkTargetError::Empty,
+	);
+	assert_eq!(
+		ReadlinkResponse::new(b"a\x00b").unwrap_err(),
+		ReadlinkTargetError::ContainsNul,
+	);
+
+	let max_len = [b'a'; ReadlinkResponse::MAX_TARGET_LEN];
+	assert!(ReadlinkResponse::new(&max_len).is_ok());
+
+	let too_long = [b'a'; ReadlinkResponse::MAX_TARGET_LEN + 1];
+	assert_eq!(
+		ReadlinkResponse::new(&too_long).unwrap_err(),
+		ReadlinkTargetError::TooLong,
+	);
+}
+
+#[test]
+fn response_impl_debug() {
+	let resp = ReadlinkResponse::new(b"../target").unwrap();
+	assert_eq!(
+		format!("{:#?}", resp),
+		concat!(
+			"ReadlinkResponse {\n",
+			"    target: \"../target\",\n",
+			"}",
+		),
+	);
+}
@@ -15,9 +15,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use core::fmt;
+use core::mem::size_of;
 
+use crate::client;
 use crate::internal::compat;
-use crate::internal::debug;
 use crate::kernel;
 use crate::server;
 use crate::server::decode;
@@ -56,7 +57,34 @@ impl ReleaseRequest<'_> {
 
 	#[must_use]
 	pub fn open_flags(&self) -> crate::OpenFlags {
-		self.body.as_v7p1().flags
+		crate::OpenFlags::new(self.body.as_v7p1().flags)
+	}
+
+	/// Returns `true` if the client requested that the handle be flushed
+	/// before it is released, as if by a `FUSE_FLUSH` request.
+	#[inline]
+	#[must_use]
+	pub fn flush(&self) -> bool {
+		self.release_flags() & kernel::FUSE_RELEASE_FLUSH != 0
+	}
+
+	/// Returns `true` if the client requested that any `flock(2)` locks held
+	/// through the handle be released.
+	///
+	/// This is only set if the [`FLOCK_LOCKS`] init flag was negotiated.
+	///
+	/// [`FLOCK_LOCKS`]: crate::FuseInitFlag::FLOCK_LOCKS
+	#[inline]
+	#[must_use]
+	pub fn flock_unlock(&self) -> bool {
+		self.release_flags() & kernel::FUSE_RELEASE_FLOCK_UNLOCK != 0
+	}
+
+	fn release_flags(&self) -> u32 {
+		match self.body.as_v7p8() {
+			Some(body) => body.release_flags,
+			None => 0,
+		}
 	}
 }
 
@@ -96,13 +124,53 @@ impl<'a> ReleaseRequest<'a> {
 	}
 }
 
+impl client::CuseRequest for ReleaseRequest<'_> {
+	fn send_to<S: client::io::CuseSocket>(
+		&self,
+		request_sender: client::CuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let version_minor = request_sender.layout.version_minor();
+		self.send(request_sender.inner, version_minor)
+	}
+}
+
+impl client::FuseRequest for ReleaseRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let version_minor = request_sender.layout.version_minor();
+		self.send(request_sender.inner, version_minor)
+	}
+}
+
+impl ReleaseRequest<'_> {
+	fn send<S: client::io::Socket>(
+		&self,
+		request_sender: client::RequestSender<'_, S>,
+		version_minor: u32,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let body = self.body.to_v7p8();
+		let mut body_bytes = body.as_bytes();
+		if version_minor < 8 {
+			let len = size_of::<compat::fuse_release_in_v7p1>();
+			body_bytes = &body_bytes[..len];
+		}
+		request_sender.send_1(
+			kernel::fuse_opcode::FUSE_RELEASE,
+			self.header.nodeid,
+			body_bytes,
+		)
+	}
+}
+
 impl fmt::Debug for ReleaseRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("ReleaseRequest")
 			.field("node_id", &self.node_id())
 			.field("handle", &self.handle())
 			.field("lock_owner", &format_args!("{:?}", self.lock_owner()))
-			.field("open_flags", &debug::hex_u32(self.open_flags()))
+			.field("open_flags", &self.open_flags())
 			.finish()
 	}
 }
@@ -39,7 +39,7 @@ fn request_v7p1() {
 	});
 
 	assert_eq!(req.handle(), 123);
-	assert_eq!(req.open_flags(), 0xFF);
+	assert_eq!(req.open_flags().get(), 0xFF);
 	assert_eq!(req.lock_owner(), None);
 }
 
@@ -61,7 +61,7 @@ fn request_v7p8() {
 	});
 
 	assert_eq!(req.handle(), 123);
-	assert_eq!(req.open_flags(), 0xFF);
+	assert_eq!(req.open_flags().get(), 0xFF);
 	assert_eq!(req.lock_owner(), None);
 }
 
@@ -88,6 +88,49 @@ fn request_lock_owner() {
 	assert_eq!(req.lock_owner(), Some(fuse::LockOwner(123)));
 }
 
+#[test]
+fn request_release_flags() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_RELEASE;
+			h.nodeid = 123;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_release_in {
+			fh: 123,
+			flags: 0xFF,
+			release_flags: kernel::FUSE_RELEASE_FLUSH,
+			lock_owner: 123,
+		}))
+		.build_aligned();
+
+	let req = decode_request!(ReleaseRequest, buf, {
+		protocol_version: (7, 8),
+	});
+
+	assert!(req.flush());
+	assert!(!req.flock_unlock());
+
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_RELEASE;
+			h.nodeid = 123;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_release_in {
+			fh: 123,
+			flags: 0xFF,
+			release_flags: kernel::FUSE_RELEASE_FLOCK_UNLOCK,
+			lock_owner: 123,
+		}))
+		.build_aligned();
+
+	let req = decode_request!(ReleaseRequest, buf, {
+		protocol_version: (7, 8),
+	});
+
+	assert!(!req.flush());
+	assert!(req.flock_unlock());
+}
+
 #[test]
 fn request_impl_debug() {
 	let buf;
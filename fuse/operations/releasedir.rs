@@ -15,9 +15,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use core::fmt;
+use core::mem::size_of;
 
+use crate::client;
 use crate::internal::compat;
-use crate::internal::debug;
 use crate::kernel;
 use crate::server::decode;
 
@@ -55,7 +56,7 @@ impl ReleasedirRequest<'_> {
 
 	#[must_use]
 	pub fn open_flags(&self) -> crate::OpenFlags {
-		self.body.as_v7p1().flags
+		crate::OpenFlags::new(self.body.as_v7p1().flags)
 	}
 }
 
@@ -78,13 +79,32 @@ try_from_fuse_request!(ReleasedirRequest<'a>, |request| {
 	Ok(Self { header, body })
 });
 
+impl client::FuseRequest for ReleasedirRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let body = self.body.to_v7p8();
+		let mut body_bytes = body.as_bytes();
+		if request_sender.layout.version_minor() < 8 {
+			let len = size_of::<compat::fuse_release_in_v7p1>();
+			body_bytes = &body_bytes[..len];
+		}
+		request_sender.inner.send_1(
+			kernel::fuse_opcode::FUSE_RELEASEDIR,
+			self.header.nodeid,
+			body_bytes,
+		)
+	}
+}
+
 impl fmt::Debug for ReleasedirRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("ReleasedirRequest")
 			.field("node_id", &self.node_id())
 			.field("handle", &self.handle())
 			.field("lock_owner", &format_args!("{:?}", self.lock_owner()))
-			.field("open_flags", &debug::hex_u32(self.open_flags()))
+			.field("open_flags", &self.open_flags())
 			.finish()
 	}
 }
@@ -39,7 +39,7 @@ fn request_v7p1() {
 	});
 
 	assert_eq!(req.handle(), 123);
-	assert_eq!(req.open_flags(), 0xFF);
+	assert_eq!(req.open_flags().get(), 0xFF);
 	assert_eq!(req.lock_owner(), None);
 }
 
@@ -61,7 +61,7 @@ fn request_v7p8() {
 	});
 
 	assert_eq!(req.handle(), 123);
-	assert_eq!(req.open_flags(), 0xFF);
+	assert_eq!(req.open_flags().get(), 0xFF);
 	assert_eq!(req.lock_owner(), None);
 }
 
@@ -16,6 +16,7 @@
 
 use core::fmt;
 
+use crate::client;
 use crate::kernel;
 use crate::server::decode;
 
@@ -52,6 +53,19 @@ try_from_fuse_request!(RemovexattrRequest<'a>, |request| {
 	Ok(Self { header, name })
 });
 
+impl client::FuseRequest for RemovexattrRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.inner.send_1(
+			kernel::fuse_opcode::FUSE_REMOVEXATTR,
+			self.header.nodeid,
+			self.name.to_bytes_with_nul(),
+		)
+	}
+}
+
 impl fmt::Debug for RemovexattrRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("RemovexattrRequest")
@@ -16,6 +16,7 @@
 
 use core::fmt;
 
+use crate::client;
 use crate::internal::debug;
 use crate::kernel;
 use crate::server::decode;
@@ -85,6 +86,42 @@ try_from_fuse_request!(RenameRequest<'a>, |request| {
 	})
 });
 
+impl client::FuseRequest for RenameRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let old_directory_id = self.old_directory_id.get();
+		if self.rename_flags == 0 {
+			let body = new!(kernel::fuse_rename_in {
+				newdir: self.new_directory_id.get(),
+			});
+			return request_sender.inner.send_5(
+				kernel::fuse_opcode::FUSE_RENAME,
+				old_directory_id,
+				body.as_bytes(),
+				self.old_name.as_bytes(),
+				b"\0",
+				self.new_name.as_bytes(),
+				b"\0",
+			);
+		}
+		let body = new!(kernel::fuse_rename2_in {
+			newdir: self.new_directory_id.get(),
+			flags: self.rename_flags,
+		});
+		request_sender.inner.send_5(
+			kernel::fuse_opcode::FUSE_RENAME2,
+			old_directory_id,
+			body.as_bytes(),
+			self.old_name.as_bytes(),
+			b"\0",
+			self.new_name.as_bytes(),
+			b"\0",
+		)
+	}
+}
+
 impl fmt::Debug for RenameRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("RenameRequest")
@@ -18,7 +18,7 @@ use fuse::kernel;
 use fuse::server::RenameRequest;
 
 use fuse_testutil as testutil;
-use fuse_testutil::{decode_request, MessageBuilder};
+use fuse_testutil::{decode_request, encode_request, MessageBuilder};
 
 #[test]
 fn request_rename() {
@@ -41,6 +41,9 @@ fn request_rename() {
 	assert_eq!(request.old_directory_id(), fuse::NodeId::new(123).unwrap());
 	assert_eq!(request.new_directory_id(), fuse::NodeId::new(456).unwrap());
 	assert_eq!(request.rename_flags(), 0);
+
+	let encoded = encode_request!(request);
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
 }
 
 #[test]
@@ -65,6 +68,9 @@ fn request_rename2() {
 	assert_eq!(request.old_directory_id(), fuse::NodeId::new(123).unwrap());
 	assert_eq!(request.new_directory_id(), fuse::NodeId::new(456).unwrap());
 	assert_eq!(request.rename_flags(), 0b111);
+
+	let encoded = encode_request!(request);
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
 }
 
 #[test]
@@ -14,6 +14,7 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::client;
 use crate::kernel;
 use crate::server::decode;
 
@@ -47,4 +48,18 @@ try_from_fuse_request!(RmdirRequest<'a>, |request| {
 	})
 });
 
+impl client::FuseRequest for RmdirRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.inner.send_2(
+			kernel::fuse_opcode::FUSE_RMDIR,
+			self.parent_id.get(),
+			self.name.as_bytes(),
+			b"\0",
+		)
+	}
+}
+
 // }}}
@@ -0,0 +1,219 @@
+// Copyright 2022 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Security contexts attached to node creation requests.
+
+use core::ffi::CStr;
+use core::fmt;
+use core::mem::size_of;
+
+use crate::kernel;
+use crate::server::decode::RequestDecoder;
+use crate::server::{FuseLayout, RequestError};
+
+// SecurityContext {{{
+
+/// A security context to apply to a newly created node.
+///
+/// The name is that of the extended attribute in which the context would be
+/// stored by a local filesystem, for example `"security.selinux"`.
+#[derive(Clone, Copy)]
+pub struct SecurityContext<'a> {
+	name: &'a CStr,
+	value: &'a [u8],
+}
+
+impl<'a> SecurityContext<'a> {
+	#[inline]
+	#[must_use]
+	pub fn name(&self) -> &'a CStr {
+		self.name
+	}
+
+	#[inline]
+	#[must_use]
+	pub fn value(&self) -> &'a [u8] {
+		self.value
+	}
+}
+
+impl fmt::Debug for SecurityContext<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("SecurityContext")
+			.field("name", &self.name)
+			.field("value", &self.value)
+			.finish()
+	}
+}
+
+// }}}
+
+// SecurityContexts {{{
+
+/// Iterator over the security contexts of a node creation request.
+///
+/// Security contexts are only sent by the client if the `SECURITY_CTX` init
+/// flag was negotiated. Otherwise, the iterator is empty.
+#[derive(Clone, Copy)]
+pub struct SecurityContexts<'a> {
+	buf: &'a [u8],
+	count: u32,
+	// The whole extension, including its `fuse_secctx_header`.
+	ext: &'a [u8],
+}
+
+impl<'a> SecurityContexts<'a> {
+	pub(crate) const EMPTY: SecurityContexts<'static> = SecurityContexts {
+		buf: &[],
+		count: 0,
+		ext: &[],
+	};
+
+	// Since protocol version 7.38 the security contexts are one of several
+	// extensions that may follow the request body. Each extension starts with
+	// a `fuse_ext_header`, whose type field doubles as the context count of a
+	// `fuse_secctx_header`. Extensions other than security contexts are
+	// skipped.
+	//
+	// The client only sends security contexts if the `SECURITY_CTX` init
+	// flag was negotiated, so other extensions are ignored without it.
+	pub(crate) fn decode(
+		dec: &mut RequestDecoder<'a>,
+		layout: FuseLayout,
+	) -> Result<SecurityContexts<'a>, RequestError> {
+		const HEADER_LEN: usize = size_of::<kernel::fuse_ext_header>();
+		const MAX_NR_SECCTX: u32 = kernel::fuse_ext_type::FUSE_MAX_NR_SECCTX.0;
+
+		let mut contexts = SecurityContexts::EMPTY;
+		if !layout.have_security_ctx() {
+			return Ok(contexts);
+		}
+		let mut buf = dec.next_remaining();
+		while !buf.is_empty() {
+			if buf.len() < HEADER_LEN {
+				return Err(RequestError::UnexpectedEof);
+			}
+			let size = read_u32(buf, 0) as usize;
+			let ext_type = read_u32(buf, 4);
+			if size < HEADER_LEN || size > buf.len() {
+				return Err(RequestError::InvalidExtension);
+			}
+			let (ext, rest) = buf.split_at(size);
+			buf = rest;
+			if ext_type > MAX_NR_SECCTX {
+				continue;
+			}
+			// The extension is forwarded as-is by `extension()`, so its size
+			// must be a whole number of the header's 8-byte units.
+			if size & 7 != 0 {
+				return Err(RequestError::InvalidExtension);
+			}
+
+			contexts = SecurityContexts {
+				buf: &ext[HEADER_LEN..],
+				count: ext_type,
+				ext,
+			};
+			let mut entries = contexts.buf;
+			for _ in 0..ext_type {
+				match next_context(entries) {
+					Some((_, next_entries)) => entries = next_entries,
+					None => return Err(RequestError::InvalidSecurityContext),
+				}
+			}
+		}
+		Ok(contexts)
+	}
+
+	// Returns the encoded extension to send with a request, which is empty if
+	// the `SECURITY_CTX` init flag wasn't negotiated.
+	pub(crate) fn extension(&self, layout: FuseLayout) -> &'a [u8] {
+		if !layout.have_security_ctx() {
+			return &[];
+		}
+		self.ext
+	}
+
+	/// Returns the number of security contexts remaining in the iterator.
+	#[inline]
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.count as usize
+	}
+
+	/// Returns `true` if the iterator contains no security contexts.
+	#[inline]
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.count == 0
+	}
+}
+
+impl fmt::Debug for SecurityContexts<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_list().entries(*self).finish()
+	}
+}
+
+impl<'a> Iterator for SecurityContexts<'a> {
+	type Item = SecurityContext<'a>;
+
+	fn next(&mut self) -> Option<SecurityContext<'a>> {
+		if self.count == 0 {
+			return None;
+		}
+		let (context, rest) = next_context(self.buf)?;
+		self.buf = rest;
+		self.count -= 1;
+		Some(context)
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(self.len(), Some(self.len()))
+	}
+}
+
+impl ExactSizeIterator for SecurityContexts<'_> {}
+
+// Each entry is a `fuse_secctx` followed by a NUL-terminated name and then
+// the context value, padded to a multiple of 8 bytes.
+fn next_context(buf: &[u8]) -> Option<(SecurityContext<'_>, &[u8])> {
+	const ENTRY_HEADER_LEN: usize = size_of::<kernel::fuse_secctx>();
+	if buf.len() < ENTRY_HEADER_LEN {
+		return None;
+	}
+	let value_len = read_u32(buf, 0) as usize;
+	let name_and_value = &buf[ENTRY_HEADER_LEN..];
+
+	let name_len = name_and_value.iter().position(|&b| b == 0)? + 1;
+	let name = CStr::from_bytes_with_nul(&name_and_value[..name_len]).ok()?;
+	let value = name_and_value.get(name_len..)?.get(..value_len)?;
+
+	let entry_len = ENTRY_HEADER_LEN
+		.checked_add(name_len)?
+		.checked_add(value_len)?;
+	let padded_len = entry_len.checked_add(7)? & !7;
+	let rest = buf.get(padded_len..).unwrap_or(&[]);
+	Some((SecurityContext { name, value }, rest))
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+	let mut bytes = [0u8; 4];
+	bytes.copy_from_slice(&buf[offset..offset + 4]);
+	u32::from_ne_bytes(bytes)
+}
+
+// }}}
@@ -15,8 +15,13 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use core::fmt;
+use core::time;
 
+use crate::client;
+use crate::internal::compat;
+use crate::internal::timestamp;
 use crate::kernel;
+use crate::server;
 use crate::server::decode;
 
 // SetattrRequest {{{
@@ -28,14 +33,100 @@ pub struct SetattrRequest<'a> {
 	raw: &'a kernel::fuse_setattr_in,
 }
 
-impl SetattrRequest<'_> {
+impl<'a> SetattrRequest<'a> {
+	/// Returns the ID of the node whose attributes should be changed.
 	#[must_use]
 	pub fn node_id(&self) -> crate::NodeId {
 		unsafe { crate::NodeId::new_unchecked(self.header.nodeid) }
 	}
 
+	/// Returns the handle of the open file, if the attributes are being
+	/// changed through an open file (as by `ftruncate()` or `fchmod()`).
 	#[must_use]
-	fn get<T>(&self, bitmask: u32, value: T) -> Option<T> {
+	pub fn handle(&self) -> Option<u64> {
+		if self.raw.valid & kernel::FATTR_FH == 0 {
+			return None;
+		}
+		Some(self.raw.fh)
+	}
+
+	/// Returns the attribute changes requested by the client.
+	#[must_use]
+	pub fn changes(&self) -> SetattrChanges<'a> {
+		SetattrChanges { raw: self.raw }
+	}
+
+	/// Returns whether the server should clear the setuid and setgid bits
+	/// of the file, along with its `security.capability` xattr.
+	///
+	/// This is equivalent to [`SetattrChanges::kill_suidgid`]. See
+	/// [`FileMode::without_suidgid`] for which bits should be cleared.
+	///
+	/// [`FileMode::without_suidgid`]: crate::FileMode::without_suidgid
+	#[must_use]
+	pub fn kill_suidgid(&self) -> bool {
+		self.changes().kill_suidgid()
+	}
+}
+
+try_from_fuse_request!(SetattrRequest<'a>, |request| {
+	let mut dec = request.decoder();
+	dec.expect_opcode(kernel::fuse_opcode::FUSE_SETATTR)?;
+	let header = dec.header();
+	decode::node_id(header.nodeid)?;
+	let raw: &kernel::fuse_setattr_in = dec.next_sized()?;
+
+	if raw.valid & kernel::FATTR_ATIME > 0 {
+		decode::check_timespec_nanos(raw.atimensec)?;
+	}
+	if raw.valid & kernel::FATTR_MTIME > 0 {
+		decode::check_timespec_nanos(raw.mtimensec)?;
+	}
+	if raw.valid & kernel::FATTR_CTIME > 0 {
+		decode::check_timespec_nanos(raw.ctimensec)?;
+	}
+
+	Ok(Self { header, raw })
+});
+
+impl client::FuseRequest for SetattrRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.inner.send_1(
+			kernel::fuse_opcode::FUSE_SETATTR,
+			self.header.nodeid,
+			self.raw.as_bytes(),
+		)
+	}
+}
+
+impl fmt::Debug for SetattrRequest<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("SetattrRequest")
+			.field("node_id", &self.node_id())
+			.field("handle", &format_args!("{:?}", self.handle()))
+			.field("changes", &self.changes())
+			.finish()
+	}
+}
+
+// }}}
+
+// SetattrChanges {{{
+
+/// The attribute changes of a [`SetattrRequest`].
+///
+/// Each attribute is `None` unless the client requested that it be changed.
+#[derive(Clone, Copy)]
+pub struct SetattrChanges<'a> {
+	raw: &'a kernel::fuse_setattr_in,
+}
+
+impl SetattrChanges<'_> {
+	#[must_use]
+	fn get<T>(self, bitmask: u32, value: T) -> Option<T> {
 		if self.raw.valid & bitmask == 0 {
 			return None;
 		}
@@ -43,128 +134,207 @@ impl SetattrRequest<'_> {
 	}
 
 	#[must_use]
-	fn get_timestamp(
-		&self,
+	fn get_time(
+		self,
 		bitmask: u32,
+		now_bitmask: u32,
 		seconds: u64,
 		nanos: u32,
-	) -> Option<crate::UnixTime> {
+	) -> Option<SetattrTime> {
+		if self.raw.valid & now_bitmask != 0 {
+			return Some(SetattrTime::Now);
+		}
 		if self.raw.valid & bitmask == 0 {
 			return None;
 		}
-		Some(unsafe {
+		Some(SetattrTime::Time(unsafe {
 			crate::UnixTime::from_timespec_unchecked(seconds, nanos)
-		})
+		}))
 	}
 
+	/// Returns the new file mode, as by `chmod()`.
 	#[must_use]
-	pub fn handle(&self) -> Option<u64> {
-		self.get(kernel::FATTR_FH, self.raw.fh)
+	pub fn mode(self) -> Option<crate::FileMode> {
+		self.get(kernel::FATTR_MODE, crate::FileMode::new(self.raw.mode))
 	}
 
+	/// Returns the new owner user ID, as by `chown()`.
 	#[must_use]
-	pub fn size(&self) -> Option<u64> {
-		self.get(kernel::FATTR_SIZE, self.raw.size)
+	pub fn user_id(self) -> Option<u32> {
+		self.get(kernel::FATTR_UID, self.raw.uid)
 	}
 
+	/// Returns the new owner group ID, as by `chown()`.
 	#[must_use]
-	pub fn lock_owner(&self) -> Option<crate::LockOwner> {
-		self.get(
-			kernel::FATTR_LOCKOWNER,
-			crate::LockOwner(self.raw.lock_owner),
-		)
+	pub fn group_id(self) -> Option<u32> {
+		self.get(kernel::FATTR_GID, self.raw.gid)
 	}
 
+	/// Returns the new file size, as by `truncate()`.
 	#[must_use]
-	pub fn atime(&self) -> Option<crate::UnixTime> {
-		self.get_timestamp(
+	pub fn size(self) -> Option<u64> {
+		self.get(kernel::FATTR_SIZE, self.raw.size)
+	}
+
+	/// Returns the new access time, as by `utimensat()`.
+	#[must_use]
+	pub fn atime(self) -> Option<SetattrTime> {
+		self.get_time(
 			kernel::FATTR_ATIME,
+			kernel::FATTR_ATIME_NOW,
 			self.raw.atime,
 			self.raw.atimensec,
 		)
 	}
 
+	/// Returns the new modification time, as by `utimensat()`.
 	#[must_use]
-	pub fn atime_now(&self) -> bool {
-		self.raw.valid & kernel::FATTR_ATIME_NOW > 0
-	}
-
-	#[must_use]
-	pub fn mtime(&self) -> Option<crate::UnixTime> {
-		self.get_timestamp(
+	pub fn mtime(self) -> Option<SetattrTime> {
+		self.get_time(
 			kernel::FATTR_MTIME,
+			kernel::FATTR_MTIME_NOW,
 			self.raw.mtime,
 			self.raw.mtimensec,
 		)
 	}
 
+	/// Returns the new change time.
+	///
+	/// The kernel only sends a change time if the filesystem was mounted
+	/// with writeback caching enabled.
 	#[must_use]
-	pub fn mtime_now(&self) -> bool {
-		self.raw.valid & kernel::FATTR_MTIME_NOW > 0
+	pub fn ctime(self) -> Option<crate::UnixTime> {
+		if self.raw.valid & kernel::FATTR_CTIME == 0 {
+			return None;
+		}
+		Some(unsafe {
+			crate::UnixTime::from_timespec_unchecked(
+				self.raw.ctime,
+				self.raw.ctimensec,
+			)
+		})
 	}
 
+	/// Returns the lock owner of the file being truncated.
+	///
+	/// The lock owner is only sent along with a new [`size`](Self::size),
+	/// so that mandatory locks can be checked.
 	#[must_use]
-	pub fn ctime(&self) -> Option<crate::UnixTime> {
-		self.get_timestamp(
-			kernel::FATTR_CTIME,
-			self.raw.ctime,
-			self.raw.ctimensec,
+	pub fn lock_owner(self) -> Option<crate::LockOwner> {
+		self.get(
+			kernel::FATTR_LOCKOWNER,
+			crate::LockOwner(self.raw.lock_owner),
 		)
 	}
 
+	/// Returns whether the server should clear the setuid and setgid bits
+	/// of the file.
+	///
+	/// This is only set if the [`HANDLE_KILLPRIV_V2`] init flag was
+	/// negotiated.
+	///
+	/// [`HANDLE_KILLPRIV_V2`]: crate::FuseInitFlag::HANDLE_KILLPRIV_V2
 	#[must_use]
-	pub fn mode(&self) -> Option<crate::FileMode> {
-		self.get(kernel::FATTR_MODE, crate::FileMode::new(self.raw.mode))
+	pub fn kill_suidgid(self) -> bool {
+		self.raw.valid & kernel::FATTR_KILL_SUIDGID != 0
 	}
+}
 
-	#[must_use]
-	pub fn user_id(&self) -> Option<u32> {
-		self.get(kernel::FATTR_UID, self.raw.uid)
+impl fmt::Debug for SetattrChanges<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("SetattrChanges")
+			.field("mode", &format_args!("{:?}", self.mode()))
+			.field("user_id", &format_args!("{:?}", self.user_id()))
+			.field("group_id", &format_args!("{:?}", self.group_id()))
+			.field("size", &format_args!("{:?}", self.size()))
+			.field("atime", &format_args!("{:?}", self.atime()))
+			.field("mtime", &format_args!("{:?}", self.mtime()))
+			.field("ctime", &format_args!("{:?}", self.ctime()))
+			.field("lock_owner", &format_args!("{:?}", self.lock_owner()))
+			.field("kill_suidgid", &self.kill_suidgid())
+			.finish()
 	}
+}
 
-	#[must_use]
-	pub fn group_id(&self) -> Option<u32> {
-		self.get(kernel::FATTR_GID, self.raw.gid)
-	}
+/// A new access or modification time in a [`SetattrRequest`].
+#[allow(clippy::exhaustive_enums)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SetattrTime {
+	/// Set the timestamp to the current time, according to the server.
+	Now,
+	/// Set the timestamp to the given time.
+	Time(crate::UnixTime),
 }
 
-try_from_fuse_request!(SetattrRequest<'a>, |request| {
-	let mut dec = request.decoder();
-	dec.expect_opcode(kernel::fuse_opcode::FUSE_SETATTR)?;
-	let header = dec.header();
-	decode::node_id(header.nodeid)?;
-	let raw: &kernel::fuse_setattr_in = dec.next_sized()?;
+// }}}
 
-	if raw.valid & kernel::FATTR_ATIME > 0 {
-		decode::check_timespec_nanos(raw.atimensec)?;
+// SetattrResponse {{{
+
+/// Response type for `FUSE_SETATTR`.
+pub struct SetattrResponse {
+	raw: kernel::fuse_attr_out,
+}
+
+impl SetattrResponse {
+	/// Creates a new `SetattrResponse` with the node's updated attributes.
+	#[inline]
+	#[must_use]
+	pub fn new(attributes: crate::NodeAttr) -> SetattrResponse {
+		Self {
+			raw: new!(kernel::fuse_attr_out {
+				attr: attributes.raw,
+			}),
+		}
 	}
-	if raw.valid & kernel::FATTR_MTIME > 0 {
-		decode::check_timespec_nanos(raw.mtimensec)?;
+
+	/// Returns the node's updated attributes.
+	#[inline]
+	#[must_use]
+	pub fn attributes(&self) -> &crate::NodeAttr {
+		unsafe { crate::NodeAttr::from_ref(&self.raw.attr) }
 	}
-	if raw.valid & kernel::FATTR_CTIME > 0 {
-		decode::check_timespec_nanos(raw.ctimensec)?;
+
+	/// Returns a mutable reference to the node's updated attributes.
+	#[inline]
+	#[must_use]
+	pub fn attributes_mut(&mut self) -> &mut crate::NodeAttr {
+		unsafe { crate::NodeAttr::from_ref_mut(&mut self.raw.attr) }
 	}
 
-	Ok(Self { header, raw })
-});
+	/// Returns the attribute cache timeout.
+	#[inline]
+	#[must_use]
+	pub fn cache_timeout(&self) -> time::Duration {
+		timestamp::new_duration(self.raw.attr_valid, self.raw.attr_valid_nsec)
+	}
 
-impl fmt::Debug for SetattrRequest<'_> {
+	/// Sets the attribute cache timeout.
+	#[inline]
+	pub fn set_cache_timeout(&mut self, timeout: time::Duration) {
+		let (seconds, nanos) = timestamp::split_duration(timeout);
+		self.raw.attr_valid = seconds;
+		self.raw.attr_valid_nsec = nanos;
+	}
+}
+
+impl fmt::Debug for SetattrResponse {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-		fmt.debug_struct("SetattrRequest")
-			.field("node_id", &self.node_id())
-			.field("handle", &format_args!("{:?}", self.handle()))
-			.field("size", &format_args!("{:?}", self.size()))
-			.field("lock_owner", &format_args!("{:?}", self.lock_owner()))
-			.field("atime", &format_args!("{:?}", self.atime()))
-			.field("atime_now", &self.atime_now())
-			.field("mtime", &format_args!("{:?}", self.mtime()))
-			.field("mtime_now", &self.mtime_now())
-			.field("ctime", &format_args!("{:?}", self.ctime()))
-			.field("mode", &format_args!("{:?}", self.mode()))
-			.field("user_id", &format_args!("{:?}", self.user_id()))
-			.field("group_id", &format_args!("{:?}", self.group_id()))
+		fmt.debug_struct("SetattrResponse")
+			.field("attributes", self.attributes())
+			.field("cache_timeout", &self.cache_timeout())
 			.finish()
 	}
 }
 
+impl server::FuseReply for SetattrResponse {
+	fn send_to<S: server::FuseSocket>(
+		&self,
+		reply_sender: server::FuseReplySender<'_, S>,
+	) -> Result<(), server::SendError<S::Error>> {
+		let version_minor = reply_sender.layout.version_minor();
+		let buf = compat::fuse_attr_out_bytes(version_minor, &self.raw);
+		reply_sender.inner.send_1(buf)
+	}
+}
+
 // }}}
@@ -90,16 +90,14 @@ where
 		if request.node_id().is_root() {
 			attr.set_mode(fuse::FileMode::S_IFDIR | 0o755);
 			attr.set_link_count(2);
-			let mut reply = fuse::kernel::fuse_attr_out::new();
-			reply.attr = *attr.raw();
+			let reply = server::GetattrResponse::new(attr);
 			return send_reply.ok(&reply).unwrap();
 		}
 
 		if request.node_id() == fuse::NodeId::new(2).unwrap() {
 			attr.set_mode(fuse::FileMode::S_IFREG | 0o644);
 			attr.set_link_count(1);
-			let mut reply = fuse::kernel::fuse_attr_out::new();
-			reply.attr = *attr.raw();
+			let reply = server::GetattrResponse::new(attr);
 			return send_reply.ok(&reply).unwrap();
 		}
 
@@ -109,8 +107,8 @@ where
 	fn open(&self, request: FuseRequest<'_>) {
 		let send_reply = self.conn.reply(request.id());
 		if request.header().raw().nodeid == 2 {
-			let mut reply = fuse::kernel::fuse_open_out::new();
-			reply.fh = 1002;
+			let mut reply = server::OpenResponse::new();
+			reply.set_handle(1002);
 			return send_reply.ok(&reply).unwrap();
 		}
 		send_reply.err(OsError::NOT_FOUND).unwrap();
@@ -140,8 +138,7 @@ where
 		attr.set_mode(fuse::FileMode::S_IFREG | 0o644);
 		attr.set_link_count(1);
 
-		let mut reply = fuse::kernel::fuse_attr_out::new();
-		reply.attr = *attr.raw();
+		let reply = server::SetattrResponse::new(attr);
 		send_reply.ok(&reply).unwrap();
 	}
 }
@@ -174,16 +171,17 @@ fn setattr_chown() {
 	let expect = r#"SetattrRequest {
     node_id: 2,
     handle: None,
-    size: None,
-    lock_owner: None,
-    atime: None,
-    atime_now: false,
-    mtime: None,
-    mtime_now: false,
-    ctime: None,
-    mode: None,
-    user_id: Some(123),
-    group_id: Some(456),
+    changes: SetattrChanges {
+        mode: None,
+        user_id: Some(123),
+        group_id: Some(456),
+        size: None,
+        atime: None,
+        mtime: None,
+        ctime: None,
+        lock_owner: None,
+        kill_suidgid: false,
+    },
 }"#;
 	if let Some(diff) = diff_str(expect, &requests[0]) {
 		println!("{}", diff);
@@ -211,16 +209,17 @@ fn setattr_chmod() {
 		r#"SetattrRequest {{
     node_id: 2,
     handle: None,
-    size: None,
-    lock_owner: None,
-    atime: None,
-    atime_now: false,
-    mtime: None,
-    mtime_now: false,
-    ctime: None,
-    mode: Some({mode}),
-    user_id: None,
-    group_id: None,
+    changes: SetattrChanges {{
+        mode: Some({mode}),
+        user_id: None,
+        group_id: None,
+        size: None,
+        atime: None,
+        mtime: None,
+        ctime: None,
+        lock_owner: None,
+        kill_suidgid: false,
+    }},
 }}"#,
 		mode = expect_mode,
 	);
@@ -256,16 +255,17 @@ fn setattr_utimens() {
 	let expect = r#"SetattrRequest {
     node_id: 2,
     handle: None,
-    size: None,
-    lock_owner: None,
-    atime: Some(UnixTime(1400000000.001234000)),
-    atime_now: false,
-    mtime: Some(UnixTime(1500000000.005678000)),
-    mtime_now: false,
-    ctime: None,
-    mode: None,
-    user_id: None,
-    group_id: None,
+    changes: SetattrChanges {
+        mode: None,
+        user_id: None,
+        group_id: None,
+        size: None,
+        atime: Some(Time(UnixTime(1400000000.001234000))),
+        mtime: Some(Time(UnixTime(1500000000.005678000))),
+        ctime: None,
+        lock_owner: None,
+        kill_suidgid: false,
+    },
 }"#;
 	if let Some(diff) = diff_str(expect, &requests[0]) {
 		println!("{}", diff);
@@ -299,16 +299,68 @@ fn setattr_utimens_negative() {
 	let expect = r#"SetattrRequest {
     node_id: 2,
     handle: None,
-    size: None,
-    lock_owner: None,
-    atime: Some(UnixTime(-1400000000.001234000)),
-    atime_now: false,
-    mtime: Some(UnixTime(-1500000000.005678000)),
-    mtime_now: false,
-    ctime: None,
-    mode: None,
-    user_id: None,
-    group_id: None,
+    changes: SetattrChanges {
+        mode: None,
+        user_id: None,
+        group_id: None,
+        size: None,
+        atime: Some(Time(UnixTime(-1400000000.001234000))),
+        mtime: Some(Time(UnixTime(-1500000000.005678000))),
+        ctime: None,
+        lock_owner: None,
+        kill_suidgid: false,
+    },
+}"#;
+	if let Some(diff) = diff_str(expect, &requests[0]) {
+		println!("{}", diff);
+		assert!(false);
+	}
+}
+
+#[test]
+fn setattr_utimens_now() {
+	let requests = setattr_test(None, |root| {
+		let path = path_cstr(root.join("file.txt"));
+
+		let times = [
+			// atime
+			libc::timespec {
+				tv_sec: 0,
+				tv_nsec: libc::UTIME_NOW,
+			},
+			// mtime
+			libc::timespec {
+				tv_sec: 0,
+				tv_nsec: libc::UTIME_NOW,
+			},
+		];
+
+		let rc = unsafe {
+			libc::utimensat(
+				libc::AT_FDCWD,
+				path.as_ptr(),
+				(&times).as_ptr(),
+				0,
+			)
+		};
+		assert_eq!(rc, 0);
+	});
+	assert_eq!(requests.len(), 1);
+
+	let expect = r#"SetattrRequest {
+    node_id: 2,
+    handle: None,
+    changes: SetattrChanges {
+        mode: None,
+        user_id: None,
+        group_id: None,
+        size: None,
+        atime: Some(Now),
+        mtime: Some(Now),
+        ctime: None,
+        lock_owner: None,
+        kill_suidgid: false,
+    },
 }"#;
 	if let Some(diff) = diff_str(expect, &requests[0]) {
 		println!("{}", diff);
@@ -332,16 +384,17 @@ fn setattr_truncate() {
 	let expect = r#"SetattrRequest {
     node_id: 2,
     handle: None,
-    size: Some(12345),
-    lock_owner: FAKE_LOCK_OWNER,
-    atime: None,
-    atime_now: false,
-    mtime: None,
-    mtime_now: false,
-    ctime: None,
-    mode: None,
-    user_id: None,
-    group_id: None,
+    changes: SetattrChanges {
+        mode: None,
+        user_id: None,
+        group_id: None,
+        size: Some(12345),
+        atime: None,
+        mtime: None,
+        ctime: None,
+        lock_owner: FAKE_LOCK_OWNER,
+        kill_suidgid: false,
+    },
 }"#;
 	if let Some(diff) = diff_str(expect, &requests[0]) {
 		println!("{}", diff);
@@ -370,16 +423,17 @@ fn setattr_ftruncate() {
 	let expect = r#"SetattrRequest {
     node_id: 2,
     handle: Some(1002),
-    size: Some(12345),
-    lock_owner: FAKE_LOCK_OWNER,
-    atime: None,
-    atime_now: false,
-    mtime: None,
-    mtime_now: false,
-    ctime: None,
-    mode: None,
-    user_id: None,
-    group_id: None,
+    changes: SetattrChanges {
+        mode: None,
+        user_id: None,
+        group_id: None,
+        size: Some(12345),
+        atime: None,
+        mtime: None,
+        ctime: None,
+        lock_owner: FAKE_LOCK_OWNER,
+        kill_suidgid: false,
+    },
 }"#;
 	if let Some(diff) = diff_str(expect, &requests[0]) {
 		println!("{}", diff);
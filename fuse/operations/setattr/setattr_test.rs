@@ -14,10 +14,14 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use core::mem::size_of;
+use core::time::Duration;
+
 use fuse::kernel;
-use fuse::server::SetattrRequest;
+use fuse::server::{SetattrRequest, SetattrResponse, SetattrTime};
 
 use fuse_testutil as testutil;
+use fuse_testutil::{encode_request, encode_response, MessageBuilder};
 
 const S_IFREG: u32 = 0o100000;
 
@@ -48,19 +52,61 @@ fn request() {
 
 	assert_eq!(request.node_id(), fuse::NodeId::new(1000).unwrap());
 	assert_eq!(request.handle(), Some(1));
-	assert_eq!(request.size(), Some(2));
-	assert_eq!(request.lock_owner(), Some(fuse::LockOwner(3)));
-	assert_eq!(request.atime(), fuse::UnixTime::new(4, 7));
-	assert_eq!(request.atime_now(), true);
-	assert_eq!(request.mtime(), fuse::UnixTime::new(5, 8));
-	assert_eq!(request.mtime_now(), true);
-	assert_eq!(request.ctime(), fuse::UnixTime::new(6, 9));
-	assert_eq!(request.user_id(), Some(12));
-	assert_eq!(request.group_id(), Some(13));
-
-	let mode = request.mode().unwrap();
+	assert_eq!(request.kill_suidgid(), true);
+
+	let changes = request.changes();
+	assert_eq!(changes.size(), Some(2));
+	assert_eq!(changes.lock_owner(), Some(fuse::LockOwner(3)));
+	assert_eq!(changes.atime(), Some(SetattrTime::Now));
+	assert_eq!(changes.mtime(), Some(SetattrTime::Now));
+	assert_eq!(changes.ctime(), fuse::UnixTime::new(6, 9));
+	assert_eq!(changes.user_id(), Some(12));
+	assert_eq!(changes.group_id(), Some(13));
+	assert_eq!(changes.kill_suidgid(), true);
+
+	let mode = changes.mode().unwrap();
 	assert_eq!(fuse::FileType::from_mode(mode), Some(fuse::FileType::Regular));
 	assert_eq!(mode.permissions(), 0o644);
+
+	let encoded = encode_request!(request);
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
+}
+
+#[test]
+fn request_partial() {
+	let buf;
+	let request = fuse_testutil::build_request!(buf, SetattrRequest, {
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_SETATTR;
+			h.nodeid = 1000;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_setattr_in {
+			valid: kernel::FATTR_SIZE | kernel::FATTR_ATIME,
+			fh: 1,
+			size: 2,
+			lock_owner: 3,
+			atime: 4,
+			mtime: 5,
+			atimensec: 7,
+			mode: S_IFREG | 0o644,
+		}))
+	});
+
+	assert_eq!(request.handle(), None);
+
+	let changes = request.changes();
+	assert_eq!(changes.size(), Some(2));
+	assert_eq!(changes.lock_owner(), None);
+	assert_eq!(
+		changes.atime(),
+		Some(SetattrTime::Time(fuse::UnixTime::new(4, 7).unwrap())),
+	);
+	assert_eq!(changes.mtime(), None);
+	assert_eq!(changes.ctime(), None);
+	assert_eq!(changes.mode(), None);
+	assert_eq!(changes.user_id(), None);
+	assert_eq!(changes.group_id(), None);
+	assert_eq!(changes.kill_suidgid(), false);
 }
 
 #[test]
@@ -72,7 +118,9 @@ fn request_negative_unix_times() {
 			h.nodeid = 1000;
 		})
 		.push_sized(&testutil::new!(kernel::fuse_setattr_in {
-			valid: 0xFFFF,
+			valid: kernel::FATTR_ATIME
+				| kernel::FATTR_MTIME
+				| kernel::FATTR_CTIME,
 			atime: -4_i64 as u64,
 			mtime: -5_i64 as u64,
 			ctime: -6_i64 as u64,
@@ -82,9 +130,16 @@ fn request_negative_unix_times() {
 		}))
 	});
 
-	assert_eq!(request.atime(), fuse::UnixTime::new(-4, 7));
-	assert_eq!(request.mtime(), fuse::UnixTime::new(-5, 8));
-	assert_eq!(request.ctime(), fuse::UnixTime::new(-6, 9));
+	let changes = request.changes();
+	assert_eq!(
+		changes.atime(),
+		Some(SetattrTime::Time(fuse::UnixTime::new(-4, 7).unwrap())),
+	);
+	assert_eq!(
+		changes.mtime(),
+		Some(SetattrTime::Time(fuse::UnixTime::new(-5, 8).unwrap())),
+	);
+	assert_eq!(changes.ctime(), fuse::UnixTime::new(-6, 9));
 }
 
 #[test]
@@ -118,17 +173,81 @@ fn request_impl_debug() {
 			"SetattrRequest {\n",
 			"    node_id: 1000,\n",
 			"    handle: Some(1),\n",
-			"    size: Some(2),\n",
-			"    lock_owner: Some(0x0000000000000003),\n",
-			"    atime: Some(UnixTime(4.000000007)),\n",
-			"    atime_now: true,\n",
-			"    mtime: Some(UnixTime(5.000000008)),\n",
-			"    mtime_now: true,\n",
-			"    ctime: Some(UnixTime(6.000000009)),\n",
-			"    mode: Some(0o100644),\n",
-			"    user_id: Some(12),\n",
-			"    group_id: Some(13),\n",
+			"    changes: SetattrChanges {\n",
+			"        mode: Some(0o100644),\n",
+			"        user_id: Some(12),\n",
+			"        group_id: Some(13),\n",
+			"        size: Some(2),\n",
+			"        atime: Some(Now),\n",
+			"        mtime: Some(Now),\n",
+			"        ctime: Some(UnixTime(6.000000009)),\n",
+			"        lock_owner: Some(0x0000000000000003),\n",
+			"        kill_suidgid: true,\n",
+			"    },\n",
 			"}",
 		),
 	);
 }
+
+#[test]
+fn response() {
+	let mut attr = fuse::NodeAttr::new(fuse::NodeId::new(11).unwrap());
+	attr.set_size(999);
+	let mut resp = SetattrResponse::new(attr);
+	resp.set_cache_timeout(Duration::new(123, 456));
+	resp.attributes_mut().set_mode(fuse::FileMode::S_IFREG | 0o644);
+	assert_eq!(resp.cache_timeout(), Duration::new(123, 456));
+	assert_eq!(resp.attributes().size(), 999);
+
+	let encoded = encode_response!(&resp);
+
+	let mut expect = testutil::new!(kernel::fuse_attr_out {
+		attr_valid: 123,
+		attr_valid_nsec: 456,
+	});
+	expect.attr.ino = 11;
+	expect.attr.size = 999;
+	expect.attr.mode = S_IFREG | 0o644;
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>()
+					+ size_of::<kernel::fuse_attr_out>()) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_sized(&expect)
+			.build()
+	);
+}
+
+#[test]
+fn response_v7p8() {
+	let attr = fuse::NodeAttr::new(fuse::NodeId::new(11).unwrap());
+	let resp = SetattrResponse::new(attr);
+
+	let encoded = encode_response!(&resp, {
+		protocol_version: (7, 8),
+	});
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>()
+					+ kernel::FUSE_COMPAT_ATTR_OUT_SIZE) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_sized(&testutil::new!(kernel::fuse_attr_out {
+				attr: testutil::new!(kernel::fuse_attr {
+					ino: 11,
+				}),
+			}))
+			.unpush(
+				size_of::<kernel::fuse_attr_out>()
+					- kernel::FUSE_COMPAT_ATTR_OUT_SIZE,
+			)
+			.build()
+	);
+}
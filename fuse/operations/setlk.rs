@@ -16,6 +16,7 @@
 
 use core::fmt;
 
+use crate::client;
 use crate::kernel;
 use crate::server::decode;
 
@@ -67,6 +68,32 @@ impl SetlkRequest<'_> {
 			bits: self.body.lk_flags,
 		}
 	}
+
+	/// Returns `true` if the lock is a whole-file `flock(2)` lock, rather
+	/// than a POSIX record lock.
+	#[inline]
+	#[must_use]
+	pub fn is_flock(&self) -> bool {
+		self.body.lk_flags & kernel::FUSE_LK_FLOCK != 0
+	}
+
+	/// Returns `true` if the lock is an open file description lock.
+	///
+	/// Open file description locks (`F_OFD_SETLK`) are POSIX record locks
+	/// owned by an open file rather than a process, so the client doesn't
+	/// send a process ID. Requests to remove a lock never have a process ID,
+	/// so they are not classified as open file description locks.
+	#[must_use]
+	pub fn is_ofd(&self) -> bool {
+		if self.is_flock() || self.lock.process_id().is_some() {
+			return false;
+		}
+		#[cfg(any(target_os = "freebsd", target_os = "linux"))]
+		if self.lock.mode() == crate::lock::F_UNLCK {
+			return false;
+		}
+		true
+	}
 }
 
 try_from_fuse_request!(SetlkRequest<'a>, |request| {
@@ -102,6 +129,24 @@ mod flags {
 	});
 }
 
+impl client::FuseRequest for SetlkRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let opcode = if self.may_block() {
+			kernel::fuse_opcode::FUSE_SETLKW
+		} else {
+			kernel::fuse_opcode::FUSE_SETLK
+		};
+		request_sender.inner.send_1(
+			opcode,
+			self.header.nodeid,
+			self.body.as_bytes(),
+		)
+	}
+}
+
 impl fmt::Debug for SetlkRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("SetlkRequest")
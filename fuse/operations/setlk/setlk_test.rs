@@ -0,0 +1,108 @@
+// Copyright 2020 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::num::NonZeroU64;
+
+use fuse::kernel;
+use fuse::server::SetlkRequest;
+use fuse::{Lock, LockRange};
+
+#[cfg(target_os = "freebsd")]
+use fuse::os::freebsd::{F_RDLCK, F_UNLCK};
+
+#[cfg(target_os = "linux")]
+use fuse::os::linux::{F_RDLCK, F_UNLCK};
+
+use fuse_testutil as testutil;
+use fuse_testutil::{decode_request, MessageBuilder};
+
+fn setlk_request(lk_flags: u32, lock_type: u32, pid: u32) -> fuse::io::MinReadBuffer {
+	MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_SETLK;
+			h.nodeid = 123;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_lk_in {
+			fh: 12,
+			owner: 34,
+			lk: testutil::new!(kernel::fuse_file_lock {
+				start: 0,
+				end: u64::MAX,
+				r#type: lock_type,
+				pid: pid,
+			}),
+			lk_flags: lk_flags,
+		}))
+		.build_aligned()
+}
+
+#[test]
+fn request_posix() {
+	let buf = setlk_request(0, F_RDLCK.0, 1000);
+	let req = decode_request!(SetlkRequest, buf);
+
+	assert_eq!(req.handle(), 12);
+	assert_eq!(req.owner(), fuse::LockOwner(34));
+	assert_eq!(req.lock(), Lock::new(
+		F_RDLCK,
+		LockRange::whole_file(),
+		fuse::LockOwnerProcessId::new(1000),
+	));
+	assert!(!req.is_flock());
+	assert!(!req.is_ofd());
+}
+
+#[test]
+fn request_flock() {
+	let buf = setlk_request(kernel::FUSE_LK_FLOCK, F_RDLCK.0, 0);
+	let req = decode_request!(SetlkRequest, buf);
+
+	assert!(req.is_flock());
+	assert!(!req.is_ofd());
+}
+
+#[test]
+fn request_ofd() {
+	let buf = setlk_request(0, F_RDLCK.0, 0);
+	let req = decode_request!(SetlkRequest, buf);
+
+	assert!(!req.is_flock());
+	assert!(req.is_ofd());
+
+	// Unlock requests have no process ID, even for POSIX locks.
+	let buf = setlk_request(0, F_UNLCK.0, 0);
+	let req = decode_request!(SetlkRequest, buf);
+	assert!(!req.is_ofd());
+}
+
+#[test]
+fn lock_range() {
+	let range = LockRange::new(10, NonZeroU64::new(5));
+	assert!(!range.contains(9));
+	assert!(range.contains(10));
+	assert!(range.contains(14));
+	assert!(!range.contains(15));
+
+	assert!(range.overlaps(&LockRange::new(14, NonZeroU64::new(1))));
+	assert!(range.overlaps(&LockRange::new(0, NonZeroU64::new(11))));
+	assert!(!range.overlaps(&LockRange::new(15, None)));
+	assert!(!range.overlaps(&LockRange::new(0, NonZeroU64::new(10))));
+	assert!(range.overlaps(&LockRange::whole_file()));
+	assert!(LockRange::from_offset(20).contains(u64::MAX));
+
+	assert_eq!(Lock::read(range), Lock::new(F_RDLCK, range, None));
+	assert_eq!(Lock::unlock(range).mode(), F_UNLCK);
+}
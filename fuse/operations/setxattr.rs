@@ -16,6 +16,7 @@
 
 use core::fmt;
 
+use crate::client;
 use crate::internal::compat;
 use crate::internal::debug;
 use crate::kernel;
@@ -45,6 +46,13 @@ impl SetxattrRequest<'_> {
 		self.name
 	}
 
+	/// Returns the `FUSE_SETXATTR` flags set by the client.
+	///
+	/// These flags are only sent if the [`SETXATTR_EXT`] init flag was
+	/// negotiated. Otherwise the request uses the legacy format, and no
+	/// flags are set.
+	///
+	/// [`SETXATTR_EXT`]: crate::FuseInitFlag::SETXATTR_EXT
 	#[inline]
 	#[must_use]
 	pub fn flags(&self) -> SetxattrRequestFlags {
@@ -56,12 +64,29 @@ impl SetxattrRequest<'_> {
 		SetxattrRequestFlags::new()
 	}
 
+	/// Returns the flags passed to `setxattr()`, such as `XATTR_CREATE` or
+	/// `XATTR_REPLACE`.
 	#[inline]
 	#[must_use]
 	pub fn setxattr_flags(&self) -> crate::SetxattrFlags {
 		self.body.as_v7p1().flags
 	}
 
+	/// Returns whether the server should clear the setgid bit of the file
+	/// when setting its `system.posix_acl_access` xattr.
+	///
+	/// The client sets this flag if the caller isn't in the file's owning
+	/// group and doesn't have `CAP_FSETID`, which matches the behavior of
+	/// `chmod()` for the same caller. It's only sent if the
+	/// [`SETXATTR_EXT`] init flag was negotiated.
+	///
+	/// [`SETXATTR_EXT`]: crate::FuseInitFlag::SETXATTR_EXT
+	#[inline]
+	#[must_use]
+	pub fn acl_kill_sgid(&self) -> bool {
+		self.flags().get(SetxattrRequestFlag::ACL_KILL_SGID)
+	}
+
 	#[inline]
 	#[must_use]
 	pub fn value(&self) -> &[u8] {
@@ -90,6 +115,26 @@ try_from_fuse_request!(SetxattrRequest<'a>, |request| {
 	Ok(Self { header, body, name, value })
 });
 
+impl client::FuseRequest for SetxattrRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let body = self.body.to_v7p33();
+		let mut body_bytes = body.as_bytes();
+		if !request_sender.layout.have_setxattr_ext() {
+			body_bytes = &body_bytes[..kernel::FUSE_COMPAT_SETXATTR_IN_SIZE];
+		}
+		request_sender.inner.send_3(
+			kernel::fuse_opcode::FUSE_SETXATTR,
+			self.header.nodeid,
+			body_bytes,
+			self.name.to_bytes_with_nul(),
+			self.value,
+		)
+	}
+}
+
 impl fmt::Debug for SetxattrRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("SetxattrRequest")
@@ -17,6 +17,7 @@
 use fuse::kernel;
 use fuse::server::SetxattrRequest;
 
+use fuse_testutil as testutil;
 use fuse_testutil::{decode_request, MessageBuilder};
 
 #[test]
@@ -37,6 +38,36 @@ fn request() {
 	assert_eq!(req.name(), c"hello.world!");
 	assert_eq!(req.value(), b"some\x00value");
 	assert_eq!(req.setxattr_flags(), 0b11);
+	assert_eq!(req.flags(), fuse::SetxattrRequestFlags::new());
+	assert_eq!(req.acl_kill_sgid(), false);
+}
+
+#[test]
+fn request_setxattr_ext() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_SETXATTR;
+			h.nodeid = 123;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_setxattr_in {
+			size: 10,
+			flags: 0b11,
+			setxattr_flags: kernel::FUSE_SETXATTR_ACL_KILL_SGID,
+		}))
+		.push_bytes(b"system.posix_acl_access\x00")
+		.push_bytes(b"some\x00value")
+		.build_aligned();
+
+	let req = decode_request!(SetxattrRequest, buf, {
+		init_flags: kernel::FUSE_SETXATTR_EXT,
+	});
+
+	assert_eq!(req.name(), c"system.posix_acl_access");
+	assert_eq!(req.value(), b"some\x00value");
+	assert_eq!(req.setxattr_flags(), 0b11);
+	assert_eq!(req.acl_kill_sgid(), true);
+	let flags = req.flags();
+	assert!(flags.get(fuse::SetxattrRequestFlag::ACL_KILL_SGID));
 }
 
 #[test]
@@ -17,7 +17,10 @@
 use core::fmt;
 use core::marker::PhantomData;
 
+use crate::client;
+use crate::internal::compat;
 use crate::kernel;
+use crate::server;
 use crate::server::decode;
 
 // StatfsRequest {{{
@@ -46,6 +49,18 @@ try_from_fuse_request!(StatfsRequest<'a>, |request| {
 	})
 });
 
+impl client::FuseRequest for StatfsRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.inner.send_0(
+			kernel::fuse_opcode::FUSE_STATFS,
+			self.node_id.get(),
+		)
+	}
+}
+
 impl fmt::Debug for StatfsRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("StatfsRequest")
@@ -165,4 +180,93 @@ impl fmt::Debug for StatfsAttributes {
 	}
 }
 
+serde_struct!(StatfsAttributes, StatfsAttributes::new(), {
+	block_count: u64 = (
+		StatfsAttributes::block_count,
+		StatfsAttributes::set_block_count,
+	),
+	block_size: u32 = (
+		StatfsAttributes::block_size,
+		StatfsAttributes::set_block_size,
+	),
+	blocks_available: u64 = (
+		StatfsAttributes::blocks_available,
+		StatfsAttributes::set_blocks_available,
+	),
+	blocks_free: u64 = (
+		StatfsAttributes::blocks_free,
+		StatfsAttributes::set_blocks_free,
+	),
+	fragment_size: u32 = (
+		StatfsAttributes::fragment_size,
+		StatfsAttributes::set_fragment_size,
+	),
+	inode_count: u64 = (
+		StatfsAttributes::inode_count,
+		StatfsAttributes::set_inode_count,
+	),
+	inodes_free: u64 = (
+		StatfsAttributes::inodes_free,
+		StatfsAttributes::set_inodes_free,
+	),
+	max_filename_length: u32 = (
+		StatfsAttributes::max_filename_length,
+		StatfsAttributes::set_max_filename_length,
+	),
+});
+
+// }}}
+
+// StatfsResponse {{{
+
+/// Response type for `FUSE_STATFS`.
+pub struct StatfsResponse {
+	attributes: StatfsAttributes,
+}
+
+impl StatfsResponse {
+	/// Creates a new `StatfsResponse` with the given filesystem attributes.
+	#[inline]
+	#[must_use]
+	pub fn new(attributes: StatfsAttributes) -> StatfsResponse {
+		Self { attributes }
+	}
+
+	/// Returns the filesystem attributes.
+	#[inline]
+	#[must_use]
+	pub fn attributes(&self) -> &StatfsAttributes {
+		&self.attributes
+	}
+
+	/// Returns a mutable reference to the filesystem attributes.
+	#[inline]
+	#[must_use]
+	pub fn attributes_mut(&mut self) -> &mut StatfsAttributes {
+		&mut self.attributes
+	}
+}
+
+impl fmt::Debug for StatfsResponse {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("StatfsResponse")
+			.field("attributes", self.attributes())
+			.finish()
+	}
+}
+
+impl server::FuseReply for StatfsResponse {
+	fn send_to<S: server::FuseSocket>(
+		&self,
+		reply_sender: server::FuseReplySender<'_, S>,
+	) -> Result<(), server::SendError<S::Error>> {
+		let raw = new!(kernel::fuse_statfs_out {
+			st: self.attributes.raw,
+		});
+		let version_minor = reply_sender.layout.version_minor();
+		let buf = compat::fuse_statfs_out_bytes(version_minor, &raw);
+		reply_sender.inner.send_1(buf)
+	}
+}
+
 // }}}
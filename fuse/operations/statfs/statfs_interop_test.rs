@@ -91,8 +91,7 @@ where
 		attr.set_inodes_free(60);
 		attr.set_max_filename_length(70);
 		attr.set_fragment_size(80);
-		let mut reply = fuse::kernel::fuse_statfs_out::new();
-		reply.st = *attr.raw();
+		let reply = server::StatfsResponse::new(attr);
 		send_reply.ok(&reply).unwrap();
 	}
 }
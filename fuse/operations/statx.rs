@@ -0,0 +1,528 @@
+// Copyright 2023 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::fmt;
+use core::time;
+
+use crate::client;
+use crate::internal::timestamp;
+use crate::kernel;
+use crate::server;
+use crate::server::decode;
+
+// StatxRequest {{{
+
+/// Request type for `FUSE_STATX`.
+#[derive(Clone, Copy)]
+pub struct StatxRequest<'a> {
+	header: &'a kernel::fuse_in_header,
+	body: &'a kernel::fuse_statx_in,
+}
+
+impl StatxRequest<'_> {
+	#[must_use]
+	pub fn node_id(&self) -> crate::NodeId {
+		unsafe { crate::NodeId::new_unchecked(self.header.nodeid) }
+	}
+
+	#[must_use]
+	pub fn handle(&self) -> Option<u64> {
+		if (self.body.getattr_flags & kernel::FUSE_GETATTR_FH) > 0 {
+			return Some(self.body.fh);
+		}
+		None
+	}
+
+	/// Returns the `AT_STATX_*` synchronization flags passed to `statx()`.
+	#[must_use]
+	pub fn flags(&self) -> u32 {
+		self.body.sx_flags
+	}
+
+	/// Returns the `STATX_*` mask of attributes requested by the caller.
+	#[must_use]
+	pub fn mask(&self) -> u32 {
+		self.body.sx_mask
+	}
+}
+
+try_from_fuse_request!(StatxRequest<'a>, |request| {
+	let mut dec = request.decoder();
+	dec.expect_opcode(kernel::fuse_opcode::FUSE_STATX)?;
+
+	let header = dec.header();
+	decode::node_id(header.nodeid)?;
+
+	let body = dec.next_sized()?;
+	Ok(Self { header, body })
+});
+
+impl client::FuseRequest for StatxRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.inner.send_1(
+			kernel::fuse_opcode::FUSE_STATX,
+			self.header.nodeid,
+			self.body.as_bytes(),
+		)
+	}
+}
+
+impl fmt::Debug for StatxRequest<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("StatxRequest")
+			.field("node_id", &self.node_id())
+			.field("handle", &format_args!("{:?}", &self.handle()))
+			.field("flags", &format_args!("{:#X}", self.flags()))
+			.field("mask", &format_args!("{:#X}", self.mask()))
+			.finish()
+	}
+}
+
+// }}}
+
+// StatxResponse {{{
+
+/// Response type for `FUSE_STATX`.
+pub struct StatxResponse {
+	raw: kernel::fuse_statx_out,
+}
+
+impl StatxResponse {
+	#[inline]
+	#[must_use]
+	pub fn new(attributes: StatxAttributes) -> StatxResponse {
+		Self {
+			raw: new!(kernel::fuse_statx_out {
+				stat: attributes.raw,
+			}),
+		}
+	}
+
+	#[inline]
+	#[must_use]
+	pub fn raw(&self) -> &kernel::fuse_statx_out {
+		&self.raw
+	}
+
+	#[inline]
+	#[must_use]
+	pub fn attributes(&self) -> &StatxAttributes {
+		unsafe { StatxAttributes::from_ref(&self.raw.stat) }
+	}
+
+	#[inline]
+	#[must_use]
+	pub fn attributes_mut(&mut self) -> &mut StatxAttributes {
+		unsafe { StatxAttributes::from_ref_mut(&mut self.raw.stat) }
+	}
+
+	/// Returns the attribute cache timeout.
+	#[inline]
+	#[must_use]
+	pub fn cache_timeout(&self) -> time::Duration {
+		timestamp::new_duration(self.raw.attr_valid, self.raw.attr_valid_nsec)
+	}
+
+	/// Sets the attribute cache timeout.
+	#[inline]
+	pub fn set_cache_timeout(&mut self, timeout: time::Duration) {
+		let (seconds, nanos) = timestamp::split_duration(timeout);
+		self.raw.attr_valid = seconds;
+		self.raw.attr_valid_nsec = nanos;
+	}
+}
+
+impl fmt::Debug for StatxResponse {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("StatxResponse")
+			.field("attributes", self.attributes())
+			.field("cache_timeout", &self.cache_timeout())
+			.finish()
+	}
+}
+
+impl server::FuseReply for StatxResponse {
+	fn send_to<S: server::FuseSocket>(
+		&self,
+		reply_sender: server::FuseReplySender<'_, S>,
+	) -> Result<(), server::SendError<S::Error>> {
+		reply_sender.inner.send_1(self.raw.as_bytes())
+	}
+}
+
+// }}}
+
+// StatxAttributes {{{
+
+const STATX_TYPE: u32 = 0x0001;
+const STATX_MODE: u32 = 0x0002;
+const STATX_NLINK: u32 = 0x0004;
+const STATX_UID: u32 = 0x0008;
+const STATX_GID: u32 = 0x0010;
+const STATX_ATIME: u32 = 0x0020;
+const STATX_MTIME: u32 = 0x0040;
+const STATX_CTIME: u32 = 0x0080;
+const STATX_INO: u32 = 0x0100;
+const STATX_SIZE: u32 = 0x0200;
+const STATX_BLOCKS: u32 = 0x0400;
+const STATX_BTIME: u32 = 0x0800;
+
+/// Extended attributes of a filesystem node, as reported by `statx()`.
+///
+/// Each field setter also sets the corresponding `STATX_*` bit in the
+/// [`mask`](Self::mask), which tells the client which fields are valid.
+///
+/// `FUSE_STATX` is only sent by clients with protocol version 7.39 or later.
+/// Older clients send `FUSE_GETATTR` instead, which can be answered with the
+/// attributes returned by [`to_node_attr`](Self::to_node_attr). Fields not
+/// present in [`NodeAttr`](crate::NodeAttr), such as the birth time, are not
+/// available to older clients.
+///
+/// The mount ID reported by `statx()` is assigned by the client, and can't be
+/// set by the filesystem.
+#[derive(Clone, Copy)]
+pub struct StatxAttributes {
+	raw: kernel::fuse_statx,
+}
+
+impl StatxAttributes {
+	/// Creates a new `StatxAttributes` for a node with the given ID.
+	#[inline]
+	#[must_use]
+	pub fn new(node_id: crate::NodeId) -> StatxAttributes {
+		Self {
+			raw: new!(kernel::fuse_statx {
+				mask: STATX_INO,
+				ino: node_id.get(),
+			}),
+		}
+	}
+
+	/// Creates a new `StatxAttributes` from the attributes of a node.
+	#[must_use]
+	pub fn from_node_attr(attr: &crate::NodeAttr) -> StatxAttributes {
+		let mut stx = StatxAttributes::new(attr.node_id());
+		stx.set_mode(attr.mode());
+		stx.set_size(attr.size());
+		stx.set_atime(attr.atime());
+		stx.set_mtime(attr.mtime());
+		stx.set_ctime(attr.ctime());
+		stx.set_link_count(attr.link_count());
+		stx.set_user_id(attr.user_id());
+		stx.set_group_id(attr.group_id());
+		let rdev = attr.device_number();
+		stx.set_device_number(
+			(rdev & 0xFFF00) >> 8,
+			(rdev & 0xFF) | ((rdev >> 12) & 0xFFF00),
+		);
+		stx.set_block_count(attr.block_count());
+		stx.set_block_size(attr.block_size());
+		stx
+	}
+
+	/// Returns the attributes as a [`NodeAttr`](crate::NodeAttr), for use
+	/// in replies to clients that don't support `FUSE_STATX`.
+	#[must_use]
+	pub fn to_node_attr(&self) -> crate::NodeAttr {
+		let mut attr = crate::NodeAttr::new(self.node_id());
+		attr.set_mode(self.mode());
+		attr.set_size(self.size());
+		attr.set_atime(self.atime());
+		attr.set_mtime(self.mtime());
+		attr.set_ctime(self.ctime());
+		attr.set_link_count(self.link_count());
+		attr.set_user_id(self.user_id());
+		attr.set_group_id(self.group_id());
+		let (major, minor) = self.device_number();
+		attr.set_device_number(
+			(minor & 0xFF) | (major << 8) | ((minor & !0xFF) << 12),
+		);
+		attr.set_block_count(self.block_count());
+		attr.set_block_size(self.block_size());
+		attr
+	}
+
+	/// Returns the raw [`fuse_statx`] for the node attributes.
+	///
+	/// [`fuse_statx`]: kernel::fuse_statx
+	#[inline]
+	#[must_use]
+	pub fn raw(&self) -> &kernel::fuse_statx {
+		&self.raw
+	}
+
+	#[inline]
+	#[must_use]
+	unsafe fn from_ref(raw: &kernel::fuse_statx) -> &Self {
+		let raw_ptr = raw as *const kernel::fuse_statx;
+		&*(raw_ptr.cast::<StatxAttributes>())
+	}
+
+	#[inline]
+	#[must_use]
+	unsafe fn from_ref_mut(raw: &mut kernel::fuse_statx) -> &mut Self {
+		let raw_ptr = raw as *mut kernel::fuse_statx;
+		&mut *(raw_ptr.cast::<StatxAttributes>())
+	}
+
+	/// Returns the `STATX_*` mask of fields that have been set.
+	#[inline]
+	#[must_use]
+	pub fn mask(&self) -> u32 {
+		self.raw.mask
+	}
+
+	/// Returns the per-mount unique identifier of the node.
+	#[inline]
+	#[must_use]
+	pub fn node_id(&self) -> crate::NodeId {
+		unsafe { crate::NodeId::new_unchecked(self.raw.ino) }
+	}
+
+	/// Returns the node's mode, including type and permissions.
+	#[inline]
+	#[must_use]
+	pub fn mode(&self) -> crate::FileMode {
+		crate::FileMode::new(u32::from(self.raw.mode))
+	}
+
+	/// Sets the node's mode, including type and permissions.
+	#[inline]
+	pub fn set_mode(&mut self, mode: crate::FileMode) {
+		self.raw.mode = mode.get() as u16;
+		self.raw.mask |= STATX_TYPE | STATX_MODE;
+	}
+
+	/// Returns the node's size.
+	#[inline]
+	#[must_use]
+	pub fn size(&self) -> u64 {
+		self.raw.size
+	}
+
+	/// Sets the node's size.
+	#[inline]
+	pub fn set_size(&mut self, size: u64) {
+		self.raw.size = size;
+		self.raw.mask |= STATX_SIZE;
+	}
+
+	/// Returns the node's last access time.
+	#[inline]
+	#[must_use]
+	pub fn atime(&self) -> crate::UnixTime {
+		sx_time(&self.raw.atime)
+	}
+
+	/// Sets the node's last access time.
+	#[inline]
+	pub fn set_atime(&mut self, atime: crate::UnixTime) {
+		set_sx_time(&mut self.raw.atime, atime);
+		self.raw.mask |= STATX_ATIME;
+	}
+
+	/// Returns the node's creation time.
+	#[inline]
+	#[must_use]
+	pub fn btime(&self) -> crate::UnixTime {
+		sx_time(&self.raw.btime)
+	}
+
+	/// Sets the node's creation time.
+	#[inline]
+	pub fn set_btime(&mut self, btime: crate::UnixTime) {
+		set_sx_time(&mut self.raw.btime, btime);
+		self.raw.mask |= STATX_BTIME;
+	}
+
+	/// Returns the node's last status change time.
+	#[inline]
+	#[must_use]
+	pub fn ctime(&self) -> crate::UnixTime {
+		sx_time(&self.raw.ctime)
+	}
+
+	/// Sets the node's last status change time.
+	#[inline]
+	pub fn set_ctime(&mut self, ctime: crate::UnixTime) {
+		set_sx_time(&mut self.raw.ctime, ctime);
+		self.raw.mask |= STATX_CTIME;
+	}
+
+	/// Returns the node's last content modification time.
+	#[inline]
+	#[must_use]
+	pub fn mtime(&self) -> crate::UnixTime {
+		sx_time(&self.raw.mtime)
+	}
+
+	/// Sets the node's last content modification time.
+	#[inline]
+	pub fn set_mtime(&mut self, mtime: crate::UnixTime) {
+		set_sx_time(&mut self.raw.mtime, mtime);
+		self.raw.mask |= STATX_MTIME;
+	}
+
+	/// Returns the node's link count.
+	#[inline]
+	#[must_use]
+	pub fn link_count(&self) -> u32 {
+		self.raw.nlink
+	}
+
+	/// Sets the node's link count.
+	#[inline]
+	pub fn set_link_count(&mut self, link_count: u32) {
+		self.raw.nlink = link_count;
+		self.raw.mask |= STATX_NLINK;
+	}
+
+	/// Returns the node's owning user ID.
+	#[inline]
+	#[must_use]
+	pub fn user_id(&self) -> u32 {
+		self.raw.uid
+	}
+
+	/// Sets the node's owning user ID.
+	#[inline]
+	pub fn set_user_id(&mut self, user_id: u32) {
+		self.raw.uid = user_id;
+		self.raw.mask |= STATX_UID;
+	}
+
+	/// Returns the node's owning group ID.
+	#[inline]
+	#[must_use]
+	pub fn group_id(&self) -> u32 {
+		self.raw.gid
+	}
+
+	/// Sets the node's owning group ID.
+	#[inline]
+	pub fn set_group_id(&mut self, group_id: u32) {
+		self.raw.gid = group_id;
+		self.raw.mask |= STATX_GID;
+	}
+
+	/// Returns the major and minor device number of a block or character
+	/// device node.
+	#[inline]
+	#[must_use]
+	pub fn device_number(&self) -> (u32, u32) {
+		(self.raw.rdev_major, self.raw.rdev_minor)
+	}
+
+	/// Sets the major and minor device number of a block or character
+	/// device node.
+	#[inline]
+	pub fn set_device_number(&mut self, major: u32, minor: u32) {
+		self.raw.rdev_major = major;
+		self.raw.rdev_minor = minor;
+	}
+
+	/// Returns the number of 512-byte blocks allocated by the node.
+	#[inline]
+	#[must_use]
+	pub fn block_count(&self) -> u64 {
+		self.raw.blocks
+	}
+
+	/// Sets the number of 512-byte blocks allocated by the node.
+	#[inline]
+	pub fn set_block_count(&mut self, block_count: u64) {
+		self.raw.blocks = block_count;
+		self.raw.mask |= STATX_BLOCKS;
+	}
+
+	/// Returns the preferred block size for I/O to the node.
+	#[inline]
+	#[must_use]
+	pub fn block_size(&self) -> u32 {
+		self.raw.blksize
+	}
+
+	/// Sets the preferred block size for I/O to the node.
+	#[inline]
+	pub fn set_block_size(&mut self, block_size: u32) {
+		self.raw.blksize = block_size;
+	}
+
+	/// Returns the node's `STATX_ATTR_*` attribute flags.
+	#[inline]
+	#[must_use]
+	pub fn attributes(&self) -> u64 {
+		self.raw.attributes
+	}
+
+	/// Returns the mask of `STATX_ATTR_*` attribute flags supported by the
+	/// filesystem.
+	#[inline]
+	#[must_use]
+	pub fn attributes_mask(&self) -> u64 {
+		self.raw.attributes_mask
+	}
+
+	/// Sets the node's `STATX_ATTR_*` attribute flags, and the mask of
+	/// attribute flags supported by the filesystem.
+	///
+	/// Flags not present in `attributes_mask` are cleared.
+	#[inline]
+	pub fn set_attributes(&mut self, attributes: u64, attributes_mask: u64) {
+		self.raw.attributes = attributes & attributes_mask;
+		self.raw.attributes_mask = attributes_mask;
+	}
+}
+
+impl fmt::Debug for StatxAttributes {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("StatxAttributes")
+			.field("mask", &format_args!("{:#X}", self.mask()))
+			.field("node_id", &self.node_id())
+			.field("mode", &self.mode())
+			.field("size", &self.size())
+			.field("atime", &self.atime())
+			.field("btime", &self.btime())
+			.field("ctime", &self.ctime())
+			.field("mtime", &self.mtime())
+			.field("link_count", &self.link_count())
+			.field("user_id", &self.user_id())
+			.field("group_id", &self.group_id())
+			.field("device_number", &self.device_number())
+			.field("block_count", &self.block_count())
+			.field("block_size", &self.block_size())
+			.field("attributes", &format_args!("{:#X}", self.attributes()))
+			.field(
+				"attributes_mask",
+				&format_args!("{:#X}", self.attributes_mask()),
+			)
+			.finish()
+	}
+}
+
+fn sx_time(raw: &kernel::fuse_sx_time) -> crate::UnixTime {
+	unsafe { crate::UnixTime::new_unchecked(raw.tv_sec, raw.tv_nsec) }
+}
+
+fn set_sx_time(raw: &mut kernel::fuse_sx_time, time: crate::UnixTime) {
+	raw.tv_sec = time.seconds();
+	raw.tv_nsec = time.nanos();
+}
+
+// }}}
@@ -0,0 +1,201 @@
+// Copyright 2023 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::mem::size_of;
+use std::time;
+
+use fuse::kernel;
+use fuse::StatxAttributes;
+use fuse::server::{StatxRequest, StatxResponse};
+
+use fuse_testutil as testutil;
+use fuse_testutil::{
+	decode_request,
+	encode_request,
+	encode_response,
+	MessageBuilder,
+};
+
+#[test]
+fn request() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_STATX;
+			h.nodeid = 123;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_statx_in {
+			sx_flags: 0x800,
+			sx_mask: 0xFFF,
+		}))
+		.build_aligned();
+
+	let req = decode_request!(StatxRequest, buf);
+
+	assert_eq!(req.node_id(), fuse::NodeId::new(123).unwrap());
+	assert_eq!(req.handle(), None);
+	assert_eq!(req.flags(), 0x800);
+	assert_eq!(req.mask(), 0xFFF);
+
+	let encoded = encode_request!(req);
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
+}
+
+#[test]
+fn request_with_handle() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_STATX;
+			h.nodeid = 123;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_statx_in {
+			getattr_flags: kernel::FUSE_GETATTR_FH,
+			fh: 456,
+		}))
+		.build_aligned();
+
+	let req = decode_request!(StatxRequest, buf);
+	assert_eq!(req.handle(), Some(456));
+}
+
+#[test]
+fn request_impl_debug() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_STATX;
+			h.nodeid = 123;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_statx_in {
+			sx_flags: 0x800,
+			sx_mask: 0xFFF,
+		}))
+		.build_aligned();
+	let request = decode_request!(StatxRequest, buf);
+
+	assert_eq!(
+		format!("{:#?}", request),
+		concat!(
+			"StatxRequest {\n",
+			"    node_id: 123,\n",
+			"    handle: None,\n",
+			"    flags: 0x800,\n",
+			"    mask: 0xFFF,\n",
+			"}",
+		),
+	);
+}
+
+#[test]
+fn response() {
+	let mut attr = StatxAttributes::new(fuse::NodeId::new(11).unwrap());
+	attr.set_btime(fuse::UnixTime::new(1234, 5678).unwrap());
+	let mut resp = StatxResponse::new(attr);
+	resp.set_cache_timeout(time::Duration::new(123, 456));
+	resp.attributes_mut().set_size(999);
+	assert_eq!(resp.cache_timeout(), time::Duration::new(123, 456));
+
+	let encoded = encode_response!(&resp);
+
+	let mut expect = testutil::new!(kernel::fuse_statx_out {
+		attr_valid: 123,
+		attr_valid_nsec: 456,
+	});
+	expect.stat.mask = 0x100 | 0x800 | 0x200;
+	expect.stat.ino = 11;
+	expect.stat.size = 999;
+	expect.stat.btime.tv_sec = 1234;
+	expect.stat.btime.tv_nsec = 5678;
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>()
+					+ size_of::<kernel::fuse_statx_out>()) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_sized(&expect)
+			.build()
+	);
+}
+
+#[test]
+fn attributes_mask() {
+	let mut attr = StatxAttributes::new(fuse::NodeId::new(11).unwrap());
+	assert_eq!(attr.mask(), 0x100); // STATX_INO
+
+	attr.set_mode(fuse::FileMode::S_IFREG | 0o644);
+	attr.set_link_count(1);
+	attr.set_btime(fuse::UnixTime::from_seconds(100));
+	assert_eq!(attr.mask(), 0x100 | 0x1 | 0x2 | 0x4 | 0x800);
+
+	attr.set_attributes(0x20 | 0x10, 0x20);
+	assert_eq!(attr.attributes(), 0x20);
+	assert_eq!(attr.attributes_mask(), 0x20);
+}
+
+#[test]
+fn attributes_node_attr_fallback() {
+	let mut attr = fuse::NodeAttr::new(fuse::NodeId::new(11).unwrap());
+	attr.set_mode(fuse::FileMode::S_IFCHR | 0o600);
+	attr.set_size(100);
+	attr.set_mtime(fuse::UnixTime::new(10, 20).unwrap());
+	attr.set_link_count(2);
+	attr.set_user_id(1000);
+	attr.set_group_id(1001);
+	attr.set_device_number(0x12345678);
+	attr.set_block_count(8);
+	attr.set_block_size(4096);
+
+	let mut stx = StatxAttributes::from_node_attr(&attr);
+	assert_eq!(stx.mode(), fuse::FileMode::S_IFCHR | 0o600);
+	assert_eq!(stx.mtime(), fuse::UnixTime::new(10, 20).unwrap());
+	assert_eq!(stx.device_number(), (0x456, 0x12378));
+
+	// Fields that are not representable in `NodeAttr` are dropped.
+	stx.set_btime(fuse::UnixTime::from_seconds(5));
+	assert_eq!(stx.to_node_attr().raw(), attr.raw());
+}
+
+#[test]
+fn attributes_impl_debug() {
+	let mut attr = StatxAttributes::new(fuse::NodeId::new(11).unwrap());
+	attr.set_mode(fuse::FileMode::S_IFREG | 0o644);
+	attr.set_btime(fuse::UnixTime::new(100, 5).unwrap());
+
+	assert_eq!(
+		format!("{:?}", attr),
+		concat!(
+			"StatxAttributes {",
+			" mask: 0x903,",
+			" node_id: 11,",
+			" mode: 0o100644,",
+			" size: 0,",
+			" atime: UnixTime(0.000000000),",
+			" btime: UnixTime(100.000000005),",
+			" ctime: UnixTime(0.000000000),",
+			" mtime: UnixTime(0.000000000),",
+			" link_count: 0,",
+			" user_id: 0,",
+			" group_id: 0,",
+			" device_number: (0, 0),",
+			" block_count: 0,",
+			" block_size: 0,",
+			" attributes: 0x0,",
+			" attributes_mask: 0x0",
+			" }",
+		),
+	);
+}
@@ -18,7 +18,9 @@
 
 use core::fmt;
 
+use crate::client;
 use crate::kernel;
+use crate::operations::security_ctx::SecurityContexts;
 use crate::server::decode;
 
 // SymlinkRequest {{{
@@ -29,9 +31,10 @@ pub struct SymlinkRequest<'a> {
 	parent_id: crate::NodeId,
 	name: &'a crate::NodeName,
 	content: &'a core::ffi::CStr,
+	security_ctx: SecurityContexts<'a>,
 }
 
-impl SymlinkRequest<'_> {
+impl<'a> SymlinkRequest<'a> {
 	#[must_use]
 	pub fn parent_id(&self) -> crate::NodeId {
 		self.parent_id
@@ -46,6 +49,15 @@ impl SymlinkRequest<'_> {
 	pub fn content(&self) -> &core::ffi::CStr {
 		self.content
 	}
+
+	/// Returns the security contexts to apply to the new node.
+	///
+	/// Security contexts are only present if the `SECURITY_CTX` init flag
+	/// was negotiated.
+	#[must_use]
+	pub fn security_contexts(&self) -> SecurityContexts<'a> {
+		self.security_ctx
+	}
 }
 
 try_from_fuse_request!(SymlinkRequest<'a>, |request| {
@@ -53,13 +65,34 @@ try_from_fuse_request!(SymlinkRequest<'a>, |request| {
 	dec.expect_opcode(kernel::fuse_opcode::FUSE_SYMLINK)?;
 	let content = dec.next_cstr()?;
 	let name = dec.next_node_name()?;
+	let security_ctx = SecurityContexts::decode(&mut dec, request.layout)?;
 	Ok(Self {
 		parent_id: decode::node_id(dec.header().nodeid)?,
 		name,
 		content,
+		security_ctx,
 	})
 });
 
+impl client::FuseRequest for SymlinkRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let ext = self.security_ctx.extension(request_sender.layout);
+		let mut inner = request_sender.inner;
+		inner.set_extensions_len(ext.len());
+		inner.send_4(
+			kernel::fuse_opcode::FUSE_SYMLINK,
+			self.parent_id.get(),
+			self.content.to_bytes_with_nul(),
+			self.name.as_bytes(),
+			b"\0",
+			ext,
+		)
+	}
+}
+
 impl fmt::Debug for SymlinkRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("SymlinkRequest")
@@ -17,7 +17,8 @@
 use fuse::kernel;
 use fuse::server::SymlinkRequest;
 
-use fuse_testutil::{decode_request, MessageBuilder};
+use fuse_testutil as testutil;
+use fuse_testutil::{decode_request, encode_request, MessageBuilder};
 
 #[test]
 fn request() {
@@ -59,3 +60,43 @@ fn request_impl_debug() {
 		),
 	);
 }
+
+#[test]
+fn request_security_contexts() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_SYMLINK;
+			h.nodeid = 100;
+			h.total_extlen = 5;
+		})
+		.push_bytes(b"link content\x00")
+		.push_bytes(b"link name\x00")
+		.push_sized(&testutil::new!(kernel::fuse_secctx_header {
+			size: 40,
+			nr_secctx: 1,
+		}))
+		.push_sized(&testutil::new!(kernel::fuse_secctx {
+			size: 6,
+		}))
+		.push_bytes(b"security.selinux\x00")
+		.push_bytes(b"label\x00")
+		.push_bytes(&[0; 1])
+		.build_aligned();
+	let request = decode_request!(SymlinkRequest, buf, {
+		init_flags: kernel::FUSE_SECURITY_CTX,
+	});
+
+	assert_eq!(request.name(), "link name");
+	assert_eq!(request.content(), c"link content");
+
+	let mut contexts = request.security_contexts();
+	let context = contexts.next().unwrap();
+	assert_eq!(context.name(), c"security.selinux");
+	assert_eq!(context.value(), b"label\x00");
+	assert!(contexts.next().is_none());
+
+	let encoded = encode_request!(request, {
+		init_flags: kernel::FUSE_SECURITY_CTX,
+	});
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
+}
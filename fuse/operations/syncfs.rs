@@ -0,0 +1,117 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::fmt;
+
+use crate::client;
+use crate::kernel;
+use crate::server;
+use crate::server::decode;
+
+// SyncfsRequest {{{
+
+/// Request type for `FUSE_SYNCFS`.
+///
+/// The kernel sends `FUSE_SYNCFS` for the `syncfs()` system call, and when
+/// the filesystem is synced as part of `sync()`. The server should persist
+/// all of the filesystem's pending writes before replying.
+#[derive(Clone, Copy)]
+pub struct SyncfsRequest<'a> {
+	header: &'a kernel::fuse_in_header,
+	body: &'a kernel::fuse_syncfs_in,
+}
+
+impl SyncfsRequest<'_> {
+	/// Returns the ID of the root node of the mount being synced.
+	///
+	/// This is [`NodeId::ROOT`] unless the server has created submounts,
+	/// in which case it identifies the submount's root directory.
+	///
+	/// [`NodeId::ROOT`]: crate::NodeId::ROOT
+	#[must_use]
+	pub fn node_id(&self) -> crate::NodeId {
+		unsafe { crate::NodeId::new_unchecked(self.header.nodeid) }
+	}
+}
+
+try_from_fuse_request!(SyncfsRequest<'a>, |request| {
+	let mut dec = request.decoder();
+	dec.expect_opcode(kernel::fuse_opcode::FUSE_SYNCFS)?;
+
+	let header = dec.header();
+	let body = dec.next_sized()?;
+	decode::node_id(header.nodeid)?;
+	Ok(Self { header, body })
+});
+
+impl client::FuseRequest for SyncfsRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.inner.send_1(
+			kernel::fuse_opcode::FUSE_SYNCFS,
+			self.header.nodeid,
+			self.body.as_bytes(),
+		)
+	}
+}
+
+impl fmt::Debug for SyncfsRequest<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("SyncfsRequest")
+			.field("node_id", &self.node_id())
+			.finish()
+	}
+}
+
+// }}}
+
+// SyncfsResponse {{{
+
+/// Response type for `FUSE_SYNCFS`.
+///
+/// A successful `FUSE_SYNCFS` reply has no body. Errors should be sent
+/// with [`FuseReplySender::err`](server::FuseReplySender::err).
+pub struct SyncfsResponse {
+	_priv: (),
+}
+
+impl SyncfsResponse {
+	/// Creates a new `SyncfsResponse`.
+	#[inline]
+	#[must_use]
+	pub fn new() -> SyncfsResponse {
+		Self { _priv: () }
+	}
+}
+
+impl fmt::Debug for SyncfsResponse {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("SyncfsResponse").finish()
+	}
+}
+
+impl server::FuseReply for SyncfsResponse {
+	fn send_to<S: server::FuseSocket>(
+		&self,
+		reply_sender: server::FuseReplySender<'_, S>,
+	) -> Result<(), server::SendError<S::Error>> {
+		reply_sender.inner.send_0(0)
+	}
+}
+
+// }}}
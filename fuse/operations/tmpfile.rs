@@ -0,0 +1,149 @@
+// Copyright 2023 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::fmt;
+
+use crate::client;
+use crate::kernel;
+use crate::operations::security_ctx::SecurityContexts;
+use crate::server::decode;
+
+// TmpfileRequest {{{
+
+/// Request type for `FUSE_TMPFILE`.
+///
+/// The new node is created without a name in the directory identified by
+/// [`node_id`](Self::node_id), and should be opened as if by `FUSE_CREATE`.
+/// The response type is [`CreateResponse`].
+///
+/// [`CreateResponse`]: crate::server::CreateResponse
+#[derive(Clone, Copy)]
+pub struct TmpfileRequest<'a> {
+	header: &'a kernel::fuse_in_header,
+	body: &'a kernel::fuse_create_in,
+	security_ctx: SecurityContexts<'a>,
+}
+
+impl<'a> TmpfileRequest<'a> {
+	#[must_use]
+	pub fn node_id(&self) -> crate::NodeId {
+		unsafe { crate::NodeId::new_unchecked(self.header.nodeid) }
+	}
+
+	#[must_use]
+	#[allow(clippy::misnamed_getters)]
+	pub fn flags(&self) -> crate::CreateRequestFlags {
+		crate::CreateRequestFlags {
+			bits: self.body.open_flags,
+		}
+	}
+
+	#[must_use]
+	#[allow(clippy::misnamed_getters)]
+	pub fn open_flags(&self) -> crate::OpenFlags {
+		crate::OpenFlags::new(self.body.flags)
+	}
+
+	#[must_use]
+	pub fn mode(&self) -> crate::FileMode {
+		crate::FileMode::new(self.body.mode)
+	}
+
+	/// Returns the umask of the process that created the file.
+	#[must_use]
+	pub fn umask(&self) -> u32 {
+		self.body.umask
+	}
+
+	/// Returns the mode of the new file, with the [`umask`] applied.
+	///
+	/// Unless the [`DONT_MASK`] init flag was negotiated, the kernel applies
+	/// the umask to the mode before sending the request. Otherwise the
+	/// server is responsible for applying it, for example so that a default
+	/// ACL of the parent directory can be used instead. Applying the umask is
+	/// idempotent, so servers that don't implement default ACLs should
+	/// always use this method rather than [`mode`].
+	///
+	/// [`umask`]: Self::umask
+	/// [`mode`]: Self::mode
+	/// [`DONT_MASK`]: crate::FuseInitFlag::DONT_MASK
+	#[must_use]
+	pub fn effective_mode(&self) -> crate::FileMode {
+		self.mode().with_umask(self.umask())
+	}
+
+	/// Returns the security contexts to apply to the new node.
+	///
+	/// Security contexts are only present if the `SECURITY_CTX` init flag
+	/// was negotiated.
+	#[must_use]
+	pub fn security_contexts(&self) -> SecurityContexts<'a> {
+		self.security_ctx
+	}
+}
+
+try_from_fuse_request!(TmpfileRequest<'a>, |request| {
+	let mut dec = request.decoder();
+	dec.expect_opcode(kernel::fuse_opcode::FUSE_TMPFILE)?;
+
+	let header = dec.header();
+	decode::node_id(header.nodeid)?;
+
+	let body = dec.next_sized()?;
+
+	// The client sends a placeholder name (`"/"` on Linux), which is not a
+	// valid node name and is otherwise ignored.
+	dec.next_cstr()?;
+	let security_ctx = SecurityContexts::decode(&mut dec, request.layout)?;
+
+	Ok(Self {
+		header,
+		body,
+		security_ctx,
+	})
+});
+
+impl client::FuseRequest for TmpfileRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let ext = self.security_ctx.extension(request_sender.layout);
+		let mut inner = request_sender.inner;
+		inner.set_extensions_len(ext.len());
+		inner.send_3(
+			kernel::fuse_opcode::FUSE_TMPFILE,
+			self.header.nodeid,
+			self.body.as_bytes(),
+			b"/\0",
+			ext,
+		)
+	}
+}
+
+impl fmt::Debug for TmpfileRequest<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("TmpfileRequest")
+			.field("node_id", &self.node_id())
+			.field("flags", &self.flags())
+			.field("open_flags", &self.open_flags())
+			.field("mode", &self.mode())
+			.field("umask", &self.umask())
+			.finish()
+	}
+}
+
+// }}}
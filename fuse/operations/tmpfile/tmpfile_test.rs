@@ -0,0 +1,124 @@
+// Copyright 2023 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use fuse::kernel;
+use fuse::server::TmpfileRequest;
+
+use fuse_testutil as testutil;
+use fuse_testutil::{decode_request, encode_request, MessageBuilder};
+
+#[test]
+fn request() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_TMPFILE;
+			h.nodeid = 123;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_create_in {
+			flags: 0xFF,
+			mode: 0xEE,
+			umask: 0xDD,
+			open_flags: kernel::FUSE_OPEN_KILL_SUIDGID,
+		}))
+		.push_bytes(b"/\x00")
+		.build_aligned();
+
+	let req = decode_request!(TmpfileRequest, buf);
+
+	let expect_flags = {
+		let mut flags = fuse::CreateRequestFlags::new();
+		flags.set(fuse::CreateRequestFlag::KILL_SUIDGID);
+		flags
+	};
+
+	assert_eq!(req.node_id(), fuse::NodeId::new(123).unwrap());
+	assert_eq!(req.flags(), expect_flags);
+	assert_eq!(req.open_flags().get(), 0xFF);
+	assert_eq!(req.mode(), fuse::FileMode::new(0xEE));
+	assert_eq!(req.umask(), 0xDD);
+	assert_eq!(req.effective_mode(), fuse::FileMode::new(0x22));
+	assert!(req.security_contexts().is_empty());
+
+	let encoded = encode_request!(req);
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
+}
+
+#[test]
+fn request_security_contexts() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_TMPFILE;
+			h.nodeid = 123;
+			h.total_extlen = 6;
+		})
+		.push_sized(&kernel::fuse_create_in::new())
+		.push_bytes(b"/\x00")
+		.push_sized(&testutil::new!(kernel::fuse_secctx_header {
+			size: 48,
+			nr_secctx: 1,
+		}))
+		.push_sized(&testutil::new!(kernel::fuse_secctx {
+			size: 10,
+		}))
+		.push_bytes(b"security.selinux\x00")
+		.push_bytes(b"unlabeled\x00")
+		.push_bytes(&[0; 5])
+		.build_aligned();
+
+	let req = decode_request!(TmpfileRequest, buf, {
+		init_flags: kernel::FUSE_SECURITY_CTX,
+	});
+
+	let contexts: Vec<_> = req.security_contexts().collect();
+	assert_eq!(contexts.len(), 1);
+	assert_eq!(contexts[0].name(), c"security.selinux");
+	assert_eq!(contexts[0].value(), b"unlabeled\x00");
+
+	let encoded = encode_request!(req, {
+		init_flags: kernel::FUSE_SECURITY_CTX,
+	});
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
+}
+
+#[test]
+fn request_impl_debug() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_TMPFILE;
+			h.nodeid = 123;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_create_in {
+			flags: 0xFF,
+			mode: 0o100644,
+			umask: 0o22,
+		}))
+		.push_bytes(b"/\x00")
+		.build_aligned();
+	let request = decode_request!(TmpfileRequest, buf);
+
+	assert_eq!(
+		format!("{:#?}", request),
+		concat!(
+			"TmpfileRequest {\n",
+			"    node_id: 123,\n",
+			"    flags: CreateRequestFlags {},\n",
+			"    open_flags: 0x000000FF,\n",
+			"    mode: 0o100644,\n",
+			"    umask: 18,\n",
+			"}",
+		),
+	);
+}
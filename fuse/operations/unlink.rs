@@ -16,6 +16,7 @@
 
 //! Implements the `FUSE_UNLINK` operation.
 
+use crate::client;
 use crate::kernel;
 use crate::server::decode;
 
@@ -49,4 +50,18 @@ try_from_fuse_request!(UnlinkRequest<'a>, |request| {
 	})
 });
 
+impl client::FuseRequest for UnlinkRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		request_sender.inner.send_2(
+			kernel::fuse_opcode::FUSE_UNLINK,
+			self.parent_id.get(),
+			self.name.as_bytes(),
+			b"\0",
+		)
+	}
+}
+
 // }}}
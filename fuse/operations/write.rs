@@ -20,6 +20,7 @@ use core::fmt;
 use core::mem;
 use core::slice;
 
+use crate::client;
 use crate::internal::compat;
 use crate::internal::debug;
 use crate::kernel;
@@ -131,9 +132,24 @@ impl WriteRequest<'_> {
 	#[must_use]
 	pub fn open_flags(&self) -> crate::OpenFlags {
 		if let Some(body) = self.body_v7p9() {
-			return body.flags;
+			return crate::OpenFlags::new(body.flags);
 		}
-		0
+		crate::OpenFlags::new(0)
+	}
+
+	/// Returns whether the server should clear the setuid and setgid bits
+	/// of the file, along with its `security.capability` xattr.
+	///
+	/// This is only set if the [`HANDLE_KILLPRIV_V2`] init flag was
+	/// negotiated, and the writing process doesn't have `CAP_FSETID`. See
+	/// [`FileMode::without_suidgid`] for which bits should be cleared.
+	///
+	/// [`HANDLE_KILLPRIV_V2`]: crate::FuseInitFlag::HANDLE_KILLPRIV_V2
+	/// [`FileMode::without_suidgid`]: crate::FileMode::without_suidgid
+	#[must_use]
+	pub fn kill_suidgid(&self) -> bool {
+		let write_flags = self.body_v7p1().write_flags;
+		write_flags & kernel::FUSE_WRITE_KILL_SUIDGID != 0
 	}
 }
 
@@ -178,6 +194,57 @@ impl<'a> WriteRequest<'a> {
 	}
 }
 
+impl client::CuseRequest for WriteRequest<'_> {
+	fn send_to<S: client::io::CuseSocket>(
+		&self,
+		request_sender: client::CuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let version_minor = request_sender.layout.version_minor();
+		self.send(request_sender.inner, version_minor)
+	}
+}
+
+impl client::FuseRequest for WriteRequest<'_> {
+	fn send_to<S: client::io::FuseSocket>(
+		&self,
+		request_sender: client::FuseRequestSender<'_, S>,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let version_minor = request_sender.layout.version_minor();
+		self.send(request_sender.inner, version_minor)
+	}
+}
+
+impl WriteRequest<'_> {
+	fn send<S: client::io::Socket>(
+		&self,
+		request_sender: client::RequestSender<'_, S>,
+		version_minor: u32,
+	) -> Result<(), client::io::SendError<S::Error>> {
+		let body = match self.body_v7p9() {
+			Some(body) => *body,
+			None => {
+				let body_v7p1 = self.body_v7p1();
+				new!(kernel::fuse_write_in {
+					fh: body_v7p1.fh,
+					offset: body_v7p1.offset,
+					size: body_v7p1.size,
+					write_flags: body_v7p1.write_flags,
+				})
+			},
+		};
+		let mut body_bytes = body.as_bytes();
+		if version_minor < 9 {
+			body_bytes = &body_bytes[..kernel::FUSE_COMPAT_WRITE_IN_SIZE];
+		}
+		request_sender.send_2(
+			kernel::fuse_opcode::FUSE_WRITE,
+			self.header().nodeid,
+			body_bytes,
+			self.value(),
+		)
+	}
+}
+
 impl fmt::Debug for WriteRequest<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("WriteRequest")
@@ -187,13 +254,66 @@ impl fmt::Debug for WriteRequest<'_> {
 			.field("value", &debug::bytes(self.value()))
 			.field("flags", &self.flags())
 			.field("lock_owner", &format_args!("{:?}", &self.lock_owner()))
-			.field("open_flags", &debug::hex_u32(self.open_flags()))
+			.field("open_flags", &self.open_flags())
 			.finish()
 	}
 }
 
 // }}}
 
+// WriteResponse {{{
+
+/// Response type for `FUSE_WRITE`.
+pub struct WriteResponse {
+	raw: kernel::fuse_write_out,
+}
+
+impl WriteResponse {
+	/// Creates a new `WriteResponse` with the number of bytes written.
+	#[inline]
+	#[must_use]
+	pub fn new(size: u32) -> WriteResponse {
+		let mut raw = kernel::fuse_write_out::new();
+		raw.size = size;
+		Self { raw }
+	}
+
+	/// Returns the number of bytes written.
+	#[inline]
+	#[must_use]
+	pub fn size(&self) -> u32 {
+		self.raw.size
+	}
+}
+
+impl fmt::Debug for WriteResponse {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("WriteResponse")
+			.field("size", &self.size())
+			.finish()
+	}
+}
+
+impl server::CuseReply for WriteResponse {
+	fn send_to<S: server::CuseSocket>(
+		&self,
+		reply_sender: server::CuseReplySender<'_, S>,
+	) -> Result<(), server::SendError<S::Error>> {
+		reply_sender.inner.send_1(self.raw.as_bytes())
+	}
+}
+
+impl server::FuseReply for WriteResponse {
+	fn send_to<S: server::FuseSocket>(
+		&self,
+		reply_sender: server::FuseReplySender<'_, S>,
+	) -> Result<(), server::SendError<S::Error>> {
+		reply_sender.inner.send_1(self.raw.as_bytes())
+	}
+}
+
+// }}}
+
 // WriteRequestFlags {{{
 
 #[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
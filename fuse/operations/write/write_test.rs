@@ -14,11 +14,18 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
+use core::mem::size_of;
+
 use fuse::kernel;
-use fuse::server::WriteRequest;
+use fuse::server::{WriteRequest, WriteResponse};
 
 use fuse_testutil as testutil;
-use fuse_testutil::{decode_request, MessageBuilder};
+use fuse_testutil::{
+	decode_request,
+	encode_request,
+	encode_response,
+	MessageBuilder,
+};
 
 const DUMMY_WRITE_FLAG: u32 = 0x80000000;
 
@@ -44,8 +51,13 @@ fn request_v7p1() {
 	assert_eq!(req.offset(), 45);
 	assert_eq!(req.lock_owner(), None);
 	assert_eq!(req.flags().get(fuse::WriteRequestFlag::WRITE_CACHE), false);
-	assert_eq!(req.open_flags(), 0);
+	assert_eq!(req.open_flags().get(), 0);
 	assert_eq!(req.value(), b"hello.world!");
+
+	let encoded = encode_request!(req, {
+		protocol_version: (7, 1),
+	});
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
 }
 
 #[test]
@@ -72,8 +84,13 @@ fn request_v7p9() {
 	assert_eq!(req.offset(), 45);
 	assert_eq!(req.lock_owner(), None);
 	assert_eq!(req.flags().get(fuse::WriteRequestFlag::WRITE_CACHE), false);
-	assert_eq!(req.open_flags(), 67);
+	assert_eq!(req.open_flags().get(), 67);
 	assert_eq!(req.value(), b"hello.world!");
+
+	let encoded = encode_request!(req, {
+		protocol_version: (7, 9),
+	});
+	assert_eq!(encoded, buf.as_slice()[..testutil::message_len(&buf)]);
 }
 
 #[test]
@@ -109,4 +126,65 @@ fn request_page_cache() {
 	let req = decode_request!(WriteRequest, buf);
 
 	assert_eq!(req.flags().get(fuse::WriteRequestFlag::WRITE_CACHE), true);
+	assert_eq!(req.kill_suidgid(), false);
+}
+
+#[test]
+fn request_kill_suidgid() {
+	let buf = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_WRITE;
+			h.nodeid = 123;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_write_in {
+			write_flags: DUMMY_WRITE_FLAG | kernel::FUSE_WRITE_KILL_SUIDGID,
+		}))
+		.build_aligned();
+
+	let req = decode_request!(WriteRequest, buf);
+
+	assert_eq!(req.kill_suidgid(), true);
+
+	// setgid is only cleared if the file is group-executable.
+	let mode = fuse::FileMode::S_IFREG | 0o6755;
+	assert_eq!(mode.without_suidgid(), fuse::FileMode::S_IFREG | 0o755);
+	let mode = fuse::FileMode::S_IFREG | 0o6745;
+	assert_eq!(mode.without_suidgid(), fuse::FileMode::S_IFREG | 0o2745);
+	let mode = fuse::FileMode::S_IFREG | 0o1644;
+	assert_eq!(mode.without_suidgid(), mode);
+}
+
+#[test]
+fn response() {
+	let resp = WriteResponse::new(123);
+	assert_eq!(resp.size(), 123);
+
+	let encoded = encode_response!(&resp);
+
+	assert_eq!(
+		encoded,
+		MessageBuilder::new()
+			.push_sized(&testutil::new!(kernel::fuse_out_header {
+				len: (size_of::<kernel::fuse_out_header>()
+					+ size_of::<kernel::fuse_write_out>()) as u32,
+				unique: 0xAABBCCDD,
+			}))
+			.push_sized(&testutil::new!(kernel::fuse_write_out {
+				size: 123,
+			}))
+			.build()
+	);
+}
+
+#[test]
+fn response_impl_debug() {
+	let resp = WriteResponse::new(123);
+	assert_eq!(
+		format!("{:#?}", resp),
+		concat!(
+			"WriteResponse {\n",
+			"    size: 123,\n",
+			"}",
+		),
+	);
 }
@@ -82,6 +82,8 @@ mod errno {
 	pub const EOPNOTSUPP: Error = Error;
 	pub const EOVERFLOW: Error = Error;
 	pub const EPROTO: Error = Error;
+	pub const EROFS: Error = Error;
+	pub const ENFILE: Error = Error;
 	pub const EAGAIN: Error = Error;
 	pub const ENOSYS: Error = Error;
 	pub const ENOATTR: Error = Error;
@@ -132,6 +134,16 @@ impl OsError {
 	/// This error maps to `EPROTO`.
 	pub const PROTOCOL_ERROR: crate::Error = fuse_error(errno::EPROTO);
 
+	/// The requested operation would modify a read-only filesystem.
+	///
+	/// This error maps to `EROFS`.
+	pub const READ_ONLY: crate::Error = fuse_error(errno::EROFS);
+
+	/// The server has too many open files or handles.
+	///
+	/// This error maps to `ENFILE`.
+	pub const TOO_MANY_OPEN_FILES: crate::Error = fuse_error(errno::ENFILE);
+
 	/// The requested operation is temporarily unavailable.
 	///
 	/// This error maps to `EAGAIN`.
@@ -206,9 +218,16 @@ impl fmt::Debug for FuseSubtype {
 // MountOptions {{{
 
 /// Builder for FreeBSD `nmount()` options.
+///
+/// Unlike Linux, FreeBSD has no options for the root node's mode or the
+/// mount owner. The root node is always a directory, and the mount is owned
+/// by the user that called `nmount()`.
 #[derive(Copy, Clone)]
 pub struct MountOptions<'a> {
+	allow_other: bool,
+	daemon_timeout: Option<u32>,
 	default_permissions: bool,
+	max_read: Option<u32>,
 	subtype: Option<&'a FuseSubtype>,
 }
 
@@ -217,11 +236,45 @@ impl<'a> MountOptions<'a> {
 	#[must_use]
 	pub fn new() -> Self {
 		MountOptions {
+			allow_other: false,
+			daemon_timeout: None,
 			default_permissions: false,
+			max_read: None,
 			subtype: None,
 		}
 	}
 
+	/// Returns the `allow_other` mount option.
+	#[must_use]
+	pub fn allow_other(&self) -> bool {
+		self.allow_other
+	}
+
+	/// Sets the `allow_other` mount option.
+	///
+	/// If `allow_other` is `true`, then all users (including root) may access
+	/// the filesystem. Non-root users may only set this option if the sysctl
+	/// `vfs.usermount` is enabled.
+	pub fn set_allow_other(&mut self, allow_other: bool) {
+		self.allow_other = allow_other;
+	}
+
+	/// Returns the `timeout=` mount option.
+	#[must_use]
+	pub fn daemon_timeout(&self) -> Option<u32> {
+		self.daemon_timeout
+	}
+
+	/// Sets the `timeout=` mount option.
+	///
+	/// This is the number of seconds that the kernel will wait for the
+	/// filesystem to respond to a request before failing it with `ETIMEDOUT`.
+	/// The kernel will clamp the timeout to its supported range, which
+	/// is currently 1 to 600 seconds. If `None`, requests will not time out.
+	pub fn set_daemon_timeout(&mut self, daemon_timeout: Option<u32>) {
+		self.daemon_timeout = daemon_timeout;
+	}
+
 	/// Returns the `default_permissions` mount option.
 	///
 	/// If true, then the kernel will perform its own permission checking
@@ -236,6 +289,20 @@ impl<'a> MountOptions<'a> {
 		self.default_permissions = default_permissions;
 	}
 
+	/// Returns the `max_read=` mount option.
+	#[must_use]
+	pub fn max_read(&self) -> Option<u32> {
+		self.max_read
+	}
+
+	/// Sets the `max_read=` mount option.
+	///
+	/// This limits the size of `FUSE_READ` requests sent by the kernel. If
+	/// `None`, the kernel's default limit will be used.
+	pub fn set_max_read(&mut self, max_read: Option<u32>) {
+		self.max_read = max_read;
+	}
+
 	/// Returns the `subtype=` mount option.
 	#[must_use]
 	pub fn subtype(&self) -> Option<&'a FuseSubtype> {
@@ -246,12 +313,26 @@ impl<'a> MountOptions<'a> {
 	pub fn set_subtype(&mut self, subtype: Option<&'a FuseSubtype>) {
 		self.subtype = subtype;
 	}
+
+	/// Returns whether `attr` has a file type compatible with the root node
+	/// of a FreeBSD FUSE mount, which is always a directory.
+	#[must_use]
+	pub fn is_valid_root_attr(&self, attr: &crate::NodeAttr) -> bool {
+		let type_bits = attr.mode().type_bits();
+		type_bits == crate::FileType::Directory.as_mode().type_bits()
+	}
 }
 
 impl fmt::Debug for MountOptions<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("MountOptions")
+			.field("allow_other", &self.allow_other())
+			.field(
+				"daemon_timeout",
+				&format_args!("{:?}", self.daemon_timeout()),
+			)
 			.field("default_permissions", &self.default_permissions())
+			.field("max_read", &format_args!("{:?}", self.max_read()))
 			.field("subtype", &format_args!("{:?}", self.subtype()))
 			.finish()
 	}
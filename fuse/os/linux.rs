@@ -103,7 +103,7 @@ const fn fcntl_unlck() -> u32 {
 	return 2;
 }
 
-// FUSE_DEV_IOC_CLONE {{{
+// FUSE_DEV_IOC_* {{{
 
 #[cfg(not(any(
 	target_arch = "alpha",
@@ -118,6 +118,8 @@ const fn fcntl_unlck() -> u32 {
 mod arch {
 	// _IOC_SIZEBITS == 14 && _IOC_DIRBITS == 2
 	pub const FUSE_DEV_IOC_CLONE: u32 = 0x8004E500; // _IOR(229, 0, uint32_t)
+	pub const FUSE_DEV_IOC_BACKING_OPEN: u32 = 0x4010E501; // _IOW(229, 1, struct fuse_backing_map)
+	pub const FUSE_DEV_IOC_BACKING_CLOSE: u32 = 0x4004E502; // _IOW(229, 2, uint32_t)
 }
 
 #[cfg(any(
@@ -133,11 +135,27 @@ mod arch {
 mod arch {
 	// _IOC_SIZEBITS == 13 && _IOC_DIRBITS == 3
 	pub const FUSE_DEV_IOC_CLONE: u32 = 0x4004E500; // _IOR(229, 0, uint32_t)
+	pub const FUSE_DEV_IOC_BACKING_OPEN: u32 = 0x8010E501; // _IOW(229, 1, struct fuse_backing_map)
+	pub const FUSE_DEV_IOC_BACKING_CLOSE: u32 = 0x8004E502; // _IOW(229, 2, uint32_t)
 }
 
 /// `ioctl` command for cloning a `/dev/fuse` device handle.
 pub const FUSE_DEV_IOC_CLONE: u32 = arch::FUSE_DEV_IOC_CLONE;
 
+/// `ioctl` command for registering a passthrough backing file.
+///
+/// The argument is a [`fuse_backing_map`], and the return value is a backing
+/// file ID that may be sent in replies to `FUSE_OPEN` or `FUSE_CREATE`.
+///
+/// [`fuse_backing_map`]: crate::kernel::fuse_backing_map
+pub const FUSE_DEV_IOC_BACKING_OPEN: u32 = arch::FUSE_DEV_IOC_BACKING_OPEN;
+
+/// `ioctl` command for unregistering a passthrough backing file.
+///
+/// The argument is the backing file ID returned by
+/// [`FUSE_DEV_IOC_BACKING_OPEN`].
+pub const FUSE_DEV_IOC_BACKING_CLOSE: u32 = arch::FUSE_DEV_IOC_BACKING_CLOSE;
+
 // }}}
 
 /// Adapter from Linux error codes to FUSE errors.
@@ -184,6 +202,8 @@ mod errno {
 	pub const EOPNOTSUPP: Error = Error;
 	pub const EOVERFLOW: Error = Error;
 	pub const EPROTO: Error = Error;
+	pub const EROFS: Error = Error;
+	pub const ENFILE: Error = Error;
 	pub const EAGAIN: Error = Error;
 	pub const ENOSYS: Error = Error;
 	pub const ENOATTR: Error = Error;
@@ -234,6 +254,16 @@ impl OsError {
 	/// This error maps to `EPROTO`.
 	pub const PROTOCOL_ERROR: crate::Error = fuse_error(errno::EPROTO);
 
+	/// The requested operation would modify a read-only filesystem.
+	///
+	/// This error maps to `EROFS`.
+	pub const READ_ONLY: crate::Error = fuse_error(errno::EROFS);
+
+	/// The server has too many open files or handles.
+	///
+	/// This error maps to `ENFILE`.
+	pub const TOO_MANY_OPEN_FILES: crate::Error = fuse_error(errno::ENFILE);
+
 	/// The requested operation is temporarily unavailable.
 	///
 	/// This error maps to `EAGAIN`.
@@ -397,6 +427,89 @@ impl fmt::Debug for FuseSubtype {
 
 // }}}
 
+// RawMountOptions {{{
+
+/// A borrowed list of raw FUSE mount data options.
+///
+/// This value is appended verbatim to the mount data generated from a
+/// [`MountOptions`], and can be used to pass options that are not otherwise
+/// supported by this crate. It has the same syntax as the argument to
+/// `mount -o`: a comma-separated list of `key` or `key=value` entries.
+///
+/// The Linux kernel splits mount data on commas without any support for
+/// escaping, so option values can't contain a comma.
+#[derive(Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct RawMountOptions {
+	inner: ffi::CStr,
+}
+
+impl RawMountOptions {
+	/// Attempts to reborrow a C string as a list of raw mount data options.
+	///
+	/// # Errors
+	///
+	/// Returns `None` if the C string is empty, contains an empty entry, or
+	/// contains an entry that would conflict with an option set by
+	/// [`MountOptions`].
+	#[must_use]
+	pub fn new(raw_options: &ffi::CStr) -> Option<&RawMountOptions> {
+		if raw_options.is_empty() {
+			return None;
+		}
+		for entry in raw_options.to_bytes().split(|&b| b == b',') {
+			let key = match entry.iter().position(|&b| b == b'=') {
+				Some(idx) => &entry[..idx],
+				None => entry,
+			};
+			if key.is_empty() || RESERVED_MOUNT_DATA_KEYS.contains(&key) {
+				return None;
+			}
+		}
+		Some(unsafe { Self::new_unchecked(raw_options) })
+	}
+
+	/// Reborrows a C string as a list of raw mount data options, without
+	/// validation.
+	///
+	/// # Safety
+	///
+	/// The provided C string must be a non-empty comma-separated list of
+	/// non-empty entries, and must not contain any entry with a key that is
+	/// set by [`MountOptions`].
+	#[must_use]
+	pub const unsafe fn new_unchecked(
+		raw_options: &ffi::CStr,
+	) -> &RawMountOptions {
+		&*(raw_options as *const ffi::CStr as *const RawMountOptions)
+	}
+
+	/// Returns these raw mount data options as a borrowed C string.
+	#[must_use]
+	pub const fn as_cstr(&self) -> &ffi::CStr {
+		&self.inner
+	}
+}
+
+impl fmt::Debug for RawMountOptions {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt::Debug::fmt(&self.inner, fmt)
+	}
+}
+
+const RESERVED_MOUNT_DATA_KEYS: &[&[u8]] = &[
+	b"allow_other",
+	b"blksize",
+	b"default_permissions",
+	b"fd",
+	b"group_id",
+	b"max_read",
+	b"rootmode",
+	b"subtype",
+	b"user_id",
+];
+
+// }}}
+
 // MountOptions {{{
 
 /// Builder for Linux `mount()` options.
@@ -406,12 +519,14 @@ pub struct MountOptions<'a> {
 	mount_type: &'a MountType,
 
 	allow_other: bool,
+	allow_root: bool,
 	block_size: Option<u32>,
 	default_permissions: bool,
 	fuse_device_fd: Option<u32>,
 	subtype: Option<&'a FuseSubtype>,
 	group_id: Option<u32>,
 	max_read: Option<u32>,
+	raw_options: Option<&'a RawMountOptions>,
 	root_mode: Option<crate::FileMode>,
 	user_id: Option<u32>,
 }
@@ -425,12 +540,14 @@ impl<'a> MountOptions<'a> {
 			mount_source: MountSource::FUSE,
 
 			allow_other: false,
+			allow_root: false,
 			block_size: None,
 			default_permissions: false,
 			fuse_device_fd: None,
 			subtype: None,
 			group_id: None,
 			max_read: None,
+			raw_options: None,
 			root_mode: None,
 			user_id: None,
 		}
@@ -445,11 +562,76 @@ impl<'a> MountOptions<'a> {
 	/// Sets the `allow_other=` mount data value.
 	///
 	/// If `allow_other` is `true`, then all users (including root) may access
-	/// the filesystem.
+	/// the filesystem. Otherwise the kernel rejects access by users other
+	/// than the mount owner without sending any request to the filesystem.
 	pub fn set_allow_other(&mut self, allow_other: bool) {
 		self.allow_other = allow_other;
 	}
 
+	/// Returns the `allow_root` mount option.
+	#[must_use]
+	pub fn allow_root(&self) -> bool {
+		self.allow_root
+	}
+
+	/// Sets the `allow_root` mount option.
+	///
+	/// The Linux kernel does not support `allow_root` directly. Setting this
+	/// option causes `allow_other` to be written to the mount data, so the
+	/// kernel allows access by all users. The server must reject requests
+	/// from users other than root and the mount owner itself, for example
+	/// by checking each request with [`allow_root_permits`].
+	///
+	/// At most one of `allow_other` and `allow_root` should be set.
+	///
+	/// [`allow_root_permits`]: MountOptions::allow_root_permits
+	pub fn set_allow_root(&mut self, allow_root: bool) {
+		self.allow_root = allow_root;
+	}
+
+	/// Returns whether the `allow_root` option permits a request from the
+	/// user `request_uid` to a filesystem mounted by `owner_uid`.
+	///
+	/// Requests that aren't permitted should be rejected, for example with
+	/// `EACCES`. If `allow_root` isn't set, or `allow_other` is also set,
+	/// then the kernel checks access itself and all requests are permitted.
+	#[must_use]
+	pub fn allow_root_permits(&self, request_uid: u32, owner_uid: u32) -> bool {
+		if !self.allow_root || self.allow_other {
+			return true;
+		}
+		request_uid == 0 || request_uid == owner_uid
+	}
+
+	/// Returns whether the mount type is [`FUSEBLK`].
+	///
+	/// [`FUSEBLK`]: MountType::FUSEBLK
+	#[must_use]
+	pub fn blkdev(&self) -> bool {
+		self.mount_type == MountType::FUSEBLK
+	}
+
+	/// Sets the mount type to [`FUSEBLK`] or [`FUSE`], equivalent to the
+	/// `blkdev` option of `fusermount`.
+	///
+	/// Clearing `blkdev` only changes the mount type if it is [`FUSEBLK`],
+	/// so that a custom [mount type] is preserved.
+	///
+	/// For `fuseblk` filesystems the [mount source] must be a block device
+	/// path.
+	///
+	/// [`FUSE`]: MountType::FUSE
+	/// [`FUSEBLK`]: MountType::FUSEBLK
+	/// [mount source]: MountOptions::set_mount_source
+	/// [mount type]: MountOptions::set_mount_type
+	pub fn set_blkdev(&mut self, blkdev: bool) {
+		if blkdev {
+			self.mount_type = MountType::FUSEBLK;
+		} else if self.blkdev() {
+			self.mount_type = MountType::FUSE;
+		}
+	}
+
 	/// Returns the `blksize=` mount data value.
 	#[must_use]
 	pub fn block_size(&self) -> Option<u32> {
@@ -475,7 +657,12 @@ impl<'a> MountOptions<'a> {
 	/// Sets the `default_permissions` mount data value.
 	///
 	/// If true then the kernel will perform its own permission checking
-	/// in addition to any permission checks by the filesystem.
+	/// in addition to any permission checks by the filesystem. Requests that
+	/// fail the kernel's checks are not sent to the filesystem, and
+	/// `FUSE_ACCESS` requests are not sent at all.
+	///
+	/// If false then the filesystem is responsible for checking permissions,
+	/// using the caller's credentials from the request header.
 	pub fn set_default_permissions(&mut self, default_permissions: bool) {
 		self.default_permissions = default_permissions;
 	}
@@ -525,6 +712,23 @@ impl<'a> MountOptions<'a> {
 		self.max_read = max_read;
 	}
 
+	/// Returns the raw mount data options.
+	#[must_use]
+	pub fn raw_options(&self) -> Option<&'a RawMountOptions> {
+		self.raw_options
+	}
+
+	/// Sets the raw mount data options.
+	///
+	/// Raw options are appended to the mount data after all options set by
+	/// other `MountOptions` methods.
+	pub fn set_raw_options(
+		&mut self,
+		raw_options: Option<&'a RawMountOptions>,
+	) {
+		self.raw_options = raw_options;
+	}
+
 	/// Returns the `source` mount parameter.
 	#[must_use]
 	pub fn mount_source(&self) -> &'a MountSource {
@@ -572,11 +776,53 @@ impl<'a> MountOptions<'a> {
 	/// This option is the filesystem root's Unix file mode. It will typically
 	/// be [`S_IFDIR`] plus appropriate permission bits.
 	///
+	/// The kernel uses the root mode until the first `FUSE_GETATTR` response
+	/// for the root node, which must have the same file type. Otherwise the
+	/// kernel marks the root node as bad, and all further access to the
+	/// filesystem fails with `EIO`. The mount fails with `ENOTDIR` if the
+	/// root and the mount target aren't both directories (or both
+	/// non-directories).
+	///
 	/// [`S_IFDIR`]: crate::FileMode::S_IFDIR
 	pub fn set_root_mode(&mut self, root_mode: Option<crate::FileMode>) {
 		self.root_mode = root_mode;
 	}
 
+	/// Returns the file type of the `rootmode` mount data value.
+	///
+	/// Returns `None` if the root mode isn't set, or if its type bits are an
+	/// unknown value.
+	#[must_use]
+	pub fn root_type(&self) -> Option<crate::FileType> {
+		crate::FileType::from_mode(self.root_mode?)
+	}
+
+	/// Sets the `rootmode` mount data value to the given file type, without
+	/// any permission bits.
+	///
+	/// The permission bits of the root mode are replaced by those of the
+	/// first `FUSE_GETATTR` response, so the file type is sufficient for
+	/// most filesystems. This matches the behavior of `fusermount`.
+	pub fn set_root_type(&mut self, root_type: Option<crate::FileType>) {
+		self.root_mode = root_type.map(crate::FileType::as_mode);
+	}
+
+	/// Returns whether `attr` has a file type compatible with the `rootmode`
+	/// mount data value.
+	///
+	/// Filesystems can use this to check the attributes of their root node
+	/// at startup, rather than failing with `EIO` on first access. Returns
+	/// `true` if the root mode isn't set.
+	#[must_use]
+	pub fn is_valid_root_attr(&self, attr: &crate::NodeAttr) -> bool {
+		match self.root_mode {
+			None => true,
+			Some(root_mode) => {
+				root_mode.type_bits() == attr.mode().type_bits()
+			},
+		}
+	}
+
 	/// Returns the `subtype=` mount data value.
 	#[must_use]
 	pub fn subtype(&self) -> Option<&'a FuseSubtype> {
@@ -612,6 +858,7 @@ impl fmt::Debug for MountOptions<'_> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
 		fmt.debug_struct("MountOptions")
 			.field("allow_other", &self.allow_other())
+			.field("allow_root", &self.allow_root())
 			.field("block_size", &format_args!("{:?}", self.block_size()))
 			.field("default_permissions", &self.default_permissions())
 			.field(
@@ -622,6 +869,7 @@ impl fmt::Debug for MountOptions<'_> {
 			.field("max_read", &format_args!("{:?}", self.max_read()))
 			.field("mount_source", &self.mount_source())
 			.field("mount_type", &self.mount_type())
+			.field("raw_options", &format_args!("{:?}", self.raw_options()))
 			.field("root_mode", &format_args!("{:?}", self.root_mode()))
 			.field("subtype", &format_args!("{:?}", self.subtype()))
 			.field("user_id", &format_args!("{:?}", self.user_id()))
@@ -669,7 +917,7 @@ fn write_mount_data(w: &mut BufWriter, opts: &MountOptions) -> fmt::Result {
 	}
 
 	// Other options are written in order by key.
-	if opts.allow_other {
+	if opts.allow_other || opts.allow_root {
 		write!(w, "{}allow_other", sep)?;
 		sep = comma;
 	}
@@ -700,6 +948,14 @@ fn write_mount_data(w: &mut BufWriter, opts: &MountOptions) -> fmt::Result {
 	}
 	if let Some(user_id) = opts.user_id {
 		write!(w, "{}user_id={}", sep, user_id)?;
+		sep = comma;
+	}
+
+	// Raw options are written last, so that they're easy to identify when
+	// debugging mount failures.
+	if let Some(raw_options) = opts.raw_options {
+		write!(w, "{}", sep)?;
+		w.write_bytes(raw_options.as_cstr().to_bytes())?;
 	}
 
 	// Ensure the output is terminated by NUL. Although the `mount()` data
@@ -710,6 +966,458 @@ fn write_mount_data(w: &mut BufWriter, opts: &MountOptions) -> fmt::Result {
 
 // }}}
 
+// FuseConnectionId {{{
+
+/// Identifier of a FUSE connection in the `fusectl` filesystem.
+///
+/// Each mounted FUSE filesystem has a directory in `/sys/fs/fuse/connections`,
+/// named by the device number of the filesystem's superblock. That device
+/// number is reported by `statx(2)` of the mountpoint.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct FuseConnectionId {
+	id: u32,
+}
+
+impl FuseConnectionId {
+	/// Creates a `FuseConnectionId` from the major and minor device numbers
+	/// of a mounted FUSE filesystem.
+	#[inline]
+	#[must_use]
+	pub const fn new(dev_major: u32, dev_minor: u32) -> FuseConnectionId {
+		// Equivalent to the kernel's internal `MKDEV()` macro.
+		Self {
+			id: (dev_major << 20) | (dev_minor & 0xFFFFF),
+		}
+	}
+
+	/// Creates a `FuseConnectionId` from the name of its `fusectl` directory.
+	#[inline]
+	#[must_use]
+	pub const fn from_raw(id: u32) -> FuseConnectionId {
+		Self { id }
+	}
+
+	/// Returns the name of this connection's `fusectl` directory.
+	#[inline]
+	#[must_use]
+	pub const fn get(self) -> u32 {
+		self.id
+	}
+}
+
+// }}}
+
+// FusectlFile {{{
+
+/// A file in a connection's `fusectl` directory.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[non_exhaustive]
+pub enum FusectlFile {
+	/// Writing any value to `abort` will abort the connection.
+	Abort,
+
+	/// The number of background requests that may be queued before the
+	/// kernel marks the filesystem as congested.
+	CongestionThreshold,
+
+	/// The maximum number of background requests.
+	MaxBackground,
+
+	/// The number of requests that are waiting for a response.
+	Waiting,
+}
+
+impl FusectlFile {
+	/// Returns the name of this file within a connection's directory.
+	#[must_use]
+	pub const fn name(self) -> &'static str {
+		match self {
+			Self::Abort => "abort",
+			Self::CongestionThreshold => "congestion_threshold",
+			Self::MaxBackground => "max_background",
+			Self::Waiting => "waiting",
+		}
+	}
+}
+
+// }}}
+
+// fusectl_path {{{
+
+/// The size of a buffer large enough to hold any [`fusectl_path`] output.
+pub const FUSECTL_PATH_MAX: usize = 64;
+
+/// Formats the path of a file in a connection's `fusectl` directory.
+///
+/// The `fusectl` filesystem is assumed to be mounted at its standard location
+/// of `/sys/fs/fuse/connections`.
+#[must_use]
+pub fn fusectl_path(
+	connection_id: FuseConnectionId,
+	file: FusectlFile,
+	storage: &mut [u8; FUSECTL_PATH_MAX],
+) -> &ffi::CStr {
+	let mut w = BufWriter {
+		buf: storage,
+		count: 0,
+	};
+
+	// The longest possible path is 57 bytes, so formatting can't fail.
+	let result = write!(
+		w,
+		"/sys/fs/fuse/connections/{}/{}\0",
+		connection_id.id,
+		file.name(),
+	);
+	debug_assert!(result.is_ok());
+	let count = w.count;
+	unsafe { ffi::CStr::from_bytes_with_nul_unchecked(&storage[..count]) }
+}
+
+/// Parses the contents of a numeric file in a connection's `fusectl`
+/// directory.
+///
+/// Returns `None` if the contents are not a decimal `u32` followed by an
+/// optional newline.
+#[must_use]
+pub fn parse_fusectl_value(buf: &[u8]) -> Option<u32> {
+	parse_decimal_u32(buf.strip_suffix(b"\n").unwrap_or(buf))
+}
+
+/// The size of a buffer large enough to hold any [`format_fusectl_value`]
+/// output.
+pub const FUSECTL_VALUE_MAX: usize = 11;
+
+/// Formats a value to be written to a numeric file in a connection's
+/// `fusectl` directory.
+#[must_use]
+pub fn format_fusectl_value(
+	value: u32,
+	storage: &mut [u8; FUSECTL_VALUE_MAX],
+) -> &[u8] {
+	let mut w = BufWriter {
+		buf: storage,
+		count: 0,
+	};
+
+	// The longest possible value is 11 bytes, so formatting can't fail.
+	let result = writeln!(w, "{}", value);
+	debug_assert!(result.is_ok());
+	let count = w.count;
+	&storage[..count]
+}
+
+// }}}
+
+// FusectlInfo {{{
+
+/// A snapshot of a FUSE connection's state, as reported by `fusectl`.
+#[derive(Clone, Copy)]
+pub struct FusectlInfo {
+	connection_id: FuseConnectionId,
+	waiting: u32,
+	max_background: u32,
+	congestion_threshold: u32,
+}
+
+impl FusectlInfo {
+	/// Create a new `FusectlInfo` with the given connection ID and all
+	/// other values set to zero.
+	#[must_use]
+	pub fn new(connection_id: FuseConnectionId) -> FusectlInfo {
+		FusectlInfo {
+			connection_id,
+			waiting: 0,
+			max_background: 0,
+			congestion_threshold: 0,
+		}
+	}
+
+	/// Returns the connection's `fusectl` identifier.
+	#[must_use]
+	pub fn connection_id(&self) -> FuseConnectionId {
+		self.connection_id
+	}
+
+	/// Returns the number of requests waiting for a response.
+	#[must_use]
+	pub fn waiting(&self) -> u32 {
+		self.waiting
+	}
+
+	/// Sets the number of requests waiting for a response.
+	pub fn set_waiting(&mut self, waiting: u32) {
+		self.waiting = waiting;
+	}
+
+	/// Returns the maximum number of background requests.
+	#[must_use]
+	pub fn max_background(&self) -> u32 {
+		self.max_background
+	}
+
+	/// Sets the maximum number of background requests.
+	pub fn set_max_background(&mut self, max_background: u32) {
+		self.max_background = max_background;
+	}
+
+	/// Returns the number of background requests that may be queued before
+	/// the kernel marks the filesystem as congested.
+	#[must_use]
+	pub fn congestion_threshold(&self) -> u32 {
+		self.congestion_threshold
+	}
+
+	/// Sets the congestion threshold.
+	pub fn set_congestion_threshold(&mut self, congestion_threshold: u32) {
+		self.congestion_threshold = congestion_threshold;
+	}
+}
+
+impl fmt::Debug for FusectlInfo {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("FusectlInfo")
+			.field("connection_id", &self.connection_id.id)
+			.field("waiting", &self.waiting)
+			.field("max_background", &self.max_background)
+			.field("congestion_threshold", &self.congestion_threshold)
+			.finish()
+	}
+}
+
+// }}}
+
+// fusectl I/O {{{
+
+/// The size of a buffer large enough to hold the contents of any numeric
+/// file in a connection's `fusectl` directory.
+pub const FUSECTL_READ_MAX: usize = 16;
+
+/// Reads the state of a connection from its `fusectl` directory.
+///
+/// The `read_file` function is called with the path of each file to read,
+/// and should read the file's contents into the given buffer and return the
+/// number of bytes read. Returns `Ok(None)` if the contents of any file are
+/// not a valid value.
+pub fn read_fusectl_info<E>(
+	connection_id: FuseConnectionId,
+	mut read_file: impl FnMut(&ffi::CStr, &mut [u8]) -> Result<usize, E>,
+) -> Result<Option<FusectlInfo>, E> {
+	let mut read = |file| -> Result<Option<u32>, E> {
+		let mut path_buf = [0u8; FUSECTL_PATH_MAX];
+		let path = fusectl_path(connection_id, file, &mut path_buf);
+		let mut buf = [0u8; FUSECTL_READ_MAX];
+		let len = read_file(path, &mut buf)?;
+		Ok(buf.get(..len).and_then(parse_fusectl_value))
+	};
+
+	let Some(waiting) = read(FusectlFile::Waiting)? else {
+		return Ok(None);
+	};
+	let Some(max_background) = read(FusectlFile::MaxBackground)? else {
+		return Ok(None);
+	};
+	let Some(congestion_threshold) = read(FusectlFile::CongestionThreshold)?
+	else {
+		return Ok(None);
+	};
+
+	let mut info = FusectlInfo::new(connection_id);
+	info.set_waiting(waiting);
+	info.set_max_background(max_background);
+	info.set_congestion_threshold(congestion_threshold);
+	Ok(Some(info))
+}
+
+/// Writes a value to a file in a connection's `fusectl` directory.
+///
+/// The `write_file` function is called with the path of the file and its
+/// new contents. Writing any value to [`FusectlFile::Abort`] will abort the
+/// connection.
+pub fn write_fusectl_file<E>(
+	connection_id: FuseConnectionId,
+	file: FusectlFile,
+	value: u32,
+	write_file: impl FnOnce(&ffi::CStr, &[u8]) -> Result<(), E>,
+) -> Result<(), E> {
+	let mut path_buf = [0u8; FUSECTL_PATH_MAX];
+	let path = fusectl_path(connection_id, file, &mut path_buf);
+	let mut value_buf = [0u8; FUSECTL_VALUE_MAX];
+	write_file(path, format_fusectl_value(value, &mut value_buf))
+}
+
+// }}}
+
+// max_pages_limit {{{
+
+/// Path of the sysctl that limits the `max_pages` of FUSE connections.
+///
+/// This sysctl was added in Linux 6.13. Older kernels have a fixed limit of
+/// [`DEFAULT_MAX_PAGES_LIMIT`].
+pub const MAX_PAGES_LIMIT_PATH: &ffi::CStr =
+	c"/proc/sys/fs/fuse/max_pages_limit";
+
+/// The default limit on the `max_pages` of FUSE connections.
+pub const DEFAULT_MAX_PAGES_LIMIT: u16 = 256;
+
+/// Parses the contents of the [`MAX_PAGES_LIMIT_PATH`] sysctl.
+///
+/// Limits greater than [`u16::MAX`] are clamped, because `max_pages` can't
+/// represent them. Returns `None` if the contents are not a decimal `u32`
+/// followed by an optional newline.
+#[must_use]
+pub fn parse_max_pages_limit(buf: &[u8]) -> Option<u16> {
+	let limit = parse_fusectl_value(buf)?;
+	Some(u16::try_from(limit).unwrap_or(u16::MAX))
+}
+
+// }}}
+
+// /proc/self/auxv {{{
+
+/// Path of the file containing the auxiliary vector of the current process.
+pub const PROC_SELF_AUXV_PATH: &ffi::CStr = c"/proc/self/auxv";
+
+/// Parses the page size from the contents of a process's auxiliary vector,
+/// as read from `/proc/self/auxv`.
+///
+/// The auxiliary vector is a list of native-endian `(type, value)` word
+/// pairs terminated by an `AT_NULL` entry. Returns `None` if it has no
+/// `AT_PAGESZ` entry.
+#[must_use]
+pub fn parse_auxv_page_size(auxv: &[u8]) -> Option<usize> {
+	const AT_NULL: usize = 0;
+	const AT_PAGESZ: usize = 6;
+	const WORD: usize = core::mem::size_of::<usize>();
+
+	let read_word = |chunk: &[u8]| {
+		let mut word = [0u8; WORD];
+		word.copy_from_slice(chunk);
+		usize::from_ne_bytes(word)
+	};
+	for entry in auxv.chunks_exact(WORD * 2) {
+		let (a_type, a_val) = entry.split_at(WORD);
+		match read_word(a_type) {
+			AT_NULL => break,
+			AT_PAGESZ => return Some(read_word(a_val)),
+			_ => {},
+		}
+	}
+	None
+}
+
+// }}}
+
+// /proc/<pid>/status {{{
+
+/// The size of a buffer large enough to hold any [`proc_status_path`] output.
+pub const PROC_STATUS_PATH_MAX: usize = 24;
+
+/// Formats the path of a process's `/proc/<pid>/status` file.
+///
+/// The process ID must be visible in the PID namespace of the `procfs`
+/// mounted at `/proc`.
+#[must_use]
+pub fn proc_status_path(
+	process_id: core::num::NonZeroU32,
+	storage: &mut [u8; PROC_STATUS_PATH_MAX],
+) -> &ffi::CStr {
+	let mut w = BufWriter {
+		buf: storage,
+		count: 0,
+	};
+
+	// The longest possible path is 24 bytes, so formatting can't fail.
+	let result = write!(w, "/proc/{}/status\0", process_id);
+	debug_assert!(result.is_ok());
+	let count = w.count;
+	unsafe { ffi::CStr::from_bytes_with_nul_unchecked(&storage[..count]) }
+}
+
+/// Parses the supplementary groups from the contents of a process's
+/// `/proc/<pid>/status` file.
+///
+/// The group IDs are written into `storage`. Returns `None` if the `Groups:`
+/// line is missing or malformed, or if the process has more supplementary
+/// groups than fit in `storage`.
+///
+/// Supplementary groups are resolved after the request was sent, so they
+/// might not match the credentials used when the request was initiated.
+/// Filesystems that need race-free permission checks should use the
+/// `default_permissions` mount option.
+#[must_use]
+pub fn parse_proc_status_groups<'a>(
+	status: &[u8],
+	storage: &'a mut [u32],
+) -> Option<&'a [u32]> {
+	parse_proc_status_u32s(status, b"Groups:", storage)
+}
+
+/// Parses the process IDs of a process in each of its PID namespaces from
+/// the contents of its `/proc/<pid>/status` file.
+///
+/// The first process ID is in the PID namespace of the `procfs` that the
+/// status was read from, and the last is in the process's own PID namespace.
+/// Each ID in between is in a PID namespace nested within the previous one.
+///
+/// The process ID in a FUSE request header is in the PID namespace of the
+/// process that mounted the filesystem. Servers running in a container may
+/// need to translate it, for example to compare it against process IDs
+/// obtained from their own `/proc`, or to report the process ID of a lock
+/// owner to a client in another namespace.
+///
+/// The process IDs are written into `storage`. Returns `None` if the `NSpid:`
+/// line is missing (as on kernels older than Linux 4.1) or malformed, or if
+/// the process is nested in more PID namespaces than fit in `storage`.
+#[must_use]
+pub fn parse_proc_status_nspid<'a>(
+	status: &[u8],
+	storage: &'a mut [u32],
+) -> Option<&'a [u32]> {
+	let process_ids = parse_proc_status_u32s(status, b"NSpid:", storage)?;
+	if process_ids.is_empty() {
+		return None;
+	}
+	Some(process_ids)
+}
+
+fn parse_proc_status_u32s<'a>(
+	status: &[u8],
+	key: &[u8],
+	storage: &'a mut [u32],
+) -> Option<&'a [u32]> {
+	let line = status
+		.split(|&b| b == b'\n')
+		.find_map(|line| line.strip_prefix(key))?;
+
+	let mut count = 0;
+	for word in line.split(|&b| b == b' ' || b == b'\t') {
+		if word.is_empty() {
+			continue;
+		}
+		let value = parse_decimal_u32(word)?;
+		*storage.get_mut(count)? = value;
+		count += 1;
+	}
+	Some(&storage[..count])
+}
+
+fn parse_decimal_u32(digits: &[u8]) -> Option<u32> {
+	if digits.is_empty() {
+		return None;
+	}
+	let mut value: u32 = 0;
+	for &b in digits {
+		if !b.is_ascii_digit() {
+			return None;
+		}
+		value = value.checked_mul(10)?.checked_add(u32::from(b - b'0'))?;
+	}
+	Some(value)
+}
+
+// }}}
+
 // BufWriter {{{
 
 struct BufWriter<'a> {
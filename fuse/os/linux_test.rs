@@ -15,15 +15,34 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::ffi::CString;
+use std::num::NonZeroU32;
+
+use fuse::{FileMode, FileType, NodeAttr, NodeId};
 
 mod linux;
 
 use crate::linux::{
+	FUSECTL_PATH_MAX,
+	FUSECTL_VALUE_MAX,
+	FuseConnectionId,
 	FuseSubtype,
+	FusectlFile,
 	MountOptions,
 	MountSource,
 	MountType,
+	PROC_STATUS_PATH_MAX,
+	RawMountOptions,
+	format_fusectl_value,
+	fusectl_path,
 	mount_data,
+	parse_auxv_page_size,
+	parse_fusectl_value,
+	parse_max_pages_limit,
+	parse_proc_status_groups,
+	parse_proc_status_nspid,
+	proc_status_path,
+	read_fusectl_info,
+	write_fusectl_file,
 };
 
 #[test]
@@ -35,6 +54,50 @@ fn opt_allow_other() {
 	assert_eq!(opts.allow_other(), true);
 }
 
+#[test]
+fn opt_allow_root() {
+	let mut opts = MountOptions::new();
+
+	assert_eq!(opts.allow_root(), false);
+	opts.set_allow_root(true);
+	assert_eq!(opts.allow_root(), true);
+}
+
+#[test]
+fn opt_allow_root_permits() {
+	let mut opts = MountOptions::new();
+
+	// Without `allow_root` the kernel checks access.
+	assert!(opts.allow_root_permits(2000, 1000));
+
+	opts.set_allow_root(true);
+	assert!(opts.allow_root_permits(0, 1000));
+	assert!(opts.allow_root_permits(1000, 1000));
+	assert!(!opts.allow_root_permits(2000, 1000));
+
+	opts.set_allow_other(true);
+	assert!(opts.allow_root_permits(2000, 1000));
+}
+
+#[test]
+fn opt_blkdev() {
+	let mut opts = MountOptions::new();
+
+	assert_eq!(opts.blkdev(), false);
+	opts.set_blkdev(true);
+	assert_eq!(opts.blkdev(), true);
+	assert_eq!(opts.mount_type(), MountType::FUSEBLK);
+	opts.set_blkdev(false);
+	assert_eq!(opts.mount_type(), MountType::FUSE);
+
+	// Clearing `blkdev` doesn't reset a custom mount type.
+	let custom = unsafe { MountType::new_unchecked(c"fuse.custom") };
+	opts.set_mount_type(custom);
+	opts.set_blkdev(false);
+	assert_eq!(opts.mount_type(), custom);
+	assert_eq!(opts.blkdev(), false);
+}
+
 #[test]
 fn opt_block_size() {
 	let mut opts = MountOptions::new();
@@ -110,6 +173,18 @@ fn opt_max_read() {
 	assert_eq!(opts.max_read(), Some(123u32));
 }
 
+#[test]
+fn opt_raw_options() {
+	let mut opts = MountOptions::new();
+
+	assert_eq!(opts.raw_options(), None);
+	opts.set_raw_options(RawMountOptions::new(c"opt_a,opt_b=1"));
+	assert_eq!(
+		opts.raw_options().unwrap().as_cstr().to_bytes(),
+		b"opt_a,opt_b=1",
+	);
+}
+
 #[test]
 fn opt_root_mode() {
 	let mut opts = MountOptions::new();
@@ -119,6 +194,27 @@ fn opt_root_mode() {
 	assert_eq!(opts.root_mode(), Some(123u32));
 }
 
+#[test]
+fn opt_root_type() {
+	let mut opts = MountOptions::new();
+
+	let mut attr = NodeAttr::new(NodeId::ROOT);
+	attr.set_mode(FileMode::S_IFREG | 0o644);
+	assert_eq!(opts.root_type(), None);
+	assert!(opts.is_valid_root_attr(&attr));
+
+	opts.set_root_type(Some(FileType::Directory));
+	assert_eq!(opts.root_type(), Some(FileType::Directory));
+	assert_eq!(opts.root_mode(), Some(FileMode::S_IFDIR));
+	assert!(!opts.is_valid_root_attr(&attr));
+
+	attr.set_mode(FileMode::S_IFDIR | 0o755);
+	assert!(opts.is_valid_root_attr(&attr));
+
+	opts.set_root_mode(Some(FileMode::new(0o777)));
+	assert_eq!(opts.root_type(), None);
+}
+
 #[test]
 fn opt_user_id() {
 	let mut opts = MountOptions::new();
@@ -158,6 +254,28 @@ fn mount_data_full() {
 	assert_eq!(mount_data(&opts, &mut buf), Some(expect));
 }
 
+#[test]
+fn mount_data_allow_root() {
+	let mut opts = MountOptions::new();
+	opts.set_allow_root(true);
+
+	let mut buf = [0u8; 512];
+	assert_eq!(mount_data(&opts, &mut buf), Some(&b"allow_other\0"[..]));
+}
+
+#[test]
+fn mount_data_raw_options() {
+	let mut opts = MountOptions::new();
+	opts.set_user_id(Some(60));
+	opts.set_raw_options(RawMountOptions::new(c"opt_a,opt_b=1"));
+
+	let mut buf = [0u8; 512];
+	assert_eq!(
+		mount_data(&opts, &mut buf),
+		Some(&b"user_id=60,opt_a,opt_b=1\0"[..]),
+	);
+}
+
 #[test]
 fn mount_data_empty() {
 	let opts = MountOptions::new();
@@ -190,3 +308,268 @@ fn subtype_new() {
 	assert!(FuseSubtype::new(c"").is_none());
 	assert!(FuseSubtype::new(c"bad,subtype").is_none());
 }
+
+#[test]
+fn raw_options_new() {
+	assert!(RawMountOptions::new(c"").is_none());
+	assert!(RawMountOptions::new(c",opt_a").is_none());
+	assert!(RawMountOptions::new(c"opt_a,,opt_b").is_none());
+	assert!(RawMountOptions::new(c"opt_a,").is_none());
+	assert!(RawMountOptions::new(c"=value").is_none());
+	assert!(RawMountOptions::new(c"max_read=4096").is_none());
+	assert!(RawMountOptions::new(c"opt_a,allow_other").is_none());
+	assert!(RawMountOptions::new(c"opt_a,opt_b=c=d").is_some());
+}
+
+#[test]
+fn connection_id_new() {
+	let id = FuseConnectionId::new(0, 45);
+	assert_eq!(id.get(), 45);
+
+	let id = FuseConnectionId::new(1, 2);
+	assert_eq!(id.get(), (1 << 20) | 2);
+}
+
+#[test]
+fn fusectl_path_files() {
+	let id = FuseConnectionId::from_raw(45);
+	let mut buf = [0u8; FUSECTL_PATH_MAX];
+
+	assert_eq!(
+		fusectl_path(id, FusectlFile::Abort, &mut buf),
+		c"/sys/fs/fuse/connections/45/abort",
+	);
+	assert_eq!(
+		fusectl_path(id, FusectlFile::Waiting, &mut buf),
+		c"/sys/fs/fuse/connections/45/waiting",
+	);
+}
+
+#[test]
+fn fusectl_path_max_len() {
+	let id = FuseConnectionId::from_raw(u32::MAX);
+	let mut buf = [0u8; FUSECTL_PATH_MAX];
+
+	assert_eq!(
+		fusectl_path(id, FusectlFile::CongestionThreshold, &mut buf),
+		c"/sys/fs/fuse/connections/4294967295/congestion_threshold",
+	);
+}
+
+#[test]
+fn parse_fusectl_values() {
+	assert_eq!(parse_fusectl_value(b"12\n"), Some(12));
+	assert_eq!(parse_fusectl_value(b"0"), Some(0));
+	assert_eq!(parse_fusectl_value(b"4294967295\n"), Some(u32::MAX));
+	assert_eq!(parse_fusectl_value(b"4294967296\n"), None);
+	assert_eq!(parse_fusectl_value(b""), None);
+	assert_eq!(parse_fusectl_value(b"\n"), None);
+	assert_eq!(parse_fusectl_value(b"-1\n"), None);
+	assert_eq!(parse_fusectl_value(b"12\n\n"), None);
+}
+
+#[test]
+fn format_fusectl_values() {
+	let mut buf = [0u8; FUSECTL_VALUE_MAX];
+	assert_eq!(format_fusectl_value(0, &mut buf), b"0\n");
+	assert_eq!(format_fusectl_value(u32::MAX, &mut buf), b"4294967295\n");
+}
+
+#[test]
+fn read_fusectl_info_files() {
+	let id = FuseConnectionId::from_raw(45);
+	let mut paths = Vec::new();
+	let info = read_fusectl_info(id, |path, buf| {
+		paths.push(path.to_owned());
+		let value: &[u8] = match path.to_bytes() {
+			b"/sys/fs/fuse/connections/45/waiting" => b"1\n",
+			b"/sys/fs/fuse/connections/45/max_background" => b"12\n",
+			b"/sys/fs/fuse/connections/45/congestion_threshold" => b"9\n",
+			_ => return Err(()),
+		};
+		buf[..value.len()].copy_from_slice(value);
+		Ok(value.len())
+	});
+	let info = info.unwrap().unwrap();
+	assert_eq!(info.connection_id(), id);
+	assert_eq!(info.waiting(), 1);
+	assert_eq!(info.max_background(), 12);
+	assert_eq!(info.congestion_threshold(), 9);
+	assert_eq!(paths.len(), 3);
+}
+
+#[test]
+fn read_fusectl_info_errors() {
+	let id = FuseConnectionId::from_raw(45);
+
+	// Errors from reading a file are returned as-is.
+	let result = read_fusectl_info(id, |_path, _buf| Err("read error"));
+	assert_eq!(result.unwrap_err(), "read error");
+
+	// Malformed contents are reported as `None`.
+	let result = read_fusectl_info(id, |_path, buf| -> Result<_, ()> {
+		buf[..2].copy_from_slice(b"x\n");
+		Ok(2)
+	});
+	assert!(result.unwrap().is_none());
+
+	// The read length is limited to the buffer.
+	let result = read_fusectl_info(id, |_path, buf| -> Result<_, ()> {
+		Ok(buf.len() + 1)
+	});
+	assert!(result.unwrap().is_none());
+}
+
+#[test]
+fn write_fusectl_file_values() {
+	let id = FuseConnectionId::from_raw(45);
+
+	let mut written = None;
+	let result = write_fusectl_file(id, FusectlFile::Abort, 1, |path, value| {
+		written = Some((path.to_owned(), value.to_vec()));
+		Ok::<(), ()>(())
+	});
+	assert_eq!(result, Ok(()));
+	assert_eq!(
+		written,
+		Some((
+			CString::from(c"/sys/fs/fuse/connections/45/abort"),
+			b"1\n".to_vec(),
+		)),
+	);
+
+	let file = FusectlFile::MaxBackground;
+	let result = write_fusectl_file(id, file, u32::MAX, |path, value| {
+		written = Some((path.to_owned(), value.to_vec()));
+		Err("write error")
+	});
+	assert_eq!(result, Err("write error"));
+	assert_eq!(
+		written,
+		Some((
+			CString::from(c"/sys/fs/fuse/connections/45/max_background"),
+			b"4294967295\n".to_vec(),
+		)),
+	);
+}
+
+#[test]
+fn parse_max_pages_limit_values() {
+	assert_eq!(parse_max_pages_limit(b"256\n"), Some(256));
+	assert_eq!(parse_max_pages_limit(b"65535\n"), Some(u16::MAX));
+	assert_eq!(parse_max_pages_limit(b"65536\n"), Some(u16::MAX));
+	assert_eq!(parse_max_pages_limit(b"4294967295\n"), Some(u16::MAX));
+	assert_eq!(parse_max_pages_limit(b"4294967296\n"), None);
+	assert_eq!(parse_max_pages_limit(b""), None);
+}
+
+fn auxv(entries: &[(usize, usize)]) -> Vec<u8> {
+	let mut buf = Vec::new();
+	for &(a_type, a_val) in entries {
+		buf.extend_from_slice(&a_type.to_ne_bytes());
+		buf.extend_from_slice(&a_val.to_ne_bytes());
+	}
+	buf
+}
+
+#[test]
+fn parse_auxv_page_size_values() {
+	const AT_NULL: usize = 0;
+	const AT_PAGESZ: usize = 6;
+	const AT_CLKTCK: usize = 17;
+
+	let buf = auxv(&[(AT_CLKTCK, 100), (AT_PAGESZ, 16384), (AT_NULL, 0)]);
+	assert_eq!(parse_auxv_page_size(&buf), Some(16384));
+
+	let buf = auxv(&[(AT_PAGESZ, 65536)]);
+	assert_eq!(parse_auxv_page_size(&buf), Some(65536));
+
+	// Entries after `AT_NULL` are ignored.
+	let buf = auxv(&[(AT_CLKTCK, 100), (AT_NULL, 0), (AT_PAGESZ, 4096)]);
+	assert_eq!(parse_auxv_page_size(&buf), None);
+
+	// A truncated entry is ignored.
+	let buf = auxv(&[(AT_CLKTCK, 100), (AT_PAGESZ, 4096)]);
+	assert_eq!(parse_auxv_page_size(&buf[..buf.len() - 1]), None);
+
+	assert_eq!(parse_auxv_page_size(&[]), None);
+}
+
+#[test]
+fn proc_status_path_max_len() {
+	let mut buf = [0u8; PROC_STATUS_PATH_MAX];
+	let pid = NonZeroU32::new(u32::MAX).unwrap();
+	assert_eq!(
+		proc_status_path(pid, &mut buf),
+		c"/proc/4294967295/status",
+	);
+}
+
+#[test]
+fn parse_proc_status_groups_values() {
+	let status = concat!(
+		"Name:\tcat\n",
+		"Uid:\t1000\t1000\t1000\t1000\n",
+		"Gid:\t1000\t1000\t1000\t1000\n",
+		"FDSize:\t256\n",
+		"Groups:\t4 24 27 1000 \n",
+		"NStgid:\t1234\n",
+	).as_bytes();
+
+	let mut buf = [0u32; 8];
+	assert_eq!(
+		parse_proc_status_groups(status, &mut buf),
+		Some(&[4, 24, 27, 1000][..]),
+	);
+
+	let mut buf = [0u32; 3];
+	assert_eq!(parse_proc_status_groups(status, &mut buf), None);
+}
+
+#[test]
+fn parse_proc_status_groups_empty() {
+	let status = b"Gid:\t0\t0\t0\t0\nGroups:\t\nNStgid:\t1\n";
+	let mut buf = [0u32; 0];
+	assert_eq!(parse_proc_status_groups(status, &mut buf), Some(&[][..]));
+}
+
+#[test]
+fn parse_proc_status_groups_malformed() {
+	let mut buf = [0u32; 8];
+	assert_eq!(parse_proc_status_groups(b"Gid:\t0\n", &mut buf), None);
+	assert_eq!(parse_proc_status_groups(b"Groups:\tx\n", &mut buf), None);
+}
+
+#[test]
+fn parse_proc_status_nspid_values() {
+	let status = concat!(
+		"Name:\tcat\n",
+		"Pid:\t5678\n",
+		"NStgid:\t5678\t120\t1\n",
+		"NSpid:\t5678\t120\t1\n",
+		"NSpgid:\t5678\t120\t1\n",
+	).as_bytes();
+
+	let mut buf = [0u32; 4];
+	assert_eq!(
+		parse_proc_status_nspid(status, &mut buf),
+		Some(&[5678, 120, 1][..]),
+	);
+
+	let mut buf = [0u32; 2];
+	assert_eq!(parse_proc_status_nspid(status, &mut buf), None);
+
+	let mut buf = [0u32; 1];
+	assert_eq!(
+		parse_proc_status_nspid(b"NSpid:\t42\n", &mut buf),
+		Some(&[42][..]),
+	);
+}
+
+#[test]
+fn parse_proc_status_nspid_malformed() {
+	let mut buf = [0u32; 4];
+	assert_eq!(parse_proc_status_nspid(b"Pid:\t42\n", &mut buf), None);
+	assert_eq!(parse_proc_status_nspid(b"NSpid:\t\n", &mut buf), None);
+	assert_eq!(parse_proc_status_nspid(b"NSpid:\t4x\n", &mut buf), None);
+}
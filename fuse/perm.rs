@@ -0,0 +1,135 @@
+// Copyright 2022 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Unix permission checking.
+//!
+//! Filesystems mounted without the `default_permissions` option are
+//! responsible for their own permission checks. The functions in this module
+//! implement the standard Unix rules, matching the checks that the kernel
+//! would perform if `default_permissions` were set.
+//!
+//! The superuser (user ID 0) bypasses read and write checks. It may execute
+//! a file only if at least one of its execute bits is set, and may always
+//! search a directory.
+
+use crate::{Credentials, FileType, NodeAttr};
+
+/// Test for execute (or search) permission.
+pub const X_OK: u32 = 1;
+
+/// Test for write permission.
+pub const W_OK: u32 = 2;
+
+/// Test for read permission.
+pub const R_OK: u32 = 4;
+
+const S_ISVTX: u32 = 0o1000;
+
+/// Returns whether the given credentials grant access to a node.
+///
+/// The `mask` is a combination of [`R_OK`], [`W_OK`], and [`X_OK`], as used
+/// by `access(2)` and [`AccessRequest::mask`]. A mask of zero tests only for
+/// the existence of the node, and is always allowed.
+///
+/// [`AccessRequest::mask`]: crate::server::AccessRequest::mask
+#[must_use]
+pub fn may_access(attr: &NodeAttr, creds: &Credentials, mask: u32) -> bool {
+	let mask = mask & (R_OK | W_OK | X_OK);
+	if creds.user_id() == 0 {
+		return superuser_may_access(attr, mask);
+	}
+
+	let perms = attr.mode().permissions();
+	let class_perms = if creds.user_id() == attr.user_id() {
+		perms >> 6
+	} else if creds.in_group(attr.group_id()) {
+		perms >> 3
+	} else {
+		perms
+	};
+	class_perms & mask == mask
+}
+
+/// Returns whether the given credentials allow `node` to be removed from
+/// directory `dir`, as by `unlink(2)` or `rmdir(2)`.
+///
+/// Removal requires write and search permission on the directory. If the
+/// directory has its sticky bit set, then the caller must additionally own
+/// either the directory or the node.
+#[must_use]
+pub fn may_delete(
+	dir: &NodeAttr,
+	node: &NodeAttr,
+	creds: &Credentials,
+) -> bool {
+	if !may_access(dir, creds, W_OK | X_OK) {
+		return false;
+	}
+	if dir.mode().get() & S_ISVTX == 0 || creds.user_id() == 0 {
+		return true;
+	}
+	creds.user_id() == dir.user_id() || creds.user_id() == node.user_id()
+}
+
+/// Returns whether the given credentials allow `node` to be renamed from
+/// directory `old_dir` into directory `new_dir`.
+///
+/// If the rename would replace an existing node, that node must be passed as
+/// `new_node`. Both the source and any replaced node must satisfy
+/// [`may_delete`], and the caller must have write and search permission on
+/// the destination directory. Moving a directory to a new parent also
+/// requires write permission on the directory itself, because its `..` entry
+/// is updated.
+#[must_use]
+pub fn may_rename(
+	old_dir: &NodeAttr,
+	node: &NodeAttr,
+	new_dir: &NodeAttr,
+	new_node: Option<&NodeAttr>,
+	creds: &Credentials,
+) -> bool {
+	if !may_delete(old_dir, node, creds) {
+		return false;
+	}
+	match new_node {
+		Some(new_node) => {
+			if !may_delete(new_dir, new_node, creds) {
+				return false;
+			}
+		},
+		None => {
+			if !may_access(new_dir, creds, W_OK | X_OK) {
+				return false;
+			}
+		},
+	}
+	let is_dir = FileType::from_mode(node.mode()) == Some(FileType::Directory);
+	if is_dir && old_dir.node_id() != new_dir.node_id() {
+		return may_access(node, creds, W_OK);
+	}
+	true
+}
+
+pub(crate) fn superuser_may_access(attr: &NodeAttr, mask: u32) -> bool {
+	if mask & X_OK == 0 {
+		return true;
+	}
+	let mode = attr.mode();
+	if FileType::from_mode(mode) == Some(FileType::Directory) {
+		return true;
+	}
+	mode.permissions() & 0o111 != 0
+}
@@ -0,0 +1,131 @@
+// Copyright 2022 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use fuse::perm::{may_access, may_delete, may_rename, R_OK, W_OK, X_OK};
+use fuse::{Credentials, FileMode, NodeAttr, NodeId};
+
+fn node(id: u64, mode: FileMode, uid: u32, gid: u32) -> NodeAttr {
+	let mut attr = NodeAttr::new(NodeId::new(id).unwrap());
+	attr.set_mode(mode);
+	attr.set_user_id(uid);
+	attr.set_group_id(gid);
+	attr
+}
+
+const ROOT: Credentials = Credentials::new(0, 0);
+const OWNER: Credentials = Credentials::new(1000, 1000);
+const OTHER: Credentials = Credentials::new(2000, 2000);
+
+#[test]
+fn access_owner_group_other() {
+	let file = node(2, FileMode::S_IFREG | 0o640, 1000, 100);
+
+	assert!(may_access(&file, &OWNER, R_OK | W_OK));
+	assert!(!may_access(&file, &OWNER, X_OK));
+
+	let mut group_member = OTHER;
+	group_member.set_group_id(100);
+	assert!(may_access(&file, &group_member, R_OK));
+	assert!(!may_access(&file, &group_member, W_OK));
+
+	assert!(!may_access(&file, &OTHER, R_OK));
+	assert!(may_access(&file, &OTHER, 0));
+}
+
+#[test]
+fn access_owner_class_is_exclusive() {
+	// The owner is denied even though "other" would be allowed.
+	let file = node(2, FileMode::S_IFREG | 0o007, 1000, 1000);
+	assert!(!may_access(&file, &OWNER, R_OK));
+	assert!(may_access(&file, &OTHER, R_OK | W_OK | X_OK));
+}
+
+#[test]
+fn access_supplementary_groups() {
+	let file = node(2, FileMode::S_IFREG | 0o040, 1000, 100);
+
+	let groups = [50, 100];
+	let mut creds = OTHER;
+	assert!(!may_access(&file, &creds, R_OK));
+	creds.set_supplementary_groups(&groups);
+	assert!(may_access(&file, &creds, R_OK));
+}
+
+#[test]
+fn access_root() {
+	let file = node(2, FileMode::S_IFREG | 0o000, 1000, 1000);
+	assert!(may_access(&file, &ROOT, R_OK | W_OK));
+	assert!(!may_access(&file, &ROOT, X_OK));
+
+	let exe = node(2, FileMode::S_IFREG | 0o001, 1000, 1000);
+	assert!(may_access(&exe, &ROOT, X_OK));
+
+	let dir = node(2, FileMode::S_IFDIR | 0o000, 1000, 1000);
+	assert!(may_access(&dir, &ROOT, R_OK | W_OK | X_OK));
+}
+
+#[test]
+fn delete() {
+	let dir = node(1, FileMode::S_IFDIR | 0o777, 0, 0);
+	let file = node(2, FileMode::S_IFREG | 0o000, 1000, 1000);
+	assert!(may_delete(&dir, &file, &OTHER));
+
+	let dir = node(1, FileMode::S_IFDIR | 0o755, 0, 0);
+	assert!(!may_delete(&dir, &file, &OWNER));
+	assert!(may_delete(&dir, &file, &ROOT));
+}
+
+#[test]
+fn delete_sticky() {
+	let dir = node(1, FileMode::S_IFDIR | 0o1777, 3000, 3000);
+	let file = node(2, FileMode::S_IFREG | 0o666, 1000, 1000);
+
+	assert!(may_delete(&dir, &file, &OWNER));
+	assert!(!may_delete(&dir, &file, &OTHER));
+	assert!(may_delete(&dir, &file, &Credentials::new(3000, 3000)));
+	assert!(may_delete(&dir, &file, &ROOT));
+}
+
+#[test]
+fn rename() {
+	let dir_a = node(1, FileMode::S_IFDIR | 0o777, 0, 0);
+	let dir_b = node(3, FileMode::S_IFDIR | 0o755, 0, 0);
+	let file = node(2, FileMode::S_IFREG | 0o644, 1000, 1000);
+
+	assert!(may_rename(&dir_a, &file, &dir_a, None, &OWNER));
+	assert!(!may_rename(&dir_a, &file, &dir_b, None, &OWNER));
+	assert!(may_rename(&dir_a, &file, &dir_b, None, &ROOT));
+}
+
+#[test]
+fn rename_replace_sticky() {
+	let dir = node(1, FileMode::S_IFDIR | 0o1777, 0, 0);
+	let file = node(2, FileMode::S_IFREG | 0o644, 1000, 1000);
+	let target = node(3, FileMode::S_IFREG | 0o666, 2000, 2000);
+
+	assert!(!may_rename(&dir, &file, &dir, Some(&target), &OWNER));
+	assert!(may_rename(&dir, &file, &dir, None, &OWNER));
+}
+
+#[test]
+fn rename_directory_to_new_parent() {
+	let dir_a = node(1, FileMode::S_IFDIR | 0o777, 0, 0);
+	let dir_b = node(3, FileMode::S_IFDIR | 0o777, 0, 0);
+	let subdir = node(2, FileMode::S_IFDIR | 0o555, 1000, 1000);
+
+	assert!(may_rename(&dir_a, &subdir, &dir_a, None, &OWNER));
+	assert!(!may_rename(&dir_a, &subdir, &dir_b, None, &OWNER));
+}
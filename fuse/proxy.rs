@@ -0,0 +1,329 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Forwarding of FUSE requests between sockets.
+//!
+//! A [`FuseProxy`] relays requests from a FUSE client, such as the kernel's
+//! `/dev/fuse` device, to a FUSE server listening on another socket, and
+//! relays the server's responses back to the client. This allows the server
+//! to run in a separate process or sandbox from the code that owns the
+//! FUSE session.
+//!
+//! Requests are forwarded with new request IDs, so that IDs chosen by one
+//! client don't need to be unique across all the clients of a server.
+//! `FUSE_INTERRUPT` requests are rewritten to refer to the forwarded ID of
+//! the interrupted request.
+
+use core::mem::size_of;
+use core::num::NonZeroU64;
+use core::ptr;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::client;
+use crate::io::SendBuf;
+use crate::kernel;
+use crate::server;
+
+// ProxyError {{{
+
+/// Errors that may be encountered by a [`FuseProxy`].
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ProxyError<ClientIoError, ServerIoError> {
+	/// The client socket encountered an error when receiving a request.
+	ClientRecvError(server::RecvError<ClientIoError>),
+
+	/// The client socket encountered an error when sending a response.
+	ClientSendError(server::SendError<ClientIoError>),
+
+	/// The server socket encountered an error when receiving a response.
+	ServerRecvError(client::io::RecvError<ServerIoError>),
+
+	/// The server socket encountered an error when sending a request.
+	ServerSendError(client::io::SendError<ServerIoError>),
+
+	/// A request received from the client is malformed.
+	InvalidRequest,
+
+	/// A response received from the server is malformed, or doesn't match
+	/// any request that is in flight.
+	InvalidResponse,
+
+	/// Every [`ProxySlot`] is in use by a request that is in flight.
+	///
+	/// The request with the given ID hasn't been forwarded. The caller
+	/// should send an error reply to the client.
+	Busy(NonZeroU64),
+}
+
+// }}}
+
+// ProxySlot {{{
+
+/// Tracks a single request that has been forwarded to the server.
+///
+/// A [`FuseProxy`] is constructed with a slice of slots, which limits the
+/// number of requests that may be in flight at the same time.
+pub struct ProxySlot {
+	client_id: AtomicU64,
+}
+
+impl ProxySlot {
+	/// Creates a new, unused `ProxySlot`.
+	#[inline]
+	#[must_use]
+	pub const fn new() -> ProxySlot {
+		Self {
+			client_id: AtomicU64::new(0),
+		}
+	}
+}
+
+// }}}
+
+// FuseProxy {{{
+
+/// Relays requests and responses between a FUSE client and a FUSE server.
+///
+/// The proxy doesn't spawn threads or wait on more than one socket. A
+/// typical proxy calls [`forward_request`] in a loop on one thread, and
+/// [`forward_response`] in a loop on another.
+///
+/// [`forward_request`]: FuseProxy::forward_request
+/// [`forward_response`]: FuseProxy::forward_response
+pub struct FuseProxy<'a, C, S> {
+	client: C,
+	server: S,
+	slots: &'a [ProxySlot],
+}
+
+// The low bit of a request ID is set for `FUSE_INTERRUPT` requests, which
+// share the ID of the request they interrupt.
+const INTERRUPT_BIT: u64 = 1;
+
+impl<'a, C, S> FuseProxy<'a, C, S>
+where
+	C: server::FuseSocket,
+	S: client::io::FuseSocket,
+{
+	/// Creates a new `FuseProxy`.
+	///
+	/// Requests are received from `client` and forwarded to `server`. The
+	/// number of requests in flight is limited to the length of `slots`.
+	#[must_use]
+	pub fn new(
+		client: C,
+		server: S,
+		slots: &'a [ProxySlot],
+	) -> FuseProxy<'a, C, S> {
+		Self {
+			client,
+			server,
+			slots,
+		}
+	}
+
+	/// Receives a request from the client and forwards it to the server.
+	///
+	/// The buffer must be large enough to contain any request that might be
+	/// received from the client.
+	///
+	/// Requests that are answered by the server are assigned a slot until
+	/// their response is forwarded. `FUSE_FORGET`, `FUSE_BATCH_FORGET`, and
+	/// `FUSE_NOTIFY_REPLY` requests don't receive a response, and are
+	/// forwarded without using a slot. A `FUSE_INTERRUPT` for a request that
+	/// has already been answered is discarded.
+	pub fn forward_request(
+		&self,
+		buf: &mut [u8],
+	) -> Result<(), ProxyError<C::Error, S::Error>> {
+		const HEADER_LEN: usize = size_of::<kernel::fuse_in_header>();
+		const INTERRUPT_LEN: usize =
+			HEADER_LEN + size_of::<kernel::fuse_interrupt_in>();
+
+		let recv_len = self
+			.client
+			.recv(buf)
+			.map_err(ProxyError::ClientRecvError)?;
+		let msg = match buf.get_mut(..recv_len) {
+			Some(msg) if msg.len() >= HEADER_LEN => msg,
+			_ => return Err(ProxyError::InvalidRequest),
+		};
+		let mut header: kernel::fuse_in_header = unsafe {
+			ptr::read_unaligned(msg.as_ptr().cast())
+		};
+		if header.len as usize != msg.len() {
+			return Err(ProxyError::InvalidRequest);
+		}
+
+		let mut slot = None;
+		match header.opcode {
+			kernel::fuse_opcode::FUSE_FORGET
+			| kernel::fuse_opcode::FUSE_BATCH_FORGET => {
+				header.unique = self.untracked_id();
+			},
+			kernel::fuse_opcode::FUSE_NOTIFY_REPLY => {
+				// The request ID was chosen by the server, in the
+				// `FUSE_NOTIFY_RETRIEVE` notification.
+			},
+			kernel::fuse_opcode::FUSE_INTERRUPT => {
+				if msg.len() < INTERRUPT_LEN {
+					return Err(ProxyError::InvalidRequest);
+				}
+				let body_ptr = msg[HEADER_LEN..].as_mut_ptr();
+				let mut body: kernel::fuse_interrupt_in = unsafe {
+					ptr::read_unaligned(body_ptr.cast())
+				};
+				let server_id = match self.find(body.unique) {
+					Some(server_id) => server_id,
+					None => return Ok(()),
+				};
+				body.unique = server_id;
+				header.unique = server_id | INTERRUPT_BIT;
+				unsafe { ptr::write_unaligned(body_ptr.cast(), body) };
+			},
+			_ => {
+				let client_id = match NonZeroU64::new(header.unique) {
+					Some(id) => id,
+					None => return Err(ProxyError::InvalidRequest),
+				};
+				let index = match self.acquire(client_id) {
+					Some(index) => index,
+					None => return Err(ProxyError::Busy(client_id)),
+				};
+				header.unique = server_id(index);
+				slot = Some(index);
+			},
+		}
+		unsafe { ptr::write_unaligned(msg.as_mut_ptr().cast(), header) };
+
+		let result = self.server.send(SendBuf::new_1(msg.len(), msg));
+		if let Err(err) = result {
+			if let Some(index) = slot {
+				self.slots[index].client_id.store(0, Ordering::Release);
+			}
+			return Err(ProxyError::ServerSendError(err));
+		}
+		Ok(())
+	}
+
+	/// Receives a response from the server and forwards it to the client.
+	///
+	/// The buffer must be large enough to contain any response that might be
+	/// received from the server. Notifications are forwarded unchanged.
+	pub fn forward_response(
+		&self,
+		buf: &mut [u8],
+	) -> Result<(), ProxyError<C::Error, S::Error>> {
+		const HEADER_LEN: usize = size_of::<kernel::fuse_out_header>();
+
+		let recv_len = self
+			.server
+			.recv(buf)
+			.map_err(ProxyError::ServerRecvError)?;
+		let msg = match buf.get_mut(..recv_len) {
+			Some(msg) if msg.len() >= HEADER_LEN => msg,
+			_ => return Err(ProxyError::InvalidResponse),
+		};
+		let mut header: kernel::fuse_out_header = unsafe {
+			ptr::read_unaligned(msg.as_ptr().cast())
+		};
+		if header.len as usize != msg.len() {
+			return Err(ProxyError::InvalidResponse);
+		}
+
+		if header.unique != 0 {
+			let index = (header.unique >> 1).wrapping_sub(1);
+			let slot = match usize::try_from(index) {
+				Ok(index) => self.slots.get(index),
+				Err(_) => None,
+			};
+			let slot = match slot {
+				Some(slot) => slot,
+				None => return Err(ProxyError::InvalidResponse),
+			};
+			let client_id = if header.unique & INTERRUPT_BIT == 0 {
+				slot.client_id.swap(0, Ordering::AcqRel)
+			} else {
+				slot.client_id.load(Ordering::Acquire) | INTERRUPT_BIT
+			};
+			if client_id & !INTERRUPT_BIT == 0 {
+				return Err(ProxyError::InvalidResponse);
+			}
+			header.unique = client_id;
+			unsafe { ptr::write_unaligned(msg.as_mut_ptr().cast(), header) };
+		}
+
+		self.client
+			.send(SendBuf::new_1(msg.len(), msg))
+			.map_err(ProxyError::ClientSendError)
+	}
+
+	fn acquire(&self, client_id: NonZeroU64) -> Option<usize> {
+		for (index, slot) in self.slots.iter().enumerate() {
+			let acquired = slot.client_id.compare_exchange(
+				0,
+				client_id.get(),
+				Ordering::AcqRel,
+				Ordering::Relaxed,
+			);
+			if acquired.is_ok() {
+				return Some(index);
+			}
+		}
+		None
+	}
+
+	fn find(&self, client_id: u64) -> Option<u64> {
+		if client_id == 0 {
+			return None;
+		}
+		for (index, slot) in self.slots.iter().enumerate() {
+			if slot.client_id.load(Ordering::Acquire) == client_id {
+				return Some(server_id(index));
+			}
+		}
+		None
+	}
+
+	// Requests without a response are forwarded with an ID that doesn't
+	// belong to any slot.
+	fn untracked_id(&self) -> u64 {
+		server_id(self.slots.len())
+	}
+}
+
+impl<C, S> FuseProxy<'_, C, S> {
+	/// Returns a reference to the socket connected to the client.
+	#[inline]
+	#[must_use]
+	pub fn client_socket(&self) -> &C {
+		&self.client
+	}
+
+	/// Returns a reference to the socket connected to the server.
+	#[inline]
+	#[must_use]
+	pub fn server_socket(&self) -> &S {
+		&self.server
+	}
+}
+
+fn server_id(index: usize) -> u64 {
+	(index as u64 + 1) << 1
+}
+
+// }}}
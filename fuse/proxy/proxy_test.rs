@@ -0,0 +1,285 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::mem::size_of;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+use fuse::client;
+use fuse::kernel;
+use fuse::kernel::fuse_opcode;
+use fuse::proxy::{FuseProxy, ProxyError, ProxySlot};
+use fuse::server;
+
+use fuse_testutil as testutil;
+use fuse_testutil::{MessageBuilder, SendBufToVec};
+
+struct FakeSocket {
+	recv: RefCell<VecDeque<Vec<u8>>>,
+	sent: RefCell<Vec<Vec<u8>>>,
+}
+
+impl FakeSocket {
+	fn new() -> FakeSocket {
+		Self {
+			recv: RefCell::new(VecDeque::new()),
+			sent: RefCell::new(Vec::new()),
+		}
+	}
+
+	fn push(&self, msg: Vec<u8>) {
+		self.recv.borrow_mut().push_back(msg);
+	}
+
+	fn pop_sent(&self) -> Option<Vec<u8>> {
+		let mut sent = self.sent.borrow_mut();
+		if sent.is_empty() {
+			return None;
+		}
+		Some(sent.remove(0))
+	}
+
+	fn recv_impl(&self, buf: &mut [u8]) -> usize {
+		let msg = self.recv.borrow_mut().pop_front().unwrap();
+		buf[..msg.len()].copy_from_slice(&msg);
+		msg.len()
+	}
+}
+
+impl server::Socket for FakeSocket {
+	type Error = ();
+
+	fn recv(&self, buf: &mut [u8]) -> Result<usize, server::RecvError<()>> {
+		Ok(self.recv_impl(buf))
+	}
+
+	fn send(
+		&self,
+		buf: fuse::io::SendBuf,
+	) -> Result<(), server::SendError<()>> {
+		self.sent.borrow_mut().push(buf.to_vec());
+		Ok(())
+	}
+}
+
+impl server::FuseSocket for FakeSocket {}
+
+impl client::io::Socket for FakeSocket {
+	type Error = ();
+
+	fn send(
+		&self,
+		buf: fuse::io::SendBuf,
+	) -> Result<(), client::io::SendError<()>> {
+		self.sent.borrow_mut().push(buf.to_vec());
+		Ok(())
+	}
+
+	fn recv(&self, buf: &mut [u8]) -> Result<usize, client::io::RecvError<()>> {
+		Ok(self.recv_impl(buf))
+	}
+}
+
+impl client::io::FuseSocket for FakeSocket {}
+
+fn request(opcode: fuse_opcode, unique: u64, body: &[u8]) -> Vec<u8> {
+	MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = opcode;
+			h.unique = unique;
+			h.nodeid = 1;
+		})
+		.push_bytes(body)
+		.build()
+}
+
+fn response(unique: u64, error: i32, body: &[u8]) -> Vec<u8> {
+	let header = testutil::new!(kernel::fuse_out_header {
+		len: (size_of::<kernel::fuse_out_header>() + body.len()) as u32,
+		error: error,
+		unique: unique,
+	});
+	MessageBuilder::new()
+		.push_sized(&header)
+		.push_bytes(body)
+		.build()
+}
+
+fn interrupt(unique: u64, interrupted: u64) -> Vec<u8> {
+	let body = testutil::new!(kernel::fuse_interrupt_in {
+		unique: interrupted,
+	});
+	request(fuse_opcode::FUSE_INTERRUPT, unique, body.as_bytes())
+}
+
+#[test]
+fn forward_request_and_response() {
+	let client_socket = FakeSocket::new();
+	let server_socket = FakeSocket::new();
+	let slots = [ProxySlot::new(), ProxySlot::new()];
+	let proxy = FuseProxy::new(&client_socket, &server_socket, &slots);
+	let mut buf = [0u8; 1024];
+
+	client_socket.push(request(fuse_opcode::FUSE_LOOKUP, 10, b"a\0"));
+	client_socket.push(request(fuse_opcode::FUSE_LOOKUP, 12, b"b\0"));
+	proxy.forward_request(&mut buf).unwrap();
+	proxy.forward_request(&mut buf).unwrap();
+	assert_eq!(
+		server_socket.pop_sent().unwrap(),
+		request(fuse_opcode::FUSE_LOOKUP, 2, b"a\0"),
+	);
+	assert_eq!(
+		server_socket.pop_sent().unwrap(),
+		request(fuse_opcode::FUSE_LOOKUP, 4, b"b\0"),
+	);
+
+	server_socket.push(response(4, -2, b""));
+	server_socket.push(response(2, 0, b"hello"));
+	proxy.forward_response(&mut buf).unwrap();
+	proxy.forward_response(&mut buf).unwrap();
+	assert_eq!(client_socket.pop_sent().unwrap(), response(12, -2, b""));
+	assert_eq!(client_socket.pop_sent().unwrap(), response(10, 0, b"hello"));
+
+	// Slots are released once the response has been forwarded.
+	server_socket.push(response(2, 0, b""));
+	assert_eq!(
+		proxy.forward_response(&mut buf),
+		Err(ProxyError::InvalidResponse),
+	);
+	client_socket.push(request(fuse_opcode::FUSE_GETATTR, 14, b""));
+	proxy.forward_request(&mut buf).unwrap();
+	assert_eq!(
+		server_socket.pop_sent().unwrap(),
+		request(fuse_opcode::FUSE_GETATTR, 2, b""),
+	);
+}
+
+#[test]
+fn forward_interrupt() {
+	let client_socket = FakeSocket::new();
+	let server_socket = FakeSocket::new();
+	let slots = [ProxySlot::new(), ProxySlot::new()];
+	let proxy = FuseProxy::new(&client_socket, &server_socket, &slots);
+	let mut buf = [0u8; 1024];
+
+	client_socket.push(request(fuse_opcode::FUSE_LOOKUP, 10, b"a\0"));
+	client_socket.push(request(fuse_opcode::FUSE_READ, 20, b""));
+	proxy.forward_request(&mut buf).unwrap();
+	proxy.forward_request(&mut buf).unwrap();
+	server_socket.pop_sent().unwrap();
+	server_socket.pop_sent().unwrap();
+
+	client_socket.push(interrupt(21, 20));
+	proxy.forward_request(&mut buf).unwrap();
+	assert_eq!(server_socket.pop_sent().unwrap(), interrupt(5, 4));
+
+	// The server asks for the interrupt to be retried.
+	server_socket.push(response(5, -11, b""));
+	proxy.forward_response(&mut buf).unwrap();
+	assert_eq!(client_socket.pop_sent().unwrap(), response(21, -11, b""));
+
+	// Interrupts for requests that have been answered are discarded.
+	server_socket.push(response(2, 0, b""));
+	proxy.forward_response(&mut buf).unwrap();
+	client_socket.pop_sent().unwrap();
+	client_socket.push(interrupt(11, 10));
+	proxy.forward_request(&mut buf).unwrap();
+	assert!(server_socket.pop_sent().is_none());
+}
+
+#[test]
+fn forward_forget() {
+	let client_socket = FakeSocket::new();
+	let server_socket = FakeSocket::new();
+	let slots = [ProxySlot::new()];
+	let proxy = FuseProxy::new(&client_socket, &server_socket, &slots);
+	let mut buf = [0u8; 1024];
+
+	let forget = testutil::new!(kernel::fuse_forget_in {
+		nlookup: 1,
+	});
+	let forget = forget.as_bytes();
+	client_socket.push(request(fuse_opcode::FUSE_FORGET, 10, forget));
+	proxy.forward_request(&mut buf).unwrap();
+	assert_eq!(
+		server_socket.pop_sent().unwrap(),
+		request(fuse_opcode::FUSE_FORGET, 4, forget),
+	);
+
+	// Forgets don't occupy a slot.
+	client_socket.push(request(fuse_opcode::FUSE_GETATTR, 12, b""));
+	proxy.forward_request(&mut buf).unwrap();
+	assert_eq!(
+		server_socket.pop_sent().unwrap(),
+		request(fuse_opcode::FUSE_GETATTR, 2, b""),
+	);
+}
+
+#[test]
+fn forward_notification() {
+	let client_socket = FakeSocket::new();
+	let server_socket = FakeSocket::new();
+	let slots = [ProxySlot::new()];
+	let proxy = FuseProxy::new(&client_socket, &server_socket, &slots);
+	let mut buf = [0u8; 1024];
+
+	let notification = response(0, 1, b"poll-wakeup");
+	server_socket.push(notification.clone());
+	proxy.forward_response(&mut buf).unwrap();
+	assert_eq!(client_socket.pop_sent().unwrap(), notification);
+}
+
+#[test]
+fn busy() {
+	let client_socket = FakeSocket::new();
+	let server_socket = FakeSocket::new();
+	let slots = [ProxySlot::new()];
+	let proxy = FuseProxy::new(&client_socket, &server_socket, &slots);
+	let mut buf = [0u8; 1024];
+
+	client_socket.push(request(fuse_opcode::FUSE_GETATTR, 10, b""));
+	client_socket.push(request(fuse_opcode::FUSE_GETATTR, 12, b""));
+	proxy.forward_request(&mut buf).unwrap();
+	assert_eq!(
+		proxy.forward_request(&mut buf),
+		Err(ProxyError::Busy(core::num::NonZeroU64::new(12).unwrap())),
+	);
+	server_socket.pop_sent().unwrap();
+	assert!(server_socket.pop_sent().is_none());
+}
+
+#[test]
+fn invalid_messages() {
+	let client_socket = FakeSocket::new();
+	let server_socket = FakeSocket::new();
+	let slots = [ProxySlot::new()];
+	let proxy = FuseProxy::new(&client_socket, &server_socket, &slots);
+	let mut buf = [0u8; 1024];
+
+	let mut truncated = request(fuse_opcode::FUSE_GETATTR, 10, b"");
+	truncated.pop();
+	client_socket.push(truncated);
+	assert_eq!(
+		proxy.forward_request(&mut buf),
+		Err(ProxyError::InvalidRequest),
+	);
+
+	server_socket.push(response(100, 0, b""));
+	assert_eq!(
+		proxy.forward_response(&mut buf),
+		Err(ProxyError::InvalidResponse),
+	);
+}
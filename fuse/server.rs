@@ -16,7 +16,15 @@
 
 //! CUSE and FUSE servers.
 
+#[cfg(target_has_atomic = "ptr")]
+mod backpressure;
+pub mod conformance;
 pub(crate) mod decode;
+#[cfg(feature = "std")]
+mod dyn_socket;
+pub mod endian;
+mod hooks;
+mod stats;
 
 use core::cmp;
 use core::fmt;
@@ -41,10 +49,26 @@ use crate::operations::fuse_init::{
 	FuseInitFlags,
 };
 
+#[cfg(target_has_atomic = "ptr")]
+pub use backpressure::{Backpressure, BackpressurePermit};
+#[cfg(feature = "std")]
+pub use backpressure::BackpressureAcquire;
+#[cfg(feature = "std")]
+pub use dyn_socket::{
+	CuseSocketDyn,
+	DynSocket,
+	DynSocketError,
+	FuseSocketDyn,
+};
+pub use hooks::{Hooks, HooksSocket};
+pub use stats::ConnectionStats;
+#[cfg(feature = "tracing")]
+pub use hooks::TracingHooks;
+
 pub use crate::operations::{
 	access::AccessRequest,
-	bmap::BmapRequest,
-	copy_file_range::CopyFileRangeRequest,
+	bmap::{BmapRequest, BmapResponse},
+	copy_file_range::{CopyFileRangeRequest, CopyFileRangeResponse},
 	create::{CreateRequest, CreateResponse},
 	cuse_init::{CuseInitRequest, CuseInitResponse},
 	fallocate::FallocateRequest,
@@ -53,51 +77,86 @@ pub use crate::operations::{
 	fsync::FsyncRequest,
 	fsyncdir::FsyncdirRequest,
 	fuse_init::{FuseInitRequest, FuseInitResponse},
-	getattr::GetattrRequest,
-	getlk::GetlkRequest,
-	getxattr::GetxattrRequest,
+	getattr::{GetattrRequest, GetattrResponse},
+	getlk::{GetlkRequest, GetlkResponse},
+	getxattr::{GetxattrRequest, GetxattrResponse},
 	interrupt::InterruptRequest,
 	ioctl::{
+		IoctlArg,
+		IoctlCmd,
+		IoctlInput,
+		IoctlInputError,
+		IoctlInputReader,
 		IoctlPtr,
 		IoctlRequest,
 		IoctlResponse,
+		IoctlRetry,
 		IoctlRetryBuf,
+		IoctlRetryError,
+		IoctlSlice,
 	},
 	link::LinkRequest,
-	listxattr::{ListxattrNamesWriter, ListxattrRequest},
-	lookup::LookupRequest,
-	lseek::LseekRequest,
+	listxattr::{
+		ListxattrNames,
+		ListxattrNamesWriter,
+		ListxattrRequest,
+		ListxattrResponse,
+	},
+	lookup::{LookupName, LookupRequest, LookupResponse},
+	lseek::{LseekRequest, LseekResponse},
 	mkdir::MkdirRequest,
-	mknod::MknodRequest,
-	open::OpenRequest,
-	opendir::OpendirRequest,
-	poll::PollRequest,
+	mknod::{MknodError, MknodKind, MknodRequest, MknodResponse},
+	notify_reply::NotifyReplyRequest,
+	open::{OpenRequest, OpenResponse},
+	opendir::{OpendirRequest, OpendirResponse},
+	poll::{PollHandle, PollRequest, PollResponse},
 	read::ReadRequest,
 	readdir::{
+		DirCookie,
+		DirCookieError,
+		ReaddirCursor,
+		ReaddirEncodedError,
 		ReaddirEntry,
 		ReaddirEntries,
+		ReaddirEntriesIter,
 		ReaddirEntriesWriter,
 		ReaddirRequest,
 	},
 	readdirplus::{
+		ReaddirplusEncodedError,
 		ReaddirplusEntry,
 		ReaddirplusEntries,
+		ReaddirplusEntriesIter,
 		ReaddirplusEntriesWriter,
+		ReaddirplusLookups,
 		ReaddirplusRequest,
 	},
-	readlink::ReadlinkRequest,
+	readlink::{
+		ReadlinkRequest,
+		ReadlinkResponse,
+		ReadlinkTargetError,
+	},
 	release::ReleaseRequest,
 	releasedir::ReleasedirRequest,
 	removexattr::RemovexattrRequest,
 	rename::RenameRequest,
 	rmdir::RmdirRequest,
-	setattr::SetattrRequest,
+	security_ctx::{SecurityContext, SecurityContexts},
+	setattr::{
+		SetattrChanges,
+		SetattrRequest,
+		SetattrResponse,
+		SetattrTime,
+	},
 	setlk::SetlkRequest,
 	setxattr::SetxattrRequest,
-	statfs::StatfsRequest,
+	statfs::{StatfsRequest, StatfsResponse},
+	statx::{StatxRequest, StatxResponse},
 	symlink::SymlinkRequest,
+	syncfs::{SyncfsRequest, SyncfsResponse},
+	tmpfile::TmpfileRequest,
 	unlink::UnlinkRequest,
-	write::WriteRequest,
+	write::{WriteRequest, WriteResponse},
 };
 
 /// Errors that may be encountered when receiving a request.
@@ -110,10 +169,44 @@ pub enum RecvError<IoError> {
 	/// The connection has been cleanly closed by the client.
 	ConnectionClosed(IoError),
 
+	/// The socket is in non-blocking mode and no request is available.
+	///
+	/// Servers driven by an event loop should wait for the socket to become
+	/// readable before trying again.
+	WouldBlock(IoError),
+
 	/// The socket encountered an error not otherwise specified.
 	Other(IoError),
 }
 
+impl<E: fmt::Display> fmt::Display for RecvError<E> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::ConnectionClosed(err) => {
+				write!(fmt, "connection closed by client: {}", err)
+			},
+			Self::WouldBlock(err) => {
+				write!(fmt, "no request available: {}", err)
+			},
+			Self::Other(err) => write!(fmt, "error receiving request: {}", err),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl<E> core::error::Error for RecvError<E>
+where
+	E: core::error::Error + 'static,
+{
+	fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+		match self {
+			Self::ConnectionClosed(err)
+			| Self::WouldBlock(err)
+			| Self::Other(err) => Some(err),
+		}
+	}
+}
+
 /// Errors that may be encountered when sending a reply.
 ///
 /// Sockets may use the variants of this enum to provide hints to server code
@@ -130,10 +223,74 @@ pub enum SendError<IoError> {
 	/// of [`u32::MAX`] bytes.
 	ReplyTooBig(u64),
 
+	/// The reply included a file descriptor, but the socket is not able to
+	/// send file descriptors.
+	///
+	/// See [`Socket::can_send_fd`].
+	FdUnsupported,
+
+	/// The error reply's error number is outside the range accepted by
+	/// the client.
+	///
+	/// Error numbers must be negative, and the client implementation in
+	/// Linux rejects error numbers less than or equal to `-512`. This error
+	/// usually means that a positive OS error number was used to construct
+	/// a [`crate::Error`] without negating it.
+	InvalidErrno(crate::Error),
+
+	/// The reply marked a node as the root of a submount, but the
+	/// [`SUBMOUNTS`] init flag wasn't negotiated for the connection.
+	///
+	/// See [`FuseServer::enable_submounts`].
+	///
+	/// [`SUBMOUNTS`]: crate::FuseInitFlag::SUBMOUNTS
+	SubmountNotEnabled,
+
 	/// The socket encountered an error not otherwise specified.
 	Other(IoError),
 }
 
+impl<E: fmt::Display> fmt::Display for SendError<E> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::NotFound(err) => {
+				write!(fmt, "reply to forgotten request: {}", err)
+			},
+			Self::ReplyTooBig(size) => write!(
+				fmt,
+				"reply size {} exceeds the maximum of {} bytes",
+				size,
+				u32::MAX,
+			),
+			Self::FdUnsupported => {
+				fmt.write_str("socket can't send file descriptors")
+			},
+			Self::InvalidErrno(err) => {
+				write!(fmt, "invalid error number {}", err.0)
+			},
+			Self::SubmountNotEnabled => {
+				fmt.write_str("submount reply without FUSE_SUBMOUNTS")
+			},
+			Self::Other(err) => write!(fmt, "error sending reply: {}", err),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl<E> core::error::Error for SendError<E>
+where
+	E: core::error::Error + 'static,
+{
+	fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+		match self {
+			Self::NotFound(err) | Self::Other(err) => Some(err),
+			Self::ReplyTooBig(_) => None,
+			Self::FdUnsupported | Self::InvalidErrno(_) => None,
+			Self::SubmountNotEnabled => None,
+		}
+	}
+}
+
 /// Trait for sockets that can receive requests and send replies.
 pub trait Socket {
 	/// Type of errors that may be returned from this socket's I/O methods.
@@ -143,33 +300,131 @@ pub trait Socket {
 	///
 	/// The buffer must be large enough to contain any request that might be
 	/// received for the current session's negotiated maximum message size.
+	///
+	/// Non-blocking sockets should return [`RecvError::WouldBlock`] if no
+	/// request is available.
 	fn recv(&self, buf: &mut [u8]) -> Result<usize, RecvError<Self::Error>>;
 
 	/// Send a single serialised reply to the client.
 	fn send(&self, buf: SendBuf) -> Result<(), SendError<Self::Error>>;
+
+	/// Returns whether this socket is able to send file descriptors.
+	///
+	/// Transports such as Unix domain sockets can attach file descriptors
+	/// to a reply. The `/dev/fuse` character device can't, and file
+	/// descriptors must instead be registered with the kernel by `ioctl()`.
+	///
+	/// The default implementation returns `false`.
+	fn can_send_fd(&self) -> bool {
+		false
+	}
+
+	/// Send a single serialised reply to the client, with an attached file
+	/// descriptor.
+	///
+	/// The default implementation returns [`SendError::FdUnsupported`].
+	/// Sockets that override this method should also override
+	/// [`Socket::can_send_fd`].
+	fn send_with_fd(
+		&self,
+		buf: SendBuf,
+		fd: i32,
+	) -> Result<(), SendError<Self::Error>> {
+		let _ = (buf, fd);
+		Err(SendError::FdUnsupported)
+	}
 }
 
-impl<S: Socket> Socket for &S {
+impl<S: Socket + ?Sized> Socket for &S {
 	type Error = S::Error;
 
 	fn recv(&self, buf: &mut [u8]) -> Result<usize, RecvError<S::Error>> {
-		(*self).recv(buf)
+		(**self).recv(buf)
 	}
 
 	fn send(&self, buf: SendBuf) -> Result<(), SendError<S::Error>> {
-		(*self).send(buf)
+		(**self).send(buf)
+	}
+
+	fn can_send_fd(&self) -> bool {
+		(**self).can_send_fd()
+	}
+
+	fn send_with_fd(
+		&self,
+		buf: SendBuf,
+		fd: i32,
+	) -> Result<(), SendError<S::Error>> {
+		(**self).send_with_fd(buf, fd)
+	}
+}
+
+#[cfg(feature = "std")]
+impl<S: Socket + ?Sized> Socket for alloc::boxed::Box<S> {
+	type Error = S::Error;
+
+	fn recv(&self, buf: &mut [u8]) -> Result<usize, RecvError<S::Error>> {
+		(**self).recv(buf)
+	}
+
+	fn send(&self, buf: SendBuf) -> Result<(), SendError<S::Error>> {
+		(**self).send(buf)
+	}
+
+	fn can_send_fd(&self) -> bool {
+		(**self).can_send_fd()
+	}
+
+	fn send_with_fd(
+		&self,
+		buf: SendBuf,
+		fd: i32,
+	) -> Result<(), SendError<S::Error>> {
+		(**self).send_with_fd(buf, fd)
 	}
 }
 
 /// Marker trait for CUSE sockets.
 pub trait CuseSocket: Socket {}
 
-impl<S: CuseSocket> CuseSocket for &S {}
+impl<S: CuseSocket + ?Sized> CuseSocket for &S {}
+
+#[cfg(feature = "std")]
+impl<S: CuseSocket + ?Sized> CuseSocket for alloc::boxed::Box<S> {}
 
 /// Marker trait for FUSE sockets.
 pub trait FuseSocket: Socket {}
 
-impl<S: FuseSocket> FuseSocket for &S {}
+impl<S: FuseSocket + ?Sized> FuseSocket for &S {}
+
+#[cfg(feature = "std")]
+impl<S: FuseSocket + ?Sized> FuseSocket for alloc::boxed::Box<S> {}
+
+/// Trait for FUSE sockets that can unmount the filesystem they serve.
+///
+/// Unmounting requires a system call, so this trait is implemented by the
+/// socket types of OS-specific crates such as `fuse-libc` and `fuse-linux`.
+pub trait FuseUnmount: FuseSocket {
+	/// Unmount the filesystem served by this socket.
+	///
+	/// After the filesystem is unmounted the client closes the session, and
+	/// further calls to [`Socket::recv`] return
+	/// [`RecvError::ConnectionClosed`].
+	fn unmount(&self) -> Result<(), Self::Error>;
+}
+
+impl<S: FuseUnmount + ?Sized> FuseUnmount for &S {
+	fn unmount(&self) -> Result<(), S::Error> {
+		(**self).unmount()
+	}
+}
+
+#[cfg(feature = "std")]
+impl<S: FuseUnmount + ?Sized> FuseUnmount for alloc::boxed::Box<S> {
+	fn unmount(&self) -> Result<(), S::Error> {
+		(**self).unmount()
+	}
+}
 
 /// Errors that may be encountered by a CUSE or FUSE server.
 #[non_exhaustive]
@@ -203,29 +458,107 @@ impl<E> From<SendError<E>> for ServerError<E> {
 	}
 }
 
-#[allow(missing_docs)] // TODO
+impl<E: fmt::Display> fmt::Display for ServerError<E> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::RequestError(err) => fmt::Display::fmt(err, fmt),
+			Self::RecvError(err) => fmt::Display::fmt(err, fmt),
+			Self::SendError(err) => fmt::Display::fmt(err, fmt),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl<E> core::error::Error for ServerError<E>
+where
+	E: core::error::Error + 'static,
+{
+	fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+		match self {
+			Self::RequestError(err) => Some(err),
+			Self::RecvError(err) => Some(err),
+			Self::SendError(err) => Some(err),
+		}
+	}
+}
+
+/// Errors describing why a request layout can't be constructed.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[non_exhaustive]
 pub enum LayoutError {
-	Todo,
+	/// The major version differs from [`FUSE_KERNEL_VERSION`].
+	///
+	/// [`FUSE_KERNEL_VERSION`]: kernel::FUSE_KERNEL_VERSION
+	MajorVersionMismatch {
+		/// The major version of the rejected init response.
+		got: u32,
+	},
+
+	/// The minor version is greater than [`FUSE_KERNEL_MINOR_VERSION`], so
+	/// the layout of its requests is unknown.
+	///
+	/// [`FUSE_KERNEL_MINOR_VERSION`]: kernel::FUSE_KERNEL_MINOR_VERSION
+	MinorVersionTooNew {
+		/// The minor version of the rejected init response.
+		got: u32,
+	},
+}
+
+impl LayoutError {
+	const fn check_version(major: u32, minor: u32) -> Result<(), LayoutError> {
+		if major != kernel::FUSE_KERNEL_VERSION {
+			return Err(LayoutError::MajorVersionMismatch { got: major });
+		}
+		if minor > kernel::FUSE_KERNEL_MINOR_VERSION {
+			return Err(LayoutError::MinorVersionTooNew { got: minor });
+		}
+		Ok(())
+	}
+}
+
+impl fmt::Display for LayoutError {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::MajorVersionMismatch { got } => write!(
+				fmt,
+				"unsupported FUSE protocol major version {} (expected {})",
+				got,
+				kernel::FUSE_KERNEL_VERSION,
+			),
+			Self::MinorVersionTooNew { got } => write!(
+				fmt,
+				"unsupported FUSE protocol minor version {} (maximum {})",
+				got,
+				kernel::FUSE_KERNEL_MINOR_VERSION,
+			),
+		}
+	}
 }
 
+#[cfg(feature = "std")]
+impl core::error::Error for LayoutError {}
+
 #[allow(missing_docs)] // TODO
 #[derive(Clone, Copy, Eq, PartialEq)]
 pub struct CuseLayout {
-	version_minor: u16,
+	pub(crate) version_minor: u16,
 }
 
 impl CuseLayout {
-	#[allow(missing_docs)] // TODO
+	/// Returns the layout of requests for a session negotiated by the given
+	/// CUSE init response.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the response's protocol version isn't supported
+	/// by this library.
 	pub const fn new(
 		init_out: &kernel::cuse_init_out,
 	) -> Result<CuseLayout, LayoutError> {
-		if init_out.major != kernel::FUSE_KERNEL_VERSION {
-			return Err(LayoutError::Todo);
-		}
-		if init_out.minor > kernel::FUSE_KERNEL_MINOR_VERSION {
-			return Err(LayoutError::Todo);
+		let major = init_out.major;
+		let minor = init_out.minor;
+		if let Err(err) = LayoutError::check_version(major, minor) {
+			return Err(err);
 		}
 		Ok(Self {
 			version_minor: init_out.minor as u16,
@@ -245,18 +578,25 @@ pub struct FuseLayout {
 	pub(crate) features: u16,
 }
 
-const FEATURE_SETXATTR_EXT: u16 = 1 << 0;
+pub(crate) const FEATURE_SETXATTR_EXT: u16 = 1 << 0;
+pub(crate) const FEATURE_SUBMOUNTS: u16 = 1 << 1;
+pub(crate) const FEATURE_SECURITY_CTX: u16 = 1 << 2;
 
 impl FuseLayout {
-	#[allow(missing_docs)] // TODO
+	/// Returns the layout of requests for a session negotiated by the given
+	/// FUSE init response.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the response's protocol version isn't supported
+	/// by this library.
 	pub const fn new(
 		init_out: &kernel::fuse_init_out,
 	) -> Result<FuseLayout, LayoutError> {
-		if init_out.major != kernel::FUSE_KERNEL_VERSION {
-			return Err(LayoutError::Todo);
-		}
-		if init_out.minor > kernel::FUSE_KERNEL_MINOR_VERSION {
-			return Err(LayoutError::Todo);
+		let major = init_out.major;
+		let minor = init_out.minor;
+		if let Err(err) = LayoutError::check_version(major, minor) {
+			return Err(err);
 		}
 		Ok(Self::new2(init_out))
 	}
@@ -266,6 +606,17 @@ impl FuseLayout {
 		if init_out.flags & kernel::FUSE_SETXATTR_EXT != 0 {
 			features |= FEATURE_SETXATTR_EXT;
 		}
+		if init_out.flags & kernel::FUSE_SUBMOUNTS != 0 {
+			features |= FEATURE_SUBMOUNTS;
+		}
+		// Request extensions were added in v7.38, and `flags2` is only valid
+		// if `FUSE_INIT_EXT` is set.
+		if init_out.minor >= 38 && init_out.flags & kernel::FUSE_INIT_EXT != 0 {
+			let flags2 = (init_out.flags2 as u64) << 32;
+			if flags2 & kernel::FUSE_SECURITY_CTX != 0 {
+				features |= FEATURE_SECURITY_CTX;
+			}
+		}
 		Self {
 			version_minor: init_out.minor as u16,
 			features,
@@ -281,6 +632,16 @@ impl FuseLayout {
 	pub(crate) fn have_setxattr_ext(self) -> bool {
 		self.features & FEATURE_SETXATTR_EXT != 0
 	}
+
+	#[must_use]
+	pub(crate) fn have_submounts(self) -> bool {
+		self.features & FEATURE_SUBMOUNTS != 0
+	}
+
+	#[must_use]
+	pub(crate) fn have_security_ctx(self) -> bool {
+		self.features & FEATURE_SECURITY_CTX != 0
+	}
 }
 
 /// Errors describing why a request is invalid.
@@ -318,6 +679,17 @@ pub enum RequestError {
 	/// The request buffer contains an incomplete request.
 	UnexpectedEof,
 
+	/// The request contains a malformed list of security contexts.
+	InvalidSecurityContext,
+
+	/// The request contains a malformed extension header.
+	InvalidExtension,
+
+	/// The request was sent by a client with the opposite byte order.
+	///
+	/// See the [`endian`] module for how to serve such clients.
+	ForeignEndian,
+
 	// Errors indicating a programming error in the server.
 
 	/// Attempted to decode a request as the wrong type.
@@ -338,12 +710,48 @@ impl From<crate::NodeNameError> for RequestError {
 	}
 }
 
+impl fmt::Display for RequestError {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		let msg = match self {
+			Self::LockError(err) => {
+				return write!(fmt, "request contains invalid lock: {:?}", err);
+			},
+			Self::NodeNameError(err) => {
+				return write!(fmt, "request contains invalid name: {:?}", err);
+			},
+			Self::MissingNodeId => "request is missing a node ID",
+			Self::MissingRequestId => {
+				"interrupt request is missing a request ID"
+			},
+			Self::TimestampOverflow => "request contains an invalid timestamp",
+			Self::InvalidRequestId => "request ID is zero",
+			Self::UnexpectedEof => "request is truncated",
+			Self::InvalidSecurityContext => {
+				"request contains malformed security contexts"
+			},
+			Self::InvalidExtension => "request contains malformed extensions",
+			Self::ForeignEndian => "request has foreign byte order",
+			Self::OpcodeMismatch => "request decoded as the wrong type",
+		};
+		fmt.write_str(msg)
+	}
+}
+
+#[cfg(feature = "std")]
+impl core::error::Error for RequestError {}
+
 #[derive(Clone, Copy)]
 pub(crate) struct Request<'a> {
 	pub(crate) ptr: NonNull<u8>,
 	_ptr: PhantomData<&'a kernel::fuse_in_header>,
 }
 
+// A `Request` is a shared borrow of an immutable buffer, so it can be sent
+// between threads in the same way as a `&[u8]`.
+unsafe impl Send for Request<'_> {}
+
+unsafe impl Sync for Request<'_> {}
+
 impl<'a> Request<'a> {
 	fn new(buf: AlignedSlice<'a>) -> Result<Request<'a>, RequestError> {
 		let buf = buf.get();
@@ -351,6 +759,10 @@ impl<'a> Request<'a> {
 			return Err(RequestError::UnexpectedEof);
 		}
 
+		if endian::is_foreign_endian(buf) {
+			return Err(RequestError::ForeignEndian);
+		}
+
 		let header_ptr = buf.as_ptr().cast::<kernel::fuse_in_header>();
 		let header = unsafe { &*header_ptr };
 
@@ -464,6 +876,55 @@ impl fmt::Debug for CuseRequest<'_> {
 	}
 }
 
+/// A coarse classification of FUSE requests, derived from their opcode.
+///
+/// Multi-threaded servers can use a request's class to route it before the
+/// request is decoded, for example so that `FUSE_FORGET` and `FUSE_INTERRUPT`
+/// are handled on a lightweight path rather than waiting behind slow I/O.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RequestClass {
+	/// `FUSE_FORGET` or `FUSE_BATCH_FORGET`, which don't expect a reply.
+	Forget,
+
+	/// `FUSE_INTERRUPT`.
+	Interrupt,
+
+	/// Reads or writes the contents of an open file or directory, such as
+	/// `FUSE_READ`, `FUSE_WRITE`, `FUSE_FSYNC`, or `FUSE_READDIR`.
+	Io,
+
+	/// Any other request, such as `FUSE_LOOKUP` or `FUSE_GETATTR`.
+	Metadata,
+}
+
+impl RequestClass {
+	/// Returns the class of requests with the given opcode.
+	#[must_use]
+	pub const fn from_opcode(opcode: fuse_opcode) -> RequestClass {
+		match opcode {
+			fuse_opcode::FUSE_BATCH_FORGET => RequestClass::Forget,
+			fuse_opcode::FUSE_FORGET => RequestClass::Forget,
+			fuse_opcode::FUSE_INTERRUPT => RequestClass::Interrupt,
+			fuse_opcode::FUSE_COPY_FILE_RANGE => RequestClass::Io,
+			fuse_opcode::FUSE_FALLOCATE => RequestClass::Io,
+			fuse_opcode::FUSE_FLUSH => RequestClass::Io,
+			fuse_opcode::FUSE_FSYNC => RequestClass::Io,
+			fuse_opcode::FUSE_FSYNCDIR => RequestClass::Io,
+			fuse_opcode::FUSE_IOCTL => RequestClass::Io,
+			fuse_opcode::FUSE_LSEEK => RequestClass::Io,
+			fuse_opcode::FUSE_POLL => RequestClass::Io,
+			fuse_opcode::FUSE_READ => RequestClass::Io,
+			fuse_opcode::FUSE_READDIR => RequestClass::Io,
+			fuse_opcode::FUSE_READDIRPLUS => RequestClass::Io,
+			fuse_opcode::FUSE_REMOVEMAPPING => RequestClass::Io,
+			fuse_opcode::FUSE_SETUPMAPPING => RequestClass::Io,
+			fuse_opcode::FUSE_WRITE => RequestClass::Io,
+			_ => RequestClass::Metadata,
+		}
+	}
+}
+
 #[allow(missing_docs)] // TODO
 #[derive(Clone, Copy)]
 pub struct FuseRequest<'a> {
@@ -499,6 +960,15 @@ impl<'a> FuseRequest<'a> {
 		self.header().request_id()
 	}
 
+	/// Returns the class of this request.
+	///
+	/// Only the request header is inspected, so this is cheaper than
+	/// decoding the request.
+	#[must_use]
+	pub fn class(self) -> RequestClass {
+		RequestClass::from_opcode(self.header().opcode())
+	}
+
 	#[allow(missing_docs)] // TODO
 	#[must_use]
 	pub fn body(self) -> &'a [u8] {
@@ -539,22 +1009,50 @@ pub trait FuseReply {
 	) -> Result<(), SendError<S::Error>>;
 }
 
+// Error numbers in replies must be in the range `(-MAX_ERRNO, 0)`.
+const MAX_ERRNO: i32 = 512;
+
 pub(crate) struct ReplySender<'a, S> {
 	pub(crate) socket: &'a S,
 	pub(crate) request_id: u64,
+	pub(crate) stats: Option<&'a ConnectionStats>,
 }
 
 impl<'a, S: Socket> ReplySender<'a, S> {
-	fn send_0(self, error: i32) -> Result<(), SendError<S::Error>> {
+	fn send(
+		&self,
+		buf: SendBuf,
+		error: i32,
+	) -> Result<(), SendError<S::Error>> {
+		let len = buf.len();
+		let result = self.socket.send(buf);
+		if let Some(stats) = self.stats {
+			stats.record_reply(len, error, result.is_ok());
+		}
+		result
+	}
+
+	pub(crate) fn send_err(
+		self,
+		error: crate::Error,
+	) -> Result<(), SendError<S::Error>> {
+		let errno = error.0.get();
+		if errno > 0 || errno <= -MAX_ERRNO {
+			return Err(SendError::InvalidErrno(error));
+		}
+		self.send_0(errno)
+	}
+
+	pub(crate) fn send_0(self, error: i32) -> Result<(), SendError<S::Error>> {
 		let header = new!(kernel::fuse_out_header {
 			len: core::mem::size_of::<kernel::fuse_out_header>() as u32,
 			error: error,
 			unique: self.request_id,
 		});
-		self.socket.send(SendBuf::new_1(
-			header.len as usize,
-			header.as_bytes(),
-		))
+		self.send(
+			SendBuf::new_1(header.len as usize, header.as_bytes()),
+			error,
+		)
 	}
 
 	pub(crate) fn send_1(
@@ -570,11 +1068,12 @@ impl<'a, S: Socket> ReplySender<'a, S> {
 			len: len as u32,
 			unique: self.request_id,
 		});
-		self.socket.send(SendBuf::new_2(
+		let buf = SendBuf::new_2(
 			len as usize,
 			header.as_bytes(),
 			bytes_1,
-		))
+		);
+		self.send(buf, 0)
 	}
 
 	pub(crate) fn send_2(
@@ -592,12 +1091,13 @@ impl<'a, S: Socket> ReplySender<'a, S> {
 			len: len as u32,
 			unique: self.request_id,
 		});
-		self.socket.send(SendBuf::new_3(
+		let buf = SendBuf::new_3(
 			len as usize,
 			header.as_bytes(),
 			bytes_1,
 			bytes_2,
-		))
+		);
+		self.send(buf, 0)
 	}
 
 	pub(crate) fn send_3(
@@ -617,13 +1117,14 @@ impl<'a, S: Socket> ReplySender<'a, S> {
 			len: len as u32,
 			unique: self.request_id,
 		});
-		self.socket.send(SendBuf::new_4(
+		let buf = SendBuf::new_4(
 			len as usize,
 			header.as_bytes(),
 			bytes_1,
 			bytes_2,
 			bytes_3,
-		))
+		);
+		self.send(buf, 0)
 	}
 
 	pub(crate) fn send_4(
@@ -645,14 +1146,15 @@ impl<'a, S: Socket> ReplySender<'a, S> {
 			len: len as u32,
 			unique: self.request_id,
 		});
-		self.socket.send(SendBuf::new_5(
+		let buf = SendBuf::new_5(
 			len as usize,
 			header.as_bytes(),
 			bytes_1,
 			bytes_2,
 			bytes_3,
 			bytes_4,
-		))
+		);
+		self.send(buf, 0)
 	}
 }
 
@@ -674,6 +1176,7 @@ impl<'a, S: CuseSocket> CuseReplySender<'a, S> {
 			inner: ReplySender {
 				socket,
 				request_id: request_id.get(),
+				stats: None,
 			},
 		}
 	}
@@ -694,11 +1197,16 @@ impl<'a, S: CuseSocket> CuseReplySender<'a, S> {
 	}
 
 	/// Send an error reply to the CUSE client.
+	///
+	/// # Errors
+	///
+	/// Returns [`SendError::InvalidErrno`] without sending a reply if the
+	/// error number is positive or too large for the client to accept.
 	pub fn err(
 		self,
 		error: impl Into<crate::Error>,
 	) -> Result<(), SendError<S::Error>> {
-		self.inner.send_0(error.into().0.get())
+		self.inner.send_err(error.into())
 	}
 }
 
@@ -720,6 +1228,7 @@ impl<'a, S: FuseSocket> FuseReplySender<'a, S> {
 			inner: ReplySender {
 				socket,
 				request_id: request_id.get(),
+				stats: None,
 			},
 			layout,
 		}
@@ -741,11 +1250,29 @@ impl<'a, S: FuseSocket> FuseReplySender<'a, S> {
 	}
 
 	/// Send an error reply to the FUSE client.
+	///
+	/// # Errors
+	///
+	/// Returns [`SendError::InvalidErrno`] without sending a reply if the
+	/// error number is positive or too large for the client to accept.
 	pub fn err(
 		self,
 		error: impl Into<crate::Error>,
 	) -> Result<(), SendError<S::Error>> {
-		self.inner.send_0(error.into().0.get())
+		self.inner.send_err(error.into())
+	}
+
+	// The client ignores `FUSE_ATTR_SUBMOUNT` unless `FUSE_SUBMOUNTS` was
+	// negotiated, so a reply that sets it is almost certainly a server bug.
+	pub(crate) fn check_attr(
+		&self,
+		attr: &kernel::fuse_attr,
+	) -> Result<(), SendError<S::Error>> {
+		let is_submount = attr.flags & kernel::FUSE_ATTR_SUBMOUNT != 0;
+		if is_submount && !self.layout.have_submounts() {
+			return Err(SendError::SubmountNotEnabled);
+		}
+		Ok(())
 	}
 }
 
@@ -862,6 +1389,7 @@ pub trait FuseHandlers {
 			fuse_opcode::FUSE_LSEEK => self.lseek(request),
 			fuse_opcode::FUSE_MKDIR => self.mkdir(request),
 			fuse_opcode::FUSE_MKNOD => self.mknod(request),
+			fuse_opcode::FUSE_NOTIFY_REPLY => self.notify_reply(request),
 			fuse_opcode::FUSE_OPEN => self.open(request),
 			fuse_opcode::FUSE_OPENDIR => self.opendir(request),
 			fuse_opcode::FUSE_POLL => self.poll(request),
@@ -879,8 +1407,10 @@ pub trait FuseHandlers {
 			fuse_opcode::FUSE_SETLKW => self.setlkw(request),
 			fuse_opcode::FUSE_SETXATTR => self.setxattr(request),
 			fuse_opcode::FUSE_STATFS => self.statfs(request),
+			fuse_opcode::FUSE_STATX => self.statx(request),
 			fuse_opcode::FUSE_SYMLINK => self.symlink(request),
 			fuse_opcode::FUSE_SYNCFS => self.syncfs(request),
+			fuse_opcode::FUSE_TMPFILE => self.tmpfile(request),
 			fuse_opcode::FUSE_UNLINK => self.unlink(request),
 			_ => self.unimplemented(request),
 		}
@@ -1000,6 +1530,16 @@ pub trait FuseHandlers {
 		self.unimplemented(request)
 	}
 
+	/// Request handler for [`FUSE_NOTIFY_REPLY`].
+	///
+	/// The kernel doesn't expect a reply to this request, so the default
+	/// implementation discards it.
+	///
+	/// [`FUSE_NOTIFY_REPLY`]: fuse_opcode::FUSE_NOTIFY_REPLY
+	fn notify_reply(&self, request: FuseRequest<'_>) {
+		let _ = request;
+	}
+
 	/// Request handler for [`FUSE_OPEN`](fuse_opcode::FUSE_OPEN).
 	fn open(&self, request: FuseRequest<'_>) {
 		self.unimplemented(request)
@@ -1090,16 +1630,30 @@ pub trait FuseHandlers {
 		self.unimplemented(request)
 	}
 
+	/// Request handler for [`FUSE_STATX`](fuse_opcode::FUSE_STATX).
+	fn statx(&self, request: FuseRequest<'_>) {
+		self.unimplemented(request)
+	}
+
 	/// Request handler for [`FUSE_SYMLINK`](fuse_opcode::FUSE_SYMLINK).
 	fn symlink(&self, request: FuseRequest<'_>) {
 		self.unimplemented(request)
 	}
 
 	/// Request handler for [`FUSE_SYNCFS`](fuse_opcode::FUSE_SYNCFS).
+	///
+	/// Filesystems that buffer writes should persist them before replying
+	/// with a [`SyncfsResponse`]. If the server has created submounts, the
+	/// request's node ID identifies which one is being synced.
 	fn syncfs(&self, request: FuseRequest<'_>) {
 		self.unimplemented(request)
 	}
 
+	/// Request handler for [`FUSE_TMPFILE`](fuse_opcode::FUSE_TMPFILE).
+	fn tmpfile(&self, request: FuseRequest<'_>) {
+		self.unimplemented(request)
+	}
+
 	/// Request handler for [`FUSE_UNLINK`](fuse_opcode::FUSE_UNLINK).
 	fn unlink(&self, request: FuseRequest<'_>) {
 		self.unimplemented(request)
@@ -1116,6 +1670,7 @@ pub struct CuseConnection<S> {
 	socket: S,
 	layout: CuseLayout,
 	recv_buf_len: usize,
+	stats: ConnectionStats,
 }
 
 impl<S: CuseSocket> CuseConnection<S> {
@@ -1130,20 +1685,53 @@ impl<S: CuseSocket> CuseConnection<S> {
 		socket: S,
 		device_name: &CuseDeviceName,
 		device_number: CuseDeviceNumber,
-		mut init_fn: F,
+		init_fn: F,
 	) -> Result<CuseConnection<S>, ServerError<S::Error>>
 	where
 		F: FnMut(&CuseInitRequest, &mut CuseInitResponse),
 	{
 		let mut buf = crate::io::MinReadBuffer::new();
+		Self::connect_with_buf(
+			socket,
+			&mut buf,
+			device_name,
+			device_number,
+			init_fn,
+		)
+	}
+
+	/// Perform a CUSE connection handshake, receiving into the given buffer.
+	///
+	/// This is equivalent to [`CuseConnection::connect`], but doesn't
+	/// allocate a [`MinReadBuffer`] on the stack. The buffer must be large
+	/// enough to contain a [`CuseInitRequest`], and for sockets such as
+	/// `/dev/cuse` it must also be at least [`MinReadBuffer::LEN`] bytes.
+	///
+	/// [`MinReadBuffer`]: crate::io::MinReadBuffer
+	/// [`MinReadBuffer::LEN`]: crate::io::MinReadBuffer::LEN
+	pub fn connect_with_buf<F>(
+		socket: S,
+		buf: &mut impl crate::io::AsAlignedSliceMut,
+		device_name: &CuseDeviceName,
+		device_number: CuseDeviceNumber,
+		mut init_fn: F,
+	) -> Result<CuseConnection<S>, ServerError<S::Error>>
+	where
+		F: FnMut(&CuseInitRequest, &mut CuseInitResponse),
+	{
+		use crate::io::AlignedSlice;
 		let layout = CuseLayout {
 			version_minor: kernel::FUSE_KERNEL_MINOR_VERSION as u16,
 		};
+		let stats = ConnectionStats::new();
 
 		loop {
-			let recv_len = socket.recv(buf.as_slice_mut())?;
-			let recv_buf = buf.as_aligned_slice().truncate(recv_len);
+			let mut recv_buf = buf.as_aligned_slice_mut();
+			let recv_len = socket.recv(recv_buf.get_mut())?;
+			let recv_buf = AlignedSlice::from(recv_buf).truncate(recv_len);
 			let request = CuseRequest::new(recv_buf, layout)?;
+			let opcode = request.header().opcode();
+			stats.record_request(recv_len, opcode);
 			let init_req = CuseInitRequest::try_from(request)?;
 
 			let (reply, ok) = cuse_handshake(&init_req, || {
@@ -1158,6 +1746,7 @@ impl<S: CuseSocket> CuseConnection<S> {
 				inner: ReplySender {
 					socket: &socket,
 					request_id: request_id.get(),
+					stats: Some(&stats),
 				},
 			})?;
 
@@ -1171,6 +1760,7 @@ impl<S: CuseSocket> CuseConnection<S> {
 					version_minor: reply.raw.minor as u16,
 				},
 				recv_buf_len: recv_buf_len(reply.max_write()),
+				stats,
 			});
 		}
 	}
@@ -1181,9 +1771,27 @@ impl<S: CuseSocket> CuseConnection<S> {
 		mut buf: crate::io::AlignedSliceMut<'a>,
 	) -> Result<CuseRequest<'a>, ServerError<S::Error>> {
 		use crate::io::AlignedSlice;
-		let recv_len = self.socket.recv(buf.get_mut())?;
+		let recv_len = match self.socket.recv(buf.get_mut()) {
+			Ok(len) => len,
+			Err(err @ RecvError::ConnectionClosed(_)) => return Err(err.into()),
+			Err(err @ RecvError::WouldBlock(_)) => return Err(err.into()),
+			Err(err) => {
+				self.stats.record_error();
+				return Err(err.into());
+			},
+		};
 		let recv_buf = AlignedSlice::from(buf).truncate(recv_len);
-		Ok(CuseRequest::new(recv_buf, self.layout)?)
+		match CuseRequest::new(recv_buf, self.layout) {
+			Ok(request) => {
+				let opcode = request.header().opcode();
+				self.stats.record_request(recv_len, opcode);
+				Ok(request)
+			},
+			Err(err) => {
+				self.stats.record_error();
+				Err(err.into())
+			},
+		}
 	}
 
 	#[allow(missing_docs)] // TODO
@@ -1192,21 +1800,27 @@ impl<S: CuseSocket> CuseConnection<S> {
 			inner: ReplySender {
 				socket: &self.socket,
 				request_id: request_id.get(),
+				stats: Some(&self.stats),
 			},
 		}
 	}
 
-	/*
-	TODO
-
-	pub fn notify(
+	/// Notify the client that a polled device may have become ready.
+	///
+	/// CUSE devices don't have filesystem nodes, so poll notifications are
+	/// the only notifications that a CUSE server can send.
+	pub fn notify_poll(
 		&self,
-		notification: &crate::FuseNotification<'_>,
+		notification: crate::NotifyPoll,
 	) -> Result<(), SendError<S::Error>> {
 		let mut header = crate::ResponseHeader::new_notification();
-		self.socket.send(notification.encode(&mut header))
+		let notification = crate::FuseNotification::Poll(notification);
+		let buf = notification.encode(&mut header);
+		let len = buf.len();
+		let result = self.socket.send(buf);
+		self.stats.record_notification(len, result.is_ok());
+		result
 	}
-	*/
 }
 
 impl<S> CuseConnection<S> {
@@ -1224,6 +1838,13 @@ impl<S> CuseConnection<S> {
 		self.layout
 	}
 
+	/// Returns the request and reply counters of this connection.
+	#[inline]
+	#[must_use]
+	pub fn stats(&self) -> &ConnectionStats {
+		&self.stats
+	}
+
 	/// Returns the minimum size of the receive buffer for this connection.
 	#[inline]
 	#[must_use]
@@ -1257,7 +1878,9 @@ where
 pub struct FuseConnection<S> {
 	socket: S,
 	layout: FuseLayout,
+	info: ConnectionInfo,
 	recv_buf_len: usize,
+	stats: ConnectionStats,
 }
 
 impl<S: FuseSocket> FuseConnection<S> {
@@ -1270,21 +1893,50 @@ impl<S: FuseSocket> FuseConnection<S> {
 	/// filesystem server.
 	pub fn connect<F>(
 		socket: S,
-		mut init_fn: F,
+		init_fn: F,
 	) -> Result<FuseConnection<S>, ServerError<S::Error>>
 	where
 		F: FnMut(&FuseInitRequest, &mut FuseInitResponse),
 	{
 		let mut buf = crate::io::MinReadBuffer::new();
+		Self::connect_with_buf(socket, &mut buf, init_fn)
+	}
+
+	/// Perform a FUSE connection handshake, receiving into the given buffer.
+	///
+	/// This is equivalent to [`FuseConnection::connect`], but doesn't
+	/// allocate a [`MinReadBuffer`] on the stack. Servers with small stacks,
+	/// such as those running on embedded devices, can use a statically
+	/// allocated buffer instead.
+	///
+	/// The buffer must be large enough to contain a [`FuseInitRequest`]. For
+	/// sockets such as `/dev/fuse` it must also be at least
+	/// [`MinReadBuffer::LEN`] bytes.
+	///
+	/// [`MinReadBuffer`]: crate::io::MinReadBuffer
+	/// [`MinReadBuffer::LEN`]: crate::io::MinReadBuffer::LEN
+	pub fn connect_with_buf<F>(
+		socket: S,
+		buf: &mut impl crate::io::AsAlignedSliceMut,
+		mut init_fn: F,
+	) -> Result<FuseConnection<S>, ServerError<S::Error>>
+	where
+		F: FnMut(&FuseInitRequest, &mut FuseInitResponse),
+	{
+		use crate::io::AlignedSlice;
 		let layout = FuseLayout {
 			version_minor: kernel::FUSE_KERNEL_MINOR_VERSION as u16,
 			features: 0,
 		};
+		let stats = ConnectionStats::new();
 
 		loop {
-			let recv_len = socket.recv(buf.as_slice_mut())?;
-			let recv_buf = buf.as_aligned_slice().truncate(recv_len);
+			let mut recv_buf = buf.as_aligned_slice_mut();
+			let recv_len = socket.recv(recv_buf.get_mut())?;
+			let recv_buf = AlignedSlice::from(recv_buf).truncate(recv_len);
 			let request = FuseRequest::new(recv_buf, layout)?;
+			let opcode = request.header().opcode();
+			stats.record_request(recv_len, opcode);
 			let init_req = FuseInitRequest::try_from(request)?;
 
 			let (reply, ok) = fuse_handshake(&init_req, || {
@@ -1298,6 +1950,7 @@ impl<S: FuseSocket> FuseConnection<S> {
 				inner: ReplySender {
 					socket: &socket,
 					request_id: request_id.get(),
+					stats: Some(&stats),
 				},
 				layout,
 			})?;
@@ -1309,12 +1962,47 @@ impl<S: FuseSocket> FuseConnection<S> {
 			return Ok(Self {
 				socket,
 				layout: FuseLayout::new2(&reply.raw),
+				info: ConnectionInfo::new(&init_req, &reply),
 				recv_buf_len: recv_buf_len(reply.max_write()),
+				stats,
 			});
 		}
 	}
 
+	/// Create a connection on a socket whose handshake has already been
+	/// performed.
+	///
+	/// A mount helper, service manager, or container runtime may mount the
+	/// filesystem and complete the `FUSE_INIT` handshake itself, then pass
+	/// the `/dev/fuse` file descriptor to the server along with the init
+	/// response it sent. The response determines the layout of requests, so
+	/// it must be identical to the one the client received.
+	///
+	/// If the handshake hasn't been performed, use [`FuseConnection::connect`]
+	/// on the socket instead.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the response's protocol version isn't supported
+	/// by this library.
+	pub fn from_init_response(
+		socket: S,
+		response: &FuseInitResponse,
+	) -> Result<FuseConnection<S>, LayoutError> {
+		Ok(Self {
+			socket,
+			layout: FuseLayout::new(&response.raw)?,
+			info: ConnectionInfo::from_response(response),
+			recv_buf_len: recv_buf_len(response.max_write()),
+			stats: ConnectionStats::new(),
+		})
+	}
+
 	/// Receive a FUSE request from the client.
+	///
+	/// Returns `Ok(None)` if the connection has been closed by the client.
+	/// If the socket is non-blocking and no request is available, returns
+	/// [`RecvError::WouldBlock`].
 	pub fn recv<'a>(
 		&self,
 		mut buf: crate::io::AlignedSliceMut<'a>,
@@ -1323,10 +2011,24 @@ impl<S: FuseSocket> FuseConnection<S> {
 		let recv_len = match self.socket.recv(buf.get_mut()) {
 			Ok(len) => len,
 			Err(RecvError::ConnectionClosed(_)) => return Ok(None),
-			Err(err) => return Err(err.into()),
+			Err(err @ RecvError::WouldBlock(_)) => return Err(err.into()),
+			Err(err) => {
+				self.stats.record_error();
+				return Err(err.into());
+			},
 		};
 		let recv_buf = AlignedSlice::from(buf).truncate(recv_len);
-		Ok(Some(FuseRequest::new(recv_buf, self.layout)?))
+		match FuseRequest::new(recv_buf, self.layout) {
+			Ok(request) => {
+				let opcode = request.header().opcode();
+				self.stats.record_request(recv_len, opcode);
+				Ok(Some(request))
+			},
+			Err(err) => {
+				self.stats.record_error();
+				Err(err.into())
+			},
+		}
 	}
 
 	#[allow(missing_docs)] // TODO
@@ -1335,6 +2037,7 @@ impl<S: FuseSocket> FuseConnection<S> {
 			inner: ReplySender {
 				socket: &self.socket,
 				request_id: request_id.get(),
+				stats: Some(&self.stats),
 			},
 			layout: self.layout,
 		}
@@ -1346,7 +2049,11 @@ impl<S: FuseSocket> FuseConnection<S> {
 		notification: &crate::FuseNotification<'_>,
 	) -> Result<(), SendError<S::Error>> {
 		let mut header = crate::ResponseHeader::new_notification();
-		self.socket.send(notification.encode(&mut header))
+		let buf = notification.encode(&mut header);
+		let len = buf.len();
+		let result = self.socket.send(buf);
+		self.stats.record_notification(len, result.is_ok());
+		result
 	}
 }
 
@@ -1365,6 +2072,20 @@ impl<S> FuseConnection<S> {
 		self.layout
 	}
 
+	/// Returns the parameters negotiated during the connection handshake.
+	#[inline]
+	#[must_use]
+	pub fn info(&self) -> &ConnectionInfo {
+		&self.info
+	}
+
+	/// Returns the request and reply counters of this connection.
+	#[inline]
+	#[must_use]
+	pub fn stats(&self) -> &ConnectionStats {
+		&self.stats
+	}
+
 	/// Returns the minimum size of the receive buffer for this connection.
 	///
 	/// This value is computed from `max_write`. Operations with their own
@@ -1377,6 +2098,293 @@ impl<S> FuseConnection<S> {
 	}
 }
 
+impl<S: FuseUnmount> FuseConnection<S> {
+	/// Unmount the filesystem served by this connection.
+	///
+	/// See [`FuseUnmount::unmount`] for details.
+	pub fn unmount(&self) -> Result<(), S::Error> {
+		self.socket.unmount()
+	}
+}
+
+/// Parameters negotiated during a FUSE connection handshake.
+#[derive(Clone, Copy)]
+pub struct ConnectionInfo {
+	kernel_flags: FuseInitFlags,
+	kernel_max_readahead: u32,
+	flags: FuseInitFlags,
+	init_out: kernel::fuse_init_out,
+}
+
+impl ConnectionInfo {
+	/// Create a new `ConnectionInfo` from a handshake's request and response.
+	#[must_use]
+	pub fn new(
+		request: &FuseInitRequest,
+		response: &FuseInitResponse,
+	) -> ConnectionInfo {
+		Self {
+			kernel_flags: request.flags(),
+			kernel_max_readahead: request.max_readahead(),
+			flags: request.flags() & response.flags(),
+			init_out: response.raw,
+		}
+	}
+
+	/// Create a new `ConnectionInfo` from a handshake's response.
+	///
+	/// The init flags offered by the client aren't known, so they're assumed
+	/// to be the flags of the response. Similarly, the `max_readahead` of
+	/// the client is assumed to be that of the response.
+	#[must_use]
+	pub fn from_response(response: &FuseInitResponse) -> ConnectionInfo {
+		Self {
+			kernel_flags: response.flags(),
+			kernel_max_readahead: response.raw.max_readahead,
+			flags: response.flags(),
+			init_out: response.raw,
+		}
+	}
+
+	/// Returns the negotiated protocol version.
+	#[inline]
+	#[must_use]
+	pub fn version(&self) -> crate::Version {
+		crate::Version::new(self.init_out.major, self.init_out.minor)
+	}
+
+	/// Returns the init flags offered by the client.
+	#[inline]
+	#[must_use]
+	pub fn kernel_flags(&self) -> FuseInitFlags {
+		self.kernel_flags
+	}
+
+	/// Returns the init flags enabled by both the client and the server.
+	#[inline]
+	#[must_use]
+	pub fn flags(&self) -> FuseInitFlags {
+		self.flags
+	}
+
+	/// Returns the negotiated `max_readahead`.
+	#[inline]
+	#[must_use]
+	pub fn max_readahead(&self) -> u32 {
+		self.init_out.max_readahead
+	}
+
+	/// Returns the negotiated `max_background`.
+	#[inline]
+	#[must_use]
+	pub fn max_background(&self) -> u16 {
+		self.init_out.max_background
+	}
+
+	/// Returns the negotiated `congestion_threshold`.
+	#[inline]
+	#[must_use]
+	pub fn congestion_threshold(&self) -> u16 {
+		self.init_out.congestion_threshold
+	}
+
+	/// Returns the negotiated `max_write`.
+	#[inline]
+	#[must_use]
+	pub fn max_write(&self) -> u32 {
+		self.init_out.max_write
+	}
+
+	/// Returns the negotiated `time_granularity`.
+	#[inline]
+	#[must_use]
+	pub fn time_granularity(&self) -> u32 {
+		self.init_out.time_gran
+	}
+
+	/// Returns the negotiated `max_pages`, or `None` if the [`MAX_PAGES`]
+	/// init flag is not enabled.
+	///
+	/// [`MAX_PAGES`]: FuseInitFlag::MAX_PAGES
+	#[inline]
+	#[must_use]
+	pub fn max_pages(&self) -> Option<u16> {
+		if self.flags.get(FuseInitFlag::MAX_PAGES) {
+			return Some(self.init_out.max_pages);
+		}
+		None
+	}
+
+	/// Returns the negotiated `max_stack_depth`, or `None` if the
+	/// [`PASSTHROUGH`] init flag is not enabled.
+	///
+	/// [`PASSTHROUGH`]: FuseInitFlag::PASSTHROUGH
+	#[inline]
+	#[must_use]
+	pub fn max_stack_depth(&self) -> Option<u32> {
+		if self.flags.get(FuseInitFlag::PASSTHROUGH) {
+			return Some(self.init_out.max_stack_depth);
+		}
+		None
+	}
+
+	/// Compares the negotiated parameters to those of libfuse.
+	///
+	/// The returned value implements [`fmt::Display`], and renders one line
+	/// for each parameter that differs from what libfuse 3 would negotiate
+	/// with the same client. This can be used to diagnose behavioral changes
+	/// when migrating a filesystem from libfuse.
+	///
+	/// The comparison assumes a libfuse filesystem that implements the
+	/// `readdirplus`, `getlk`, `setlk`, and `flock` operations, running on
+	/// a host with 4 KiB pages.
+	#[must_use]
+	pub fn diff_against_libfuse(&self) -> LibfuseDiff {
+		LibfuseDiff { info: *self }
+	}
+}
+
+impl fmt::Debug for ConnectionInfo {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("ConnectionInfo")
+			.field("version", &self.version())
+			.field("kernel_flags", &self.kernel_flags())
+			.field("flags", &self.flags())
+			.field("max_readahead", &self.max_readahead())
+			.field("max_background", &self.max_background())
+			.field("congestion_threshold", &self.congestion_threshold())
+			.field("max_write", &self.max_write())
+			.field("time_granularity", &self.time_granularity())
+			.finish()
+	}
+}
+
+/// Differences between a connection's parameters and those of libfuse.
+///
+/// See [`ConnectionInfo::diff_against_libfuse`] for details.
+#[derive(Clone, Copy)]
+pub struct LibfuseDiff {
+	info: ConnectionInfo,
+}
+
+// Init flags requested by `fuse_session_new()` in libfuse 3, if supported by
+// the kernel.
+const LIBFUSE_FLAGS: &[FuseInitFlag] = &[
+	FuseInitFlag::ASYNC_READ,
+	FuseInitFlag::POSIX_LOCKS,
+	FuseInitFlag::ATOMIC_O_TRUNC,
+	FuseInitFlag::BIG_WRITES,
+	FuseInitFlag::FLOCK_LOCKS,
+	FuseInitFlag::HAS_IOCTL_DIR,
+	FuseInitFlag::AUTO_INVAL_DATA,
+	FuseInitFlag::DO_READDIRPLUS,
+	FuseInitFlag::READDIRPLUS_AUTO,
+	FuseInitFlag::ASYNC_DIO,
+	FuseInitFlag::PARALLEL_DIROPS,
+	FuseInitFlag::HANDLE_KILLPRIV,
+	FuseInitFlag::MAX_PAGES,
+];
+
+const LIBFUSE_MAX_BACKGROUND: u16 = 0;
+const LIBFUSE_CONGESTION_THRESHOLD: u16 = 0;
+const LIBFUSE_MAX_WRITE: u32 = 256 * 4096;
+const LIBFUSE_TIME_GRANULARITY: u32 = 1;
+
+impl LibfuseDiff {
+	/// Returns `true` if the connection's parameters match libfuse.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		let mut empty = true;
+		_ = self.for_each(|_| {
+			empty = false;
+			Ok(())
+		});
+		empty
+	}
+
+	fn for_each(
+		&self,
+		mut f: impl FnMut(fmt::Arguments) -> fmt::Result,
+	) -> fmt::Result {
+		let info = &self.info;
+		let kernel_flags = info.kernel_flags();
+		let flags = info.flags();
+		for &flag in LIBFUSE_FLAGS {
+			let libfuse = kernel_flags.get(flag);
+			let negotiated = flags.get(flag);
+			if libfuse != negotiated {
+				f(format_args!(
+					"{:?}: libfuse {}, negotiated {}",
+					flag,
+					enabled_str(libfuse),
+					enabled_str(negotiated),
+				))?;
+			}
+		}
+
+		let kernel_max_readahead = info.kernel_max_readahead;
+		if info.max_readahead() != kernel_max_readahead {
+			f(format_args!(
+				"max_readahead: libfuse {}, negotiated {}",
+				kernel_max_readahead,
+				info.max_readahead(),
+			))?;
+		}
+		if info.max_background() != LIBFUSE_MAX_BACKGROUND {
+			f(format_args!(
+				"max_background: libfuse {}, negotiated {}",
+				LIBFUSE_MAX_BACKGROUND,
+				info.max_background(),
+			))?;
+		}
+		if info.congestion_threshold() != LIBFUSE_CONGESTION_THRESHOLD {
+			f(format_args!(
+				"congestion_threshold: libfuse {}, negotiated {}",
+				LIBFUSE_CONGESTION_THRESHOLD,
+				info.congestion_threshold(),
+			))?;
+		}
+		if info.max_write() != LIBFUSE_MAX_WRITE {
+			f(format_args!(
+				"max_write: libfuse {}, negotiated {}",
+				LIBFUSE_MAX_WRITE,
+				info.max_write(),
+			))?;
+		}
+		if info.time_granularity() != LIBFUSE_TIME_GRANULARITY {
+			f(format_args!(
+				"time_granularity: libfuse {}, negotiated {}",
+				LIBFUSE_TIME_GRANULARITY,
+				info.time_granularity(),
+			))?;
+		}
+		Ok(())
+	}
+}
+
+fn enabled_str(enabled: bool) -> &'static str {
+	if enabled {
+		"enabled"
+	} else {
+		"disabled"
+	}
+}
+
+impl fmt::Display for LibfuseDiff {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		self.for_each(|line| {
+			fmt.write_fmt(line)?;
+			fmt.write_str("\n")
+		})
+	}
+}
+
+impl fmt::Debug for LibfuseDiff {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt::Display::fmt(self, fmt)
+	}
+}
+
 pub(crate) fn fuse_handshake<E, F>(
 	request: &FuseInitRequest,
 	mut new_reply: F,
@@ -1388,6 +2396,12 @@ where
 		Some(version) => {
 			let mut reply = new_reply();
 			reply.set_version(version);
+			if !request.flags().get(FuseInitFlag::INIT_EXT) {
+				// Flags outside the lower 32 bits can only be negotiated
+				// if the client supports extended init flags.
+				reply.raw.flags &= !kernel::FUSE_INIT_EXT;
+				reply.raw.flags2 = 0;
+			}
 			Ok((reply, true))
 		},
 		None => {
@@ -1428,8 +2442,23 @@ impl<'a> CuseServer<'a> {
 		&self,
 		socket: S,
 	) -> Result<CuseConnection<S>, ServerError<S::Error>> {
-		CuseConnection::connect(
+		let mut buf = crate::io::MinReadBuffer::new();
+		self.connect_with_buf(socket, &mut buf)
+	}
+
+	/// Establish a new CUSE connection on the given socket, receiving the
+	/// handshake into the given buffer.
+	///
+	/// See [`CuseConnection::connect_with_buf`] for the buffer's
+	/// requirements.
+	pub fn connect_with_buf<S: CuseSocket>(
+		&self,
+		socket: S,
+		buf: &mut impl crate::io::AsAlignedSliceMut,
+	) -> Result<CuseConnection<S>, ServerError<S::Error>> {
+		CuseConnection::connect_with_buf(
 			socket,
+			buf,
 			self.device_name,
 			self.device_number,
 			|request, reply| {
@@ -1440,6 +2469,28 @@ impl<'a> CuseServer<'a> {
 		)
 	}
 
+	/// Set the name of the device created by subsequent connections.
+	///
+	/// A single process can create a family of devices (e.g. `/dev/foo0`
+	/// through `/dev/fooN`) by changing the device name and number between
+	/// calls to [`CuseServer::connect`], with one socket per device.
+	pub fn device_name(
+		&mut self,
+		device_name: &'a CuseDeviceName,
+	) -> &mut Self {
+		self.device_name = device_name;
+		self
+	}
+
+	/// Set the number of the device created by subsequent connections.
+	pub fn device_number(
+		&mut self,
+		device_number: CuseDeviceNumber,
+	) -> &mut Self {
+		self.device_number = device_number;
+		self
+	}
+
 	/// Set the connection's [`max_read`].
 	///
 	/// [`max_read`]: CuseInitResponse::max_read
@@ -1482,6 +2533,8 @@ impl<'a> CuseServer<'a> {
 /// Builder for FUSE connections.
 pub struct FuseServer {
 	init_reply: FuseInitResponse,
+	page_size: usize,
+	max_pages: Option<u16>,
 }
 
 impl FuseServer {
@@ -1490,6 +2543,8 @@ impl FuseServer {
 	pub fn new() -> FuseServer {
 		Self {
 			init_reply: FuseInitResponse::new(),
+			page_size: DEFAULT_PAGE_SIZE,
+			max_pages: None,
 		}
 	}
 
@@ -1497,18 +2552,61 @@ impl FuseServer {
 	pub fn connect<S: FuseSocket>(
 		&self,
 		socket: S,
+	) -> Result<FuseConnection<S>, ServerError<S::Error>> {
+		let mut buf = crate::io::MinReadBuffer::new();
+		self.connect_with_buf(socket, &mut buf)
+	}
+
+	/// Establish a new FUSE connection on the given socket, receiving the
+	/// handshake into the given buffer.
+	///
+	/// See [`FuseConnection::connect_with_buf`] for the buffer's
+	/// requirements.
+	pub fn connect_with_buf<S: FuseSocket>(
+		&self,
+		socket: S,
+		buf: &mut impl crate::io::AsAlignedSliceMut,
 	) -> Result<FuseConnection<S>, ServerError<S::Error>> {
 		let opts = &self.init_reply;
-		FuseConnection::connect(socket, |request, reply| {
+		FuseConnection::connect_with_buf(socket, buf, |request, reply| {
 			reply.set_congestion_threshold(opts.congestion_threshold());
 			reply.set_max_background(opts.max_background());
 			reply.set_max_readahead(opts.max_readahead());
 			reply.set_max_write(opts.max_write());
 			reply.set_time_granularity(opts.time_granularity());
 			reply.set_flags(request.flags() & opts.flags());
+			match self.max_pages {
+				Some(max_pages) => {
+					limit_max_pages(request, reply, max_pages, self.page_size);
+				},
+				None => set_max_pages(request, reply, self.page_size),
+			}
+		})
+		.map(|mut conn| {
+			conn.recv_buf_len = fuse_recv_buf_len(
+				conn.info.max_write(),
+				self.page_size,
+			);
+			conn
 		})
 	}
 
+	/// Set the page size of the host.
+	///
+	/// The page size is used to compute the `max_pages` needed for the
+	/// connection's [`max_write`], and the minimum length of its receive
+	/// buffers. On hosts with pages larger than 4 KiB, the default of 4 KiB
+	/// will cause `max_pages` to be larger than necessary.
+	///
+	/// The OS-specific crates provide a `page_size()` function that returns
+	/// the host's page size at runtime.
+	///
+	/// [`max_write`]: FuseInitResponse::max_write
+	pub fn page_size(&mut self, page_size: usize) -> &mut Self {
+		self.page_size = page_size;
+		self
+	}
+
 	/// Set the connection's [`congestion_threshold`].
 	///
 	/// [`congestion_threshold`]: FuseInitResponse::congestion_threshold
@@ -1553,6 +2651,25 @@ impl FuseServer {
 		self
 	}
 
+	/// Set the maximum number of pages in each request.
+	///
+	/// The connection's [`max_write`] is computed from `max_pages` and the
+	/// [`page_size`](Self::page_size), limited to the value set by
+	/// [`FuseServer::max_write`] if any. Receive buffers are sized to match.
+	///
+	/// If the client supports the [`MAX_PAGES`] init flag then it will be
+	/// enabled, otherwise the client's default of 32 pages is used. The
+	/// client may also clamp `max_pages` to its own limit, which on Linux is
+	/// set by the `fs.fuse.max_pages_limit` sysctl (default 256 pages). A
+	/// `max_pages` of zero is treated as one.
+	///
+	/// [`max_write`]: FuseInitResponse::max_write
+	/// [`MAX_PAGES`]: FuseInitFlag::MAX_PAGES
+	pub fn max_pages(&mut self, max_pages: u16) -> &mut Self {
+		self.max_pages = Some(max_pages);
+		self
+	}
+
 	/// Set the connection's [`time_granularity`].
 	///
 	/// [`time_granularity`]: FuseInitResponse::time_granularity
@@ -1561,6 +2678,201 @@ impl FuseServer {
 		self
 	}
 
+	/// Offer the [`EXPORT_SUPPORT`] init flag.
+	///
+	/// If enabled, the client may send `FUSE_LOOKUP` requests for the names
+	/// `"."` and `".."` when resolving NFS file handles, which servers can
+	/// distinguish with [`LookupRequest::name_or_dots`]. Servers should also
+	/// return stable generation numbers in their lookup responses, so that
+	/// file handles remain valid across node ID reuse.
+	///
+	/// [`EXPORT_SUPPORT`]: FuseInitFlag::EXPORT_SUPPORT
+	pub fn enable_export_support(&mut self) -> &mut Self {
+		self.init_reply.update_flags(|flags| {
+			flags.set(FuseInitFlag::EXPORT_SUPPORT);
+		});
+		self
+	}
+
+	/// Offer the [`CACHE_SYMLINKS`] init flag.
+	///
+	/// If enabled, the client caches symlink targets returned by
+	/// `FUSE_READLINK` in its page cache, and only sends another
+	/// `FUSE_READLINK` after the cached target has been evicted or
+	/// invalidated. A server that changes the target of an existing symlink
+	/// must invalidate the node's cached data with a
+	/// [`FuseNotification::InvalidateInode`] notification.
+	///
+	/// The cached target is limited to one page, so targets should be no
+	/// longer than [`ReadlinkResponse::MAX_TARGET_LEN`].
+	///
+	/// [`CACHE_SYMLINKS`]: FuseInitFlag::CACHE_SYMLINKS
+	/// [`FuseNotification::InvalidateInode`]: crate::FuseNotification::InvalidateInode
+	pub fn enable_symlink_caching(&mut self) -> &mut Self {
+		self.init_reply.update_flags(|flags| {
+			flags.set(FuseInitFlag::CACHE_SYMLINKS);
+		});
+		self
+	}
+
+	/// Offer the [`SUBMOUNTS`] init flag.
+	///
+	/// If enabled, a directory whose attributes have the submount flag set
+	/// (see [`NodeAttr::set_flag_submount`]) is automatically mounted by the
+	/// client as a separate filesystem when it's first accessed. Each
+	/// submount has its own device number, so that paths which cross from
+	/// one backing filesystem to another are visible as mount points to
+	/// tools such as `find -xdev` and `du -x`. This is mostly useful for
+	/// servers that export an existing directory tree, such as `virtiofsd`.
+	///
+	/// Node IDs are shared by all submounts of a connection, and the client
+	/// uses the node ID as the inode number, so inodes of different backing
+	/// filesystems must still be given distinct node IDs. See
+	/// [`SubmountDevices`] for one way to allocate them.
+	///
+	/// Entry replies with the submount flag set are rejected with
+	/// [`SendError::SubmountNotEnabled`] if the flag wasn't negotiated.
+	///
+	/// [`SUBMOUNTS`]: FuseInitFlag::SUBMOUNTS
+	/// [`NodeAttr::set_flag_submount`]: crate::NodeAttr::set_flag_submount
+	/// [`SubmountDevices`]: crate::SubmountDevices
+	pub fn enable_submounts(&mut self) -> &mut Self {
+		self.init_reply.update_flags(|flags| {
+			flags.set(FuseInitFlag::SUBMOUNTS);
+		});
+		self
+	}
+
+	/// Offer the [`HANDLE_KILLPRIV_V2`] init flag.
+	///
+	/// If enabled, the server is responsible for clearing the setuid and
+	/// setgid bits (and the `security.capability` xattr) of a file when it
+	/// is written to, truncated, or has its owner changed. The client marks
+	/// requests that need this with a `kill_suidgid()` flag, for example
+	/// [`WriteRequest::kill_suidgid`], and the server should then update
+	/// the file's mode with [`FileMode::without_suidgid`].
+	///
+	/// Without this flag the client clears the bits itself with a separate
+	/// `FUSE_SETATTR` request, which is slower and isn't atomic with the
+	/// write.
+	///
+	/// [`HANDLE_KILLPRIV_V2`]: FuseInitFlag::HANDLE_KILLPRIV_V2
+	/// [`FileMode::without_suidgid`]: crate::FileMode::without_suidgid
+	pub fn enable_killpriv_v2(&mut self) -> &mut Self {
+		self.init_reply.update_flags(|flags| {
+			flags.set(FuseInitFlag::HANDLE_KILLPRIV_V2);
+		});
+		self
+	}
+
+	/// Offer the [`SETXATTR_EXT`] init flag.
+	///
+	/// If enabled, `FUSE_SETXATTR` requests use the extended request format,
+	/// which includes additional [`SetxattrRequest::flags`]. Servers that
+	/// store POSIX ACLs should enable this so that they can honor
+	/// [`SetxattrRequest::acl_kill_sgid`].
+	///
+	/// The request format is selected per-connection, so requests will be
+	/// decoded correctly whether or not the client accepts the flag.
+	///
+	/// [`SETXATTR_EXT`]: FuseInitFlag::SETXATTR_EXT
+	pub fn enable_setxattr_ext(&mut self) -> &mut Self {
+		self.init_reply.update_flags(|flags| {
+			flags.set(FuseInitFlag::SETXATTR_EXT);
+		});
+		self
+	}
+
+	/// Configure the connection for a filesystem whose contents may be
+	/// changed by other clients, such as a network filesystem.
+	///
+	/// File data is written through to the server rather than being cached
+	/// by the client, and the client discards its cached data for a file
+	/// when a `FUSE_GETATTR` reply shows that the file's size or modification
+	/// time has changed. Directory operations may be run in parallel, and
+	/// `FUSE_READDIRPLUS` is used when it would reduce the number of
+	/// `FUSE_LOOKUP` round-trips.
+	///
+	/// Specifically, this offers the [`AUTO_INVAL_DATA`], [`ASYNC_READ`],
+	/// [`PARALLEL_DIROPS`], [`DO_READDIRPLUS`], and [`READDIRPLUS_AUTO`] init
+	/// flags, and stops offering [`WRITEBACK_CACHE`] and
+	/// [`EXPLICIT_INVAL_DATA`].
+	///
+	/// The trade-off is that write performance is limited by the server's
+	/// latency, and that changes made by other clients are only noticed
+	/// after the client's cached attributes have expired.
+	///
+	/// [`AUTO_INVAL_DATA`]: FuseInitFlag::AUTO_INVAL_DATA
+	/// [`ASYNC_READ`]: FuseInitFlag::ASYNC_READ
+	/// [`PARALLEL_DIROPS`]: FuseInitFlag::PARALLEL_DIROPS
+	/// [`DO_READDIRPLUS`]: FuseInitFlag::DO_READDIRPLUS
+	/// [`READDIRPLUS_AUTO`]: FuseInitFlag::READDIRPLUS_AUTO
+	/// [`WRITEBACK_CACHE`]: FuseInitFlag::WRITEBACK_CACHE
+	/// [`EXPLICIT_INVAL_DATA`]: FuseInitFlag::EXPLICIT_INVAL_DATA
+	pub fn profile_network_fs(&mut self) -> &mut Self {
+		self.init_reply.update_flags(|flags| {
+			flags.set(FuseInitFlag::AUTO_INVAL_DATA);
+			flags.set(FuseInitFlag::ASYNC_READ);
+			flags.set(FuseInitFlag::PARALLEL_DIROPS);
+			flags.set(FuseInitFlag::DO_READDIRPLUS);
+			flags.set(FuseInitFlag::READDIRPLUS_AUTO);
+			flags.set_to(FuseInitFlag::WRITEBACK_CACHE, false);
+			flags.set_to(FuseInitFlag::EXPLICIT_INVAL_DATA, false);
+		});
+		self
+	}
+
+	/// Configure the connection for a filesystem whose contents are only
+	/// changed through this connection, such as a local or caching
+	/// filesystem.
+	///
+	/// Writes are buffered in the client's page cache and written back to
+	/// the server in the background, and the client keeps cached file data
+	/// until the server invalidates it with a
+	/// [`FuseNotification::InvalidateInode`] notification. Symlink targets
+	/// are also cached. Directory operations may be run in parallel, and
+	/// `FUSE_READDIRPLUS` is used when it would reduce the number of
+	/// `FUSE_LOOKUP` round-trips.
+	///
+	/// With writeback caching the client maintains file sizes and timestamps
+	/// itself, so the [`time_granularity`] is set to one nanosecond. Servers
+	/// that store timestamps with less precision should set a larger
+	/// granularity after calling this method.
+	///
+	/// Specifically, this offers the [`WRITEBACK_CACHE`],
+	/// [`EXPLICIT_INVAL_DATA`], [`CACHE_SYMLINKS`], [`ASYNC_READ`],
+	/// [`PARALLEL_DIROPS`], [`DO_READDIRPLUS`], and [`READDIRPLUS_AUTO`] init
+	/// flags, and stops offering [`AUTO_INVAL_DATA`].
+	///
+	/// The trade-off is that the server must notify the client of any change
+	/// made to a file other than through the client, and that buffered
+	/// writes may be lost if the server exits before they're written back.
+	///
+	/// [`FuseNotification::InvalidateInode`]: crate::FuseNotification::InvalidateInode
+	/// [`time_granularity`]: FuseInitResponse::time_granularity
+	/// [`WRITEBACK_CACHE`]: FuseInitFlag::WRITEBACK_CACHE
+	/// [`EXPLICIT_INVAL_DATA`]: FuseInitFlag::EXPLICIT_INVAL_DATA
+	/// [`CACHE_SYMLINKS`]: FuseInitFlag::CACHE_SYMLINKS
+	/// [`ASYNC_READ`]: FuseInitFlag::ASYNC_READ
+	/// [`PARALLEL_DIROPS`]: FuseInitFlag::PARALLEL_DIROPS
+	/// [`DO_READDIRPLUS`]: FuseInitFlag::DO_READDIRPLUS
+	/// [`READDIRPLUS_AUTO`]: FuseInitFlag::READDIRPLUS_AUTO
+	/// [`AUTO_INVAL_DATA`]: FuseInitFlag::AUTO_INVAL_DATA
+	pub fn profile_local_cachefs(&mut self) -> &mut Self {
+		self.init_reply.set_time_granularity(1);
+		self.init_reply.update_flags(|flags| {
+			flags.set(FuseInitFlag::WRITEBACK_CACHE);
+			flags.set(FuseInitFlag::EXPLICIT_INVAL_DATA);
+			flags.set(FuseInitFlag::CACHE_SYMLINKS);
+			flags.set(FuseInitFlag::ASYNC_READ);
+			flags.set(FuseInitFlag::PARALLEL_DIROPS);
+			flags.set(FuseInitFlag::DO_READDIRPLUS);
+			flags.set(FuseInitFlag::READDIRPLUS_AUTO);
+			flags.set_to(FuseInitFlag::AUTO_INVAL_DATA, false);
+		});
+		self
+	}
+
 	/// Adjust which [`FuseInitFlags`] the server will offer.
 	///
 	/// Init flags will be enabled if they are offered by the server and
@@ -1587,14 +2899,74 @@ fn negotiate_version(kernel: crate::Version) -> Option<crate::Version> {
 	))
 }
 
+const DEFAULT_PAGE_SIZE: usize = 4096;
+
+// The number of pages per request used by the client if the `MAX_PAGES` init
+// flag is not enabled.
+const DEFAULT_MAX_PAGES: usize = 32;
+
 fn recv_buf_len(max_write: u32) -> usize {
-	const FUSE_BUFFER_HEADER_SIZE: usize = 4096;
+	fuse_recv_buf_len(max_write, DEFAULT_PAGE_SIZE)
+}
+
+// Requests are written to the receive buffer with their payload following a
+// header of less than one page, so one extra page is reserved beyond
+// `max_write`.
+fn fuse_recv_buf_len(max_write: u32, page_size: usize) -> usize {
+	let header_size = cmp::max(page_size, DEFAULT_PAGE_SIZE);
 	cmp::max(
-		(max_write as usize).saturating_add(FUSE_BUFFER_HEADER_SIZE),
+		(max_write as usize).saturating_add(header_size),
 		crate::io::MinReadBuffer::LEN,
 	)
 }
 
+fn set_max_pages(
+	request: &FuseInitRequest,
+	reply: &mut FuseInitResponse,
+	page_size: usize,
+) {
+	if page_size == 0 || !request.flags().get(FuseInitFlag::MAX_PAGES) {
+		return;
+	}
+	let max_write = reply.max_write() as usize;
+	if max_write <= DEFAULT_MAX_PAGES.saturating_mul(page_size) {
+		return;
+	}
+	let max_pages = max_write.div_ceil(page_size);
+	let max_pages = u16::try_from(max_pages).unwrap_or(u16::MAX);
+	reply.set_max_pages(max_pages);
+	reply.update_flags(|flags| flags.set(FuseInitFlag::MAX_PAGES));
+}
+
+// Sets `max_pages` to the value requested by the server, and limits
+// `max_write` to the number of pages the client will read for each request.
+fn limit_max_pages(
+	request: &FuseInitRequest,
+	reply: &mut FuseInitResponse,
+	max_pages: u16,
+	page_size: usize,
+) {
+	let max_pages = if request.flags().get(FuseInitFlag::MAX_PAGES) {
+		let max_pages = cmp::max(max_pages, 1);
+		reply.set_max_pages(max_pages);
+		reply.update_flags(|flags| flags.set(FuseInitFlag::MAX_PAGES));
+		usize::from(max_pages)
+	} else {
+		DEFAULT_MAX_PAGES
+	};
+
+	let limit = max_pages.saturating_mul(page_size);
+	let limit = u32::try_from(limit).unwrap_or(u32::MAX);
+	let max_write = match reply.max_write() {
+		0 => limit,
+		max_write => cmp::min(max_write, limit),
+	};
+	reply.set_max_write(max_write);
+	if max_write > 4096 && request.flags().get(FuseInitFlag::BIG_WRITES) {
+		reply.update_flags(|flags| flags.set(FuseInitFlag::BIG_WRITES));
+	}
+}
+
 /// Serve CUSE requests in a loop, in a single thread without allocating.
 pub fn cuse_serve_local<S: CuseSocket>(
 	conn: &CuseConnection<S>,
@@ -1603,6 +2975,8 @@ pub fn cuse_serve_local<S: CuseSocket>(
 ) -> Result<(), ServerError<S::Error>> {
 	loop {
 		let request = conn.recv(buf.as_aligned_slice_mut())?;
+		#[cfg(feature = "tracing")]
+		let _span = hooks::request_span(request.header()).entered();
 		handlers.dispatch(request);
 	}
 }
@@ -1617,6 +2991,8 @@ pub fn fuse_serve_local<S: FuseSocket>(
 	buf: &mut impl crate::io::AsAlignedSliceMut,
 ) -> Result<(), ServerError<S::Error>> {
 	while let Some(request) = conn.recv(buf.as_aligned_slice_mut())? {
+		#[cfg(feature = "tracing")]
+		let _span = hooks::request_span(request.header()).entered();
 		handlers.dispatch(request);
 	}
 	Ok(())
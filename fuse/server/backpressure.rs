@@ -0,0 +1,324 @@
+// Copyright 2023 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Tracking of in-flight background requests.
+//!
+//! The FUSE client limits the number of background requests, such as
+//! readahead and writeback, that may be outstanding at once. The limits are
+//! negotiated during `FUSE_INIT` with the `max_background` and
+//! `congestion_threshold` fields of [`FuseInitResponse`]. When the number of
+//! outstanding background requests reaches the congestion threshold, the
+//! client marks the filesystem as congested, and when it reaches the
+//! maximum, the client stops sending background requests.
+//!
+//! A server that hands requests off to a slower backend can use
+//! [`Backpressure`] to apply the same limits to its own queue of work.
+//!
+//! [`FuseInitResponse`]: crate::server::FuseInitResponse
+
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(feature = "std")]
+use core::future::Future;
+#[cfg(feature = "std")]
+use core::pin::Pin;
+#[cfg(feature = "std")]
+use core::task::{Context, Poll, Waker};
+#[cfg(feature = "std")]
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+
+use crate::server::FuseInitResponse;
+
+// The client's defaults, used when `FUSE_INIT` leaves the limits unset.
+const DEFAULT_MAX_BACKGROUND: u16 = 12;
+const DEFAULT_CONGESTION_THRESHOLD: u16 = DEFAULT_MAX_BACKGROUND * 3 / 4;
+
+// Backpressure {{{
+
+/// Tracks in-flight requests against the negotiated background limits.
+///
+/// Each in-flight request is represented by a [`BackpressurePermit`], which
+/// releases its slot when dropped.
+///
+/// With the `std` feature, callers can wait for a slot to be released with
+/// [`acquire_blocking`](Backpressure::acquire_blocking) or
+/// [`acquire_async`](Backpressure::acquire_async).
+pub struct Backpressure {
+	max_background: usize,
+	congestion_threshold: usize,
+	in_flight: AtomicUsize,
+
+	// Tasks waiting in `acquire_async` register their wakers in `waiters`,
+	// and threads waiting in `acquire_blocking` wait on `released` with the
+	// same lock.
+	#[cfg(feature = "std")]
+	waiters: Mutex<alloc::vec::Vec<Waker>>,
+	#[cfg(feature = "std")]
+	released: Condvar,
+}
+
+impl Backpressure {
+	/// Creates a new `Backpressure` with the given limits.
+	///
+	/// A limit of zero means the client's default is used, in the same way as
+	/// the corresponding fields of [`FuseInitResponse`].
+	#[must_use]
+	pub const fn new(
+		max_background: u16,
+		congestion_threshold: u16,
+	) -> Backpressure {
+		let max_background = if max_background == 0 {
+			DEFAULT_MAX_BACKGROUND
+		} else {
+			max_background
+		};
+		let congestion_threshold = if congestion_threshold == 0 {
+			DEFAULT_CONGESTION_THRESHOLD
+		} else {
+			congestion_threshold
+		};
+		Backpressure {
+			max_background: max_background as usize,
+			congestion_threshold: congestion_threshold as usize,
+			in_flight: AtomicUsize::new(0),
+			#[cfg(feature = "std")]
+			waiters: Mutex::new(alloc::vec::Vec::new()),
+			#[cfg(feature = "std")]
+			released: Condvar::new(),
+		}
+	}
+
+	/// Creates a new `Backpressure` with the limits of a `FUSE_INIT`
+	/// response.
+	///
+	/// Clients using protocol versions older than v7.13 ignore the limits
+	/// set in the response, so the client's defaults are used instead.
+	#[must_use]
+	pub fn from_init_response(response: &FuseInitResponse) -> Backpressure {
+		if response.version().minor() < 13 {
+			return Self::new(0, 0);
+		}
+		Self::new(
+			response.max_background(),
+			response.congestion_threshold(),
+		)
+	}
+
+	/// Returns the maximum number of in-flight requests.
+	#[inline]
+	#[must_use]
+	pub fn max_background(&self) -> usize {
+		self.max_background
+	}
+
+	/// Returns the number of in-flight requests at which new work should be
+	/// throttled.
+	#[inline]
+	#[must_use]
+	pub fn congestion_threshold(&self) -> usize {
+		self.congestion_threshold
+	}
+
+	/// Returns the number of in-flight requests.
+	#[must_use]
+	pub fn in_flight(&self) -> usize {
+		self.in_flight.load(Ordering::Relaxed)
+	}
+
+	/// Returns `true` if the number of in-flight requests has reached the
+	/// congestion threshold.
+	#[must_use]
+	pub fn should_throttle(&self) -> bool {
+		self.in_flight() >= self.congestion_threshold
+	}
+
+	/// Returns `true` if the number of in-flight requests has reached the
+	/// maximum.
+	#[must_use]
+	pub fn is_full(&self) -> bool {
+		self.in_flight() >= self.max_background
+	}
+
+	/// Attempts to start tracking a request.
+	///
+	/// Returns `None` if the number of in-flight requests has reached the
+	/// maximum.
+	#[must_use]
+	pub fn try_acquire(&self) -> Option<BackpressurePermit<'_>> {
+		let mut in_flight = self.in_flight.load(Ordering::Relaxed);
+		loop {
+			if in_flight >= self.max_background {
+				return None;
+			}
+			match self.in_flight.compare_exchange_weak(
+				in_flight,
+				in_flight + 1,
+				Ordering::Acquire,
+				Ordering::Relaxed,
+			) {
+				Ok(_) => return Some(BackpressurePermit { owner: self }),
+				Err(current) => in_flight = current,
+			}
+		}
+	}
+
+	/// Starts tracking a request, even if the number of in-flight requests
+	/// has reached the maximum.
+	///
+	/// This is intended for requests that can't be deferred, such as those
+	/// the client doesn't count against its own limits.
+	pub fn acquire(&self) -> BackpressurePermit<'_> {
+		self.in_flight.fetch_add(1, Ordering::Acquire);
+		BackpressurePermit { owner: self }
+	}
+
+	/// Starts tracking a request, blocking the current thread until the
+	/// number of in-flight requests is below the maximum.
+	///
+	/// ```
+	/// # use fuse::server::Backpressure;
+	/// let backpressure = Backpressure::new(1, 1);
+	/// let permit = backpressure.try_acquire().unwrap();
+	/// std::thread::scope(|s| {
+	/// 	s.spawn(|| {
+	/// 		// Waits until `permit` is dropped.
+	/// 		let _permit = backpressure.acquire_blocking();
+	/// 	});
+	/// 	drop(permit);
+	/// });
+	/// assert_eq!(backpressure.in_flight(), 0);
+	/// ```
+	#[cfg(feature = "std")]
+	pub fn acquire_blocking(&self) -> BackpressurePermit<'_> {
+		let mut waiters = self.lock_waiters();
+		loop {
+			if let Some(permit) = self.try_acquire() {
+				return permit;
+			}
+			waiters = self.released.wait(waiters)
+				.unwrap_or_else(PoisonError::into_inner);
+		}
+	}
+
+	/// Starts tracking a request, waiting asynchronously until the number of
+	/// in-flight requests is below the maximum.
+	///
+	/// The returned future doesn't need to be polled in any particular
+	/// runtime. Every waiting task is woken when a permit is released, and
+	/// tasks that don't acquire the slot continue waiting.
+	#[cfg(feature = "std")]
+	pub fn acquire_async(&self) -> BackpressureAcquire<'_> {
+		BackpressureAcquire { owner: self }
+	}
+
+	#[cfg(feature = "std")]
+	fn lock_waiters(&self) -> MutexGuard<'_, alloc::vec::Vec<Waker>> {
+		self.waiters.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	#[cfg(feature = "std")]
+	fn wake_waiters(&self) {
+		let mut waiters = self.lock_waiters();
+		let wakers = core::mem::take(&mut *waiters);
+		self.released.notify_all();
+		drop(waiters);
+		for waker in wakers {
+			waker.wake();
+		}
+	}
+}
+
+impl fmt::Debug for Backpressure {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("Backpressure")
+			.field("max_background", &self.max_background)
+			.field("congestion_threshold", &self.congestion_threshold)
+			.field("in_flight", &self.in_flight())
+			.finish()
+	}
+}
+
+// }}}
+
+// BackpressurePermit {{{
+
+/// A slot for an in-flight request, acquired from a [`Backpressure`].
+///
+/// The slot is released when the permit is dropped.
+#[must_use]
+pub struct BackpressurePermit<'a> {
+	owner: &'a Backpressure,
+}
+
+impl Drop for BackpressurePermit<'_> {
+	fn drop(&mut self) {
+		self.owner.in_flight.fetch_sub(1, Ordering::Release);
+		#[cfg(feature = "std")]
+		self.owner.wake_waiters();
+	}
+}
+
+impl fmt::Debug for BackpressurePermit<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("BackpressurePermit").finish()
+	}
+}
+
+// }}}
+
+// BackpressureAcquire {{{
+
+/// Future returned by [`Backpressure::acquire_async`].
+#[cfg(feature = "std")]
+#[must_use = "futures do nothing unless polled"]
+pub struct BackpressureAcquire<'a> {
+	owner: &'a Backpressure,
+}
+
+#[cfg(feature = "std")]
+impl<'a> Future for BackpressureAcquire<'a> {
+	type Output = BackpressurePermit<'a>;
+
+	fn poll(
+		self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+	) -> Poll<BackpressurePermit<'a>> {
+		let owner = self.owner;
+		if let Some(permit) = owner.try_acquire() {
+			return Poll::Ready(permit);
+		}
+		let mut waiters = owner.lock_waiters();
+		// Checked again with the lock held, so that a permit released since
+		// the first check can't be missed.
+		if let Some(permit) = owner.try_acquire() {
+			return Poll::Ready(permit);
+		}
+		if !waiters.iter().any(|waker| waker.will_wake(cx.waker())) {
+			waiters.push(cx.waker().clone());
+		}
+		Poll::Pending
+	}
+}
+
+#[cfg(feature = "std")]
+impl fmt::Debug for BackpressureAcquire<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("BackpressureAcquire").finish()
+	}
+}
+
+// }}}
@@ -0,0 +1,72 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use fuse::server::{Backpressure, FuseInitResponse};
+use fuse::Version;
+
+#[test]
+fn backpressure_limits() {
+	let backpressure = Backpressure::new(3, 2);
+	assert_eq!(backpressure.max_background(), 3);
+	assert_eq!(backpressure.congestion_threshold(), 2);
+
+	let permit_1 = backpressure.try_acquire().unwrap();
+	assert!(!backpressure.should_throttle());
+	let permit_2 = backpressure.try_acquire().unwrap();
+	assert!(backpressure.should_throttle());
+	assert!(!backpressure.is_full());
+	let permit_3 = backpressure.try_acquire().unwrap();
+	assert!(backpressure.is_full());
+	assert!(backpressure.try_acquire().is_none());
+	assert_eq!(backpressure.in_flight(), 3);
+
+	// Permits acquired without checking can exceed the maximum.
+	let permit_4 = backpressure.acquire();
+	assert_eq!(backpressure.in_flight(), 4);
+
+	drop(permit_4);
+	drop(permit_3);
+	assert!(!backpressure.is_full());
+	drop(permit_2);
+	assert!(!backpressure.should_throttle());
+	drop(permit_1);
+	assert_eq!(backpressure.in_flight(), 0);
+}
+
+#[test]
+fn backpressure_defaults() {
+	let backpressure = Backpressure::new(0, 0);
+	assert_eq!(backpressure.max_background(), 12);
+	assert_eq!(backpressure.congestion_threshold(), 9);
+}
+
+#[test]
+fn backpressure_from_init_response() {
+	let mut response = FuseInitResponse::new();
+	response.set_version(Version::new(7, 31));
+	response.set_max_background(100);
+	response.set_congestion_threshold(80);
+
+	let backpressure = Backpressure::from_init_response(&response);
+	assert_eq!(backpressure.max_background(), 100);
+	assert_eq!(backpressure.congestion_threshold(), 80);
+
+	// Older clients ignore the limits in the response.
+	response.set_version(Version::new(7, 12));
+	let backpressure = Backpressure::from_init_response(&response);
+	assert_eq!(backpressure.max_background(), 12);
+	assert_eq!(backpressure.congestion_threshold(), 9);
+}
@@ -0,0 +1,349 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::mem::size_of;
+
+use fuse::kernel;
+use fuse::server::{
+	CreateResponse,
+	CuseLayout,
+	FuseLayout,
+	GetattrResponse,
+	LayoutError,
+	LookupResponse,
+	SetattrResponse,
+	SetxattrRequest,
+	StatfsResponse,
+	WriteRequest,
+};
+
+use fuse_testutil as testutil;
+use fuse_testutil::{decode_request, encode_response, MessageBuilder};
+
+// Protocol versions with distinct wire layouts, plus versions negotiated by
+// commonly deployed clients (FreeBSD speaks 7.28).
+const VERSIONS: &[(u32, u32)] = &[
+	(7, 1),
+	(7, 3),
+	(7, 4),
+	(7, 5),
+	(7, 8),
+	(7, 9),
+	(7, 11),
+	(7, 12),
+	(7, 22),
+	(7, 23),
+	(7, 28),
+	(7, 35),
+	(kernel::FUSE_KERNEL_VERSION, kernel::FUSE_KERNEL_MINOR_VERSION),
+];
+
+const HEADER_LEN: usize = size_of::<kernel::fuse_out_header>();
+
+fn attr_out_len(minor: u32) -> usize {
+	if minor < 9 {
+		return kernel::FUSE_COMPAT_ATTR_OUT_SIZE;
+	}
+	size_of::<kernel::fuse_attr_out>()
+}
+
+fn entry_out_len(minor: u32) -> usize {
+	if minor < 9 {
+		return kernel::FUSE_COMPAT_ENTRY_OUT_SIZE;
+	}
+	size_of::<kernel::fuse_entry_out>()
+}
+
+fn node_attr() -> fuse::NodeAttr {
+	let mut attr = fuse::NodeAttr::new(fuse::NodeId::new(2).unwrap());
+	attr.set_size(123);
+	attr
+}
+
+#[test]
+fn getattr_response() {
+	let resp = GetattrResponse::new(node_attr());
+	let latest = encode_response!(&resp);
+	let latest = &latest[HEADER_LEN..];
+	for &(major, minor) in VERSIONS {
+		let encoded = encode_response!(&resp, {
+			protocol_version: (major, minor),
+		});
+		assert_eq!(
+			encoded.len(),
+			HEADER_LEN + attr_out_len(minor),
+			"protocol version {}.{}",
+			major,
+			minor,
+		);
+		assert_eq!(encoded[HEADER_LEN..], latest[..attr_out_len(minor)]);
+	}
+}
+
+#[test]
+fn setattr_response() {
+	let resp = SetattrResponse::new(node_attr());
+	let latest = encode_response!(&resp);
+	let latest = &latest[HEADER_LEN..];
+	for &(major, minor) in VERSIONS {
+		let encoded = encode_response!(&resp, {
+			protocol_version: (major, minor),
+		});
+		assert_eq!(
+			encoded.len(),
+			HEADER_LEN + attr_out_len(minor),
+			"protocol version {}.{}",
+			major,
+			minor,
+		);
+		assert_eq!(encoded[HEADER_LEN..], latest[..attr_out_len(minor)]);
+	}
+}
+
+#[test]
+fn lookup_response() {
+	let resp = LookupResponse::new(fuse::Entry::new(node_attr()));
+	let latest = encode_response!(&resp);
+	let latest = &latest[HEADER_LEN..];
+	for &(major, minor) in VERSIONS {
+		let encoded = encode_response!(&resp, {
+			protocol_version: (major, minor),
+		});
+		assert_eq!(
+			encoded.len(),
+			HEADER_LEN + entry_out_len(minor),
+			"protocol version {}.{}",
+			major,
+			minor,
+		);
+		assert_eq!(encoded[HEADER_LEN..], latest[..entry_out_len(minor)]);
+	}
+}
+
+#[test]
+fn entry_response() {
+	let entry = fuse::Entry::new(node_attr());
+	let latest = encode_response!(&entry);
+	let latest = &latest[HEADER_LEN..];
+	for &(major, minor) in VERSIONS {
+		let encoded = encode_response!(&entry, {
+			protocol_version: (major, minor),
+		});
+		assert_eq!(
+			encoded.len(),
+			HEADER_LEN + entry_out_len(minor),
+			"protocol version {}.{}",
+			major,
+			minor,
+		);
+		assert_eq!(encoded[HEADER_LEN..], latest[..entry_out_len(minor)]);
+	}
+}
+
+#[test]
+fn create_response() {
+	let mut resp = CreateResponse::new(fuse::Entry::new(node_attr()));
+	resp.set_handle(0x1122);
+	for &(major, minor) in VERSIONS {
+		let encoded = encode_response!(&resp, {
+			protocol_version: (major, minor),
+		});
+		let entry_len = entry_out_len(minor);
+		assert_eq!(
+			encoded.len(),
+			HEADER_LEN + entry_len + size_of::<kernel::fuse_open_out>(),
+			"protocol version {}.{}",
+			major,
+			minor,
+		);
+		let open_out = &encoded[HEADER_LEN + entry_len..];
+		assert_eq!(open_out[..8], 0x1122u64.to_ne_bytes());
+	}
+}
+
+#[test]
+fn statfs_response() {
+	let mut attrs = fuse::StatfsAttributes::new();
+	attrs.set_block_count(10);
+	attrs.set_fragment_size(512);
+	let resp = StatfsResponse::new(attrs);
+	for &(major, minor) in VERSIONS {
+		let encoded = encode_response!(&resp, {
+			protocol_version: (major, minor),
+		});
+		let expect_len = if minor < 4 {
+			kernel::FUSE_COMPAT_STATFS_SIZE
+		} else {
+			size_of::<kernel::fuse_statfs_out>()
+		};
+		assert_eq!(
+			encoded.len(),
+			HEADER_LEN + expect_len,
+			"protocol version {}.{}",
+			major,
+			minor,
+		);
+		assert_eq!(encoded[HEADER_LEN..HEADER_LEN + 8], 10u64.to_ne_bytes());
+	}
+}
+
+#[test]
+fn init_response() {
+	for &(major, minor) in VERSIONS {
+		let init_out = testutil::new!(kernel::fuse_init_out {
+			major: major,
+			minor: minor,
+			max_write: 4096,
+			flags2: 1,
+		});
+		let encoded = encode_response!(&init_out);
+		let expect_len = if minor < 5 {
+			kernel::FUSE_COMPAT_INIT_OUT_SIZE
+		} else if minor < 23 {
+			kernel::FUSE_COMPAT_22_INIT_OUT_SIZE
+		} else {
+			size_of::<kernel::fuse_init_out>()
+		};
+		assert_eq!(
+			encoded.len(),
+			HEADER_LEN + expect_len,
+			"protocol version {}.{}",
+			major,
+			minor,
+		);
+
+		let mut expect = init_out;
+		expect.flags2 = 0;
+		assert_eq!(encoded[HEADER_LEN..], expect.as_bytes()[..expect_len]);
+	}
+}
+
+#[test]
+fn write_request() {
+	for &(major, minor) in VERSIONS {
+		let compat_len = if minor < 9 {
+			size_of::<kernel::fuse_write_in>()
+				- kernel::FUSE_COMPAT_WRITE_IN_SIZE
+		} else {
+			0
+		};
+		let buf = MessageBuilder::new()
+			.set_header(|h| {
+				h.opcode = kernel::fuse_opcode::FUSE_WRITE;
+				h.nodeid = 123;
+			})
+			.push_sized(&testutil::new!(kernel::fuse_write_in {
+				fh: 1,
+				offset: 2,
+				size: 5,
+				flags: 3,
+			}))
+			.unpush(compat_len)
+			.push_bytes(b"hello")
+			.build_aligned();
+
+		let req = decode_request!(WriteRequest, buf, {
+			protocol_version: (major, minor),
+		});
+		assert_eq!(req.handle(), 1);
+		assert_eq!(req.offset(), 2);
+		assert_eq!(req.value(), b"hello");
+		if minor < 9 {
+			assert_eq!(req.open_flags().get(), 0);
+		} else {
+			assert_eq!(req.open_flags().get(), 3);
+		}
+	}
+}
+
+#[test]
+fn setxattr_request() {
+	// FUSE_SETXATTR_EXT is negotiated by an init flag, so the compact
+	// `fuse_setxattr_in` is used at every protocol version by default.
+	for &(major, minor) in VERSIONS {
+		let buf = MessageBuilder::new()
+			.set_header(|h| {
+				h.opcode = kernel::fuse_opcode::FUSE_SETXATTR;
+				h.nodeid = 123;
+			})
+			.push_sized(&5u32) // fuse_setxattr_in::size
+			.push_sized(&0b1u32) // fuse_setxattr_in::flags
+			.push_bytes(b"user.name\x00")
+			.push_bytes(b"value")
+			.build_aligned();
+
+		let req = decode_request!(SetxattrRequest, buf, {
+			protocol_version: (major, minor),
+		});
+		assert_eq!(req.name(), c"user.name");
+		assert_eq!(req.value(), b"value");
+		assert_eq!(req.setxattr_flags(), 0b1);
+	}
+}
+
+#[test]
+fn layout_versions() {
+	for &(major, minor) in VERSIONS {
+		let init_out = testutil::new!(kernel::fuse_init_out {
+			major: major,
+			minor: minor,
+		});
+		assert!(FuseLayout::new(&init_out).is_ok());
+
+		let init_out = testutil::new!(kernel::cuse_init_out {
+			major: major,
+			minor: minor,
+		});
+		assert!(CuseLayout::new(&init_out).is_ok());
+	}
+}
+
+#[test]
+fn layout_errors() {
+	let init_out = testutil::new!(kernel::fuse_init_out {
+		major: kernel::FUSE_KERNEL_VERSION + 1,
+		minor: 1,
+	});
+	let err = FuseLayout::new(&init_out).err();
+	assert_eq!(err, Some(LayoutError::MajorVersionMismatch {
+		got: kernel::FUSE_KERNEL_VERSION + 1,
+	}));
+	assert_eq!(
+		format!("{}", err.unwrap()),
+		format!(
+			"unsupported FUSE protocol major version {} (expected {})",
+			kernel::FUSE_KERNEL_VERSION + 1,
+			kernel::FUSE_KERNEL_VERSION,
+		),
+	);
+
+	let init_out = testutil::new!(kernel::cuse_init_out {
+		major: kernel::FUSE_KERNEL_VERSION,
+		minor: kernel::FUSE_KERNEL_MINOR_VERSION + 1,
+	});
+	let err = CuseLayout::new(&init_out).err();
+	assert_eq!(err, Some(LayoutError::MinorVersionTooNew {
+		got: kernel::FUSE_KERNEL_MINOR_VERSION + 1,
+	}));
+	assert_eq!(
+		format!("{}", err.unwrap()),
+		format!(
+			"unsupported FUSE protocol minor version {} (maximum {})",
+			kernel::FUSE_KERNEL_MINOR_VERSION + 1,
+			kernel::FUSE_KERNEL_MINOR_VERSION,
+		),
+	);
+}
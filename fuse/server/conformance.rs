@@ -0,0 +1,376 @@
+// Copyright 2023 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Protocol conformance reports.
+//!
+//! The FUSE protocol has many optional operations, and many init flags that
+//! only work correctly if certain operations are implemented. The [`check`]
+//! function summarizes which operations a filesystem implements, which init
+//! flags it requests, and any known gaps between the two.
+//!
+//! The report is intended for filesystems that publish compatibility
+//! matrices, so that the published information can be generated from the
+//! same source as the filesystem's handlers.
+
+use core::fmt;
+
+use crate::kernel::fuse_opcode;
+use crate::operations::fuse_init::{FuseInitFlag, FuseInitFlags};
+
+// Opcodes that may be sent by a FUSE client, excluding `FUSE_INIT`, in
+// numeric order.
+const OPCODES: &[fuse_opcode] = &[
+	fuse_opcode::FUSE_LOOKUP,
+	fuse_opcode::FUSE_FORGET,
+	fuse_opcode::FUSE_GETATTR,
+	fuse_opcode::FUSE_SETATTR,
+	fuse_opcode::FUSE_READLINK,
+	fuse_opcode::FUSE_SYMLINK,
+	fuse_opcode::FUSE_MKNOD,
+	fuse_opcode::FUSE_MKDIR,
+	fuse_opcode::FUSE_UNLINK,
+	fuse_opcode::FUSE_RMDIR,
+	fuse_opcode::FUSE_RENAME,
+	fuse_opcode::FUSE_LINK,
+	fuse_opcode::FUSE_OPEN,
+	fuse_opcode::FUSE_READ,
+	fuse_opcode::FUSE_WRITE,
+	fuse_opcode::FUSE_STATFS,
+	fuse_opcode::FUSE_RELEASE,
+	fuse_opcode::FUSE_FSYNC,
+	fuse_opcode::FUSE_SETXATTR,
+	fuse_opcode::FUSE_GETXATTR,
+	fuse_opcode::FUSE_LISTXATTR,
+	fuse_opcode::FUSE_REMOVEXATTR,
+	fuse_opcode::FUSE_FLUSH,
+	fuse_opcode::FUSE_OPENDIR,
+	fuse_opcode::FUSE_READDIR,
+	fuse_opcode::FUSE_RELEASEDIR,
+	fuse_opcode::FUSE_FSYNCDIR,
+	fuse_opcode::FUSE_GETLK,
+	fuse_opcode::FUSE_SETLK,
+	fuse_opcode::FUSE_SETLKW,
+	fuse_opcode::FUSE_ACCESS,
+	fuse_opcode::FUSE_CREATE,
+	fuse_opcode::FUSE_INTERRUPT,
+	fuse_opcode::FUSE_BMAP,
+	fuse_opcode::FUSE_DESTROY,
+	fuse_opcode::FUSE_IOCTL,
+	fuse_opcode::FUSE_POLL,
+	fuse_opcode::FUSE_BATCH_FORGET,
+	fuse_opcode::FUSE_FALLOCATE,
+	fuse_opcode::FUSE_READDIRPLUS,
+	fuse_opcode::FUSE_RENAME2,
+	fuse_opcode::FUSE_LSEEK,
+	fuse_opcode::FUSE_COPY_FILE_RANGE,
+	fuse_opcode::FUSE_SETUPMAPPING,
+	fuse_opcode::FUSE_REMOVEMAPPING,
+	fuse_opcode::FUSE_SYNCFS,
+	fuse_opcode::FUSE_TMPFILE,
+	fuse_opcode::FUSE_STATX,
+];
+
+// Operations that every filesystem must implement to be mountable.
+const REQUIRED: &[fuse_opcode] = &[
+	fuse_opcode::FUSE_LOOKUP,
+	fuse_opcode::FUSE_GETATTR,
+];
+
+// Init flags that cause the client to send operations it would otherwise
+// not send, or that have no effect unless an operation is implemented.
+const FLAG_REQUIREMENTS: &[(FuseInitFlag, fuse_opcode)] = &[
+	(FuseInitFlag::POSIX_LOCKS, fuse_opcode::FUSE_GETLK),
+	(FuseInitFlag::POSIX_LOCKS, fuse_opcode::FUSE_SETLK),
+	(FuseInitFlag::POSIX_LOCKS, fuse_opcode::FUSE_SETLKW),
+	(FuseInitFlag::EXPORT_SUPPORT, fuse_opcode::FUSE_LOOKUP),
+	(FuseInitFlag::FLOCK_LOCKS, fuse_opcode::FUSE_SETLK),
+	(FuseInitFlag::FLOCK_LOCKS, fuse_opcode::FUSE_SETLKW),
+	(FuseInitFlag::HAS_IOCTL_DIR, fuse_opcode::FUSE_IOCTL),
+	(FuseInitFlag::DO_READDIRPLUS, fuse_opcode::FUSE_READDIRPLUS),
+	(FuseInitFlag::READDIRPLUS_AUTO, fuse_opcode::FUSE_READDIRPLUS),
+	(FuseInitFlag::POSIX_ACL, fuse_opcode::FUSE_GETXATTR),
+	(FuseInitFlag::POSIX_ACL, fuse_opcode::FUSE_SETXATTR),
+	(FuseInitFlag::CACHE_SYMLINKS, fuse_opcode::FUSE_READLINK),
+	(FuseInitFlag::SETXATTR_EXT, fuse_opcode::FUSE_SETXATTR),
+];
+
+/// Returns a conformance report for a filesystem.
+#[must_use]
+pub fn check(handlers: &HandlersDescription) -> Report {
+	Report { handlers: *handlers }
+}
+
+// HandlersDescription {{{
+
+/// Describes the operations implemented by a filesystem.
+///
+/// The default methods of [`FuseHandlers`] can't be detected at runtime, so
+/// the implemented operations must be described explicitly.
+///
+/// [`FuseHandlers`]: crate::server::FuseHandlers
+#[derive(Clone, Copy)]
+pub struct HandlersDescription {
+	opcodes: u64,
+	init_flags: FuseInitFlags,
+}
+
+impl HandlersDescription {
+	/// Returns a description of a filesystem that implements no operations
+	/// and requests no init flags.
+	#[must_use]
+	pub const fn new() -> HandlersDescription {
+		Self {
+			opcodes: 0,
+			init_flags: FuseInitFlags::new(),
+		}
+	}
+
+	/// Returns whether the given operation is implemented.
+	#[inline]
+	#[must_use]
+	pub fn implements(&self, opcode: fuse_opcode) -> bool {
+		match opcode_mask(opcode) {
+			Some(mask) => self.opcodes & mask != 0,
+			None => false,
+		}
+	}
+
+	/// Sets whether the given operation is implemented.
+	///
+	/// Opcodes not listed by [`Report::operations`] are ignored.
+	pub fn set_implements(&mut self, opcode: fuse_opcode, implements: bool) {
+		let mask = match opcode_mask(opcode) {
+			Some(mask) => mask,
+			None => return,
+		};
+		if implements {
+			self.opcodes |= mask;
+		} else {
+			self.opcodes &= !mask;
+		}
+	}
+
+	/// Returns the init flags requested by the filesystem.
+	#[inline]
+	#[must_use]
+	pub fn init_flags(&self) -> FuseInitFlags {
+		self.init_flags
+	}
+
+	/// Sets the init flags requested by the filesystem.
+	///
+	/// To describe an active connection, use the flags negotiated with the
+	/// client as reported by [`ConnectionInfo::flags`].
+	///
+	/// [`ConnectionInfo::flags`]: crate::server::ConnectionInfo::flags
+	#[inline]
+	pub fn set_init_flags(&mut self, init_flags: FuseInitFlags) {
+		self.init_flags = init_flags;
+	}
+}
+
+impl fmt::Debug for HandlersDescription {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		let implemented = OPCODES.iter().filter(|op| self.implements(**op));
+		fmt.debug_struct("HandlersDescription")
+			.field("operations", &DebugList(implemented))
+			.field("init_flags", &self.init_flags)
+			.finish()
+	}
+}
+
+fn opcode_mask(opcode: fuse_opcode) -> Option<u64> {
+	if !OPCODES.contains(&opcode) {
+		return None;
+	}
+	Some(1u64 << opcode.0)
+}
+
+// }}}
+
+// Report {{{
+
+/// A conformance report, as returned by [`check`].
+#[derive(Clone, Copy)]
+pub struct Report {
+	handlers: HandlersDescription,
+}
+
+impl Report {
+	/// Returns an iterator over all operations that may be sent by a FUSE
+	/// client, and whether each is implemented by the filesystem.
+	#[must_use]
+	pub fn operations(&self) -> Operations {
+		Operations {
+			handlers: self.handlers,
+			opcodes: OPCODES.iter(),
+		}
+	}
+
+	/// Returns the init flags requested by the filesystem.
+	#[inline]
+	#[must_use]
+	pub fn init_flags(&self) -> FuseInitFlags {
+		self.handlers.init_flags
+	}
+
+	/// Returns an iterator over the known gaps in the filesystem's
+	/// implementation.
+	#[must_use]
+	pub fn gaps(&self) -> Gaps {
+		Gaps {
+			handlers: self.handlers,
+			required: REQUIRED.iter(),
+			flag_requirements: FLAG_REQUIREMENTS.iter(),
+		}
+	}
+
+	/// Returns `true` if the report contains no gaps.
+	#[must_use]
+	pub fn is_conformant(&self) -> bool {
+		self.gaps().next().is_none()
+	}
+}
+
+impl fmt::Debug for Report {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("Report")
+			.field("operations", &DebugList(self.operations()))
+			.field("init_flags", &self.init_flags())
+			.field("gaps", &DebugList(self.gaps()))
+			.finish()
+	}
+}
+
+// }}}
+
+// Operation {{{
+
+/// The implementation status of a single operation.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct Operation {
+	opcode: fuse_opcode,
+	implemented: bool,
+}
+
+impl Operation {
+	/// Returns the operation's opcode.
+	#[inline]
+	#[must_use]
+	pub fn opcode(&self) -> fuse_opcode {
+		self.opcode
+	}
+
+	/// Returns whether the operation is implemented by the filesystem.
+	#[inline]
+	#[must_use]
+	pub fn is_implemented(&self) -> bool {
+		self.implemented
+	}
+}
+
+impl fmt::Debug for Operation {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("Operation")
+			.field("opcode", &self.opcode)
+			.field("implemented", &self.implemented)
+			.finish()
+	}
+}
+
+/// Iterator over the operations of a [`Report`].
+#[derive(Clone)]
+pub struct Operations {
+	handlers: HandlersDescription,
+	opcodes: core::slice::Iter<'static, fuse_opcode>,
+}
+
+impl Iterator for Operations {
+	type Item = Operation;
+
+	fn next(&mut self) -> Option<Operation> {
+		let opcode = *self.opcodes.next()?;
+		Some(Operation {
+			opcode,
+			implemented: self.handlers.implements(opcode),
+		})
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.opcodes.size_hint()
+	}
+}
+
+impl ExactSizeIterator for Operations {}
+
+// }}}
+
+// Gap {{{
+
+/// A known gap in a filesystem's implementation.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Gap {
+	/// An operation required by all filesystems is not implemented.
+	MissingOperation(fuse_opcode),
+
+	/// An init flag was requested, but an operation that the flag depends
+	/// on is not implemented.
+	FlagWithoutOperation(FuseInitFlag, fuse_opcode),
+}
+
+/// Iterator over the gaps of a [`Report`].
+#[derive(Clone)]
+pub struct Gaps {
+	handlers: HandlersDescription,
+	required: core::slice::Iter<'static, fuse_opcode>,
+	flag_requirements: core::slice::Iter<'static, (FuseInitFlag, fuse_opcode)>,
+}
+
+impl Iterator for Gaps {
+	type Item = Gap;
+
+	fn next(&mut self) -> Option<Gap> {
+		for &opcode in self.required.by_ref() {
+			if !self.handlers.implements(opcode) {
+				return Some(Gap::MissingOperation(opcode));
+			}
+		}
+		for &(flag, opcode) in self.flag_requirements.by_ref() {
+			if !self.handlers.init_flags.get(flag) {
+				continue;
+			}
+			if !self.handlers.implements(opcode) {
+				return Some(Gap::FlagWithoutOperation(flag, opcode));
+			}
+		}
+		None
+	}
+}
+
+// }}}
+
+struct DebugList<I>(I);
+
+impl<I> fmt::Debug for DebugList<I>
+where
+	I: Iterator + Clone,
+	I::Item: fmt::Debug,
+{
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_list().entries(self.0.clone()).finish()
+	}
+}
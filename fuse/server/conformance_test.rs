@@ -0,0 +1,143 @@
+// Copyright 2023 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use fuse::kernel::fuse_opcode;
+use fuse::server::conformance::{check, Gap, HandlersDescription};
+use fuse::{FuseInitFlag, FuseInitFlags};
+
+fn handlers(opcodes: &[fuse_opcode]) -> HandlersDescription {
+	let mut handlers = HandlersDescription::new();
+	for opcode in opcodes {
+		handlers.set_implements(*opcode, true);
+	}
+	handlers
+}
+
+#[test]
+fn operations() {
+	let handlers = handlers(&[
+		fuse_opcode::FUSE_LOOKUP,
+		fuse_opcode::FUSE_GETATTR,
+		fuse_opcode::FUSE_READ,
+	]);
+	let report = check(&handlers);
+
+	let implemented: Vec<_> = report
+		.operations()
+		.filter(|op| op.is_implemented())
+		.map(|op| op.opcode())
+		.collect();
+	assert_eq!(implemented, &[
+		fuse_opcode::FUSE_LOOKUP,
+		fuse_opcode::FUSE_GETATTR,
+		fuse_opcode::FUSE_READ,
+	]);
+
+	let first = report.operations().next().unwrap();
+	assert_eq!(first.opcode(), fuse_opcode::FUSE_LOOKUP);
+	assert!(report.operations().any(|op| {
+		op.opcode() == fuse_opcode::FUSE_STATX && !op.is_implemented()
+	}));
+
+	assert!(report.is_conformant());
+}
+
+#[test]
+fn unknown_opcodes_ignored() {
+	let mut handlers = HandlersDescription::new();
+	handlers.set_implements(fuse_opcode::FUSE_INIT, true);
+	handlers.set_implements(fuse_opcode::CUSE_INIT, true);
+	handlers.set_implements(fuse_opcode(63), true);
+
+	assert!(!handlers.implements(fuse_opcode::FUSE_INIT));
+	assert!(!handlers.implements(fuse_opcode::CUSE_INIT));
+	assert!(!handlers.implements(fuse_opcode(63)));
+}
+
+#[test]
+fn missing_required_operations() {
+	let report = check(&handlers(&[fuse_opcode::FUSE_GETATTR]));
+
+	let gaps: Vec<_> = report.gaps().collect();
+	assert_eq!(gaps, &[Gap::MissingOperation(fuse_opcode::FUSE_LOOKUP)]);
+	assert!(!report.is_conformant());
+}
+
+#[test]
+fn flag_without_operation() {
+	let mut handlers = handlers(&[
+		fuse_opcode::FUSE_LOOKUP,
+		fuse_opcode::FUSE_GETATTR,
+		fuse_opcode::FUSE_GETLK,
+	]);
+	let mut flags = FuseInitFlags::new();
+	flags.set(FuseInitFlag::POSIX_LOCKS);
+	flags.set(FuseInitFlag::DO_READDIRPLUS);
+	flags.set(FuseInitFlag::ASYNC_READ);
+	handlers.set_init_flags(flags);
+
+	let report = check(&handlers);
+	assert_eq!(report.init_flags(), flags);
+
+	let gaps: Vec<_> = report.gaps().collect();
+	assert_eq!(gaps, &[
+		Gap::FlagWithoutOperation(
+			FuseInitFlag::POSIX_LOCKS,
+			fuse_opcode::FUSE_SETLK,
+		),
+		Gap::FlagWithoutOperation(
+			FuseInitFlag::POSIX_LOCKS,
+			fuse_opcode::FUSE_SETLKW,
+		),
+		Gap::FlagWithoutOperation(
+			FuseInitFlag::DO_READDIRPLUS,
+			fuse_opcode::FUSE_READDIRPLUS,
+		),
+	]);
+}
+
+#[test]
+fn report_impl_debug() {
+	let mut handlers = handlers(&[fuse_opcode::FUSE_GETATTR]);
+	let mut flags = FuseInitFlags::new();
+	flags.set(FuseInitFlag::CACHE_SYMLINKS);
+	handlers.set_init_flags(flags);
+
+	assert_eq!(
+		format!("{:?}", handlers),
+		concat!(
+			"HandlersDescription {",
+			" operations: [FUSE_GETATTR],",
+			" init_flags: FuseInitFlags {CACHE_SYMLINKS}",
+			" }",
+		),
+	);
+
+	let report = check(&handlers);
+	let debug = format!("{:?}", report);
+	assert!(debug.starts_with(concat!(
+		"Report { operations: [",
+		"Operation { opcode: FUSE_LOOKUP, implemented: false }, ",
+		"Operation { opcode: FUSE_FORGET, implemented: false }, ",
+		"Operation { opcode: FUSE_GETATTR, implemented: true }, ",
+	)));
+	assert!(debug.ends_with(concat!(
+		"init_flags: FuseInitFlags {CACHE_SYMLINKS}, gaps: [",
+		"MissingOperation(FUSE_LOOKUP), ",
+		"FlagWithoutOperation(CACHE_SYMLINKS, FUSE_READLINK)",
+		"] }",
+	)));
+}
@@ -139,6 +139,16 @@ impl<'a> RequestDecoder<'a> {
 		Ok(out)
 	}
 
+	pub(crate) fn next_remaining(&mut self) -> &'a [u8] {
+		let len = self.header_len.saturating_sub(self.consumed);
+		let out = unsafe {
+			let out_p = self.buf.slice.ptr.add(self.consumed);
+			from_raw_parts(out_p, len)
+		};
+		self.consumed += len;
+		out
+	}
+
 	pub(crate) fn next_node_name(
 		&mut self,
 	) -> Result<&'a NodeName, RequestError> {
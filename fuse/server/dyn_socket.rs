@@ -0,0 +1,280 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Sockets with dynamically dispatched I/O.
+//!
+//! The socket traits are object safe, so a `&dyn FuseSocket<Error = E>` or
+//! `Box<dyn FuseSocket<Error = E>>` can be used as the socket of a
+//! connection. Sockets of different types usually have different error types,
+//! which must be erased before the sockets can share a trait object type.
+//!
+//! A [`DynSocket`] wraps a socket and boxes its errors in a
+//! [`DynSocketError`]. Servers that choose their transport at runtime, for
+//! example from a plugin or a command-line flag, can then store any socket
+//! as a `Box<dyn FuseSocketDyn>`:
+//!
+//! ```
+//! use fuse::server::{DynSocket, FuseConnection, FuseSocketDyn};
+//! use fuse::server::{RecvError, ServerError};
+//! use fuse::testing::{SocketError, SocketPair};
+//!
+//! let mut request_buf = [0u8; 1024];
+//! let mut reply_buf = [0u8; 1024];
+//! let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+//!
+//! let socket: Box<dyn FuseSocketDyn> =
+//! 	Box::new(DynSocket::new(pair.server()));
+//! let conn = FuseConnection::connect(socket, |_, _| {});
+//!
+//! // No `FUSE_INIT` request was queued, and the socket's own error can be
+//! // recovered from the boxed error.
+//! let Err(ServerError::RecvError(RecvError::ConnectionClosed(err))) = conn
+//! else {
+//! 	panic!("expected ConnectionClosed");
+//! };
+//! assert_eq!(err.downcast_ref(), Some(&SocketError::Empty));
+//! ```
+//!
+//! Each `recv()` and `send()` through a trait object costs one indirect call,
+//! which is small compared to the system call it wraps. Boxing an error
+//! costs a heap allocation, including for [`RecvError::WouldBlock`] from a
+//! non-blocking socket. Servers that poll non-blocking sockets in a loop
+//! should prefer a concrete socket type, or a trait object with the socket's
+//! own error type.
+
+use alloc::boxed::Box;
+use core::any::Any;
+use core::fmt;
+
+use crate::io::SendBuf;
+use crate::server::{
+	CuseSocket,
+	FuseSocket,
+	RecvError,
+	SendError,
+	Socket,
+};
+
+// DynSocketError {{{
+
+/// Errors returned by a [`DynSocket`].
+///
+/// The error of the wrapped socket can be recovered with
+/// [`DynSocketError::downcast_ref`] or [`DynSocketError::downcast`].
+pub struct DynSocketError {
+	err: Box<dyn ErrorValue>,
+}
+
+trait ErrorValue: Any + fmt::Debug + Send + Sync {
+	fn as_any(&self) -> &dyn Any;
+}
+
+impl<E: Any + fmt::Debug + Send + Sync> ErrorValue for E {
+	fn as_any(&self) -> &dyn Any {
+		self
+	}
+}
+
+impl DynSocketError {
+	/// Creates a new `DynSocketError` containing the given error.
+	#[must_use]
+	pub fn new<E>(err: E) -> DynSocketError
+	where
+		E: Any + fmt::Debug + Send + Sync,
+	{
+		Self { err: Box::new(err) }
+	}
+
+	/// Returns whether the contained error is of type `E`.
+	#[must_use]
+	pub fn is<E: Any>(&self) -> bool {
+		(*self.err).as_any().is::<E>()
+	}
+
+	/// Returns a reference to the contained error, or `None` if it isn't
+	/// of type `E`.
+	#[must_use]
+	pub fn downcast_ref<E: Any>(&self) -> Option<&E> {
+		(*self.err).as_any().downcast_ref()
+	}
+
+	/// Returns the contained error, or `Err(self)` if it isn't of type `E`.
+	///
+	/// # Errors
+	///
+	/// Returns `self` if the contained error isn't of type `E`.
+	pub fn downcast<E: Any>(self) -> Result<E, DynSocketError> {
+		if !self.is::<E>() {
+			return Err(self);
+		}
+		let raw = Box::into_raw(self.err).cast::<E>();
+		Ok(*unsafe { Box::from_raw(raw) })
+	}
+}
+
+impl fmt::Debug for DynSocketError {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_tuple("DynSocketError").field(&self.err).finish()
+	}
+}
+
+// }}}
+
+// DynSocket {{{
+
+/// A socket that boxes the errors of the socket it wraps.
+///
+/// See the [module documentation](self) for details.
+pub struct DynSocket<S> {
+	socket: S,
+}
+
+impl<S> DynSocket<S> {
+	/// Creates a new `DynSocket` wrapping the given socket.
+	#[must_use]
+	pub fn new(socket: S) -> DynSocket<S> {
+		Self { socket }
+	}
+
+	/// Returns a reference to the wrapped socket.
+	#[inline]
+	#[must_use]
+	pub fn socket(&self) -> &S {
+		&self.socket
+	}
+
+	/// Consumes the `DynSocket`, returning the wrapped socket.
+	#[inline]
+	#[must_use]
+	pub fn into_inner(self) -> S {
+		self.socket
+	}
+}
+
+impl<S> fmt::Debug for DynSocket<S> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("DynSocket").finish_non_exhaustive()
+	}
+}
+
+fn map_send_error<E>(err: SendError<E>) -> SendError<DynSocketError>
+where
+	E: Any + fmt::Debug + Send + Sync,
+{
+	match err {
+		SendError::NotFound(err) => {
+			SendError::NotFound(DynSocketError::new(err))
+		},
+		SendError::ReplyTooBig(len) => SendError::ReplyTooBig(len),
+		SendError::FdUnsupported => SendError::FdUnsupported,
+		SendError::InvalidErrno(err) => SendError::InvalidErrno(err),
+		SendError::SubmountNotEnabled => SendError::SubmountNotEnabled,
+		SendError::Other(err) => {
+			SendError::Other(DynSocketError::new(err))
+		},
+	}
+}
+
+impl<S> Socket for DynSocket<S>
+where
+	S: Socket,
+	S::Error: Any + fmt::Debug + Send + Sync,
+{
+	type Error = DynSocketError;
+
+	fn recv(
+		&self,
+		buf: &mut [u8],
+	) -> Result<usize, RecvError<DynSocketError>> {
+		self.socket.recv(buf).map_err(|err| match err {
+			RecvError::ConnectionClosed(err) => {
+				RecvError::ConnectionClosed(DynSocketError::new(err))
+			},
+			RecvError::WouldBlock(err) => {
+				RecvError::WouldBlock(DynSocketError::new(err))
+			},
+			RecvError::Other(err) => {
+				RecvError::Other(DynSocketError::new(err))
+			},
+		})
+	}
+
+	fn send(&self, buf: SendBuf) -> Result<(), SendError<DynSocketError>> {
+		self.socket.send(buf).map_err(map_send_error)
+	}
+
+	fn can_send_fd(&self) -> bool {
+		self.socket.can_send_fd()
+	}
+
+	fn send_with_fd(
+		&self,
+		buf: SendBuf,
+		fd: i32,
+	) -> Result<(), SendError<DynSocketError>> {
+		self.socket.send_with_fd(buf, fd).map_err(map_send_error)
+	}
+}
+
+impl<S> CuseSocket for DynSocket<S>
+where
+	S: CuseSocket,
+	S::Error: Any + fmt::Debug + Send + Sync,
+{
+}
+
+impl<S> FuseSocket for DynSocket<S>
+where
+	S: FuseSocket,
+	S::Error: Any + fmt::Debug + Send + Sync,
+{
+}
+
+// }}}
+
+// CuseSocketDyn {{{
+
+/// CUSE sockets with a [`DynSocketError`] error type.
+///
+/// This trait is implemented for every such socket, and exists so that the
+/// trait object type can be written as `dyn CuseSocketDyn`.
+pub trait CuseSocketDyn: CuseSocket<Error = DynSocketError> {}
+
+impl<S> CuseSocketDyn for S
+where
+	S: CuseSocket<Error = DynSocketError> + ?Sized,
+{
+}
+
+// }}}
+
+// FuseSocketDyn {{{
+
+/// FUSE sockets with a [`DynSocketError`] error type.
+///
+/// This trait is implemented for every such socket, and exists so that the
+/// trait object type can be written as `dyn FuseSocketDyn`. Servers that
+/// handle requests on multiple threads should use
+/// `dyn FuseSocketDyn + Send + Sync`.
+pub trait FuseSocketDyn: FuseSocket<Error = DynSocketError> {}
+
+impl<S> FuseSocketDyn for S
+where
+	S: FuseSocket<Error = DynSocketError> + ?Sized,
+{
+}
+
+// }}}
@@ -0,0 +1,645 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Support for FUSE clients with the opposite byte order.
+//!
+//! FUSE messages are encoded in the byte order of the client. This is always
+//! the server's native byte order for `/dev/fuse`, but a transport such as
+//! virtiofs may connect a server to a guest running on a different
+//! architecture.
+//!
+//! The protocol reserves the opcode `FUSE_INIT_BSWAP_RESERVED`, which is the
+//! byte-swapped value of `FUSE_INIT`, so that the server can detect such a
+//! client from its first request. [`FuseRequest::new`] rejects these requests
+//! with [`RequestError::ForeignEndian`].
+//!
+//! A server that wants to serve foreign-endian clients can convert each
+//! request to native byte order with [`swap_fuse_request`] before decoding
+//! it, and convert each reply back to the client's byte order with
+//! [`swap_fuse_reply`] before sending it. Replies are converted in place, so
+//! the server must first copy the reply's [`SendBuf`] into a contiguous
+//! buffer. Notifications are converted with [`swap_fuse_notification`].
+//!
+//! CUSE sessions aren't supported.
+//!
+//! [`FuseRequest::new`]: crate::server::FuseRequest::new
+//! [`RequestError::ForeignEndian`]: crate::server::RequestError::ForeignEndian
+//! [`SendBuf`]: crate::io::SendBuf
+
+use core::mem::size_of;
+
+use crate::internal::compat;
+use crate::kernel;
+use crate::kernel::{fuse_notify_code, fuse_opcode};
+use crate::server::{FuseLayout, RequestError};
+
+// Each layout lists the widths, in bytes, of a structure's fields. Fields
+// of type `u8`, such as names and data, aren't part of a layout.
+
+const IN_HEADER: &[u8] = &[4, 4, 8, 8, 4, 4, 4, 2, 2];
+const OUT_HEADER: &[u8] = &[4, 4, 8];
+
+const U32_U32: &[u8] = &[4, 4];
+const U64: &[u8] = &[8];
+const U64_U32_U32: &[u8] = &[8, 4, 4];
+const U64_U64_U32_U32: &[u8] = &[8, 8, 4, 4];
+
+const ATTR: &[u8] = &[8, 8, 8, 8, 8, 8, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4];
+const ATTR_OUT_HEADER: &[u8] = &[8, 4, 4];
+const ENTRY_OUT_HEADER: &[u8] = &[8, 8, 8, 8, 4, 4];
+const DIRENT: &[u8] = &[8, 8, 4, 4];
+const FILE_LOCK: &[u8] = &[8, 8, 4, 4];
+const FORGET_ONE: &[u8] = &[8, 8];
+const INIT_OUT: &[u8] = &[
+	4, 4, 4, 4, 2, 2, 4, 4, 2, 2, 4, 4, 4, 4, 4, 4, 4, 4,
+];
+const IOCTL_IN: &[u8] = &[8, 4, 4, 8, 4, 4];
+const IOCTL_OUT: &[u8] = &[4, 4, 4, 4];
+const KSTATFS: &[u8] = &[8, 8, 8, 8, 8, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4];
+const LK_IN: &[u8] = &[8, 8, 8, 8, 4, 4, 4, 4];
+const REMOVEMAPPING_ONE: &[u8] = &[8, 8];
+const READ_IN: &[u8] = &[8, 8, 4, 4, 8, 4, 4];
+const READ_IN_V7P1: &[u8] = &[8, 8, 4, 4];
+const RELEASE_IN: &[u8] = &[8, 4, 4, 8];
+const SETATTR_IN: &[u8] = &[
+	4, 4, 8, 8, 8, 8, 8, 8, 4, 4, 4, 4, 4, 4, 4, 4,
+];
+const STATX_IN: &[u8] = &[4, 4, 8, 4, 4];
+const STATX_OUT_HEADER: &[u8] = &[8, 4, 4, 8, 8];
+const STATX: &[u8] = &[
+	4, 4, 8, 4, 4, 4, 2, 2, 8, 8, 8, 8, // mask .. attributes_mask
+	8, 4, 4, 8, 4, 4, 8, 4, 4, 8, 4, 4, // atime, btime, ctime, mtime
+	4, 4, 4, 4, // rdev_major .. dev_minor
+	8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, // __spare2
+];
+
+// PendingReply {{{
+
+/// Describes how to convert the reply to a byte-swapped request.
+///
+/// A `PendingReply` is returned by [`swap_fuse_request`], and should be
+/// passed to [`swap_fuse_reply`] when the request's reply is sent.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PendingReply {
+	opcode: fuse_opcode,
+	version_minor: u32,
+	size_only: bool,
+}
+
+impl PendingReply {
+	/// Returns the opcode of the request this reply is for.
+	#[inline]
+	#[must_use]
+	pub fn opcode(&self) -> fuse_opcode {
+		self.opcode
+	}
+}
+
+// }}}
+
+// ReplyError {{{
+
+/// Errors describing why a reply can't be converted to the client's byte
+/// order.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ReplyError {
+	/// The buffer is shorter than the length in the reply header.
+	UnexpectedEof,
+
+	/// The reply body doesn't have the layout expected for its request.
+	InvalidLayout,
+
+	/// The notification has an unknown notification code.
+	UnknownNotifyCode,
+}
+
+// }}}
+
+/// Returns whether the request in `buf` was sent by a client with the
+/// opposite byte order.
+///
+/// Only the client's first request, `FUSE_INIT` or `CUSE_INIT`, can be
+/// detected. The byte order of later requests is determined by the session.
+#[must_use]
+pub fn is_foreign_endian(buf: &[u8]) -> bool {
+	if buf.len() < size_of::<kernel::fuse_in_header>() {
+		return false;
+	}
+	let opcode = fuse_opcode(u32::from_ne_bytes([
+		buf[4], buf[5], buf[6], buf[7],
+	]));
+	matches!(
+		opcode,
+		fuse_opcode::FUSE_INIT_BSWAP_RESERVED
+			| fuse_opcode::CUSE_INIT_BSWAP_RESERVED
+	)
+}
+
+/// Converts a request from a foreign-endian client to native byte order.
+///
+/// The `layout` is ignored for `FUSE_INIT` requests, which have a layout
+/// determined by their own protocol version.
+///
+/// The request is converted in place. Names and data are left as-is. On
+/// success, returns a [`PendingReply`] for use with [`swap_fuse_reply`].
+pub fn swap_fuse_request(
+	buf: &mut [u8],
+	layout: FuseLayout,
+) -> Result<PendingReply, RequestError> {
+	const HEADER_LEN: usize = size_of::<kernel::fuse_in_header>();
+
+	if buf.len() < HEADER_LEN {
+		return Err(RequestError::UnexpectedEof);
+	}
+	swap_fields(&mut buf[..HEADER_LEN], IN_HEADER);
+	let header: kernel::fuse_in_header = unsafe {
+		core::ptr::read_unaligned(buf.as_ptr().cast())
+	};
+	let msg = match buf.get_mut(..header.len as usize) {
+		Some(msg) if msg.len() >= HEADER_LEN => msg,
+		_ => return Err(RequestError::UnexpectedEof),
+	};
+
+	let ext_len = usize::from(header.total_extlen) * 8;
+	let body_len = match (msg.len() - HEADER_LEN).checked_sub(ext_len) {
+		Some(body_len) => body_len,
+		None => return Err(RequestError::InvalidExtension),
+	};
+	let (body, ext) = msg[HEADER_LEN..].split_at_mut(body_len);
+
+	let mut version_minor = layout.version_minor();
+	if header.opcode == fuse_opcode::FUSE_INIT {
+		// Every field of `fuse_init_in` is a `u32`.
+		swap_array(body, 4, body.len() / 4);
+		if body.len() >= 8 {
+			version_minor = u32::from_ne_bytes([
+				body[4], body[5], body[6], body[7],
+			]);
+		}
+	} else {
+		swap_request_body(header.opcode, body, layout)?;
+	}
+	swap_extensions(ext)?;
+
+	let size_only = match header.opcode {
+		fuse_opcode::FUSE_GETXATTR | fuse_opcode::FUSE_LISTXATTR => {
+			body.get(..4) == Some(&[0, 0, 0, 0])
+		},
+		_ => false,
+	};
+
+	Ok(PendingReply {
+		opcode: header.opcode,
+		version_minor,
+		size_only,
+	})
+}
+
+fn swap_request_body(
+	opcode: fuse_opcode,
+	body: &mut [u8],
+	layout: FuseLayout,
+) -> Result<(), RequestError> {
+	let version_minor = layout.version_minor();
+	let fields: &[u8] = match opcode {
+		fuse_opcode::FUSE_FORGET
+		| fuse_opcode::FUSE_RENAME
+		| fuse_opcode::FUSE_LINK
+		| fuse_opcode::FUSE_INTERRUPT
+		| fuse_opcode::FUSE_SYNCFS => U64,
+		fuse_opcode::FUSE_GETATTR => {
+			if version_minor >= 9 {
+				&[4, 4, 8]
+			} else {
+				&[]
+			}
+		},
+		fuse_opcode::FUSE_SETATTR => SETATTR_IN,
+		fuse_opcode::FUSE_MKNOD
+		| fuse_opcode::FUSE_CREATE
+		| fuse_opcode::FUSE_TMPFILE => {
+			if version_minor >= 12 {
+				&[4, 4, 4, 4]
+			} else {
+				U32_U32
+			}
+		},
+		fuse_opcode::FUSE_MKDIR
+		| fuse_opcode::FUSE_OPEN
+		| fuse_opcode::FUSE_OPENDIR
+		| fuse_opcode::FUSE_GETXATTR
+		| fuse_opcode::FUSE_LISTXATTR
+		| fuse_opcode::FUSE_ACCESS => U32_U32,
+		fuse_opcode::FUSE_RENAME2
+		| fuse_opcode::FUSE_FSYNC
+		| fuse_opcode::FUSE_FSYNCDIR
+		| fuse_opcode::FUSE_BMAP => U64_U32_U32,
+		fuse_opcode::FUSE_READ
+		| fuse_opcode::FUSE_WRITE
+		| fuse_opcode::FUSE_READDIR
+		| fuse_opcode::FUSE_READDIRPLUS => {
+			if version_minor >= 9 {
+				READ_IN
+			} else {
+				READ_IN_V7P1
+			}
+		},
+		fuse_opcode::FUSE_RELEASE | fuse_opcode::FUSE_RELEASEDIR => {
+			if version_minor >= 8 {
+				RELEASE_IN
+			} else {
+				U64_U32_U32
+			}
+		},
+		fuse_opcode::FUSE_FLUSH => RELEASE_IN,
+		fuse_opcode::FUSE_SETXATTR => {
+			if layout.have_setxattr_ext() {
+				&[4, 4, 4, 4]
+			} else {
+				U32_U32
+			}
+		},
+		fuse_opcode::FUSE_GETLK
+		| fuse_opcode::FUSE_SETLK
+		| fuse_opcode::FUSE_SETLKW => LK_IN,
+		fuse_opcode::FUSE_IOCTL => IOCTL_IN,
+		fuse_opcode::FUSE_POLL
+		| fuse_opcode::FUSE_LSEEK => U64_U64_U32_U32,
+		fuse_opcode::FUSE_NOTIFY_REPLY => &[8, 8, 4, 4, 8, 8],
+		fuse_opcode::FUSE_BATCH_FORGET => {
+			return swap_counted(body, U32_U32, FORGET_ONE);
+		},
+		fuse_opcode::FUSE_FALLOCATE => &[8, 8, 8, 4, 4],
+		fuse_opcode::FUSE_COPY_FILE_RANGE => &[8, 8, 8, 8, 8, 8, 8],
+		fuse_opcode::FUSE_SETUPMAPPING => &[8, 8, 8, 8, 8],
+		fuse_opcode::FUSE_REMOVEMAPPING => {
+			return swap_counted(body, &[4], REMOVEMAPPING_ONE);
+		},
+		fuse_opcode::FUSE_STATX => STATX_IN,
+		_ => &[],
+	};
+	require_fields(body, fields)?;
+	swap_fields(body, fields);
+	Ok(())
+}
+
+// Swaps a header that starts with a `u32` count, followed by an array of
+// that many items.
+fn swap_counted(
+	body: &mut [u8],
+	header_fields: &[u8],
+	item_fields: &[u8],
+) -> Result<(), RequestError> {
+	let (header, items) = split_fields(body, header_fields)?;
+	swap_fields(header, header_fields);
+	let count = u32::from_ne_bytes([
+		header[0], header[1], header[2], header[3],
+	]);
+	let item_len = fields_len(item_fields);
+	if items.len() / item_len < count as usize {
+		return Err(RequestError::UnexpectedEof);
+	}
+	for item in items.chunks_exact_mut(item_len).take(count as usize) {
+		swap_fields(item, item_fields);
+	}
+	Ok(())
+}
+
+fn swap_extensions(mut ext: &mut [u8]) -> Result<(), RequestError> {
+	const EXT_HEADER_LEN: usize = size_of::<kernel::fuse_ext_header>();
+	const MAX_NR_SECCTX: u32 = kernel::fuse_ext_type::FUSE_MAX_NR_SECCTX.0;
+	while !ext.is_empty() {
+		if ext.len() < EXT_HEADER_LEN {
+			return Err(RequestError::InvalidExtension);
+		}
+		swap_fields(&mut ext[..EXT_HEADER_LEN], U32_U32);
+		let header: kernel::fuse_ext_header = unsafe {
+			core::ptr::read_unaligned(ext.as_ptr().cast())
+		};
+		let size = header.size as usize;
+		if size < EXT_HEADER_LEN || size > ext.len() {
+			return Err(RequestError::InvalidExtension);
+		}
+		let (item, rest) = ext.split_at_mut(size);
+		let item_body = &mut item[EXT_HEADER_LEN..];
+		match header.r#type {
+			kernel::fuse_ext_type::FUSE_EXT_GROUPS => {
+				// `fuse_supp_groups` is a count followed by an array of
+				// group IDs, all of type `u32`.
+				swap_array(item_body, 4, item_body.len() / 4);
+			},
+			// Types up to `FUSE_MAX_NR_SECCTX` are security contexts, with
+			// the type being the number of contexts.
+			ext_type if ext_type.0 <= MAX_NR_SECCTX => {
+				swap_security_contexts(item_body, ext_type.0)?;
+			},
+			_ => {},
+		}
+		ext = rest;
+	}
+	Ok(())
+}
+
+fn swap_security_contexts(
+	mut buf: &mut [u8],
+	count: u32,
+) -> Result<(), RequestError> {
+	const CTX_LEN: usize = size_of::<kernel::fuse_secctx>();
+	for _ in 0..count {
+		if buf.len() < CTX_LEN {
+			return Err(RequestError::InvalidSecurityContext);
+		}
+		swap_fields(&mut buf[..CTX_LEN], U32_U32);
+		let value_len = u32::from_ne_bytes([
+			buf[0], buf[1], buf[2], buf[3],
+		]) as usize;
+
+		// Each context is a NUL-terminated name followed by its value,
+		// padded to a multiple of 8 bytes.
+		let name_len = match buf[CTX_LEN..].iter().position(|&b| b == 0) {
+			Some(nul_idx) => nul_idx + 1,
+			None => return Err(RequestError::InvalidSecurityContext),
+		};
+		let entry_len = CTX_LEN + name_len + value_len;
+		if entry_len > buf.len() {
+			return Err(RequestError::InvalidSecurityContext);
+		}
+		let padded_len = (entry_len + 7) & !7;
+		buf = buf.get_mut(padded_len..).unwrap_or(&mut []);
+	}
+	Ok(())
+}
+
+/// Converts a native-endian reply to the byte order of the client.
+///
+/// The `buf` must contain the entire reply, including its header. The
+/// `pending` value must be the result of the [`swap_fuse_request`] call for
+/// the request being replied to.
+///
+/// The contents of `buf` are unspecified if an error is returned.
+pub fn swap_fuse_reply(
+	buf: &mut [u8],
+	pending: PendingReply,
+) -> Result<(), ReplyError> {
+	let body = match split_reply(buf)? {
+		Some(body) => body,
+		None => return Ok(()),
+	};
+
+	let version_minor = pending.version_minor;
+	match pending.opcode {
+		fuse_opcode::FUSE_LOOKUP
+		| fuse_opcode::FUSE_MKNOD
+		| fuse_opcode::FUSE_MKDIR
+		| fuse_opcode::FUSE_SYMLINK
+		| fuse_opcode::FUSE_LINK => {
+			let rest = swap_entry_out(body, version_minor)?;
+			swap_exact(rest, &[])
+		},
+		fuse_opcode::FUSE_GETATTR | fuse_opcode::FUSE_SETATTR => {
+			if body.len() != compat::fuse_attr_out_len(version_minor) {
+				return Err(ReplyError::InvalidLayout);
+			}
+			let (header, attr) = body.split_at_mut(16);
+			swap_fields(header, ATTR_OUT_HEADER);
+			swap_fields(attr, ATTR);
+			Ok(())
+		},
+		fuse_opcode::FUSE_CREATE | fuse_opcode::FUSE_TMPFILE => {
+			let open_out = swap_entry_out(body, version_minor)?;
+			swap_exact(open_out, U64_U32_U32)
+		},
+		fuse_opcode::FUSE_OPEN | fuse_opcode::FUSE_OPENDIR => {
+			swap_exact(body, U64_U32_U32)
+		},
+		fuse_opcode::FUSE_WRITE
+		| fuse_opcode::FUSE_COPY_FILE_RANGE
+		| fuse_opcode::FUSE_POLL => swap_exact(body, U32_U32),
+		fuse_opcode::FUSE_STATFS => {
+			if body.len() != compat::fuse_statfs_out_len(version_minor) {
+				return Err(ReplyError::InvalidLayout);
+			}
+			swap_fields(body, KSTATFS);
+			Ok(())
+		},
+		fuse_opcode::FUSE_GETXATTR | fuse_opcode::FUSE_LISTXATTR => {
+			if pending.size_only {
+				return swap_exact(body, U32_U32);
+			}
+			Ok(())
+		},
+		fuse_opcode::FUSE_INIT => {
+			// The reply layout is determined by the server's version.
+			let minor = match body.get(4..8) {
+				Some(minor) => [minor[0], minor[1], minor[2], minor[3]],
+				None => return Err(ReplyError::InvalidLayout),
+			};
+			let init_out_len =
+				compat::fuse_init_out_len(u32::from_ne_bytes(minor));
+			if body.len() != init_out_len {
+				return Err(ReplyError::InvalidLayout);
+			}
+			swap_fields(body, INIT_OUT);
+			Ok(())
+		},
+		fuse_opcode::FUSE_GETLK => swap_exact(body, FILE_LOCK),
+		fuse_opcode::FUSE_BMAP | fuse_opcode::FUSE_LSEEK => {
+			swap_exact(body, U64)
+		},
+		fuse_opcode::FUSE_IOCTL => swap_ioctl_out(body),
+		fuse_opcode::FUSE_READDIR => swap_dirents(body, false),
+		fuse_opcode::FUSE_READDIRPLUS => swap_dirents(body, true),
+		fuse_opcode::FUSE_STATX => {
+			if body.len() != size_of::<kernel::fuse_statx_out>() {
+				return Err(ReplyError::InvalidLayout);
+			}
+			let (header, statx) = body.split_at_mut(32);
+			swap_fields(header, STATX_OUT_HEADER);
+			swap_fields(statx, STATX);
+			Ok(())
+		},
+		_ => Ok(()),
+	}
+}
+
+/// Converts a native-endian notification to the byte order of the client.
+///
+/// The `buf` must contain the entire notification, including its header.
+///
+/// The contents of `buf` are unspecified if an error is returned.
+pub fn swap_fuse_notification(buf: &mut [u8]) -> Result<(), ReplyError> {
+	const HEADER_LEN: usize = size_of::<kernel::fuse_out_header>();
+	let header = read_out_header(buf)?;
+	let fields: &[u8] = match fuse_notify_code(header.error as u32) {
+		fuse_notify_code::FUSE_NOTIFY_POLL => U64,
+		fuse_notify_code::FUSE_NOTIFY_INVAL_INODE => &[8, 8, 8],
+		fuse_notify_code::FUSE_NOTIFY_INVAL_ENTRY => U64_U32_U32,
+		fuse_notify_code::FUSE_NOTIFY_STORE
+		| fuse_notify_code::FUSE_NOTIFY_DELETE => U64_U64_U32_U32,
+		fuse_notify_code::FUSE_NOTIFY_RETRIEVE => &[8, 8, 8, 4, 4],
+		fuse_notify_code::FUSE_NOTIFY_RESEND => &[],
+		_ => return Err(ReplyError::UnknownNotifyCode),
+	};
+	let msg = &mut buf[..header.len as usize];
+	let (header, body) = msg.split_at_mut(HEADER_LEN);
+	if body.len() < fields_len(fields) {
+		return Err(ReplyError::InvalidLayout);
+	}
+	swap_fields(header, OUT_HEADER);
+	swap_fields(body, fields);
+	Ok(())
+}
+
+fn read_out_header(
+	buf: &[u8],
+) -> Result<kernel::fuse_out_header, ReplyError> {
+	const HEADER_LEN: usize = size_of::<kernel::fuse_out_header>();
+	if buf.len() < HEADER_LEN {
+		return Err(ReplyError::UnexpectedEof);
+	}
+	let header: kernel::fuse_out_header = unsafe {
+		core::ptr::read_unaligned(buf.as_ptr().cast())
+	};
+	let len = header.len as usize;
+	if len < HEADER_LEN || len > buf.len() {
+		return Err(ReplyError::UnexpectedEof);
+	}
+	Ok(header)
+}
+
+// Swaps the reply header, and returns the reply body if the reply isn't an
+// error.
+fn split_reply(buf: &mut [u8]) -> Result<Option<&mut [u8]>, ReplyError> {
+	const HEADER_LEN: usize = size_of::<kernel::fuse_out_header>();
+	let header = read_out_header(buf)?;
+	let msg = &mut buf[..header.len as usize];
+	let (header_buf, body) = msg.split_at_mut(HEADER_LEN);
+	swap_fields(header_buf, OUT_HEADER);
+	if header.error != 0 {
+		return Ok(None);
+	}
+	Ok(Some(body))
+}
+
+// Swaps a `fuse_entry_out`, and returns the remainder of the buffer.
+fn swap_entry_out(
+	buf: &mut [u8],
+	version_minor: u32,
+) -> Result<&mut [u8], ReplyError> {
+	let len = compat::fuse_entry_out_len(version_minor);
+	if buf.len() < len {
+		return Err(ReplyError::InvalidLayout);
+	}
+	let (entry_out, rest) = buf.split_at_mut(len);
+	let (header, attr) = entry_out.split_at_mut(40);
+	swap_fields(header, ENTRY_OUT_HEADER);
+	swap_fields(attr, ATTR);
+	Ok(rest)
+}
+
+fn swap_ioctl_out(buf: &mut [u8]) -> Result<(), ReplyError> {
+	const IOCTL_OUT_LEN: usize = size_of::<kernel::fuse_ioctl_out>();
+	const IOVEC_LEN: usize = size_of::<kernel::fuse_ioctl_iovec>();
+	if buf.len() < IOCTL_OUT_LEN {
+		return Err(ReplyError::InvalidLayout);
+	}
+	let ioctl_out: kernel::fuse_ioctl_out = unsafe {
+		core::ptr::read_unaligned(buf.as_ptr().cast())
+	};
+	let (ioctl_out_buf, iovecs) = buf.split_at_mut(IOCTL_OUT_LEN);
+	swap_fields(ioctl_out_buf, IOCTL_OUT);
+	if ioctl_out.flags & kernel::FUSE_IOCTL_RETRY == 0 {
+		return Ok(());
+	}
+	let count = ioctl_out.in_iovs as usize + ioctl_out.out_iovs as usize;
+	if iovecs.len() != count * IOVEC_LEN {
+		return Err(ReplyError::InvalidLayout);
+	}
+	swap_array(iovecs, 8, count * 2);
+	Ok(())
+}
+
+fn swap_dirents(mut buf: &mut [u8], plus: bool) -> Result<(), ReplyError> {
+	const DIRENT_LEN: usize = size_of::<kernel::fuse_dirent>();
+	while !buf.is_empty() {
+		if plus {
+			let minor = kernel::FUSE_KERNEL_MINOR_VERSION;
+			buf = swap_entry_out(buf, minor)?;
+		}
+		if buf.len() < DIRENT_LEN {
+			return Err(ReplyError::InvalidLayout);
+		}
+		let dirent: kernel::fuse_dirent = unsafe {
+			core::ptr::read_unaligned(buf.as_ptr().cast())
+		};
+		let dirent_len = DIRENT_LEN + dirent.namelen as usize;
+		let padded_len = (dirent_len + 7) & !7;
+		if padded_len > buf.len() {
+			return Err(ReplyError::InvalidLayout);
+		}
+		swap_fields(&mut buf[..DIRENT_LEN], DIRENT);
+		buf = &mut buf[padded_len..];
+	}
+	Ok(())
+}
+
+fn fields_len(fields: &[u8]) -> usize {
+	fields.iter().map(|&width| usize::from(width)).sum()
+}
+
+fn require_fields(buf: &[u8], fields: &[u8]) -> Result<(), RequestError> {
+	if buf.len() < fields_len(fields) {
+		return Err(RequestError::UnexpectedEof);
+	}
+	Ok(())
+}
+
+fn split_fields<'a>(
+	buf: &'a mut [u8],
+	fields: &[u8],
+) -> Result<(&'a mut [u8], &'a mut [u8]), RequestError> {
+	require_fields(buf, fields)?;
+	Ok(buf.split_at_mut(fields_len(fields)))
+}
+
+fn swap_exact(buf: &mut [u8], fields: &[u8]) -> Result<(), ReplyError> {
+	if buf.len() != fields_len(fields) {
+		return Err(ReplyError::InvalidLayout);
+	}
+	swap_fields(buf, fields);
+	Ok(())
+}
+
+// Reverses the byte order of each field, stopping at the first field that
+// doesn't fit within `buf`.
+fn swap_fields(buf: &mut [u8], fields: &[u8]) {
+	let mut offset = 0;
+	for &width in fields {
+		let end = offset + usize::from(width);
+		match buf.get_mut(offset..end) {
+			Some(field) => field.reverse(),
+			None => return,
+		}
+		offset = end;
+	}
+}
+
+fn swap_array(buf: &mut [u8], width: usize, count: usize) {
+	for field in buf.chunks_exact_mut(width).take(count) {
+		field.reverse();
+	}
+}
@@ -0,0 +1,392 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::mem::size_of;
+
+use fuse::kernel;
+use fuse::server::endian::{self, ReplyError};
+use fuse::server::{
+	CreateRequest,
+	FuseInitRequest,
+	FuseLayout,
+	FuseRequest,
+	GetattrRequest,
+	GetattrResponse,
+	RequestError,
+};
+
+use fuse_testutil as testutil;
+use fuse_testutil::{decode_request, encode_response, MessageBuilder};
+
+fn layout(minor: u32) -> FuseLayout {
+	let init_out = testutil::new!(kernel::fuse_init_out {
+		major: kernel::FUSE_KERNEL_VERSION,
+		minor: minor,
+	});
+	FuseLayout::new(&init_out).unwrap()
+}
+
+fn foreign_in_header(
+	opcode: kernel::fuse_opcode,
+	body_len: usize,
+) -> kernel::fuse_in_header {
+	let len = (size_of::<kernel::fuse_in_header>() + body_len) as u32;
+	testutil::new!(kernel::fuse_in_header {
+		len: len.swap_bytes(),
+		opcode: kernel::fuse_opcode(opcode.0.swap_bytes()),
+		unique: 0xAABBCCDDu64.swap_bytes(),
+		nodeid: 123u64.swap_bytes(),
+		uid: 1000u32.swap_bytes(),
+	})
+}
+
+#[test]
+fn foreign_init_rejected() {
+	let buf = MessageBuilder::new()
+		.push_sized(&foreign_in_header(
+			kernel::fuse_opcode::FUSE_INIT,
+			size_of::<kernel::fuse_init_in>(),
+		))
+		.push_sized(&testutil::new!(kernel::fuse_init_in {
+			major: 7u32.swap_bytes(),
+			minor: 36u32.swap_bytes(),
+		}))
+		.build_aligned();
+
+	assert!(endian::is_foreign_endian(buf.as_slice()));
+	let req_buf = buf.as_aligned_slice();
+	let err = FuseRequest::new(req_buf, layout(36)).unwrap_err();
+	assert_eq!(err, RequestError::ForeignEndian);
+}
+
+#[test]
+fn native_init_accepted() {
+	let buf = MessageBuilder::new()
+		.set_opcode(kernel::fuse_opcode::FUSE_INIT)
+		.push_sized(&7u32) // fuse_init_in::major
+		.push_sized(&1u32) // fuse_init_in::minor
+		.build_aligned();
+	assert!(!endian::is_foreign_endian(buf.as_slice()));
+}
+
+#[test]
+fn swap_init_request() {
+	let mut buf = MessageBuilder::new()
+		.push_sized(&foreign_in_header(
+			kernel::fuse_opcode::FUSE_INIT,
+			size_of::<kernel::fuse_init_in>(),
+		))
+		.push_sized(&testutil::new!(kernel::fuse_init_in {
+			major: 7u32.swap_bytes(),
+			minor: 36u32.swap_bytes(),
+			max_readahead: 9u32.swap_bytes(),
+			flags: kernel::FUSE_ASYNC_READ.swap_bytes(),
+		}))
+		.build_aligned();
+
+	let pending = endian::swap_fuse_request(buf.as_slice_mut(), layout(1))
+		.unwrap();
+	assert_eq!(pending.opcode(), kernel::fuse_opcode::FUSE_INIT);
+
+	let req = decode_request!(FuseInitRequest, buf);
+	assert_eq!(req.version().major(), 7);
+	assert_eq!(req.version().minor(), 36);
+	assert_eq!(req.max_readahead(), 9);
+	assert_eq!(req.flags(), fuse::FuseInitFlag::ASYNC_READ);
+}
+
+#[test]
+fn swap_getattr_request() {
+	let mut buf = MessageBuilder::new()
+		.push_sized(&foreign_in_header(
+			kernel::fuse_opcode::FUSE_GETATTR,
+			size_of::<kernel::fuse_getattr_in>(),
+		))
+		.push_sized(&testutil::new!(kernel::fuse_getattr_in {
+			getattr_flags: kernel::FUSE_GETATTR_FH.swap_bytes(),
+			fh: 0x1122334455667788u64.swap_bytes(),
+		}))
+		.build_aligned();
+
+	endian::swap_fuse_request(buf.as_slice_mut(), layout(9)).unwrap();
+
+	let req = decode_request!(GetattrRequest, buf, {
+		protocol_version: (7, 9),
+	});
+	assert_eq!(req.node_id(), fuse::NodeId::new(123).unwrap());
+	assert_eq!(req.handle(), Some(0x1122334455667788));
+}
+
+#[test]
+fn swap_batch_forget_request() {
+	let mut buf = MessageBuilder::new()
+		.push_sized(&foreign_in_header(
+			kernel::fuse_opcode::FUSE_BATCH_FORGET,
+			size_of::<kernel::fuse_batch_forget_in>()
+				+ size_of::<kernel::fuse_forget_one>() * 2,
+		))
+		.push_sized(&testutil::new!(kernel::fuse_batch_forget_in {
+			count: 2u32.swap_bytes(),
+		}))
+		.push_sized(&testutil::new!(kernel::fuse_forget_one {
+			nodeid: 10u64.swap_bytes(),
+			nlookup: 1u64.swap_bytes(),
+		}))
+		.push_sized(&testutil::new!(kernel::fuse_forget_one {
+			nodeid: 11u64.swap_bytes(),
+			nlookup: 2u64.swap_bytes(),
+		}))
+		.build_aligned();
+
+	endian::swap_fuse_request(buf.as_slice_mut(), layout(16)).unwrap();
+
+	let expect = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = kernel::fuse_opcode::FUSE_BATCH_FORGET;
+			h.unique = 0xAABBCCDD;
+			h.nodeid = 123;
+			h.uid = 1000;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_batch_forget_in {
+			count: 2,
+		}))
+		.push_sized(&testutil::new!(kernel::fuse_forget_one {
+			nodeid: 10,
+			nlookup: 1,
+		}))
+		.push_sized(&testutil::new!(kernel::fuse_forget_one {
+			nodeid: 11,
+			nlookup: 2,
+		}))
+		.build();
+	assert_eq!(buf.as_slice()[..expect.len()], expect);
+}
+
+#[test]
+fn swap_create_request_security_contexts() {
+	let mut header = foreign_in_header(
+		kernel::fuse_opcode::FUSE_CREATE,
+		size_of::<kernel::fuse_create_in>() + 13 + 40,
+	);
+	header.total_extlen = 5u16.swap_bytes();
+	let mut buf = MessageBuilder::new()
+		.push_sized(&header)
+		.push_sized(&testutil::new!(kernel::fuse_create_in {
+			flags: 0xFFu32.swap_bytes(),
+			mode: 0xEEu32.swap_bytes(),
+			umask: 0xDDu32.swap_bytes(),
+		}))
+		.push_bytes(b"hello.world!\x00")
+		.push_sized(&testutil::new!(kernel::fuse_secctx_header {
+			size: 40u32.swap_bytes(),
+			nr_secctx: 1u32.swap_bytes(),
+		}))
+		.push_sized(&testutil::new!(kernel::fuse_secctx {
+			size: 6u32.swap_bytes(),
+		}))
+		.push_bytes(b"security.selinux\x00")
+		.push_bytes(b"label\x00")
+		.push_bytes(&[0; 1])
+		.build_aligned();
+
+	endian::swap_fuse_request(buf.as_slice_mut(), layout(12)).unwrap();
+
+	let req = decode_request!(CreateRequest, buf, {
+		init_flags: kernel::FUSE_SECURITY_CTX,
+	});
+	assert_eq!(req.name(), "hello.world!");
+	assert_eq!(req.open_flags().get(), 0xFF);
+	assert_eq!(req.mode(), fuse::FileMode::new(0xEE));
+	assert_eq!(req.umask(), 0xDD);
+
+	let mut contexts = req.security_contexts();
+	let context = contexts.next().unwrap();
+	assert_eq!(context.name(), c"security.selinux");
+	assert_eq!(context.value(), b"label\x00");
+	assert!(contexts.next().is_none());
+}
+
+#[test]
+fn swap_request_truncated() {
+	let mut buf = MessageBuilder::new()
+		.push_sized(&foreign_in_header(kernel::fuse_opcode::FUSE_GETATTR, 4))
+		.push_sized(&0u32)
+		.build();
+
+	let err = endian::swap_fuse_request(&mut buf, layout(9)).unwrap_err();
+	assert_eq!(err, RequestError::UnexpectedEof);
+}
+
+#[test]
+fn swap_attr_reply() {
+	let mut attr = fuse::NodeAttr::new(fuse::NodeId::new(123).unwrap());
+	attr.set_size(0x1122);
+	let resp = GetattrResponse::new(attr);
+
+	let mut buf = MessageBuilder::new()
+		.push_sized(&foreign_in_header(kernel::fuse_opcode::FUSE_GETATTR, 0))
+		.build();
+	let pending = endian::swap_fuse_request(&mut buf, layout(8)).unwrap();
+
+	let mut encoded = encode_response!(&resp, {
+		protocol_version: (7, 8),
+	});
+	let native_len = encoded.len();
+	endian::swap_fuse_reply(&mut encoded, pending).unwrap();
+
+	let header_len = size_of::<kernel::fuse_out_header>();
+	assert_eq!(encoded.len(), native_len);
+	let len = (native_len as u32).swap_bytes();
+	assert_eq!(encoded[..4], len.to_ne_bytes());
+
+	let attr_offset = header_len + 16;
+	let ino = &encoded[attr_offset..attr_offset + 8];
+	assert_eq!(ino, 123u64.swap_bytes().to_ne_bytes());
+	let size = &encoded[attr_offset + 8..attr_offset + 16];
+	assert_eq!(size, 0x1122u64.swap_bytes().to_ne_bytes());
+}
+
+#[test]
+fn swap_error_reply() {
+	let mut buf = MessageBuilder::new()
+		.push_sized(&foreign_in_header(kernel::fuse_opcode::FUSE_GETATTR, 0))
+		.build();
+	let pending = endian::swap_fuse_request(&mut buf, layout(8)).unwrap();
+
+	let mut reply = MessageBuilder::new()
+		.push_sized(&testutil::new!(kernel::fuse_out_header {
+			len: 16,
+			error: -2,
+			unique: 0xAABBCCDD,
+		}))
+		.build();
+	endian::swap_fuse_reply(&mut reply, pending).unwrap();
+
+	let expect = MessageBuilder::new()
+		.push_sized(&testutil::new!(kernel::fuse_out_header {
+			len: 16u32.swap_bytes(),
+			error: (-2i32).swap_bytes(),
+			unique: 0xAABBCCDDu64.swap_bytes(),
+		}))
+		.build();
+	assert_eq!(reply, expect);
+}
+
+#[test]
+fn swap_readdir_reply() {
+	let mut buf = MessageBuilder::new()
+		.push_sized(&foreign_in_header(
+			kernel::fuse_opcode::FUSE_READDIR,
+			size_of::<kernel::fuse_read_in>(),
+		))
+		.push_sized(&kernel::fuse_read_in::new())
+		.build();
+	let pending = endian::swap_fuse_request(&mut buf, layout(9)).unwrap();
+
+	let dirent_len = size_of::<kernel::fuse_dirent>() + 8;
+	let mut reply = MessageBuilder::new()
+		.push_sized(&testutil::new!(kernel::fuse_out_header {
+			len: (16 + dirent_len * 2) as u32,
+			unique: 0xAABBCCDD,
+		}))
+		.push_sized(&testutil::new!(kernel::fuse_dirent {
+			ino: 10,
+			off: 1,
+			namelen: 5,
+			r#type: 4,
+		}))
+		.push_bytes(b"hello\x00\x00\x00")
+		.push_sized(&testutil::new!(kernel::fuse_dirent {
+			ino: 11,
+			off: 2,
+			namelen: 8,
+			r#type: 8,
+		}))
+		.push_bytes(b"world.tx")
+		.build();
+	endian::swap_fuse_reply(&mut reply, pending).unwrap();
+
+	let expect = MessageBuilder::new()
+		.push_sized(&testutil::new!(kernel::fuse_out_header {
+			len: ((16 + dirent_len * 2) as u32).swap_bytes(),
+			unique: 0xAABBCCDDu64.swap_bytes(),
+		}))
+		.push_sized(&testutil::new!(kernel::fuse_dirent {
+			ino: 10u64.swap_bytes(),
+			off: 1u64.swap_bytes(),
+			namelen: 5u32.swap_bytes(),
+			r#type: 4u32.swap_bytes(),
+		}))
+		.push_bytes(b"hello\x00\x00\x00")
+		.push_sized(&testutil::new!(kernel::fuse_dirent {
+			ino: 11u64.swap_bytes(),
+			off: 2u64.swap_bytes(),
+			namelen: 8u32.swap_bytes(),
+			r#type: 8u32.swap_bytes(),
+		}))
+		.push_bytes(b"world.tx")
+		.build();
+	assert_eq!(reply, expect);
+}
+
+#[test]
+fn swap_reply_invalid_layout() {
+	let mut buf = MessageBuilder::new()
+		.push_sized(&foreign_in_header(kernel::fuse_opcode::FUSE_OPEN, 8))
+		.push_sized(&kernel::fuse_open_in::new())
+		.build();
+	let pending = endian::swap_fuse_request(&mut buf, layout(9)).unwrap();
+
+	let mut reply = MessageBuilder::new()
+		.push_sized(&testutil::new!(kernel::fuse_out_header {
+			len: 16 + 8,
+			unique: 0xAABBCCDD,
+		}))
+		.push_sized(&0u64)
+		.build();
+	let err = endian::swap_fuse_reply(&mut reply, pending).unwrap_err();
+	assert_eq!(err, ReplyError::InvalidLayout);
+}
+
+#[test]
+fn swap_notification() {
+	let mut notify = MessageBuilder::new()
+		.push_sized(&testutil::new!(kernel::fuse_out_header {
+			len: 16 + 24,
+			error: kernel::fuse_notify_code::FUSE_NOTIFY_INVAL_INODE.0 as i32,
+		}))
+		.push_sized(&testutil::new!(kernel::fuse_notify_inval_inode_out {
+			ino: 123,
+			off: 0,
+			len: -1,
+		}))
+		.build();
+	endian::swap_fuse_notification(&mut notify).unwrap();
+
+	let code = kernel::fuse_notify_code::FUSE_NOTIFY_INVAL_INODE.0 as i32;
+	let expect = MessageBuilder::new()
+		.push_sized(&testutil::new!(kernel::fuse_out_header {
+			len: 40u32.swap_bytes(),
+			error: code.swap_bytes(),
+		}))
+		.push_sized(&testutil::new!(kernel::fuse_notify_inval_inode_out {
+			ino: 123u64.swap_bytes(),
+			off: 0,
+			len: (-1i64).swap_bytes(),
+		}))
+		.build();
+	assert_eq!(notify, expect);
+}
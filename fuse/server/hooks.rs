@@ -0,0 +1,321 @@
+// Copyright 2023 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Observation of requests and replies.
+//!
+//! A [`Hooks`] implementation receives callbacks as a server handles
+//! requests, so that metrics and logging can be added to a server without
+//! changing each of its handlers.
+//!
+//! Requests and replies are observed by wrapping the server's socket in a
+//! [`HooksSocket`]. The time taken to handle each request is reported by
+//! the code that dispatches requests to handlers, because measuring it
+//! requires a clock. The `fuse-std` crate provides a handlers wrapper that
+//! does so.
+//!
+//! When the `tracing` feature is enabled, [`TracingHooks`] reports requests
+//! and replies as [`tracing`] events, and the `*_serve_local` functions
+//! dispatch each request within a span.
+
+use core::mem::size_of;
+use core::num::{NonZeroI32, NonZeroU64};
+use core::time::Duration;
+
+use crate::io::SendBuf;
+use crate::kernel;
+use crate::server;
+
+// Hooks {{{
+
+/// Callbacks for observing the requests and replies of a server.
+///
+/// Every method has a default implementation that does nothing. Hooks may
+/// be called from multiple threads, and should return quickly.
+pub trait Hooks {
+	/// Called after a request has been received.
+	fn request(&self, header: &crate::RequestHeader) {
+		let _ = header;
+	}
+
+	/// Called after a reply has been sent.
+	///
+	/// The `error` is `None` for a successful reply. The `len` is the length
+	/// of the reply, including its header.
+	///
+	/// The opcode of the request isn't known when the reply is sent. Hooks
+	/// that need it can store the opcode when the request is received.
+	fn reply(
+		&self,
+		request_id: NonZeroU64,
+		error: Option<crate::Error>,
+		len: usize,
+	) {
+		let _ = (request_id, error, len);
+	}
+
+	/// Called after the handler for a request has returned.
+	///
+	/// The `duration` is the time spent in the handler. Handlers that reply
+	/// asynchronously may return before their reply is sent.
+	fn request_completed(
+		&self,
+		header: &crate::RequestHeader,
+		duration: Duration,
+	) {
+		let _ = (header, duration);
+	}
+}
+
+impl<H: Hooks> Hooks for &H {
+	fn request(&self, header: &crate::RequestHeader) {
+		(*self).request(header)
+	}
+
+	fn reply(
+		&self,
+		request_id: NonZeroU64,
+		error: Option<crate::Error>,
+		len: usize,
+	) {
+		(*self).reply(request_id, error, len)
+	}
+
+	fn request_completed(
+		&self,
+		header: &crate::RequestHeader,
+		duration: Duration,
+	) {
+		(*self).request_completed(header, duration)
+	}
+}
+
+// }}}
+
+// HooksSocket {{{
+
+/// A server socket that reports its requests and replies to a [`Hooks`].
+///
+/// Received messages that are too short to contain a request header are
+/// not reported. Notifications, which have a request ID of zero, are not
+/// reported as replies.
+pub struct HooksSocket<S, H> {
+	socket: S,
+	hooks: H,
+}
+
+impl<S, H> HooksSocket<S, H> {
+	/// Creates a new `HooksSocket` wrapping the given socket.
+	#[must_use]
+	pub fn new(socket: S, hooks: H) -> HooksSocket<S, H> {
+		Self { socket, hooks }
+	}
+
+	/// Returns a reference to the wrapped socket.
+	#[inline]
+	#[must_use]
+	pub fn socket(&self) -> &S {
+		&self.socket
+	}
+
+	/// Returns a reference to the hooks.
+	#[inline]
+	#[must_use]
+	pub fn hooks(&self) -> &H {
+		&self.hooks
+	}
+}
+
+impl<S: server::Socket, H: Hooks> HooksSocket<S, H> {
+	fn report_request(&self, buf: &[u8]) {
+		const HEADER_LEN: usize = size_of::<kernel::fuse_in_header>();
+		if buf.len() < HEADER_LEN {
+			return;
+		}
+		let raw = unsafe {
+			buf.as_ptr().cast::<kernel::fuse_in_header>().read_unaligned()
+		};
+		if raw.unique == 0 || raw.len == 0 {
+			return;
+		}
+		self.hooks.request(&crate::RequestHeader(raw));
+	}
+
+	fn report_reply(&self, chunks: &[&[u8]]) {
+		const HEADER_LEN: usize = size_of::<kernel::fuse_out_header>();
+		let mut header_buf = [0u8; HEADER_LEN];
+		let mut copied = 0;
+		let mut len = 0;
+		for chunk in chunks {
+			let n = core::cmp::min(chunk.len(), HEADER_LEN - copied);
+			header_buf[copied..copied + n].copy_from_slice(&chunk[..n]);
+			copied += n;
+			len += chunk.len();
+		}
+		if copied < HEADER_LEN {
+			return;
+		}
+		let raw = unsafe {
+			let ptr = header_buf.as_ptr().cast::<kernel::fuse_out_header>();
+			ptr.read_unaligned()
+		};
+		if let Some(request_id) = NonZeroU64::new(raw.unique) {
+			let error = NonZeroI32::new(raw.error).map(crate::Error);
+			self.hooks.reply(request_id, error, len);
+		}
+	}
+}
+
+impl<S: server::Socket, H: Hooks> server::Socket for HooksSocket<S, H> {
+	type Error = S::Error;
+
+	fn recv(
+		&self,
+		buf: &mut [u8],
+	) -> Result<usize, server::RecvError<S::Error>> {
+		let len = self.socket.recv(buf)?;
+		self.report_request(&buf[..len]);
+		Ok(len)
+	}
+
+	fn send(&self, buf: SendBuf) -> Result<(), server::SendError<S::Error>> {
+		let (chunks, chunks_len) = copy_chunks(&buf);
+		self.socket.send(buf)?;
+		self.report_reply(&chunks[..chunks_len]);
+		Ok(())
+	}
+
+	fn can_send_fd(&self) -> bool {
+		self.socket.can_send_fd()
+	}
+
+	fn send_with_fd(
+		&self,
+		buf: SendBuf,
+		fd: i32,
+	) -> Result<(), server::SendError<S::Error>> {
+		let (chunks, chunks_len) = copy_chunks(&buf);
+		self.socket.send_with_fd(buf, fd)?;
+		self.report_reply(&chunks[..chunks_len]);
+		Ok(())
+	}
+}
+
+impl<S, H> server::CuseSocket for HooksSocket<S, H>
+where
+	S: server::CuseSocket,
+	H: Hooks,
+{
+}
+
+impl<S, H> server::FuseSocket for HooksSocket<S, H>
+where
+	S: server::FuseSocket,
+	H: Hooks,
+{
+}
+
+impl<S, H> server::FuseUnmount for HooksSocket<S, H>
+where
+	S: server::FuseUnmount,
+	H: Hooks,
+{
+	fn unmount(&self) -> Result<(), S::Error> {
+		self.socket.unmount()
+	}
+}
+
+fn copy_chunks<'a>(
+	buf: &SendBuf<'a>,
+) -> ([&'a [u8]; SendBuf::MAX_CHUNKS_LEN], usize) {
+	let mut chunks: [&[u8]; SendBuf::MAX_CHUNKS_LEN] =
+		[&[]; SendBuf::MAX_CHUNKS_LEN];
+	let chunks_len = buf.chunks_len();
+	chunks[..chunks_len].copy_from_slice(buf.chunks());
+	(chunks, chunks_len)
+}
+
+// }}}
+
+// TracingHooks {{{
+
+/// A [`Hooks`] implementation that emits [`tracing`] events.
+///
+/// Received requests, successful replies, and completed requests are
+/// reported at the `TRACE` level. Error replies are reported at the `DEBUG`
+/// level.
+#[cfg(feature = "tracing")]
+#[derive(Clone, Copy, Debug)]
+#[allow(clippy::exhaustive_structs)]
+pub struct TracingHooks;
+
+#[cfg(feature = "tracing")]
+impl Hooks for TracingHooks {
+	fn request(&self, header: &crate::RequestHeader) {
+		tracing::trace!(
+			unique = header.request_id().get(),
+			opcode = header.opcode().0,
+			node_id = header.raw().nodeid,
+			len = header.request_len().get(),
+			"received request",
+		);
+	}
+
+	fn reply(
+		&self,
+		request_id: NonZeroU64,
+		error: Option<crate::Error>,
+		len: usize,
+	) {
+		match error {
+			None => tracing::trace!(
+				unique = request_id.get(),
+				len,
+				"sent reply",
+			),
+			Some(err) => tracing::debug!(
+				unique = request_id.get(),
+				error = err.0.get(),
+				len,
+				"sent error reply",
+			),
+		}
+	}
+
+	fn request_completed(
+		&self,
+		header: &crate::RequestHeader,
+		duration: Duration,
+	) {
+		tracing::trace!(
+			unique = header.request_id().get(),
+			opcode = header.opcode().0,
+			?duration,
+			"completed request",
+		);
+	}
+}
+
+#[cfg(feature = "tracing")]
+pub(crate) fn request_span(header: &crate::RequestHeader) -> tracing::Span {
+	tracing::debug_span!(
+		"fuse_request",
+		unique = header.request_id().get(),
+		opcode = header.opcode().0,
+		node_id = header.raw().nodeid,
+	)
+}
+
+// }}}
@@ -0,0 +1,144 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::cell::RefCell;
+use core::mem::size_of;
+use core::num::NonZeroU64;
+
+use fuse::kernel;
+use fuse::kernel::fuse_opcode;
+use fuse::os::OsError;
+use fuse::server;
+use fuse::testing::SocketPair;
+
+use fuse_testutil as testutil;
+use fuse_testutil::MessageBuilder;
+
+#[derive(Debug, PartialEq)]
+enum Event {
+	Request { request_id: u64, opcode: u32 },
+	Reply { request_id: u64, error: i32, len: usize },
+}
+
+#[derive(Default)]
+struct RecordingHooks {
+	events: RefCell<Vec<Event>>,
+}
+
+impl server::Hooks for RecordingHooks {
+	fn request(&self, header: &fuse::RequestHeader) {
+		self.events.borrow_mut().push(Event::Request {
+			request_id: header.request_id().get(),
+			opcode: header.opcode().0,
+		});
+	}
+
+	fn reply(
+		&self,
+		request_id: NonZeroU64,
+		error: Option<fuse::Error>,
+		len: usize,
+	) {
+		self.events.borrow_mut().push(Event::Reply {
+			request_id: request_id.get(),
+			error: error.map_or(0, |err| err.0.get()),
+			len,
+		});
+	}
+}
+
+fn init_request() -> Vec<u8> {
+	MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_opcode::FUSE_INIT;
+			h.unique = 1;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_init_in {
+			major: kernel::FUSE_KERNEL_VERSION,
+			minor: kernel::FUSE_KERNEL_MINOR_VERSION,
+			max_readahead: 4096,
+		}))
+		.build()
+}
+
+fn getattr_request(request_id: u64) -> Vec<u8> {
+	MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_opcode::FUSE_GETATTR;
+			h.unique = request_id;
+			h.nodeid = fuse::NodeId::ROOT.get();
+		})
+		.push_sized(&kernel::fuse_getattr_in::new())
+		.build()
+}
+
+#[test]
+fn hooks_socket_reports_requests_and_replies() {
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	let kernel_socket = pair.kernel();
+
+	kernel_socket.push_request(&init_request()).unwrap();
+	kernel_socket.push_request(&getattr_request(2)).unwrap();
+
+	let hooks = RecordingHooks::default();
+	let socket = server::HooksSocket::new(pair.server(), &hooks);
+	let conn = server::FuseConnection::connect(socket, |_, _| {}).unwrap();
+	let mut buf = fuse::io::MinReadBuffer::new();
+	while let Some(request) = conn.recv(buf.as_aligned_slice_mut()).unwrap() {
+		conn.reply(request.id()).err(OsError::NOT_FOUND).unwrap();
+	}
+
+	let mut reply = [0u8; 512];
+	let init_reply_len = kernel_socket.pop_reply(&mut reply).unwrap();
+
+	let out_header_len = size_of::<kernel::fuse_out_header>();
+	assert_eq!(*hooks.events.borrow(), [
+		Event::Request {
+			request_id: 1,
+			opcode: fuse_opcode::FUSE_INIT.0,
+		},
+		Event::Reply {
+			request_id: 1,
+			error: 0,
+			len: init_reply_len,
+		},
+		Event::Request {
+			request_id: 2,
+			opcode: fuse_opcode::FUSE_GETATTR.0,
+		},
+		Event::Reply {
+			request_id: 2,
+			error: OsError::NOT_FOUND.0.get(),
+			len: out_header_len,
+		},
+	]);
+}
+
+#[test]
+fn hooks_socket_ignores_short_messages() {
+	let mut request_buf = [0u8; 64];
+	let mut reply_buf = [0u8; 0];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	pair.kernel().push_request(&[1; 8]).unwrap();
+
+	let hooks = RecordingHooks::default();
+	let socket = server::HooksSocket::new(pair.server(), &hooks);
+	let mut buf = [0u8; 64];
+	assert_eq!(server::Socket::recv(&socket, &mut buf), Ok(8));
+	assert!(hooks.events.borrow().is_empty());
+}
@@ -0,0 +1,236 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Per-connection statistics.
+//!
+//! Each [`FuseConnection`] and [`CuseConnection`] counts the requests it
+//! receives and the replies it sends. The counters are always enabled, and
+//! are cheap enough that servers which don't read them won't notice their
+//! cost. Servers that need more detail, such as latency histograms, should
+//! use [`Hooks`] instead.
+//!
+//! [`FuseConnection`]: crate::server::FuseConnection
+//! [`CuseConnection`]: crate::server::CuseConnection
+//! [`Hooks`]: crate::server::Hooks
+
+use core::fmt;
+
+use crate::kernel::fuse_opcode;
+
+// Requests with an opcode of `OPCODE_COUNT` or greater, such as `CUSE_INIT`,
+// are counted together.
+const OPCODE_COUNT: usize = 64;
+
+// Counter {{{
+
+// Counters are updated with relaxed atomic operations. Targets without
+// 64-bit atomics don't collect statistics, and all counters read as zero.
+#[cfg(target_has_atomic = "64")]
+struct Counter(core::sync::atomic::AtomicU64);
+
+#[cfg(target_has_atomic = "64")]
+impl Counter {
+	const fn new() -> Counter {
+		Counter(core::sync::atomic::AtomicU64::new(0))
+	}
+
+	fn add(&self, n: u64) {
+		self.0.fetch_add(n, core::sync::atomic::Ordering::Relaxed);
+	}
+
+	fn get(&self) -> u64 {
+		self.0.load(core::sync::atomic::Ordering::Relaxed)
+	}
+}
+
+#[cfg(not(target_has_atomic = "64"))]
+struct Counter;
+
+#[cfg(not(target_has_atomic = "64"))]
+impl Counter {
+	const fn new() -> Counter {
+		Counter
+	}
+
+	fn add(&self, n: u64) {
+		let _ = n;
+	}
+
+	fn get(&self) -> u64 {
+		0
+	}
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: Counter = Counter::new();
+
+// }}}
+
+// ConnectionStats {{{
+
+/// Counters of the requests and replies of a connection.
+///
+/// Requests are counted when they're received by the connection's `recv`
+/// method, including the `FUSE_INIT` or `CUSE_INIT` request of the
+/// connection handshake. Requests received by reading from the socket
+/// directly aren't counted. Replies are counted when they're sent by a reply
+/// sender obtained from the connection's `reply` method.
+///
+/// Counters are updated with relaxed atomic operations, so values read
+/// while requests are in flight may be slightly inconsistent between
+/// counters. On targets without 64-bit atomics, all counters are zero.
+pub struct ConnectionStats {
+	requests: [Counter; OPCODE_COUNT],
+	other_requests: Counter,
+	bytes_received: Counter,
+	bytes_sent: Counter,
+	replies: Counter,
+	error_replies: Counter,
+	notifications: Counter,
+	errors: Counter,
+}
+
+impl ConnectionStats {
+	pub(crate) const fn new() -> ConnectionStats {
+		ConnectionStats {
+			requests: [ZERO; OPCODE_COUNT],
+			other_requests: ZERO,
+			bytes_received: ZERO,
+			bytes_sent: ZERO,
+			replies: ZERO,
+			error_replies: ZERO,
+			notifications: ZERO,
+			errors: ZERO,
+		}
+	}
+
+	/// Returns the total number of requests received.
+	#[must_use]
+	pub fn total_requests(&self) -> u64 {
+		let requests = self.requests.iter().map(Counter::get);
+		requests.sum::<u64>() + self.other_requests.get()
+	}
+
+	/// Returns the number of requests received with the given opcode.
+	///
+	/// Opcodes of 64 or greater, such as `CUSE_INIT`, don't have their own
+	/// counter and are counted by [`ConnectionStats::other_requests`]. This
+	/// method returns zero for them.
+	#[must_use]
+	pub fn requests(&self, opcode: fuse_opcode) -> u64 {
+		match self.requests.get(opcode.0 as usize) {
+			Some(counter) => counter.get(),
+			None => 0,
+		}
+	}
+
+	/// Returns the number of requests received with an opcode that doesn't
+	/// have its own counter.
+	#[must_use]
+	pub fn other_requests(&self) -> u64 {
+		self.other_requests.get()
+	}
+
+	/// Returns the total length of the requests received, in bytes.
+	#[must_use]
+	pub fn bytes_received(&self) -> u64 {
+		self.bytes_received.get()
+	}
+
+	/// Returns the total length of the replies and notifications sent, in
+	/// bytes.
+	#[must_use]
+	pub fn bytes_sent(&self) -> u64 {
+		self.bytes_sent.get()
+	}
+
+	/// Returns the number of replies sent, including error replies.
+	#[must_use]
+	pub fn replies(&self) -> u64 {
+		self.replies.get()
+	}
+
+	/// Returns the number of error replies sent.
+	#[must_use]
+	pub fn error_replies(&self) -> u64 {
+		self.error_replies.get()
+	}
+
+	/// Returns the number of notifications sent.
+	#[must_use]
+	pub fn notifications(&self) -> u64 {
+		self.notifications.get()
+	}
+
+	/// Returns the number of requests that couldn't be received or decoded,
+	/// and of replies or notifications that couldn't be sent.
+	///
+	/// A non-blocking socket with no request available, or a connection
+	/// closed by the client, isn't counted as an error.
+	#[must_use]
+	pub fn errors(&self) -> u64 {
+		self.errors.get()
+	}
+
+	pub(crate) fn record_request(&self, len: usize, opcode: fuse_opcode) {
+		self.bytes_received.add(len as u64);
+		match self.requests.get(opcode.0 as usize) {
+			Some(counter) => counter.add(1),
+			None => self.other_requests.add(1),
+		}
+	}
+
+	pub(crate) fn record_error(&self) {
+		self.errors.add(1);
+	}
+
+	pub(crate) fn record_reply(&self, len: usize, error: i32, sent: bool) {
+		if !sent {
+			self.errors.add(1);
+			return;
+		}
+		self.bytes_sent.add(len as u64);
+		self.replies.add(1);
+		if error != 0 {
+			self.error_replies.add(1);
+		}
+	}
+
+	pub(crate) fn record_notification(&self, len: usize, sent: bool) {
+		if !sent {
+			self.errors.add(1);
+			return;
+		}
+		self.bytes_sent.add(len as u64);
+		self.notifications.add(1);
+	}
+}
+
+impl fmt::Debug for ConnectionStats {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("ConnectionStats")
+			.field("total_requests", &self.total_requests())
+			.field("bytes_received", &self.bytes_received())
+			.field("bytes_sent", &self.bytes_sent())
+			.field("replies", &self.replies())
+			.field("error_replies", &self.error_replies())
+			.field("notifications", &self.notifications())
+			.field("errors", &self.errors())
+			.finish()
+	}
+}
+
+// }}}
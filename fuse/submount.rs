@@ -0,0 +1,183 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::fmt;
+
+use crate::NodeId;
+
+// Inode numbers are stored in the low bits of a node ID, and the device
+// index (plus one) in the high bits.
+const INODE_BITS: u32 = 48;
+const INODE_MASK: u64 = (1 << INODE_BITS) - 1;
+const MAX_DEVICES: usize = (1 << (64 - INODE_BITS)) - 1;
+
+// SubmountDevicesError {{{
+
+/// Errors returned by [`SubmountDevices::node_id`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum SubmountDevicesError {
+	/// The device isn't yet known, and there is no space to add it.
+	CapacityExceeded,
+
+	/// The inode number is too large to be stored in a node ID.
+	InodeTooLarge(u64),
+}
+
+impl fmt::Display for SubmountDevicesError {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::CapacityExceeded => fmt.write_str("too many devices"),
+			Self::InodeTooLarge(inode) => write!(
+				fmt,
+				"inode number {} exceeds the maximum of {}",
+				inode,
+				INODE_MASK,
+			),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl core::error::Error for SubmountDevicesError {}
+
+// }}}
+
+// SubmountDevices {{{
+
+/// Allocates node IDs for a filesystem that spans up to `N` devices.
+///
+/// Servers that export an existing directory tree, such as `virtiofsd`,
+/// may find that the tree contains mount points of other filesystems.
+/// Inode numbers are only unique within a single filesystem, so they can't
+/// be used directly as node IDs. `SubmountDevices` assigns each device
+/// (`st_dev`) an index, and combines it with the inode number (`st_ino`) to
+/// produce a node ID that is unique within the connection.
+///
+/// The device index is stored in the upper 16 bits of the node ID, so
+/// inode numbers must be less than 2<sup>48</sup>. Node IDs produced by
+/// `SubmountDevices` are never equal to [`NodeId::ROOT`], which the server
+/// should continue to use for the root of the exported tree.
+///
+/// When the [`SUBMOUNTS`] init flag has been negotiated, the server should
+/// also mark each directory whose device differs from that of its parent as
+/// a submount root. The client then mounts it as a separate filesystem with
+/// its own `st_dev`, so that tools such as `find -xdev` treat it as a mount
+/// point.
+///
+/// ```
+/// # fn f() -> Result<(), fuse::SubmountDevicesError> {
+/// # let (parent_dev, dev, ino) = (1, 2, 100);
+/// let mut devices = fuse::SubmountDevices::<16>::new();
+/// let node_id = devices.node_id(dev, ino)?;
+/// let mut attr = fuse::NodeAttr::new(node_id);
+/// attr.set_flag_submount(dev != parent_dev);
+/// assert_eq!(devices.get(node_id), Some((dev, ino)));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`SUBMOUNTS`]: crate::FuseInitFlag::SUBMOUNTS
+pub struct SubmountDevices<const N: usize> {
+	devices: [u64; N],
+	len: usize,
+}
+
+impl<const N: usize> SubmountDevices<N> {
+	/// Creates a `SubmountDevices` with no known devices.
+	#[must_use]
+	pub const fn new() -> SubmountDevices<N> {
+		Self {
+			devices: [0; N],
+			len: 0,
+		}
+	}
+
+	/// Returns the number of known devices.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Returns `true` if no devices are known.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Returns the node ID for an inode of the given device.
+	///
+	/// The device is added if it isn't already known.
+	///
+	/// # Errors
+	///
+	/// Returns [`SubmountDevicesError::InodeTooLarge`] if the inode number
+	/// doesn't fit in a node ID, or [`SubmountDevicesError::CapacityExceeded`]
+	/// if the device would exceed the capacity of `N` devices.
+	pub fn node_id(
+		&mut self,
+		device: u64,
+		inode: u64,
+	) -> Result<NodeId, SubmountDevicesError> {
+		if inode > INODE_MASK {
+			return Err(SubmountDevicesError::InodeTooLarge(inode));
+		}
+		let known = &self.devices[..self.len];
+		let index = match known.iter().position(|&dev| dev == device) {
+			Some(index) => index,
+			None => {
+				if self.len == N || self.len == MAX_DEVICES {
+					return Err(SubmountDevicesError::CapacityExceeded);
+				}
+				self.devices[self.len] = device;
+				self.len += 1;
+				self.len - 1
+			},
+		};
+		let bits = ((index as u64 + 1) << INODE_BITS) | inode;
+		Ok(unsafe { NodeId::new_unchecked(bits) })
+	}
+
+	/// Returns the device and inode number of a node ID.
+	///
+	/// Returns `None` if the node ID wasn't produced by this
+	/// `SubmountDevices`.
+	#[must_use]
+	pub fn get(&self, node_id: NodeId) -> Option<(u64, u64)> {
+		let bits = node_id.get();
+		let index = (bits >> INODE_BITS) as usize;
+		if index == 0 || index > self.len {
+			return None;
+		}
+		Some((self.devices[index - 1], bits & INODE_MASK))
+	}
+}
+
+impl<const N: usize> Default for SubmountDevices<N> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<const N: usize> fmt::Debug for SubmountDevices<N> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("SubmountDevices")
+			.field("devices", &&self.devices[..self.len])
+			.finish()
+	}
+}
+
+// }}}
@@ -0,0 +1,593 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Utilities for testing FUSE servers without mounting a filesystem.
+//!
+//! A [`SocketPair`] connects a server socket to a simulated kernel. Tests
+//! queue requests on the [`KernelSocket`], run the server code under test
+//! against the [`ServerSocket`], and then inspect the replies. This allows
+//! request handlers to be tested without a kernel or elevated privileges.
+//!
+//! The sockets are single-threaded and never block. Once every queued
+//! request has been received, the server socket reports that the connection
+//! has been closed, so a server's main loop returns normally at the end of
+//! each script.
+//!
+//! Requests for a [`KernelSocket`], or for decoding directly with
+//! [`server::FuseRequest::new`], can be encoded with a [`RequestBuilder`].
+
+use core::cell::RefCell;
+use core::ffi;
+use core::mem::size_of;
+use core::num::NonZeroU64;
+
+use crate::client;
+use crate::internal::compat;
+use crate::io::{AlignedSlice, AlignedSliceMut, SendBuf};
+use crate::kernel;
+use crate::kernel::fuse_opcode;
+use crate::server;
+
+const FRAME_LEN_SIZE: usize = size_of::<u32>();
+
+// SocketError {{{
+
+/// Errors that may be encountered by the sockets of a [`SocketPair`].
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SocketError {
+	/// No messages are queued.
+	Empty,
+
+	/// The queue's buffer doesn't have enough free space for the message.
+	QueueFull,
+
+	/// The receive buffer is too small for the next queued message.
+	///
+	/// The message remains queued.
+	BufferTooSmall,
+}
+
+// }}}
+
+// Queue {{{
+
+struct Queue<'a> {
+	buf: &'a mut [u8],
+	start: usize,
+	end: usize,
+}
+
+impl<'a> Queue<'a> {
+	fn new(buf: &'a mut [u8]) -> Self {
+		Self { buf, start: 0, end: 0 }
+	}
+
+	fn is_empty(&self) -> bool {
+		self.start == self.end
+	}
+
+	fn push(&mut self, chunks: &[&[u8]]) -> Result<(), SocketError> {
+		let msg_len: usize = chunks.iter().map(|chunk| chunk.len()).sum();
+		let frame_len = match u32::try_from(msg_len) {
+			Ok(len) => len,
+			Err(_) => return Err(SocketError::QueueFull),
+		};
+		let needed = FRAME_LEN_SIZE + msg_len;
+		if self.buf.len() - self.end < needed {
+			self.buf.copy_within(self.start..self.end, 0);
+			self.end -= self.start;
+			self.start = 0;
+			if self.buf.len() - self.end < needed {
+				return Err(SocketError::QueueFull);
+			}
+		}
+
+		let mut offset = self.end;
+		let frame_end = offset + FRAME_LEN_SIZE;
+		self.buf[offset..frame_end].copy_from_slice(&frame_len.to_ne_bytes());
+		offset = frame_end;
+		for chunk in chunks {
+			self.buf[offset..offset + chunk.len()].copy_from_slice(chunk);
+			offset += chunk.len();
+		}
+		self.end = offset;
+		Ok(())
+	}
+
+	fn pop(&mut self, out: &mut [u8]) -> Result<usize, SocketError> {
+		if self.is_empty() {
+			return Err(SocketError::Empty);
+		}
+		let mut frame_len = [0u8; FRAME_LEN_SIZE];
+		let msg_start = self.start + FRAME_LEN_SIZE;
+		frame_len.copy_from_slice(&self.buf[self.start..msg_start]);
+		let msg_len = u32::from_ne_bytes(frame_len) as usize;
+		if msg_len > out.len() {
+			return Err(SocketError::BufferTooSmall);
+		}
+		let msg_end = msg_start + msg_len;
+		out[..msg_len].copy_from_slice(&self.buf[msg_start..msg_end]);
+		self.start = msg_end;
+		if self.is_empty() {
+			self.start = 0;
+			self.end = 0;
+		}
+		Ok(msg_len)
+	}
+}
+
+// }}}
+
+// SocketPair {{{
+
+/// A connected pair of in-memory sockets.
+///
+/// Messages are queued in buffers provided by the caller. Each queued
+/// message uses four bytes of buffer space in addition to its own length.
+pub struct SocketPair<'a> {
+	requests: RefCell<Queue<'a>>,
+	replies: RefCell<Queue<'a>>,
+}
+
+impl<'a> SocketPair<'a> {
+	/// Creates a new `SocketPair` with the given queue buffers.
+	///
+	/// Requests sent by the kernel socket are stored in `request_buf`, and
+	/// replies sent by the server socket are stored in `reply_buf`.
+	#[must_use]
+	pub fn new(
+		request_buf: &'a mut [u8],
+		reply_buf: &'a mut [u8],
+	) -> SocketPair<'a> {
+		Self {
+			requests: RefCell::new(Queue::new(request_buf)),
+			replies: RefCell::new(Queue::new(reply_buf)),
+		}
+	}
+
+	/// Returns the socket used by the server under test.
+	#[inline]
+	#[must_use]
+	pub fn server(&self) -> ServerSocket<'_, 'a> {
+		ServerSocket { pair: self }
+	}
+
+	/// Returns the socket used to simulate the kernel.
+	#[inline]
+	#[must_use]
+	pub fn kernel(&self) -> KernelSocket<'_, 'a> {
+		KernelSocket { pair: self }
+	}
+}
+
+// }}}
+
+// ServerSocket {{{
+
+/// The server end of a [`SocketPair`].
+///
+/// Receiving from a `ServerSocket` when no requests are queued fails with
+/// [`server::RecvError::ConnectionClosed`].
+#[derive(Clone, Copy)]
+pub struct ServerSocket<'b, 'a> {
+	pair: &'b SocketPair<'a>,
+}
+
+impl server::Socket for ServerSocket<'_, '_> {
+	type Error = SocketError;
+
+	fn recv(
+		&self,
+		buf: &mut [u8],
+	) -> Result<usize, server::RecvError<SocketError>> {
+		match self.pair.requests.borrow_mut().pop(buf) {
+			Ok(len) => Ok(len),
+			Err(SocketError::Empty) => {
+				Err(server::RecvError::ConnectionClosed(SocketError::Empty))
+			},
+			Err(err) => Err(server::RecvError::Other(err)),
+		}
+	}
+
+	fn send(&self, buf: SendBuf) -> Result<(), server::SendError<SocketError>> {
+		let mut replies = self.pair.replies.borrow_mut();
+		replies.push(buf.chunks()).map_err(server::SendError::Other)
+	}
+}
+
+impl server::CuseSocket for ServerSocket<'_, '_> {}
+
+impl server::FuseSocket for ServerSocket<'_, '_> {}
+
+// }}}
+
+// KernelSocket {{{
+
+/// The kernel end of a [`SocketPair`].
+///
+/// Requests may be queued with [`KernelSocket::push_request`] and replies
+/// received with [`KernelSocket::pop_reply`]. A `KernelSocket` also
+/// implements the client socket traits.
+#[derive(Clone, Copy)]
+pub struct KernelSocket<'b, 'a> {
+	pair: &'b SocketPair<'a>,
+}
+
+impl KernelSocket<'_, '_> {
+	/// Queues a serialised request for the server to receive.
+	///
+	/// The request is not validated, so tests may queue malformed requests
+	/// to check how they're handled.
+	pub fn push_request(&self, request: &[u8]) -> Result<(), SocketError> {
+		self.pair.requests.borrow_mut().push(&[request])
+	}
+
+	/// Receives the next reply sent by the server, returning its length.
+	pub fn pop_reply(&self, buf: &mut [u8]) -> Result<usize, SocketError> {
+		self.pair.replies.borrow_mut().pop(buf)
+	}
+
+	/// Returns `true` if every queued request has been received.
+	#[must_use]
+	pub fn requests_done(&self) -> bool {
+		self.pair.requests.borrow().is_empty()
+	}
+
+	/// Returns `true` if every reply sent by the server has been received.
+	#[must_use]
+	pub fn replies_done(&self) -> bool {
+		self.pair.replies.borrow().is_empty()
+	}
+}
+
+impl client::io::Socket for KernelSocket<'_, '_> {
+	type Error = SocketError;
+
+	fn send(
+		&self,
+		buf: SendBuf,
+	) -> Result<(), client::io::SendError<SocketError>> {
+		let mut requests = self.pair.requests.borrow_mut();
+		requests.push(buf.chunks()).map_err(client::io::SendError::Other)
+	}
+
+	fn recv(
+		&self,
+		buf: &mut [u8],
+	) -> Result<usize, client::io::RecvError<SocketError>> {
+		self.pop_reply(buf).map_err(client::io::RecvError::Other)
+	}
+}
+
+impl client::io::CuseSocket for KernelSocket<'_, '_> {}
+
+impl client::io::FuseSocket for KernelSocket<'_, '_> {}
+
+// }}}
+
+// RequestBuilder {{{
+
+/// Encodes FUSE requests as they would be sent by the kernel.
+///
+/// The request body is encoded according to the builder's [`FuseLayout`],
+/// so tests can check how a server handles requests from older clients.
+/// Bodies are passed as their kernel structs, which are truncated to the
+/// length used by the layout's protocol version.
+///
+/// Requests are written to a caller-provided buffer. Each method returns
+/// the encoded request, or `None` if the buffer is too small.
+///
+/// [`FuseLayout`]: server::FuseLayout
+#[derive(Clone, Copy)]
+pub struct RequestBuilder {
+	header: kernel::fuse_in_header,
+	layout: server::FuseLayout,
+}
+
+impl RequestBuilder {
+	/// Creates a new `RequestBuilder` for the given layout.
+	///
+	/// Requests have a request ID of 1 until changed with
+	/// [`set_request_id`](Self::set_request_id).
+	#[must_use]
+	pub fn new(layout: server::FuseLayout) -> RequestBuilder {
+		Self {
+			header: new!(kernel::fuse_in_header {
+				unique: NonZeroU64::MIN.get(),
+			}),
+			layout,
+		}
+	}
+
+	/// Returns the layout used to encode request bodies.
+	#[inline]
+	#[must_use]
+	pub fn layout(&self) -> server::FuseLayout {
+		self.layout
+	}
+
+	/// Set the request ID.
+	///
+	/// A request ID of zero is invalid, and may be used to test how servers
+	/// handle malformed requests.
+	pub fn set_request_id(&mut self, request_id: u64) -> &mut Self {
+		self.header.unique = request_id;
+		self
+	}
+
+	/// Set the user ID of the process that initiated the request.
+	pub fn set_user_id(&mut self, user_id: u32) -> &mut Self {
+		self.header.uid = user_id;
+		self
+	}
+
+	/// Set the group ID of the process that initiated the request.
+	pub fn set_group_id(&mut self, group_id: u32) -> &mut Self {
+		self.header.gid = group_id;
+		self
+	}
+
+	/// Set the process ID of the process that initiated the request.
+	pub fn set_process_id(&mut self, process_id: u32) -> &mut Self {
+		self.header.pid = process_id;
+		self
+	}
+
+	/// Encodes a request with the given opcode, node ID, and body.
+	///
+	/// The body is written as-is after the request header, so this method
+	/// can encode requests for any opcode, including malformed requests.
+	#[must_use]
+	pub fn build<'a>(
+		&self,
+		buf: AlignedSliceMut<'a>,
+		opcode: fuse_opcode,
+		node_id: u64,
+		body: &[&[u8]],
+	) -> Option<AlignedSlice<'a>> {
+		let mut buf = buf;
+		let header_len = size_of::<kernel::fuse_in_header>();
+		let mut len = header_len;
+		for chunk in body {
+			len = len.checked_add(chunk.len())?;
+		}
+		if len > u32::MAX as usize || len > buf.get_mut().len() {
+			return None;
+		}
+
+		let mut header = self.header;
+		header.len = len as u32;
+		header.opcode = opcode;
+		header.nodeid = node_id;
+
+		let bytes = buf.get_mut();
+		bytes[..header_len].copy_from_slice(header.as_bytes());
+		let mut offset = header_len;
+		for chunk in body {
+			bytes[offset..offset + chunk.len()].copy_from_slice(chunk);
+			offset += chunk.len();
+		}
+		Some(AlignedSlice::from(buf).truncate(len))
+	}
+
+	/// Encodes a `FUSE_INIT` request.
+	///
+	/// The body is truncated according to its own `major` and `minor`
+	/// fields rather than the builder's layout.
+	#[must_use]
+	pub fn init<'a>(
+		&self,
+		buf: AlignedSliceMut<'a>,
+		body: &kernel::fuse_init_in,
+	) -> Option<AlignedSlice<'a>> {
+		let mut body_bytes = body.as_bytes();
+		if body.minor < 6 || body.major != kernel::FUSE_KERNEL_VERSION {
+			body_bytes = &body_bytes[..size_of::<compat::fuse_init_in_v7p1>()];
+		} else if body.minor < 36 {
+			body_bytes = &body_bytes[..size_of::<compat::fuse_init_in_v7p6>()];
+		}
+		self.build(buf, fuse_opcode::FUSE_INIT, 0, &[body_bytes])
+	}
+
+	/// Encodes a `FUSE_GETATTR` request.
+	#[must_use]
+	pub fn getattr<'a>(
+		&self,
+		buf: AlignedSliceMut<'a>,
+		node_id: u64,
+		body: &kernel::fuse_getattr_in,
+	) -> Option<AlignedSlice<'a>> {
+		let mut body_bytes = body.as_bytes();
+		if self.layout.version_minor() < 9 {
+			body_bytes = &[];
+		}
+		self.build(buf, fuse_opcode::FUSE_GETATTR, node_id, &[body_bytes])
+	}
+
+	/// Encodes a `FUSE_MKNOD` request.
+	#[must_use]
+	pub fn mknod<'a>(
+		&self,
+		buf: AlignedSliceMut<'a>,
+		node_id: u64,
+		body: &kernel::fuse_mknod_in,
+		name: &crate::NodeName,
+	) -> Option<AlignedSlice<'a>> {
+		let mut body_bytes = body.as_bytes();
+		if self.layout.version_minor() < 12 {
+			body_bytes = &body_bytes[..kernel::FUSE_COMPAT_MKNOD_IN_SIZE];
+		}
+		self.build(buf, fuse_opcode::FUSE_MKNOD, node_id, &[
+			body_bytes,
+			name.as_bytes(),
+			b"\0",
+		])
+	}
+
+	/// Encodes a `FUSE_CREATE` request.
+	#[must_use]
+	pub fn create<'a>(
+		&self,
+		buf: AlignedSliceMut<'a>,
+		node_id: u64,
+		body: &kernel::fuse_create_in,
+		name: &crate::NodeName,
+	) -> Option<AlignedSlice<'a>> {
+		let mut body_bytes = body.as_bytes();
+		if self.layout.version_minor() < 12 {
+			let len = size_of::<compat::fuse_create_in_v7p1>();
+			body_bytes = &body_bytes[..len];
+		}
+		self.build(buf, fuse_opcode::FUSE_CREATE, node_id, &[
+			body_bytes,
+			name.as_bytes(),
+			b"\0",
+		])
+	}
+
+	/// Encodes a `FUSE_READ` request.
+	#[must_use]
+	pub fn read<'a>(
+		&self,
+		buf: AlignedSliceMut<'a>,
+		node_id: u64,
+		body: &kernel::fuse_read_in,
+	) -> Option<AlignedSlice<'a>> {
+		self.build_read(buf, fuse_opcode::FUSE_READ, node_id, body)
+	}
+
+	/// Encodes a `FUSE_READDIR` request.
+	#[must_use]
+	pub fn readdir<'a>(
+		&self,
+		buf: AlignedSliceMut<'a>,
+		node_id: u64,
+		body: &kernel::fuse_read_in,
+	) -> Option<AlignedSlice<'a>> {
+		self.build_read(buf, fuse_opcode::FUSE_READDIR, node_id, body)
+	}
+
+	/// Encodes a `FUSE_READDIRPLUS` request.
+	#[must_use]
+	pub fn readdirplus<'a>(
+		&self,
+		buf: AlignedSliceMut<'a>,
+		node_id: u64,
+		body: &kernel::fuse_read_in,
+	) -> Option<AlignedSlice<'a>> {
+		self.build_read(buf, fuse_opcode::FUSE_READDIRPLUS, node_id, body)
+	}
+
+	fn build_read<'a>(
+		&self,
+		buf: AlignedSliceMut<'a>,
+		opcode: fuse_opcode,
+		node_id: u64,
+		body: &kernel::fuse_read_in,
+	) -> Option<AlignedSlice<'a>> {
+		let mut body_bytes = body.as_bytes();
+		if self.layout.version_minor() < 9 {
+			body_bytes = &body_bytes[..size_of::<compat::fuse_read_in_v7p1>()];
+		}
+		self.build(buf, opcode, node_id, &[body_bytes])
+	}
+
+	/// Encodes a `FUSE_WRITE` request.
+	///
+	/// The `size` field of the body isn't checked against the length of
+	/// the written data.
+	#[must_use]
+	pub fn write<'a>(
+		&self,
+		buf: AlignedSliceMut<'a>,
+		node_id: u64,
+		body: &kernel::fuse_write_in,
+		data: &[u8],
+	) -> Option<AlignedSlice<'a>> {
+		let mut body_bytes = body.as_bytes();
+		if self.layout.version_minor() < 9 {
+			body_bytes = &body_bytes[..kernel::FUSE_COMPAT_WRITE_IN_SIZE];
+		}
+		self.build(buf, fuse_opcode::FUSE_WRITE, node_id, &[
+			body_bytes,
+			data,
+		])
+	}
+
+	/// Encodes a `FUSE_RELEASE` request.
+	#[must_use]
+	pub fn release<'a>(
+		&self,
+		buf: AlignedSliceMut<'a>,
+		node_id: u64,
+		body: &kernel::fuse_release_in,
+	) -> Option<AlignedSlice<'a>> {
+		self.build_release(buf, fuse_opcode::FUSE_RELEASE, node_id, body)
+	}
+
+	/// Encodes a `FUSE_RELEASEDIR` request.
+	#[must_use]
+	pub fn releasedir<'a>(
+		&self,
+		buf: AlignedSliceMut<'a>,
+		node_id: u64,
+		body: &kernel::fuse_release_in,
+	) -> Option<AlignedSlice<'a>> {
+		self.build_release(buf, fuse_opcode::FUSE_RELEASEDIR, node_id, body)
+	}
+
+	fn build_release<'a>(
+		&self,
+		buf: AlignedSliceMut<'a>,
+		opcode: fuse_opcode,
+		node_id: u64,
+		body: &kernel::fuse_release_in,
+	) -> Option<AlignedSlice<'a>> {
+		let mut body_bytes = body.as_bytes();
+		if self.layout.version_minor() < 8 {
+			let len = size_of::<compat::fuse_release_in_v7p1>();
+			body_bytes = &body_bytes[..len];
+		}
+		self.build(buf, opcode, node_id, &[body_bytes])
+	}
+
+	/// Encodes a `FUSE_SETXATTR` request.
+	///
+	/// The extended body is only used if the layout's session negotiated
+	/// the `SETXATTR_EXT` flag.
+	#[must_use]
+	pub fn setxattr<'a>(
+		&self,
+		buf: AlignedSliceMut<'a>,
+		node_id: u64,
+		body: &kernel::fuse_setxattr_in,
+		name: &ffi::CStr,
+		value: &[u8],
+	) -> Option<AlignedSlice<'a>> {
+		let mut body_bytes = body.as_bytes();
+		if !self.layout.have_setxattr_ext() {
+			body_bytes = &body_bytes[..kernel::FUSE_COMPAT_SETXATTR_IN_SIZE];
+		}
+		self.build(buf, fuse_opcode::FUSE_SETXATTR, node_id, &[
+			body_bytes,
+			name.to_bytes_with_nul(),
+			value,
+		])
+	}
+}
+
+// }}}
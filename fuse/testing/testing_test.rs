@@ -0,0 +1,815 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use core::mem::size_of;
+
+use fuse::client;
+use fuse::io::AsAlignedSliceMut;
+use fuse::kernel;
+use fuse::kernel::fuse_opcode;
+use fuse::os::OsError;
+use fuse::server;
+use fuse::testing::{RequestBuilder, SocketError, SocketPair};
+
+use fuse_testutil as testutil;
+use fuse_testutil::MessageBuilder;
+
+fn init_request() -> Vec<u8> {
+	MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_opcode::FUSE_INIT;
+			h.unique = 1;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_init_in {
+			major: kernel::FUSE_KERNEL_VERSION,
+			minor: kernel::FUSE_KERNEL_MINOR_VERSION,
+			max_readahead: 4096,
+		}))
+		.build()
+}
+
+fn getattr_request(request_id: u64) -> Vec<u8> {
+	MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_opcode::FUSE_GETATTR;
+			h.unique = request_id;
+			h.nodeid = fuse::NodeId::ROOT.get();
+		})
+		.push_sized(&kernel::fuse_getattr_in::new())
+		.build()
+}
+
+fn out_header(reply: &[u8]) -> kernel::fuse_out_header {
+	assert!(reply.len() >= size_of::<kernel::fuse_out_header>());
+	unsafe { reply.as_ptr().cast::<kernel::fuse_out_header>().read_unaligned() }
+}
+
+#[test]
+fn serve_requests() {
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	let kernel_socket = pair.kernel();
+
+	kernel_socket.push_request(&init_request()).unwrap();
+	kernel_socket.push_request(&getattr_request(2)).unwrap();
+	kernel_socket.push_request(&getattr_request(3)).unwrap();
+
+	let conn = server::FuseConnection::connect(pair.server(), |_, _| {})
+		.unwrap();
+	let mut buf = fuse::io::MinReadBuffer::new();
+	let mut request_ids = Vec::new();
+	while let Some(request) = conn.recv(buf.as_aligned_slice_mut()).unwrap() {
+		request_ids.push(request.id().get());
+		conn.reply(request.id()).err(OsError::NOT_FOUND).unwrap();
+	}
+	assert_eq!(request_ids, [2, 3]);
+	assert!(kernel_socket.requests_done());
+
+	let mut reply = [0u8; 512];
+	let reply_len = kernel_socket.pop_reply(&mut reply).unwrap();
+	let header = out_header(&reply[..reply_len]);
+	assert_eq!(header.unique, 1);
+	assert_eq!(header.error, 0);
+	assert_eq!(header.len as usize, reply_len);
+
+	for request_id in [2, 3] {
+		let reply_len = kernel_socket.pop_reply(&mut reply).unwrap();
+		let header = out_header(&reply[..reply_len]);
+		assert_eq!(header.unique, request_id);
+		assert_eq!(header.error, OsError::NOT_FOUND.0.get());
+		assert_eq!(reply_len, size_of::<kernel::fuse_out_header>());
+	}
+	assert!(kernel_socket.replies_done());
+	assert_eq!(kernel_socket.pop_reply(&mut reply), Err(SocketError::Empty));
+}
+
+#[test]
+fn connect_with_buf() {
+	#[repr(align(8))]
+	struct Buffer([u8; 512]);
+
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	let kernel_socket = pair.kernel();
+
+	kernel_socket.push_request(&init_request()).unwrap();
+	kernel_socket.push_request(&getattr_request(2)).unwrap();
+
+	let mut buf = Buffer([0u8; 512]);
+	let mut buf = fuse::io::AlignedSliceMut::new(&mut buf.0).unwrap();
+	let conn = server::FuseServer::new()
+		.connect_with_buf(pair.server(), &mut buf)
+		.unwrap();
+	let mut request_ids = Vec::new();
+	while let Some(request) = conn.recv(buf.as_aligned_slice_mut()).unwrap() {
+		request_ids.push(request.id().get());
+		conn.reply(request.id()).err(OsError::NOT_FOUND).unwrap();
+	}
+	assert_eq!(request_ids, [2]);
+
+	let mut reply = [0u8; 512];
+	let reply_len = kernel_socket.pop_reply(&mut reply).unwrap();
+	let header = out_header(&reply[..reply_len]);
+	assert_eq!(header.unique, 1);
+	assert_eq!(header.error, 0);
+}
+
+fn connect_with_flags(
+	init_flags: u32,
+	f: impl FnOnce(&mut server::FuseServer),
+) -> server::ConnectionInfo {
+	connect_recv_buf_len(init_flags, f).0
+}
+
+// Returns the negotiated connection info and the receive buffer length.
+fn connect_recv_buf_len(
+	init_flags: u32,
+	f: impl FnOnce(&mut server::FuseServer),
+) -> (server::ConnectionInfo, usize) {
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+
+	let init_request = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_opcode::FUSE_INIT;
+			h.unique = 1;
+		})
+		.push_sized(&testutil::new!(kernel::fuse_init_in {
+			major: kernel::FUSE_KERNEL_VERSION,
+			minor: kernel::FUSE_KERNEL_MINOR_VERSION,
+			flags: init_flags,
+		}))
+		.build();
+	pair.kernel().push_request(&init_request).unwrap();
+
+	let mut srv = server::FuseServer::new();
+	f(&mut srv);
+	let conn = srv.connect(pair.server()).unwrap();
+	assert!(conn.recv_buf_len() >= conn.info().max_write() as usize + 4096);
+	(*conn.info(), conn.recv_buf_len())
+}
+
+#[test]
+fn max_pages() {
+	let flags = kernel::FUSE_MAX_PAGES | kernel::FUSE_BIG_WRITES;
+
+	// `max_write` is computed from `max_pages`.
+	let info = connect_with_flags(flags, |srv| {
+		srv.max_pages(512);
+	});
+	assert_eq!(info.max_pages(), Some(512));
+	assert_eq!(info.max_write(), 512 * 4096);
+	assert!(info.flags().get(fuse::FuseInitFlag::BIG_WRITES));
+
+	// ... and limited by an explicit `max_write`.
+	let info = connect_with_flags(flags, |srv| {
+		srv.max_write(1 << 20).max_pages(512);
+	});
+	assert_eq!(info.max_pages(), Some(512));
+	assert_eq!(info.max_write(), 1 << 20);
+
+	// A `max_pages` of zero is treated as one.
+	let info = connect_with_flags(flags, |srv| {
+		srv.max_pages(0);
+	});
+	assert_eq!(info.max_pages(), Some(1));
+	assert_eq!(info.max_write(), 4096);
+
+	// The page size is taken into account.
+	let info = connect_with_flags(flags, |srv| {
+		srv.page_size(16384).max_pages(64);
+	});
+	assert_eq!(info.max_write(), 64 * 16384);
+
+	// Clients without `MAX_PAGES` use 32 pages per request.
+	let info = connect_with_flags(0, |srv| {
+		srv.max_pages(512);
+	});
+	assert_eq!(info.max_pages(), None);
+	assert_eq!(info.max_write(), 32 * 4096);
+}
+
+#[test]
+fn page_size() {
+	let flags = kernel::FUSE_MAX_PAGES | kernel::FUSE_BIG_WRITES;
+
+	// With 64 KiB pages, a `max_write` of 1 MiB fits in the default 32
+	// pages, so `max_pages` is not sent.
+	let (info, recv_buf_len) = connect_recv_buf_len(flags, |srv| {
+		srv.page_size(65536).max_write(1 << 20);
+	});
+	assert_eq!(info.max_pages(), None);
+	assert_eq!(info.max_write(), 1 << 20);
+	assert_eq!(recv_buf_len, (1 << 20) + 65536);
+
+	let (info, recv_buf_len) = connect_recv_buf_len(flags, |srv| {
+		srv.page_size(65536).max_write(4 << 20);
+	});
+	assert_eq!(info.max_pages(), Some(64));
+	assert_eq!(info.max_write(), 4 << 20);
+	assert_eq!(recv_buf_len, (4 << 20) + 65536);
+
+	// With 16 KiB pages, a partial page rounds `max_pages` up.
+	let (info, recv_buf_len) = connect_recv_buf_len(flags, |srv| {
+		srv.page_size(16384).max_write(1 << 20);
+	});
+	assert_eq!(info.max_pages(), Some(64));
+	assert_eq!(recv_buf_len, (1 << 20) + 16384);
+
+	let (info, recv_buf_len) = connect_recv_buf_len(flags, |srv| {
+		srv.page_size(16384).max_write((1 << 20) + 1);
+	});
+	assert_eq!(info.max_pages(), Some(65));
+	assert_eq!(recv_buf_len, (1 << 20) + 1 + 16384);
+
+	// Pages smaller than 4 KiB still reserve 4 KiB for the header.
+	let (_, recv_buf_len) = connect_recv_buf_len(flags, |srv| {
+		srv.page_size(1024).max_write(1 << 20);
+	});
+	assert_eq!(recv_buf_len, (1 << 20) + 4096);
+
+	// Small `max_write` values use the minimum read buffer length.
+	let (info, recv_buf_len) = connect_recv_buf_len(flags, |srv| {
+		srv.page_size(1024).max_write(1024);
+	});
+	assert_eq!(info.max_pages(), None);
+	assert_eq!(recv_buf_len, fuse::io::MinReadBuffer::LEN);
+}
+
+#[test]
+fn page_size_max_pages_overflow() {
+	let flags = kernel::FUSE_MAX_PAGES | kernel::FUSE_BIG_WRITES;
+
+	// The page count is clamped to the range of `max_pages`.
+	let (info, recv_buf_len) = connect_recv_buf_len(flags, |srv| {
+		srv.max_write(u32::MAX);
+	});
+	assert_eq!(info.max_pages(), Some(u16::MAX));
+	assert_eq!(info.max_write(), u32::MAX);
+	assert_eq!(recv_buf_len, (u32::MAX as usize).saturating_add(4096));
+}
+
+#[test]
+fn page_size_zero() {
+	let flags = kernel::FUSE_MAX_PAGES | kernel::FUSE_BIG_WRITES;
+
+	// A page size of zero disables `max_pages`, and the receive buffer
+	// reserves the default page size for the header.
+	let (info, recv_buf_len) = connect_recv_buf_len(flags, |srv| {
+		srv.page_size(0).max_write(1 << 20);
+	});
+	assert_eq!(info.max_pages(), None);
+	assert_eq!(info.max_write(), 1 << 20);
+	assert_eq!(recv_buf_len, (1 << 20) + 4096);
+}
+
+#[test]
+fn init_profiles() {
+	use fuse::FuseInitFlag;
+
+	let info = connect_with_flags(u32::MAX, |srv| {
+		srv.profile_local_cachefs().profile_network_fs();
+	});
+	let flags = info.flags();
+	assert!(flags.get(FuseInitFlag::AUTO_INVAL_DATA));
+	assert!(flags.get(FuseInitFlag::PARALLEL_DIROPS));
+	assert!(flags.get(FuseInitFlag::READDIRPLUS_AUTO));
+	assert!(!flags.get(FuseInitFlag::WRITEBACK_CACHE));
+	assert!(!flags.get(FuseInitFlag::EXPLICIT_INVAL_DATA));
+
+	let info = connect_with_flags(u32::MAX, |srv| {
+		srv.profile_network_fs().profile_local_cachefs();
+	});
+	let flags = info.flags();
+	assert!(flags.get(FuseInitFlag::WRITEBACK_CACHE));
+	assert!(flags.get(FuseInitFlag::EXPLICIT_INVAL_DATA));
+	assert!(flags.get(FuseInitFlag::CACHE_SYMLINKS));
+	assert!(!flags.get(FuseInitFlag::AUTO_INVAL_DATA));
+	assert_eq!(info.time_granularity(), 1);
+
+	// Flags not supported by the client are not enabled.
+	let info = connect_with_flags(kernel::FUSE_ASYNC_READ, |srv| {
+		srv.profile_local_cachefs();
+	});
+	assert_eq!(info.flags(), FuseInitFlag::ASYNC_READ);
+}
+
+#[test]
+fn submounts() {
+	use fuse::FuseInitFlag;
+
+	let info = connect_with_flags(kernel::FUSE_SUBMOUNTS, |srv| {
+		srv.enable_submounts();
+	});
+	assert!(info.flags().get(FuseInitFlag::SUBMOUNTS));
+
+	let info = connect_with_flags(0, |srv| {
+		srv.enable_submounts();
+	});
+	assert!(!info.flags().get(FuseInitFlag::SUBMOUNTS));
+
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	let request_id = core::num::NonZeroU64::new(2).unwrap();
+
+	let mut attr = fuse::NodeAttr::new(fuse::NodeId::new(2).unwrap());
+	attr.set_mode(fuse::FileMode::S_IFDIR | 0o755);
+	attr.set_flag_submount(true);
+	let entry = fuse::Entry::new(attr);
+	let create = fuse::server::CreateResponse::new(entry);
+
+	// Submount roots are rejected unless `FUSE_SUBMOUNTS` was negotiated.
+	let version_minor = kernel::FUSE_KERNEL_MINOR_VERSION;
+	let socket = &pair.server();
+	let no_submounts = layout(version_minor, 0);
+	let sender = server::FuseReplySender::new(socket, no_submounts, request_id);
+	assert_eq!(sender.ok(&entry), Err(server::SendError::SubmountNotEnabled));
+	let sender = server::FuseReplySender::new(socket, no_submounts, request_id);
+	assert_eq!(sender.ok(&create), Err(server::SendError::SubmountNotEnabled));
+
+	let submounts = layout(version_minor, kernel::FUSE_SUBMOUNTS);
+	let sender = server::FuseReplySender::new(socket, submounts, request_id);
+	assert_eq!(sender.ok(&entry), Ok(()));
+
+	let mut reply = [0u8; 512];
+	let reply_len = pair.kernel().pop_reply(&mut reply).unwrap();
+	let entry_out = unsafe {
+		reply[size_of::<kernel::fuse_out_header>()..]
+			.as_ptr()
+			.cast::<kernel::fuse_entry_out>()
+			.read_unaligned()
+	};
+	assert_eq!(reply_len, size_of::<kernel::fuse_out_header>()
+		+ size_of::<kernel::fuse_entry_out>());
+	assert_eq!(entry_out.attr.flags, kernel::FUSE_ATTR_SUBMOUNT);
+	assert!(pair.kernel().replies_done());
+}
+
+#[test]
+fn submount_devices() {
+	let mut devices = fuse::SubmountDevices::<2>::new();
+	assert!(devices.is_empty());
+
+	let root_a = devices.node_id(10, 1).unwrap();
+	let root_b = devices.node_id(20, 1).unwrap();
+	assert_ne!(root_a, root_b);
+	assert_ne!(root_a, fuse::NodeId::ROOT);
+	assert_eq!(devices.node_id(10, 1), Ok(root_a));
+	assert_eq!(devices.len(), 2);
+
+	assert_eq!(devices.get(root_a), Some((10, 1)));
+	assert_eq!(devices.get(root_b), Some((20, 1)));
+	assert_eq!(devices.get(fuse::NodeId::ROOT), None);
+	assert_eq!(devices.get(fuse::NodeId::new(3 << 48).unwrap()), None);
+
+	let max_inode = (1 << 48) - 1;
+	let node_id = devices.node_id(20, max_inode).unwrap();
+	assert_eq!(devices.get(node_id), Some((20, max_inode)));
+	assert_eq!(
+		devices.node_id(20, 1 << 48),
+		Err(fuse::SubmountDevicesError::InodeTooLarge(1 << 48)),
+	);
+	assert_eq!(
+		devices.node_id(30, 1),
+		Err(fuse::SubmountDevicesError::CapacityExceeded),
+	);
+}
+
+#[test]
+fn from_init_response() {
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	let kernel_socket = pair.kernel();
+
+	// The handshake was performed elsewhere, so the first request received
+	// by the server is a regular request.
+	kernel_socket.push_request(&getattr_request(2)).unwrap();
+
+	let mut response = server::FuseInitResponse::new();
+	response.set_version(fuse::Version::new(7, 40));
+	response.set_max_write(8192);
+	response.update_flags(|flags| {
+		flags.set(fuse::FuseInitFlag::ASYNC_READ);
+	});
+	let response = server::FuseInitResponse::from_raw(*response.raw());
+
+	let conn = server::FuseConnection::from_init_response(
+		pair.server(),
+		&response,
+	).unwrap();
+	assert_eq!(conn.info().version(), fuse::Version::new(7, 40));
+	assert_eq!(conn.info().max_write(), 8192);
+	assert!(conn.info().flags().get(fuse::FuseInitFlag::ASYNC_READ));
+	assert!(conn.recv_buf_len() >= 8192);
+
+	let mut buf = fuse::io::MinReadBuffer::new();
+	let request = conn.recv(buf.as_aligned_slice_mut()).unwrap().unwrap();
+	assert_eq!(request.header().opcode(), fuse_opcode::FUSE_GETATTR);
+	conn.reply(request.id()).err(OsError::NOT_FOUND).unwrap();
+
+	let mut reply = [0u8; 512];
+	let reply_len = kernel_socket.pop_reply(&mut reply).unwrap();
+	assert_eq!(out_header(&reply[..reply_len]).unique, 2);
+
+	let mut response = server::FuseInitResponse::new();
+	response.set_version(fuse::Version::new(8, 0));
+	assert!(server::FuseConnection::from_init_response(
+		pair.server(),
+		&response,
+	).is_err());
+}
+
+#[test]
+fn connection_stats() {
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	let kernel_socket = pair.kernel();
+
+	let init = init_request();
+	let getattr = getattr_request(2);
+	kernel_socket.push_request(&init).unwrap();
+	kernel_socket.push_request(&getattr).unwrap();
+	kernel_socket.push_request(&getattr_request(3)).unwrap();
+
+	let conn = server::FuseConnection::connect(pair.server(), |_, _| {})
+		.unwrap();
+	let stats = conn.stats();
+	assert_eq!(stats.total_requests(), 1);
+	assert_eq!(stats.requests(fuse_opcode::FUSE_INIT), 1);
+	assert_eq!(stats.replies(), 1);
+	let init_reply_len = stats.bytes_sent();
+
+	let mut buf = fuse::io::MinReadBuffer::new();
+	let request = conn.recv(buf.as_aligned_slice_mut()).unwrap().unwrap();
+	conn.reply(request.id()).ok_empty().unwrap();
+	let request = conn.recv(buf.as_aligned_slice_mut()).unwrap().unwrap();
+	conn.reply(request.id()).err(OsError::NOT_FOUND).unwrap();
+	assert!(conn.recv(buf.as_aligned_slice_mut()).unwrap().is_none());
+
+	let header_len = size_of::<kernel::fuse_out_header>() as u64;
+	let request_len = init.len() + getattr.len() * 2;
+	assert_eq!(stats.total_requests(), 3);
+	assert_eq!(stats.requests(fuse_opcode::FUSE_GETATTR), 2);
+	assert_eq!(stats.requests(fuse_opcode::CUSE_INIT), 0);
+	assert_eq!(stats.other_requests(), 0);
+	assert_eq!(stats.bytes_received(), request_len as u64);
+	assert_eq!(stats.replies(), 3);
+	assert_eq!(stats.error_replies(), 1);
+	assert_eq!(stats.bytes_sent(), init_reply_len + header_len * 2);
+	assert_eq!(stats.notifications(), 0);
+	assert_eq!(stats.errors(), 0);
+}
+
+#[test]
+fn dyn_socket() {
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	let kernel_socket = pair.kernel();
+	kernel_socket.push_request(&init_request()).unwrap();
+	kernel_socket.push_request(&getattr_request(2)).unwrap();
+
+	let server_socket = pair.server();
+	let socket: &dyn server::FuseSocket<Error = SocketError> = &server_socket;
+	let conn = server::FuseConnection::connect(socket, |_, _| {}).unwrap();
+	let mut buf = fuse::io::MinReadBuffer::new();
+	let request = conn.recv(buf.as_aligned_slice_mut()).unwrap().unwrap();
+	conn.reply(request.id()).err(OsError::NOT_FOUND).unwrap();
+	assert!(conn.recv(buf.as_aligned_slice_mut()).unwrap().is_none());
+
+	let mut reply = [0u8; 512];
+	kernel_socket.pop_reply(&mut reply).unwrap();
+	let reply_len = kernel_socket.pop_reply(&mut reply).unwrap();
+	let header = out_header(&reply[..reply_len]);
+	assert_eq!(header.unique, 2);
+	assert_eq!(header.error, OsError::NOT_FOUND.0.get());
+}
+
+#[test]
+fn invalid_errno() {
+	use core::num::NonZeroI32;
+
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	let kernel_socket = pair.kernel();
+
+	kernel_socket.push_request(&init_request()).unwrap();
+	for request_id in 2..6 {
+		kernel_socket.push_request(&getattr_request(request_id)).unwrap();
+	}
+
+	let conn = server::FuseConnection::connect(pair.server(), |_, _| {})
+		.unwrap();
+	let mut buf = fuse::io::MinReadBuffer::new();
+	let mut results = Vec::new();
+	for errno in [2, -512, 512, -511] {
+		let request = conn.recv(buf.as_aligned_slice_mut()).unwrap().unwrap();
+		let error = fuse::Error(NonZeroI32::new(errno).unwrap());
+		results.push(conn.reply(request.id()).err(error));
+	}
+	assert_eq!(results, [
+		Err(server::SendError::InvalidErrno(fuse::Error(
+			NonZeroI32::new(2).unwrap()
+		))),
+		Err(server::SendError::InvalidErrno(fuse::Error(
+			NonZeroI32::new(-512).unwrap()
+		))),
+		Err(server::SendError::InvalidErrno(fuse::Error(
+			NonZeroI32::new(512).unwrap()
+		))),
+		Ok(()),
+	]);
+
+	// Only the init reply and the valid error reply were sent.
+	let mut reply = [0u8; 512];
+	let reply_len = kernel_socket.pop_reply(&mut reply).unwrap();
+	assert_eq!(out_header(&reply[..reply_len]).unique, 1);
+	let reply_len = kernel_socket.pop_reply(&mut reply).unwrap();
+	let header = out_header(&reply[..reply_len]);
+	assert_eq!(header.unique, 5);
+	assert_eq!(header.error, -511);
+	assert!(kernel_socket.replies_done());
+}
+
+#[test]
+fn server_recv_empty() {
+	let mut request_buf = [0u8; 64];
+	let mut reply_buf = [0u8; 64];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+
+	let mut buf = [0u8; 64];
+	let err = server::Socket::recv(&pair.server(), &mut buf).unwrap_err();
+	assert!(matches!(
+		err,
+		server::RecvError::ConnectionClosed(SocketError::Empty),
+	));
+}
+
+#[test]
+fn queue_full() {
+	let mut request_buf = [0u8; 16];
+	let mut reply_buf = [0u8; 0];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	let kernel_socket = pair.kernel();
+
+	// Each message uses four bytes for its length.
+	kernel_socket.push_request(&[1; 4]).unwrap();
+	assert_eq!(
+		kernel_socket.push_request(&[2; 5]),
+		Err(SocketError::QueueFull),
+	);
+	kernel_socket.push_request(&[2; 4]).unwrap();
+
+	// Space freed by received messages is reused.
+	let mut buf = [0u8; 8];
+	assert_eq!(server::Socket::recv(&pair.server(), &mut buf), Ok(4));
+	assert_eq!(buf[..4], [1; 4]);
+	kernel_socket.push_request(&[3; 4]).unwrap();
+
+	assert_eq!(server::Socket::recv(&pair.server(), &mut buf), Ok(4));
+	assert_eq!(buf[..4], [2; 4]);
+	assert_eq!(server::Socket::recv(&pair.server(), &mut buf), Ok(4));
+	assert_eq!(buf[..4], [3; 4]);
+}
+
+#[test]
+fn buffer_too_small() {
+	let mut request_buf = [0u8; 64];
+	let mut reply_buf = [0u8; 0];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	pair.kernel().push_request(&[1; 8]).unwrap();
+
+	let mut buf = [0u8; 4];
+	let err = server::Socket::recv(&pair.server(), &mut buf).unwrap_err();
+	assert!(matches!(
+		err,
+		server::RecvError::Other(SocketError::BufferTooSmall),
+	));
+
+	// The message remains queued.
+	let mut buf = [0u8; 8];
+	assert_eq!(server::Socket::recv(&pair.server(), &mut buf), Ok(8));
+	assert_eq!(buf, [1; 8]);
+}
+
+#[test]
+fn kernel_client_socket() {
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+
+	// Requests sent with the client socket traits are received by the
+	// server end.
+	let conn = client::FuseConnection::connect(
+		pair.kernel(),
+		4096,
+		fuse::FuseInitFlags::new(),
+	);
+	assert!(matches!(
+		conn,
+		Err(client::ClientError::RecvError(SocketError::Empty)),
+	));
+
+	let mut buf = [0u8; 512];
+	let recv_len = server::Socket::recv(&pair.server(), &mut buf).unwrap();
+	assert!(recv_len >= size_of::<kernel::fuse_in_header>());
+	let header = unsafe {
+		buf.as_ptr().cast::<kernel::fuse_in_header>().read_unaligned()
+	};
+	assert_eq!(header.len as usize, recv_len);
+	assert_eq!(header.opcode, fuse_opcode::FUSE_INIT);
+	assert_eq!(header.unique, 1);
+}
+
+fn layout(version_minor: u32, flags: u32) -> server::FuseLayout {
+	server::FuseLayout::new(&testutil::new!(kernel::fuse_init_out {
+		major: kernel::FUSE_KERNEL_VERSION,
+		minor: version_minor,
+		flags: flags,
+	}))
+	.unwrap()
+}
+
+#[test]
+fn request_builder_build() {
+	let mut builder = RequestBuilder::new(layout(31, 0));
+	builder
+		.set_request_id(0xAABBCCDD)
+		.set_user_id(1000)
+		.set_group_id(2000)
+		.set_process_id(3000);
+
+	let mut buf = fuse::io::MinReadBuffer::new();
+	let request = builder.build(
+		buf.as_aligned_slice_mut(),
+		fuse_opcode::FUSE_LOOKUP,
+		fuse::NodeId::ROOT.get(),
+		&[b"hello.txt", b"\0"],
+	);
+
+	let expect = MessageBuilder::new()
+		.set_header(|h| {
+			h.opcode = fuse_opcode::FUSE_LOOKUP;
+			h.nodeid = fuse::NodeId::ROOT.get();
+			h.uid = 1000;
+			h.gid = 2000;
+			h.pid = 3000;
+		})
+		.push_bytes(b"hello.txt\0")
+		.build();
+	assert_eq!(request.unwrap().get(), expect);
+}
+
+#[test]
+fn request_builder_buffer_too_small() {
+	let builder = RequestBuilder::new(layout(31, 0));
+	let mut buf = fuse::io::MinReadBuffer::new();
+	let small = buf.as_aligned_slice_mut().truncate(40);
+	let request = builder.build(small, fuse_opcode::FUSE_STATFS, 1, &[]);
+	assert_eq!(request.unwrap().get().len(), 40);
+
+	let small = buf.as_aligned_slice_mut().truncate(40);
+	let request = builder.build(small, fuse_opcode::FUSE_LOOKUP, 1, &[b"\0"]);
+	assert!(request.is_none());
+}
+
+#[test]
+fn request_builder_init() {
+	let builder = RequestBuilder::new(layout(31, 0));
+	let mut buf = fuse::io::MinReadBuffer::new();
+	let header_len = size_of::<kernel::fuse_in_header>();
+
+	for (minor, body_len) in [(5, 8), (6, 16), (35, 16), (36, 64)] {
+		let body = testutil::new!(kernel::fuse_init_in {
+			major: kernel::FUSE_KERNEL_VERSION,
+			minor: minor,
+		});
+		let request = builder.init(buf.as_aligned_slice_mut(), &body);
+		assert_eq!(request.unwrap().get().len(), header_len + body_len);
+	}
+
+	let body = testutil::new!(kernel::fuse_init_in {
+		major: kernel::FUSE_KERNEL_VERSION,
+		minor: kernel::FUSE_KERNEL_MINOR_VERSION,
+		flags: kernel::FUSE_INIT_EXT,
+		flags2: 1,
+	});
+	let request = builder.init(buf.as_aligned_slice_mut(), &body).unwrap();
+	let request = server::FuseRequest::new(request, builder.layout()).unwrap();
+	let init = server::FuseInitRequest::try_from(request).unwrap();
+	assert!(init.flags().get(fuse::FuseInitFlag::SECURITY_CTX));
+}
+
+#[test]
+fn request_builder_getattr() {
+	let body = testutil::new!(kernel::fuse_getattr_in {
+		getattr_flags: kernel::FUSE_GETATTR_FH,
+		fh: 123,
+	});
+	let mut buf = fuse::io::MinReadBuffer::new();
+
+	let builder = RequestBuilder::new(layout(8, 0));
+	let request = builder.getattr(buf.as_aligned_slice_mut(), 10, &body);
+	let request = request.unwrap();
+	assert_eq!(request.get().len(), size_of::<kernel::fuse_in_header>());
+	let request = server::FuseRequest::new(request, builder.layout()).unwrap();
+	let getattr = server::GetattrRequest::try_from(request).unwrap();
+	assert_eq!(getattr.node_id().get(), 10);
+	assert_eq!(getattr.handle(), None);
+
+	let builder = RequestBuilder::new(layout(9, 0));
+	let request = builder.getattr(buf.as_aligned_slice_mut(), 10, &body);
+	let request = server::FuseRequest::new(request.unwrap(), builder.layout());
+	let getattr = server::GetattrRequest::try_from(request.unwrap()).unwrap();
+	assert_eq!(getattr.handle(), Some(123));
+}
+
+#[test]
+fn request_builder_write() {
+	let body = testutil::new!(kernel::fuse_write_in {
+		fh: 123,
+		offset: 45,
+		size: 5,
+		lock_owner: 67,
+		flags: 89,
+	});
+	let mut buf = fuse::io::MinReadBuffer::new();
+
+	for version_minor in [8, 9] {
+		let builder = RequestBuilder::new(layout(version_minor, 0));
+		let request = builder.write(
+			buf.as_aligned_slice_mut(),
+			10,
+			&body,
+			b"hello",
+		);
+		let request = server::FuseRequest::new(
+			request.unwrap(),
+			builder.layout(),
+		);
+		let write = server::WriteRequest::try_from(request.unwrap()).unwrap();
+		assert_eq!(write.handle(), 123);
+		assert_eq!(write.offset(), 45);
+		assert_eq!(write.value(), b"hello");
+		if version_minor < 9 {
+			assert_eq!(write.open_flags().get(), 0);
+		} else {
+			assert_eq!(write.open_flags().get(), 89);
+		}
+	}
+}
+
+#[test]
+fn request_builder_setxattr() {
+	let body = testutil::new!(kernel::fuse_setxattr_in {
+		size: 5,
+		flags: 1,
+		setxattr_flags: 2,
+	});
+	let mut buf = fuse::io::MinReadBuffer::new();
+	let header_len = size_of::<kernel::fuse_in_header>();
+
+	for (flags, body_len) in [
+		(0, kernel::FUSE_COMPAT_SETXATTR_IN_SIZE),
+		(kernel::FUSE_SETXATTR_EXT, size_of::<kernel::fuse_setxattr_in>()),
+	] {
+		let builder = RequestBuilder::new(layout(33, flags));
+		let request = builder.setxattr(
+			buf.as_aligned_slice_mut(),
+			10,
+			&body,
+			c"user.name",
+			b"value",
+		);
+		let request = request.unwrap();
+		assert_eq!(
+			request.get().len(),
+			header_len + body_len + b"user.name\0value".len(),
+		);
+		let request = server::FuseRequest::new(request, builder.layout());
+		let setxattr = server::SetxattrRequest::try_from(request.unwrap());
+		let setxattr = setxattr.unwrap();
+		assert_eq!(setxattr.name(), c"user.name");
+		assert_eq!(setxattr.value(), b"value");
+	}
+}
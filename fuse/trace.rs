@@ -0,0 +1,540 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Capture and replay of FUSE traffic.
+//!
+//! A [`TraceSocket`] wraps a server socket and records every request it
+//! receives and every reply it sends. The recorded trace can be parsed with
+//! a [`TraceReader`], or replayed against a server with a [`ReplaySocket`]
+//! to check that the server still sends the same replies.
+//!
+//! # Trace format
+//!
+//! A trace starts with the eight-byte magic number `FUSETRC1`, followed by
+//! zero or more records. Each record has an eight-byte header containing the
+//! message length as a little-endian `u32`, the record kind (`1` for a
+//! request and `2` for a reply), and three zero bytes. The header is followed
+//! by the message, exactly as it was received or sent.
+//!
+//! Messages use the byte order of the host they were recorded on, so traces
+//! can only be replayed on hosts with the same byte order.
+
+use core::cell::Cell;
+use core::fmt;
+
+use crate::io::SendBuf;
+use crate::server;
+
+const TRACE_MAGIC: [u8; 8] = *b"FUSETRC1";
+const RECORD_HEADER_LEN: usize = 8;
+
+const RECORD_KIND_REQUEST: u8 = 1;
+const RECORD_KIND_REPLY: u8 = 2;
+
+// RecordKind {{{
+
+/// The kind of message stored in a trace [`Record`].
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RecordKind {
+	/// A request received by the server.
+	Request,
+
+	/// A reply sent by the server.
+	Reply,
+}
+
+impl RecordKind {
+	fn to_u8(self) -> u8 {
+		match self {
+			RecordKind::Request => RECORD_KIND_REQUEST,
+			RecordKind::Reply => RECORD_KIND_REPLY,
+		}
+	}
+}
+
+// }}}
+
+// Record {{{
+
+/// A single message stored in a trace.
+#[derive(Clone, Copy)]
+pub struct Record<'a> {
+	kind: RecordKind,
+	message: &'a [u8],
+}
+
+impl<'a> Record<'a> {
+	/// Returns whether the message is a request or a reply.
+	#[inline]
+	#[must_use]
+	pub fn kind(&self) -> RecordKind {
+		self.kind
+	}
+
+	/// Returns the message, exactly as it was received or sent.
+	#[inline]
+	#[must_use]
+	pub fn message(&self) -> &'a [u8] {
+		self.message
+	}
+}
+
+impl fmt::Debug for Record<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("Record")
+			.field("kind", &self.kind)
+			.field("message_len", &self.message.len())
+			.finish()
+	}
+}
+
+// }}}
+
+// TraceError {{{
+
+/// Errors that may be encountered when parsing a trace.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TraceError {
+	/// The trace doesn't start with the trace magic number.
+	InvalidMagic,
+
+	/// A record has an unknown kind.
+	InvalidRecordKind(u8),
+
+	/// The trace ends partway through a record.
+	UnexpectedEof,
+}
+
+// }}}
+
+// TraceReader {{{
+
+/// Parses the records of a trace.
+#[derive(Clone, Copy)]
+pub struct TraceReader<'a> {
+	buf: &'a [u8],
+}
+
+impl<'a> TraceReader<'a> {
+	/// Creates a new `TraceReader` for the given trace.
+	pub fn new(trace: &'a [u8]) -> Result<TraceReader<'a>, TraceError> {
+		match trace.strip_prefix(&TRACE_MAGIC) {
+			Some(buf) => Ok(Self { buf }),
+			None => Err(TraceError::InvalidMagic),
+		}
+	}
+
+	fn next_record(&mut self) -> Result<Record<'a>, TraceError> {
+		if self.buf.len() < RECORD_HEADER_LEN {
+			return Err(TraceError::UnexpectedEof);
+		}
+		let (header, rest) = self.buf.split_at(RECORD_HEADER_LEN);
+		let len = u32::from_le_bytes([
+			header[0],
+			header[1],
+			header[2],
+			header[3],
+		]) as usize;
+		let kind = match header[4] {
+			RECORD_KIND_REQUEST => RecordKind::Request,
+			RECORD_KIND_REPLY => RecordKind::Reply,
+			kind => return Err(TraceError::InvalidRecordKind(kind)),
+		};
+		if rest.len() < len {
+			return Err(TraceError::UnexpectedEof);
+		}
+		let (message, rest) = rest.split_at(len);
+		self.buf = rest;
+		Ok(Record { kind, message })
+	}
+}
+
+impl<'a> Iterator for TraceReader<'a> {
+	type Item = Result<Record<'a>, TraceError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.buf.is_empty() {
+			return None;
+		}
+		let result = self.next_record();
+		if result.is_err() {
+			self.buf = &[];
+		}
+		Some(result)
+	}
+}
+
+impl fmt::Debug for TraceReader<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("TraceReader")
+			.field("remaining", &self.buf.len())
+			.finish()
+	}
+}
+
+// }}}
+
+// TraceWrite {{{
+
+/// Trait for destinations that traces can be written to.
+pub trait TraceWrite {
+	/// Type of errors that may be returned when writing to the trace.
+	type Error;
+
+	/// Append the given chunks to the trace, in order.
+	///
+	/// A [`TraceSocket`] may call this method from multiple threads. Each
+	/// call writes a whole record, which must not be interleaved with the
+	/// chunks of other calls.
+	fn write(&self, chunks: &[&[u8]]) -> Result<(), Self::Error>;
+}
+
+impl<W: TraceWrite> TraceWrite for &W {
+	type Error = W::Error;
+
+	fn write(&self, chunks: &[&[u8]]) -> Result<(), W::Error> {
+		(*self).write(chunks)
+	}
+}
+
+// }}}
+
+// TraceSocket {{{
+
+/// Errors that may be encountered by a [`TraceSocket`].
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TraceSocketError<SocketError, WriteError> {
+	/// The wrapped socket encountered an error.
+	Socket(SocketError),
+
+	/// The message couldn't be written to the trace.
+	Write(WriteError),
+}
+
+/// A server socket that records its traffic to a trace.
+///
+/// Requests are recorded after they're received, and replies after they've
+/// been sent successfully. A recording error is reported as a socket error
+/// even though the message itself was received or sent.
+pub struct TraceSocket<S, W> {
+	socket: S,
+	writer: W,
+}
+
+impl<S, W: TraceWrite> TraceSocket<S, W> {
+	/// Creates a new `TraceSocket`, writing the trace magic number to
+	/// `writer`.
+	pub fn new(socket: S, writer: W) -> Result<TraceSocket<S, W>, W::Error> {
+		writer.write(&[&TRACE_MAGIC])?;
+		Ok(Self { socket, writer })
+	}
+
+	fn record(
+		&self,
+		kind: RecordKind,
+		chunks: &[&[u8]],
+	) -> Result<(), W::Error> {
+		let len: usize = chunks.iter().map(|chunk| chunk.len()).sum();
+		let len = (len as u32).to_le_bytes();
+		let header = [len[0], len[1], len[2], len[3], kind.to_u8(), 0, 0, 0];
+
+		let mut record: [&[u8]; SendBuf::MAX_CHUNKS_LEN + 1] =
+			[&[]; SendBuf::MAX_CHUNKS_LEN + 1];
+		record[0] = &header;
+		record[1..=chunks.len()].copy_from_slice(chunks);
+		self.writer.write(&record[..=chunks.len()])
+	}
+}
+
+impl<S, W> TraceSocket<S, W> {
+	/// Returns a reference to the wrapped socket.
+	#[inline]
+	#[must_use]
+	pub fn socket(&self) -> &S {
+		&self.socket
+	}
+
+	/// Returns a reference to the trace writer.
+	#[inline]
+	#[must_use]
+	pub fn writer(&self) -> &W {
+		&self.writer
+	}
+}
+
+fn copy_chunks<'a>(
+	buf: &SendBuf<'a>,
+) -> ([&'a [u8]; SendBuf::MAX_CHUNKS_LEN], usize) {
+	let mut chunks: [&[u8]; SendBuf::MAX_CHUNKS_LEN] =
+		[&[]; SendBuf::MAX_CHUNKS_LEN];
+	let chunks_len = buf.chunks_len();
+	chunks[..chunks_len].copy_from_slice(buf.chunks());
+	(chunks, chunks_len)
+}
+
+fn send_write_error<E, W>(
+	err: W,
+) -> server::SendError<TraceSocketError<E, W>> {
+	server::SendError::Other(TraceSocketError::Write(err))
+}
+
+fn map_send_error<E, W>(
+	err: server::SendError<E>,
+) -> server::SendError<TraceSocketError<E, W>> {
+	match err {
+		server::SendError::NotFound(err) => {
+			server::SendError::NotFound(TraceSocketError::Socket(err))
+		},
+		server::SendError::ReplyTooBig(len) => {
+			server::SendError::ReplyTooBig(len)
+		},
+		server::SendError::FdUnsupported => server::SendError::FdUnsupported,
+		server::SendError::InvalidErrno(err) => {
+			server::SendError::InvalidErrno(err)
+		},
+		server::SendError::SubmountNotEnabled => {
+			server::SendError::SubmountNotEnabled
+		},
+		server::SendError::Other(err) => {
+			server::SendError::Other(TraceSocketError::Socket(err))
+		},
+	}
+}
+
+impl<S, W> server::Socket for TraceSocket<S, W>
+where
+	S: server::Socket,
+	W: TraceWrite,
+{
+	type Error = TraceSocketError<S::Error, W::Error>;
+
+	fn recv(
+		&self,
+		buf: &mut [u8],
+	) -> Result<usize, server::RecvError<Self::Error>> {
+		let len = self.socket.recv(buf).map_err(|err| match err {
+			server::RecvError::ConnectionClosed(err) => {
+				let err = TraceSocketError::Socket(err);
+				server::RecvError::ConnectionClosed(err)
+			},
+			server::RecvError::WouldBlock(err) => {
+				let err = TraceSocketError::Socket(err);
+				server::RecvError::WouldBlock(err)
+			},
+			server::RecvError::Other(err) => {
+				server::RecvError::Other(TraceSocketError::Socket(err))
+			},
+		})?;
+		match self.record(RecordKind::Request, &[&buf[..len]]) {
+			Ok(()) => Ok(len),
+			Err(err) => {
+				let err = TraceSocketError::Write(err);
+				Err(server::RecvError::Other(err))
+			},
+		}
+	}
+
+	fn send(&self, buf: SendBuf) -> Result<(), server::SendError<Self::Error>> {
+		let (chunks, chunks_len) = copy_chunks(&buf);
+		self.socket.send(buf).map_err(map_send_error)?;
+		self.record(RecordKind::Reply, &chunks[..chunks_len])
+			.map_err(send_write_error)
+	}
+
+	fn can_send_fd(&self) -> bool {
+		self.socket.can_send_fd()
+	}
+
+	fn send_with_fd(
+		&self,
+		buf: SendBuf,
+		fd: i32,
+	) -> Result<(), server::SendError<Self::Error>> {
+		let (chunks, chunks_len) = copy_chunks(&buf);
+		self.socket.send_with_fd(buf, fd).map_err(map_send_error)?;
+		self.record(RecordKind::Reply, &chunks[..chunks_len])
+			.map_err(send_write_error)
+	}
+}
+
+impl<S, W> server::CuseSocket for TraceSocket<S, W>
+where
+	S: server::CuseSocket,
+	W: TraceWrite,
+{
+}
+
+impl<S, W> server::FuseSocket for TraceSocket<S, W>
+where
+	S: server::FuseSocket,
+	W: TraceWrite,
+{
+}
+
+// }}}
+
+// ReplaySocket {{{
+
+/// Errors that may be encountered by a [`ReplaySocket`].
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ReplayError {
+	/// The trace is malformed.
+	InvalidTrace(TraceError),
+
+	/// Every request in the trace has been received.
+	EndOfTrace,
+
+	/// The receive buffer is too small for the next recorded request.
+	BufferTooSmall,
+
+	/// A reply was sent after every recorded reply had been matched.
+	UnexpectedReply,
+
+	/// A reply doesn't match the recorded reply.
+	///
+	/// The value is the index of the mismatched reply, counting from zero.
+	ReplyMismatch(usize),
+}
+
+/// A server socket that replays the requests recorded in a trace.
+///
+/// Replies sent to a `ReplaySocket` are compared against the recorded
+/// replies, in the order they were recorded. Servers that reply out of order,
+/// for example because requests are handled by multiple threads, should be
+/// replayed with a single thread.
+///
+/// Receiving from a `ReplaySocket` after every recorded request has been
+/// received fails with [`server::RecvError::ConnectionClosed`].
+pub struct ReplaySocket<'a> {
+	requests: Cell<TraceReader<'a>>,
+	replies: Cell<TraceReader<'a>>,
+	replies_matched: Cell<usize>,
+}
+
+impl<'a> ReplaySocket<'a> {
+	/// Creates a new `ReplaySocket` for the given trace.
+	pub fn new(trace: &'a [u8]) -> Result<ReplaySocket<'a>, TraceError> {
+		let reader = TraceReader::new(trace)?;
+		for record in reader {
+			record?;
+		}
+		Ok(Self {
+			requests: Cell::new(reader),
+			replies: Cell::new(reader),
+			replies_matched: Cell::new(0),
+		})
+	}
+
+	/// Returns the number of recorded replies that have been matched.
+	#[inline]
+	#[must_use]
+	pub fn replies_matched(&self) -> usize {
+		self.replies_matched.get()
+	}
+
+	/// Returns `true` if every recorded reply has been matched.
+	#[must_use]
+	pub fn is_done(&self) -> bool {
+		next_of_kind(self.replies.get(), RecordKind::Reply).is_none()
+	}
+
+	fn check_reply(&self, chunks: &[&[u8]]) -> Result<(), ReplayError> {
+		let index = self.replies_matched.get();
+		let (recorded, rest) =
+			match next_of_kind(self.replies.get(), RecordKind::Reply) {
+				Some(next) => next,
+				None => return Err(ReplayError::UnexpectedReply),
+			};
+		let mut remaining = recorded.message;
+		for chunk in chunks {
+			match remaining.strip_prefix(*chunk) {
+				Some(tail) => remaining = tail,
+				None => return Err(ReplayError::ReplyMismatch(index)),
+			}
+		}
+		if !remaining.is_empty() {
+			return Err(ReplayError::ReplyMismatch(index));
+		}
+		self.replies.set(rest);
+		self.replies_matched.set(index + 1);
+		Ok(())
+	}
+}
+
+// Returns the next record of the given kind, and a reader positioned after
+// it. The trace is validated when a `ReplaySocket` is created, so errors
+// can't be encountered here.
+fn next_of_kind<'a>(
+	mut reader: TraceReader<'a>,
+	kind: RecordKind,
+) -> Option<(Record<'a>, TraceReader<'a>)> {
+	while let Some(record) = reader.next() {
+		match record {
+			Ok(record) if record.kind == kind => return Some((record, reader)),
+			_ => {},
+		}
+	}
+	None
+}
+
+impl fmt::Debug for ReplaySocket<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_struct("ReplaySocket")
+			.field("replies_matched", &self.replies_matched.get())
+			.finish()
+	}
+}
+
+impl server::Socket for ReplaySocket<'_> {
+	type Error = ReplayError;
+
+	fn recv(
+		&self,
+		buf: &mut [u8],
+	) -> Result<usize, server::RecvError<ReplayError>> {
+		let (request, rest) =
+			match next_of_kind(self.requests.get(), RecordKind::Request) {
+				Some(next) => next,
+				None => {
+					let err = ReplayError::EndOfTrace;
+					return Err(server::RecvError::ConnectionClosed(err));
+				},
+			};
+		let request = request.message;
+		if request.len() > buf.len() {
+			// Leave the request to be received into a larger buffer.
+			let err = ReplayError::BufferTooSmall;
+			return Err(server::RecvError::Other(err));
+		}
+		self.requests.set(rest);
+		buf[..request.len()].copy_from_slice(request);
+		Ok(request.len())
+	}
+
+	fn send(&self, buf: SendBuf) -> Result<(), server::SendError<ReplayError>> {
+		self.check_reply(buf.chunks()).map_err(server::SendError::Other)
+	}
+}
+
+impl server::CuseSocket for ReplaySocket<'_> {}
+
+impl server::FuseSocket for ReplaySocket<'_> {}
+
+// }}}
@@ -0,0 +1,237 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::cell::RefCell;
+
+use fuse::kernel;
+use fuse::os::OsError;
+use fuse::server;
+use fuse::testing::{RequestBuilder, SocketPair};
+use fuse::trace::{
+	RecordKind,
+	ReplayError,
+	ReplaySocket,
+	TraceError,
+	TraceReader,
+	TraceSocket,
+	TraceSocketError,
+	TraceWrite,
+};
+
+use fuse_testutil as testutil;
+
+struct VecWriter(RefCell<Vec<u8>>);
+
+impl TraceWrite for VecWriter {
+	type Error = ();
+
+	fn write(&self, chunks: &[&[u8]]) -> Result<(), ()> {
+		let mut buf = self.0.borrow_mut();
+		for chunk in chunks {
+			buf.extend_from_slice(chunk);
+		}
+		Ok(())
+	}
+}
+
+fn queue_requests(pair: &SocketPair) {
+	let layout = server::FuseLayout::new(&testutil::new!(kernel::fuse_init_out {
+		major: kernel::FUSE_KERNEL_VERSION,
+		minor: kernel::FUSE_KERNEL_MINOR_VERSION,
+	}))
+	.unwrap();
+	let mut builder = RequestBuilder::new(layout);
+	let mut buf = fuse::io::MinReadBuffer::new();
+
+	let init = testutil::new!(kernel::fuse_init_in {
+		major: kernel::FUSE_KERNEL_VERSION,
+		minor: kernel::FUSE_KERNEL_MINOR_VERSION,
+	});
+	let request = builder.init(buf.as_aligned_slice_mut(), &init).unwrap();
+	pair.kernel().push_request(request.get()).unwrap();
+
+	for (request_id, node_id) in [(2, 1), (3, 10)] {
+		builder.set_request_id(request_id);
+		let request = builder.getattr(
+			buf.as_aligned_slice_mut(),
+			node_id,
+			&kernel::fuse_getattr_in::new(),
+		);
+		pair.kernel().push_request(request.unwrap().get()).unwrap();
+	}
+}
+
+// Replies ENOENT to every request for node 10, and ENOSYS otherwise.
+fn serve<S: server::FuseSocket>(
+	socket: S,
+) -> Result<(), server::ServerError<S::Error>> {
+	let conn = server::FuseConnection::connect(socket, |_, _| {})?;
+	let mut buf = fuse::io::MinReadBuffer::new();
+	while let Some(request) = conn.recv(buf.as_aligned_slice_mut())? {
+		let err = if request.header().node_id().map(|id| id.get()) == Some(10)
+		{
+			OsError::NOT_FOUND
+		} else {
+			OsError::UNIMPLEMENTED
+		};
+		conn.reply(request.id()).err(err)?;
+	}
+	Ok(())
+}
+
+fn record_trace() -> Vec<u8> {
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	queue_requests(&pair);
+
+	let writer = VecWriter(RefCell::new(Vec::new()));
+	serve(TraceSocket::new(pair.server(), &writer).unwrap()).unwrap();
+	writer.0.into_inner()
+}
+
+#[test]
+fn record() {
+	let trace = record_trace();
+	assert_eq!(&trace[..8], b"FUSETRC1");
+
+	let records: Vec<_> = TraceReader::new(&trace)
+		.unwrap()
+		.map(|record| record.unwrap())
+		.collect();
+	let kinds: Vec<_> = records.iter().map(|record| record.kind()).collect();
+	assert_eq!(kinds, [
+		RecordKind::Request,
+		RecordKind::Reply,
+		RecordKind::Request,
+		RecordKind::Reply,
+		RecordKind::Request,
+		RecordKind::Reply,
+	]);
+
+	// Recorded messages are byte-exact copies of the messages on the wire.
+	let mut request_buf = [0u8; 1024];
+	let mut reply_buf = [0u8; 1024];
+	let pair = SocketPair::new(&mut request_buf, &mut reply_buf);
+	queue_requests(&pair);
+	serve(pair.server()).unwrap();
+
+	let mut buf = [0u8; 1024];
+	for record in records.iter().filter(|r| r.kind() == RecordKind::Reply) {
+		let len = pair.kernel().pop_reply(&mut buf).unwrap();
+		assert_eq!(record.message(), &buf[..len]);
+	}
+}
+
+#[test]
+fn replay() {
+	let trace = record_trace();
+	let socket = ReplaySocket::new(&trace).unwrap();
+	serve(&socket).unwrap();
+	assert_eq!(socket.replies_matched(), 3);
+	assert!(socket.is_done());
+}
+
+#[test]
+fn replay_mismatch() {
+	let trace = record_trace();
+	let socket = ReplaySocket::new(&trace).unwrap();
+
+	let conn = server::FuseConnection::connect(&socket, |_, _| {}).unwrap();
+	let mut buf = fuse::io::MinReadBuffer::new();
+	let request = conn.recv(buf.as_aligned_slice_mut()).unwrap().unwrap();
+	let err = conn.reply(request.id()).err(OsError::NOT_FOUND).unwrap_err();
+	assert_eq!(err, server::SendError::Other(ReplayError::ReplyMismatch(1)));
+	assert_eq!(socket.replies_matched(), 1);
+	assert!(!socket.is_done());
+
+	// The mismatched reply can be retried.
+	conn.reply(request.id()).err(OsError::UNIMPLEMENTED).unwrap();
+	assert_eq!(socket.replies_matched(), 2);
+}
+
+#[test]
+fn replay_buffer_too_small() {
+	let trace = record_trace();
+	let socket = ReplaySocket::new(&trace).unwrap();
+
+	let mut buf = [0u8; 16];
+	let err = server::Socket::recv(&socket, &mut buf).unwrap_err();
+	assert_eq!(err, server::RecvError::Other(ReplayError::BufferTooSmall));
+
+	let mut buf = [0u8; 1024];
+	let len = server::Socket::recv(&socket, &mut buf).unwrap();
+	assert!(len > 16);
+}
+
+struct NonblockSocket;
+
+impl server::Socket for NonblockSocket {
+	type Error = ();
+
+	fn recv(&self, _buf: &mut [u8]) -> Result<usize, server::RecvError<()>> {
+		Err(server::RecvError::WouldBlock(()))
+	}
+
+	fn send(
+		&self,
+		_buf: fuse::io::SendBuf,
+	) -> Result<(), server::SendError<()>> {
+		Ok(())
+	}
+}
+
+#[test]
+fn record_would_block() {
+	let writer = VecWriter(RefCell::new(Vec::new()));
+	let socket = TraceSocket::new(NonblockSocket, &writer).unwrap();
+
+	let mut buf = [0u8; 1024];
+	let err = server::Socket::recv(&socket, &mut buf).unwrap_err();
+	let expect = server::RecvError::WouldBlock(TraceSocketError::Socket(()));
+	assert_eq!(err, expect);
+
+	// Nothing is recorded if no request was received.
+	assert_eq!(writer.0.into_inner(), b"FUSETRC1");
+}
+
+#[test]
+fn reader_errors() {
+	assert_eq!(
+		TraceReader::new(b"FUSETRC2").unwrap_err(),
+		TraceError::InvalidMagic,
+	);
+	assert_eq!(TraceReader::new(b"FUSETRC1").unwrap().count(), 0);
+
+	let truncated_header = b"FUSETRC1\x04\x00\x00";
+	let mut reader = TraceReader::new(truncated_header).unwrap();
+	assert_eq!(reader.next().unwrap().unwrap_err(), TraceError::UnexpectedEof);
+	assert!(reader.next().is_none());
+
+	let truncated_message = b"FUSETRC1\x04\x00\x00\x00\x01\x00\x00\x00abc";
+	let mut reader = TraceReader::new(truncated_message).unwrap();
+	assert_eq!(reader.next().unwrap().unwrap_err(), TraceError::UnexpectedEof);
+
+	let invalid_kind = b"FUSETRC1\x00\x00\x00\x00\x03\x00\x00\x00";
+	let mut reader = TraceReader::new(invalid_kind).unwrap();
+	let err = reader.next().unwrap().unwrap_err();
+	assert_eq!(err, TraceError::InvalidRecordKind(3));
+
+	assert_eq!(
+		ReplaySocket::new(invalid_kind).unwrap_err(),
+		TraceError::InvalidRecordKind(3),
+	);
+}
@@ -15,6 +15,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use core::fmt;
+use core::time::Duration;
 
 /// A measurement of Unix time with nanosecond precision.
 ///
@@ -99,6 +100,114 @@ impl UnixTime {
 	pub const fn nanos(&self) -> u32 {
 		self.nanos
 	}
+
+	/// Returns the current time, as reported by [`SystemTime::now`].
+	///
+	/// [`SystemTime::now`]: std::time::SystemTime::now
+	#[cfg(feature = "std")]
+	#[must_use]
+	pub fn now() -> UnixTime {
+		Self::from_system_time(std::time::SystemTime::now())
+	}
+
+	/// Converts a [`SystemTime`] into a `UnixTime`.
+	///
+	/// Times outside the range of `UnixTime` are saturated to the earliest
+	/// or latest representable time.
+	///
+	/// [`SystemTime`]: std::time::SystemTime
+	#[cfg(feature = "std")]
+	#[must_use]
+	pub fn from_system_time(time: std::time::SystemTime) -> UnixTime {
+		const MAX: UnixTime = UnixTime {
+			seconds: i64::MAX,
+			nanos: crate::internal::timestamp::MAX_NANOS,
+		};
+		const MIN: UnixTime = UnixTime {
+			seconds: i64::MIN,
+			nanos: 0,
+		};
+
+		match time.duration_since(std::time::UNIX_EPOCH) {
+			Ok(after) => match i64::try_from(after.as_secs()) {
+				Ok(seconds) => Self {
+					seconds,
+					nanos: after.subsec_nanos(),
+				},
+				Err(_) => MAX,
+			},
+			Err(err) => {
+				let before = err.duration();
+				let mut seconds = match i64::try_from(before.as_secs()) {
+					Ok(seconds) => -seconds,
+					Err(_) => return MIN,
+				};
+				let mut nanos = before.subsec_nanos();
+				if nanos > 0 {
+					seconds = match seconds.checked_sub(1) {
+						Some(seconds) => seconds,
+						None => return MIN,
+					};
+					nanos = 1_000_000_000 - nanos;
+				}
+				Self { seconds, nanos }
+			},
+		}
+	}
+}
+
+/// Converts a duration since the Unix epoch into a `UnixTime`.
+impl TryFrom<Duration> for UnixTime {
+	type Error = UnixTimeRangeError;
+
+	/// Fails if the duration is longer than [`i64::MAX`] seconds.
+	fn try_from(since_epoch: Duration) -> Result<UnixTime, UnixTimeRangeError> {
+		match i64::try_from(since_epoch.as_secs()) {
+			Ok(seconds) => Ok(Self {
+				seconds,
+				nanos: since_epoch.subsec_nanos(),
+			}),
+			Err(_) => Err(UnixTimeRangeError { _priv: () }),
+		}
+	}
+}
+
+/// Converts a `UnixTime` into a duration since the Unix epoch.
+impl TryFrom<UnixTime> for Duration {
+	type Error = UnixTimeRangeError;
+
+	/// Fails if the time is before the Unix epoch.
+	fn try_from(time: UnixTime) -> Result<Duration, UnixTimeRangeError> {
+		match u64::try_from(time.seconds) {
+			Ok(seconds) => Ok(Duration::new(seconds, time.nanos)),
+			Err(_) => Err(UnixTimeRangeError { _priv: () }),
+		}
+	}
+}
+
+/// Converts a `UnixTime` into a [`SystemTime`].
+///
+/// [`SystemTime`]: std::time::SystemTime
+#[cfg(feature = "std")]
+impl TryFrom<UnixTime> for std::time::SystemTime {
+	type Error = UnixTimeRangeError;
+
+	/// Fails if the time can't be represented by `SystemTime` on the current
+	/// platform.
+	fn try_from(
+		time: UnixTime,
+	) -> Result<std::time::SystemTime, UnixTimeRangeError> {
+		let epoch = std::time::UNIX_EPOCH;
+		let system_time = if time.seconds >= 0 {
+			let after = Duration::new(time.seconds as u64, time.nanos);
+			epoch.checked_add(after)
+		} else {
+			let before = Duration::from_secs(time.seconds.unsigned_abs());
+			let nanos = Duration::from_nanos(u64::from(time.nanos));
+			epoch.checked_sub(before).and_then(|t| t.checked_add(nanos))
+		};
+		system_time.ok_or(UnixTimeRangeError { _priv: () })
+	}
 }
 
 impl fmt::Debug for UnixTime {
@@ -108,3 +217,44 @@ impl fmt::Debug for UnixTime {
 			.finish()
 	}
 }
+
+// Serialized as a `(seconds, nanos)` tuple.
+#[cfg(feature = "serde")]
+impl serde::Serialize for UnixTime {
+	fn serialize<S: serde::Serializer>(
+		&self,
+		serializer: S,
+	) -> Result<S::Ok, S::Error> {
+		let value = (self.seconds, self.nanos);
+		serde::Serialize::serialize(&value, serializer)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for UnixTime {
+	fn deserialize<D: serde::Deserializer<'de>>(
+		deserializer: D,
+	) -> Result<UnixTime, D::Error> {
+		use serde::de::{Error, Unexpected};
+		let (seconds, nanos) = serde::Deserialize::deserialize(deserializer)?;
+		UnixTime::new(seconds, nanos).ok_or_else(|| {
+			let unexpected = Unexpected::Unsigned(u64::from(nanos));
+			Error::invalid_value(unexpected, &"nanoseconds below 1,000,000,000")
+		})
+	}
+}
+
+/// An error returned when a time is out of range for a conversion.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct UnixTimeRangeError {
+	_priv: (),
+}
+
+impl fmt::Display for UnixTimeRangeError {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.write_str("time out of range for conversion")
+	}
+}
+
+#[cfg(feature = "std")]
+impl core::error::Error for UnixTimeRangeError {}
@@ -0,0 +1,122 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+
+use core::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use fuse::UnixTime;
+
+fn unix_time(seconds: i64, nanos: u32) -> UnixTime {
+	UnixTime::new(seconds, nanos).unwrap()
+}
+
+#[test]
+fn from_system_time() {
+	let after = UNIX_EPOCH + Duration::new(1, 500_000_000);
+	assert_eq!(UnixTime::from_system_time(after), unix_time(1, 500_000_000));
+	assert_eq!(UnixTime::from_system_time(UNIX_EPOCH), UnixTime::EPOCH);
+}
+
+#[test]
+fn from_system_time_before_epoch() {
+	// Negative times are rounded down to whole seconds, with a positive
+	// nanoseconds value.
+	let before = UNIX_EPOCH - Duration::new(1, 500_000_000);
+	let time = UnixTime::from_system_time(before);
+	assert_eq!(time, unix_time(-2, 500_000_000));
+	assert_eq!(time.seconds(), -2);
+	assert_eq!(time.nanos(), 500_000_000);
+
+	let before = UNIX_EPOCH - Duration::from_secs(2);
+	assert_eq!(UnixTime::from_system_time(before), unix_time(-2, 0));
+
+	let before = UNIX_EPOCH - Duration::from_nanos(1);
+	assert_eq!(
+		UnixTime::from_system_time(before),
+		unix_time(-1, 999_999_999),
+	);
+}
+
+#[test]
+fn from_system_time_min() {
+	let min = UnixTime::from_seconds(i64::MIN);
+
+	// Exactly `i64::MIN` seconds before the epoch, which doesn't fit in an
+	// `i64` as a positive duration.
+	let before = UNIX_EPOCH.checked_sub(Duration::from_secs(1 << 63)).unwrap();
+	assert_eq!(UnixTime::from_system_time(before), min);
+
+	// Within the last second before `i64::MIN`.
+	let before = before + Duration::from_millis(500);
+	assert_eq!(
+		UnixTime::from_system_time(before),
+		unix_time(i64::MIN, 500_000_000),
+	);
+}
+
+#[test]
+fn into_system_time() {
+	let time = unix_time(1, 500_000_000);
+	let expect = UNIX_EPOCH + Duration::new(1, 500_000_000);
+	assert_eq!(SystemTime::try_from(time), Ok(expect));
+	assert_eq!(SystemTime::try_from(UnixTime::EPOCH), Ok(UNIX_EPOCH));
+}
+
+#[test]
+fn into_system_time_before_epoch() {
+	let time = unix_time(-2, 500_000_000);
+	let expect = UNIX_EPOCH - Duration::new(1, 500_000_000);
+	assert_eq!(SystemTime::try_from(time), Ok(expect));
+
+	let time = unix_time(i64::MIN, 500_000_000);
+	let system_time = SystemTime::try_from(time).unwrap();
+	assert_eq!(UnixTime::from_system_time(system_time), time);
+}
+
+#[test]
+fn system_time_round_trip() {
+	for time in [
+		unix_time(0, 1),
+		unix_time(-1, 0),
+		unix_time(-1, 1),
+		unix_time(-1, 999_999_999),
+		unix_time(1_700_000_000, 123_456_789),
+		unix_time(-1_700_000_000, 123_456_789),
+	] {
+		let system_time = SystemTime::try_from(time).unwrap();
+		assert_eq!(UnixTime::from_system_time(system_time), time);
+	}
+}
+
+#[test]
+fn duration_conversions() {
+	let duration = Duration::new(1, 500_000_000);
+	assert_eq!(UnixTime::try_from(duration), Ok(unix_time(1, 500_000_000)));
+	assert_eq!(Duration::try_from(unix_time(1, 500_000_000)), Ok(duration));
+
+	assert!(UnixTime::try_from(Duration::MAX).is_err());
+	assert!(Duration::try_from(unix_time(-1, 500_000_000)).is_err());
+}
+
+#[test]
+fn range_error() {
+	let err = Duration::try_from(unix_time(-1, 0)).unwrap_err();
+	assert_eq!(err.to_string(), "time out of range for conversion");
+
+	let err: Box<dyn std::error::Error> = Box::new(err);
+	assert!(err.source().is_none());
+}
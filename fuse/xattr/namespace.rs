@@ -0,0 +1,228 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::perm::{may_access, R_OK, W_OK};
+use crate::{Credentials, Errno, FileType, NodeAttr};
+
+const S_ISVTX: u32 = 0o1000;
+
+// FreeBSD `EXTATTR_NAMESPACE_*` values from `<sys/extattr.h>`.
+const EXTATTR_NAMESPACE_USER: i32 = 1;
+const EXTATTR_NAMESPACE_SYSTEM: i32 = 2;
+
+/// The namespace of an extended attribute, as determined by its name prefix.
+///
+/// The kernel passes extended attribute names to the server with their
+/// prefix intact, so that `getfattr -n user.comment` results in a
+/// `FUSE_GETXATTR` request for `"user.comment"`. Servers that store extended
+/// attributes are responsible for enforcing the access rules of each
+/// namespace, which [`Namespace::check_get`] and [`Namespace::check_set`]
+/// implement for the standard Linux namespaces.
+///
+/// The superuser is identified by user ID 0. Servers that track the
+/// capabilities of their callers (such as `CAP_SYS_ADMIN`) may implement
+/// more precise checks.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum Namespace {
+	/// Attributes with the `"user."` prefix, which are accessible to any
+	/// process with permission to read or write the node.
+	User,
+
+	/// Attributes with the `"system."` prefix, such as POSIX ACLs, which
+	/// have meanings defined by the kernel.
+	System,
+
+	/// Attributes with the `"security."` prefix, which are used by security
+	/// modules such as SELinux.
+	Security,
+
+	/// Attributes with the `"trusted."` prefix, which are only accessible to
+	/// the superuser.
+	Trusted,
+}
+
+impl Namespace {
+	/// Returns the namespace of an extended attribute name, or `None` if
+	/// the name doesn't start with a known prefix.
+	#[must_use]
+	pub fn from_name(name: &[u8]) -> Option<Namespace> {
+		Self::split_name(name).map(|(namespace, _)| namespace)
+	}
+
+	/// Splits an extended attribute name into its namespace and the
+	/// remainder of the name, which follows the namespace's prefix.
+	///
+	/// Returns `None` if the name doesn't start with a known prefix.
+	#[must_use]
+	pub fn split_name(name: &[u8]) -> Option<(Namespace, &[u8])> {
+		const NAMESPACES: [Namespace; 4] = [
+			Namespace::User,
+			Namespace::System,
+			Namespace::Security,
+			Namespace::Trusted,
+		];
+		NAMESPACES.into_iter().find_map(|namespace| {
+			let prefix = namespace.prefix().as_bytes();
+			let rest = name.strip_prefix(prefix)?;
+			Some((namespace, rest))
+		})
+	}
+
+	/// Returns the name prefix of the namespace, including the trailing
+	/// `'.'`.
+	#[must_use]
+	pub const fn prefix(self) -> &'static str {
+		match self {
+			Namespace::User => "user.",
+			Namespace::System => "system.",
+			Namespace::Security => "security.",
+			Namespace::Trusted => "trusted.",
+		}
+	}
+
+	/// Checks whether an attribute in this namespace may be read by a
+	/// process with the given credentials.
+	///
+	/// # Errors
+	///
+	/// Returns [`Errno::ENOATTR`] if the attribute should appear to not
+	/// exist, or [`Errno::EACCES`] if the process doesn't have permission
+	/// to read the node.
+	pub fn check_get(
+		self,
+		attr: &NodeAttr,
+		creds: &Credentials,
+	) -> Result<(), Errno> {
+		match self {
+			Namespace::System | Namespace::Security => Ok(()),
+			Namespace::Trusted => {
+				if creds.user_id() != 0 {
+					return Err(Errno::ENOATTR);
+				}
+				Ok(())
+			},
+			Namespace::User => {
+				if !is_regular_or_dir(attr) {
+					return Err(Errno::ENOATTR);
+				}
+				if !may_access(attr, creds, R_OK) {
+					return Err(Errno::EACCES);
+				}
+				Ok(())
+			},
+		}
+	}
+
+	/// Checks whether an attribute in this namespace may be set or removed
+	/// by a process with the given credentials.
+	///
+	/// The `"system."` and `"security."` namespaces have no access rules of
+	/// their own. Servers that store POSIX ACLs should check them with the
+	/// rules of the [`posix_acl`](super::posix_acl) module.
+	///
+	/// # Errors
+	///
+	/// Returns [`Errno::EPERM`] if attributes in this namespace can't be
+	/// changed by the process, or [`Errno::EACCES`] if the process doesn't
+	/// have permission to write the node.
+	pub fn check_set(
+		self,
+		attr: &NodeAttr,
+		creds: &Credentials,
+	) -> Result<(), Errno> {
+		match self {
+			Namespace::System | Namespace::Security => Ok(()),
+			Namespace::Trusted => {
+				if creds.user_id() != 0 {
+					return Err(Errno::EPERM);
+				}
+				Ok(())
+			},
+			Namespace::User => {
+				if !is_regular_or_dir(attr) {
+					return Err(Errno::EPERM);
+				}
+				// As with `unlink()`, user attributes of a directory with
+				// the sticky bit set may only be changed by its owner.
+				let is_sticky = attr.mode().get() & S_ISVTX != 0;
+				let is_owner = creds.user_id() == attr.user_id();
+				let is_dir = file_type(attr) == Some(FileType::Directory);
+				if is_dir && is_sticky && !is_owner && creds.user_id() != 0 {
+					return Err(Errno::EPERM);
+				}
+				if !may_access(attr, creds, W_OK) {
+					return Err(Errno::EACCES);
+				}
+				Ok(())
+			},
+		}
+	}
+
+	/// Returns whether names in this namespace should be included in the
+	/// response to a `FUSE_LISTXATTR` request from a process with the given
+	/// credentials.
+	///
+	/// Names in the `"trusted."` namespace are hidden from processes other
+	/// than the superuser.
+	#[must_use]
+	pub fn is_listable(self, creds: &Credentials) -> bool {
+		match self {
+			Namespace::Trusted => creds.user_id() == 0,
+			_ => true,
+		}
+	}
+
+	/// Returns the FreeBSD `EXTATTR_NAMESPACE_*` value corresponding to
+	/// this namespace.
+	///
+	/// The FreeBSD FUSE client maps its `EXTATTR_NAMESPACE_USER` and
+	/// `EXTATTR_NAMESPACE_SYSTEM` namespaces to the `"user."` and
+	/// `"system."` prefixes, so other namespaces are inaccessible from
+	/// FreeBSD and return `None`.
+	#[must_use]
+	pub const fn freebsd_extattr_namespace(self) -> Option<i32> {
+		match self {
+			Namespace::User => Some(EXTATTR_NAMESPACE_USER),
+			Namespace::System => Some(EXTATTR_NAMESPACE_SYSTEM),
+			Namespace::Security | Namespace::Trusted => None,
+		}
+	}
+
+	/// Returns the namespace corresponding to a FreeBSD
+	/// `EXTATTR_NAMESPACE_*` value, or `None` if the value is unknown.
+	#[must_use]
+	pub const fn from_freebsd_extattr_namespace(
+		attrnamespace: i32,
+	) -> Option<Namespace> {
+		match attrnamespace {
+			EXTATTR_NAMESPACE_USER => Some(Namespace::User),
+			EXTATTR_NAMESPACE_SYSTEM => Some(Namespace::System),
+			_ => None,
+		}
+	}
+}
+
+fn file_type(attr: &NodeAttr) -> Option<FileType> {
+	FileType::from_mode(attr.mode())
+}
+
+fn is_regular_or_dir(attr: &NodeAttr) -> bool {
+	matches!(
+		file_type(attr),
+		Some(FileType::Regular | FileType::Directory),
+	)
+}
@@ -0,0 +1,138 @@
+// Copyright 2024 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use fuse::xattr::Namespace;
+use fuse::{Credentials, Errno, FileMode, NodeAttr, NodeId};
+
+fn node_attr(mode: FileMode, uid: u32) -> NodeAttr {
+	let mut attr = NodeAttr::new(NodeId::new(2).unwrap());
+	attr.set_mode(mode);
+	attr.set_user_id(uid);
+	attr.set_group_id(uid);
+	attr
+}
+
+#[test]
+fn from_name() {
+	assert_eq!(Namespace::from_name(b"user.comment"), Some(Namespace::User));
+	assert_eq!(
+		Namespace::from_name(b"system.posix_acl_access"),
+		Some(Namespace::System),
+	);
+	assert_eq!(
+		Namespace::from_name(b"security.selinux"),
+		Some(Namespace::Security),
+	);
+	assert_eq!(
+		Namespace::from_name(b"trusted.overlay.opaque"),
+		Some(Namespace::Trusted),
+	);
+	assert_eq!(Namespace::from_name(b"user"), None);
+	assert_eq!(Namespace::from_name(b"users.comment"), None);
+	assert_eq!(Namespace::from_name(b"os2.comment"), None);
+	assert_eq!(Namespace::from_name(b""), None);
+
+	assert_eq!(
+		Namespace::split_name(b"user.comment"),
+		Some((Namespace::User, &b"comment"[..])),
+	);
+	assert_eq!(
+		Namespace::split_name(b"user."),
+		Some((Namespace::User, &b""[..])),
+	);
+
+	for namespace in [
+		Namespace::User,
+		Namespace::System,
+		Namespace::Security,
+		Namespace::Trusted,
+	] {
+		let prefix = namespace.prefix().as_bytes();
+		assert_eq!(Namespace::from_name(prefix), Some(namespace));
+	}
+}
+
+#[test]
+fn check_trusted() {
+	let attr = node_attr(FileMode::S_IFREG | 0o666, 1000);
+	let root = Credentials::new(0, 0);
+	let user = Credentials::new(1000, 1000);
+
+	assert_eq!(Namespace::Trusted.check_get(&attr, &root), Ok(()));
+	assert_eq!(Namespace::Trusted.check_set(&attr, &root), Ok(()));
+	assert_eq!(Namespace::Trusted.check_get(&attr, &user), Err(Errno::ENOATTR));
+	assert_eq!(Namespace::Trusted.check_set(&attr, &user), Err(Errno::EPERM));
+
+	assert!(Namespace::Trusted.is_listable(&root));
+	assert!(!Namespace::Trusted.is_listable(&user));
+	assert!(Namespace::User.is_listable(&user));
+}
+
+#[test]
+fn check_user() {
+	let file = node_attr(FileMode::S_IFREG | 0o644, 1000);
+	let owner = Credentials::new(1000, 1000);
+	let other = Credentials::new(2000, 2000);
+
+	assert_eq!(Namespace::User.check_get(&file, &owner), Ok(()));
+	assert_eq!(Namespace::User.check_set(&file, &owner), Ok(()));
+	assert_eq!(Namespace::User.check_get(&file, &other), Ok(()));
+	assert_eq!(Namespace::User.check_set(&file, &other), Err(Errno::EACCES));
+
+	// User attributes are only supported on files and directories.
+	let symlink = node_attr(FileMode::S_IFLNK | 0o777, 1000);
+	assert_eq!(
+		Namespace::User.check_get(&symlink, &owner),
+		Err(Errno::ENOATTR),
+	);
+	assert_eq!(Namespace::User.check_set(&symlink, &owner), Err(Errno::EPERM));
+
+	// Sticky directories restrict changes to the owner.
+	let dir = node_attr(FileMode::S_IFDIR | 0o1777, 1000);
+	assert_eq!(Namespace::User.check_set(&dir, &owner), Ok(()));
+	assert_eq!(Namespace::User.check_set(&dir, &other), Err(Errno::EPERM));
+	assert_eq!(Namespace::User.check_get(&dir, &other), Ok(()));
+	let root = Credentials::new(0, 0);
+	assert_eq!(Namespace::User.check_set(&dir, &root), Ok(()));
+}
+
+#[test]
+fn check_system_security() {
+	let attr = node_attr(FileMode::S_IFREG | 0o600, 1000);
+	let other = Credentials::new(2000, 2000);
+	for namespace in [Namespace::System, Namespace::Security] {
+		assert_eq!(namespace.check_get(&attr, &other), Ok(()));
+		assert_eq!(namespace.check_set(&attr, &other), Ok(()));
+	}
+}
+
+#[test]
+fn freebsd_extattr_namespace() {
+	assert_eq!(Namespace::User.freebsd_extattr_namespace(), Some(1));
+	assert_eq!(Namespace::System.freebsd_extattr_namespace(), Some(2));
+	assert_eq!(Namespace::Security.freebsd_extattr_namespace(), None);
+	assert_eq!(Namespace::Trusted.freebsd_extattr_namespace(), None);
+
+	assert_eq!(
+		Namespace::from_freebsd_extattr_namespace(1),
+		Some(Namespace::User),
+	);
+	assert_eq!(
+		Namespace::from_freebsd_extattr_namespace(2),
+		Some(Namespace::System),
+	);
+	assert_eq!(Namespace::from_freebsd_extattr_namespace(0), None);
+}
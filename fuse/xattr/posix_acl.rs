@@ -0,0 +1,462 @@
+// Copyright 2022 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! POSIX access control lists.
+//!
+//! If the `FUSE_POSIX_ACL` init flag is negotiated, the kernel stores a
+//! node's ACLs in the [`XATTR_NAME_ACCESS`] and [`XATTR_NAME_DEFAULT`]
+//! extended attributes. The attribute values use the Linux binary ACL format,
+//! which this module can parse, validate, and serialize.
+
+use core::fmt;
+
+use crate::perm::{superuser_may_access, R_OK, W_OK, X_OK};
+use crate::{Credentials, NodeAttr};
+
+/// Name of the extended attribute containing a node's access ACL.
+pub const XATTR_NAME_ACCESS: &str = "system.posix_acl_access";
+
+/// Name of the extended attribute containing a directory's default ACL,
+/// which is inherited by newly created nodes.
+pub const XATTR_NAME_DEFAULT: &str = "system.posix_acl_default";
+
+const POSIX_ACL_XATTR_VERSION: u32 = 2;
+const HEADER_LEN: usize = 4;
+const ENTRY_LEN: usize = 8;
+const ACL_UNDEFINED_ID: u32 = u32::MAX;
+
+const ACL_USER_OBJ: u16 = 0x01;
+const ACL_USER: u16 = 0x02;
+const ACL_GROUP_OBJ: u16 = 0x04;
+const ACL_GROUP: u16 = 0x08;
+const ACL_MASK: u16 = 0x10;
+const ACL_OTHER: u16 = 0x20;
+
+// PosixAclError {{{
+
+/// Errors that may be encountered when parsing or serializing an ACL.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum PosixAclError {
+	/// The ACL's length is not a whole number of entries, or the output
+	/// buffer is too small.
+	InvalidLength,
+
+	/// The ACL's format version is not supported.
+	UnsupportedVersion(u32),
+
+	/// An entry has an unknown tag or invalid permission bits.
+	InvalidEntry,
+
+	/// The entries are not in canonical order, or a required entry is
+	/// missing.
+	InvalidStructure,
+}
+
+// }}}
+
+// AclTag {{{
+
+/// The subject of an ACL entry.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum AclTag {
+	/// The owner of the node (`ACL_USER_OBJ`).
+	UserObj,
+
+	/// The user with the given user ID (`ACL_USER`).
+	User(u32),
+
+	/// The owning group of the node (`ACL_GROUP_OBJ`).
+	GroupObj,
+
+	/// The group with the given group ID (`ACL_GROUP`).
+	Group(u32),
+
+	/// The maximum permissions granted by `User`, `GroupObj`, and `Group`
+	/// entries (`ACL_MASK`).
+	Mask,
+
+	/// Users that don't match any other entry (`ACL_OTHER`).
+	Other,
+}
+
+impl AclTag {
+	fn decode(tag: u16, id: u32) -> Option<AclTag> {
+		match tag {
+			ACL_USER_OBJ => Some(AclTag::UserObj),
+			ACL_USER => Some(AclTag::User(id)),
+			ACL_GROUP_OBJ => Some(AclTag::GroupObj),
+			ACL_GROUP => Some(AclTag::Group(id)),
+			ACL_MASK => Some(AclTag::Mask),
+			ACL_OTHER => Some(AclTag::Other),
+			_ => None,
+		}
+	}
+
+	fn encode(self) -> (u16, u32) {
+		match self {
+			AclTag::UserObj => (ACL_USER_OBJ, ACL_UNDEFINED_ID),
+			AclTag::User(id) => (ACL_USER, id),
+			AclTag::GroupObj => (ACL_GROUP_OBJ, ACL_UNDEFINED_ID),
+			AclTag::Group(id) => (ACL_GROUP, id),
+			AclTag::Mask => (ACL_MASK, ACL_UNDEFINED_ID),
+			AclTag::Other => (ACL_OTHER, ACL_UNDEFINED_ID),
+		}
+	}
+}
+
+// }}}
+
+// AclEntry {{{
+
+/// An entry in a POSIX ACL.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct AclEntry {
+	tag: AclTag,
+	perms: u32,
+}
+
+impl AclEntry {
+	/// Creates a new `AclEntry`.
+	///
+	/// The `perms` are a combination of [`R_OK`], [`W_OK`], and [`X_OK`].
+	#[inline]
+	#[must_use]
+	pub const fn new(tag: AclTag, perms: u32) -> AclEntry {
+		Self { tag, perms }
+	}
+
+	/// Returns the subject of this entry.
+	#[inline]
+	#[must_use]
+	pub fn tag(&self) -> AclTag {
+		self.tag
+	}
+
+	/// Returns the permissions granted by this entry.
+	#[inline]
+	#[must_use]
+	pub fn perms(&self) -> u32 {
+		self.perms
+	}
+}
+
+// }}}
+
+// PosixAcl {{{
+
+/// A borrowed POSIX ACL, in the Linux extended attribute format.
+///
+/// An ACL with no entries is valid. Setting an ACL attribute to an empty ACL
+/// removes the ACL.
+#[derive(Clone, Copy)]
+pub struct PosixAcl<'a> {
+	entries: &'a [u8],
+}
+
+impl<'a> PosixAcl<'a> {
+	/// Parses and validates an ACL from an extended attribute value.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the ACL is malformed, or if its entries aren't
+	/// valid according to the rules enforced by the kernel.
+	pub fn parse(value: &'a [u8]) -> Result<PosixAcl<'a>, PosixAclError> {
+		if value.len() < HEADER_LEN {
+			return Err(PosixAclError::InvalidLength);
+		}
+		let (header, entries) = value.split_at(HEADER_LEN);
+		if entries.len() % ENTRY_LEN != 0 {
+			return Err(PosixAclError::InvalidLength);
+		}
+		let version = u32::from_le_bytes([
+			header[0], header[1], header[2], header[3],
+		]);
+		if version != POSIX_ACL_XATTR_VERSION {
+			return Err(PosixAclError::UnsupportedVersion(version));
+		}
+
+		let acl = PosixAcl { entries };
+		let mut validator = Validator::new();
+		for chunk in entries.chunks_exact(ENTRY_LEN) {
+			validator.push(decode_entry(chunk)?)?;
+		}
+		if !acl.is_empty() {
+			validator.finish()?;
+		}
+		Ok(acl)
+	}
+
+	/// Returns the number of entries in this ACL.
+	#[inline]
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.entries.len() / ENTRY_LEN
+	}
+
+	/// Returns `true` if this ACL has no entries.
+	#[inline]
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	/// Returns an iterator over the entries of this ACL.
+	#[must_use]
+	pub fn entries(&self) -> AclEntries<'a> {
+		AclEntries {
+			chunks: self.entries.chunks_exact(ENTRY_LEN),
+		}
+	}
+
+	/// Returns whether the given credentials grant access to a node with
+	/// this ACL.
+	///
+	/// The ACL is evaluated by the same algorithm as the kernel uses when the
+	/// `default_permissions` mount option is set. If the ACL is empty, then
+	/// the node's mode is checked by [`perm::may_access`].
+	///
+	/// [`perm::may_access`]: crate::perm::may_access
+	#[must_use]
+	pub fn may_access(
+		&self,
+		attr: &NodeAttr,
+		creds: &Credentials,
+		mask: u32,
+	) -> bool {
+		if self.is_empty() {
+			return crate::perm::may_access(attr, creds, mask);
+		}
+		let want = mask & (R_OK | W_OK | X_OK);
+		if creds.user_id() == 0 {
+			return superuser_may_access(attr, want);
+		}
+
+		let mut group_found = false;
+		for entry in self.entries() {
+			match entry.tag {
+				AclTag::UserObj => {
+					if creds.user_id() == attr.user_id() {
+						return entry.perms & want == want;
+					}
+				},
+				AclTag::User(uid) => {
+					if creds.user_id() == uid {
+						return self.masked_perms(entry) & want == want;
+					}
+				},
+				AclTag::GroupObj => {
+					if creds.in_group(attr.group_id()) {
+						group_found = true;
+						if entry.perms & want == want {
+							return self.masked_perms(entry) & want == want;
+						}
+					}
+				},
+				AclTag::Group(gid) => {
+					if creds.in_group(gid) {
+						group_found = true;
+						if entry.perms & want == want {
+							return self.masked_perms(entry) & want == want;
+						}
+					}
+				},
+				AclTag::Mask => {},
+				AclTag::Other => {
+					if group_found {
+						return false;
+					}
+					return entry.perms & want == want;
+				},
+			}
+		}
+		false
+	}
+
+	fn masked_perms(&self, entry: AclEntry) -> u32 {
+		for other in self.entries() {
+			if other.tag == AclTag::Mask {
+				return entry.perms & other.perms;
+			}
+		}
+		entry.perms
+	}
+}
+
+impl fmt::Debug for PosixAcl<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_list().entries(self.entries()).finish()
+	}
+}
+
+fn decode_entry(chunk: &[u8]) -> Result<AclEntry, PosixAclError> {
+	let tag = u16::from_le_bytes([chunk[0], chunk[1]]);
+	let perms = u16::from_le_bytes([chunk[2], chunk[3]]);
+	let id = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+	if u32::from(perms) & !(R_OK | W_OK | X_OK) != 0 {
+		return Err(PosixAclError::InvalidEntry);
+	}
+	match AclTag::decode(tag, id) {
+		Some(tag) => Ok(AclEntry::new(tag, u32::from(perms))),
+		None => Err(PosixAclError::InvalidEntry),
+	}
+}
+
+// }}}
+
+// AclEntries {{{
+
+/// Iterator over the entries of a [`PosixAcl`].
+#[derive(Clone)]
+pub struct AclEntries<'a> {
+	chunks: core::slice::ChunksExact<'a, u8>,
+}
+
+impl Iterator for AclEntries<'_> {
+	type Item = AclEntry;
+
+	fn next(&mut self) -> Option<AclEntry> {
+		let chunk = self.chunks.next()?;
+		// Entries were validated by `PosixAcl::parse()`.
+		decode_entry(chunk).ok()
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.chunks.size_hint()
+	}
+}
+
+impl ExactSizeIterator for AclEntries<'_> {}
+
+impl fmt::Debug for AclEntries<'_> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		fmt.debug_list().entries(self.clone()).finish()
+	}
+}
+
+// }}}
+
+// encode {{{
+
+/// Returns the length in bytes of an encoded ACL with `count` entries.
+#[must_use]
+pub const fn encoded_len(count: usize) -> usize {
+	HEADER_LEN + (count * ENTRY_LEN)
+}
+
+/// Serializes ACL entries in the Linux extended attribute format.
+///
+/// Returns the prefix of `storage` containing the encoded ACL.
+///
+/// # Errors
+///
+/// Returns an error if `storage` is shorter than [`encoded_len`], or if the
+/// entries are not a valid ACL. Entries must be in canonical order:
+/// `UserObj`, any `User` entries, `GroupObj`, any `Group` entries, `Mask`,
+/// and `Other`. The `Mask` entry is required if there are any `User` or
+/// `Group` entries.
+pub fn encode<'a>(
+	entries: &[AclEntry],
+	storage: &'a mut [u8],
+) -> Result<&'a [u8], PosixAclError> {
+	let len = encoded_len(entries.len());
+	if storage.len() < len {
+		return Err(PosixAclError::InvalidLength);
+	}
+
+	let mut validator = Validator::new();
+	for entry in entries {
+		if entry.perms & !(R_OK | W_OK | X_OK) != 0 {
+			return Err(PosixAclError::InvalidEntry);
+		}
+		validator.push(*entry)?;
+	}
+	if !entries.is_empty() {
+		validator.finish()?;
+	}
+
+	let (header, mut rest) = storage[..len].split_at_mut(HEADER_LEN);
+	header.copy_from_slice(&POSIX_ACL_XATTR_VERSION.to_le_bytes());
+	for entry in entries {
+		let (chunk, next) = rest.split_at_mut(ENTRY_LEN);
+		let (tag, id) = entry.tag.encode();
+		chunk[0..2].copy_from_slice(&tag.to_le_bytes());
+		chunk[2..4].copy_from_slice(&(entry.perms as u16).to_le_bytes());
+		chunk[4..8].copy_from_slice(&id.to_le_bytes());
+		rest = next;
+	}
+	Ok(&storage[..len])
+}
+
+// }}}
+
+// Validator {{{
+
+// Checks entry order by the same state machine as the kernel's
+// `posix_acl_valid()`.
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum ValidatorState {
+	UserObj,
+	User,
+	Group,
+	Other,
+	Done,
+}
+
+struct Validator {
+	state: ValidatorState,
+	needs_mask: bool,
+}
+
+impl Validator {
+	fn new() -> Self {
+		Self {
+			state: ValidatorState::UserObj,
+			needs_mask: false,
+		}
+	}
+
+	fn push(&mut self, entry: AclEntry) -> Result<(), PosixAclError> {
+		use ValidatorState as S;
+		self.state = match (entry.tag, self.state) {
+			(AclTag::UserObj, S::UserObj) => S::User,
+			(AclTag::User(_), S::User) => {
+				self.needs_mask = true;
+				S::User
+			},
+			(AclTag::GroupObj, S::User) => S::Group,
+			(AclTag::Group(_), S::Group) => {
+				self.needs_mask = true;
+				S::Group
+			},
+			(AclTag::Mask, S::Group) => S::Other,
+			(AclTag::Other, S::Other) => S::Done,
+			(AclTag::Other, S::Group) if !self.needs_mask => S::Done,
+			_ => return Err(PosixAclError::InvalidStructure),
+		};
+		Ok(())
+	}
+
+	fn finish(self) -> Result<(), PosixAclError> {
+		if self.state != ValidatorState::Done {
+			return Err(PosixAclError::InvalidStructure);
+		}
+		Ok(())
+	}
+}
+
+// }}}
@@ -0,0 +1,182 @@
+// Copyright 2022 John Millikin and the rust-fuse contributors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use fuse::perm::{R_OK, W_OK, X_OK};
+use fuse::xattr::posix_acl::{
+	encode,
+	encoded_len,
+	AclEntry,
+	AclTag,
+	PosixAcl,
+	PosixAclError,
+};
+use fuse::{Credentials, FileMode, NodeAttr, NodeId};
+
+fn file_attr(mode: u32, uid: u32, gid: u32) -> NodeAttr {
+	let mut attr = NodeAttr::new(NodeId::new(2).unwrap());
+	attr.set_mode(FileMode::S_IFREG | mode);
+	attr.set_user_id(uid);
+	attr.set_group_id(gid);
+	attr
+}
+
+const ENTRIES: &[AclEntry] = &[
+	AclEntry::new(AclTag::UserObj, R_OK | W_OK),
+	AclEntry::new(AclTag::User(1001), R_OK | W_OK),
+	AclEntry::new(AclTag::GroupObj, R_OK),
+	AclEntry::new(AclTag::Group(2001), R_OK | W_OK | X_OK),
+	AclEntry::new(AclTag::Mask, R_OK | X_OK),
+	AclEntry::new(AclTag::Other, 0),
+];
+
+#[test]
+fn encode_parse_roundtrip() {
+	let mut buf = [0u8; 64];
+	let encoded = encode(ENTRIES, &mut buf).unwrap();
+	assert_eq!(encoded.len(), encoded_len(ENTRIES.len()));
+	assert_eq!(encoded, &[
+		2, 0, 0, 0, // version
+		0x01, 0x00, 0x06, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, // user_obj
+		0x02, 0x00, 0x06, 0x00, 0xE9, 0x03, 0x00, 0x00, // user 1001
+		0x04, 0x00, 0x04, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, // group_obj
+		0x08, 0x00, 0x07, 0x00, 0xD1, 0x07, 0x00, 0x00, // group 2001
+		0x10, 0x00, 0x05, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, // mask
+		0x20, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, // other
+	][..]);
+
+	let acl = PosixAcl::parse(encoded).unwrap();
+	assert_eq!(acl.len(), ENTRIES.len());
+	assert!(acl.entries().eq(ENTRIES.iter().copied()));
+}
+
+#[test]
+fn encode_buffer_too_small() {
+	let mut buf = [0u8; 16];
+	assert_eq!(encode(ENTRIES, &mut buf), Err(PosixAclError::InvalidLength));
+}
+
+#[test]
+fn parse_empty() {
+	let acl = PosixAcl::parse(&[2, 0, 0, 0]).unwrap();
+	assert!(acl.is_empty());
+}
+
+#[test]
+fn parse_errors() {
+	assert_eq!(
+		PosixAcl::parse(&[2, 0, 0]).unwrap_err(),
+		PosixAclError::InvalidLength,
+	);
+	assert_eq!(
+		PosixAcl::parse(&[2, 0, 0, 0, 1]).unwrap_err(),
+		PosixAclError::InvalidLength,
+	);
+	assert_eq!(
+		PosixAcl::parse(&[1, 0, 0, 0]).unwrap_err(),
+		PosixAclError::UnsupportedVersion(1),
+	);
+	assert_eq!(
+		PosixAcl::parse(&[
+			2, 0, 0, 0,
+			0x40, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF,
+		]).unwrap_err(),
+		PosixAclError::InvalidEntry,
+	);
+	assert_eq!(
+		PosixAcl::parse(&[
+			2, 0, 0, 0,
+			0x01, 0x00, 0x08, 0x00, 0xFF, 0xFF, 0xFF, 0xFF,
+		]).unwrap_err(),
+		PosixAclError::InvalidEntry,
+	);
+}
+
+#[test]
+fn validate_structure() {
+	let mut buf = [0u8; 64];
+
+	// Minimal ACL, equivalent to a file mode.
+	assert!(encode(&[
+		AclEntry::new(AclTag::UserObj, R_OK),
+		AclEntry::new(AclTag::GroupObj, R_OK),
+		AclEntry::new(AclTag::Other, R_OK),
+	], &mut buf).is_ok());
+
+	// Named entries require a mask.
+	assert_eq!(encode(&[
+		AclEntry::new(AclTag::UserObj, R_OK),
+		AclEntry::new(AclTag::User(1001), R_OK),
+		AclEntry::new(AclTag::GroupObj, R_OK),
+		AclEntry::new(AclTag::Other, R_OK),
+	], &mut buf), Err(PosixAclError::InvalidStructure));
+
+	// Entries must be in canonical order.
+	assert_eq!(encode(&[
+		AclEntry::new(AclTag::GroupObj, R_OK),
+		AclEntry::new(AclTag::UserObj, R_OK),
+		AclEntry::new(AclTag::Other, R_OK),
+	], &mut buf), Err(PosixAclError::InvalidStructure));
+
+	// The `Other` entry is required.
+	assert_eq!(encode(&[
+		AclEntry::new(AclTag::UserObj, R_OK),
+		AclEntry::new(AclTag::GroupObj, R_OK),
+	], &mut buf), Err(PosixAclError::InvalidStructure));
+}
+
+#[test]
+fn may_access() {
+	let mut buf = [0u8; 64];
+	let acl = PosixAcl::parse(encode(ENTRIES, &mut buf).unwrap()).unwrap();
+	let attr = file_attr(0o650, 1000, 2000);
+
+	// Owner uses the `UserObj` entry, which isn't limited by the mask.
+	let owner = Credentials::new(1000, 1000);
+	assert!(acl.may_access(&attr, &owner, R_OK | W_OK));
+	assert!(!acl.may_access(&attr, &owner, X_OK));
+
+	// Named users are limited by the mask.
+	let user = Credentials::new(1001, 1001);
+	assert!(acl.may_access(&attr, &user, R_OK));
+	assert!(!acl.may_access(&attr, &user, W_OK));
+
+	// The owning group grants read.
+	let group_member = Credentials::new(3000, 2000);
+	assert!(acl.may_access(&attr, &group_member, R_OK));
+	assert!(!acl.may_access(&attr, &group_member, W_OK));
+
+	// A matching named group denies access beyond the mask, and prevents
+	// fallback to `Other`.
+	let groups = [2001];
+	let mut named_group = Credentials::new(3000, 3000);
+	named_group.set_supplementary_groups(&groups);
+	assert!(acl.may_access(&attr, &named_group, R_OK | X_OK));
+	assert!(!acl.may_access(&attr, &named_group, W_OK));
+
+	// Others get no access.
+	let other = Credentials::new(4000, 4000);
+	assert!(!acl.may_access(&attr, &other, R_OK));
+	assert!(acl.may_access(&attr, &other, 0));
+}
+
+#[test]
+fn may_access_empty_acl() {
+	let acl = PosixAcl::parse(&[2, 0, 0, 0]).unwrap();
+	let attr = file_attr(0o640, 1000, 2000);
+
+	assert!(acl.may_access(&attr, &Credentials::new(1000, 1000), W_OK));
+	assert!(!acl.may_access(&attr, &Credentials::new(4000, 4000), R_OK));
+}